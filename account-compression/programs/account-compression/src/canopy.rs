@@ -18,9 +18,13 @@
 
 use crate::error::AccountCompressionError;
 use crate::events::ChangeLogEvent;
+use crate::state::PathNode;
 use anchor_lang::prelude::*;
 use bytemuck::{cast_slice, cast_slice_mut};
-use spl_concurrent_merkle_tree::node::{empty_node_cached, Node, EMPTY};
+use spl_concurrent_merkle_tree::{
+    hash::{hash_to_parent, multi_proof_positions},
+    node::{empty_node_cached, Node, EMPTY},
+};
 use std::mem::size_of;
 
 #[inline(always)]
@@ -78,9 +82,12 @@ pub fn update_canopy(
         match &*cl_event {
             ChangeLogEvent::V1(cl) => {
                 // Update the canopy from the newest change log
-                for path_node in cl.path.iter().rev().skip(1).take(path_len as usize) {
-                    // node_idx - 2 maps to the canopy index
-                    canopy[(path_node.index - 2) as usize] = path_node.node;
+                update_canopy_from_path(canopy, path_len, &cl.path);
+            }
+            ChangeLogEvent::V2(cl) => {
+                // Apply the change logs of a batch in order, so the newest nodes win
+                for change in cl.changes.iter() {
+                    update_canopy_from_path(canopy, path_len, &change.path);
                 }
             }
         }
@@ -88,6 +95,93 @@ pub fn update_canopy(
     Ok(())
 }
 
+#[inline(always)]
+fn update_canopy_from_path(canopy: &mut [Node], path_len: u32, path: &[PathNode]) {
+    for path_node in path.iter().rev().skip(1).take(path_len as usize) {
+        // node_idx - 2 maps to the canopy index
+        canopy[(path_node.index - 2) as usize] = path_node.node;
+    }
+}
+
+/// Writes nodes into the canopy, starting at `start_index`. The canopy stores levels from the
+/// top of the tree down, left to right, without the root.
+pub fn set_canopy_nodes(canopy_bytes: &mut [u8], start_index: u32, nodes: &[Node]) -> Result<()> {
+    check_canopy_bytes(canopy_bytes)?;
+    let canopy = cast_slice_mut::<u8, Node>(canopy_bytes);
+    let start_index = start_index as usize;
+    let end_index = start_index.saturating_add(nodes.len());
+    if end_index > canopy.len() {
+        msg!(
+            "Canopy nodes {}..{} exceed the canopy length {}",
+            start_index,
+            end_index,
+            canopy.len()
+        );
+        return err!(AccountCompressionError::CanopyLengthMismatch);
+    }
+    canopy[start_index..end_index].copy_from_slice(nodes);
+    Ok(())
+}
+
+/// Checks that the nodes loaded into a canopy hash to `root`, and that the nodes to the right of
+/// the leaf at `rightmost_index` are empty, as expected by the appends that follow it. Every
+/// cached node is hashed once, so the canopy that can be checked in a single instruction is
+/// bounded by the compute budget.
+pub fn check_canopy_root(
+    canopy_bytes: &[u8],
+    max_depth: u32,
+    root: &Node,
+    rightmost_index: u32,
+) -> Result<()> {
+    // 30 is hard coded as it is the current max depth that SPL Compression supports
+    let mut empty_node_cache = Box::new([EMPTY; 30]);
+    check_canopy_bytes(canopy_bytes)?;
+    let canopy = cast_slice::<u8, Node>(canopy_bytes);
+    let path_len = get_cached_path_length(canopy, max_depth)?;
+    if path_len == 0 {
+        return Ok(());
+    }
+
+    let bottom_level = max_depth - path_len;
+    let empty_bottom_node = empty_node_cached::<30>(bottom_level, &mut empty_node_cache);
+
+    // node_idx - 2 maps to the canopy index, and the children of node_idx are 2 * node_idx
+    // and 2 * node_idx + 1. Empty nodes may be stored as EMPTY.
+    let mut get_node = |node_idx: u32| {
+        let level = max_depth - (31 - node_idx.leading_zeros());
+        let cached = canopy[node_idx as usize - 2];
+        if cached == EMPTY {
+            empty_node_cached::<30>(level, &mut empty_node_cache)
+        } else {
+            cached
+        }
+    };
+
+    let first_bottom_idx: u32 = 1 << path_len;
+    let first_empty_idx = first_bottom_idx + (rightmost_index >> bottom_level) + 1;
+    for node_idx in first_empty_idx..(first_bottom_idx << 1) {
+        if get_node(node_idx) != empty_bottom_node {
+            msg!("Canopy node {} is not empty", node_idx - 2);
+            return err!(AccountCompressionError::CanopyRightmostLeafMismatch);
+        }
+    }
+
+    for node_idx in 1..first_bottom_idx {
+        let mut node = get_node(node_idx << 1);
+        hash_to_parent(&mut node, &get_node((node_idx << 1) + 1), true);
+        if node_idx == 1 {
+            if node != *root {
+                msg!("Canopy does not hash to the root");
+                return err!(AccountCompressionError::CanopyRootMismatch);
+            }
+        } else if node != get_node(node_idx) {
+            msg!("Canopy node {} does not match its children", node_idx - 2);
+            return err!(AccountCompressionError::CanopyRootMismatch);
+        }
+    }
+    Ok(())
+}
+
 pub fn fill_in_proof_from_canopy(
     canopy_bytes: &[u8],
     max_depth: u32,
@@ -128,3 +222,36 @@ pub fn fill_in_proof_from_canopy(
     proof.extend(inferred_nodes.iter().skip(overlap));
     Ok(())
 }
+
+/// Fills in the combined proof of the leaves at `indices` with the nodes cached in the canopy,
+/// like `fill_in_proof_from_canopy` does for the proof of a single leaf.
+pub fn fill_in_multi_proof_from_canopy(
+    canopy_bytes: &[u8],
+    max_depth: u32,
+    indices: &[u32],
+    proof: &mut Vec<Node>,
+) -> Result<()> {
+    // 30 is hard coded as it is the current max depth that SPL Compression supports
+    let mut empty_node_cache = Box::new([EMPTY; 30]);
+    check_canopy_bytes(canopy_bytes)?;
+    let canopy = cast_slice::<u8, Node>(canopy_bytes);
+    let path_len = get_cached_path_length(canopy, max_depth)?;
+
+    // Proof nodes are ordered by level from the leaves up, so the canopy can only provide the
+    // trailing ones. If a node below the canopy is missing, the instruction will fail.
+    let positions = multi_proof_positions(indices, max_depth as usize);
+    for (level, index) in positions.into_iter().skip(proof.len()) {
+        if max_depth - level > path_len {
+            break;
+        }
+        // node_idx - 2 maps to the canopy index
+        let node_idx = (1 << (max_depth - level)) + index;
+        let cached = canopy[node_idx as usize - 2];
+        if cached == EMPTY {
+            proof.push(empty_node_cached::<30>(level, &mut empty_node_cache));
+        } else {
+            proof.push(cached);
+        }
+    }
+    Ok(())
+}
@@ -47,6 +47,23 @@ pub enum AccountCompressionError {
     /// is out of bounds of tree's maximum leaf capacity
     #[msg("Leaf index of concurrent merkle tree is out of bounds")]
     LeafIndexOutOfBounds,
+
+    /// A batch instruction modifies no leaves, or more leaves than the tree's max buffer size
+    #[msg("Invalid batch of leaves for concurrent merkle tree")]
+    InvalidBatch,
+
+    /// The tree must be prepared with `prepare_merkle_tree` before its canopy can be
+    /// loaded and it can be initialized with an existing root
+    #[msg("Concurrent merkle tree was not prepared for initialization with a root")]
+    TreeNotPrepared,
+
+    /// The nodes loaded into the canopy of a prepared tree do not hash to the provided root
+    #[msg("Canopy nodes do not match the root of the concurrent merkle tree")]
+    CanopyRootMismatch,
+
+    /// The canopy of a prepared tree has non-empty nodes to the right of the rightmost leaf
+    #[msg("Canopy has non-empty nodes to the right of the rightmost leaf")]
+    CanopyRightmostLeafMismatch,
 }
 
 impl From<&ConcurrentMerkleTreeError> for AccountCompressionError {
@@ -7,6 +7,7 @@ use spl_concurrent_merkle_tree::changelog::ChangeLog;
 #[repr(C)]
 pub enum ChangeLogEvent {
     V1(ChangeLogEventV1),
+    V2(ChangeLogEventV2),
 }
 
 #[derive(AnchorDeserialize, AnchorSerialize)]
@@ -25,6 +26,29 @@ pub struct ChangeLogEventV1 {
     pub index: u32,
 }
 
+/// Changelogs of a batch operation, which modifies several leaves of the same tree
+#[derive(AnchorDeserialize, AnchorSerialize)]
+pub struct ChangeLogEventV2 {
+    /// Public key of the ConcurrentMerkleTree
+    pub id: Pubkey,
+
+    /// One entry per modified leaf, in the order they were applied
+    pub changes: Vec<ChangeLogEntry>,
+}
+
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Debug, PartialEq, Eq)]
+pub struct ChangeLogEntry {
+    /// Nodes of off-chain merkle tree needed by indexer
+    pub path: Vec<PathNode>,
+
+    /// Index corresponding to the number of successful operations on this tree.
+    /// Used by the off-chain indexer to figure out when there are gaps to be backfilled.
+    pub seq: u64,
+
+    /// Bitmap of node parity (used when hashing)
+    pub index: u32,
+}
+
 impl ChangeLogEvent {
     pub fn new(id: Pubkey, path: Vec<PathNode>, seq: u64, index: u32) -> Self {
         Self::V1(ChangeLogEventV1 {
@@ -41,24 +65,52 @@ impl<const MAX_DEPTH: usize> From<(Box<ChangeLog<MAX_DEPTH>>, Pubkey, u64)>
 {
     fn from(log_info: (Box<ChangeLog<MAX_DEPTH>>, Pubkey, u64)) -> Self {
         let (changelog, tree_id, seq) = log_info;
-        let path_len = changelog.path.len() as u32;
-        let mut path: Vec<PathNode> = changelog
-            .path
-            .iter()
-            .enumerate()
-            .map(|(lvl, n)| {
-                PathNode::new(
-                    *n,
-                    (1 << (path_len - lvl as u32)) + (changelog.index >> lvl),
-                )
-            })
-            .collect();
-        path.push(PathNode::new(changelog.root, 1));
         Box::new(ChangeLogEvent::V1(ChangeLogEventV1 {
             id: tree_id,
-            path,
+            path: get_path_nodes(&changelog),
             seq,
             index: changelog.index,
         }))
     }
 }
+
+/// Converts the most recent changelogs of a tree, oldest first, into a single event.
+/// `seq` is the sequence number of the tree after the last of them.
+impl<const MAX_DEPTH: usize> From<(Vec<ChangeLog<MAX_DEPTH>>, Pubkey, u64)>
+    for Box<ChangeLogEvent>
+{
+    fn from(log_info: (Vec<ChangeLog<MAX_DEPTH>>, Pubkey, u64)) -> Self {
+        let (changelogs, tree_id, seq) = log_info;
+        let first_seq = seq + 1 - changelogs.len() as u64;
+        Box::new(ChangeLogEvent::V2(ChangeLogEventV2 {
+            id: tree_id,
+            changes: changelogs
+                .iter()
+                .enumerate()
+                .map(|(i, changelog)| ChangeLogEntry {
+                    path: get_path_nodes(changelog),
+                    seq: first_seq + i as u64,
+                    index: changelog.index,
+                })
+                .collect(),
+        }))
+    }
+}
+
+/// Returns the nodes of a changelog from the leaf up to and including the root
+fn get_path_nodes<const MAX_DEPTH: usize>(changelog: &ChangeLog<MAX_DEPTH>) -> Vec<PathNode> {
+    let path_len = changelog.path.len() as u32;
+    let mut path: Vec<PathNode> = changelog
+        .path
+        .iter()
+        .enumerate()
+        .map(|(lvl, n)| {
+            PathNode::new(
+                *n,
+                (1 << (path_len - lvl as u32)) + (changelog.index >> lvl),
+            )
+        })
+        .collect();
+    path.push(PathNode::new(changelog.root, 1));
+    path
+}
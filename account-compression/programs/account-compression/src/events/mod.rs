@@ -7,7 +7,7 @@ mod application_data;
 mod changelog_event;
 
 pub use application_data::{ApplicationDataEvent, ApplicationDataEventV1};
-pub use changelog_event::{ChangeLogEvent, ChangeLogEventV1};
+pub use changelog_event::{ChangeLogEntry, ChangeLogEvent, ChangeLogEventV1, ChangeLogEventV2};
 
 #[derive(AnchorDeserialize, AnchorSerialize)]
 #[repr(C)]
@@ -39,7 +39,9 @@ pub mod zero_copy;
 
 pub use crate::noop::{wrap_application_data_v1, Noop};
 
-use crate::canopy::{fill_in_proof_from_canopy, update_canopy};
+use crate::canopy::{
+    check_canopy_root, fill_in_multi_proof_from_canopy, fill_in_proof_from_canopy, update_canopy,
+};
 pub use crate::error::AccountCompressionError;
 pub use crate::events::{AccountCompressionEvent, ChangeLogEvent};
 use crate::noop::wrap_event;
@@ -47,6 +49,7 @@ use crate::state::{
     merkle_tree_get_size, ConcurrentMerkleTreeHeader, CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1,
 };
 use crate::zero_copy::ZeroCopy;
+use spl_concurrent_merkle_tree::concurrent_merkle_tree::LeafUpdate;
 
 /// Exported for Anchor / Solita
 pub use spl_concurrent_merkle_tree::{
@@ -88,6 +91,17 @@ pub struct Modify<'info> {
     pub noop: Program<'info, Noop>,
 }
 
+/// Context for loading the canopy of a tree prepared with `prepare_merkle_tree`
+#[derive(Accounts)]
+pub struct ModifyCanopy<'info> {
+    #[account(mut)]
+    /// CHECK: This account is validated in the instruction
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// Authority that controls write-access to the tree
+    pub authority: Signer<'info>,
+}
+
 /// Context for validating a provided proof against the SPL ConcurrentMerkleTree.
 /// Throws an error if provided proof is invalid.
 #[derive(Accounts)]
@@ -123,6 +137,14 @@ pub struct CloseTree<'info> {
     pub recipient: AccountInfo<'info>,
 }
 
+/// A leaf to overwrite with `replace_leaves`
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Copy, Debug)]
+pub struct LeafReplacement {
+    pub previous_leaf: [u8; 32],
+    pub new_leaf: [u8; 32],
+    pub index: u32,
+}
+
 #[program]
 pub mod spl_account_compression {
     use super::*;
@@ -173,6 +195,67 @@ pub mod spl_account_compression {
         update_canopy(canopy_bytes, header.get_max_depth(), None)
     }
 
+    /// Prepares a new merkle tree to be initialized with an existing root by
+    /// `init_merkle_tree_with_root`, so that trees built off-chain can be migrated on-chain.
+    ///
+    /// While the tree is prepared, its `authority` can load the canopy with
+    /// `set_canopy_nodes`. For instructions on enabling the canopy, see [canopy].
+    pub fn prepare_merkle_tree(
+        ctx: Context<Initialize>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        require_eq!(
+            *ctx.accounts.merkle_tree.owner,
+            crate::id(),
+            AccountCompressionError::IncorrectAccountOwner
+        );
+        let mut merkle_tree_bytes = ctx.accounts.merkle_tree.try_borrow_mut_data()?;
+
+        let (mut header_bytes, rest) =
+            merkle_tree_bytes.split_at_mut(CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1);
+
+        let mut header = ConcurrentMerkleTreeHeader::try_from_slice(header_bytes)?;
+        header.prepare(
+            max_depth,
+            max_buffer_size,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.slot,
+        );
+        header.serialize(&mut header_bytes)?;
+        let merkle_tree_size = merkle_tree_get_size(&header)?;
+        let (_tree_bytes, canopy_bytes) = rest.split_at_mut(merkle_tree_size);
+        update_canopy(canopy_bytes, header.get_max_depth(), None)
+    }
+
+    /// Writes nodes of a prepared tree's canopy, starting at `start_index`.
+    /// The canopy stores the upper levels of the tree from the top down, left to right,
+    /// without the root, so the canopy of a tree with existing leaves can be loaded over
+    /// several transactions. The nodes are checked against the root by
+    /// `init_merkle_tree_with_root`.
+    pub fn set_canopy_nodes(
+        ctx: Context<ModifyCanopy>,
+        start_index: u32,
+        canopy_nodes: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        require_eq!(
+            *ctx.accounts.merkle_tree.owner,
+            crate::id(),
+            AccountCompressionError::IncorrectAccountOwner
+        );
+        let mut merkle_tree_bytes = ctx.accounts.merkle_tree.try_borrow_mut_data()?;
+        let (header_bytes, rest) =
+            merkle_tree_bytes.split_at_mut(CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1);
+
+        let header = ConcurrentMerkleTreeHeader::try_from_slice(header_bytes)?;
+        header.assert_valid_authority(&ctx.accounts.authority.key())?;
+        header.assert_prepared()?;
+
+        let merkle_tree_size = merkle_tree_get_size(&header)?;
+        let (_tree_bytes, canopy_bytes) = rest.split_at_mut(merkle_tree_size);
+        canopy::set_canopy_nodes(canopy_bytes, start_index, &canopy_nodes)
+    }
+
     /// Note:
     /// Supporting this instruction open a security vulnerability for indexers.
     /// This instruction has been deemed unusable for publicly indexed compressed NFTs.
@@ -180,62 +263,79 @@ pub mod spl_account_compression {
     /// and then into their database. This opens up a DOS attack vector, whereby this instruction is
     /// repeatedly invoked, causing indexers to fail.
     ///
-    /// Because this instruction was deemed insecure, this instruction has been removed
-    /// until secure usage is available on-chain.
-    // pub fn init_merkle_tree_with_root(
-    //     ctx: Context<Initialize>,
-    //     max_depth: u32,
-    //     max_buffer_size: u32,
-    //     root: [u8; 32],
-    //     leaf: [u8; 32],
-    //     index: u32,
-    //     _changelog_db_uri: String,
-    //     _metadata_db_uri: String,
-    // ) -> Result<()> {
-    //     require_eq!(
-    //         *ctx.accounts.merkle_tree.owner,
-    //         crate::id(),
-    //         AccountCompressionError::IncorrectAccountOwner
-    //     );
-    //     let mut merkle_tree_bytes = ctx.accounts.merkle_tree.try_borrow_mut_data()?;
-
-    //     let (mut header_bytes, rest) =
-    //         merkle_tree_bytes.split_at_mut(CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1);
-
-    //     let mut header = ConcurrentMerkleTreeHeader::try_from_slice(&header_bytes)?;
-    //     header.initialize(
-    //         max_depth,
-    //         max_buffer_size,
-    //         &ctx.accounts.authority.key(),
-    //         Clock::get()?.slot,
-    //     );
-    //     header.serialize(&mut header_bytes)?;
-    //     let merkle_tree_size = merkle_tree_get_size(&header)?;
-    //     let (tree_bytes, canopy_bytes) = rest.split_at_mut(merkle_tree_size);
-
-    //     // Get rightmost proof from accounts
-    //     let mut proof = vec![];
-    //     for node in ctx.remaining_accounts.iter() {
-    //         proof.push(node.key().to_bytes());
-    //     }
-    //     fill_in_proof_from_canopy(canopy_bytes, header.max_depth, index, &mut proof)?;
-    //     assert_eq!(proof.len(), max_depth as usize);
-
-    //     let id = ctx.accounts.merkle_tree.key();
-    //     // A call is made to ConcurrentMerkleTree::initialize_with_root(root, leaf, proof, index)
-    //     let change_log = merkle_tree_apply_fn!(
-    //         header,
-    //         id,
-    //         tree_bytes,
-    //         initialize_with_root,
-    //         root,
-    //         leaf,
-    //         &proof,
-    //         index
-    //     )?;
-    //     wrap_event(change_log.try_to_vec()?, &ctx.accounts.log_wrapper)?;
-    //     update_canopy(canopy_bytes, header.max_depth, Some(change_log))
-    // }
+    /// Because this instruction was deemed insecure, it was removed until secure usage was
+    /// available on-chain. It is now only available for trees set up with `prepare_merkle_tree`,
+    /// whose canopy is checked against `root`, but the tree still cannot verify the leaves behind
+    /// `root`. Indexers should only index such trees once the leaves published by the `authority`
+    /// hash to `root`, and skip them otherwise.
+    ///
+    /// Initializes a prepared tree with the root of leaves that were appended off-chain,
+    /// together with the rightmost leaf and its index. Following appends are placed after
+    /// the rightmost leaf.
+    ///
+    /// The proof of the rightmost leaf is provided as 32-byte nodes via "remaining accounts",
+    /// and is completed by the canopy, so only the lower levels are needed for deep trees
+    /// whose canopy was loaded with `set_canopy_nodes`. The canopy must hash to `root` and be
+    /// empty to the right of the rightmost leaf.
+    pub fn init_merkle_tree_with_root(
+        ctx: Context<Modify>,
+        root: [u8; 32],
+        rightmost_leaf: [u8; 32],
+        rightmost_index: u32,
+    ) -> Result<()> {
+        require_eq!(
+            *ctx.accounts.merkle_tree.owner,
+            crate::id(),
+            AccountCompressionError::IncorrectAccountOwner
+        );
+        let mut merkle_tree_bytes = ctx.accounts.merkle_tree.try_borrow_mut_data()?;
+        let (mut header_bytes, rest) =
+            merkle_tree_bytes.split_at_mut(CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1);
+
+        let mut header = ConcurrentMerkleTreeHeader::try_from_slice(header_bytes)?;
+        header.assert_valid_authority(&ctx.accounts.authority.key())?;
+        header.assert_prepared()?;
+        header.assert_valid_leaf_index(rightmost_index)?;
+        header.set_prepared(false);
+        header.serialize(&mut header_bytes)?;
+
+        let merkle_tree_size = merkle_tree_get_size(&header)?;
+        let (tree_bytes, canopy_bytes) = rest.split_at_mut(merkle_tree_size);
+
+        check_canopy_root(canopy_bytes, header.get_max_depth(), &root, rightmost_index)?;
+
+        let mut proof = vec![];
+        for node in ctx.remaining_accounts.iter() {
+            proof.push(node.key().to_bytes());
+        }
+        fill_in_proof_from_canopy(
+            canopy_bytes,
+            header.get_max_depth(),
+            rightmost_index,
+            &mut proof,
+        )?;
+        let id = ctx.accounts.merkle_tree.key();
+        // A call is made to ConcurrentMerkleTree::initialize_with_root(root, rightmost_leaf, proof, rightmost_index)
+        let change_log_event = merkle_tree_apply_fn_mut!(
+            header,
+            id,
+            tree_bytes,
+            initialize_with_root,
+            root,
+            rightmost_leaf,
+            &proof,
+            rightmost_index,
+        )?;
+        update_canopy(
+            canopy_bytes,
+            header.get_max_depth(),
+            Some(&change_log_event),
+        )?;
+        wrap_event(
+            &AccountCompressionEvent::ChangeLog(*change_log_event),
+            &ctx.accounts.noop,
+        )
+    }
 
     /// Executes an instruction that overwrites a leaf node.
     /// Composing programs should check that the data hashed into previous_leaf
@@ -292,6 +392,80 @@ pub mod spl_account_compression {
         )
     }
 
+    /// Executes an instruction that overwrites several leaf nodes, emitting a single
+    /// changelog event for all of them.
+    ///
+    /// The leaves must be sorted by strictly increasing index and share a single combined proof,
+    /// valid for `root`, which is provided as 32-byte nodes via "remaining accounts". It holds
+    /// the siblings of the paths from the leaves to the root that are not on any of those paths,
+    /// ordered by level from the leaves up, then from left to right, as returned by
+    /// `spl_concurrent_merkle_tree::hash::multi_proof_positions`. Nodes cached in the canopy
+    /// can be left out.
+    ///
+    /// The number of leaves cannot exceed the tree's max buffer size.
+    pub fn replace_leaves(
+        ctx: Context<Modify>,
+        root: [u8; 32],
+        leaves: Vec<LeafReplacement>,
+    ) -> Result<()> {
+        require_eq!(
+            *ctx.accounts.merkle_tree.owner,
+            crate::id(),
+            AccountCompressionError::IncorrectAccountOwner
+        );
+        let mut merkle_tree_bytes = ctx.accounts.merkle_tree.try_borrow_mut_data()?;
+        let (header_bytes, rest) =
+            merkle_tree_bytes.split_at_mut(CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1);
+
+        let header = ConcurrentMerkleTreeHeader::try_from_slice(header_bytes)?;
+        header.assert_valid_authority(&ctx.accounts.authority.key())?;
+        require!(
+            !leaves.is_empty() && leaves.len() <= header.get_max_buffer_size() as usize,
+            AccountCompressionError::InvalidBatch
+        );
+
+        let merkle_tree_size = merkle_tree_get_size(&header)?;
+        let (tree_bytes, canopy_bytes) = rest.split_at_mut(merkle_tree_size);
+
+        let mut indices = Vec::with_capacity(leaves.len());
+        for leaf in leaves.iter() {
+            header.assert_valid_leaf_index(leaf.index)?;
+            indices.push(leaf.index);
+        }
+        let mut proof = vec![];
+        for node in ctx.remaining_accounts.iter() {
+            proof.push(node.key().to_bytes());
+        }
+        fill_in_multi_proof_from_canopy(
+            canopy_bytes,
+            header.get_max_depth(),
+            &indices,
+            &mut proof,
+        )?;
+        let updates: Vec<LeafUpdate> = leaves
+            .iter()
+            .map(|leaf| LeafUpdate {
+                previous_leaf: leaf.previous_leaf,
+                new_leaf: leaf.new_leaf,
+                index: leaf.index,
+            })
+            .collect();
+        let id = ctx.accounts.merkle_tree.key();
+        // A call is made to ConcurrentMerkleTree::set_leaves(root, updates, proof)
+        let change_log_event = merkle_tree_apply_fn_mut_batch!(
+            header, id, tree_bytes, set_leaves, root, &updates, &proof,
+        )?;
+        update_canopy(
+            canopy_bytes,
+            header.get_max_depth(),
+            Some(&change_log_event),
+        )?;
+        wrap_event(
+            &AccountCompressionEvent::ChangeLog(*change_log_event),
+            &ctx.accounts.noop,
+        )
+    }
+
     /// Transfers `authority`.
     /// Requires `authority` to sign
     pub fn transfer_authority(
@@ -385,6 +559,44 @@ pub mod spl_account_compression {
         )
     }
 
+    /// This instruction allows the tree's `authority` to append several leaves to the tree
+    /// without having to supply proofs, emitting a single changelog event for all of them.
+    ///
+    /// The number of leaves cannot exceed the tree's max buffer size, so that concurrent
+    /// replaces can still be fast-forwarded through every appended leaf.
+    pub fn append_batch(ctx: Context<Modify>, leaves: Vec<[u8; 32]>) -> Result<()> {
+        require_eq!(
+            *ctx.accounts.merkle_tree.owner,
+            crate::id(),
+            AccountCompressionError::IncorrectAccountOwner
+        );
+        let mut merkle_tree_bytes = ctx.accounts.merkle_tree.try_borrow_mut_data()?;
+        let (header_bytes, rest) =
+            merkle_tree_bytes.split_at_mut(CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1);
+
+        let header = ConcurrentMerkleTreeHeader::try_from_slice(header_bytes)?;
+        header.assert_valid_authority(&ctx.accounts.authority.key())?;
+        require!(
+            !leaves.is_empty() && leaves.len() <= header.get_max_buffer_size() as usize,
+            AccountCompressionError::InvalidBatch
+        );
+
+        let id = ctx.accounts.merkle_tree.key();
+        let merkle_tree_size = merkle_tree_get_size(&header)?;
+        let (tree_bytes, canopy_bytes) = rest.split_at_mut(merkle_tree_size);
+        let change_log_event =
+            merkle_tree_apply_fn_mut_batch!(header, id, tree_bytes, append_batch, &leaves)?;
+        update_canopy(
+            canopy_bytes,
+            header.get_max_depth(),
+            Some(&change_log_event),
+        )?;
+        wrap_event(
+            &AccountCompressionEvent::ChangeLog(*change_log_event),
+            &ctx.accounts.noop,
+        )
+    }
+
     /// This instruction takes a proof, and will attempt to write the given leaf
     /// to the specified index in the tree. If the insert operation fails, the leaf will be `append`-ed
     /// to the tree.
@@ -455,8 +667,11 @@ pub mod spl_account_compression {
         let merkle_tree_size = merkle_tree_get_size(&header)?;
         let (tree_bytes, canopy_bytes) = rest.split_at_mut(merkle_tree_size);
 
-        let id = ctx.accounts.merkle_tree.key();
-        merkle_tree_apply_fn_mut!(header, id, tree_bytes, prove_tree_is_empty,)?;
+        // A prepared tree has no leaves until it is initialized
+        if !header.is_prepared() {
+            let id = ctx.accounts.merkle_tree.key();
+            merkle_tree_apply_fn_mut!(header, id, tree_bytes, prove_tree_is_empty,)?;
+        }
 
         // Close merkle tree account
         // 1. Move lamports
@@ -2,6 +2,7 @@
 enum TreeLoad {
     Immutable,
     Mutable,
+    MutableBatch,
 }
 
 /// This macro applies functions on a ConcurrentMerkleT:ee and emits leaf information
@@ -28,6 +29,28 @@ macro_rules! _merkle_tree_depth_size_apply_fn {
             }
         }
     };
+    ($max_depth:literal, $max_size:literal, $id:ident, $bytes:ident, $func:ident, TreeLoad::MutableBatch, $($arg:tt)*)
+     => {
+        match ConcurrentMerkleTree::<$max_depth, $max_size>::load_mut_bytes($bytes) {
+            Ok(merkle_tree) => {
+                let previous_sequence_number = merkle_tree.sequence_number;
+                match merkle_tree.$func($($arg)*) {
+                    Ok(_) => {
+                        let num_changes = (merkle_tree.sequence_number - previous_sequence_number) as usize;
+                        Ok(Box::<ChangeLogEvent>::from((merkle_tree.get_change_logs(num_changes), $id, merkle_tree.sequence_number)))
+                    }
+                    Err(err) => {
+                        msg!("Error using concurrent merkle tree: {}", err);
+                        err!(AccountCompressionError::ConcurrentMerkleTreeError)
+                    }
+                }
+            }
+            Err(err) => {
+                msg!("Error zero copying concurrent merkle tree: {}", err);
+                err!(AccountCompressionError::ZeroCopyError)
+            }
+        }
+    };
     ($max_depth:literal, $max_size:literal, $id:ident, $bytes:ident, $func:ident, TreeLoad::Immutable, $($arg:tt)*) => {
         match ConcurrentMerkleTree::<$max_depth, $max_size>::load_bytes($bytes) {
             Ok(merkle_tree) => {
@@ -103,6 +126,15 @@ macro_rules! merkle_tree_apply_fn_mut {
     };
 }
 
+/// This applies a given function that modifies several leaves on a mutable ConcurrentMerkleTree,
+/// emitting the changelogs of all of them in a single event
+#[macro_export]
+macro_rules! merkle_tree_apply_fn_mut_batch {
+    ($header:ident, $id:ident, $bytes:ident, $func:ident, $($arg:tt)*) => {
+        _merkle_tree_apply_fn!($header, $id, $bytes, $func, TreeLoad::MutableBatch, $($arg)*)
+    };
+}
+
 /// This applies a given function on a read-only ConcurrentMerkleTree
 #[macro_export]
 macro_rules! merkle_tree_apply_fn {
@@ -65,9 +65,13 @@ pub struct ConcurrentMerkleTreeHeaderDataV1 {
     /// Provides a lower-bound on what slot to start (re-)building a tree from.
     creation_slot: u64,
 
+    /// Set while the tree is prepared to be initialized with an existing root,
+    /// during which the authority can load the canopy.
+    is_prepared: bool,
+
     /// Needs padding for the account to be 8-byte aligned
     /// 8-byte alignment is necessary to zero-copy the SPL ConcurrentMerkleTree
-    _padding: [u8; 6],
+    _padding: [u8; 5],
 }
 
 #[repr(C)]
@@ -99,6 +103,33 @@ impl ConcurrentMerkleTreeHeader {
         }
     }
 
+    /// Initializes the header of a tree that will be initialized with an existing root
+    /// once its canopy is loaded
+    pub fn prepare(
+        &mut self,
+        max_depth: u32,
+        max_buffer_size: u32,
+        authority: &Pubkey,
+        creation_slot: u64,
+    ) {
+        self.initialize(max_depth, max_buffer_size, authority, creation_slot);
+        self.set_prepared(true);
+    }
+
+    pub fn is_prepared(&self) -> bool {
+        match &self.header {
+            ConcurrentMerkleTreeHeaderData::V1(header) => header.is_prepared,
+        }
+    }
+
+    pub fn set_prepared(&mut self, is_prepared: bool) {
+        match self.header {
+            ConcurrentMerkleTreeHeaderData::V1(ref mut header) => {
+                header.is_prepared = is_prepared;
+            }
+        }
+    }
+
     pub fn get_max_depth(&self) -> u32 {
         match &self.header {
             ConcurrentMerkleTreeHeaderData::V1(header) => header.max_depth,
@@ -149,6 +180,13 @@ impl ConcurrentMerkleTreeHeader {
         Ok(())
     }
 
+    pub fn assert_prepared(&self) -> Result<()> {
+        if !self.is_prepared() {
+            return Err(AccountCompressionError::TreeNotPrepared.into());
+        }
+        Ok(())
+    }
+
     pub fn assert_valid_leaf_index(&self, leaf_index: u32) -> Result<()> {
         if leaf_index >= (1 << self.get_max_depth()) {
             return Err(AccountCompressionError::LeafIndexOutOfBounds.into());
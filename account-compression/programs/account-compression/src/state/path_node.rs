@@ -1,7 +1,7 @@
 use anchor_lang::prelude::*;
 use spl_concurrent_merkle_tree::node::Node;
 
-#[derive(AnchorDeserialize, AnchorSerialize, Clone, Copy, Debug)]
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct PathNode {
     pub node: [u8; 32],
     pub index: u32,
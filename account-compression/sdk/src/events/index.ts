@@ -1,8 +1,8 @@
 import BN from 'bn.js';
 
-import { ApplicationDataEvent, ChangeLogEventV1 as CLV1 } from '../generated';
+import { ApplicationDataEvent, ChangeLogEventV1 as CLV1, ChangeLogEventV2 as CLV2 } from '../generated';
 import { accountCompressionEventBeet } from '../generated/types/AccountCompressionEvent';
-import { ChangeLogEventV1 } from '../types';
+import { ChangeLogEventV1, ChangeLogEventV2 } from '../types';
 
 /**
  * Helper method for indexing a {@link ConcurrentMerkleTree}
@@ -25,6 +25,29 @@ export function deserializeChangeLogEventV1(data: Buffer): ChangeLogEventV1 {
     }
 }
 
+/**
+ * Helper method for indexing the batch operations of a {@link ConcurrentMerkleTree}
+ * @param data
+ * @returns
+ */
+export function deserializeChangeLogEventV2(data: Buffer): ChangeLogEventV2 {
+    const event = accountCompressionEventBeet.toFixedFromData(data, 0).read(data, 0);
+
+    if (event.__kind == 'ChangeLog' && event.fields[0].__kind == 'V2') {
+        const changeLogV2: CLV2 = event.fields[0].fields[0];
+        return {
+            changes: changeLogV2.changes.map(change => ({
+                index: change.index,
+                path: change.path,
+                seq: new BN.BN(change.seq),
+            })),
+            treeId: changeLogV2.id,
+        };
+    } else {
+        throw Error('Unable to decode buffer as ChangeLogEvent V2');
+    }
+}
+
 /**
  * Helper function for indexing data logged via `wrap_application_data_v1`
  * @param data
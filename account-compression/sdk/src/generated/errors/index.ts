@@ -194,6 +194,86 @@ export class LeafIndexOutOfBoundsError extends Error {
 createErrorFromCodeLookup.set(0x1778, () => new LeafIndexOutOfBoundsError());
 createErrorFromNameLookup.set('LeafIndexOutOfBounds', () => new LeafIndexOutOfBoundsError());
 
+/**
+ * InvalidBatch: 'Invalid batch of leaves for concurrent merkle tree'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class InvalidBatchError extends Error {
+    readonly code: number = 0x1779;
+    readonly name: string = 'InvalidBatch';
+    constructor() {
+        super('Invalid batch of leaves for concurrent merkle tree');
+        if (typeof Error.captureStackTrace === 'function') {
+            Error.captureStackTrace(this, InvalidBatchError);
+        }
+    }
+}
+
+createErrorFromCodeLookup.set(0x1779, () => new InvalidBatchError());
+createErrorFromNameLookup.set('InvalidBatch', () => new InvalidBatchError());
+
+/**
+ * TreeNotPrepared: 'Concurrent merkle tree was not prepared for initialization with a root'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class TreeNotPreparedError extends Error {
+    readonly code: number = 0x177a;
+    readonly name: string = 'TreeNotPrepared';
+    constructor() {
+        super('Concurrent merkle tree was not prepared for initialization with a root');
+        if (typeof Error.captureStackTrace === 'function') {
+            Error.captureStackTrace(this, TreeNotPreparedError);
+        }
+    }
+}
+
+createErrorFromCodeLookup.set(0x177a, () => new TreeNotPreparedError());
+createErrorFromNameLookup.set('TreeNotPrepared', () => new TreeNotPreparedError());
+
+/**
+ * CanopyRootMismatch: 'Canopy nodes do not match the root of the concurrent merkle tree'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class CanopyRootMismatchError extends Error {
+    readonly code: number = 0x177b;
+    readonly name: string = 'CanopyRootMismatch';
+    constructor() {
+        super('Canopy nodes do not match the root of the concurrent merkle tree');
+        if (typeof Error.captureStackTrace === 'function') {
+            Error.captureStackTrace(this, CanopyRootMismatchError);
+        }
+    }
+}
+
+createErrorFromCodeLookup.set(0x177b, () => new CanopyRootMismatchError());
+createErrorFromNameLookup.set('CanopyRootMismatch', () => new CanopyRootMismatchError());
+
+/**
+ * CanopyRightmostLeafMismatch: 'Canopy has non-empty nodes to the right of the rightmost leaf'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class CanopyRightmostLeafMismatchError extends Error {
+    readonly code: number = 0x177c;
+    readonly name: string = 'CanopyRightmostLeafMismatch';
+    constructor() {
+        super('Canopy has non-empty nodes to the right of the rightmost leaf');
+        if (typeof Error.captureStackTrace === 'function') {
+            Error.captureStackTrace(this, CanopyRightmostLeafMismatchError);
+        }
+    }
+}
+
+createErrorFromCodeLookup.set(0x177c, () => new CanopyRightmostLeafMismatchError());
+createErrorFromNameLookup.set('CanopyRightmostLeafMismatch', () => new CanopyRightmostLeafMismatchError());
+
 /**
  * Attempts to resolve a custom program error from the provided error code.
  * @category Errors
@@ -1,7 +1,12 @@
 export * from './append';
+export * from './appendBatch';
 export * from './closeEmptyTree';
 export * from './initEmptyMerkleTree';
+export * from './initMerkleTreeWithRoot';
 export * from './insertOrAppend';
+export * from './prepareMerkleTree';
 export * from './replaceLeaf';
+export * from './replaceLeaves';
+export * from './setCanopyNodes';
 export * from './transferAuthority';
 export * from './verifyLeaf';
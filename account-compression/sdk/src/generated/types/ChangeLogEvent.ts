@@ -8,6 +8,7 @@
 import * as beet from '@metaplex-foundation/beet';
 
 import { ChangeLogEventV1, changeLogEventV1Beet } from './ChangeLogEventV1';
+import { ChangeLogEventV2, changeLogEventV2Beet } from './ChangeLogEventV2';
 /**
  * This type is used to derive the {@link ChangeLogEvent} type as well as the de/serializer.
  * However don't refer to it in your code but use the {@link ChangeLogEvent} type instead.
@@ -19,6 +20,7 @@ import { ChangeLogEventV1, changeLogEventV1Beet } from './ChangeLogEventV1';
  */
 export type ChangeLogEventRecord = {
     V1: { fields: [ChangeLogEventV1] };
+    V2: { fields: [ChangeLogEventV2] };
 };
 
 /**
@@ -35,6 +37,7 @@ export type ChangeLogEventRecord = {
 export type ChangeLogEvent = beet.DataEnumKeyAsKind<ChangeLogEventRecord>;
 
 export const isChangeLogEventV1 = (x: ChangeLogEvent): x is ChangeLogEvent & { __kind: 'V1' } => x.__kind === 'V1';
+export const isChangeLogEventV2 = (x: ChangeLogEvent): x is ChangeLogEvent & { __kind: 'V2' } => x.__kind === 'V2';
 
 /**
  * @category userTypes
@@ -48,4 +51,11 @@ export const changeLogEventBeet = beet.dataEnum<ChangeLogEventRecord>([
             'ChangeLogEventRecord["V1"]'
         ),
     ],
+    [
+        'V2',
+        new beet.FixableBeetArgsStruct<ChangeLogEventRecord['V2']>(
+            [['fields', beet.tuple([changeLogEventV2Beet])]],
+            'ChangeLogEventRecord["V2"]'
+        ),
+    ],
 ]) as beet.FixableBeet<ChangeLogEvent>;
@@ -13,7 +13,8 @@ export type ConcurrentMerkleTreeHeaderDataV1 = {
     maxDepth: number;
     authority: web3.PublicKey;
     creationSlot: beet.bignum;
-    padding: number[] /* size: 6 */;
+    isPrepared: boolean;
+    padding: number[] /* size: 5 */;
 };
 
 /**
@@ -26,7 +27,8 @@ export const concurrentMerkleTreeHeaderDataV1Beet = new beet.BeetArgsStruct<Conc
         ['maxDepth', beet.u32],
         ['authority', beetSolana.publicKey],
         ['creationSlot', beet.u64],
-        ['padding', beet.uniformFixedSizeArray(beet.u8, 6)],
+        ['isPrepared', beet.bool],
+        ['padding', beet.uniformFixedSizeArray(beet.u8, 5)],
     ],
     'ConcurrentMerkleTreeHeaderDataV1'
 );
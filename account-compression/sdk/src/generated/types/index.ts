@@ -1,10 +1,13 @@
 export * from './AccountCompressionEvent';
 export * from './ApplicationDataEvent';
 export * from './ApplicationDataEventV1';
+export * from './ChangeLogEntry';
 export * from './ChangeLogEvent';
 export * from './ChangeLogEventV1';
+export * from './ChangeLogEventV2';
 export * from './CompressionAccountType';
 export * from './ConcurrentMerkleTreeHeader';
 export * from './ConcurrentMerkleTreeHeaderData';
 export * from './ConcurrentMerkleTreeHeaderDataV1';
+export * from './LeafReplacement';
 export * from './PathNode';
@@ -9,4 +9,4 @@ export * from './events';
 export * from './constants';
 export * from './types';
 export * from './merkle-tree';
-export type { ChangeLogEventV1 } from './types';
+export type { ChangeLogEventV1, ChangeLogEventV2 } from './types';
@@ -3,10 +3,15 @@ import { Connection, PublicKey, SystemProgram, TransactionInstruction } from '@s
 import { getConcurrentMerkleTreeAccountSize } from '../accounts';
 import { SPL_NOOP_PROGRAM_ID, ValidDepthSizePair } from '../constants';
 import {
+    createAppendBatchInstruction,
     createAppendInstruction,
     createCloseEmptyTreeInstruction,
     createInitEmptyMerkleTreeInstruction,
+    createInitMerkleTreeWithRootInstruction,
+    createPrepareMerkleTreeInstruction,
     createReplaceLeafInstruction,
+    createReplaceLeavesInstruction,
+    createSetCanopyNodesInstruction,
     createTransferAuthorityInstruction,
     createVerifyLeafInstruction,
     PROGRAM_ID,
@@ -53,6 +58,84 @@ export function createInitEmptyMerkleTreeIx(
     );
 }
 
+/**
+ * Helper function for {@link createPrepareMerkleTreeInstruction}
+ *
+ * @param merkleTree
+ * @param authority
+ * @param depthSizePair
+ * @returns
+ */
+export function createPrepareMerkleTreeIx(
+    merkleTree: PublicKey,
+    authority: PublicKey,
+    depthSizePair: ValidDepthSizePair
+): TransactionInstruction {
+    return createPrepareMerkleTreeInstruction(
+        {
+            authority: authority,
+            merkleTree,
+            noop: SPL_NOOP_PROGRAM_ID,
+        },
+        depthSizePair
+    );
+}
+
+/**
+ * Helper function for {@link createSetCanopyNodesInstruction}
+ * @param merkleTree
+ * @param authority
+ * @param startIndex index of the first node in the canopy
+ * @param canopyNodes
+ * @returns
+ */
+export function createSetCanopyNodesIx(
+    merkleTree: PublicKey,
+    authority: PublicKey,
+    startIndex: number,
+    canopyNodes: Buffer[]
+): TransactionInstruction {
+    return createSetCanopyNodesInstruction(
+        {
+            authority,
+            merkleTree,
+        },
+        {
+            canopyNodes: canopyNodes.map(node => Array.from(node)),
+            startIndex,
+        }
+    );
+}
+
+/**
+ * Helper function for {@link createInitMerkleTreeWithRootInstruction}
+ * @param merkleTree
+ * @param authority
+ * @param proof proof of the rightmost leaf
+ * @returns
+ */
+export function createInitMerkleTreeWithRootIx(
+    merkleTree: PublicKey,
+    authority: PublicKey,
+    proof: MerkleTreeProof
+): TransactionInstruction {
+    return addProof(
+        createInitMerkleTreeWithRootInstruction(
+            {
+                authority: authority,
+                merkleTree,
+                noop: SPL_NOOP_PROGRAM_ID,
+            },
+            {
+                rightmostIndex: proof.leafIndex,
+                rightmostLeaf: Array.from(proof.leaf),
+                root: Array.from(proof.root),
+            }
+        ),
+        proof.proof
+    );
+}
+
 /**
  * Helper function for {@link createReplaceLeafInstruction}
  * @param merkleTree
@@ -85,6 +168,42 @@ export function createReplaceIx(
     );
 }
 
+/**
+ * Helper function for {@link createReplaceLeavesInstruction}
+ * @param merkleTree
+ * @param authority
+ * @param root
+ * @param leaves sorted by strictly increasing index
+ * @param proof combined proof of the leaves, without the nodes cached in the canopy
+ * @returns
+ */
+export function createReplaceLeavesIx(
+    merkleTree: PublicKey,
+    authority: PublicKey,
+    root: Buffer,
+    leaves: { previousLeaf: Buffer; newLeaf: Buffer; index: number }[],
+    proof: Buffer[]
+): TransactionInstruction {
+    return addProof(
+        createReplaceLeavesInstruction(
+            {
+                authority: authority,
+                merkleTree,
+                noop: SPL_NOOP_PROGRAM_ID,
+            },
+            {
+                leaves: leaves.map(leaf => ({
+                    index: leaf.index,
+                    newLeaf: Array.from(leaf.newLeaf),
+                    previousLeaf: Array.from(leaf.previousLeaf),
+                })),
+                root: Array.from(root),
+            }
+        ),
+        proof
+    );
+}
+
 /**
  * Helper function for {@link createAppendInstruction}
  * @param merkleTree
@@ -109,6 +228,30 @@ export function createAppendIx(
     );
 }
 
+/**
+ * Helper function for {@link createAppendBatchInstruction}
+ * @param merkleTree
+ * @param authority
+ * @param newLeaves
+ * @returns
+ */
+export function createAppendBatchIx(
+    merkleTree: PublicKey,
+    authority: PublicKey,
+    newLeaves: (Buffer | ArrayLike<number>)[]
+): TransactionInstruction {
+    return createAppendBatchInstruction(
+        {
+            authority: authority,
+            merkleTree,
+            noop: SPL_NOOP_PROGRAM_ID,
+        },
+        {
+            leaves: newLeaves.map(leaf => Array.from(leaf)),
+        }
+    );
+}
+
 /**
  * Helper function for {@link createTransferAuthorityIx}
  * @param merkleTree
@@ -129,6 +129,38 @@ export class MerkleTree {
         };
     }
 
+    /**
+     * Returns the combined proof of several leaves, as expected by `replace_leaves`: the siblings
+     * of the paths from the leaves to the root that are not on any of those paths, ordered by
+     * level from the leaves up, then from left to right
+     *
+     * @param leafIndices strictly increasing indices of the leaves
+     * @param minimizeProofHeight whether to leave out the levels cached in the canopy
+     * @param treeHeight number of levels below the canopy
+     * @returns
+     */
+    getMultiProof(leafIndices: number[], minimizeProofHeight = false, treeHeight = -1): Buffer[] {
+        const proof: Buffer[] = [];
+        let nodes = [...leafIndices];
+        for (let level = 0; level < this.depth - 1; level++) {
+            if (minimizeProofHeight && level >= treeHeight) {
+                break;
+            }
+            for (let i = 0; i < nodes.length; i++) {
+                const index = nodes[i];
+                if (index % 2 == 0 && nodes[i + 1] === index + 1) {
+                    // Both children are on a path, so neither is needed in the proof
+                    i++;
+                } else {
+                    // The proof of a leaf below the sibling of this node contains it at this level
+                    proof.push(this.getProof((index ^ 1) << level).proof[level]);
+                }
+            }
+            nodes = nodes.map(index => index >> 1).filter((index, i, all) => i == 0 || all[i - 1] != index);
+        }
+        return proof;
+    }
+
     updateLeaf(leafIndex: number, newLeaf: Buffer, verbose = false) {
         const leaf = this.leaves[leafIndex];
         leaf.node = newLeaf;
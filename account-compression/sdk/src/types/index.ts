@@ -12,3 +12,12 @@ export type ChangeLogEventV1 = {
     seq: BN;
     index: number;
 };
+
+export type ChangeLogEventV2 = {
+    treeId: PublicKey;
+    changes: {
+        path: PathNode[];
+        seq: BN;
+        index: number;
+    }[];
+};
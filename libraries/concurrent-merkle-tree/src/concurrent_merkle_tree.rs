@@ -1,7 +1,7 @@
 use crate::{
     changelog::ChangeLog,
     error::ConcurrentMerkleTreeError,
-    hash::{fill_in_proof, hash_to_parent, recompute},
+    hash::{fill_in_proof, hash_to_parent, multi_proof_positions, recompute, recompute_multi},
     node::{empty_node, empty_node_cached, Node, EMPTY},
     path::Path,
 };
@@ -26,6 +26,16 @@ fn check_leaf_index(leaf_index: u32, max_depth: usize) -> Result<(), ConcurrentM
     Ok(())
 }
 
+/// A leaf replacement for [set_leaves](ConcurrentMerkleTree::set_leaves), equivalent to the
+/// arguments of [set_leaf](ConcurrentMerkleTree::set_leaf) without the proof, which is shared by
+/// the whole batch
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LeafUpdate {
+    pub previous_leaf: Node,
+    pub new_leaf: Node,
+    pub index: u32,
+}
+
 /// Conurrent Merkle Tree is a Merkle Tree that allows
 /// multiple tree operations targeted for the same tree root to succeed.
 ///
@@ -136,23 +146,40 @@ impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize>
         if self.is_initialized() {
             return Err(ConcurrentMerkleTreeError::TreeAlreadyInitialized);
         }
+        if proof_vec.len() != MAX_DEPTH {
+            solana_logging!(
+                "Proof length {} does not match the tree depth {}",
+                proof_vec.len(),
+                MAX_DEPTH
+            );
+            return Err(ConcurrentMerkleTreeError::InvalidProof);
+        }
         let mut proof: [Node; MAX_DEPTH] = [Node::default(); MAX_DEPTH];
         proof.copy_from_slice(proof_vec);
+
+        // Record the path to the rightmost leaf as the first changelog
+        let mut path = [Node::default(); MAX_DEPTH];
+        let mut node = rightmost_leaf;
+        for (i, sibling) in proof.iter().enumerate() {
+            path[i] = node;
+            hash_to_parent(&mut node, sibling, (index >> i) & 1 == 0);
+        }
+        if root != node {
+            solana_logging!("Proof failed to verify");
+            return Err(ConcurrentMerkleTreeError::InvalidProof);
+        }
+
         let rightmost_proof = Path {
             proof,
             index: index + 1,
             leaf: rightmost_leaf,
             _padding: 0,
         };
-        self.change_logs[0].root = root;
+        self.change_logs[0] = ChangeLog::<MAX_DEPTH>::new(root, path, index);
         self.sequence_number = 1;
         self.active_index = 0;
         self.buffer_size = 1;
         self.rightmost_proof = rightmost_proof;
-        if root != recompute(rightmost_leaf, &proof, index) {
-            solana_logging!("Proof failed to verify");
-            return Err(ConcurrentMerkleTreeError::InvalidProof);
-        }
         Ok(root)
     }
 
@@ -184,6 +211,17 @@ impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize>
         Box::new(self.change_logs[self.active_index as usize])
     }
 
+    /// Returns the `count` most recent changelogs, oldest first, such as the changes made by a
+    /// batch operation. At most `buffer_size` changelogs are available.
+    pub fn get_change_logs(&self, count: usize) -> Vec<ChangeLog<MAX_DEPTH>> {
+        let mask: usize = MAX_BUFFER_SIZE - 1;
+        let count = count.min(self.buffer_size as usize);
+        (0..count)
+            .rev()
+            .map(|i| self.change_logs[(self.active_index as usize).wrapping_sub(i) & mask])
+            .collect()
+    }
+
     /// This method will fail if the leaf cannot be proven
     /// to exist in the current tree root.
     ///
@@ -244,7 +282,7 @@ impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize>
     }
 
     /// Appending a non-empty Node will always succeed .
-    pub fn append(&mut self, mut node: Node) -> Result<Node, ConcurrentMerkleTreeError> {
+    pub fn append(&mut self, node: Node) -> Result<Node, ConcurrentMerkleTreeError> {
         check_bounds(MAX_DEPTH, MAX_BUFFER_SIZE);
         if !self.is_initialized() {
             return Err(ConcurrentMerkleTreeError::TreeNotInitialized);
@@ -255,26 +293,70 @@ impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize>
         if self.rightmost_proof.index >= 1 << MAX_DEPTH {
             return Err(ConcurrentMerkleTreeError::TreeFull);
         }
+        let mut empty_node_cache = Box::new([Node::default(); MAX_DEPTH]);
+        self.append_leaf(node, &mut empty_node_cache, false)
+    }
+
+    /// Appends multiple non-empty Nodes, with the same result as calling `append` for each of
+    /// them in order, including one changelog per leaf so that concurrent operations can still be
+    /// fast-forwarded. Hashing is amortized across the batch: empty nodes are only computed once,
+    /// and each leaf reuses the path of the previous one instead of recomputing it.
+    ///
+    /// Fails without modifying the tree if any leaf is empty or the leaves do not fit in the tree.
+    pub fn append_batch(&mut self, leaves: &[Node]) -> Result<Node, ConcurrentMerkleTreeError> {
+        check_bounds(MAX_DEPTH, MAX_BUFFER_SIZE);
+        if !self.is_initialized() {
+            return Err(ConcurrentMerkleTreeError::TreeNotInitialized);
+        }
+        if leaves.contains(&EMPTY) {
+            return Err(ConcurrentMerkleTreeError::CannotAppendEmptyNode);
+        }
+        if self.rightmost_proof.index as usize + leaves.len() > 1 << MAX_DEPTH {
+            return Err(ConcurrentMerkleTreeError::TreeFull);
+        }
+        let mut empty_node_cache = Box::new([Node::default(); MAX_DEPTH]);
+        let mut root = self.get_root();
+        for (i, leaf) in leaves.iter().enumerate() {
+            // After the first leaf, the latest changelog holds the path to the rightmost leaf
+            root = self.append_leaf(*leaf, &mut empty_node_cache, i > 0)?;
+        }
+        Ok(root)
+    }
+
+    /// Appends a leaf to a tree that has room for it. If `rightmost_path_in_change_log` is set,
+    /// the latest changelog must be the path to the current rightmost leaf, which is then reused
+    /// instead of being recomputed from the rightmost proof.
+    fn append_leaf(
+        &mut self,
+        mut node: Node,
+        empty_node_cache: &mut Box<[Node; MAX_DEPTH]>,
+        rightmost_path_in_change_log: bool,
+    ) -> Result<Node, ConcurrentMerkleTreeError> {
         if self.rightmost_proof.index == 0 {
             return self.initialize_tree_from_append(node, self.rightmost_proof.proof);
         }
         let leaf = node;
         let intersection = self.rightmost_proof.index.trailing_zeros() as usize;
         let mut change_list = [EMPTY; MAX_DEPTH];
-        let mut intersection_node = self.rightmost_proof.leaf;
-        let mut empty_node_cache = Box::new([Node::default(); MAX_DEPTH]);
+        let mut intersection_node = if rightmost_path_in_change_log {
+            self.change_logs[self.active_index as usize].path[intersection]
+        } else {
+            self.rightmost_proof.leaf
+        };
 
         for (i, cl_item) in change_list.iter_mut().enumerate().take(MAX_DEPTH) {
             *cl_item = node;
             match i {
                 i if i < intersection => {
                     // Compute proof to the appended node from empty nodes
-                    let sibling = empty_node_cached::<MAX_DEPTH>(i as u32, &mut empty_node_cache);
-                    hash_to_parent(
-                        &mut intersection_node,
-                        &self.rightmost_proof.proof[i],
-                        ((self.rightmost_proof.index - 1) >> i) & 1 == 0,
-                    );
+                    let sibling = empty_node_cached::<MAX_DEPTH>(i as u32, empty_node_cache);
+                    if !rightmost_path_in_change_log {
+                        hash_to_parent(
+                            &mut intersection_node,
+                            &self.rightmost_proof.proof[i],
+                            ((self.rightmost_proof.index - 1) >> i) & 1 == 0,
+                        );
+                    }
                     hash_to_parent(&mut node, &sibling, true);
                     self.rightmost_proof.proof[i] = sibling;
                 }
@@ -367,6 +449,97 @@ impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize>
         }
     }
 
+    /// Replaces multiple leaves, with the same result as calling `set_leaf` for each of them in
+    /// order with the same `current_root`, and each leaf gets its own changelog.
+    ///
+    /// The leaves must be sorted by strictly increasing index and share a single combined proof,
+    /// laid out as returned by [multi_proof_positions]. Missing trailing nodes are inferred as
+    /// empty node hashes, like in `set_leaf`. The combined proof is fast-forwarded once for the
+    /// whole batch and nodes shared by the paths of several leaves are only hashed once.
+    ///
+    /// All leaves are verified before any of them is replaced, so a failed batch does not modify
+    /// the tree.
+    pub fn set_leaves(
+        &mut self,
+        current_root: Node,
+        leaves: &[LeafUpdate],
+        proof_vec: &[Node],
+    ) -> Result<Node, ConcurrentMerkleTreeError> {
+        check_bounds(MAX_DEPTH, MAX_BUFFER_SIZE);
+        if !self.is_initialized() {
+            return Err(ConcurrentMerkleTreeError::TreeNotInitialized);
+        }
+        if leaves.is_empty() {
+            return Ok(self.get_root());
+        }
+
+        let mut indices = Vec::with_capacity(leaves.len());
+        for leaf in leaves {
+            check_leaf_index(leaf.index, MAX_DEPTH)?;
+            if leaf.index > self.rightmost_proof.index {
+                return Err(ConcurrentMerkleTreeError::LeafIndexOutOfBounds);
+            }
+            if indices.last().map_or(false, |last| *last >= leaf.index) {
+                return Err(ConcurrentMerkleTreeError::UnorderedLeafIndices);
+            }
+            indices.push(leaf.index);
+        }
+
+        let positions = multi_proof_positions(&indices, MAX_DEPTH);
+        if proof_vec.len() > positions.len() {
+            return Err(ConcurrentMerkleTreeError::InvalidProof);
+        }
+        let mut proof = proof_vec.to_vec();
+        proof.extend(
+            positions
+                .iter()
+                .skip(proof_vec.len())
+                .map(|(level, _)| empty_node(*level)),
+        );
+
+        log_compute!();
+        let mut previous_leaves: Vec<(u32, Node)> = leaves
+            .iter()
+            .map(|leaf| (leaf.index, leaf.previous_leaf))
+            .collect();
+        self.fast_forward_multi_proof(current_root, &positions, &mut proof, &mut previous_leaves)?;
+
+        let mut levels = recompute_multi(&previous_leaves, &proof, MAX_DEPTH)
+            .ok_or(ConcurrentMerkleTreeError::InvalidProof)?;
+        if levels[MAX_DEPTH][0].1 != self.get_root() {
+            return Err(ConcurrentMerkleTreeError::InvalidProof);
+        }
+        log_compute!();
+
+        // Every sibling on the path of a leaf is either in the combined proof or on the path of
+        // another leaf, so the proof of each leaf can be read from the nodes recomputed above,
+        // which are kept up to date with the changes made by earlier leaves
+        let mut root = self.get_root();
+        for leaf in leaves {
+            let mut leaf_proof = [EMPTY; MAX_DEPTH];
+            for (level, node) in leaf_proof.iter_mut().enumerate() {
+                let sibling_index = (leaf.index >> level) ^ 1;
+                let position = levels[level]
+                    .binary_search_by_key(&sibling_index, |(index, _)| *index)
+                    .map_err(|_| ConcurrentMerkleTreeError::InvalidProof)?;
+                *node = levels[level][position].1;
+            }
+            self.update_internal_counters();
+            root = self.update_buffers_from_proof(leaf.new_leaf, &leaf_proof, leaf.index);
+
+            let path = self.change_logs[self.active_index as usize].path;
+            for (level, node) in path.iter().enumerate() {
+                let path_index = leaf.index >> level;
+                if let Ok(position) =
+                    levels[level].binary_search_by_key(&path_index, |(index, _)| *index)
+                {
+                    levels[level][position].1 = *node;
+                }
+            }
+        }
+        Ok(root)
+    }
+
     /// Returns the Current Seq of the tree, the seq is the monotonic counter of the tree operations
     /// that is incremented every time a mutable operation is performed on the tree.
     pub fn get_seq(&self) -> u64 {
@@ -417,6 +590,58 @@ impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize>
         proof_leaf_unchanged
     }
 
+    /// Modifies the combined `proof` of a batch of leaves in place by fast-forwarding it through
+    /// the `changelog`s recorded after `current_root`, or through the whole buffer if it cannot
+    /// be found. Fails if any of the `leaves` was modified in the meantime.
+    fn fast_forward_multi_proof(
+        &self,
+        current_root: Node,
+        positions: &[(u32, u32)],
+        proof: &mut [Node],
+        leaves: &mut [(u32, Node)],
+    ) -> Result<(), ConcurrentMerkleTreeError> {
+        let mask: usize = MAX_BUFFER_SIZE - 1;
+        let (mut changelog_buffer_index, use_full_buffer) =
+            match self.find_root_in_changelog(current_root) {
+                Some(matching_changelog_index) => (matching_changelog_index, false),
+                None => {
+                    solana_logging!("Failed to find root in change log -> replaying full buffer");
+                    (
+                        self.active_index.wrapping_sub(self.buffer_size - 1) & mask as u64,
+                        true,
+                    )
+                }
+            };
+        let mut updated_leaves = leaves.to_vec();
+        loop {
+            if !use_full_buffer && changelog_buffer_index == self.active_index {
+                break;
+            }
+            changelog_buffer_index = (changelog_buffer_index + 1) & mask as u64;
+            let change_log = &self.change_logs[changelog_buffer_index as usize];
+            match updated_leaves.binary_search_by_key(&change_log.index, |(index, _)| *index) {
+                Ok(i) => updated_leaves[i].1 = change_log.get_leaf(),
+                Err(_) => {
+                    // The changed path meets the paths of the batch at exactly one proof node
+                    for (level, node) in change_log.path.iter().enumerate() {
+                        let position = (level as u32, change_log.index >> level);
+                        if let Ok(i) = positions.binary_search(&position) {
+                            proof[i] = *node;
+                            break;
+                        }
+                    }
+                }
+            }
+            if use_full_buffer && changelog_buffer_index == self.active_index {
+                break;
+            }
+        }
+        if updated_leaves != leaves {
+            return Err(ConcurrentMerkleTreeError::LeafContentsModified);
+        }
+        Ok(())
+    }
+
     #[inline(always)]
     fn find_root_in_changelog(&self, current_root: Node) -> Option<u64> {
         let mask: usize = MAX_BUFFER_SIZE - 1;
@@ -38,4 +38,8 @@ pub enum ConcurrentMerkleTreeError {
     /// Tree has at least 1 non-EMTPY leaf
     #[error("Tree is not empty")]
     TreeNonEmpty,
+
+    /// The leaves of a batch are not in strictly increasing index order
+    #[error("Leaf indices in a batch must be strictly increasing")]
+    UnorderedLeafIndices,
 }
@@ -41,3 +41,77 @@ pub fn fill_in_proof<const MAX_DEPTH: usize>(
         *item = empty_node(i as u32);
     }
 }
+
+/// Returns the positions of the nodes in a combined proof for the leaves at `indices`, which must
+/// be strictly increasing. These are the siblings of the nodes on the paths from the leaves to
+/// the root that are not on any of those paths themselves, ordered by level from the leaves up,
+/// then from left to right. Each position is a `(level, index within the level)` pair.
+pub fn multi_proof_positions(indices: &[u32], max_depth: usize) -> Vec<(u32, u32)> {
+    let mut positions = vec![];
+    let mut nodes = indices.to_vec();
+    for level in 0..max_depth as u32 {
+        let mut i = 0;
+        while i < nodes.len() {
+            let index = nodes[i];
+            if index & 1 == 0 && nodes.get(i + 1) == Some(&(index + 1)) {
+                // Both children are on a path, so neither is needed in the proof
+                i += 1;
+            } else {
+                positions.push((level, index ^ 1));
+            }
+            i += 1;
+        }
+        for node in nodes.iter_mut() {
+            *node >>= 1;
+        }
+        nodes.dedup();
+    }
+    positions
+}
+
+/// Recomputes root of the Merkle tree from leaves at strictly increasing indices & their combined
+/// proof, laid out as returned by [multi_proof_positions]. Nodes shared by the paths of several
+/// leaves are only hashed once.
+///
+/// Returns every node used at each level, sorted by index, with the root alone at level
+/// `max_depth`, or `None` if the length of the proof does not match the leaves.
+pub fn recompute_multi(
+    leaves: &[(u32, Node)],
+    proof: &[Node],
+    max_depth: usize,
+) -> Option<Vec<Vec<(u32, Node)>>> {
+    let mut proof = proof.iter();
+    let mut levels = Vec::with_capacity(max_depth + 1);
+    let mut nodes = leaves.to_vec();
+    for _ in 0..max_depth {
+        let mut level = Vec::with_capacity(nodes.len() * 2);
+        let mut parents = Vec::with_capacity(nodes.len());
+        let mut i = 0;
+        while i < nodes.len() {
+            let (index, mut node) = nodes[i];
+            let sibling = match nodes.get(i + 1) {
+                Some(&(next_index, next_node)) if index & 1 == 0 && next_index == index + 1 => {
+                    i += 1;
+                    next_node
+                }
+                _ => {
+                    let sibling = *proof.next()?;
+                    level.push((index ^ 1, sibling));
+                    sibling
+                }
+            };
+            hash_to_parent(&mut node, &sibling, index & 1 == 0);
+            parents.push((index >> 1, node));
+            i += 1;
+        }
+        level.extend_from_slice(&nodes);
+        level.sort_unstable_by_key(|(index, _)| *index);
+        levels.push(level);
+        nodes = parents;
+    }
+    if proof.next().is_some() {
+        return None;
+    }
+    levels.push(nodes);
+    Some(levels)
+}
@@ -19,10 +19,13 @@ pub fn empty_node_cached<const N: usize>(level: u32, cache: &mut Box<[Node; N]>)
         let lower_empty = if target < cache.len() && cache[target] != EMPTY {
             cache[target]
         } else {
-            empty_node(target as u32)
+            empty_node_cached(target as u32, cache)
         };
         let hash = hashv(&[lower_empty.as_ref(), lower_empty.as_ref()]);
         data.copy_from_slice(hash.as_ref());
+        if (level as usize) < cache.len() {
+            cache[level as usize] = data;
+        }
     }
     data
 }
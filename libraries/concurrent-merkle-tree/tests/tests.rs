@@ -1,8 +1,9 @@
 #![allow(clippy::integer_arithmetic)]
 use rand::thread_rng;
 use rand::{self, Rng};
-use spl_concurrent_merkle_tree::concurrent_merkle_tree::ConcurrentMerkleTree;
+use spl_concurrent_merkle_tree::concurrent_merkle_tree::{ConcurrentMerkleTree, LeafUpdate};
 use spl_concurrent_merkle_tree::error::ConcurrentMerkleTreeError;
+use spl_concurrent_merkle_tree::hash::multi_proof_positions;
 use spl_concurrent_merkle_tree::node::{Node, EMPTY};
 use spl_merkle_tree_reference::MerkleTree;
 
@@ -193,6 +194,11 @@ async fn test_initialize_with_root() {
         tree.get_root(),
         "Init failed to set root properly"
     );
+    assert_eq!(
+        cmt.get_change_log().get_leaf(),
+        tree.get_leaf(last_leaf_idx),
+        "Init failed to record the path to the rightmost leaf"
+    );
 
     // Check that reinitialization fails
     if let Err(ConcurrentMerkleTreeError::TreeAlreadyInitialized) = cmt.initialize_with_root(
@@ -548,3 +554,315 @@ async fn test_prove_tree_empty_batched() {
     // Check that the last leaf was successfully removed
     cmt.prove_tree_is_empty().unwrap();
 }
+
+#[tokio::test(flavor = "multi_thread")]
+/// Test that appending in batches matches appending leaves one at a time
+async fn test_append_batch() {
+    let (mut cmt, mut tree) = setup();
+    let (mut sequential_cmt, _) = setup();
+    let mut rng = thread_rng();
+    cmt.initialize().unwrap();
+    sequential_cmt.initialize().unwrap();
+
+    let mut num_leaves = 0;
+    while num_leaves < 1 << DEPTH {
+        let batch_size = rng
+            .gen_range(1..=BUFFER_SIZE)
+            .min((1 << DEPTH) - num_leaves);
+        let leaves: Vec<Node> = (0..batch_size).map(|_| rng.gen::<[u8; 32]>()).collect();
+        for (i, leaf) in leaves.iter().enumerate() {
+            sequential_cmt.append(*leaf).unwrap();
+            tree.add_leaf(*leaf, num_leaves + i);
+        }
+        num_leaves += batch_size;
+
+        assert_eq!(cmt.append_batch(&leaves).unwrap(), tree.get_root());
+        assert_eq!(cmt.get_root(), tree.get_root());
+        assert_eq!(cmt.sequence_number, sequential_cmt.sequence_number);
+        assert_eq!(
+            cmt.get_change_logs(batch_size),
+            sequential_cmt.get_change_logs(batch_size),
+            "Batch append should record one changelog per leaf"
+        );
+        assert_eq!(
+            cmt.rightmost_proof.proof,
+            sequential_cmt.rightmost_proof.proof
+        );
+        assert_eq!(cmt.rightmost_proof.leaf, tree.get_leaf(num_leaves - 1));
+    }
+
+    assert_eq!(
+        ConcurrentMerkleTreeError::TreeFull,
+        cmt.append_batch(&[rng.gen::<[u8; 32]>()]).unwrap_err(),
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+/// Test that a batch append can be fast-forwarded through by concurrent replaces
+async fn test_append_batch_concurrent_replace() {
+    let (mut cmt, mut tree) = setup();
+    let mut rng = thread_rng();
+    cmt.initialize().unwrap();
+
+    let leaves: Vec<Node> = (0..5).map(|_| rng.gen::<[u8; 32]>()).collect();
+    cmt.append_batch(&leaves).unwrap();
+    for (i, leaf) in leaves.iter().enumerate() {
+        tree.add_leaf(*leaf, i);
+    }
+
+    // Proofs taken before the batch are still valid after it
+    let stale_root = tree.get_root();
+    let stale_proof = tree.get_proof_of_leaf(1);
+    let batch: Vec<Node> = (0..7).map(|_| rng.gen::<[u8; 32]>()).collect();
+    cmt.append_batch(&batch).unwrap();
+    for (i, leaf) in batch.iter().enumerate() {
+        tree.add_leaf(*leaf, leaves.len() + i);
+    }
+
+    let new_leaf = rng.gen::<[u8; 32]>();
+    cmt.set_leaf(stale_root, tree.get_leaf(1), new_leaf, &stale_proof, 1)
+        .unwrap();
+    tree.add_leaf(new_leaf, 1);
+    assert_eq!(cmt.get_root(), tree.get_root());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_append_batch_failures() {
+    let (mut cmt, _) = setup();
+    let mut rng = thread_rng();
+
+    assert_eq!(
+        ConcurrentMerkleTreeError::TreeNotInitialized,
+        cmt.append_batch(&[rng.gen::<[u8; 32]>()]).unwrap_err(),
+    );
+
+    cmt.initialize().unwrap();
+    let root = cmt.get_root();
+    assert_eq!(
+        ConcurrentMerkleTreeError::CannotAppendEmptyNode,
+        cmt.append_batch(&[rng.gen::<[u8; 32]>(), EMPTY])
+            .unwrap_err(),
+    );
+    assert_eq!(
+        ConcurrentMerkleTreeError::TreeFull,
+        cmt.append_batch(&vec![rng.gen::<[u8; 32]>(); (1 << DEPTH) + 1])
+            .unwrap_err(),
+    );
+    assert_eq!(
+        cmt.get_root(),
+        root,
+        "Failed batch should not modify the tree"
+    );
+    assert_eq!(cmt.rightmost_proof.index, 0);
+}
+
+/// Builds the combined proof of the leaves at `indices` from the off-chain tree
+fn get_multi_proof(tree: &MerkleTree, indices: &[u32]) -> Vec<Node> {
+    multi_proof_positions(indices, DEPTH)
+        .iter()
+        .map(|(level, index)| {
+            // The proof of a leaf below the sibling of this node contains it at this level
+            tree.get_proof_of_leaf(((index ^ 1) << level) as usize)[*level as usize]
+        })
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_leaves() {
+    let (mut cmt, mut tree) = setup();
+    let mut rng = thread_rng();
+    cmt.initialize().unwrap();
+
+    let leaves: Vec<Node> = (0..(1 << DEPTH)).map(|_| rng.gen::<[u8; 32]>()).collect();
+    cmt.append_batch(&leaves).unwrap();
+    for (i, leaf) in leaves.iter().enumerate() {
+        tree.add_leaf(*leaf, i);
+    }
+
+    for _ in 0..16 {
+        // The combined proof of a batch is taken against a single root
+        let root = tree.get_root();
+        let mut indices: Vec<u32> = (0..BUFFER_SIZE / 2)
+            .map(|_| rng.gen_range(0..1 << DEPTH))
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        let proof = get_multi_proof(&tree, &indices);
+        assert!(proof.len() < indices.len() * DEPTH);
+        let updates: Vec<LeafUpdate> = indices
+            .iter()
+            .map(|i| LeafUpdate {
+                previous_leaf: tree.get_leaf(*i as usize),
+                new_leaf: rng.gen::<[u8; 32]>(),
+                index: *i,
+            })
+            .collect();
+        for update in updates.iter() {
+            tree.add_leaf(update.new_leaf, update.index as usize);
+        }
+
+        assert_eq!(
+            cmt.set_leaves(root, &updates, &proof).unwrap(),
+            tree.get_root()
+        );
+        assert_eq!(cmt.get_root(), tree.get_root());
+        let change_logs = cmt.get_change_logs(updates.len());
+        for (change_log, update) in change_logs.iter().zip(updates.iter()) {
+            assert_eq!(change_log.index, update.index);
+            assert_eq!(change_log.get_leaf(), update.new_leaf);
+        }
+    }
+
+    // A combined proof is fast-forwarded through concurrent changes to other leaves
+    let root = tree.get_root();
+    let indices = [3, 4, 500];
+    let proof = get_multi_proof(&tree, &indices);
+    for i in [2, 5, 700] {
+        let new_leaf = rng.gen::<[u8; 32]>();
+        cmt.set_leaf(
+            tree.get_root(),
+            tree.get_leaf(i),
+            new_leaf,
+            &tree.get_proof_of_leaf(i),
+            i as u32,
+        )
+        .unwrap();
+        tree.add_leaf(new_leaf, i);
+    }
+    let updates: Vec<LeafUpdate> = indices
+        .iter()
+        .map(|i| LeafUpdate {
+            previous_leaf: tree.get_leaf(*i as usize),
+            new_leaf: rng.gen::<[u8; 32]>(),
+            index: *i,
+        })
+        .collect();
+    for update in updates.iter() {
+        tree.add_leaf(update.new_leaf, update.index as usize);
+    }
+    assert_eq!(
+        cmt.set_leaves(root, &updates, &proof).unwrap(),
+        tree.get_root()
+    );
+
+    // But fails if one of its leaves was changed
+    let root = tree.get_root();
+    let proof = get_multi_proof(&tree, &indices);
+    let updates: Vec<LeafUpdate> = indices
+        .iter()
+        .map(|i| LeafUpdate {
+            previous_leaf: tree.get_leaf(*i as usize),
+            new_leaf: rng.gen::<[u8; 32]>(),
+            index: *i,
+        })
+        .collect();
+    let new_leaf = rng.gen::<[u8; 32]>();
+    cmt.set_leaf(
+        root,
+        tree.get_leaf(4),
+        new_leaf,
+        &tree.get_proof_of_leaf(4),
+        4,
+    )
+    .unwrap();
+    tree.add_leaf(new_leaf, 4);
+    assert_eq!(
+        ConcurrentMerkleTreeError::LeafContentsModified,
+        cmt.set_leaves(root, &updates, &proof).unwrap_err(),
+    );
+
+    // A batch with an invalid leaf fails without replacing any of the leaves before it
+    let root = tree.get_root();
+    let seq = cmt.get_seq();
+    let proof = get_multi_proof(&tree, &indices);
+    let mut updates: Vec<LeafUpdate> = indices
+        .iter()
+        .map(|i| LeafUpdate {
+            previous_leaf: tree.get_leaf(*i as usize),
+            new_leaf: rng.gen::<[u8; 32]>(),
+            index: *i,
+        })
+        .collect();
+    updates[2].previous_leaf = rng.gen::<[u8; 32]>();
+    assert_eq!(
+        ConcurrentMerkleTreeError::InvalidProof,
+        cmt.set_leaves(root, &updates, &proof).unwrap_err(),
+    );
+    assert_eq!(cmt.get_root(), root);
+    assert_eq!(cmt.get_seq(), seq);
+
+    // Leaves must be sorted by index
+    updates[2].previous_leaf = tree.get_leaf(500);
+    updates.swap(0, 1);
+    assert_eq!(
+        ConcurrentMerkleTreeError::UnorderedLeafIndices,
+        cmt.set_leaves(root, &updates, &proof).unwrap_err(),
+    );
+    assert_eq!(cmt.get_root(), root);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+/// Test that a tree initialized with a partially filled root can be modified like the original
+async fn test_initialize_with_root_then_modify() {
+    let (mut cmt, mut tree) = setup();
+    let mut rng = thread_rng();
+
+    let num_leaves = (1 << (DEPTH - 1)) + 3;
+    for i in 0..num_leaves {
+        tree.add_leaf(rng.gen::<[u8; 32]>(), i);
+    }
+
+    let rightmost_index = num_leaves - 1;
+    assert_eq!(
+        ConcurrentMerkleTreeError::InvalidProof,
+        cmt.initialize_with_root(
+            tree.get_root(),
+            tree.get_leaf(rightmost_index),
+            &tree.get_proof_of_leaf(rightmost_index)[1..],
+            rightmost_index as u32,
+        )
+        .unwrap_err(),
+    );
+    assert_eq!(
+        ConcurrentMerkleTreeError::InvalidProof,
+        cmt.initialize_with_root(
+            tree.get_root(),
+            rng.gen::<[u8; 32]>(),
+            &tree.get_proof_of_leaf(rightmost_index),
+            rightmost_index as u32,
+        )
+        .unwrap_err(),
+    );
+    assert!(!cmt.is_initialized());
+
+    cmt.initialize_with_root(
+        tree.get_root(),
+        tree.get_leaf(rightmost_index),
+        &tree.get_proof_of_leaf(rightmost_index),
+        rightmost_index as u32,
+    )
+    .unwrap();
+
+    // Appends continue after the rightmost leaf
+    let leaves: Vec<Node> = (0..4).map(|_| rng.gen::<[u8; 32]>()).collect();
+    cmt.append(leaves[0]).unwrap();
+    cmt.append_batch(&leaves[1..]).unwrap();
+    for (i, leaf) in leaves.iter().enumerate() {
+        tree.add_leaf(*leaf, num_leaves + i);
+    }
+    assert_eq!(cmt.get_root(), tree.get_root());
+
+    // Leaves loaded before initialization can be replaced
+    let index = rng.gen_range(0..num_leaves);
+    let new_leaf = rng.gen::<[u8; 32]>();
+    cmt.set_leaf(
+        tree.get_root(),
+        tree.get_leaf(index),
+        new_leaf,
+        &tree.get_proof_of_leaf(index),
+        index as u32,
+    )
+    .unwrap();
+    tree.add_leaf(new_leaf, index);
+    assert_eq!(cmt.get_root(), tree.get_root());
+}
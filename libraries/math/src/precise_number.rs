@@ -277,7 +277,6 @@ impl PreciseNumber {
     /// (numerator / denominator)
     /// NOTE: this function is private because its accurate range and precision
     /// have not been estbalished.
-    #[allow(dead_code)]
     fn checked_pow_fraction(&self, exponent: &Self) -> Option<Self> {
         assert!(self.value >= Self::min_pow_base());
         assert!(self.value <= Self::max_pow_base());
@@ -293,6 +292,37 @@ impl PreciseNumber {
         precise_whole.checked_mul(&precise_remainder)
     }
 
+    /// Maximum error, in units of 10^-12, of the fractional part of a power
+    /// computed by checked_pow_near_one.
+    ///
+    /// For bases above 1, the terms of the Taylor Series alternate in sign and
+    /// shrink, so stopping once a term is below `precision()` leaves an error
+    /// below 100 units.  Each term takes three operations rounded to half a
+    /// unit, and since the base is at most 3/2, the error carried into the
+    /// following terms at most doubles it, adding at most 3 units for each of
+    /// the 40 terms needed for (1/2)^k to drop below a unit.
+    pub const POW_NEAR_ONE_MAX_ERROR: u128 = 300;
+
+    /// Maximum base allowed when calculating exponents in checked_pow_near_one
+    fn max_pow_near_one_base() -> InnerUint {
+        InnerUint::from(3 * ONE / 2)
+    }
+
+    /// Get the power of a base between 1 and 3/2, inclusive, where the exponent
+    /// is any non-negative number.
+    ///
+    /// The whole part of the exponent is applied by repeated squaring, which
+    /// adds at most half a unit of relative error per unit of the exponent, and
+    /// the fractional part is approximated within `POW_NEAR_ONE_MAX_ERROR`
+    /// units.  The relative error of the result is therefore below
+    /// `(exponent + 1) * POW_NEAR_ONE_MAX_ERROR` units of 10^-12.
+    pub fn checked_pow_near_one(&self, exponent: &Self) -> Option<Self> {
+        if self.value < one() || self.value > Self::max_pow_near_one_base() {
+            return None;
+        }
+        self.checked_pow_fraction(exponent)
+    }
+
     /// Approximate the nth root of a number using Newton's method
     /// https://en.wikipedia.org/wiki/Newton%27s_method
     /// NOTE: this function is private because its accurate range and precision
@@ -444,6 +474,43 @@ mod tests {
         ); // 8.629769290
     }
 
+    #[test]
+    fn test_pow_near_one() {
+        let one = one();
+        let to_f64 = |value: InnerUint| value.as_u128() as f64 / ONE as f64;
+        for base in [one, one + 1, one * 11 / 10, one * 5 / 4, one * 3 / 2] {
+            for exponent in [
+                one / 100,
+                one / 3,
+                one * 99 / 100,
+                one,
+                one * 7 / 2,
+                one * 99,
+            ] {
+                let power = PreciseNumber { value: base }
+                    .checked_pow_near_one(&PreciseNumber { value: exponent })
+                    .unwrap();
+                let expected = to_f64(base).powf(to_f64(exponent));
+                let max_error = expected
+                    * (to_f64(exponent) + 1.0)
+                    * PreciseNumber::POW_NEAR_ONE_MAX_ERROR as f64
+                    / ONE as f64;
+                assert!((to_f64(power.value) - expected).abs() <= max_error);
+            }
+        }
+
+        // bases outside of the range of the bound are rejected
+        let exponent = PreciseNumber { value: one / 2 };
+        assert!(PreciseNumber { value: one - 1 }
+            .checked_pow_near_one(&exponent)
+            .is_none());
+        assert!(PreciseNumber {
+            value: one * 3 / 2 + 1
+        }
+        .checked_pow_near_one(&exponent)
+        .is_none());
+    }
+
     #[test]
     fn test_newtonian_approximation() {
         let test = PreciseNumber::new(0).unwrap();
@@ -0,0 +1,28 @@
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    #[error("Max depth {0} is not supported, expected at most 30")]
+    InvalidMaxDepth(u32),
+
+    #[error("Leaf index {0} is out of bounds of the tree")]
+    LeafIndexOutOfBounds(u32),
+
+    #[error("Canopy depth {0} is larger than the tree depth")]
+    InvalidCanopyDepth(u32),
+
+    #[error("Changelog {seq} has a path that does not match its leaf index or the tree depth")]
+    InvalidPath { seq: u64 },
+
+    #[error("Changelog {seq} does not match the replayed tree, a changelog may have been missed")]
+    PathMismatch { seq: u64 },
+
+    #[error("Expected changelog {expected} but received {received}, changelogs are missing")]
+    SequenceGap { expected: u64, received: u64 },
+
+    #[error("Expected changelog {expected} but received {received}, which was already applied")]
+    StaleChangeLog { expected: u64, received: u64 },
+
+    #[error("Data is not a valid SPL Account Compression event")]
+    InvalidEvent,
+}
@@ -0,0 +1,19 @@
+use crate::error::ReplayError;
+use anchor_lang::AnchorDeserialize;
+use spl_account_compression::events::{AccountCompressionEvent, ChangeLogEvent};
+
+/// Decodes a `ChangeLogEvent`, as serialized by SPL Account Compression
+pub fn parse_change_log_event(data: &[u8]) -> Result<ChangeLogEvent, ReplayError> {
+    ChangeLogEvent::try_from_slice(data).map_err(|_| ReplayError::InvalidEvent)
+}
+
+/// Decodes the data of an SPL Noop instruction invoked by SPL Account Compression.
+/// Returns `None` for application data events, which do not modify the tree.
+///
+/// The `id` of the returned event identifies the tree it must be ingested by.
+pub fn parse_noop_data(data: &[u8]) -> Result<Option<ChangeLogEvent>, ReplayError> {
+    match AccountCompressionEvent::try_from_slice(data).map_err(|_| ReplayError::InvalidEvent)? {
+        AccountCompressionEvent::ChangeLog(event) => Ok(Some(event)),
+        AccountCompressionEvent::ApplicationData(_) => Ok(None),
+    }
+}
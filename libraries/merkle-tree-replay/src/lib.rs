@@ -0,0 +1,16 @@
+//! # Merkle Tree Replay
+//!
+//! Off-chain reconstruction of SPL ConcurrentMerkleTrees from the changelogs
+//! emitted by SPL Account Compression, for indexers that need to serve proofs.
+//!
+//! A [ReplayTree](replay_tree::ReplayTree) ingests `ChangeLogEvent`s, either
+//! decoded or as the raw bytes logged through SPL Noop, detects gaps in their
+//! sequence numbers, and produces proofs and canopy nodes for the current
+//! state of the tree.
+
+/// Descriptive errors
+pub mod error;
+/// Decoding of the events logged by SPL Account Compression
+pub mod event;
+/// Replayed merkle tree with proof generation
+pub mod replay_tree;
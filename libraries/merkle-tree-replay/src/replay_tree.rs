@@ -0,0 +1,278 @@
+use crate::{error::ReplayError, event::parse_change_log_event};
+use spl_account_compression::events::{ChangeLogEntry, ChangeLogEvent};
+use spl_concurrent_merkle_tree::{
+    hash::hash_to_parent,
+    node::{empty_node, Node},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+};
+
+/// Max depth supported by SPL ConcurrentMerkleTrees
+pub const MAX_SUPPORTED_DEPTH: u32 = 30;
+
+/// Full merkle tree rebuilt from the changelogs of an SPL ConcurrentMerkleTree.
+///
+/// Nodes are stored sparsely, so the memory used grows with the number of
+/// non-empty leaves rather than the capacity of the tree.
+pub struct ReplayTree {
+    max_depth: u32,
+    /// Non-empty nodes, by heap index
+    nodes: HashMap<u32, Node>,
+    /// Empty node of each level, from the leaves up to the root
+    empty_nodes: Vec<Node>,
+    /// Sequence number of the next changelog to apply
+    next_seq: u64,
+    /// Changelogs ingested ahead of `next_seq`
+    pending: BTreeMap<u64, ChangeLogEntry>,
+}
+
+impl ReplayTree {
+    /// Creates an empty tree. The changelog emitted when the tree was initialized by
+    /// `init_empty_merkle_tree`, with sequence number 0, is expected first.
+    pub fn new(max_depth: u32) -> Result<Self, ReplayError> {
+        if max_depth > MAX_SUPPORTED_DEPTH {
+            return Err(ReplayError::InvalidMaxDepth(max_depth));
+        }
+        Ok(Self {
+            max_depth,
+            nodes: HashMap::new(),
+            empty_nodes: (0..=max_depth).map(empty_node).collect(),
+            next_seq: 0,
+            pending: BTreeMap::new(),
+        })
+    }
+
+    /// Creates a tree holding `leaves` from index 0, matching a tree initialized with their
+    /// root by `init_merkle_tree_with_root`. The changelog emitted by that instruction, with
+    /// sequence number 1, is expected next.
+    // Levels are bounded by `MAX_SUPPORTED_DEPTH` and the leaves by the capacity of
+    // the tree, so the index arithmetic cannot overflow
+    #[allow(clippy::integer_arithmetic)]
+    pub fn from_leaves(max_depth: u32, leaves: &[Node]) -> Result<Self, ReplayError> {
+        let mut tree = Self::new(max_depth)?;
+        if leaves.len() as u64 > 1 << max_depth {
+            let index = u32::try_from(leaves.len() - 1).unwrap_or(u32::MAX);
+            return Err(ReplayError::LeafIndexOutOfBounds(index));
+        }
+        for (index, leaf) in leaves.iter().enumerate() {
+            tree.set_node(0, index as u32, *leaf);
+        }
+        let mut width = leaves.len() as u32;
+        for level in 1..=max_depth {
+            width = (width + 1) / 2;
+            for index in 0..width {
+                let mut node = tree.get_node(level - 1, 2 * index);
+                hash_to_parent(&mut node, &tree.get_node(level - 1, 2 * index + 1), true);
+                tree.set_node(level, index, node);
+            }
+        }
+        tree.next_seq = 1;
+        Ok(tree)
+    }
+
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    /// Returns the sequence number of the next changelog to apply
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    pub fn get_root(&self) -> Node {
+        self.get_node(self.max_depth, 0)
+    }
+
+    pub fn get_leaf(&self, index: u32) -> Result<Node, ReplayError> {
+        self.check_leaf_index(index)?;
+        Ok(self.get_node(0, index))
+    }
+
+    /// Returns the full proof of the leaf at `index`, from the leaf level up
+    #[allow(clippy::integer_arithmetic)]
+    pub fn get_proof(&self, index: u32) -> Result<Vec<Node>, ReplayError> {
+        self.check_leaf_index(index)?;
+        Ok((0..self.max_depth)
+            .map(|level| self.get_node(level, (index >> level) ^ 1))
+            .collect())
+    }
+
+    /// Returns the proof of the leaf at `index` without the nodes cached by a canopy
+    /// of depth `canopy_depth`, as expected by SPL Account Compression instructions
+    pub fn get_truncated_proof(
+        &self,
+        index: u32,
+        canopy_depth: u32,
+    ) -> Result<Vec<Node>, ReplayError> {
+        self.check_canopy_depth(canopy_depth)?;
+        let mut proof = self.get_proof(index)?;
+        proof.truncate(self.max_depth.saturating_sub(canopy_depth) as usize);
+        Ok(proof)
+    }
+
+    /// Returns the upper `canopy_depth` levels of the tree without the root, from the top
+    /// down and left to right, in the layout of an SPL ConcurrentMerkleTree canopy
+    #[allow(clippy::integer_arithmetic)]
+    pub fn get_canopy(&self, canopy_depth: u32) -> Result<Vec<Node>, ReplayError> {
+        self.check_canopy_depth(canopy_depth)?;
+        let mut canopy = Vec::with_capacity((1 << (canopy_depth + 1)) - 2);
+        for depth in 1..=canopy_depth {
+            for index in 0..(1 << depth) {
+                canopy.push(self.get_node(self.max_depth - depth, index));
+            }
+        }
+        Ok(canopy)
+    }
+
+    /// Applies the changelog with sequence number `next_seq`. The changelog must be
+    /// consistent with the replayed tree, which detects changelogs missed without a gap
+    /// in sequence numbers, such as changelogs of a different tree.
+    #[allow(clippy::integer_arithmetic)]
+    pub fn apply_change_log(&mut self, record: &ChangeLogEntry) -> Result<(), ReplayError> {
+        if record.seq < self.next_seq {
+            return Err(ReplayError::StaleChangeLog {
+                expected: self.next_seq,
+                received: record.seq,
+            });
+        }
+        if record.seq > self.next_seq {
+            return Err(ReplayError::SequenceGap {
+                expected: self.next_seq,
+                received: record.seq,
+            });
+        }
+        self.check_path(record)?;
+
+        for (level, nodes) in record.path.windows(2).enumerate() {
+            let level = level as u32;
+            let mut node = nodes[0].node;
+            let sibling = self.get_node(level, (record.index >> level) ^ 1);
+            hash_to_parent(&mut node, &sibling, (record.index >> level) & 1 == 0);
+            if node != nodes[1].node {
+                return Err(ReplayError::PathMismatch { seq: record.seq });
+            }
+        }
+        for (level, path_node) in record.path.iter().enumerate() {
+            let level = level as u32;
+            self.set_node(level, record.index >> level, path_node.node);
+        }
+        self.next_seq = self.next_seq.saturating_add(1);
+        Ok(())
+    }
+
+    /// Ingests a changelog that may arrive out of order. Changelogs ahead of `next_seq`
+    /// are buffered until the missing ones are ingested, and changelogs that were already
+    /// applied are ignored.
+    ///
+    /// Returns the number of changelogs applied. If applying a buffered changelog fails,
+    /// it is discarded and the error is returned.
+    pub fn ingest(&mut self, record: ChangeLogEntry) -> Result<usize, ReplayError> {
+        if record.seq < self.next_seq {
+            return Ok(0);
+        }
+        self.check_path(&record)?;
+        self.pending.insert(record.seq, record);
+
+        let mut num_applied: usize = 0;
+        while let Some(record) = self.pending.remove(&self.next_seq) {
+            self.apply_change_log(&record)?;
+            num_applied = num_applied.saturating_add(1);
+        }
+        Ok(num_applied)
+    }
+
+    /// Ingests the changelogs of an event emitted by SPL Account Compression, see
+    /// [ingest](Self::ingest). The event must have been emitted for the replayed tree.
+    ///
+    /// Returns the number of changelogs applied. The changes of a `V2` event are ingested
+    /// in order, so the ones before a failing change remain ingested.
+    pub fn ingest_event(&mut self, event: ChangeLogEvent) -> Result<usize, ReplayError> {
+        match event {
+            ChangeLogEvent::V1(event) => self.ingest(ChangeLogEntry {
+                path: event.path,
+                seq: event.seq,
+                index: event.index,
+            }),
+            ChangeLogEvent::V2(event) => {
+                let mut num_applied: usize = 0;
+                for change in event.changes {
+                    num_applied = num_applied.saturating_add(self.ingest(change)?);
+                }
+                Ok(num_applied)
+            }
+        }
+    }
+
+    /// Ingests a serialized `ChangeLogEvent`, see [ingest_event](Self::ingest_event).
+    /// The data logged through SPL Noop is decoded with
+    /// [parse_noop_data](crate::event::parse_noop_data).
+    pub fn ingest_event_bytes(&mut self, data: &[u8]) -> Result<usize, ReplayError> {
+        self.ingest_event(parse_change_log_event(data)?)
+    }
+
+    /// Returns the ranges of sequence numbers that must be backfilled before the
+    /// buffered changelogs can be applied
+    pub fn missing_sequence_numbers(&self) -> Vec<Range<u64>> {
+        let mut missing = vec![];
+        let mut expected = self.next_seq;
+        for seq in self.pending.keys() {
+            if *seq > expected {
+                missing.push(expected..*seq);
+            }
+            expected = seq.saturating_add(1);
+        }
+        missing
+    }
+
+    fn get_node(&self, level: u32, index: u32) -> Node {
+        self.nodes
+            .get(&self.heap_index(level, index))
+            .copied()
+            .unwrap_or(self.empty_nodes[level as usize])
+    }
+
+    fn set_node(&mut self, level: u32, index: u32, node: Node) {
+        let heap_index = self.heap_index(level, index);
+        if node == self.empty_nodes[level as usize] {
+            self.nodes.remove(&heap_index);
+        } else {
+            self.nodes.insert(heap_index, node);
+        }
+    }
+
+    #[allow(clippy::integer_arithmetic)]
+    fn heap_index(&self, level: u32, index: u32) -> u32 {
+        (1 << (self.max_depth - level)) + index
+    }
+
+    #[allow(clippy::integer_arithmetic)]
+    fn check_leaf_index(&self, index: u32) -> Result<(), ReplayError> {
+        if index as u64 >= 1 << self.max_depth {
+            return Err(ReplayError::LeafIndexOutOfBounds(index));
+        }
+        Ok(())
+    }
+
+    fn check_canopy_depth(&self, canopy_depth: u32) -> Result<(), ReplayError> {
+        if canopy_depth > self.max_depth {
+            return Err(ReplayError::InvalidCanopyDepth(canopy_depth));
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::integer_arithmetic)]
+    fn check_path(&self, record: &ChangeLogEntry) -> Result<(), ReplayError> {
+        if (record.index as u64) >= 1 << self.max_depth
+            || record.path.len() != self.max_depth as usize + 1
+            || record.path.iter().enumerate().any(|(level, path_node)| {
+                let level = level as u32;
+                path_node.index != self.heap_index(level, record.index >> level)
+            })
+        {
+            return Err(ReplayError::InvalidPath { seq: record.seq });
+        }
+        Ok(())
+    }
+}
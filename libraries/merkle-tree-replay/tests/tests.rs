@@ -0,0 +1,360 @@
+#![allow(clippy::integer_arithmetic)]
+use anchor_lang::{prelude::Pubkey, AnchorSerialize};
+use rand::{self, rngs::ThreadRng, thread_rng, Rng};
+use spl_account_compression::events::{
+    AccountCompressionEvent, ApplicationDataEvent, ApplicationDataEventV1, ChangeLogEntry,
+    ChangeLogEvent,
+};
+use spl_concurrent_merkle_tree::concurrent_merkle_tree::{ConcurrentMerkleTree, LeafUpdate};
+use spl_concurrent_merkle_tree::hash::{multi_proof_positions, recompute};
+use spl_concurrent_merkle_tree::node::{Node, EMPTY};
+use spl_merkle_tree_replay::error::ReplayError;
+use spl_merkle_tree_replay::event::parse_noop_data;
+use spl_merkle_tree_replay::replay_tree::ReplayTree;
+
+const DEPTH: usize = 10;
+const BUFFER_SIZE: usize = 32;
+const CANOPY_DEPTH: u32 = 4;
+
+fn setup() -> (Box<ConcurrentMerkleTree<DEPTH, BUFFER_SIZE>>, ReplayTree) {
+    let mut cmt = Box::new(ConcurrentMerkleTree::<DEPTH, BUFFER_SIZE>::new());
+    cmt.initialize().unwrap();
+    let mut tree = ReplayTree::new(DEPTH as u32).unwrap();
+    for record in new_change_logs(&cmt, 1) {
+        tree.apply_change_log(&record).unwrap();
+    }
+    (cmt, tree)
+}
+
+/// Returns the event emitted by SPL Account Compression for the `count` most recent changelogs
+fn new_event(cmt: &ConcurrentMerkleTree<DEPTH, BUFFER_SIZE>, count: usize) -> ChangeLogEvent {
+    *Box::<ChangeLogEvent>::from((
+        cmt.get_change_logs(count),
+        Pubkey::default(),
+        cmt.sequence_number,
+    ))
+}
+
+/// Returns the `count` most recent changelogs of the tree as they would be emitted
+fn new_change_logs(
+    cmt: &ConcurrentMerkleTree<DEPTH, BUFFER_SIZE>,
+    count: usize,
+) -> Vec<ChangeLogEntry> {
+    match new_event(cmt, count) {
+        ChangeLogEvent::V2(event) => event.changes,
+        ChangeLogEvent::V1(_) => unreachable!(),
+    }
+}
+
+/// Returns the data logged through SPL Noop for the `count` most recent changelogs
+fn new_noop_data(cmt: &ConcurrentMerkleTree<DEPTH, BUFFER_SIZE>, count: usize) -> Vec<u8> {
+    AccountCompressionEvent::ChangeLog(new_event(cmt, count))
+        .try_to_vec()
+        .unwrap()
+}
+
+/// Applies a random operation to the tree, using proofs from the replayed tree
+fn random_operation(
+    rng: &mut ThreadRng,
+    cmt: &mut ConcurrentMerkleTree<DEPTH, BUFFER_SIZE>,
+    tree: &ReplayTree,
+) {
+    let num_leaves = cmt.rightmost_proof.index;
+    let capacity = 1 << DEPTH;
+    let root = tree.get_root();
+    match rng.gen_range(0..5) {
+        0 if num_leaves < capacity => {
+            cmt.append(rng.gen::<Node>()).unwrap();
+        }
+        1 if num_leaves < capacity => {
+            let batch_size = rng
+                .gen_range(1..=BUFFER_SIZE)
+                .min((capacity - num_leaves) as usize);
+            let leaves: Vec<Node> = (0..batch_size).map(|_| rng.gen::<Node>()).collect();
+            cmt.append_batch(&leaves).unwrap();
+        }
+        2 if num_leaves > 0 => {
+            let index = rng.gen_range(0..num_leaves);
+            // Leaves are sometimes removed, so that they can be filled again
+            let new_leaf = if rng.gen_bool(0.2) {
+                EMPTY
+            } else {
+                rng.gen::<Node>()
+            };
+            cmt.set_leaf(
+                root,
+                tree.get_leaf(index).unwrap(),
+                new_leaf,
+                &tree.get_proof(index).unwrap(),
+                index,
+            )
+            .unwrap();
+        }
+        3 if num_leaves > 0 => {
+            let mut indices: Vec<u32> = (0..rng.gen_range(1..=BUFFER_SIZE / 2))
+                .map(|_| rng.gen_range(0..num_leaves))
+                .collect();
+            indices.sort_unstable();
+            indices.dedup();
+            // The proof of a leaf below the sibling of a node contains it at that level
+            let proof: Vec<Node> = multi_proof_positions(&indices, DEPTH)
+                .iter()
+                .map(|(level, index)| {
+                    tree.get_proof((index ^ 1) << level).unwrap()[*level as usize]
+                })
+                .collect();
+            let updates: Vec<LeafUpdate> = indices
+                .iter()
+                .map(|index| LeafUpdate {
+                    previous_leaf: tree.get_leaf(*index).unwrap(),
+                    new_leaf: rng.gen::<Node>(),
+                    index: *index,
+                })
+                .collect();
+            cmt.set_leaves(root, &updates, &proof).unwrap();
+        }
+        4 => {
+            let index = rng.gen_range(0..=num_leaves.min(capacity - 1));
+            // With an up to date proof, only empty leaves can be filled
+            if tree.get_leaf(index).unwrap() == EMPTY {
+                cmt.fill_empty_or_append(
+                    root,
+                    rng.gen::<Node>(),
+                    &tree.get_proof(index).unwrap(),
+                    index,
+                )
+                .unwrap();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_tree(cmt: &ConcurrentMerkleTree<DEPTH, BUFFER_SIZE>, tree: &ReplayTree) {
+    assert_eq!(tree.get_root(), cmt.get_root());
+    assert_eq!(tree.next_seq(), cmt.sequence_number + 1);
+
+    // Proofs of the rightmost leaf match the tree's own, which is not updated once full
+    if cmt.rightmost_proof.index > 0 && cmt.rightmost_proof.index < 1 << DEPTH {
+        let rightmost_index = cmt.rightmost_proof.index - 1;
+        assert_eq!(
+            tree.get_leaf(rightmost_index).unwrap(),
+            cmt.rightmost_proof.leaf
+        );
+        assert_eq!(
+            tree.get_proof(rightmost_index).unwrap(),
+            cmt.rightmost_proof.proof
+        );
+    }
+
+    // Truncated proofs are completed by the canopy
+    let canopy = tree.get_canopy(CANOPY_DEPTH).unwrap();
+    assert_eq!(canopy.len(), (1 << (CANOPY_DEPTH + 1)) - 2);
+    let index = thread_rng().gen_range(0..1 << DEPTH);
+    let mut proof = tree.get_truncated_proof(index, CANOPY_DEPTH).unwrap();
+    let mut node_index = ((1 << DEPTH) + index) >> (DEPTH as u32 - CANOPY_DEPTH);
+    while node_index > 1 {
+        // node_index - 2 maps to the canopy index
+        proof.push(canopy[(node_index ^ 1) as usize - 2]);
+        node_index >>= 1;
+    }
+    assert_eq!(proof, tree.get_proof(index).unwrap());
+    assert_eq!(
+        recompute(tree.get_leaf(index).unwrap(), &proof, index),
+        cmt.get_root()
+    );
+}
+
+#[test]
+fn test_replay_random_operations() {
+    let mut rng = thread_rng();
+    let (mut cmt, mut tree) = setup();
+    check_tree(&cmt, &tree);
+
+    for _ in 0..500 {
+        let previous_seq = cmt.sequence_number;
+        random_operation(&mut rng, &mut cmt, &tree);
+        let num_changes = (cmt.sequence_number - previous_seq) as usize;
+        let event = parse_noop_data(&new_noop_data(&cmt, num_changes))
+            .unwrap()
+            .unwrap();
+        assert_eq!(tree.ingest_event(event).unwrap(), num_changes);
+        check_tree(&cmt, &tree);
+    }
+}
+
+#[test]
+fn test_ingest_out_of_order() {
+    let mut rng = thread_rng();
+    let (mut cmt, mut tree) = setup();
+
+    let initial_root = cmt.get_root();
+    let leaves: Vec<Node> = (0..BUFFER_SIZE).map(|_| rng.gen::<Node>()).collect();
+    cmt.append_batch(&leaves).unwrap();
+    let mut records = new_change_logs(&cmt, BUFFER_SIZE);
+
+    // Receive every other changelog first
+    let late_records: Vec<ChangeLogEntry> = records.iter().step_by(2).cloned().collect();
+    for record in records.iter().skip(1).step_by(2) {
+        assert_eq!(tree.ingest(record.clone()).unwrap(), 0);
+    }
+    let missing = tree.missing_sequence_numbers();
+    assert_eq!(missing.len(), BUFFER_SIZE / 2);
+    for (range, record) in missing.iter().zip(late_records.iter()) {
+        assert_eq!(*range, record.seq..record.seq + 1);
+    }
+    assert_eq!(tree.get_root(), initial_root);
+
+    // Backfill the gaps, with duplicates
+    let mut num_applied = 0;
+    for record in late_records.iter() {
+        num_applied += tree.ingest(record.clone()).unwrap();
+        assert_eq!(tree.ingest(record.clone()).unwrap(), 0);
+    }
+    assert_eq!(num_applied, BUFFER_SIZE);
+    assert!(tree.missing_sequence_numbers().is_empty());
+    check_tree(&cmt, &tree);
+
+    // Strict application reports gaps and stale changelogs
+    let stale = records.pop().unwrap();
+    assert_eq!(
+        tree.apply_change_log(&stale).unwrap_err(),
+        ReplayError::StaleChangeLog {
+            expected: stale.seq + 1,
+            received: stale.seq,
+        }
+    );
+    cmt.append(rng.gen::<Node>()).unwrap();
+    cmt.append(rng.gen::<Node>()).unwrap();
+    let new_records = new_change_logs(&cmt, 2);
+    assert_eq!(
+        tree.apply_change_log(&new_records[1]).unwrap_err(),
+        ReplayError::SequenceGap {
+            expected: new_records[0].seq,
+            received: new_records[1].seq,
+        }
+    );
+    assert_eq!(tree.missing_sequence_numbers(), vec![]);
+}
+
+#[test]
+fn test_invalid_change_logs() {
+    let mut rng = thread_rng();
+    let (mut cmt, mut tree) = setup();
+
+    cmt.append(rng.gen::<Node>()).unwrap();
+    let record = new_change_logs(&cmt, 1).pop().unwrap();
+
+    let mut wrong_index = record.clone();
+    wrong_index.index += 1;
+    assert_eq!(
+        tree.ingest(wrong_index).unwrap_err(),
+        ReplayError::InvalidPath { seq: record.seq }
+    );
+
+    let mut short_path = record.clone();
+    short_path.path.remove(0);
+    assert_eq!(
+        tree.apply_change_log(&short_path).unwrap_err(),
+        ReplayError::InvalidPath { seq: record.seq }
+    );
+
+    tree.apply_change_log(&record).unwrap();
+    check_tree(&cmt, &tree);
+
+    // A changelog from another tree does not match the replayed nodes
+    let (mut other_cmt, _) = setup();
+    other_cmt.append(rng.gen::<Node>()).unwrap();
+    other_cmt.append(rng.gen::<Node>()).unwrap();
+    let other_record = new_change_logs(&other_cmt, 1).pop().unwrap();
+    assert_eq!(other_record.seq, tree.next_seq());
+    assert_eq!(
+        tree.apply_change_log(&other_record).unwrap_err(),
+        ReplayError::PathMismatch {
+            seq: other_record.seq
+        }
+    );
+    check_tree(&cmt, &tree);
+
+    assert_eq!(
+        tree.get_proof(1 << DEPTH).unwrap_err(),
+        ReplayError::LeafIndexOutOfBounds(1 << DEPTH)
+    );
+    assert_eq!(
+        tree.get_canopy(DEPTH as u32 + 1).unwrap_err(),
+        ReplayError::InvalidCanopyDepth(DEPTH as u32 + 1)
+    );
+    assert_eq!(
+        ReplayTree::new(31).err(),
+        Some(ReplayError::InvalidMaxDepth(31))
+    );
+
+    // Application data does not modify the tree, and other data is rejected
+    let application_data = AccountCompressionEvent::ApplicationData(ApplicationDataEvent::V1(
+        ApplicationDataEventV1 {
+            application_data: vec![1, 2, 3],
+        },
+    ))
+    .try_to_vec()
+    .unwrap();
+    assert!(parse_noop_data(&application_data).unwrap().is_none());
+    assert_eq!(
+        tree.ingest_event_bytes(&application_data).unwrap_err(),
+        ReplayError::InvalidEvent
+    );
+    let mut noop_data = new_noop_data(&cmt, 1);
+    noop_data.push(0);
+    assert_eq!(
+        parse_noop_data(&noop_data).err(),
+        Some(ReplayError::InvalidEvent)
+    );
+}
+
+#[test]
+fn test_from_leaves() {
+    let mut rng = thread_rng();
+    let num_leaves = rng.gen_range(1..1 << DEPTH);
+    let leaves: Vec<Node> = (0..num_leaves).map(|_| rng.gen::<Node>()).collect();
+    let mut tree = ReplayTree::from_leaves(DEPTH as u32, &leaves).unwrap();
+
+    // The tree is initialized on-chain with the root of the leaves built off-chain
+    let mut cmt = Box::new(ConcurrentMerkleTree::<DEPTH, BUFFER_SIZE>::new());
+    let rightmost_index = num_leaves as u32 - 1;
+    cmt.initialize_with_root(
+        tree.get_root(),
+        leaves[num_leaves - 1],
+        &tree.get_proof(rightmost_index).unwrap(),
+        rightmost_index,
+    )
+    .unwrap();
+    for record in new_change_logs(&cmt, 1) {
+        tree.apply_change_log(&record).unwrap();
+    }
+    check_tree(&cmt, &tree);
+
+    for _ in 0..100 {
+        let previous_seq = cmt.sequence_number;
+        random_operation(&mut rng, &mut cmt, &tree);
+        let num_changes = (cmt.sequence_number - previous_seq) as usize;
+        // Single changes are emitted as V1 events
+        let first_seq = cmt.sequence_number + 1 - num_changes as u64;
+        for (i, change_log) in cmt.get_change_logs(num_changes).into_iter().enumerate() {
+            let event = Box::<ChangeLogEvent>::from((
+                Box::new(change_log),
+                Pubkey::default(),
+                first_seq + i as u64,
+            ));
+            assert_eq!(
+                tree.ingest_event_bytes(&event.try_to_vec().unwrap())
+                    .unwrap(),
+                1
+            );
+        }
+        check_tree(&cmt, &tree);
+    }
+
+    assert_eq!(
+        ReplayTree::from_leaves(3, &[rng.gen::<Node>(); 9]).err(),
+        Some(ReplayError::LeafIndexOutOfBounds(8))
+    );
+}
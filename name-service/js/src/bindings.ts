@@ -9,15 +9,17 @@ import {
   createInstruction,
   deleteInstruction,
   reallocInstruction,
+  setReverseRecordInstruction,
   transferInstruction,
   updateInstruction,
 } from './instructions';
-import { NameRegistryState } from './state';
+import { NameRegistryState, ReverseRecordState } from './state';
 import { Numberu64 } from './utils';
 import {
   getHashedName,
   getNameAccountKey,
   getNameOwner,
+  getReverseRecordKey,
   Numberu32,
 } from './utils';
 
@@ -264,3 +266,85 @@ export async function reallocNameAccount(
 
   return reallocInstr;
 }
+
+/**
+ * Point the reverse record of the name owner at the given name account.
+ *
+ * @param connection The solana connection object to the RPC node
+ * @param name The name of the name account
+ * @param payerKey The payer of the reverse record if it does not exist yet
+ * @param nameClass The class of this name, if it exsists
+ * @param nameParent The parent name of this name, if it exists
+ * @returns
+ */
+export async function setReverseRecord(
+  connection: Connection,
+  name: string,
+  payerKey: PublicKey,
+  nameClass?: PublicKey,
+  nameParent?: PublicKey,
+): Promise<TransactionInstruction> {
+  const hashedName = await getHashedName(name);
+  const nameAccountKey = await getNameAccountKey(
+    hashedName,
+    nameClass,
+    nameParent,
+  );
+
+  const nameOwner = (await getNameOwner(connection, nameAccountKey)).owner;
+  const reverseRecordKey = await getReverseRecordKey(nameOwner, nameClass);
+
+  const setReverseRecordInstr = setReverseRecordInstruction(
+    NAME_PROGRAM_ID,
+    SystemProgram.programId,
+    payerKey,
+    nameAccountKey,
+    nameOwner,
+    reverseRecordKey,
+  );
+
+  return setReverseRecordInstr;
+}
+
+/**
+ * Resolve the name account that the reverse record of an owner points to.
+ *
+ * @param connection The solana connection object to the RPC node
+ * @param nameOwner The owner to resolve
+ * @param nameClass The class of the name, if it exists
+ * @returns The name account key, or undefined if the reverse record is missing, cleared or stale
+ */
+export async function resolveReverseRecord(
+  connection: Connection,
+  nameOwner: PublicKey,
+  nameClass?: PublicKey,
+): Promise<PublicKey | undefined> {
+  const defaultKey = new PublicKey(Buffer.alloc(32));
+  const reverseRecordKey = await getReverseRecordKey(nameOwner, nameClass);
+  const reverseRecord = await ReverseRecordState.retrieve(
+    connection,
+    reverseRecordKey,
+  );
+  if (!reverseRecord || reverseRecord.nameAccount.equals(defaultKey)) {
+    return undefined;
+  }
+
+  const nameAccount = await connection.getAccountInfo(
+    reverseRecord.nameAccount,
+    'processed',
+  );
+  if (!nameAccount) {
+    return undefined;
+  }
+  const nameRegistry = await NameRegistryState.retrieve(
+    connection,
+    reverseRecord.nameAccount,
+  );
+  if (
+    !nameRegistry.owner.equals(nameOwner) ||
+    !nameRegistry.class.equals(nameClass ?? defaultKey)
+  ) {
+    return undefined;
+  }
+  return reverseRecord.nameAccount;
+}
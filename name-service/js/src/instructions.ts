@@ -1,30 +1,42 @@
-import { PublicKey, TransactionInstruction } from '@solana/web3.js';
+import {
+  AccountMeta,
+  PublicKey,
+  TransactionInstruction,
+} from '@solana/web3.js';
 
 import { Numberu32, Numberu64 } from './utils';
 
-export function createInstruction(
-  nameProgramId: PublicKey,
+/**
+ * Token accounts paying the fee of a subdomain policy priced in SPL tokens
+ */
+export interface TokenFeeAccounts {
+  source: PublicKey;
+  destination: PublicKey;
+  tokenProgramId: PublicKey;
+}
+
+/**
+ * Who can create subdomains of a parent name without the signature of its
+ * owner. Priced policies take their fee in lamports, or in SPL tokens of
+ * `mint` if set.
+ */
+export type SubdomainPolicy =
+  | { kind: 'closed' }
+  | { kind: 'open' }
+  | { kind: 'allowlist'; allowlist: PublicKey[] }
+  | { kind: 'priced'; mint?: PublicKey; price: Numberu64 };
+
+function createKeys(
   systemProgramId: PublicKey,
   nameKey: PublicKey,
   nameOwnerKey: PublicKey,
   payerKey: PublicKey,
-  hashed_name: Buffer,
-  lamports: Numberu64,
-  space: Numberu32,
   nameClassKey?: PublicKey,
   nameParent?: PublicKey,
   nameParentOwner?: PublicKey,
-): TransactionInstruction {
-  const buffers = [
-    Buffer.from(Int8Array.from([0])),
-    new Numberu32(hashed_name.length).toBuffer(),
-    hashed_name,
-    lamports.toBuffer(),
-    space.toBuffer(),
-  ];
-
-  const data = Buffer.concat(buffers);
-
+  subdomainPolicyKey?: PublicKey,
+  tokenFeeAccounts?: TokenFeeAccounts,
+): AccountMeta[] {
   const keys = [
     {
       pubkey: systemProgramId,
@@ -43,7 +55,7 @@ export function createInstruction(
     },
     {
       pubkey: nameOwnerKey,
-      isSigner: false,
+      isSigner: !!subdomainPolicyKey,
       isWritable: false,
     },
   ];
@@ -74,7 +86,41 @@ export function createInstruction(
       isWritable: false,
     });
   }
-  if (nameParentOwner) {
+  // Without the signature of the parent owner, the subdomain policy of the
+  // parent name decides who can create the name
+  if (nameParentOwner && subdomainPolicyKey) {
+    keys.push(
+      {
+        pubkey: nameParentOwner,
+        isSigner: false,
+        isWritable: true,
+      },
+      {
+        pubkey: subdomainPolicyKey,
+        isSigner: false,
+        isWritable: false,
+      },
+    );
+    if (tokenFeeAccounts) {
+      keys.push(
+        {
+          pubkey: tokenFeeAccounts.source,
+          isSigner: false,
+          isWritable: true,
+        },
+        {
+          pubkey: tokenFeeAccounts.destination,
+          isSigner: false,
+          isWritable: true,
+        },
+        {
+          pubkey: tokenFeeAccounts.tokenProgramId,
+          isSigner: false,
+          isWritable: false,
+        },
+      );
+    }
+  } else if (nameParentOwner) {
     keys.push({
       pubkey: nameParentOwner,
       isSigner: true,
@@ -82,6 +128,104 @@ export function createInstruction(
     });
   }
 
+  return keys;
+}
+
+/**
+ * Create a name record. To create a subdomain under the subdomain policy of
+ * its parent name, without the signature of the parent owner, pass the key of
+ * the policy, and the fee accounts if it is priced in SPL tokens. The name
+ * owner must then sign.
+ */
+export function createInstruction(
+  nameProgramId: PublicKey,
+  systemProgramId: PublicKey,
+  nameKey: PublicKey,
+  nameOwnerKey: PublicKey,
+  payerKey: PublicKey,
+  hashed_name: Buffer,
+  lamports: Numberu64,
+  space: Numberu32,
+  nameClassKey?: PublicKey,
+  nameParent?: PublicKey,
+  nameParentOwner?: PublicKey,
+  subdomainPolicyKey?: PublicKey,
+  tokenFeeAccounts?: TokenFeeAccounts,
+): TransactionInstruction {
+  const buffers = [
+    Buffer.from(Int8Array.from([0])),
+    new Numberu32(hashed_name.length).toBuffer(),
+    hashed_name,
+    lamports.toBuffer(),
+    space.toBuffer(),
+  ];
+
+  const data = Buffer.concat(buffers);
+
+  const keys = createKeys(
+    systemProgramId,
+    nameKey,
+    nameOwnerKey,
+    payerKey,
+    nameClassKey,
+    nameParent,
+    nameParentOwner,
+    subdomainPolicyKey,
+    tokenFeeAccounts,
+  );
+
+  return new TransactionInstruction({
+    keys,
+    programId: nameProgramId,
+    data,
+  });
+}
+
+/**
+ * Create a name record that expires at the given unix timestamp, at most the
+ * maximum renewal period from now. Subdomains created under a priced
+ * subdomain policy must expire. The accounts are the same as for
+ * `createInstruction`.
+ */
+export function createWithExpiryInstruction(
+  nameProgramId: PublicKey,
+  systemProgramId: PublicKey,
+  nameKey: PublicKey,
+  nameOwnerKey: PublicKey,
+  payerKey: PublicKey,
+  hashed_name: Buffer,
+  lamports: Numberu64,
+  space: Numberu32,
+  expiresAt: Numberu64,
+  nameClassKey?: PublicKey,
+  nameParent?: PublicKey,
+  nameParentOwner?: PublicKey,
+  subdomainPolicyKey?: PublicKey,
+  tokenFeeAccounts?: TokenFeeAccounts,
+): TransactionInstruction {
+  const buffers = [
+    Buffer.from(Int8Array.from([6])),
+    new Numberu32(hashed_name.length).toBuffer(),
+    hashed_name,
+    lamports.toBuffer(),
+    space.toBuffer(),
+    expiresAt.toBuffer(),
+  ];
+
+  const data = Buffer.concat(buffers);
+
+  const keys = createKeys(
+    systemProgramId,
+    nameKey,
+    nameOwnerKey,
+    payerKey,
+    nameClassKey,
+    nameParent,
+    nameParentOwner,
+    subdomainPolicyKey,
+    tokenFeeAccounts,
+  );
+
   return new TransactionInstruction({
     keys,
     programId: nameProgramId,
@@ -255,3 +399,242 @@ export function reallocInstruction(
     data,
   });
 }
+
+export function setReverseRecordInstruction(
+  nameProgramId: PublicKey,
+  systemProgramId: PublicKey,
+  payerKey: PublicKey,
+  nameAccountKey: PublicKey,
+  nameOwnerKey: PublicKey,
+  reverseRecordKey: PublicKey,
+): TransactionInstruction {
+  const buffers = [Buffer.from(Int8Array.from([5]))];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: systemProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: payerKey,
+      isSigner: true,
+      isWritable: true,
+    },
+    {
+      pubkey: nameAccountKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: nameOwnerKey,
+      isSigner: true,
+      isWritable: false,
+    },
+    {
+      pubkey: reverseRecordKey,
+      isSigner: false,
+      isWritable: true,
+    },
+  ];
+
+  return new TransactionInstruction({
+    keys,
+    programId: nameProgramId,
+    data,
+  });
+}
+
+/**
+ * Renew a name record. Subdomains need their parent name, its owner and its
+ * subdomain policy. If the policy is priced in SPL tokens, the source and
+ * destination token accounts of the fee and the token program must be
+ * appended to the keys.
+ */
+export function renewInstruction(
+  nameProgramId: PublicKey,
+  systemProgramId: PublicKey,
+  nameAccountKey: PublicKey,
+  payerKey: PublicKey,
+  expiresAt: Numberu64,
+  nameParent?: PublicKey,
+  nameParentOwner?: PublicKey,
+  subdomainPolicyKey?: PublicKey,
+): TransactionInstruction {
+  const buffers = [Buffer.from(Int8Array.from([7])), expiresAt.toBuffer()];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: nameAccountKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: payerKey,
+      isSigner: true,
+      isWritable: true,
+    },
+    {
+      pubkey: systemProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+  ];
+
+  if (nameParent && nameParentOwner && subdomainPolicyKey) {
+    keys.push(
+      {
+        pubkey: nameParent,
+        isSigner: false,
+        isWritable: false,
+      },
+      {
+        pubkey: nameParentOwner,
+        isSigner: false,
+        isWritable: true,
+      },
+      {
+        pubkey: subdomainPolicyKey,
+        isSigner: false,
+        isWritable: false,
+      },
+    );
+  }
+
+  return new TransactionInstruction({
+    keys,
+    programId: nameProgramId,
+    data,
+  });
+}
+
+export function reclaimInstruction(
+  nameProgramId: PublicKey,
+  nameAccountKey: PublicKey,
+  refundTargetKey: PublicKey,
+  nameParent?: PublicKey,
+  reverseRecordKey?: PublicKey,
+): TransactionInstruction {
+  const buffers = [Buffer.from(Int8Array.from([8]))];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: nameAccountKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: refundTargetKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: nameParent ?? new PublicKey(Buffer.alloc(32)),
+      isSigner: false,
+      isWritable: false,
+    },
+  ];
+
+  if (reverseRecordKey) {
+    keys.push({
+      pubkey: reverseRecordKey,
+      isSigner: false,
+      isWritable: true,
+    });
+  }
+
+  return new TransactionInstruction({
+    keys,
+    programId: nameProgramId,
+    data,
+  });
+}
+
+function serializeSubdomainPolicy(policy: SubdomainPolicy): Buffer {
+  switch (policy.kind) {
+    case 'closed':
+      return Buffer.from(Int8Array.from([0]));
+    case 'open':
+      return Buffer.from(Int8Array.from([1]));
+    case 'allowlist':
+      return Buffer.concat([
+        Buffer.from(Int8Array.from([2])),
+        new Numberu32(policy.allowlist.length).toBuffer(),
+        ...policy.allowlist.map((key) => key.toBuffer()),
+      ]);
+    case 'priced':
+      return Buffer.concat([
+        Buffer.from(Int8Array.from([3])),
+        policy.mint
+          ? Buffer.concat([Buffer.from([1]), policy.mint.toBuffer()])
+          : Buffer.from([0]),
+        policy.price.toBuffer(),
+      ]);
+  }
+}
+
+/**
+ * Set the subdomain policy of a parent name, see `getSubdomainPolicyKey` for
+ * the address of the policy. The fee mint of a policy priced in SPL tokens is
+ * appended to the keys.
+ */
+export function setSubdomainPolicyInstruction(
+  nameProgramId: PublicKey,
+  systemProgramId: PublicKey,
+  payerKey: PublicKey,
+  nameParent: PublicKey,
+  nameParentOwner: PublicKey,
+  subdomainPolicyKey: PublicKey,
+  policy: SubdomainPolicy,
+): TransactionInstruction {
+  const buffers = [
+    Buffer.from(Int8Array.from([9])),
+    serializeSubdomainPolicy(policy),
+  ];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: systemProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: payerKey,
+      isSigner: true,
+      isWritable: true,
+    },
+    {
+      pubkey: nameParent,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: nameParentOwner,
+      isSigner: true,
+      isWritable: false,
+    },
+    {
+      pubkey: subdomainPolicyKey,
+      isSigner: false,
+      isWritable: true,
+    },
+  ];
+
+  if (policy.kind === 'priced' && policy.mint) {
+    keys.push({
+      pubkey: policy.mint,
+      isSigner: false,
+      isWritable: false,
+    });
+  }
+
+  return new TransactionInstruction({
+    keys,
+    programId: nameProgramId,
+    data,
+  });
+}
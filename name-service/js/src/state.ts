@@ -3,9 +3,14 @@ import { deserializeUnchecked, Schema } from 'borsh';
 
 export class NameRegistryState {
   static HEADER_LEN = 96;
+  static EXPIRING_HEADER_LEN = 112;
+  static EXPIRING_HEADER_TAG = Buffer.from([
+    194, 139, 71, 52, 234, 220, 42, 112,
+  ]);
   parentName: PublicKey;
   owner: PublicKey;
   class: PublicKey;
+  expiresAt: number | undefined;
   data: Buffer | undefined;
 
   static schema: Schema = new Map([
@@ -49,8 +54,63 @@ export class NameRegistryState {
       nameAccount.data,
     );
 
-    res.data = nameAccount.data?.slice(this.HEADER_LEN);
+    // Expiring names have the tag and the expiry after the header
+    const tag = nameAccount.data.slice(
+      this.HEADER_LEN,
+      this.HEADER_LEN + this.EXPIRING_HEADER_TAG.length,
+    );
+    const isExpiring =
+      nameAccount.data.length >= this.EXPIRING_HEADER_LEN &&
+      tag.equals(this.EXPIRING_HEADER_TAG);
+
+    if (isExpiring) {
+      res.expiresAt = Number(
+        nameAccount.data.readBigInt64LE(
+          this.HEADER_LEN + this.EXPIRING_HEADER_TAG.length,
+        ),
+      );
+      res.data = nameAccount.data.slice(this.EXPIRING_HEADER_LEN);
+    } else {
+      res.data = nameAccount.data?.slice(this.HEADER_LEN);
+    }
 
     return res;
   }
 }
+
+export class ReverseRecordState {
+  static LEN = 32;
+  nameAccount: PublicKey;
+
+  static schema: Schema = new Map([
+    [
+      ReverseRecordState,
+      {
+        kind: 'struct',
+        fields: [['nameAccount', [32]]],
+      },
+    ],
+  ]);
+  constructor(obj: { nameAccount: Uint8Array }) {
+    this.nameAccount = new PublicKey(obj.nameAccount);
+  }
+
+  public static async retrieve(
+    connection: Connection,
+    reverseRecordKey: PublicKey,
+  ): Promise<ReverseRecordState | undefined> {
+    const reverseRecordAccount = await connection.getAccountInfo(
+      reverseRecordKey,
+      'processed',
+    );
+    if (!reverseRecordAccount) {
+      return undefined;
+    }
+
+    return deserializeUnchecked(
+      this.schema,
+      ReverseRecordState,
+      reverseRecordAccount.data,
+    );
+  }
+}
@@ -121,6 +121,33 @@ export async function getNameAccountKey(
   return nameAccountKey;
 }
 
+export async function getReverseRecordKey(
+  nameOwner: PublicKey,
+  nameClass?: PublicKey,
+): Promise<PublicKey> {
+  const seeds = [Buffer.from('reverse'), nameOwner.toBuffer()];
+  if (nameClass) {
+    seeds.push(nameClass.toBuffer());
+  } else {
+    seeds.push(Buffer.alloc(32));
+  }
+  const [reverseRecordKey] = await PublicKey.findProgramAddress(
+    seeds,
+    NAME_PROGRAM_ID,
+  );
+  return reverseRecordKey;
+}
+
+export async function getSubdomainPolicyKey(
+  nameParent: PublicKey,
+): Promise<PublicKey> {
+  const [subdomainPolicyKey] = await PublicKey.findProgramAddress(
+    [Buffer.from('subdomain_policy'), nameParent.toBuffer()],
+    NAME_PROGRAM_ID,
+  );
+  return subdomainPolicyKey;
+}
+
 export async function getNameOwner(
   connection: Connection,
   nameAccountKey: PublicKey,
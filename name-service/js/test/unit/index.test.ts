@@ -9,8 +9,10 @@ import chaiAsPromised from 'chai-as-promised';
 
 import {
   createInstruction,
+  createWithExpiryInstruction,
   deleteInstruction,
   reallocInstruction,
+  setSubdomainPolicyInstruction,
   transferInstruction,
   updateInstruction,
 } from '../../src';
@@ -76,6 +78,102 @@ describe('SplNameService Instructions', () => {
     instruction.keys[6].pubkey.equals(nameParentOwner);
   });
 
+  it('createInstruction under a subdomain policy priced in tokens', () => {
+    const subdomainPolicyKey = Keypair.generate().publicKey;
+    const tokenFeeAccounts = {
+      source: Keypair.generate().publicKey,
+      destination: Keypair.generate().publicKey,
+      tokenProgramId: Keypair.generate().publicKey,
+    };
+    const instruction = createInstruction(
+      nameServiceAddress,
+      SystemProgram.programId,
+      nameAccountKey,
+      nameOwnerKey,
+      payerKey,
+      name,
+      new Numberu64(LAMPORTS_PER_SOL),
+      new Numberu64(10),
+      undefined,
+      nameParent,
+      nameParentOwner,
+      subdomainPolicyKey,
+      tokenFeeAccounts,
+    );
+
+    expect(instruction.keys).to.have.length(11);
+    expect(instruction.keys.slice(6).map((key) => key.pubkey)).to.eql([
+      nameParentOwner,
+      subdomainPolicyKey,
+      tokenFeeAccounts.source,
+      tokenFeeAccounts.destination,
+      tokenFeeAccounts.tokenProgramId,
+    ]);
+    expect(instruction.keys[3].isSigner).to.equal(true);
+    expect(instruction.keys[6].isSigner).to.equal(false);
+    expect(instruction.keys[6].isWritable).to.equal(true);
+  });
+
+  it('createWithExpiryInstruction', () => {
+    const expiresAt = new Numberu64(1_700_000_000);
+    const instruction = createWithExpiryInstruction(
+      nameServiceAddress,
+      SystemProgram.programId,
+      nameAccountKey,
+      nameOwnerKey,
+      payerKey,
+      name,
+      new Numberu64(LAMPORTS_PER_SOL),
+      new Numberu32(10),
+      expiresAt,
+    );
+
+    expect(instruction.keys).to.have.length(6);
+    expect(instruction.data[0]).to.eql(6);
+    expect(instruction.data.subarray(-8)).to.eql(expiresAt.toBuffer());
+  });
+
+  it('setSubdomainPolicyInstruction', () => {
+    const subdomainPolicyKey = Keypair.generate().publicKey;
+    const mint = Keypair.generate().publicKey;
+    const instruction = setSubdomainPolicyInstruction(
+      nameServiceAddress,
+      SystemProgram.programId,
+      payerKey,
+      nameParent,
+      nameParentOwner,
+      subdomainPolicyKey,
+      { kind: 'priced', mint, price: new Numberu64(42) },
+    );
+
+    expect(instruction.keys).to.have.length(6);
+    expect(instruction.keys.slice(3).map((key) => key.pubkey)).to.eql([
+      nameParentOwner,
+      subdomainPolicyKey,
+      mint,
+    ]);
+    expect(instruction.keys[3].isSigner).to.equal(true);
+    expect(instruction.data).to.eql(
+      Buffer.concat([
+        Buffer.from([9, 3, 1]),
+        mint.toBuffer(),
+        new Numberu64(42).toBuffer(),
+      ]),
+    );
+
+    const openInstruction = setSubdomainPolicyInstruction(
+      nameServiceAddress,
+      SystemProgram.programId,
+      payerKey,
+      nameParent,
+      nameParentOwner,
+      subdomainPolicyKey,
+      { kind: 'open' },
+    );
+    expect(openInstruction.keys).to.have.length(5);
+    expect(openInstruction.data).to.eql(Buffer.from([9, 1]));
+  });
+
   it('updateInstruction', () => {
     const data = Buffer.from('@Dudl');
     const instruction = updateInstruction(
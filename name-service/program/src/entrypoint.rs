@@ -31,6 +31,12 @@ impl PrintProgramError for NameServiceError {
     {
         match self {
             NameServiceError::OutOfSpace => msg!("Error: Registry is out of space!"),
+            NameServiceError::NameExpired => msg!("Error: The name has expired!"),
+            NameServiceError::NameNotExpiring => msg!("Error: The name does not expire!"),
+            NameServiceError::NameNotReclaimable => {
+                msg!("Error: The name is still in its grace period!")
+            }
+            NameServiceError::InvalidExpiry => msg!("Error: Invalid expiry timestamp!"),
         }
     }
 }
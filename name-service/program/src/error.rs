@@ -8,6 +8,14 @@ use {
 pub enum NameServiceError {
     #[error("Out of space")]
     OutOfSpace,
+    #[error("The name has expired")]
+    NameExpired,
+    #[error("The name does not expire")]
+    NameNotExpiring,
+    #[error("The name is still in its grace period")]
+    NameNotReclaimable,
+    #[error("Invalid expiry timestamp")]
+    InvalidExpiry,
 }
 
 pub type NameServiceResult = Result<(), NameServiceError>;
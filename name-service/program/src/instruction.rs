@@ -1,4 +1,5 @@
 use {
+    crate::state::{get_reverse_record_key, get_subdomain_policy_key, SubdomainPolicy},
     borsh::{BorshDeserialize, BorshSerialize},
     solana_program::{
         instruction::{AccountMeta, Instruction},
@@ -19,7 +20,8 @@ pub enum NameRegistryInstruction {
     ///     * Account class (account #3)
     ///     * Parent name record address (account #4)
     ///
-    /// If this is a child record, the parent record's owner must approve by signing (account #5)
+    /// If this is a child record, the parent record's owner must approve by signing (account #5),
+    /// unless the subdomain policy of the parent name allows otherwise (see `SetSubdomainPolicy`)
     ///
     /// Accounts expected by this instruction:
     ///   0. `[]` System program
@@ -29,7 +31,19 @@ pub enum NameRegistryInstruction {
     ///   4. `[signer]` Account class (written into `NameRecordHeader::class`).
     ///                 If `Pubkey::default()` then the `signer` bit is not required
     ///   5. `[]` Parent name record (written into `NameRecordHeader::parent_name). `Pubkey::default()` is equivalent to no existing parent.
-    ///   6. `[signer]` Owner of the parent name record. Optional but needed if parent name different than default.
+    ///   6. `[signer]` Owner of the parent name record. Needed if parent name different than default,
+    ///                 and left out otherwise.
+    ///
+    /// If the owner of the parent name record does not sign, it must be writeable and followed by:
+    ///   7. `[]` Subdomain policy of the parent name record
+    ///   * If the policy is priced in SPL tokens:
+    ///   8. `[writeable]` Token account paying the fee, owned by the funding account
+    ///   9. `[writeable]` Token account of the parent owner receiving the fee
+    ///   10. `[]` SPL Token program
+    ///
+    /// Optionally, the reverse record of the owner for this class (see `state::get_reverse_record_key`)
+    /// can be passed as a writeable account after the accounts above, in which case it is created or
+    /// updated to point at the new name record. The account owner must then sign.
     ///
     Create {
         /// SHA256 of the (HASH_PREFIX + Name) of the record to create, hashing is done off-chain
@@ -44,6 +58,8 @@ pub enum NameRegistryInstruction {
 
     /// Update the data in a name record
     ///
+    /// Expired names cannot be updated.
+    ///
     /// Accounts expected by this instruction:
     ///   * If account class is `Pubkey::default()`:
     ///   0. `[writeable]` Name record to be updated
@@ -61,6 +77,8 @@ pub enum NameRegistryInstruction {
 
     /// Transfer ownership of a name record
     ///
+    /// Expired names cannot be transferred.
+    ///
     /// Accounts expected by this instruction:
     ///
     ///   * If account class is `Pubkey::default()`:
@@ -77,6 +95,14 @@ pub enum NameRegistryInstruction {
     ///   1. `[signer]` Account owner
     ///   2. `[signer]` Account class
     ///   3. `[]` Parent name record
+    ///
+    /// Optionally, the reverse record of the current owner for the class of the name record (see
+    /// `state::get_reverse_record_key`) can follow, in which case accounts #2 and #3 must be
+    /// passed, as `Pubkey::default()` if there is no class or parent name record:
+    ///   4. `[writeable]` Reverse record of the current owner, cleared if it points at the name record
+    ///
+    /// The reverse record of the new owner is not modified, the new owner can point it at the
+    /// name record with `SetReverseRecord`.
     Transfer { new_owner: Pubkey },
 
     /// Delete a name record.
@@ -88,6 +114,9 @@ pub enum NameRegistryInstruction {
     ///   1. `[signer]` Account owner
     ///   2. `[writeable]` Refund account
     ///
+    /// Optionally, the reverse record of the owner can be passed as account #3, writeable, in which
+    /// case it is closed into the refund account if it points at the deleted name record.
+    ///
     Delete,
 
     /// Realloc the data of a name record.
@@ -106,6 +135,110 @@ pub enum NameRegistryInstruction {
         /// There are no checks on the existing data; it will be truncated if the new space is less than the current space.
         space: u32,
     },
+
+    /// Point the reverse record of a name owner at one of its name records, creating the
+    /// reverse record if needed.
+    ///
+    /// The address of the reverse record (account #4) is a program-derived address with the
+    /// following seeds, see `state::get_reverse_record_key`:
+    ///     * `state::REVERSE_RECORD_PREFIX`
+    ///     * Account owner (account #3)
+    ///     * Class of the name record (account #2)
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[]` System program
+    ///   1. `[writeable, signer]` Funding account, pays for the reverse record if it does not exist yet
+    ///   2. `[]` Name record
+    ///   3. `[signer]` Account owner
+    ///   4. `[writeable]` Reverse record
+    ///
+    SetReverseRecord,
+
+    /// Create an empty name record that expires, see `Create` for the accounts
+    ///
+    /// The header of the name record is followed by `state::EXPIRING_HEADER_TAG` and its expiry,
+    /// so the record data starts at `state::EXPIRING_HEADER_LEN`. Once expired, the name can no longer be updated or
+    /// transferred, and anyone can `Reclaim` it after `state::EXPIRY_GRACE_PERIOD`.
+    ///
+    /// The expiry can be at most `state::MAX_RENEWAL_PERIOD` from now, as for `Renew`. Subdomains
+    /// created through a priced subdomain policy, without the signature of the parent owner, must
+    /// be created with this instruction rather than `Create`.
+    ///
+    CreateWithExpiry {
+        /// SHA256 of the (HASH_PREFIX + Name) of the record to create, hashing is done off-chain
+        hashed_name: Vec<u8>,
+
+        /// Number of lamports to fund the name record with
+        lamports: u64,
+
+        /// Number of bytes of memory to allocate in addition to the expiring header
+        space: u32,
+
+        /// Unix timestamp at which the name expires, in the future and within
+        /// `state::MAX_RENEWAL_PERIOD` from now
+        expires_at: i64,
+    },
+
+    /// Push back the expiry of a name record, by at most `state::MAX_RENEWAL_PERIOD` from now
+    ///
+    /// Anyone can renew a name, without the signature of its owner. Subdomains whose parent name
+    /// has a priced subdomain policy are renewed at the price of the policy, which the funding
+    /// account pays to the parent owner as when creating a subdomain. Other names are renewed
+    /// for free.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[writeable]` Name record to be renewed
+    ///   1. `[writeable, signer]` Funding account
+    ///   2. `[]` System program
+    ///
+    ///   * If the name record has a parent name record:
+    ///   3. `[]` Parent name record
+    ///   4. `[writeable]` Owner of the parent name record
+    ///   5. `[]` Subdomain policy of the parent name record
+    ///   * If the policy is priced in SPL tokens:
+    ///   6. `[writeable]` Token account paying the fee, owned by the funding account
+    ///   7. `[writeable]` Token account of the parent owner receiving the fee
+    ///   8. `[]` SPL Token program
+    ///
+    Renew {
+        /// New unix timestamp at which the name expires, later than the current one
+        expires_at: i64,
+    },
+
+    /// Permissionlessly reclaim a name record whose grace period after expiry is over
+    ///
+    /// The name is transferred to its class if there is one, otherwise to the owner of its
+    /// parent name if there is one. Names with neither are deleted, refunding the owner.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[writeable]` Name record to be reclaimed
+    ///   1. `[writeable]` Refund account, must be the owner of the name record if it is deleted
+    ///                    or its reverse record is closed
+    ///   2. `[]` Parent name record, needed if the name is transferred to the parent owner.
+    ///          `Pubkey::default()` is equivalent to no parent name record
+    ///   3. `[writeable]` Reverse record of the owner for the class of the name record, optional.
+    ///                    It is closed into the refund account if it points at the name record
+    ///
+    Reclaim,
+
+    /// Set the subdomain policy of a parent name, which decides who can create subdomains
+    /// without the signature of the parent owner, creating or resizing the policy account
+    ///
+    /// The address of the subdomain policy (account #4) is a program-derived address with the
+    /// following seeds, see `state::get_subdomain_policy_key`:
+    ///     * `state::SUBDOMAIN_POLICY_PREFIX`
+    ///     * Parent name record address (account #2)
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[]` System program
+    ///   1. `[writeable, signer]` Funding account, pays for or is refunded by resizing the policy
+    ///   2. `[]` Parent name record
+    ///   3. `[signer]` Owner of the parent name record
+    ///   4. `[writeable]` Subdomain policy
+    ///   5. `[]` Fee mint, only if the policy is `SubdomainPolicy::Priced` with a mint. It must be
+    ///          an SPL Token mint, Token-2022 mints are not supported
+    ///
+    SetSubdomainPolicy { policy: SubdomainPolicy },
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -147,6 +280,49 @@ pub fn create(
     })
 }
 
+/// Create a subdomain of `name_parent` under its subdomain policy, without the signature of the
+/// parent owner. For policies priced in SPL tokens, `token_fee_accounts_opt` holds the source and
+/// destination token accounts of the fee. The account owner signs, as allowlists require.
+#[allow(clippy::too_many_arguments)]
+pub fn create_with_subdomain_policy(
+    name_service_program_id: Pubkey,
+    instruction_data: NameRegistryInstruction,
+    name_account_key: Pubkey,
+    payer_key: Pubkey,
+    name_owner: Pubkey,
+    name_class_opt: Option<Pubkey>,
+    name_parent: Pubkey,
+    name_parent_owner: Pubkey,
+    token_fee_accounts_opt: Option<(Pubkey, Pubkey)>,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = create(
+        name_service_program_id,
+        instruction_data,
+        name_account_key,
+        payer_key,
+        name_owner,
+        name_class_opt,
+        Some(name_parent),
+        None,
+    )?;
+    let (subdomain_policy_key, _) =
+        get_subdomain_policy_key(&name_service_program_id, &name_parent);
+    instruction.accounts[3].is_signer = true;
+    instruction.accounts.extend([
+        AccountMeta::new(name_parent_owner, false),
+        AccountMeta::new_readonly(subdomain_policy_key, false),
+    ]);
+    if let Some((source_key, destination_key)) = token_fee_accounts_opt {
+        instruction.accounts.extend([
+            AccountMeta::new(source_key, false),
+            AccountMeta::new(destination_key, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]);
+    }
+
+    Ok(instruction)
+}
+
 pub fn update(
     name_service_program_id: Pubkey,
     offset: u32,
@@ -198,6 +374,41 @@ pub fn transfer(
     })
 }
 
+/// Transfer a name record, clearing the reverse record of the current owner `name_owner_key` if it
+/// points at the name record
+pub fn transfer_with_reverse_record(
+    name_service_program_id: Pubkey,
+    new_owner: Pubkey,
+    name_account_key: Pubkey,
+    name_owner_key: Pubkey,
+    name_class_opt: Option<Pubkey>,
+    name_parent_opt: Option<Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = transfer(
+        name_service_program_id,
+        new_owner,
+        name_account_key,
+        name_owner_key,
+        name_class_opt,
+    )?;
+    if name_class_opt.is_none() {
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(Pubkey::default(), false));
+    }
+    let (reverse_record_key, _) = get_reverse_record_key(
+        &name_service_program_id,
+        &name_owner_key,
+        name_class_opt.as_ref(),
+    );
+    instruction.accounts.extend([
+        AccountMeta::new_readonly(name_parent_opt.unwrap_or_default(), false),
+        AccountMeta::new(reverse_record_key, false),
+    ]);
+
+    Ok(instruction)
+}
+
 pub fn delete(
     name_service_program_id: Pubkey,
     name_account_key: Pubkey,
@@ -241,3 +452,167 @@ pub fn realloc(
         data,
     })
 }
+
+pub fn set_reverse_record(
+    name_service_program_id: Pubkey,
+    payer_key: Pubkey,
+    name_account_key: Pubkey,
+    name_owner_key: Pubkey,
+    name_class_opt: Option<Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let instruction_data = NameRegistryInstruction::SetReverseRecord;
+    let data = instruction_data.try_to_vec().unwrap();
+    let (reverse_record_key, _) = get_reverse_record_key(
+        &name_service_program_id,
+        &name_owner_key,
+        name_class_opt.as_ref(),
+    );
+    let accounts = vec![
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(payer_key, true),
+        AccountMeta::new_readonly(name_account_key, false),
+        AccountMeta::new_readonly(name_owner_key, true),
+        AccountMeta::new(reverse_record_key, false),
+    ];
+
+    Ok(Instruction {
+        program_id: name_service_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Add the reverse record of `name_owner_key` to a `create` or `delete` instruction, so that the
+/// program keeps it up to date. The owner is marked as a signer, which `create`
+/// requires to set the reverse record.
+pub fn with_reverse_record(
+    mut instruction: Instruction,
+    name_owner_key: Pubkey,
+    name_class_opt: Option<Pubkey>,
+) -> Instruction {
+    let (reverse_record_key, _) = get_reverse_record_key(
+        &instruction.program_id,
+        &name_owner_key,
+        name_class_opt.as_ref(),
+    );
+    for account in instruction.accounts.iter_mut() {
+        if account.pubkey == name_owner_key {
+            account.is_signer = true;
+        }
+    }
+    instruction
+        .accounts
+        .push(AccountMeta::new(reverse_record_key, false));
+    instruction
+}
+
+/// Renew a name record. Subdomains need `name_parent_opt` and `name_parent_owner_opt`, as well as
+/// the source and destination token accounts of the fee in `token_fee_accounts_opt` if their parent
+/// has a subdomain policy priced in SPL tokens.
+#[allow(clippy::too_many_arguments)]
+pub fn renew(
+    name_service_program_id: Pubkey,
+    expires_at: i64,
+    name_account_key: Pubkey,
+    payer_key: Pubkey,
+    name_parent_opt: Option<Pubkey>,
+    name_parent_owner_opt: Option<Pubkey>,
+    token_fee_accounts_opt: Option<(Pubkey, Pubkey)>,
+) -> Result<Instruction, ProgramError> {
+    let instruction_data = NameRegistryInstruction::Renew { expires_at };
+    let data = instruction_data.try_to_vec().unwrap();
+    let mut accounts = vec![
+        AccountMeta::new(name_account_key, false),
+        AccountMeta::new(payer_key, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    if let (Some(name_parent), Some(name_parent_owner)) = (name_parent_opt, name_parent_owner_opt) {
+        let (subdomain_policy_key, _) =
+            get_subdomain_policy_key(&name_service_program_id, &name_parent);
+        accounts.extend([
+            AccountMeta::new_readonly(name_parent, false),
+            AccountMeta::new(name_parent_owner, false),
+            AccountMeta::new_readonly(subdomain_policy_key, false),
+        ]);
+        if let Some((source_key, destination_key)) = token_fee_accounts_opt {
+            accounts.extend([
+                AccountMeta::new(source_key, false),
+                AccountMeta::new(destination_key, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ]);
+        }
+    }
+
+    Ok(Instruction {
+        program_id: name_service_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Reclaim a name record. `name_owner_and_class_opt` holds the owner and class of the name record
+/// to close its reverse record, in which case the refund account must be the owner.
+pub fn reclaim(
+    name_service_program_id: Pubkey,
+    name_account_key: Pubkey,
+    refund_target: Pubkey,
+    name_parent_opt: Option<Pubkey>,
+    name_owner_and_class_opt: Option<(Pubkey, Option<Pubkey>)>,
+) -> Result<Instruction, ProgramError> {
+    let instruction_data = NameRegistryInstruction::Reclaim;
+    let data = instruction_data.try_to_vec().unwrap();
+    let mut accounts = vec![
+        AccountMeta::new(name_account_key, false),
+        AccountMeta::new(refund_target, false),
+        AccountMeta::new_readonly(name_parent_opt.unwrap_or_default(), false),
+    ];
+
+    if let Some((name_owner_key, name_class_opt)) = name_owner_and_class_opt {
+        let (reverse_record_key, _) = get_reverse_record_key(
+            &name_service_program_id,
+            &name_owner_key,
+            name_class_opt.as_ref(),
+        );
+        accounts.push(AccountMeta::new(reverse_record_key, false));
+    }
+
+    Ok(Instruction {
+        program_id: name_service_program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn set_subdomain_policy(
+    name_service_program_id: Pubkey,
+    payer_key: Pubkey,
+    name_parent: Pubkey,
+    name_parent_owner: Pubkey,
+    policy: SubdomainPolicy,
+) -> Result<Instruction, ProgramError> {
+    let mint_opt = match policy {
+        SubdomainPolicy::Priced { mint, .. } => mint,
+        _ => None,
+    };
+    let instruction_data = NameRegistryInstruction::SetSubdomainPolicy { policy };
+    let data = instruction_data.try_to_vec().unwrap();
+    let (subdomain_policy_key, _) =
+        get_subdomain_policy_key(&name_service_program_id, &name_parent);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(payer_key, true),
+        AccountMeta::new_readonly(name_parent, false),
+        AccountMeta::new_readonly(name_parent_owner, true),
+        AccountMeta::new(subdomain_policy_key, false),
+    ];
+    if let Some(mint) = mint_opt {
+        accounts.push(AccountMeta::new_readonly(mint, false));
+    }
+
+    Ok(Instruction {
+        program_id: name_service_program_id,
+        accounts,
+        data,
+    })
+}
@@ -1,12 +1,18 @@
 use {
     crate::{
+        error::NameServiceError,
         instruction::NameRegistryInstruction,
         state::get_seeds_and_key,
-        state::{write_data, NameRecordHeader},
+        state::{
+            get_reverse_record_key, get_subdomain_policy_key, write_data, NameRecordExpiry,
+            NameRecordHeader, ReverseRecord, SubdomainPolicy, EXPIRING_HEADER_LEN,
+            MAX_RENEWAL_PERIOD, REVERSE_RECORD_PREFIX, SUBDOMAIN_POLICY_PREFIX,
+        },
     },
-    borsh::BorshDeserialize,
+    borsh::{BorshDeserialize, BorshSerialize},
     solana_program::{
         account_info::{next_account_info, AccountInfo},
+        clock::{Clock, UnixTimestamp},
         entrypoint::ProgramResult,
         msg,
         program::{invoke, invoke_signed},
@@ -20,15 +26,193 @@ use {
     std::cmp::Ordering,
 };
 
+/// Check that the optional reverse record account is the one of `owner` for `class`, returning it
+/// with its bump seed. The address is only derived if the account was provided.
+fn check_reverse_record<'a, 'b>(
+    program_id: &Pubkey,
+    reverse_record: Option<&'a AccountInfo<'b>>,
+    owner: &Pubkey,
+    class: &Pubkey,
+) -> Result<Option<(&'a AccountInfo<'b>, u8)>, ProgramError> {
+    reverse_record
+        .map(|reverse_record| {
+            let (reverse_record_key, bump) = get_reverse_record_key(program_id, owner, Some(class));
+            if reverse_record_key != *reverse_record.key {
+                msg!("The given reverse record is incorrect.");
+                return Err(ProgramError::InvalidArgument);
+            }
+            Ok((reverse_record, bump))
+        })
+        .transpose()
+}
+
+/// Read an existing reverse record, returning `None` if it has not been created
+fn unpack_reverse_record(
+    program_id: &Pubkey,
+    reverse_record: &AccountInfo,
+) -> Result<Option<ReverseRecord>, ProgramError> {
+    if reverse_record.data_len() == 0 {
+        return Ok(None);
+    }
+    if reverse_record.owner != program_id {
+        msg!("The given reverse record is not owned by the program.");
+        return Err(ProgramError::InvalidArgument);
+    }
+    ReverseRecord::unpack_from_slice(&reverse_record.data.borrow()).map(Some)
+}
+
+/// Close a reverse record into the refund account if it points at the given name record
+fn close_reverse_record(
+    program_id: &Pubkey,
+    reverse_record: &AccountInfo,
+    name_account_key: &Pubkey,
+    refund_target: &AccountInfo,
+) -> ProgramResult {
+    if let Some(reverse_state) = unpack_reverse_record(program_id, reverse_record)? {
+        if reverse_state.name_account == *name_account_key {
+            write_data(reverse_record, &[0; ReverseRecord::LEN], 0);
+            let source_amount: &mut u64 = &mut reverse_record.lamports.borrow_mut();
+            let dest_amount: &mut u64 = &mut refund_target.lamports.borrow_mut();
+            *dest_amount = dest_amount.saturating_add(*source_amount);
+            *source_amount = 0;
+        }
+    }
+    Ok(())
+}
+
+/// Fail if the name record expires and has expired
+fn check_not_expired(name_account: &AccountInfo) -> ProgramResult {
+    if let Some(expiry) = NameRecordHeader::unpack_expiry(&name_account.data.borrow()) {
+        if expiry.is_expired(Clock::get()?.unix_timestamp) {
+            msg!("The name has expired.");
+            return Err(NameServiceError::NameExpired.into());
+        }
+    }
+    Ok(())
+}
+
+/// Create a program-derived account owned by the program, funded for rent exemption by the payer.
+/// The creation is done in three steps: transfer, allocate and assign, because the account may
+/// already hold lamports.
+fn create_program_account<'a>(
+    program_id: &Pubkey,
+    system_program: &AccountInfo<'a>,
+    payer_account: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    space: usize,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let required_lamports = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(account.lamports());
+    if required_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer_account.key, account.key, required_lamports),
+            &[
+                payer_account.clone(),
+                account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account.key, space as u64),
+        &[account.clone(), system_program.clone()],
+        &[seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, program_id),
+        &[account.clone(), system_program.clone()],
+        &[seeds],
+    )
+}
+
+/// Resize a program account, topping up its lamports from the payer or refunding the payer so
+/// that it stays exactly rent-exempt
+fn resize_account<'a>(
+    system_program: &AccountInfo<'a>,
+    payer_account: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    new_space: usize,
+) -> ProgramResult {
+    let required_lamports = Rent::get()?.minimum_balance(new_space);
+    match account.lamports().cmp(&required_lamports) {
+        Ordering::Less => {
+            // Overflow cannot happen here because we already checked the sizes.
+            #[allow(clippy::integer_arithmetic)]
+            let lamports_to_add = required_lamports - account.lamports();
+            invoke(
+                &system_instruction::transfer(payer_account.key, account.key, lamports_to_add),
+                &[
+                    payer_account.clone(),
+                    account.clone(),
+                    system_program.clone(),
+                ],
+            )?;
+        }
+        Ordering::Greater => {
+            // Overflow cannot happen here because we already checked the sizes.
+            #[allow(clippy::integer_arithmetic)]
+            let lamports_to_remove = account.lamports() - required_lamports;
+            let source_amount: &mut u64 = &mut account.lamports.borrow_mut();
+            let dest_amount: &mut u64 = &mut payer_account.lamports.borrow_mut();
+            *source_amount = source_amount.saturating_sub(lamports_to_remove);
+            *dest_amount = dest_amount.saturating_add(lamports_to_remove);
+        }
+        Ordering::Equal => {}
+    }
+    // Max data increase is checked in realloc. No need to check here.
+    account.realloc(new_space, false)
+}
+
 pub struct Processor {}
 
 impl Processor {
+    #[allow(clippy::too_many_arguments)]
+    fn write_reverse_record<'a>(
+        program_id: &Pubkey,
+        system_program: &AccountInfo<'a>,
+        payer_account: &AccountInfo<'a>,
+        reverse_record: &AccountInfo<'a>,
+        bump: u8,
+        owner: &Pubkey,
+        class: &Pubkey,
+        name_account_key: &Pubkey,
+    ) -> ProgramResult {
+        if reverse_record.data_len() == 0 {
+            create_program_account(
+                program_id,
+                system_program,
+                payer_account,
+                reverse_record,
+                ReverseRecord::LEN,
+                &[
+                    REVERSE_RECORD_PREFIX,
+                    owner.as_ref(),
+                    class.as_ref(),
+                    &[bump],
+                ],
+            )?;
+        } else if reverse_record.owner != program_id {
+            msg!("The given reverse record is not owned by the program.");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let reverse_state = ReverseRecord {
+            name_account: *name_account_key,
+        };
+        reverse_state.pack_into_slice(&mut reverse_record.data.borrow_mut());
+
+        Ok(())
+    }
+
     pub fn process_create(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         hashed_name: Vec<u8>,
         lamports: u64,
         space: u32,
+        expires_at: Option<UnixTimestamp>,
     ) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
 
@@ -38,7 +222,6 @@ impl Processor {
         let name_owner = next_account_info(accounts_iter)?;
         let name_class = next_account_info(accounts_iter)?;
         let parent_name_account = next_account_info(accounts_iter)?;
-        let parent_name_owner = next_account_info(accounts_iter).ok();
 
         let (name_account_key, seeds) = get_seeds_and_key(
             program_id,
@@ -65,22 +248,44 @@ impl Processor {
             return Err(ProgramError::InvalidArgument);
         }
         if *parent_name_account.key != Pubkey::default() {
-            if !parent_name_owner.unwrap().is_signer {
-                msg!("The given parent name account owner is not a signer.");
+            let parent_name_owner = next_account_info(accounts_iter)?;
+            let parent_name_record_header =
+                NameRecordHeader::unpack_from_slice(&parent_name_account.data.borrow())?;
+            if &parent_name_record_header.owner != parent_name_owner.key {
+                msg!("The given parent name account owner is not correct.");
                 return Err(ProgramError::InvalidArgument);
-            } else {
-                let parent_name_record_header =
-                    NameRecordHeader::unpack_from_slice(&parent_name_account.data.borrow())?;
-                if &parent_name_record_header.owner != parent_name_owner.unwrap().key {
-                    msg!("The given parent name account owner is not correct.");
-                    return Err(ProgramError::InvalidArgument);
-                }
+            }
+            if !parent_name_owner.is_signer {
+                Self::check_subdomain_policy(
+                    program_id,
+                    accounts_iter,
+                    system_program,
+                    payer_account,
+                    name_owner,
+                    parent_name_account,
+                    parent_name_owner,
+                    expires_at,
+                )?;
             }
         }
         if name_owner.key == &Pubkey::default() {
             msg!("The owner cannot be `Pubkey::default()`.");
             return Err(ProgramError::InvalidArgument);
         }
+        let header_len = if let Some(expires_at) = expires_at {
+            let now = Clock::get()?.unix_timestamp;
+            if expires_at <= now || expires_at > now.saturating_add(MAX_RENEWAL_PERIOD) {
+                msg!("The name must expire in the future, and within the maximum renewal period.");
+                return Err(NameServiceError::InvalidExpiry.into());
+            }
+            EXPIRING_HEADER_LEN
+        } else {
+            NameRecordHeader::LEN
+        };
+        if name_account.data_len() > 0 && name_account.data_len() < header_len {
+            msg!("The given name account is too small.");
+            return Err(ProgramError::AccountDataTooSmall);
+        }
 
         if name_account.data.borrow().len() == 0 {
             // Issue the name registry account
@@ -98,7 +303,7 @@ impl Processor {
             invoke_signed(
                 &system_instruction::allocate(
                     &name_account_key,
-                    NameRecordHeader::LEN.saturating_add(space as usize) as u64,
+                    header_len.saturating_add(space as usize) as u64,
                 ),
                 &[name_account.clone(), system_program.clone()],
                 &[&seeds.chunks(32).collect::<Vec<&[u8]>>()],
@@ -118,6 +323,35 @@ impl Processor {
         };
 
         name_state.pack_into_slice(&mut name_account.data.borrow_mut());
+        if let Some(expires_at) = expires_at {
+            NameRecordHeader::pack_expiry(
+                &NameRecordExpiry { expires_at },
+                &mut name_account.data.borrow_mut(),
+            );
+        }
+
+        // The reverse record follows the parent owner and subdomain policy accounts, if any
+        if let Some((reverse_record, bump)) = check_reverse_record(
+            program_id,
+            next_account_info(accounts_iter).ok(),
+            name_owner.key,
+            name_class.key,
+        )? {
+            if !name_owner.is_signer {
+                msg!("The name owner must sign to set its reverse record.");
+                return Err(ProgramError::InvalidArgument);
+            }
+            Self::write_reverse_record(
+                program_id,
+                system_program,
+                payer_account,
+                reverse_record,
+                bump,
+                name_owner.key,
+                name_class.key,
+                name_account.key,
+            )?;
+        }
 
         Ok(())
     }
@@ -160,27 +394,49 @@ impl Processor {
             msg!("The given name owner account is incorrect.");
             return Err(ProgramError::InvalidArgument);
         }
+        check_not_expired(name_account)?;
 
+        let header_len = NameRecordHeader::header_len(&name_account.data.borrow());
         write_data(
             name_account,
             &data,
-            NameRecordHeader::LEN.saturating_add(offset as usize),
+            header_len.saturating_add(offset as usize),
         );
+        // The data of a name that does not expire starts where the tag of an expiring name would be
+        if header_len == NameRecordHeader::LEN
+            && NameRecordHeader::has_expiring_tag(&name_account.data.borrow())
+        {
+            msg!("The data of a name that does not expire cannot start with the expiring header tag.");
+            return Err(ProgramError::InvalidArgument);
+        }
 
         Ok(())
     }
 
-    pub fn process_transfer(accounts: &[AccountInfo], new_owner: Pubkey) -> ProgramResult {
+    pub fn process_transfer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        new_owner: Pubkey,
+    ) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
 
         let name_account = next_account_info(accounts_iter)?;
         let name_owner = next_account_info(accounts_iter)?;
         let name_class_opt = next_account_info(accounts_iter).ok();
-        let parent_name = next_account_info(accounts_iter).ok();
+        let parent_name = next_account_info(accounts_iter)
+            .ok()
+            .filter(|parent_name| *parent_name.key != Pubkey::default());
 
         let mut name_record_header =
             NameRecordHeader::unpack_from_slice(&name_account.data.borrow())?;
 
+        let reverse_record = check_reverse_record(
+            program_id,
+            next_account_info(accounts_iter).ok(),
+            &name_record_header.owner,
+            &name_record_header.class,
+        )?;
+
         // Verifications
         let is_parent_owner = if let Some(parent_name) = parent_name {
             if name_record_header.parent_name != *parent_name.key {
@@ -208,14 +464,26 @@ impl Processor {
             return Err(ProgramError::InvalidArgument);
         }
 
+        check_not_expired(name_account)?;
+
+        // Clear the previous owner's reverse record if it points at this name
+        if let Some((reverse_record, _)) = reverse_record {
+            if let Some(mut reverse_state) = unpack_reverse_record(program_id, reverse_record)? {
+                if reverse_state.name_account == *name_account.key {
+                    reverse_state.name_account = Pubkey::default();
+                    reverse_state.pack_into_slice(&mut reverse_record.data.borrow_mut());
+                }
+            }
+        }
+
+        // The new owner must sign `SetReverseRecord` to point its reverse record at this name
         name_record_header.owner = new_owner;
-        name_record_header
-            .pack_into_slice(&mut name_account.data.borrow_mut()[..NameRecordHeader::LEN]);
+        name_record_header.pack_into_slice(&mut name_account.data.borrow_mut());
 
         Ok(())
     }
 
-    pub fn process_delete(accounts: &[AccountInfo]) -> ProgramResult {
+    pub fn process_delete(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
 
         let name_account = next_account_info(accounts_iter)?;
@@ -230,6 +498,16 @@ impl Processor {
             return Err(ProgramError::InvalidArgument);
         }
 
+        // Close the owner's reverse record if it points at this name
+        if let Some((reverse_record, _)) = check_reverse_record(
+            program_id,
+            next_account_info(accounts_iter).ok(),
+            &name_record_header.owner,
+            &name_record_header.class,
+        )? {
+            close_reverse_record(program_id, reverse_record, name_account.key, refund_target)?;
+        }
+
         // Overwrite the data with zeroes
         write_data(name_account, &vec![0; name_account.data_len()], 0);
 
@@ -257,39 +535,378 @@ impl Processor {
             return Err(ProgramError::InvalidArgument);
         }
 
-        let new_space = NameRecordHeader::LEN.saturating_add(space as usize);
-        let required_lamports = Rent::get()?.minimum_balance(new_space);
-        match name_account.lamports().cmp(&required_lamports) {
-            Ordering::Less => {
-                // Overflow cannot happen here because we already checked the sizes.
-                #[allow(clippy::integer_arithmetic)]
-                let lamports_to_add = required_lamports - name_account.lamports();
+        let header_len = NameRecordHeader::header_len(&name_account.data.borrow());
+        let new_space = header_len.saturating_add(space as usize);
+        resize_account(system_program, payer_account, name_account, new_space)
+    }
+
+    fn process_set_reverse_record(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let system_program = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let name_account = next_account_info(accounts_iter)?;
+        let name_owner = next_account_info(accounts_iter)?;
+        let reverse_record = next_account_info(accounts_iter)?;
+
+        // Verifications
+        if name_account.owner != program_id {
+            msg!("The given name account is not owned by the program.");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let name_record_header = NameRecordHeader::unpack_from_slice(&name_account.data.borrow())?;
+        if !name_owner.is_signer || name_record_header.owner != *name_owner.key {
+            msg!("The given name owner is incorrect or not a signer.");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let (reverse_record_key, bump) =
+            get_reverse_record_key(program_id, name_owner.key, Some(&name_record_header.class));
+        if reverse_record_key != *reverse_record.key {
+            msg!("The given reverse record is incorrect.");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Self::write_reverse_record(
+            program_id,
+            system_program,
+            payer_account,
+            reverse_record,
+            bump,
+            name_owner.key,
+            &name_record_header.class,
+            name_account.key,
+        )
+    }
+
+    /// Allow creating a subdomain without the signature of the parent owner if the subdomain
+    /// policy of the parent name permits it, collecting the fee of priced policies
+    #[allow(clippy::too_many_arguments)]
+    fn check_subdomain_policy<'a>(
+        program_id: &Pubkey,
+        accounts_iter: &mut std::slice::Iter<AccountInfo<'a>>,
+        system_program: &AccountInfo<'a>,
+        payer_account: &AccountInfo<'a>,
+        name_owner: &AccountInfo<'a>,
+        parent_name_account: &AccountInfo<'a>,
+        parent_name_owner: &AccountInfo<'a>,
+        expires_at: Option<UnixTimestamp>,
+    ) -> ProgramResult {
+        let (subdomain_policy_key, _) =
+            get_subdomain_policy_key(program_id, parent_name_account.key);
+        let subdomain_policy = match next_account_info(accounts_iter) {
+            Ok(subdomain_policy)
+                if *subdomain_policy.key == subdomain_policy_key
+                    && subdomain_policy.owner == program_id =>
+            {
+                subdomain_policy
+            }
+            _ => {
+                msg!("The given parent name account owner is not a signer.");
+                return Err(ProgramError::InvalidArgument);
+            }
+        };
+        check_not_expired(parent_name_account)?;
+
+        match SubdomainPolicy::unpack(&subdomain_policy.data.borrow())? {
+            SubdomainPolicy::Closed => {
+                msg!("The given parent name account owner is not a signer.");
+                return Err(ProgramError::InvalidArgument);
+            }
+            SubdomainPolicy::Open => {}
+            SubdomainPolicy::Allowlist(allowlist) => {
+                if !name_owner.is_signer || !allowlist.contains(name_owner.key) {
+                    msg!("The given name owner is not allowed to create subdomains.");
+                    return Err(ProgramError::InvalidArgument);
+                }
+            }
+            SubdomainPolicy::Priced { mint, price } => {
+                // The fee is paid again at every renewal, so the subdomain must expire
+                if expires_at.is_none() {
+                    msg!("Subdomains of a priced policy must be created with an expiry.");
+                    return Err(NameServiceError::NameNotExpiring.into());
+                }
+                Self::pay_subdomain_fee(
+                    accounts_iter,
+                    system_program,
+                    payer_account,
+                    parent_name_owner,
+                    mint,
+                    price,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collect the fee of a priced subdomain policy from the funding account for the owner of the
+    /// parent name, in lamports or in SPL tokens of `mint`
+    fn pay_subdomain_fee<'a>(
+        accounts_iter: &mut std::slice::Iter<AccountInfo<'a>>,
+        system_program: &AccountInfo<'a>,
+        payer_account: &AccountInfo<'a>,
+        parent_name_owner: &AccountInfo<'a>,
+        mint: Option<Pubkey>,
+        price: u64,
+    ) -> ProgramResult {
+        match mint {
+            None => invoke(
+                &system_instruction::transfer(payer_account.key, parent_name_owner.key, price),
+                &[
+                    payer_account.clone(),
+                    parent_name_owner.clone(),
+                    system_program.clone(),
+                ],
+            ),
+            Some(mint) => {
+                let source = next_account_info(accounts_iter)?;
+                let destination = next_account_info(accounts_iter)?;
+                let token_program = next_account_info(accounts_iter)?;
+                if *token_program.key != spl_token::id() || *destination.owner != spl_token::id() {
+                    return Err(ProgramError::IncorrectProgramId);
+                }
+                let destination_account =
+                    spl_token::state::Account::unpack(&destination.data.borrow())?;
+                if destination_account.owner != *parent_name_owner.key
+                    || destination_account.mint != mint
+                {
+                    msg!("The given fee destination is incorrect.");
+                    return Err(ProgramError::InvalidArgument);
+                }
                 invoke(
-                    &system_instruction::transfer(
+                    &spl_token::instruction::transfer(
+                        token_program.key,
+                        source.key,
+                        destination.key,
                         payer_account.key,
-                        name_account.key,
-                        lamports_to_add,
-                    ),
+                        &[],
+                        price,
+                    )?,
                     &[
+                        source.clone(),
+                        destination.clone(),
                         payer_account.clone(),
-                        name_account.clone(),
-                        system_program.clone(),
+                        token_program.clone(),
                     ],
-                )?;
+                )
+            }
+        }
+    }
+
+    fn process_set_subdomain_policy(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        policy: SubdomainPolicy,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let system_program = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let parent_name_account = next_account_info(accounts_iter)?;
+        let parent_name_owner = next_account_info(accounts_iter)?;
+        let subdomain_policy = next_account_info(accounts_iter)?;
+
+        // Verifications
+        if parent_name_account.owner != program_id {
+            msg!("The given parent name account is not owned by the program.");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let parent_name_record_header =
+            NameRecordHeader::unpack_from_slice(&parent_name_account.data.borrow())?;
+        if !parent_name_owner.is_signer || parent_name_record_header.owner != *parent_name_owner.key
+        {
+            msg!("The given parent name account owner is incorrect or not a signer.");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let (subdomain_policy_key, bump) =
+            get_subdomain_policy_key(program_id, parent_name_account.key);
+        if subdomain_policy_key != *subdomain_policy.key {
+            msg!("The given subdomain policy is incorrect.");
+            return Err(ProgramError::InvalidArgument);
+        }
+        // Fees are paid with the SPL Token program, so the mint must belong to it
+        if let SubdomainPolicy::Priced {
+            mint: Some(mint), ..
+        } = policy
+        {
+            let mint_account = next_account_info(accounts_iter)?;
+            if *mint_account.key != mint {
+                msg!("The given fee mint is incorrect.");
+                return Err(ProgramError::InvalidArgument);
             }
-            Ordering::Greater => {
-                // Overflow cannot happen here because we already checked the sizes.
-                #[allow(clippy::integer_arithmetic)]
-                let lamports_to_remove = name_account.lamports() - required_lamports;
-                let source_amount: &mut u64 = &mut name_account.lamports.borrow_mut();
-                let dest_amount: &mut u64 = &mut payer_account.lamports.borrow_mut();
-                *source_amount = source_amount.saturating_sub(lamports_to_remove);
-                *dest_amount = dest_amount.saturating_add(lamports_to_remove);
+            if *mint_account.owner != spl_token::id() {
+                msg!("The fee mint must be owned by the SPL Token program.");
+                return Err(ProgramError::IncorrectProgramId);
             }
-            Ordering::Equal => {}
+            spl_token::state::Mint::unpack(&mint_account.data.borrow())?;
         }
-        // Max data increase is checked in realloc. No need to check here.
-        name_account.realloc(new_space, false)?;
+
+        let policy_data = policy.try_to_vec()?;
+        if subdomain_policy.data_len() == 0 {
+            create_program_account(
+                program_id,
+                system_program,
+                payer_account,
+                subdomain_policy,
+                policy_data.len(),
+                &[
+                    SUBDOMAIN_POLICY_PREFIX,
+                    parent_name_account.key.as_ref(),
+                    &[bump],
+                ],
+            )?;
+        } else {
+            if subdomain_policy.owner != program_id {
+                msg!("The given subdomain policy is not owned by the program.");
+                return Err(ProgramError::InvalidArgument);
+            }
+            resize_account(
+                system_program,
+                payer_account,
+                subdomain_policy,
+                policy_data.len(),
+            )?;
+        }
+        write_data(subdomain_policy, &policy_data, 0);
+
+        Ok(())
+    }
+
+    fn process_renew(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        expires_at: UnixTimestamp,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let name_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        let name_record_header = NameRecordHeader::unpack_from_slice(&name_account.data.borrow())?;
+        let mut expiry = NameRecordHeader::unpack_expiry(&name_account.data.borrow())
+            .ok_or(NameServiceError::NameNotExpiring)?;
+
+        // Verifications
+        if !payer_account.is_signer {
+            msg!("The funding account is not a signer.");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let now = Clock::get()?.unix_timestamp;
+        if expires_at <= expiry.expires_at
+            || expires_at <= now
+            || expires_at > now.saturating_add(MAX_RENEWAL_PERIOD)
+        {
+            msg!("The name must expire later than currently, in the future, and within the maximum renewal period.");
+            return Err(NameServiceError::InvalidExpiry.into());
+        }
+
+        // Subdomains are renewed at the price of the subdomain policy of their parent, if priced
+        if name_record_header.parent_name != Pubkey::default() {
+            let parent_name = next_account_info(accounts_iter)?;
+            let parent_name_owner = next_account_info(accounts_iter)?;
+            let subdomain_policy = next_account_info(accounts_iter)?;
+            if name_record_header.parent_name != *parent_name.key {
+                msg!("Invalid parent name account");
+                return Err(ProgramError::InvalidArgument);
+            }
+            let parent_name_record_header =
+                NameRecordHeader::unpack_from_slice(&parent_name.data.borrow())?;
+            if parent_name_record_header.owner != *parent_name_owner.key {
+                msg!("The given parent name account owner is not correct.");
+                return Err(ProgramError::InvalidArgument);
+            }
+            let (subdomain_policy_key, _) = get_subdomain_policy_key(program_id, parent_name.key);
+            if subdomain_policy_key != *subdomain_policy.key {
+                msg!("The given subdomain policy is incorrect.");
+                return Err(ProgramError::InvalidArgument);
+            }
+            if subdomain_policy.owner == program_id {
+                if let SubdomainPolicy::Priced { mint, price } =
+                    SubdomainPolicy::unpack(&subdomain_policy.data.borrow())?
+                {
+                    Self::pay_subdomain_fee(
+                        accounts_iter,
+                        system_program,
+                        payer_account,
+                        parent_name_owner,
+                        mint,
+                        price,
+                    )?;
+                }
+            }
+        }
+
+        expiry.expires_at = expires_at;
+        NameRecordHeader::pack_expiry(&expiry, &mut name_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_reclaim(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let name_account = next_account_info(accounts_iter)?;
+        let refund_target = next_account_info(accounts_iter)?;
+        let parent_name = next_account_info(accounts_iter)
+            .ok()
+            .filter(|parent_name| *parent_name.key != Pubkey::default());
+        let reverse_record = next_account_info(accounts_iter).ok();
+
+        // Verifications
+        if name_account.owner != program_id {
+            msg!("The given name account is not owned by the program.");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let mut name_record_header =
+            NameRecordHeader::unpack_from_slice(&name_account.data.borrow())?;
+        let expiry = NameRecordHeader::unpack_expiry(&name_account.data.borrow())
+            .ok_or(NameServiceError::NameNotExpiring)?;
+        if !expiry.is_reclaimable(Clock::get()?.unix_timestamp) {
+            msg!("The name cannot be reclaimed before the end of its grace period.");
+            return Err(NameServiceError::NameNotReclaimable.into());
+        }
+
+        // Close the owner's reverse record if it points at this name
+        if let Some((reverse_record, _)) = check_reverse_record(
+            program_id,
+            reverse_record,
+            &name_record_header.owner,
+            &name_record_header.class,
+        )? {
+            if name_record_header.owner != *refund_target.key {
+                msg!("The refund account must be the name owner.");
+                return Err(ProgramError::InvalidArgument);
+            }
+            close_reverse_record(program_id, reverse_record, name_account.key, refund_target)?;
+        }
+
+        if name_record_header.class != Pubkey::default() {
+            name_record_header.owner = name_record_header.class;
+        } else if name_record_header.parent_name != Pubkey::default() {
+            let parent_name = parent_name.ok_or(ProgramError::NotEnoughAccountKeys)?;
+            if name_record_header.parent_name != *parent_name.key {
+                msg!("Invalid parent name account");
+                return Err(ProgramError::InvalidArgument);
+            }
+            name_record_header.owner =
+                NameRecordHeader::unpack_from_slice(&parent_name.data.borrow())?.owner;
+        } else {
+            if name_record_header.owner != *refund_target.key {
+                msg!("The refund account must be the name owner.");
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            // Overwrite the data with zeroes
+            write_data(name_account, &vec![0; name_account.data_len()], 0);
+
+            // Close the account by transferring the rent sol
+            let source_amount: &mut u64 = &mut name_account.lamports.borrow_mut();
+            let dest_amount: &mut u64 = &mut refund_target.lamports.borrow_mut();
+            *dest_amount = dest_amount.saturating_add(*source_amount);
+            *source_amount = 0;
+
+            return Ok(());
+        }
+
+        name_record_header.pack_into_slice(&mut name_account.data.borrow_mut());
+
         Ok(())
     }
 
@@ -310,7 +927,14 @@ impl Processor {
                 space,
             } => {
                 msg!("Instruction: Create");
-                Processor::process_create(program_id, accounts, hashed_name, lamports, space)?;
+                Processor::process_create(
+                    program_id,
+                    accounts,
+                    hashed_name,
+                    lamports,
+                    space,
+                    None,
+                )?;
             }
             NameRegistryInstruction::Update { offset, data } => {
                 msg!("Instruction: Update Data");
@@ -318,16 +942,48 @@ impl Processor {
             }
             NameRegistryInstruction::Transfer { new_owner } => {
                 msg!("Instruction: Transfer Ownership");
-                Processor::process_transfer(accounts, new_owner)?;
+                Processor::process_transfer(program_id, accounts, new_owner)?;
             }
             NameRegistryInstruction::Delete => {
                 msg!("Instruction: Delete Name");
-                Processor::process_delete(accounts)?;
+                Processor::process_delete(program_id, accounts)?;
             }
             NameRegistryInstruction::Realloc { space } => {
                 msg!("Instruction: Realloc Name Record");
                 Processor::process_realloc(accounts, space)?;
             }
+            NameRegistryInstruction::SetReverseRecord => {
+                msg!("Instruction: Set Reverse Record");
+                Processor::process_set_reverse_record(program_id, accounts)?;
+            }
+            NameRegistryInstruction::CreateWithExpiry {
+                hashed_name,
+                lamports,
+                space,
+                expires_at,
+            } => {
+                msg!("Instruction: Create With Expiry");
+                Processor::process_create(
+                    program_id,
+                    accounts,
+                    hashed_name,
+                    lamports,
+                    space,
+                    Some(expires_at),
+                )?;
+            }
+            NameRegistryInstruction::Renew { expires_at } => {
+                msg!("Instruction: Renew");
+                Processor::process_renew(program_id, accounts, expires_at)?;
+            }
+            NameRegistryInstruction::Reclaim => {
+                msg!("Instruction: Reclaim");
+                Processor::process_reclaim(program_id, accounts)?;
+            }
+            NameRegistryInstruction::SetSubdomainPolicy { policy } => {
+                msg!("Instruction: Set Subdomain Policy");
+                Processor::process_set_subdomain_policy(program_id, accounts, policy)?;
+            }
         }
         Ok(())
     }
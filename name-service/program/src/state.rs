@@ -2,6 +2,7 @@ use {
     borsh::{BorshDeserialize, BorshSerialize},
     solana_program::{
         account_info::AccountInfo,
+        clock::UnixTimestamp,
         msg,
         program_error::ProgramError,
         program_pack::{IsInitialized, Pack, Sealed},
@@ -52,6 +53,145 @@ impl IsInitialized for NameRecordHeader {
     }
 }
 
+impl NameRecordHeader {
+    /// Whether `EXPIRING_HEADER_TAG` follows the `NameRecordHeader` fields
+    pub fn has_expiring_tag(src: &[u8]) -> bool {
+        src.get(Self::LEN..EXPIRY_OFFSET) == Some(&EXPIRING_HEADER_TAG[..])
+    }
+
+    fn is_expiring(src: &[u8]) -> bool {
+        src.len() >= EXPIRING_HEADER_LEN && Self::has_expiring_tag(src)
+    }
+
+    /// Read the expiry of a name record, or `None` if the name does not expire
+    pub fn unpack_expiry(src: &[u8]) -> Option<NameRecordExpiry> {
+        if !Self::is_expiring(src) {
+            return None;
+        }
+        NameRecordExpiry::deserialize(&mut &src[EXPIRY_OFFSET..]).ok()
+    }
+
+    /// Write the tag and the expiry after the `NameRecordHeader` fields, where the record data of a
+    /// name that does not expire would start
+    pub fn pack_expiry(expiry: &NameRecordExpiry, dst: &mut [u8]) {
+        dst[Self::LEN..EXPIRY_OFFSET].copy_from_slice(&EXPIRING_HEADER_TAG);
+        let mut slice = &mut dst[EXPIRY_OFFSET..EXPIRING_HEADER_LEN];
+        expiry.serialize(&mut slice).unwrap()
+    }
+
+    /// Length of the header of a name record, after which the record data starts
+    pub fn header_len(src: &[u8]) -> usize {
+        if Self::unpack_expiry(src).is_some() {
+            EXPIRING_HEADER_LEN
+        } else {
+            Self::LEN
+        }
+    }
+}
+
+/// Names that expire use a second version of the header: the `NameRecordHeader` fields, followed by
+/// `EXPIRING_HEADER_TAG` and a `NameRecordExpiry`.
+///
+/// The `NameRecordHeader` fields keep their offsets, so readers that only know the first version
+/// still read the correct parent name, owner and class, and see the tag and expiry as the first
+/// bytes of the record data. `Update` refuses to write the tag at the start of the data of a name
+/// that does not expire, so its owner cannot turn it into an expiring one.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct NameRecordExpiry {
+    // Unix timestamp at which the name expires
+    pub expires_at: UnixTimestamp,
+}
+
+impl NameRecordExpiry {
+    pub fn is_expired(&self, now: UnixTimestamp) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Expired names can be reclaimed by anyone once the grace period is over
+    pub fn is_reclaimable(&self, now: UnixTimestamp) -> bool {
+        now >= self.expires_at.saturating_add(EXPIRY_GRACE_PERIOD)
+    }
+}
+
+/// A reverse record points from an owner back to one of its name records of a given class.
+///
+/// Its address is a program-derived address from the owner and class, see `get_reverse_record_key`.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct ReverseRecord {
+    // The name record this owner resolves to, or `Pubkey::default()` if it was cleared
+    pub name_account: Pubkey,
+}
+
+impl Sealed for ReverseRecord {}
+
+impl Pack for ReverseRecord {
+    const LEN: usize = 32;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let mut slice = dst;
+        self.serialize(&mut slice).unwrap()
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let mut p = src;
+        ReverseRecord::deserialize(&mut p).map_err(|_| {
+            msg!("Failed to deserialize reverse record");
+            ProgramError::InvalidAccountData
+        })
+    }
+}
+
+impl ReverseRecord {
+    /// Resolve the name record of a reverse record, given the data of that name record.
+    ///
+    /// A reverse record can go stale when a name is transferred or deleted without it, so the
+    /// name record must still belong to `owner` and have the expected class.
+    pub fn resolve(
+        &self,
+        owner: &Pubkey,
+        class: &Pubkey,
+        name_record_data: &[u8],
+    ) -> Option<Pubkey> {
+        if self.name_account == Pubkey::default() {
+            return None;
+        }
+        let header = NameRecordHeader::unpack_from_slice(name_record_data).ok()?;
+        if header.owner == *owner && header.class == *class {
+            Some(self.name_account)
+        } else {
+            None
+        }
+    }
+}
+
+/// Rules for creating subdomains of a parent name without the signature of its owner, stored in a
+/// program-derived account per parent name, see `get_subdomain_policy_key`.
+///
+/// The policy belongs to the parent name, so it carries over if the parent is transferred, and
+/// fees are always paid to the current owner of the parent name.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum SubdomainPolicy {
+    /// Only the parent owner can create subdomains, by signing `Create`
+    Closed,
+    /// Anyone can create subdomains
+    Open,
+    /// Only the listed accounts can create subdomains, which they must own and sign for
+    Allowlist(Vec<Pubkey>),
+    /// Anyone can create subdomains by paying a fee to the parent owner, in lamports if `mint` is
+    /// `None`, otherwise in SPL tokens of that mint, which must not be a Token-2022 mint. The
+    /// subdomains must be created with an expiry, and the fee is paid again to renew them
+    Priced { mint: Option<Pubkey>, price: u64 },
+}
+
+impl SubdomainPolicy {
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        SubdomainPolicy::deserialize(&mut &src[..]).map_err(|_| {
+            msg!("Failed to deserialize subdomain policy");
+            ProgramError::InvalidAccountData
+        })
+    }
+}
+
 pub fn write_data(account: &AccountInfo, input: &[u8], offset: usize) {
     let mut account_data = account.data.borrow_mut();
     account_data[offset..offset.saturating_add(input.len())].copy_from_slice(input);
@@ -61,6 +201,25 @@ pub fn write_data(account: &AccountInfo, input: &[u8], offset: usize) {
 
 pub const HASH_PREFIX: &str = "SPL Name Service";
 
+pub const REVERSE_RECORD_PREFIX: &[u8] = b"reverse";
+
+pub const SUBDOMAIN_POLICY_PREFIX: &[u8] = b"subdomain_policy";
+
+/// Marks a name record header as the expiring version, right after the `NameRecordHeader` fields
+pub const EXPIRING_HEADER_TAG: [u8; 8] = [194, 139, 71, 52, 234, 220, 42, 112];
+
+const EXPIRY_OFFSET: usize = NameRecordHeader::LEN + EXPIRING_HEADER_TAG.len();
+
+/// Length of the header of an expiring name, after which its data starts
+pub const EXPIRING_HEADER_LEN: usize = EXPIRY_OFFSET + 8;
+
+/// Time after expiry during which a name can still be renewed before anyone can reclaim it
+pub const EXPIRY_GRACE_PERIOD: UnixTimestamp = 30 * 24 * 60 * 60;
+
+/// How far into the future a renewal can push the expiry of a name, so that names which are not
+/// renewed regularly still expire
+pub const MAX_RENEWAL_PERIOD: UnixTimestamp = 365 * 24 * 60 * 60;
+
 ////////////////////////////////////////////////////////////
 
 pub fn get_seeds_and_key(
@@ -90,3 +249,26 @@ pub fn get_seeds_and_key(
 
     (name_account_key, seeds_vec)
 }
+
+pub fn get_reverse_record_key(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    name_class_opt: Option<&Pubkey>,
+) -> (Pubkey, u8) {
+    let name_class = name_class_opt.cloned().unwrap_or_default();
+    Pubkey::find_program_address(
+        &[
+            REVERSE_RECORD_PREFIX,
+            &owner.to_bytes(),
+            &name_class.to_bytes(),
+        ],
+        program_id,
+    )
+}
+
+pub fn get_subdomain_policy_key(program_id: &Pubkey, parent_name: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[SUBDOMAIN_POLICY_PREFIX, &parent_name.to_bytes()],
+        program_id,
+    )
+}
@@ -1,7 +1,9 @@
 #![cfg(feature = "test-sbf")]
 use std::str::FromStr;
 
-use solana_program::{instruction::Instruction, program_pack::Pack, pubkey::Pubkey};
+use solana_program::{
+    clock::Clock, instruction::Instruction, program_pack::Pack, pubkey::Pubkey, system_instruction,
+};
 use solana_program_test::{
     processor, tokio, ProgramTest, ProgramTestBanksClientExt, ProgramTestContext,
 };
@@ -13,9 +15,17 @@ use solana_sdk::{
     transport::TransportError,
 };
 use spl_name_service::{
-    instruction::{create, delete, realloc, transfer, update, NameRegistryInstruction},
+    instruction::{
+        create, create_with_subdomain_policy, delete, realloc, reclaim, renew, set_reverse_record,
+        set_subdomain_policy, transfer, transfer_with_reverse_record, update, with_reverse_record,
+        NameRegistryInstruction,
+    },
     processor::Processor,
-    state::{get_seeds_and_key, NameRecordHeader, HASH_PREFIX},
+    state::{
+        get_reverse_record_key, get_seeds_and_key, NameRecordHeader, ReverseRecord,
+        SubdomainPolicy, EXPIRING_HEADER_LEN, EXPIRING_HEADER_TAG, EXPIRY_GRACE_PERIOD,
+        HASH_PREFIX, MAX_RENEWAL_PERIOD,
+    },
 };
 
 #[tokio::test]
@@ -242,7 +252,988 @@ async fn test_name_service() {
         .unwrap();
 }
 
+#[tokio::test]
+async fn test_reverse_record() {
+    let program_id = Pubkey::from_str("XCWuBvfNamesXCWuBvfkegQfZyiNwAJb9Ss623VQ5DA").unwrap();
+
+    let program_test = ProgramTest::new(
+        "spl_name_service",
+        program_id,
+        processor!(Processor::process_instruction),
+    );
+
+    let mut ctx = program_test.start_with_context().await;
+
+    let class = Keypair::new();
+    let owner = Keypair::new();
+    let new_owner = Keypair::new();
+    let (reverse_record_key, _) =
+        get_reverse_record_key(&program_id, &owner.pubkey(), Some(&class.pubkey()));
+
+    let space = 100usize;
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    let mut name_account_keys = vec![];
+    for name in ["first", "second"] {
+        let hashed_name: Vec<u8> = hashv(&[(HASH_PREFIX.to_owned() + name).as_bytes()])
+            .as_ref()
+            .to_vec();
+        let (name_account_key, _) = get_seeds_and_key(
+            &program_id,
+            hashed_name.clone(),
+            Some(&class.pubkey()),
+            None,
+        );
+        let create_name_instruction = create(
+            program_id,
+            NameRegistryInstruction::Create {
+                hashed_name,
+                lamports: rent.minimum_balance(space.saturating_add(NameRecordHeader::LEN)),
+                space: space as u32,
+            },
+            name_account_key,
+            ctx.payer.pubkey(),
+            owner.pubkey(),
+            Some(class.pubkey()),
+            None,
+            None,
+        )
+        .unwrap();
+        sign_send_instruction(
+            &mut ctx,
+            with_reverse_record(
+                create_name_instruction,
+                owner.pubkey(),
+                Some(class.pubkey()),
+            ),
+            vec![&class, &owner],
+        )
+        .await
+        .unwrap();
+        name_account_keys.push(name_account_key);
+    }
+
+    // The reverse record follows the latest created name
+    assert_eq!(
+        resolve_reverse_record(&mut ctx, reverse_record_key, &owner, &class).await,
+        Some(name_account_keys[1])
+    );
+
+    let set_reverse_record_instruction = set_reverse_record(
+        program_id,
+        ctx.payer.pubkey(),
+        name_account_keys[0],
+        owner.pubkey(),
+        Some(class.pubkey()),
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, set_reverse_record_instruction, vec![&owner])
+        .await
+        .unwrap();
+    assert_eq!(
+        resolve_reverse_record(&mut ctx, reverse_record_key, &owner, &class).await,
+        Some(name_account_keys[0])
+    );
+
+    // Only the owner can set its reverse record
+    let set_reverse_record_instruction = set_reverse_record(
+        program_id,
+        ctx.payer.pubkey(),
+        name_account_keys[1],
+        new_owner.pubkey(),
+        Some(class.pubkey()),
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, set_reverse_record_instruction, vec![&new_owner])
+        .await
+        .unwrap_err();
+
+    // The reverse record must be at its derived address
+    let (new_reverse_record_key, _) =
+        get_reverse_record_key(&program_id, &new_owner.pubkey(), Some(&class.pubkey()));
+    let mut transfer_instruction = transfer_with_reverse_record(
+        program_id,
+        new_owner.pubkey(),
+        name_account_keys[1],
+        owner.pubkey(),
+        Some(class.pubkey()),
+        None,
+    )
+    .unwrap();
+    transfer_instruction.accounts[4].pubkey = new_reverse_record_key;
+    sign_send_instruction(&mut ctx, transfer_instruction, vec![&owner, &class])
+        .await
+        .unwrap_err();
+
+    // Transferring another name leaves the reverse record untouched, and the reverse record of
+    // the new owner is only set once the new owner signs
+    let transfer_instruction = transfer_with_reverse_record(
+        program_id,
+        new_owner.pubkey(),
+        name_account_keys[1],
+        owner.pubkey(),
+        Some(class.pubkey()),
+        None,
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, transfer_instruction, vec![&owner, &class])
+        .await
+        .unwrap();
+    assert_eq!(
+        resolve_reverse_record(&mut ctx, reverse_record_key, &owner, &class).await,
+        Some(name_account_keys[0])
+    );
+    assert!(ctx
+        .banks_client
+        .get_account(new_reverse_record_key)
+        .await
+        .unwrap()
+        .is_none());
+    // update blockhash to prevent losing txn to dedup with the failed one above
+    ctx.last_blockhash = ctx
+        .banks_client
+        .get_new_latest_blockhash(&ctx.last_blockhash)
+        .await
+        .unwrap();
+    let set_reverse_record_instruction = set_reverse_record(
+        program_id,
+        ctx.payer.pubkey(),
+        name_account_keys[1],
+        new_owner.pubkey(),
+        Some(class.pubkey()),
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, set_reverse_record_instruction, vec![&new_owner])
+        .await
+        .unwrap();
+    assert_eq!(
+        resolve_reverse_record(&mut ctx, new_reverse_record_key, &new_owner, &class).await,
+        Some(name_account_keys[1])
+    );
+
+    // Transferring the resolved name clears the reverse record, and leaves the reverse record of
+    // the new owner pointing at its other name
+    let transfer_instruction = transfer_with_reverse_record(
+        program_id,
+        new_owner.pubkey(),
+        name_account_keys[0],
+        owner.pubkey(),
+        Some(class.pubkey()),
+        None,
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, transfer_instruction, vec![&owner, &class])
+        .await
+        .unwrap();
+    assert_eq!(
+        resolve_reverse_record(&mut ctx, reverse_record_key, &owner, &class).await,
+        None
+    );
+    assert_eq!(
+        resolve_reverse_record(&mut ctx, new_reverse_record_key, &new_owner, &class).await,
+        Some(name_account_keys[1])
+    );
+
+    // Deleting the resolved name closes the reverse record
+    let delete_instruction = delete(
+        program_id,
+        name_account_keys[1],
+        new_owner.pubkey(),
+        ctx.payer.pubkey(),
+    )
+    .unwrap();
+    sign_send_instruction(
+        &mut ctx,
+        with_reverse_record(delete_instruction, new_owner.pubkey(), Some(class.pubkey())),
+        vec![&new_owner],
+    )
+    .await
+    .unwrap();
+    assert!(ctx
+        .banks_client
+        .get_account(new_reverse_record_key)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_expiring_name() {
+    let program_id = Pubkey::from_str("XCWuBvfNamesXCWuBvfkegQfZyiNwAJb9Ss623VQ5DA").unwrap();
+
+    let program_test = ProgramTest::new(
+        "spl_name_service",
+        program_id,
+        processor!(Processor::process_instruction),
+    );
+
+    let mut ctx = program_test.start_with_context().await;
+
+    let class = Keypair::new();
+    let owner = Keypair::new();
+    let new_owner = Keypair::new();
+    let clock = ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+    let expires_at = clock.unix_timestamp + 100;
+
+    let space = 100usize;
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    let create_name = |name: &str, class_opt: Option<Pubkey>, expires_at: Option<i64>| {
+        let hashed_name: Vec<u8> = hashv(&[(HASH_PREFIX.to_owned() + name).as_bytes()])
+            .as_ref()
+            .to_vec();
+        let (name_account_key, _) =
+            get_seeds_and_key(&program_id, hashed_name.clone(), class_opt.as_ref(), None);
+        let instruction_data = if let Some(expires_at) = expires_at {
+            NameRegistryInstruction::CreateWithExpiry {
+                hashed_name,
+                lamports: rent.minimum_balance(space.saturating_add(EXPIRING_HEADER_LEN)),
+                space: space as u32,
+                expires_at,
+            }
+        } else {
+            NameRegistryInstruction::Create {
+                hashed_name,
+                lamports: rent.minimum_balance(space.saturating_add(NameRecordHeader::LEN)),
+                space: space as u32,
+            }
+        };
+        let instruction = create(
+            program_id,
+            instruction_data,
+            name_account_key,
+            ctx.payer.pubkey(),
+            owner.pubkey(),
+            class_opt,
+            None,
+            None,
+        )
+        .unwrap();
+        (name_account_key, instruction)
+    };
+    let (name_account_key, create_expiring_instruction) =
+        create_name("expiring", Some(class.pubkey()), Some(expires_at));
+    let (unclassed_name_account_key, create_unclassed_instruction) =
+        create_name("unclassed", None, Some(expires_at));
+    let (permanent_name_account_key, create_permanent_instruction) =
+        create_name("permanent", None, None);
+    let (_, create_never_expiring_instruction) = create_name("never", None, Some(i64::MAX));
+    let (_, create_late_expiring_instruction) = create_name(
+        "late",
+        None,
+        Some(clock.unix_timestamp + MAX_RENEWAL_PERIOD + 1),
+    );
+
+    sign_send_instruction(&mut ctx, create_expiring_instruction, vec![&class])
+        .await
+        .unwrap();
+    sign_send_instruction(&mut ctx, create_unclassed_instruction, vec![])
+        .await
+        .unwrap();
+    sign_send_instruction(&mut ctx, create_permanent_instruction, vec![])
+        .await
+        .unwrap();
+
+    // Names cannot be created to expire further ahead than they could be renewed
+    sign_send_instruction(&mut ctx, create_never_expiring_instruction, vec![])
+        .await
+        .unwrap_err();
+    sign_send_instruction(&mut ctx, create_late_expiring_instruction, vec![])
+        .await
+        .unwrap_err();
+
+    let set_reverse_record_instruction = set_reverse_record(
+        program_id,
+        ctx.payer.pubkey(),
+        unclassed_name_account_key,
+        owner.pubkey(),
+        None,
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, set_reverse_record_instruction, vec![&owner])
+        .await
+        .unwrap();
+
+    let data = ctx
+        .banks_client
+        .get_account(name_account_key)
+        .await
+        .unwrap()
+        .unwrap()
+        .data;
+    assert_eq!(data.len(), EXPIRING_HEADER_LEN + space);
+    assert_eq!(
+        &data[NameRecordHeader::LEN..NameRecordHeader::LEN + EXPIRING_HEADER_TAG.len()],
+        &EXPIRING_HEADER_TAG
+    );
+    assert_eq!(NameRecordHeader::header_len(&data), EXPIRING_HEADER_LEN);
+
+    // The owner and class stay where readers of the first header version expect them
+    let header = NameRecordHeader::unpack_from_slice(&data[..NameRecordHeader::LEN]).unwrap();
+    assert_eq!(header.owner, owner.pubkey());
+    assert_eq!(header.class, class.pubkey());
+    assert_eq!(
+        NameRecordHeader::unpack_expiry(&data).unwrap().expires_at,
+        expires_at
+    );
+
+    // Data is written after the expiring header
+    let update_instruction = update(
+        program_id,
+        0,
+        b"hello".to_vec(),
+        name_account_key,
+        class.pubkey(),
+        None,
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, update_instruction.clone(), vec![&class])
+        .await
+        .unwrap();
+    let data = ctx
+        .banks_client
+        .get_account(name_account_key)
+        .await
+        .unwrap()
+        .unwrap()
+        .data;
+    assert_eq!(
+        &data[EXPIRING_HEADER_LEN..EXPIRING_HEADER_LEN + 5],
+        b"hello"
+    );
+
+    // Names that do not expire cannot be made to expire through their data
+    let update_instruction_permanent = update(
+        program_id,
+        0,
+        EXPIRING_HEADER_TAG.to_vec(),
+        permanent_name_account_key,
+        owner.pubkey(),
+        None,
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, update_instruction_permanent, vec![&owner])
+        .await
+        .unwrap_err();
+    let data = ctx
+        .banks_client
+        .get_account(permanent_name_account_key)
+        .await
+        .unwrap()
+        .unwrap()
+        .data;
+    assert_eq!(NameRecordHeader::header_len(&data), NameRecordHeader::LEN);
+    assert!(NameRecordHeader::unpack_expiry(&data).is_none());
+
+    // Anyone can renew a name, without the owner or class signing, but not too far ahead
+    let renew_instruction = |expires_at: i64| {
+        renew(
+            program_id,
+            expires_at,
+            name_account_key,
+            ctx.payer.pubkey(),
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+    };
+    let early_renew_instruction = renew_instruction(expires_at - 50);
+    let late_renew_instruction = renew_instruction(clock.unix_timestamp + MAX_RENEWAL_PERIOD + 1);
+    let expires_at = expires_at + 100;
+    let renew_name_instruction = renew_instruction(expires_at);
+    sign_send_instruction(&mut ctx, early_renew_instruction, vec![])
+        .await
+        .unwrap_err();
+    sign_send_instruction(&mut ctx, late_renew_instruction, vec![])
+        .await
+        .unwrap_err();
+    sign_send_instruction(&mut ctx, renew_name_instruction, vec![])
+        .await
+        .unwrap();
+    let data = ctx
+        .banks_client
+        .get_account(name_account_key)
+        .await
+        .unwrap()
+        .unwrap()
+        .data;
+    assert_eq!(
+        NameRecordHeader::unpack_expiry(&data).unwrap().expires_at,
+        expires_at
+    );
+
+    // Subdomains are renewed at the price of the subdomain policy of their parent
+    let price = 1_000_000;
+    let set_policy_instruction = set_subdomain_policy(
+        program_id,
+        ctx.payer.pubkey(),
+        permanent_name_account_key,
+        owner.pubkey(),
+        SubdomainPolicy::Priced { mint: None, price },
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, set_policy_instruction, vec![&owner])
+        .await
+        .unwrap();
+    let hashed_name: Vec<u8> = hashv(&[(HASH_PREFIX.to_owned() + "subdomain").as_bytes()])
+        .as_ref()
+        .to_vec();
+    let (subdomain_account_key, _) = get_seeds_and_key(
+        &program_id,
+        hashed_name.clone(),
+        None,
+        Some(&permanent_name_account_key),
+    );
+    let create_subdomain_instruction = create(
+        program_id,
+        NameRegistryInstruction::CreateWithExpiry {
+            hashed_name,
+            lamports: rent.minimum_balance(space.saturating_add(EXPIRING_HEADER_LEN)),
+            space: space as u32,
+            expires_at,
+        },
+        subdomain_account_key,
+        ctx.payer.pubkey(),
+        new_owner.pubkey(),
+        None,
+        Some(permanent_name_account_key),
+        Some(owner.pubkey()),
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, create_subdomain_instruction, vec![&owner])
+        .await
+        .unwrap();
+    let renew_subdomain_instruction = |name_parent_opt, name_parent_owner_opt| {
+        renew(
+            program_id,
+            expires_at + 100,
+            subdomain_account_key,
+            ctx.payer.pubkey(),
+            name_parent_opt,
+            name_parent_owner_opt,
+            None,
+        )
+        .unwrap()
+    };
+    let unpaid_renew_instruction = renew_subdomain_instruction(None, None);
+    let paid_renew_instruction =
+        renew_subdomain_instruction(Some(permanent_name_account_key), Some(owner.pubkey()));
+    sign_send_instruction(&mut ctx, unpaid_renew_instruction, vec![])
+        .await
+        .unwrap_err();
+    let owner_balance = ctx.banks_client.get_balance(owner.pubkey()).await.unwrap();
+    sign_send_instruction(&mut ctx, paid_renew_instruction, vec![])
+        .await
+        .unwrap();
+    assert_eq!(
+        ctx.banks_client.get_balance(owner.pubkey()).await.unwrap(),
+        owner_balance + price
+    );
+
+    // Expired names can no longer be updated or transferred
+    ctx.set_sysvar(&Clock {
+        unix_timestamp: expires_at,
+        ..clock.clone()
+    });
+    ctx.last_blockhash = ctx
+        .banks_client
+        .get_new_latest_blockhash(&ctx.last_blockhash)
+        .await
+        .unwrap();
+    sign_send_instruction(&mut ctx, update_instruction, vec![&class])
+        .await
+        .unwrap_err();
+    let transfer_instruction = transfer(
+        program_id,
+        new_owner.pubkey(),
+        name_account_key,
+        owner.pubkey(),
+        Some(class.pubkey()),
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, transfer_instruction, vec![&owner, &class])
+        .await
+        .unwrap_err();
+
+    // Names cannot be reclaimed during the grace period
+    let reclaim_instruction =
+        reclaim(program_id, name_account_key, owner.pubkey(), None, None).unwrap();
+    sign_send_instruction(&mut ctx, reclaim_instruction.clone(), vec![])
+        .await
+        .unwrap_err();
+
+    ctx.set_sysvar(&Clock {
+        unix_timestamp: expires_at + EXPIRY_GRACE_PERIOD,
+        ..clock
+    });
+    ctx.last_blockhash = ctx
+        .banks_client
+        .get_new_latest_blockhash(&ctx.last_blockhash)
+        .await
+        .unwrap();
+
+    // Reclaiming transfers the name to its class
+    sign_send_instruction(&mut ctx, reclaim_instruction, vec![])
+        .await
+        .unwrap();
+    let name_record_header = NameRecordHeader::unpack_from_slice(
+        &ctx.banks_client
+            .get_account(name_account_key)
+            .await
+            .unwrap()
+            .unwrap()
+            .data,
+    )
+    .unwrap();
+    assert_eq!(name_record_header.owner, class.pubkey());
+
+    // Reclaiming a name without class nor parent deletes it and its reverse record, refunding
+    // the owner
+    let reclaim_instruction = reclaim(
+        program_id,
+        unclassed_name_account_key,
+        new_owner.pubkey(),
+        None,
+        None,
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, reclaim_instruction, vec![])
+        .await
+        .unwrap_err();
+    let owner_balance = ctx.banks_client.get_balance(owner.pubkey()).await.unwrap();
+    let reclaim_instruction = reclaim(
+        program_id,
+        unclassed_name_account_key,
+        owner.pubkey(),
+        None,
+        Some((owner.pubkey(), None)),
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, reclaim_instruction, vec![])
+        .await
+        .unwrap();
+    assert!(ctx
+        .banks_client
+        .get_account(unclassed_name_account_key)
+        .await
+        .unwrap()
+        .is_none());
+    let (reverse_record_key, _) = get_reverse_record_key(&program_id, &owner.pubkey(), None);
+    assert!(ctx
+        .banks_client
+        .get_account(reverse_record_key)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        ctx.banks_client.get_balance(owner.pubkey()).await.unwrap(),
+        owner_balance
+            + rent.minimum_balance(space.saturating_add(EXPIRING_HEADER_LEN))
+            + rent.minimum_balance(ReverseRecord::LEN)
+    );
+}
+
+#[tokio::test]
+async fn test_subdomain_policy() {
+    let program_id = Pubkey::from_str("XCWuBvfNamesXCWuBvfkegQfZyiNwAJb9Ss623VQ5DA").unwrap();
+
+    let program_test = ProgramTest::new(
+        "spl_name_service",
+        program_id,
+        processor!(Processor::process_instruction),
+    );
+
+    let mut ctx = program_test.start_with_context().await;
+
+    let parent_owner = Keypair::new();
+    let registrant = Keypair::new();
+    let space = 100usize;
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    let clock = ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+    let expires_at = clock.unix_timestamp + 100;
+    let name_instruction_data = |name: &str, parent: Option<&Pubkey>, expires_at: Option<i64>| {
+        let hashed_name: Vec<u8> = hashv(&[(HASH_PREFIX.to_owned() + name).as_bytes()])
+            .as_ref()
+            .to_vec();
+        let (name_account_key, _) =
+            get_seeds_and_key(&program_id, hashed_name.clone(), None, parent);
+        let instruction_data = if let Some(expires_at) = expires_at {
+            NameRegistryInstruction::CreateWithExpiry {
+                hashed_name,
+                lamports: rent.minimum_balance(space.saturating_add(EXPIRING_HEADER_LEN)),
+                space: space as u32,
+                expires_at,
+            }
+        } else {
+            NameRegistryInstruction::Create {
+                hashed_name,
+                lamports: rent.minimum_balance(space.saturating_add(NameRecordHeader::LEN)),
+                space: space as u32,
+            }
+        };
+        (name_account_key, instruction_data)
+    };
+
+    let (parent_name_key, instruction_data) = name_instruction_data(".team", None, None);
+    let create_parent_instruction = create(
+        program_id,
+        instruction_data,
+        parent_name_key,
+        ctx.payer.pubkey(),
+        parent_owner.pubkey(),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    sign_send_instruction(&mut ctx, create_parent_instruction, vec![])
+        .await
+        .unwrap();
+    // Fund the parent owner so that it can receive lamport fees
+    let fund_instruction = system_instruction::transfer(
+        &ctx.payer.pubkey(),
+        &parent_owner.pubkey(),
+        rent.minimum_balance(0),
+    );
+    sign_send_instruction(&mut ctx, fund_instruction, vec![])
+        .await
+        .unwrap();
+
+    let create_subdomain_instruction =
+        |name: &str,
+         payer: Pubkey,
+         token_fee_accounts_opt: Option<(Pubkey, Pubkey)>,
+         expires_at: Option<i64>| {
+            let (name_account_key, instruction_data) =
+                name_instruction_data(name, Some(&parent_name_key), expires_at);
+            create_with_subdomain_policy(
+                program_id,
+                instruction_data,
+                name_account_key,
+                payer,
+                registrant.pubkey(),
+                None,
+                parent_name_key,
+                parent_owner.pubkey(),
+                token_fee_accounts_opt,
+            )
+            .unwrap()
+        };
+    let set_policy_instruction = |payer: Pubkey, policy: SubdomainPolicy| {
+        set_subdomain_policy(
+            program_id,
+            payer,
+            parent_name_key,
+            parent_owner.pubkey(),
+            policy,
+        )
+        .unwrap()
+    };
+    let payer_key = ctx.payer.pubkey();
+
+    // Without a policy, the parent owner must sign
+    sign_send_instruction(
+        &mut ctx,
+        create_subdomain_instruction("alice", payer_key, None, None),
+        vec![&registrant],
+    )
+    .await
+    .unwrap_err();
+
+    // Only the parent owner can set the policy
+    let mut wrong_owner_instruction = set_policy_instruction(payer_key, SubdomainPolicy::Open);
+    wrong_owner_instruction.accounts[3].pubkey = registrant.pubkey();
+    sign_send_instruction(&mut ctx, wrong_owner_instruction, vec![&registrant])
+        .await
+        .unwrap_err();
+
+    sign_send_instruction(
+        &mut ctx,
+        set_policy_instruction(payer_key, SubdomainPolicy::Open),
+        vec![&parent_owner],
+    )
+    .await
+    .unwrap();
+    // update blockhash to prevent losing txn to dedup with the failed one above
+    ctx.last_blockhash = ctx
+        .banks_client
+        .get_new_latest_blockhash(&ctx.last_blockhash)
+        .await
+        .unwrap();
+    sign_send_instruction(
+        &mut ctx,
+        create_subdomain_instruction("alice", payer_key, None, None),
+        vec![&registrant],
+    )
+    .await
+    .unwrap();
+
+    // Allowlisted registration
+    sign_send_instruction(
+        &mut ctx,
+        set_policy_instruction(
+            payer_key,
+            SubdomainPolicy::Allowlist(vec![Pubkey::new_unique()]),
+        ),
+        vec![&parent_owner],
+    )
+    .await
+    .unwrap();
+    sign_send_instruction(
+        &mut ctx,
+        create_subdomain_instruction("bob", payer_key, None, None),
+        vec![&registrant],
+    )
+    .await
+    .unwrap_err();
+    sign_send_instruction(
+        &mut ctx,
+        set_policy_instruction(
+            payer_key,
+            SubdomainPolicy::Allowlist(vec![Pubkey::new_unique(), registrant.pubkey()]),
+        ),
+        vec![&parent_owner],
+    )
+    .await
+    .unwrap();
+    ctx.last_blockhash = ctx
+        .banks_client
+        .get_new_latest_blockhash(&ctx.last_blockhash)
+        .await
+        .unwrap();
+    sign_send_instruction(
+        &mut ctx,
+        create_subdomain_instruction("bob", payer_key, None, None),
+        vec![&registrant],
+    )
+    .await
+    .unwrap();
+
+    // Registration priced in lamports
+    let price = 1_000_000;
+    sign_send_instruction(
+        &mut ctx,
+        set_policy_instruction(payer_key, SubdomainPolicy::Priced { mint: None, price }),
+        vec![&parent_owner],
+    )
+    .await
+    .unwrap();
+    let parent_owner_balance = ctx
+        .banks_client
+        .get_balance(parent_owner.pubkey())
+        .await
+        .unwrap();
+    // Priced subdomains must expire, so that their fee is paid again to renew them
+    sign_send_instruction(
+        &mut ctx,
+        create_subdomain_instruction("carol", payer_key, None, None),
+        vec![&registrant],
+    )
+    .await
+    .unwrap_err();
+    sign_send_instruction(
+        &mut ctx,
+        create_subdomain_instruction("carol", payer_key, None, Some(expires_at)),
+        vec![&registrant],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        ctx.banks_client
+            .get_balance(parent_owner.pubkey())
+            .await
+            .unwrap(),
+        parent_owner_balance + price
+    );
+
+    // Registration priced in SPL tokens
+    let mint = Keypair::new();
+    let source = Keypair::new();
+    let destination = Keypair::new();
+    let mint_rent = rent.minimum_balance(spl_token::state::Mint::LEN);
+    let account_rent = rent.minimum_balance(spl_token::state::Account::LEN);
+    let setup_instructions = [
+        system_instruction::create_account(
+            &payer_key,
+            &mint.pubkey(),
+            mint_rent,
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &payer_key,
+            None,
+            0,
+        )
+        .unwrap(),
+        system_instruction::create_account(
+            &payer_key,
+            &source.pubkey(),
+            account_rent,
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &source.pubkey(),
+            &mint.pubkey(),
+            &payer_key,
+        )
+        .unwrap(),
+        system_instruction::create_account(
+            &payer_key,
+            &destination.pubkey(),
+            account_rent,
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account(
+            &spl_token::id(),
+            &destination.pubkey(),
+            &mint.pubkey(),
+            &parent_owner.pubkey(),
+        )
+        .unwrap(),
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &source.pubkey(),
+            &payer_key,
+            &[],
+            100,
+        )
+        .unwrap(),
+    ];
+    let mut transaction = Transaction::new_with_payer(&setup_instructions, Some(&payer_key));
+    transaction.sign(
+        &[&ctx.payer, &mint, &source, &destination],
+        ctx.last_blockhash,
+    );
+    ctx.banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let price = 42;
+    // The fee mint must be an SPL Token mint
+    sign_send_instruction(
+        &mut ctx,
+        set_policy_instruction(
+            payer_key,
+            SubdomainPolicy::Priced {
+                mint: Some(source.pubkey()),
+                price,
+            },
+        ),
+        vec![&parent_owner],
+    )
+    .await
+    .unwrap_err();
+    sign_send_instruction(
+        &mut ctx,
+        set_policy_instruction(
+            payer_key,
+            SubdomainPolicy::Priced {
+                mint: Some(Pubkey::new_unique()),
+                price,
+            },
+        ),
+        vec![&parent_owner],
+    )
+    .await
+    .unwrap_err();
+    sign_send_instruction(
+        &mut ctx,
+        set_policy_instruction(
+            payer_key,
+            SubdomainPolicy::Priced {
+                mint: Some(mint.pubkey()),
+                price,
+            },
+        ),
+        vec![&parent_owner],
+    )
+    .await
+    .unwrap();
+    // The fee must go to the parent owner
+    sign_send_instruction(
+        &mut ctx,
+        create_subdomain_instruction(
+            "dave",
+            payer_key,
+            Some((source.pubkey(), source.pubkey())),
+            Some(expires_at),
+        ),
+        vec![&registrant],
+    )
+    .await
+    .unwrap_err();
+    sign_send_instruction(
+        &mut ctx,
+        create_subdomain_instruction(
+            "dave",
+            payer_key,
+            Some((source.pubkey(), destination.pubkey())),
+            Some(expires_at),
+        ),
+        vec![&registrant],
+    )
+    .await
+    .unwrap();
+    let destination_account = spl_token::state::Account::unpack(
+        &ctx.banks_client
+            .get_account(destination.pubkey())
+            .await
+            .unwrap()
+            .unwrap()
+            .data,
+    )
+    .unwrap();
+    assert_eq!(destination_account.amount, price);
+
+    // Closing registration again
+    sign_send_instruction(
+        &mut ctx,
+        set_policy_instruction(payer_key, SubdomainPolicy::Closed),
+        vec![&parent_owner],
+    )
+    .await
+    .unwrap();
+    sign_send_instruction(
+        &mut ctx,
+        create_subdomain_instruction("erin", payer_key, None, None),
+        vec![&registrant],
+    )
+    .await
+    .unwrap_err();
+}
+
 // Utils
+pub async fn resolve_reverse_record(
+    ctx: &mut ProgramTestContext,
+    reverse_record_key: Pubkey,
+    owner: &Keypair,
+    class: &Keypair,
+) -> Option<Pubkey> {
+    let reverse_record = ReverseRecord::unpack_from_slice(
+        &ctx.banks_client
+            .get_account(reverse_record_key)
+            .await
+            .unwrap()?
+            .data,
+    )
+    .unwrap();
+    let name_record_data = ctx
+        .banks_client
+        .get_account(reverse_record.name_account)
+        .await
+        .unwrap()
+        .map(|account| account.data)
+        .unwrap_or_default();
+    reverse_record.resolve(&owner.pubkey(), &class.pubkey(), &name_record_data)
+}
+
 pub async fn sign_send_instruction(
     ctx: &mut ProgramTestContext,
     instruction: Instruction,
@@ -14,6 +14,26 @@ pub enum RecordError {
     /// Calculation overflow
     #[error("Calculation overflow")]
     Overflow,
+
+    /// Record has been finalized and can no longer be modified
+    #[error("Record has been finalized and can no longer be modified")]
+    RecordFinalized,
+
+    /// Write to an append-only record does not start at the end of its data
+    #[error("Write to an append-only record does not start at the end of its data")]
+    NonSequentialWrite,
+
+    /// Record is not in append-only mode
+    #[error("Record is not in append-only mode")]
+    NotAppendOnly,
+
+    /// Provided hash does not match the record data
+    #[error("Provided hash does not match the record data")]
+    HashMismatch,
+
+    /// Reallocation would truncate data already appended to the record
+    #[error("Reallocation would truncate data already appended to the record")]
+    AppendedDataTruncated,
 }
 impl From<RecordError> for ProgramError {
     fn from(e: RecordError) -> Self {
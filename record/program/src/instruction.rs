@@ -1,6 +1,6 @@
 //! Program instructions
 
-use crate::id;
+use crate::{id, state::record_data_hash};
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     instruction::{AccountMeta, Instruction},
@@ -40,7 +40,8 @@ pub enum RecordInstruction {
     /// 2. `[]` New record authority
     SetAuthority,
 
-    /// Close the provided record account, draining lamports to recipient account
+    /// Close the provided record account, draining lamports to recipient account.
+    /// Finalized append-only records cannot be closed.
     ///
     /// Accounts expected by this instruction:
     ///
@@ -48,8 +49,57 @@ pub enum RecordInstruction {
     /// 1. `[signer]` Record authority
     /// 2. `[]` Receiver of account lamports
     CloseAccount,
+
+    /// Resize the record data of the provided record account. Growing the
+    /// account requires that it already holds enough lamports to stay
+    /// rent-exempt at the new size, and is limited by the runtime to 10KiB per
+    /// instruction. Any lamports above the rent-exempt minimum at the new size,
+    /// such as those freed by shrinking the account, go to the receiver.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Record account, must be previously initialized
+    /// 1. `[signer]` Record authority
+    /// 2. `[writable]` Receiver of excess lamports
+    Reallocate {
+        /// New length of the record data, excluding the header
+        data_length: u64,
+    },
+
+    /// Create a new append-only record. Writes must start exactly at the end
+    /// of the data appended so far, and the record may later be finalized.
+    /// Append-only records use a separate account version, with the
+    /// append-only state stored before the record data, so the account must
+    /// be sized with `RecordData::append_only_account_length`.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Record account, must be uninitialized
+    /// 1. `[]` Record authority
+    InitializeAppendOnly,
+
+    /// Check the data appended to an append-only record against a hash and
+    /// freeze the record, so that it can no longer be written, reallocated,
+    /// given a new authority, or closed.
+    ///
+    /// The hash of the appended data is computed on-chain, so a record too
+    /// large to hash within the compute budget of a transaction cannot be
+    /// finalized.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Record account, must be previously initialized as append-only
+    /// 1. `[signer]` Record authority
+    Finalize {
+        /// SHA-256 hash of all appended data, see `state::record_data_hash`
+        hash: [u8; 32],
+    },
 }
 
+/// Maximum data length for a single `Write`, so that it fits in a transaction
+/// signed by both a fee payer and the record authority
+pub const MAX_WRITE_CHUNK_SIZE: usize = 900;
+
 /// Create a `RecordInstruction::Initialize` instruction
 pub fn initialize(record_account: &Pubkey, authority: &Pubkey) -> Instruction {
     Instruction::new_with_borsh(
@@ -104,10 +154,83 @@ pub fn close_account(record_account: &Pubkey, signer: &Pubkey, receiver: &Pubkey
     )
 }
 
+/// Create a `RecordInstruction::Reallocate` instruction
+pub fn reallocate(
+    record_account: &Pubkey,
+    signer: &Pubkey,
+    receiver: &Pubkey,
+    data_length: u64,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        id(),
+        &RecordInstruction::Reallocate { data_length },
+        vec![
+            AccountMeta::new(*record_account, false),
+            AccountMeta::new_readonly(*signer, true),
+            AccountMeta::new(*receiver, false),
+        ],
+    )
+}
+
+/// Create a `RecordInstruction::InitializeAppendOnly` instruction
+pub fn initialize_append_only(record_account: &Pubkey, authority: &Pubkey) -> Instruction {
+    Instruction::new_with_borsh(
+        id(),
+        &RecordInstruction::InitializeAppendOnly,
+        vec![
+            AccountMeta::new(*record_account, false),
+            AccountMeta::new_readonly(*authority, false),
+        ],
+    )
+}
+
+/// Create a `RecordInstruction::Finalize` instruction
+pub fn finalize(record_account: &Pubkey, signer: &Pubkey, hash: [u8; 32]) -> Instruction {
+    Instruction::new_with_borsh(
+        id(),
+        &RecordInstruction::Finalize { hash },
+        vec![
+            AccountMeta::new(*record_account, false),
+            AccountMeta::new_readonly(*signer, true),
+        ],
+    )
+}
+
+/// Create `RecordInstruction::Write` instructions that write `data` starting
+/// at `offset`, split into chunks of at most `MAX_WRITE_CHUNK_SIZE` bytes.
+/// Each instruction should be sent in its own transaction, in order.
+pub fn write_chunks(
+    record_account: &Pubkey,
+    signer: &Pubkey,
+    offset: u64,
+    data: &[u8],
+) -> Vec<Instruction> {
+    data.chunks(MAX_WRITE_CHUNK_SIZE)
+        .scan(offset, |chunk_offset, chunk| {
+            let instruction = write(record_account, signer, *chunk_offset, chunk.to_vec());
+            *chunk_offset = chunk_offset.saturating_add(chunk.len() as u64);
+            Some(instruction)
+        })
+        .collect()
+}
+
+/// Create all instructions to upload `data` to a new, already initialized
+/// append-only record and finalize it. Each instruction should be sent in its
+/// own transaction, in order.
+pub fn upload_append_only(
+    record_account: &Pubkey,
+    signer: &Pubkey,
+    data: &[u8],
+) -> Vec<Instruction> {
+    let mut instructions = write_chunks(record_account, signer, 0, data);
+    instructions.push(finalize(record_account, signer, record_data_hash(data)));
+    instructions
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::state::tests::TEST_DATA;
+    use crate::state::tests::TEST_BYTES;
     use solana_program::program_error::ProgramError;
 
     #[test]
@@ -123,7 +246,7 @@ mod tests {
 
     #[test]
     fn serialize_write() {
-        let data = TEST_DATA.try_to_vec().unwrap();
+        let data = TEST_BYTES.to_vec();
         let offset = 0u64;
         let instruction = RecordInstruction::Write {
             offset: 0,
@@ -161,10 +284,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn serialize_reallocate() {
+        let data_length = 16u64;
+        let instruction = RecordInstruction::Reallocate { data_length };
+        let mut expected = vec![4];
+        expected.extend_from_slice(&data_length.to_le_bytes());
+        assert_eq!(instruction.try_to_vec().unwrap(), expected);
+        assert_eq!(
+            RecordInstruction::try_from_slice(&expected).unwrap(),
+            instruction
+        );
+    }
+
+    #[test]
+    fn serialize_initialize_append_only() {
+        let instruction = RecordInstruction::InitializeAppendOnly;
+        let expected = vec![5];
+        assert_eq!(instruction.try_to_vec().unwrap(), expected);
+        assert_eq!(
+            RecordInstruction::try_from_slice(&expected).unwrap(),
+            instruction
+        );
+    }
+
+    #[test]
+    fn serialize_finalize() {
+        let hash = [7; 32];
+        let instruction = RecordInstruction::Finalize { hash };
+        let mut expected = vec![6];
+        expected.extend_from_slice(&hash);
+        assert_eq!(instruction.try_to_vec().unwrap(), expected);
+        assert_eq!(
+            RecordInstruction::try_from_slice(&expected).unwrap(),
+            instruction
+        );
+    }
+
+    #[test]
+    fn write_chunks_are_sequential() {
+        let record_account = Pubkey::new_unique();
+        let signer = Pubkey::new_unique();
+        let data = vec![1; MAX_WRITE_CHUNK_SIZE * 2 + 1];
+        let instructions = write_chunks(&record_account, &signer, 10, &data);
+        assert_eq!(instructions.len(), 3);
+
+        let mut expected_offset = 10;
+        let mut uploaded = vec![];
+        for instruction in instructions {
+            match RecordInstruction::try_from_slice(&instruction.data).unwrap() {
+                RecordInstruction::Write { offset, data } => {
+                    assert_eq!(offset, expected_offset);
+                    assert!(data.len() <= MAX_WRITE_CHUNK_SIZE);
+                    expected_offset += data.len() as u64;
+                    uploaded.extend(data);
+                }
+                _ => panic!("expected write instruction"),
+            }
+        }
+        assert_eq!(uploaded, data);
+    }
+
     #[test]
     fn deserialize_invalid_instruction() {
         let mut expected = vec![12];
-        expected.append(&mut TEST_DATA.try_to_vec().unwrap());
+        expected.extend_from_slice(&TEST_BYTES);
         let err: ProgramError = RecordInstruction::try_from_slice(&expected)
             .unwrap_err()
             .into();
@@ -4,7 +4,7 @@ use {
     crate::{
         error::RecordError,
         instruction::RecordInstruction,
-        state::{Data, RecordData},
+        state::{record_data_hash, AppendOnlyState, RecordData},
     },
     borsh::BorshDeserialize,
     solana_program::{
@@ -14,6 +14,8 @@ use {
         program_error::ProgramError,
         program_pack::IsInitialized,
         pubkey::Pubkey,
+        rent::Rent,
+        sysvar::Sysvar,
     },
 };
 
@@ -29,6 +31,45 @@ fn check_authority(authority_info: &AccountInfo, expected_authority: &Pubkey) ->
     Ok(())
 }
 
+/// Get the append-only state of the record, if any, failing if it was finalized
+fn check_not_finalized(
+    account_data: &RecordData,
+    raw_data: &[u8],
+) -> Result<Option<AppendOnlyState>, ProgramError> {
+    if !account_data.is_append_only() {
+        return Ok(None);
+    }
+    let append_only_state = AppendOnlyState::unpack(raw_data)?;
+    if append_only_state.finalized {
+        msg!("Record has been finalized");
+        return Err(RecordError::RecordFinalized.into());
+    }
+    Ok(Some(append_only_state))
+}
+
+fn process_initialize(accounts: &[AccountInfo], version: u8) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let data_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+
+    let (mut account_data, _) = RecordData::unpack(&data_info.data.borrow())?;
+    if account_data.is_initialized() {
+        msg!("Record account already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    account_data.authority = *authority_info.key;
+    account_data.version = version;
+    if account_data.is_append_only() {
+        AppendOnlyState::default().pack_into(&mut data_info.data.borrow_mut())?;
+    }
+    borsh::to_writer(
+        &mut data_info.data.borrow_mut()[..RecordData::WRITABLE_START_INDEX],
+        &account_data,
+    )
+    .map_err(|e| e.into())
+}
+
 /// Instruction processor
 pub fn process_instruction(
     _program_id: &Pubkey,
@@ -41,40 +82,51 @@ pub fn process_instruction(
     match instruction {
         RecordInstruction::Initialize => {
             msg!("RecordInstruction::Initialize");
+            process_initialize(accounts, RecordData::CURRENT_VERSION)
+        }
 
-            let data_info = next_account_info(account_info_iter)?;
-            let authority_info = next_account_info(account_info_iter)?;
-
-            let mut account_data = RecordData::try_from_slice(*data_info.data.borrow())?;
-            if account_data.is_initialized() {
-                msg!("Record account already initialized");
-                return Err(ProgramError::AccountAlreadyInitialized);
-            }
-
-            account_data.authority = *authority_info.key;
-            account_data.version = RecordData::CURRENT_VERSION;
-            borsh::to_writer(&mut data_info.data.borrow_mut()[..], &account_data)
-                .map_err(|e| e.into())
+        RecordInstruction::InitializeAppendOnly => {
+            msg!("RecordInstruction::InitializeAppendOnly");
+            process_initialize(accounts, RecordData::APPEND_ONLY_VERSION)
         }
 
         RecordInstruction::Write { offset, data } => {
             msg!("RecordInstruction::Write");
             let data_info = next_account_info(account_info_iter)?;
             let authority_info = next_account_info(account_info_iter)?;
-            let account_data = RecordData::try_from_slice(&data_info.data.borrow())?;
+            let (account_data, _) = RecordData::unpack(&data_info.data.borrow())?;
             if !account_data.is_initialized() {
                 msg!("Record account not initialized");
                 return Err(ProgramError::UninitializedAccount);
             }
             check_authority(authority_info, &account_data.authority)?;
-            let start = RecordData::WRITABLE_START_INDEX.saturating_add(offset as usize);
+            let append_only_state = check_not_finalized(&account_data, &data_info.data.borrow())?;
+            if let Some(append_only_state) = append_only_state {
+                if offset != append_only_state.appended_length {
+                    msg!(
+                        "Append must start at offset {}, received {}",
+                        append_only_state.appended_length,
+                        offset
+                    );
+                    return Err(RecordError::NonSequentialWrite.into());
+                }
+            }
+            let start = account_data
+                .writable_start_index()
+                .saturating_add(offset as usize);
             let end = start.saturating_add(data.len());
             if end > data_info.data.borrow().len() {
-                Err(ProgramError::AccountDataTooSmall)
-            } else {
-                data_info.data.borrow_mut()[start..end].copy_from_slice(&data);
-                Ok(())
+                return Err(ProgramError::AccountDataTooSmall);
+            }
+            data_info.data.borrow_mut()[start..end].copy_from_slice(&data);
+
+            if let Some(mut append_only_state) = append_only_state {
+                append_only_state.appended_length = offset
+                    .checked_add(data.len() as u64)
+                    .ok_or(RecordError::Overflow)?;
+                append_only_state.pack_into(&mut data_info.data.borrow_mut())?;
             }
+            Ok(())
         }
 
         RecordInstruction::SetAuthority => {
@@ -82,15 +134,19 @@ pub fn process_instruction(
             let data_info = next_account_info(account_info_iter)?;
             let authority_info = next_account_info(account_info_iter)?;
             let new_authority_info = next_account_info(account_info_iter)?;
-            let mut account_data = RecordData::try_from_slice(&data_info.data.borrow())?;
+            let (mut account_data, _) = RecordData::unpack(&data_info.data.borrow())?;
             if !account_data.is_initialized() {
                 msg!("Record account not initialized");
                 return Err(ProgramError::UninitializedAccount);
             }
             check_authority(authority_info, &account_data.authority)?;
+            check_not_finalized(&account_data, &data_info.data.borrow())?;
             account_data.authority = *new_authority_info.key;
-            borsh::to_writer(&mut data_info.data.borrow_mut()[..], &account_data)
-                .map_err(|e| e.into())
+            borsh::to_writer(
+                &mut data_info.data.borrow_mut()[..RecordData::WRITABLE_START_INDEX],
+                &account_data,
+            )
+            .map_err(|e| e.into())
         }
 
         RecordInstruction::CloseAccount => {
@@ -98,21 +154,106 @@ pub fn process_instruction(
             let data_info = next_account_info(account_info_iter)?;
             let authority_info = next_account_info(account_info_iter)?;
             let destination_info = next_account_info(account_info_iter)?;
-            let mut account_data = RecordData::try_from_slice(&data_info.data.borrow())?;
+            let (account_data, _) = RecordData::unpack(&data_info.data.borrow())?;
             if !account_data.is_initialized() {
                 msg!("Record not initialized");
                 return Err(ProgramError::UninitializedAccount);
             }
             check_authority(authority_info, &account_data.authority)?;
+            check_not_finalized(&account_data, &data_info.data.borrow())?;
             let destination_starting_lamports = destination_info.lamports();
             let data_lamports = data_info.lamports();
             **data_info.lamports.borrow_mut() = 0;
             **destination_info.lamports.borrow_mut() = destination_starting_lamports
                 .checked_add(data_lamports)
                 .ok_or(RecordError::Overflow)?;
-            account_data.data = Data::default();
-            borsh::to_writer(&mut data_info.data.borrow_mut()[..], &account_data)
-                .map_err(|e| e.into())
+            data_info.data.borrow_mut()[RecordData::WRITABLE_START_INDEX..].fill(0);
+            Ok(())
+        }
+
+        RecordInstruction::Reallocate { data_length } => {
+            msg!("RecordInstruction::Reallocate");
+            let data_info = next_account_info(account_info_iter)?;
+            let authority_info = next_account_info(account_info_iter)?;
+            let receiver_info = next_account_info(account_info_iter)?;
+            let (account_data, _) = RecordData::unpack(&data_info.data.borrow())?;
+            if !account_data.is_initialized() {
+                msg!("Record account not initialized");
+                return Err(ProgramError::UninitializedAccount);
+            }
+            check_authority(authority_info, &account_data.authority)?;
+            let append_only_state = check_not_finalized(&account_data, &data_info.data.borrow())?;
+            if let Some(append_only_state) = append_only_state {
+                if data_length < append_only_state.appended_length {
+                    msg!(
+                        "Record has {} bytes of appended data, cannot shrink to {}",
+                        append_only_state.appended_length,
+                        data_length
+                    );
+                    return Err(RecordError::AppendedDataTruncated.into());
+                }
+            }
+
+            let account_length = usize::try_from(data_length)
+                .ok()
+                .and_then(|data_length| {
+                    account_data.writable_start_index().checked_add(data_length)
+                })
+                .ok_or(RecordError::Overflow)?;
+            data_info.realloc(account_length, true)?;
+
+            let rent = Rent::get()?;
+            let minimum_balance = rent.minimum_balance(account_length);
+            let data_lamports = data_info.lamports();
+            if data_lamports < minimum_balance {
+                msg!(
+                    "Record account needs {} lamports to be rent-exempt, has {}",
+                    minimum_balance,
+                    data_lamports
+                );
+                return Err(ProgramError::AccountNotRentExempt);
+            }
+
+            // return lamports freed up by shrinking the account
+            let excess_lamports = data_lamports.saturating_sub(minimum_balance);
+            if excess_lamports > 0 && receiver_info.key != data_info.key {
+                let receiver_starting_lamports = receiver_info.lamports();
+                **data_info.lamports.borrow_mut() = minimum_balance;
+                **receiver_info.lamports.borrow_mut() = receiver_starting_lamports
+                    .checked_add(excess_lamports)
+                    .ok_or(RecordError::Overflow)?;
+            }
+            Ok(())
+        }
+
+        RecordInstruction::Finalize { hash } => {
+            msg!("RecordInstruction::Finalize");
+            let data_info = next_account_info(account_info_iter)?;
+            let authority_info = next_account_info(account_info_iter)?;
+            let (account_data, _) = RecordData::unpack(&data_info.data.borrow())?;
+            if !account_data.is_initialized() {
+                msg!("Record account not initialized");
+                return Err(ProgramError::UninitializedAccount);
+            }
+            check_authority(authority_info, &account_data.authority)?;
+            if !account_data.is_append_only() {
+                msg!("Only append-only records can be finalized");
+                return Err(RecordError::NotAppendOnly.into());
+            }
+            let mut append_only_state =
+                check_not_finalized(&account_data, &data_info.data.borrow())?
+                    .ok_or(RecordError::NotAppendOnly)?;
+            {
+                let raw_data = data_info.data.borrow();
+                let (_, data) = RecordData::unpack(&raw_data)?;
+                if record_data_hash(append_only_state.appended_data(data)?) != hash {
+                    msg!("Provided hash does not match the appended data");
+                    return Err(RecordError::HashMismatch.into());
+                }
+            }
+
+            append_only_state.finalized = true;
+            append_only_state.pack_into(&mut data_info.data.borrow_mut())
         }
     }
 }
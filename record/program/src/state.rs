@@ -1,10 +1,13 @@
 //! Program state
 use {
     borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
-    solana_program::{program_pack::IsInitialized, pubkey::Pubkey},
+    solana_program::{
+        hash::hash, program_error::ProgramError, program_pack::IsInitialized, pubkey::Pubkey,
+    },
 };
 
-/// Struct wrapping data and providing metadata
+/// Header of a record account, followed by the record data itself, which may
+/// be of any length
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
 pub struct RecordData {
     /// Struct version, allows for upgrades to the program
@@ -12,64 +15,137 @@ pub struct RecordData {
 
     /// The account allowed to update the data
     pub authority: Pubkey,
-
-    /// The data contained by the account, could be anything serializable
-    pub data: Data,
-}
-
-/// Struct just for data
-#[derive(Clone, Debug, Default, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
-pub struct Data {
-    /// The data contained by the account, could be anything or serializable
-    pub bytes: [u8; Self::DATA_SIZE],
-}
-
-impl Data {
-    /// very small data for easy testing
-    pub const DATA_SIZE: usize = 8;
 }
 
 impl RecordData {
     /// Version to fill in on new created accounts
     pub const CURRENT_VERSION: u8 = 1;
 
+    /// Version of append-only records, which store an `AppendOnlyState`
+    /// between the header and the record data
+    pub const APPEND_ONLY_VERSION: u8 = 2;
+
     /// Start of writable account data, after version and authority
     pub const WRITABLE_START_INDEX: usize = 33;
+
+    /// Start of writable account data in append-only records, after version,
+    /// authority, and append-only state
+    pub const APPEND_ONLY_WRITABLE_START_INDEX: usize = 42;
+
+    /// Size of an account holding `data_length` bytes of record data
+    pub fn account_length(data_length: usize) -> Option<usize> {
+        Self::WRITABLE_START_INDEX.checked_add(data_length)
+    }
+
+    /// Size of an append-only account holding `data_length` bytes of record
+    /// data
+    pub fn append_only_account_length(data_length: usize) -> Option<usize> {
+        Self::APPEND_ONLY_WRITABLE_START_INDEX.checked_add(data_length)
+    }
+
+    /// Whether the record was initialized as append-only
+    pub fn is_append_only(&self) -> bool {
+        self.version == Self::APPEND_ONLY_VERSION
+    }
+
+    /// Start of writable account data for this record's version
+    pub fn writable_start_index(&self) -> usize {
+        if self.is_append_only() {
+            Self::APPEND_ONLY_WRITABLE_START_INDEX
+        } else {
+            Self::WRITABLE_START_INDEX
+        }
+    }
+
+    /// Split raw account data into the record header and the record data
+    pub fn unpack(account_data: &[u8]) -> Result<(Self, &[u8]), ProgramError> {
+        if account_data.len() < Self::WRITABLE_START_INDEX {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        let record = Self::try_from_slice(&account_data[..Self::WRITABLE_START_INDEX])?;
+        let data = account_data
+            .get(record.writable_start_index()..)
+            .ok_or(ProgramError::AccountDataTooSmall)?;
+        Ok((record, data))
+    }
 }
 
 impl IsInitialized for RecordData {
     /// Is initialized
     fn is_initialized(&self) -> bool {
-        self.version == Self::CURRENT_VERSION
+        self.version == Self::CURRENT_VERSION || self.version == Self::APPEND_ONLY_VERSION
+    }
+}
+
+/// State of an append-only record, stored after the record header
+#[derive(Clone, Copy, Debug, Default, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
+pub struct AppendOnlyState {
+    /// Whether the appended data was verified against a hash, after which the
+    /// record can no longer change
+    pub finalized: bool,
+
+    /// Length of the data appended so far
+    pub appended_length: u64,
+}
+
+impl AppendOnlyState {
+    /// Read the append-only state from the raw data of an append-only record
+    pub fn unpack(account_data: &[u8]) -> Result<Self, ProgramError> {
+        let state_data = account_data
+            .get(RecordData::WRITABLE_START_INDEX..RecordData::APPEND_ONLY_WRITABLE_START_INDEX)
+            .ok_or(ProgramError::AccountDataTooSmall)?;
+        Self::try_from_slice(state_data).map_err(|e| e.into())
+    }
+
+    /// Write the append-only state into the raw data of an append-only record
+    pub fn pack_into(&self, account_data: &mut [u8]) -> Result<(), ProgramError> {
+        let state_data = account_data
+            .get_mut(RecordData::WRITABLE_START_INDEX..RecordData::APPEND_ONLY_WRITABLE_START_INDEX)
+            .ok_or(ProgramError::AccountDataTooSmall)?;
+        borsh::to_writer(state_data, self).map_err(|e| e.into())
     }
+
+    /// Get the part of the record data appended so far
+    pub fn appended_data<'a>(&self, data: &'a [u8]) -> Result<&'a [u8], ProgramError> {
+        usize::try_from(self.appended_length)
+            .ok()
+            .and_then(|length| data.get(..length))
+            .ok_or(ProgramError::InvalidAccountData)
+    }
+}
+
+/// SHA-256 hash of record data, as expected by `RecordInstruction::Finalize`
+pub fn record_data_hash(data: &[u8]) -> [u8; 32] {
+    hash(data).to_bytes()
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use solana_program::program_error::ProgramError;
 
     /// Version for tests
     pub const TEST_VERSION: u8 = 1;
     /// Pubkey for tests
     pub const TEST_PUBKEY: Pubkey = Pubkey::new_from_array([100; 32]);
     /// Bytes for tests
-    pub const TEST_BYTES: [u8; Data::DATA_SIZE] = [42; Data::DATA_SIZE];
-    /// Data for tests
-    pub const TEST_DATA: Data = Data { bytes: TEST_BYTES };
+    pub const TEST_BYTES: [u8; 8] = [42; 8];
     /// RecordData for tests
     pub const TEST_RECORD_DATA: RecordData = RecordData {
         version: TEST_VERSION,
         authority: TEST_PUBKEY,
-        data: TEST_DATA,
+    };
+    /// AppendOnlyState for tests
+    pub const TEST_APPEND_ONLY_STATE: AppendOnlyState = AppendOnlyState {
+        finalized: false,
+        appended_length: 3,
     };
 
     #[test]
     fn serialize_data() {
         let mut expected = vec![TEST_VERSION];
         expected.extend_from_slice(&TEST_PUBKEY.to_bytes());
-        expected.extend_from_slice(&TEST_DATA.bytes);
         assert_eq!(TEST_RECORD_DATA.try_to_vec().unwrap(), expected);
+        assert_eq!(expected.len(), RecordData::WRITABLE_START_INDEX);
         assert_eq!(
             RecordData::try_from_slice(&expected).unwrap(),
             TEST_RECORD_DATA
@@ -77,11 +153,76 @@ pub mod tests {
     }
 
     #[test]
-    fn deserialize_invalid_slice() {
-        let data = [200; Data::DATA_SIZE - 1];
+    fn unpack_variable_length() {
         let mut expected = vec![TEST_VERSION];
         expected.extend_from_slice(&TEST_PUBKEY.to_bytes());
-        expected.extend_from_slice(&data);
+        assert_eq!(
+            RecordData::unpack(&expected).unwrap(),
+            (TEST_RECORD_DATA, &[][..])
+        );
+
+        expected.extend_from_slice(&TEST_BYTES);
+        assert_eq!(
+            RecordData::unpack(&expected).unwrap(),
+            (TEST_RECORD_DATA, &TEST_BYTES[..])
+        );
+        assert_eq!(
+            RecordData::account_length(TEST_BYTES.len()),
+            Some(expected.len())
+        );
+    }
+
+    #[test]
+    fn unpack_append_only() {
+        let record = RecordData {
+            version: RecordData::APPEND_ONLY_VERSION,
+            authority: TEST_PUBKEY,
+        };
+        let mut expected = record.try_to_vec().unwrap();
+        expected.extend_from_slice(&TEST_APPEND_ONLY_STATE.try_to_vec().unwrap());
+        assert_eq!(expected.len(), RecordData::APPEND_ONLY_WRITABLE_START_INDEX);
+        expected.extend_from_slice(&TEST_BYTES);
+
+        let (unpacked, data) = RecordData::unpack(&expected).unwrap();
+        assert_eq!(unpacked, record);
+        assert_eq!(data, &TEST_BYTES[..]);
+        assert_eq!(
+            RecordData::append_only_account_length(TEST_BYTES.len()),
+            Some(expected.len())
+        );
+
+        let state = AppendOnlyState::unpack(&expected).unwrap();
+        assert_eq!(state, TEST_APPEND_ONLY_STATE);
+        assert_eq!(state.appended_data(data).unwrap(), &TEST_BYTES[..3]);
+
+        let finalized = AppendOnlyState {
+            finalized: true,
+            appended_length: TEST_BYTES.len() as u64 + 1,
+        };
+        finalized.pack_into(&mut expected).unwrap();
+        assert_eq!(AppendOnlyState::unpack(&expected).unwrap(), finalized);
+        assert_eq!(
+            finalized
+                .appended_data(&expected[RecordData::APPEND_ONLY_WRITABLE_START_INDEX..])
+                .unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+
+        // the header alone is not enough for an append-only record
+        assert_eq!(
+            RecordData::unpack(&expected[..RecordData::WRITABLE_START_INDEX]).unwrap_err(),
+            ProgramError::AccountDataTooSmall
+        );
+    }
+
+    #[test]
+    fn deserialize_invalid_slice() {
+        let mut expected = vec![TEST_VERSION];
+        expected.extend_from_slice(&TEST_PUBKEY.to_bytes()[..31]);
+        assert_eq!(
+            RecordData::unpack(&expected).unwrap_err(),
+            ProgramError::AccountDataTooSmall
+        );
         let err: ProgramError = RecordData::try_from_slice(&expected).unwrap_err().into();
         assert!(matches!(err, ProgramError::BorshIoError(_)));
     }
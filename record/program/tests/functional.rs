@@ -1,9 +1,7 @@
 #![cfg(feature = "test-sbf")]
 
 use {
-    borsh::BorshSerialize,
     solana_program::{
-        borsh::get_packed_len,
         instruction::{AccountMeta, Instruction, InstructionError},
         pubkey::Pubkey,
         rent::Rent,
@@ -11,6 +9,7 @@ use {
     },
     solana_program_test::*,
     solana_sdk::{
+        account::Account,
         signature::{Keypair, Signer},
         transaction::{Transaction, TransactionError},
     },
@@ -18,36 +17,45 @@ use {
         error::RecordError,
         id, instruction,
         processor::process_instruction,
-        state::{Data, RecordData},
+        state::{record_data_hash, AppendOnlyState, RecordData},
     },
 };
 
+const DATA_SIZE: usize = 8;
+
 fn program_test() -> ProgramTest {
     ProgramTest::new("spl_record", id(), processor!(process_instruction))
 }
 
+async fn get_record(context: &mut ProgramTestContext, address: &Pubkey) -> (RecordData, Vec<u8>) {
+    let account = context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .unwrap();
+    let (account_data, record_data) = RecordData::unpack(&account.data).unwrap();
+    (account_data, record_data.to_vec())
+}
+
 async fn initialize_storage_account(
     context: &mut ProgramTestContext,
     authority: &Keypair,
     account: &Keypair,
-    data: Data,
+    data: &[u8],
 ) {
+    let account_length = RecordData::account_length(data.len()).unwrap();
     let transaction = Transaction::new_signed_with_payer(
         &[
             system_instruction::create_account(
                 &context.payer.pubkey(),
                 &account.pubkey(),
-                1.max(Rent::default().minimum_balance(get_packed_len::<RecordData>())),
-                get_packed_len::<RecordData>() as u64,
+                1.max(Rent::default().minimum_balance(account_length)),
+                account_length as u64,
                 &id(),
             ),
             instruction::initialize(&account.pubkey(), &authority.pubkey()),
-            instruction::write(
-                &account.pubkey(),
-                &authority.pubkey(),
-                0,
-                data.try_to_vec().unwrap(),
-            ),
+            instruction::write(&account.pubkey(), &authority.pubkey(), 0, data.to_vec()),
         ],
         Some(&context.payer.pubkey()),
         &[&context.payer, account, authority],
@@ -66,16 +74,10 @@ async fn initialize_success() {
 
     let authority = Keypair::new();
     let account = Keypair::new();
-    let data = Data {
-        bytes: [111u8; Data::DATA_SIZE],
-    };
-    initialize_storage_account(&mut context, &authority, &account, data.clone()).await;
-    let account_data = context
-        .banks_client
-        .get_account_data_with_borsh::<RecordData>(account.pubkey())
-        .await
-        .unwrap();
-    assert_eq!(account_data.data, data);
+    let data = [111u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
+    let (account_data, record_data) = get_record(&mut context, &account.pubkey()).await;
+    assert_eq!(record_data, data);
     assert_eq!(account_data.authority, authority.pubkey());
     assert_eq!(account_data.version, RecordData::CURRENT_VERSION);
 }
@@ -87,9 +89,8 @@ async fn initialize_with_seed_success() {
     let authority = Keypair::new();
     let seed = "storage";
     let account = Pubkey::create_with_seed(&authority.pubkey(), seed, &id()).unwrap();
-    let data = Data {
-        bytes: [111u8; Data::DATA_SIZE],
-    };
+    let data = [111u8; DATA_SIZE];
+    let account_length = RecordData::account_length(data.len()).unwrap();
     let transaction = Transaction::new_signed_with_payer(
         &[
             system_instruction::create_account_with_seed(
@@ -97,12 +98,12 @@ async fn initialize_with_seed_success() {
                 &account,
                 &authority.pubkey(),
                 seed,
-                1.max(Rent::default().minimum_balance(get_packed_len::<RecordData>())),
-                get_packed_len::<RecordData>() as u64,
+                1.max(Rent::default().minimum_balance(account_length)),
+                account_length as u64,
                 &id(),
             ),
             instruction::initialize(&account, &authority.pubkey()),
-            instruction::write(&account, &authority.pubkey(), 0, data.try_to_vec().unwrap()),
+            instruction::write(&account, &authority.pubkey(), 0, data.to_vec()),
         ],
         Some(&context.payer.pubkey()),
         &[&context.payer, &authority],
@@ -113,12 +114,8 @@ async fn initialize_with_seed_success() {
         .process_transaction(transaction)
         .await
         .unwrap();
-    let account_data = context
-        .banks_client
-        .get_account_data_with_borsh::<RecordData>(account)
-        .await
-        .unwrap();
-    assert_eq!(account_data.data, data);
+    let (account_data, record_data) = get_record(&mut context, &account).await;
+    assert_eq!(record_data, data);
     assert_eq!(account_data.authority, authority.pubkey());
     assert_eq!(account_data.version, RecordData::CURRENT_VERSION);
 }
@@ -129,10 +126,8 @@ async fn initialize_twice_fail() {
 
     let authority = Keypair::new();
     let account = Keypair::new();
-    let data = Data {
-        bytes: [111u8; Data::DATA_SIZE],
-    };
-    initialize_storage_account(&mut context, &authority, &account, data).await;
+    let data = [111u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
     let transaction = Transaction::new_signed_with_payer(
         &[instruction::initialize(
             &account.pubkey(),
@@ -159,20 +154,16 @@ async fn write_success() {
 
     let authority = Keypair::new();
     let account = Keypair::new();
-    let data = Data {
-        bytes: [222u8; Data::DATA_SIZE],
-    };
-    initialize_storage_account(&mut context, &authority, &account, data).await;
-
-    let new_data = Data {
-        bytes: [200u8; Data::DATA_SIZE],
-    };
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
+
+    let new_data = [200u8; DATA_SIZE];
     let transaction = Transaction::new_signed_with_payer(
         &[instruction::write(
             &account.pubkey(),
             &authority.pubkey(),
             0,
-            new_data.try_to_vec().unwrap(),
+            new_data.to_vec(),
         )],
         Some(&context.payer.pubkey()),
         &[&context.payer, &authority],
@@ -184,12 +175,8 @@ async fn write_success() {
         .await
         .unwrap();
 
-    let account_data = context
-        .banks_client
-        .get_account_data_with_borsh::<RecordData>(account.pubkey())
-        .await
-        .unwrap();
-    assert_eq!(account_data.data, new_data);
+    let (account_data, record_data) = get_record(&mut context, &account.pubkey()).await;
+    assert_eq!(record_data, new_data);
     assert_eq!(account_data.authority, authority.pubkey());
     assert_eq!(account_data.version, RecordData::CURRENT_VERSION);
 }
@@ -200,21 +187,17 @@ async fn write_fail_wrong_authority() {
 
     let authority = Keypair::new();
     let account = Keypair::new();
-    let data = Data {
-        bytes: [222u8; Data::DATA_SIZE],
-    };
-    initialize_storage_account(&mut context, &authority, &account, data).await;
-
-    let new_data = Data {
-        bytes: [200u8; Data::DATA_SIZE],
-    };
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
+
+    let new_data = [200u8; DATA_SIZE];
     let wrong_authority = Keypair::new();
     let transaction = Transaction::new_signed_with_payer(
         &[instruction::write(
             &account.pubkey(),
             &wrong_authority.pubkey(),
             0,
-            new_data.try_to_vec().unwrap(),
+            new_data.to_vec(),
         )],
         Some(&context.payer.pubkey()),
         &[&context.payer, &wrong_authority],
@@ -240,16 +223,10 @@ async fn write_fail_unsigned() {
 
     let authority = Keypair::new();
     let account = Keypair::new();
-    let data = Data {
-        bytes: [222u8; Data::DATA_SIZE],
-    };
-    initialize_storage_account(&mut context, &authority, &account, data).await;
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
 
-    let data = Data {
-        bytes: [200u8; Data::DATA_SIZE],
-    }
-    .try_to_vec()
-    .unwrap();
+    let data = [200u8; DATA_SIZE].to_vec();
     let transaction = Transaction::new_signed_with_payer(
         &[Instruction::new_with_borsh(
             id(),
@@ -280,10 +257,8 @@ async fn close_account_success() {
 
     let authority = Keypair::new();
     let account = Keypair::new();
-    let data = Data {
-        bytes: [222u8; Data::DATA_SIZE],
-    };
-    initialize_storage_account(&mut context, &authority, &account, data).await;
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
     let recipient = Pubkey::new_unique();
 
     let transaction = Transaction::new_signed_with_payer(
@@ -310,7 +285,7 @@ async fn close_account_success() {
         .unwrap();
     assert_eq!(
         account.lamports,
-        1.max(Rent::default().minimum_balance(get_packed_len::<RecordData>()))
+        1.max(Rent::default().minimum_balance(RecordData::account_length(DATA_SIZE).unwrap()))
     );
 }
 
@@ -320,10 +295,8 @@ async fn close_account_fail_wrong_authority() {
 
     let authority = Keypair::new();
     let account = Keypair::new();
-    let data = Data {
-        bytes: [222u8; Data::DATA_SIZE],
-    };
-    initialize_storage_account(&mut context, &authority, &account, data).await;
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
 
     let wrong_authority = Keypair::new();
     let transaction = Transaction::new_signed_with_payer(
@@ -360,10 +333,8 @@ async fn close_account_fail_unsigned() {
 
     let authority = Keypair::new();
     let account = Keypair::new();
-    let data = Data {
-        bytes: [222u8; Data::DATA_SIZE],
-    };
-    initialize_storage_account(&mut context, &authority, &account, data).await;
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
 
     let transaction = Transaction::new_signed_with_payer(
         &[Instruction::new_with_borsh(
@@ -396,10 +367,8 @@ async fn set_authority_success() {
 
     let authority = Keypair::new();
     let account = Keypair::new();
-    let data = Data {
-        bytes: [222u8; Data::DATA_SIZE],
-    };
-    initialize_storage_account(&mut context, &authority, &account, data).await;
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
     let new_authority = Keypair::new();
 
     let transaction = Transaction::new_signed_with_payer(
@@ -418,22 +387,16 @@ async fn set_authority_success() {
         .await
         .unwrap();
 
-    let account_data = context
-        .banks_client
-        .get_account_data_with_borsh::<RecordData>(account.pubkey())
-        .await
-        .unwrap();
+    let (account_data, _) = get_record(&mut context, &account.pubkey()).await;
     assert_eq!(account_data.authority, new_authority.pubkey());
 
-    let new_data = Data {
-        bytes: [200u8; Data::DATA_SIZE],
-    };
+    let new_data = [200u8; DATA_SIZE];
     let transaction = Transaction::new_signed_with_payer(
         &[instruction::write(
             &account.pubkey(),
             &new_authority.pubkey(),
             0,
-            new_data.try_to_vec().unwrap(),
+            new_data.to_vec(),
         )],
         Some(&context.payer.pubkey()),
         &[&context.payer, &new_authority],
@@ -445,12 +408,8 @@ async fn set_authority_success() {
         .await
         .unwrap();
 
-    let account_data = context
-        .banks_client
-        .get_account_data_with_borsh::<RecordData>(account.pubkey())
-        .await
-        .unwrap();
-    assert_eq!(account_data.data, new_data);
+    let (account_data, record_data) = get_record(&mut context, &account.pubkey()).await;
+    assert_eq!(record_data, new_data);
     assert_eq!(account_data.authority, new_authority.pubkey());
     assert_eq!(account_data.version, RecordData::CURRENT_VERSION);
 }
@@ -461,10 +420,8 @@ async fn set_authority_fail_wrong_authority() {
 
     let authority = Keypair::new();
     let account = Keypair::new();
-    let data = Data {
-        bytes: [222u8; Data::DATA_SIZE],
-    };
-    initialize_storage_account(&mut context, &authority, &account, data).await;
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
 
     let wrong_authority = Keypair::new();
     let transaction = Transaction::new_signed_with_payer(
@@ -501,10 +458,8 @@ async fn set_authority_fail_unsigned() {
 
     let authority = Keypair::new();
     let account = Keypair::new();
-    let data = Data {
-        bytes: [222u8; Data::DATA_SIZE],
-    };
-    initialize_storage_account(&mut context, &authority, &account, data).await;
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
 
     let transaction = Transaction::new_signed_with_payer(
         &[Instruction::new_with_borsh(
@@ -530,3 +485,476 @@ async fn set_authority_fail_unsigned() {
         TransactionError::InstructionError(0, InstructionError::MissingRequiredSignature)
     );
 }
+
+#[tokio::test]
+async fn reallocate_grow_success() {
+    let mut context = program_test().start_with_context().await;
+
+    let authority = Keypair::new();
+    let account = Keypair::new();
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
+
+    let new_data_length = 1_000;
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let new_rent = rent.minimum_balance(RecordData::account_length(new_data_length).unwrap());
+    let old_rent = rent.minimum_balance(RecordData::account_length(DATA_SIZE).unwrap());
+    let new_data = vec![200u8; new_data_length];
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::transfer(
+                &context.payer.pubkey(),
+                &account.pubkey(),
+                new_rent - old_rent,
+            ),
+            instruction::reallocate(
+                &account.pubkey(),
+                &authority.pubkey(),
+                &authority.pubkey(),
+                new_data_length as u64,
+            ),
+            instruction::write(&account.pubkey(), &authority.pubkey(), 0, new_data.clone()),
+        ],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &authority],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let (account_data, record_data) = get_record(&mut context, &account.pubkey()).await;
+    assert_eq!(record_data, new_data);
+    assert_eq!(account_data.authority, authority.pubkey());
+}
+
+#[tokio::test]
+async fn reallocate_shrink_success() {
+    let mut context = program_test().start_with_context().await;
+
+    let authority = Keypair::new();
+    let account = Keypair::new();
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
+    let recipient = Pubkey::new_unique();
+
+    let new_data_length = DATA_SIZE / 2;
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let recipient_lamports = rent.minimum_balance(0);
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::transfer(&context.payer.pubkey(), &recipient, recipient_lamports),
+            instruction::reallocate(
+                &account.pubkey(),
+                &authority.pubkey(),
+                &recipient,
+                new_data_length as u64,
+            ),
+        ],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &authority],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let (_, record_data) = get_record(&mut context, &account.pubkey()).await;
+    assert_eq!(record_data, data[..new_data_length]);
+
+    // freed rent goes to the receiver
+    let old_rent = rent.minimum_balance(RecordData::account_length(DATA_SIZE).unwrap());
+    let new_rent = rent.minimum_balance(RecordData::account_length(new_data_length).unwrap());
+    let record_account = context
+        .banks_client
+        .get_account(account.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record_account.lamports, new_rent);
+    let recipient_account = context
+        .banks_client
+        .get_account(recipient)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        recipient_account.lamports,
+        recipient_lamports + old_rent - new_rent
+    );
+}
+
+#[tokio::test]
+async fn reallocate_fail_not_rent_exempt() {
+    let mut context = program_test().start_with_context().await;
+
+    let authority = Keypair::new();
+    let account = Keypair::new();
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::reallocate(
+            &account.pubkey(),
+            &authority.pubkey(),
+            &authority.pubkey(),
+            1_000,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &authority],
+        context.last_blockhash,
+    );
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(0, InstructionError::AccountNotRentExempt)
+    );
+}
+
+#[tokio::test]
+async fn reallocate_fail_wrong_authority() {
+    let mut context = program_test().start_with_context().await;
+
+    let authority = Keypair::new();
+    let account = Keypair::new();
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
+
+    let wrong_authority = Keypair::new();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::reallocate(
+            &account.pubkey(),
+            &wrong_authority.pubkey(),
+            &wrong_authority.pubkey(),
+            0,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &wrong_authority],
+        context.last_blockhash,
+    );
+    assert_eq!(
+        context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(RecordError::IncorrectAuthority as u32)
+        )
+    );
+}
+
+async fn initialize_append_only_account(
+    context: &mut ProgramTestContext,
+    authority: &Keypair,
+    account: &Keypair,
+    data_length: usize,
+) {
+    let account_length = RecordData::append_only_account_length(data_length).unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::create_account(
+                &context.payer.pubkey(),
+                &account.pubkey(),
+                1.max(Rent::default().minimum_balance(account_length)),
+                account_length as u64,
+                &id(),
+            ),
+            instruction::initialize_append_only(&account.pubkey(), &authority.pubkey()),
+        ],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, account],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+async fn get_append_only_state(
+    context: &mut ProgramTestContext,
+    address: &Pubkey,
+) -> AppendOnlyState {
+    let account = context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .unwrap();
+    AppendOnlyState::unpack(&account.data).unwrap()
+}
+
+async fn process_authority_instruction(
+    context: &mut ProgramTestContext,
+    authority: &Keypair,
+    instruction: Instruction,
+) -> Result<(), TransactionError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, authority],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap())
+}
+
+#[tokio::test]
+async fn append_only_upload_success() {
+    let mut context = program_test().start_with_context().await;
+
+    let authority = Keypair::new();
+    let account = Keypair::new();
+    let data = (0..2_000).map(|i| i as u8).collect::<Vec<_>>();
+    initialize_append_only_account(&mut context, &authority, &account, data.len()).await;
+
+    let instructions =
+        instruction::upload_append_only(&account.pubkey(), &authority.pubkey(), &data);
+    assert_eq!(instructions.len(), 4);
+    for instruction in instructions {
+        process_authority_instruction(&mut context, &authority, instruction)
+            .await
+            .unwrap();
+    }
+
+    let (account_data, record_data) = get_record(&mut context, &account.pubkey()).await;
+    assert_eq!(account_data.version, RecordData::APPEND_ONLY_VERSION);
+    assert_eq!(record_data, data);
+    let append_only_state = get_append_only_state(&mut context, &account.pubkey()).await;
+    assert!(append_only_state.finalized);
+    assert_eq!(append_only_state.appended_length, data.len() as u64);
+
+    // finalized records are frozen
+    let err = process_authority_instruction(
+        &mut context,
+        &authority,
+        instruction::write(&account.pubkey(), &authority.pubkey(), 0, vec![1]),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(RecordError::RecordFinalized as u32)
+        )
+    );
+    let err = process_authority_instruction(
+        &mut context,
+        &authority,
+        instruction::close_account(
+            &account.pubkey(),
+            &authority.pubkey(),
+            &Pubkey::new_unique(),
+        ),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(RecordError::RecordFinalized as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn append_only_fail_non_sequential() {
+    let mut context = program_test().start_with_context().await;
+
+    let authority = Keypair::new();
+    let account = Keypair::new();
+    initialize_append_only_account(&mut context, &authority, &account, DATA_SIZE).await;
+
+    process_authority_instruction(
+        &mut context,
+        &authority,
+        instruction::write(&account.pubkey(), &authority.pubkey(), 0, vec![1, 2]),
+    )
+    .await
+    .unwrap();
+
+    // overwriting appended data is not allowed, nor is leaving a gap
+    for offset in [0, 3] {
+        let err = process_authority_instruction(
+            &mut context,
+            &authority,
+            instruction::write(&account.pubkey(), &authority.pubkey(), offset, vec![3]),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err,
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(RecordError::NonSequentialWrite as u32)
+            )
+        );
+    }
+
+    // nor is dropping appended data by shrinking the account
+    let err = process_authority_instruction(
+        &mut context,
+        &authority,
+        instruction::reallocate(
+            &account.pubkey(),
+            &authority.pubkey(),
+            &authority.pubkey(),
+            1,
+        ),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(RecordError::AppendedDataTruncated as u32)
+        )
+    );
+
+    let (_, record_data) = get_record(&mut context, &account.pubkey()).await;
+    assert_eq!(record_data[..2], [1, 2]);
+    let append_only_state = get_append_only_state(&mut context, &account.pubkey()).await;
+    assert!(!append_only_state.finalized);
+    assert_eq!(append_only_state.appended_length, 2);
+}
+
+#[tokio::test]
+async fn finalize_fail_hash_mismatch() {
+    let mut context = program_test().start_with_context().await;
+
+    let authority = Keypair::new();
+    let account = Keypair::new();
+    let data = [222u8; DATA_SIZE];
+    initialize_append_only_account(&mut context, &authority, &account, DATA_SIZE).await;
+
+    process_authority_instruction(
+        &mut context,
+        &authority,
+        instruction::write(
+            &account.pubkey(),
+            &authority.pubkey(),
+            0,
+            data[..4].to_vec(),
+        ),
+    )
+    .await
+    .unwrap();
+
+    // the hash covers only appended data, not the unwritten remainder of the account
+    let err = process_authority_instruction(
+        &mut context,
+        &authority,
+        instruction::finalize(
+            &account.pubkey(),
+            &authority.pubkey(),
+            record_data_hash(&data),
+        ),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(RecordError::HashMismatch as u32)
+        )
+    );
+
+    process_authority_instruction(
+        &mut context,
+        &authority,
+        instruction::finalize(
+            &account.pubkey(),
+            &authority.pubkey(),
+            record_data_hash(&data[..4]),
+        ),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn finalize_fail_not_append_only() {
+    let mut context = program_test().start_with_context().await;
+
+    let authority = Keypair::new();
+    let account = Keypair::new();
+    let data = [222u8; DATA_SIZE];
+    initialize_storage_account(&mut context, &authority, &account, &data).await;
+
+    let err = process_authority_instruction(
+        &mut context,
+        &authority,
+        instruction::finalize(
+            &account.pubkey(),
+            &authority.pubkey(),
+            record_data_hash(&data),
+        ),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(RecordError::NotAppendOnly as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn v1_account_success() {
+    let mut context = program_test().start_with_context().await;
+
+    // an account written before append-only records existed: version, authority, data
+    let authority = Keypair::new();
+    let account = Keypair::new();
+    let data = [111u8; DATA_SIZE];
+    let mut account_data = vec![1];
+    account_data.extend_from_slice(authority.pubkey().as_ref());
+    account_data.extend_from_slice(&data);
+    context.set_account(
+        &account.pubkey(),
+        &Account {
+            lamports: Rent::default().minimum_balance(account_data.len()),
+            data: account_data,
+            owner: id(),
+            ..Account::default()
+        }
+        .into(),
+    );
+
+    let (account_data, record_data) = get_record(&mut context, &account.pubkey()).await;
+    assert_eq!(account_data.version, RecordData::CURRENT_VERSION);
+    assert_eq!(account_data.authority, authority.pubkey());
+    assert_eq!(record_data, data);
+
+    let new_data = [222u8; DATA_SIZE];
+    process_authority_instruction(
+        &mut context,
+        &authority,
+        instruction::write(&account.pubkey(), &authority.pubkey(), 0, new_data.to_vec()),
+    )
+    .await
+    .unwrap();
+    let (_, record_data) = get_record(&mut context, &account.pubkey()).await;
+    assert_eq!(record_data, new_data);
+}
@@ -0,0 +1,273 @@
+//! Helpers for reading record accounts from other programs
+#![deny(missing_docs)]
+
+use {
+    borsh::BorshDeserialize,
+    solana_program::{
+        account_info::AccountInfo, msg, program_error::ProgramError, program_pack::IsInitialized,
+        pubkey::Pubkey,
+    },
+    spl_record::{
+        error::RecordError,
+        state::{AppendOnlyState, RecordData},
+    },
+    std::cell::Ref,
+};
+
+// Export current SDK types for downstream users building with a different SDK version
+pub use spl_record;
+
+/// Check that the account is an initialized record, optionally written by the
+/// expected authority, and borrow its record data, excluding the header. For
+/// append-only and finalized records, only the appended data is returned.
+pub fn read_record<'a>(
+    record_info: &'a AccountInfo,
+    expected_authority: Option<&Pubkey>,
+) -> Result<Ref<'a, [u8]>, ProgramError> {
+    if *record_info.owner != spl_record::id() {
+        msg!("Record account not owned by the record program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let account_data = record_info.try_borrow_data()?;
+    let (record, data) = RecordData::unpack(&account_data)?;
+    if !record.is_initialized() {
+        msg!("Record account not initialized");
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if let Some(expected_authority) = expected_authority {
+        if record.authority != *expected_authority {
+            msg!("Incorrect record authority");
+            return Err(RecordError::IncorrectAuthority.into());
+        }
+    }
+
+    let data_start = record.writable_start_index();
+    let data_length = if record.is_append_only() {
+        AppendOnlyState::unpack(&account_data)?
+            .appended_data(data)?
+            .len()
+    } else {
+        data.len()
+    };
+    let data_end = data_start.saturating_add(data_length);
+
+    Ok(Ref::map(account_data, |account_data| {
+        &account_data[data_start..data_end]
+    }))
+}
+
+/// Like `read_record`, but additionally require that the record has been
+/// finalized, so its data was verified against a hash and can no longer change
+pub fn read_finalized_record<'a>(
+    record_info: &'a AccountInfo,
+    expected_authority: Option<&Pubkey>,
+) -> Result<Ref<'a, [u8]>, ProgramError> {
+    let record_data = read_record(record_info, expected_authority)?;
+    let account_data = record_info.try_borrow_data()?;
+    let (record, _) = RecordData::unpack(&account_data)?;
+    if !record.is_append_only() || !AppendOnlyState::unpack(&account_data)?.finalized {
+        msg!("Record has not been finalized");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(record_data)
+}
+
+/// Deserialize a borsh-encoded value from the start of the record data. Any
+/// bytes left over after the value, such as unused space in the record, are
+/// ignored.
+pub fn read_record_borsh<T: BorshDeserialize>(
+    record_info: &AccountInfo,
+    expected_authority: Option<&Pubkey>,
+) -> Result<T, ProgramError> {
+    let record_data = read_record(record_info, expected_authority)?;
+    T::deserialize(&mut &record_data[..]).map_err(|e| e.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        borsh::BorshSerialize,
+        solana_program::{clock::Epoch, pubkey::Pubkey},
+    };
+
+    const AUTHORITY: Pubkey = Pubkey::new_from_array([100; 32]);
+
+    type ReadFn =
+        for<'a, 'b> fn(&'a AccountInfo<'b>, Option<&Pubkey>) -> Result<Ref<'a, [u8]>, ProgramError>;
+
+    fn record_account_data(version: u8, data: &[u8]) -> Vec<u8> {
+        let mut account_data = RecordData {
+            version,
+            authority: AUTHORITY,
+        }
+        .try_to_vec()
+        .unwrap();
+        account_data.extend_from_slice(data);
+        account_data
+    }
+
+    fn append_only_account_data(append_only_state: AppendOnlyState, data: &[u8]) -> Vec<u8> {
+        let mut account_data = record_account_data(RecordData::APPEND_ONLY_VERSION, &[]);
+        account_data.extend_from_slice(&append_only_state.try_to_vec().unwrap());
+        account_data.extend_from_slice(data);
+        account_data
+    }
+
+    fn check_read(
+        owner: &Pubkey,
+        account_data: &mut [u8],
+        expected_authority: Option<&Pubkey>,
+    ) -> Result<Vec<u8>, ProgramError> {
+        check_read_with(read_record, owner, account_data, expected_authority)
+    }
+
+    fn check_read_with(
+        read: ReadFn,
+        owner: &Pubkey,
+        account_data: &mut [u8],
+        expected_authority: Option<&Pubkey>,
+    ) -> Result<Vec<u8>, ProgramError> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let record_info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            account_data,
+            owner,
+            false,
+            Epoch::default(),
+        );
+        read(&record_info, expected_authority).map(|data| data.to_vec())
+    }
+
+    #[test]
+    fn read_success() {
+        let data = [42; 100];
+        let mut account_data = record_account_data(RecordData::CURRENT_VERSION, &data);
+        assert_eq!(
+            check_read(&spl_record::id(), &mut account_data, None).unwrap(),
+            data
+        );
+        assert_eq!(
+            check_read(&spl_record::id(), &mut account_data, Some(&AUTHORITY)).unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn read_fail() {
+        let mut account_data = record_account_data(RecordData::CURRENT_VERSION, &[42; 8]);
+        assert_eq!(
+            check_read(&Pubkey::new_unique(), &mut account_data, None).unwrap_err(),
+            ProgramError::IncorrectProgramId
+        );
+        assert_eq!(
+            check_read(
+                &spl_record::id(),
+                &mut account_data,
+                Some(&Pubkey::new_unique())
+            )
+            .unwrap_err(),
+            RecordError::IncorrectAuthority.into()
+        );
+
+        let mut account_data = record_account_data(0, &[42; 8]);
+        assert_eq!(
+            check_read(&spl_record::id(), &mut account_data, None).unwrap_err(),
+            ProgramError::UninitializedAccount
+        );
+
+        let mut account_data = vec![RecordData::CURRENT_VERSION; 8];
+        assert_eq!(
+            check_read(&spl_record::id(), &mut account_data, None).unwrap_err(),
+            ProgramError::AccountDataTooSmall
+        );
+    }
+
+    #[test]
+    fn read_append_only() {
+        let data = [42; 100];
+        let mut account_data = append_only_account_data(
+            AppendOnlyState {
+                finalized: false,
+                appended_length: 10,
+            },
+            &data,
+        );
+        assert_eq!(
+            check_read(&spl_record::id(), &mut account_data, None).unwrap(),
+            data[..10]
+        );
+        assert_eq!(
+            check_read_with(
+                read_finalized_record,
+                &spl_record::id(),
+                &mut account_data,
+                None
+            )
+            .unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+
+        let mut account_data = append_only_account_data(
+            AppendOnlyState {
+                finalized: true,
+                appended_length: 10,
+            },
+            &data,
+        );
+        assert_eq!(
+            check_read_with(
+                read_finalized_record,
+                &spl_record::id(),
+                &mut account_data,
+                None
+            )
+            .unwrap(),
+            data[..10]
+        );
+
+        // regular records are never finalized
+        let mut account_data = record_account_data(RecordData::CURRENT_VERSION, &data);
+        assert_eq!(
+            check_read_with(
+                read_finalized_record,
+                &spl_record::id(),
+                &mut account_data,
+                None
+            )
+            .unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+    }
+
+    #[test]
+    fn read_borsh_with_trailing_space() {
+        let value = (7u64, String::from("config"));
+        let mut data = value.try_to_vec().unwrap();
+        data.resize(data.len() + 64, 0);
+        let mut account_data = record_account_data(RecordData::CURRENT_VERSION, &data);
+
+        let key = Pubkey::new_unique();
+        let owner = spl_record::id();
+        let mut lamports = 0;
+        let record_info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut account_data,
+            &owner,
+            false,
+            Epoch::default(),
+        );
+        assert_eq!(
+            read_record_borsh::<(u64, String)>(&record_info, Some(&AUTHORITY)).unwrap(),
+            value
+        );
+    }
+}
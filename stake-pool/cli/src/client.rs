@@ -7,11 +7,12 @@ use {
         rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
         rpc_filter::{Memcmp, RpcFilterType},
     },
-    solana_program::{borsh::try_from_slice_unchecked, program_pack::Pack, pubkey::Pubkey, stake},
+    solana_program::{borsh::try_from_slice_unchecked, pubkey::Pubkey, stake},
     spl_stake_pool::{
         find_withdraw_authority_program_address,
         state::{StakePool, ValidatorList},
     },
+    spl_token_2022::extension::StateWithExtensions,
     std::collections::HashSet,
 };
 
@@ -41,10 +42,12 @@ pub fn get_token_account(
     rpc_client: &RpcClient,
     token_account_address: &Pubkey,
     expected_token_mint: &Pubkey,
-) -> Result<spl_token::state::Account, Error> {
+) -> Result<spl_token_2022::state::Account, Error> {
     let account_data = rpc_client.get_account_data(token_account_address)?;
-    let token_account = spl_token::state::Account::unpack_from_slice(account_data.as_slice())
-        .map_err(|err| format!("Invalid token account {}: {}", token_account_address, err))?;
+    let token_account =
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(account_data.as_slice())
+            .map_err(|err| format!("Invalid token account {}: {}", token_account_address, err))?
+            .base;
 
     if token_account.mint != *expected_token_mint {
         Err(format!(
@@ -60,10 +63,12 @@ pub fn get_token_account(
 pub fn get_token_mint(
     rpc_client: &RpcClient,
     token_mint_address: &Pubkey,
-) -> Result<spl_token::state::Mint, Error> {
+) -> Result<spl_token_2022::state::Mint, Error> {
     let account_data = rpc_client.get_account_data(token_mint_address)?;
-    let token_mint = spl_token::state::Mint::unpack_from_slice(account_data.as_slice())
-        .map_err(|err| format!("Invalid token mint {}: {}", token_mint_address, err))?;
+    let token_mint =
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(account_data.as_slice())
+            .map_err(|err| format!("Invalid token mint {}: {}", token_mint_address, err))?
+            .base;
 
     Ok(token_mint)
 }
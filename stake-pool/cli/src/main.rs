@@ -25,7 +25,6 @@ use {
     solana_program::{
         borsh::{get_instance_packed_len, get_packed_len},
         instruction::Instruction,
-        program_pack::Pack,
         pubkey::Pubkey,
         stake,
     },
@@ -40,22 +39,22 @@ use {
         system_instruction,
         transaction::Transaction,
     },
-    spl_associated_token_account::get_associated_token_address,
+    spl_associated_token_account::{
+        get_associated_token_address_with_program_id, instruction::create_associated_token_account,
+    },
     spl_stake_pool::state::ValidatorStakeInfo,
     spl_stake_pool::{
-        self, find_stake_program_address, find_transient_stake_program_address,
-        find_withdraw_authority_program_address,
+        self, find_ephemeral_stake_program_address, find_stake_program_address,
+        find_transient_stake_program_address, find_withdraw_authority_program_address,
         instruction::{FundingType, PreferredValidatorType},
         minimum_delegation,
-        state::{Fee, FeeType, StakePool, ValidatorList},
+        state::{Fee, FeeType, StakePool, ValidatorList, WithdrawalTicket},
         MINIMUM_RESERVE_LAMPORTS,
     },
+    spl_token_2022::extension::ExtensionType,
     std::cmp::Ordering,
     std::{num::NonZeroU32, process::exit, sync::Arc},
 };
-// use instruction::create_associated_token_account once ATA 1.0.5 is released
-#[allow(deprecated)]
-use spl_associated_token_account::create_associated_token_account;
 
 pub(crate) struct Config {
     rpc_client: RpcClient,
@@ -236,6 +235,7 @@ fn command_create_pool(
     mint_keypair: Option<Keypair>,
     reserve_keypair: Option<Keypair>,
     unsafe_fees: bool,
+    token_2022: bool,
 ) -> CommandResult {
     if !unsafe_fees {
         check_stake_pool_fees(&epoch_fee, &withdrawal_fee, &deposit_fee)?;
@@ -250,16 +250,33 @@ fn command_create_pool(
 
     let validator_list_keypair = validator_list_keypair.unwrap_or_else(Keypair::new);
 
+    // Token-2022 pool mints carry a metadata pointer to themselves, so that
+    // token metadata can be stored directly in the mint
+    let (token_program_id, mint_extensions, fee_account_extensions) = if token_2022 {
+        (
+            spl_token_2022::id(),
+            vec![ExtensionType::MetadataPointer],
+            vec![ExtensionType::ImmutableOwner],
+        )
+    } else {
+        (spl_token::id(), vec![], vec![])
+    };
+    let mint_len =
+        ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&mint_extensions)?;
+    let pool_fee_account_len = ExtensionType::try_calculate_account_len::<
+        spl_token_2022::state::Account,
+    >(&fee_account_extensions)?;
+
     let reserve_stake_balance = config
         .rpc_client
         .get_minimum_balance_for_rent_exemption(STAKE_STATE_LEN)?
         + MINIMUM_RESERVE_LAMPORTS;
     let mint_account_balance = config
         .rpc_client
-        .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)?;
+        .get_minimum_balance_for_rent_exemption(mint_len)?;
     let pool_fee_account_balance = config
         .rpc_client
-        .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)?;
+        .get_minimum_balance_for_rent_exemption(pool_fee_account_len)?;
     let stake_pool_account_lamports = config
         .rpc_client
         .get_minimum_balance_for_rent_exemption(get_packed_len::<StakePool>())?;
@@ -308,23 +325,34 @@ fn command_create_pool(
             &config.fee_payer.pubkey(),
             &mint_keypair.pubkey(),
             mint_account_balance,
-            spl_token::state::Mint::LEN as u64,
-            &spl_token::id(),
+            mint_len as u64,
+            &token_program_id,
         ),
-        // Initialize pool token mint account
-        spl_token::instruction::initialize_mint(
-            &spl_token::id(),
-            &mint_keypair.pubkey(),
-            &withdraw_authority,
-            None,
-            default_decimals,
-        )?,
     ];
+    if token_2022 {
+        instructions.push(
+            spl_token_2022::extension::metadata_pointer::instruction::initialize(
+                &token_program_id,
+                &mint_keypair.pubkey(),
+                Some(withdraw_authority),
+                Some(mint_keypair.pubkey()),
+            )?,
+        );
+    }
+    // Initialize pool token mint account
+    instructions.push(spl_token_2022::instruction::initialize_mint(
+        &token_program_id,
+        &mint_keypair.pubkey(),
+        &withdraw_authority,
+        None,
+        default_decimals,
+    )?);
 
     let pool_fee_account = add_associated_token_account(
         config,
         &mint_keypair.pubkey(),
         &config.manager.pubkey(),
+        &token_program_id,
         &mut instructions,
         &mut total_rent_free_balances,
     );
@@ -365,7 +393,7 @@ fn command_create_pool(
                 &reserve_keypair.pubkey(),
                 &mint_keypair.pubkey(),
                 &pool_fee_account,
-                &spl_token::id(),
+                &token_program_id,
                 deposit_authority.as_ref().map(|x| x.pubkey()),
                 epoch_fee,
                 withdrawal_fee,
@@ -607,6 +635,167 @@ fn command_decrease_validator_stake(
     Ok(())
 }
 
+fn command_increase_additional_validator_stake(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    vote_account: &Pubkey,
+    amount: f64,
+    ephemeral_stake_seed: u64,
+) -> CommandResult {
+    let lamports = native_token::sol_to_lamports(amount);
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+    let validator_stake_info = validator_list
+        .find(vote_account)
+        .ok_or("Vote account not found in validator list")?;
+    let validator_seed = NonZeroU32::new(validator_stake_info.validator_seed_suffix);
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.staker.as_ref()];
+    unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[
+            spl_stake_pool::instruction::increase_additional_validator_stake_with_vote(
+                &spl_stake_pool::id(),
+                &stake_pool,
+                stake_pool_address,
+                vote_account,
+                lamports,
+                validator_seed,
+                validator_stake_info.transient_seed_suffix,
+                ephemeral_stake_seed,
+            ),
+        ],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_decrease_additional_validator_stake(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    vote_account: &Pubkey,
+    amount: f64,
+    ephemeral_stake_seed: u64,
+) -> CommandResult {
+    let lamports = native_token::sol_to_lamports(amount);
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+    let validator_stake_info = validator_list
+        .find(vote_account)
+        .ok_or("Vote account not found in validator list")?;
+    let validator_seed = NonZeroU32::new(validator_stake_info.validator_seed_suffix);
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.staker.as_ref()];
+    unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[
+            spl_stake_pool::instruction::decrease_additional_validator_stake_with_vote(
+                &spl_stake_pool::id(),
+                &stake_pool,
+                stake_pool_address,
+                vote_account,
+                lamports,
+                validator_seed,
+                validator_stake_info.transient_seed_suffix,
+                ephemeral_stake_seed,
+            ),
+        ],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_redelegate(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    source_vote_account: &Pubkey,
+    destination_vote_account: &Pubkey,
+    amount: f64,
+    ephemeral_stake_seed: u64,
+) -> CommandResult {
+    let lamports = native_token::sol_to_lamports(amount);
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+    let source_stake_info = validator_list
+        .find(source_vote_account)
+        .ok_or("Source vote account not found in validator list")?;
+    let destination_stake_info = validator_list
+        .find(destination_vote_account)
+        .ok_or("Destination vote account not found in validator list")?;
+
+    let program_id = spl_stake_pool::id();
+    let (withdraw_authority, _) =
+        find_withdraw_authority_program_address(&program_id, stake_pool_address);
+    let (source_validator_stake, _) = find_stake_program_address(
+        &program_id,
+        source_vote_account,
+        stake_pool_address,
+        NonZeroU32::new(source_stake_info.validator_seed_suffix),
+    );
+    let (source_transient_stake, _) = find_transient_stake_program_address(
+        &program_id,
+        source_vote_account,
+        stake_pool_address,
+        source_stake_info.transient_seed_suffix,
+    );
+    let (ephemeral_stake, _) =
+        find_ephemeral_stake_program_address(&program_id, stake_pool_address, ephemeral_stake_seed);
+    let (destination_transient_stake, _) = find_transient_stake_program_address(
+        &program_id,
+        destination_vote_account,
+        stake_pool_address,
+        destination_stake_info.transient_seed_suffix,
+    );
+    let (destination_validator_stake, _) = find_stake_program_address(
+        &program_id,
+        destination_vote_account,
+        stake_pool_address,
+        NonZeroU32::new(destination_stake_info.validator_seed_suffix),
+    );
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.staker.as_ref()];
+    unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::redelegate(
+            &program_id,
+            stake_pool_address,
+            &config.staker.pubkey(),
+            &withdraw_authority,
+            &stake_pool.validator_list,
+            &source_validator_stake,
+            &source_transient_stake,
+            &ephemeral_stake,
+            &destination_transient_stake,
+            &destination_validator_stake,
+            destination_vote_account,
+            lamports,
+            source_stake_info.transient_seed_suffix,
+            ephemeral_stake_seed,
+            destination_stake_info.transient_seed_suffix,
+        )],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
 fn command_set_preferred_validator(
     config: &Config,
     stake_pool_address: &Pubkey,
@@ -636,24 +825,37 @@ fn add_associated_token_account(
     config: &Config,
     mint: &Pubkey,
     owner: &Pubkey,
+    token_program_id: &Pubkey,
     instructions: &mut Vec<Instruction>,
     rent_free_balances: &mut u64,
 ) -> Pubkey {
     // Account for tokens not specified, creating one
-    let account = get_associated_token_address(owner, mint);
+    let account = get_associated_token_address_with_program_id(owner, mint, token_program_id);
     if get_token_account(&config.rpc_client, &account, mint).is_err() {
         println!("Creating associated token account {} to receive stake pool tokens of mint {}, owned by {}", account, mint, owner);
 
+        // Token-2022 associated token accounts are created with the
+        // immutable owner extension
+        let account_extensions = if *token_program_id == spl_token_2022::id() {
+            vec![ExtensionType::ImmutableOwner]
+        } else {
+            vec![]
+        };
+        let account_len =
+            ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(
+                &account_extensions,
+            )
+            .unwrap();
         let min_account_balance = config
             .rpc_client
-            .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)
+            .get_minimum_balance_for_rent_exemption(account_len)
             .unwrap();
 
-        #[allow(deprecated)]
         instructions.push(create_associated_token_account(
             &config.fee_payer.pubkey(),
             owner,
             mint,
+            token_program_id,
         ));
 
         *rent_free_balances += min_account_balance;
@@ -722,6 +924,7 @@ fn command_deposit_stake(
             config,
             &stake_pool.pool_mint,
             &config.token_owner.pubkey(),
+            &stake_pool.token_program_id,
             &mut instructions,
             &mut total_rent_free_balances,
         ));
@@ -757,7 +960,7 @@ fn command_deposit_stake(
                 &stake_pool.manager_fee_account,
                 &referrer_token_account,
                 &stake_pool.pool_mint,
-                &spl_token::id(),
+                &stake_pool.token_program_id,
             )
         } else {
             spl_stake_pool::instruction::deposit_stake(
@@ -773,7 +976,7 @@ fn command_deposit_stake(
                 &stake_pool.manager_fee_account,
                 &referrer_token_account,
                 &stake_pool.pool_mint,
-                &spl_token::id(),
+                &stake_pool.token_program_id,
             )
         };
 
@@ -818,6 +1021,7 @@ fn command_deposit_all_stake(
             config,
             &stake_pool.pool_mint,
             &config.token_owner.pubkey(),
+            &stake_pool.token_program_id,
             &mut create_token_account_instructions,
             &mut total_rent_free_balances,
         ));
@@ -905,7 +1109,7 @@ fn command_deposit_all_stake(
                 &stake_pool.manager_fee_account,
                 &referrer_token_account,
                 &stake_pool.pool_mint,
-                &spl_token::id(),
+                &stake_pool.token_program_id,
             )
         } else {
             spl_stake_pool::instruction::deposit_stake(
@@ -921,7 +1125,7 @@ fn command_deposit_all_stake(
                 &stake_pool.manager_fee_account,
                 &referrer_token_account,
                 &stake_pool.pool_mint,
-                &spl_token::id(),
+                &stake_pool.token_program_id,
             )
         };
 
@@ -992,6 +1196,7 @@ fn command_deposit_sol(
             config,
             &stake_pool.pool_mint,
             &config.token_owner.pubkey(),
+            &stake_pool.token_program_id,
             &mut instructions,
             &mut total_rent_free_balances,
         ));
@@ -1026,7 +1231,7 @@ fn command_deposit_sol(
             &stake_pool.manager_fee_account,
             &referrer_token_account,
             &stake_pool.pool_mint,
-            &spl_token::id(),
+            &stake_pool.token_program_id,
             amount,
         )
     } else {
@@ -1040,7 +1245,7 @@ fn command_deposit_sol(
             &stake_pool.manager_fee_account,
             &referrer_token_account,
             &stake_pool.pool_mint,
-            &spl_token::id(),
+            &stake_pool.token_program_id,
             amount,
         )
     };
@@ -1063,6 +1268,76 @@ fn command_deposit_sol(
     Ok(())
 }
 
+fn command_deposit_rewards(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    from: &Option<Keypair>,
+    amount: f64,
+) -> CommandResult {
+    let amount = native_token::sol_to_lamports(amount);
+
+    let from_pubkey = from
+        .as_ref()
+        .map_or_else(|| config.fee_payer.pubkey(), |keypair| keypair.pubkey());
+    let from_balance = config.rpc_client.get_balance(&from_pubkey)?;
+    if from_balance < amount {
+        return Err(format!(
+            "Not enough SOL to deposit rewards into pool: {}.\nMaximum deposit amount is {} SOL.",
+            Sol(amount),
+            Sol(from_balance)
+        )
+        .into());
+    }
+
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+
+    let mut signers = vec![config.fee_payer.as_ref()];
+    if let Some(keypair) = from.as_ref() {
+        signers.push(keypair)
+    }
+    unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::deposit_rewards(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &stake_pool.reserve_stake,
+            &from_pubkey,
+            amount,
+        )],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_realloc_stake_pool(config: &Config, stake_pool_address: &Pubkey) -> CommandResult {
+    let account_len = config
+        .rpc_client
+        .get_account(stake_pool_address)?
+        .data
+        .len();
+    if account_len >= get_packed_len::<StakePool>() {
+        println!(
+            "Stake pool {} already holds the current layout",
+            stake_pool_address
+        );
+        return Ok(());
+    }
+
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::realloc_stake_pool(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.fee_payer.pubkey(),
+        )],
+        &[config.fee_payer.as_ref()],
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
 fn command_list(config: &Config, stake_pool_address: &Pubkey) -> CommandResult {
     let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
     let reserve_stake_account_address = stake_pool.reserve_stake.to_string();
@@ -1374,10 +1649,12 @@ fn command_withdraw_stake(
     let pool_withdraw_authority =
         find_withdraw_authority_program_address(&spl_stake_pool::id(), stake_pool_address).0;
 
-    let pool_token_account = pool_token_account.unwrap_or(get_associated_token_address(
-        &config.token_owner.pubkey(),
-        &stake_pool.pool_mint,
-    ));
+    let pool_token_account =
+        pool_token_account.unwrap_or(get_associated_token_address_with_program_id(
+            &config.token_owner.pubkey(),
+            &stake_pool.pool_mint,
+            &stake_pool.token_program_id,
+        ));
     let token_account = get_token_account(
         &config.rpc_client,
         &pool_token_account,
@@ -1527,8 +1804,8 @@ fn command_withdraw_stake(
 
     instructions.push(
         // Approve spending token
-        spl_token::instruction::approve(
-            &spl_token::id(),
+        spl_token_2022::instruction::approve(
+            &stake_pool.token_program_id,
             &pool_token_account,
             &user_transfer_authority.pubkey(),
             &config.token_owner.pubkey(),
@@ -1589,7 +1866,7 @@ fn command_withdraw_stake(
             &pool_token_account,
             &stake_pool.manager_fee_account,
             &stake_pool.pool_mint,
-            &spl_token::id(),
+            &stake_pool.token_program_id,
             withdraw_account.pool_amount,
         ));
     }
@@ -1639,10 +1916,12 @@ fn command_withdraw_sol(
     let pool_mint = get_token_mint(&config.rpc_client, &stake_pool.pool_mint)?;
     let pool_amount = spl_token::ui_amount_to_amount(pool_amount, pool_mint.decimals);
 
-    let pool_token_account = pool_token_account.unwrap_or(get_associated_token_address(
-        &config.token_owner.pubkey(),
-        &stake_pool.pool_mint,
-    ));
+    let pool_token_account =
+        pool_token_account.unwrap_or(get_associated_token_address_with_program_id(
+            &config.token_owner.pubkey(),
+            &stake_pool.pool_mint,
+            &stake_pool.token_program_id,
+        ));
     let token_account = get_token_account(
         &config.rpc_client,
         &pool_token_account,
@@ -1669,8 +1948,8 @@ fn command_withdraw_sol(
 
     let mut instructions = vec![
         // Approve spending token
-        spl_token::instruction::approve(
-            &spl_token::id(),
+        spl_token_2022::instruction::approve(
+            &stake_pool.token_program_id,
             &pool_token_account,
             &user_transfer_authority.pubkey(),
             &config.token_owner.pubkey(),
@@ -1708,7 +1987,7 @@ fn command_withdraw_sol(
             sol_receiver,
             &stake_pool.manager_fee_account,
             &stake_pool.pool_mint,
-            &spl_token::id(),
+            &stake_pool.token_program_id,
             pool_amount,
         )
     } else {
@@ -1722,7 +2001,7 @@ fn command_withdraw_sol(
             sol_receiver,
             &stake_pool.manager_fee_account,
             &stake_pool.pool_mint,
-            &spl_token::id(),
+            &stake_pool.token_program_id,
             pool_amount,
         )
     };
@@ -1742,54 +2021,203 @@ fn command_withdraw_sol(
     Ok(())
 }
 
-fn command_set_manager(
+fn command_create_withdrawal_ticket(
     config: &Config,
     stake_pool_address: &Pubkey,
-    new_manager: &Option<Box<dyn Signer>>,
-    new_fee_receiver: &Option<Pubkey>,
+    pool_token_account: &Option<Pubkey>,
+    pool_amount: f64,
 ) -> CommandResult {
     if !config.no_update {
         command_update(config, stake_pool_address, false, false)?;
     }
+
     let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let pool_mint = get_token_mint(&config.rpc_client, &stake_pool.pool_mint)?;
+    let pool_amount = spl_token::ui_amount_to_amount(pool_amount, pool_mint.decimals);
 
-    // If new accounts are missing in the arguments use the old ones
-    let (new_manager_pubkey, mut signers): (Pubkey, Vec<&dyn Signer>) = match new_manager {
-        None => (stake_pool.manager, vec![]),
-        Some(value) => (value.pubkey(), vec![value.as_ref()]),
-    };
+    let pool_token_account =
+        pool_token_account.unwrap_or(get_associated_token_address_with_program_id(
+            &config.token_owner.pubkey(),
+            &stake_pool.pool_mint,
+            &stake_pool.token_program_id,
+        ));
+    let token_account = get_token_account(
+        &config.rpc_client,
+        &pool_token_account,
+        &stake_pool.pool_mint,
+    )?;
 
-    let new_fee_receiver = match new_fee_receiver {
-        None => stake_pool.manager_fee_account,
-        Some(value) => {
-            // Check for fee receiver being a valid token account and have to same mint as the stake pool
-            let token_account =
-                get_token_account(&config.rpc_client, value, &stake_pool.pool_mint)?;
-            if token_account.mint != stake_pool.pool_mint {
-                return Err("Fee receiver account belongs to a different mint"
-                    .to_string()
-                    .into());
-            }
-            *value
-        }
-    };
+    // Check withdraw_from balance
+    if token_account.amount < pool_amount {
+        return Err(format!(
+            "Not enough token balance to withdraw {} pool tokens.\nMaximum withdraw amount is {} pool tokens.",
+            spl_token::amount_to_ui_amount(pool_amount, pool_mint.decimals),
+            spl_token::amount_to_ui_amount(token_account.amount, pool_mint.decimals)
+        )
+        .into());
+    }
 
-    signers.append(&mut vec![
+    let withdrawal_ticket = Keypair::new();
+    println!("Creating withdrawal ticket {}", withdrawal_ticket.pubkey());
+    let ticket_space = get_packed_len::<WithdrawalTicket>();
+    let ticket_rent = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(ticket_space)?;
+
+    let user_transfer_authority = Keypair::new(); // ephemeral keypair just to do the transfer
+    let mut signers = vec![
         config.fee_payer.as_ref(),
-        config.manager.as_ref(),
-    ]);
-    unique_signers!(signers);
-    let transaction = checked_transaction_with_signers(
-        config,
-        &[spl_stake_pool::instruction::set_manager(
-            &spl_stake_pool::id(),
-            stake_pool_address,
-            &config.manager.pubkey(),
-            &new_manager_pubkey,
-            &new_fee_receiver,
-        )],
-        &signers,
-    )?;
+        config.token_owner.as_ref(),
+        &user_transfer_authority,
+        &withdrawal_ticket,
+    ];
+
+    let sol_withdraw_authority = if let Some(withdraw_authority) = config.funding_authority.as_ref()
+    {
+        let expected_sol_withdraw_authority =
+            stake_pool.sol_withdraw_authority.ok_or_else(|| {
+                "SOL withdraw authority specified in arguments but stake pool has none".to_string()
+            })?;
+        if withdraw_authority.pubkey() != expected_sol_withdraw_authority {
+            let error = format!(
+                "Invalid deposit withdraw specified, expected {}, received {}",
+                expected_sol_withdraw_authority,
+                withdraw_authority.pubkey()
+            );
+            return Err(error.into());
+        }
+        signers.push(withdraw_authority.as_ref());
+        Some(withdraw_authority.pubkey())
+    } else {
+        None
+    };
+
+    let instructions = vec![
+        system_instruction::create_account(
+            &config.fee_payer.pubkey(),
+            &withdrawal_ticket.pubkey(),
+            ticket_rent,
+            ticket_space as u64,
+            &spl_stake_pool::id(),
+        ),
+        // Approve spending token
+        spl_token_2022::instruction::approve(
+            &stake_pool.token_program_id,
+            &pool_token_account,
+            &user_transfer_authority.pubkey(),
+            &config.token_owner.pubkey(),
+            &[],
+            pool_amount,
+        )?,
+        spl_stake_pool::instruction::create_withdrawal_ticket(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &user_transfer_authority.pubkey(),
+            &pool_token_account,
+            &withdrawal_ticket.pubkey(),
+            &config.token_owner.pubkey(),
+            &stake_pool.manager_fee_account,
+            &stake_pool.pool_mint,
+            &stake_pool.token_program_id,
+            sol_withdraw_authority.as_ref(),
+            pool_amount,
+        ),
+    ];
+
+    let recent_blockhash = get_latest_blockhash(&config.rpc_client)?;
+    let message = Message::new_with_blockhash(
+        &instructions,
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+    check_fee_payer_balance(
+        config,
+        ticket_rent + config.rpc_client.get_fee_for_message(&message)?,
+    )?;
+    unique_signers!(signers);
+    let transaction = Transaction::new(&signers, message, recent_blockhash);
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_claim_withdrawal_ticket(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    withdrawal_ticket: &Pubkey,
+    sol_receiver: &Pubkey,
+) -> CommandResult {
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.token_owner.as_ref()];
+    unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::claim_withdrawal_ticket(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &stake_pool.reserve_stake,
+            withdrawal_ticket,
+            &config.token_owner.pubkey(),
+            sol_receiver,
+        )],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_set_manager(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    new_manager: &Option<Box<dyn Signer>>,
+    new_fee_receiver: &Option<Pubkey>,
+) -> CommandResult {
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+
+    // If new accounts are missing in the arguments use the old ones
+    let (new_manager_pubkey, mut signers): (Pubkey, Vec<&dyn Signer>) = match new_manager {
+        None => (stake_pool.manager, vec![]),
+        Some(value) => (value.pubkey(), vec![value.as_ref()]),
+    };
+
+    let new_fee_receiver = match new_fee_receiver {
+        None => stake_pool.manager_fee_account,
+        Some(value) => {
+            // Check for fee receiver being a valid token account and have to same mint as the stake pool
+            let token_account =
+                get_token_account(&config.rpc_client, value, &stake_pool.pool_mint)?;
+            if token_account.mint != stake_pool.pool_mint {
+                return Err("Fee receiver account belongs to a different mint"
+                    .to_string()
+                    .into());
+            }
+            *value
+        }
+    };
+
+    signers.append(&mut vec![
+        config.fee_payer.as_ref(),
+        config.manager.as_ref(),
+    ]);
+    unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::set_manager(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.manager.pubkey(),
+            &new_manager_pubkey,
+            &new_fee_receiver,
+        )],
+        &signers,
+    )?;
     send_transaction(config, transaction)?;
     Ok(())
 }
@@ -1868,6 +2296,137 @@ fn command_set_fee(
     Ok(())
 }
 
+fn command_create_token_metadata(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> CommandResult {
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.manager.as_ref()];
+    unique_signers!(signers);
+    let instruction = if stake_pool.token_program_id == spl_token_2022::id() {
+        spl_stake_pool::instruction::create_token_2022_metadata(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.manager.pubkey(),
+            &stake_pool.pool_mint,
+            &config.fee_payer.pubkey(),
+            name,
+            symbol,
+            uri,
+        )
+    } else {
+        spl_stake_pool::instruction::create_token_metadata(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.manager.pubkey(),
+            &stake_pool.pool_mint,
+            &config.fee_payer.pubkey(),
+            name,
+            symbol,
+            uri,
+        )
+    };
+    let transaction = checked_transaction_with_signers(config, &[instruction], &signers)?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_update_token_metadata(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> CommandResult {
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.manager.as_ref()];
+    unique_signers!(signers);
+    let instruction = if stake_pool.token_program_id == spl_token_2022::id() {
+        spl_stake_pool::instruction::update_token_2022_metadata(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.manager.pubkey(),
+            &stake_pool.pool_mint,
+            &config.fee_payer.pubkey(),
+            name,
+            symbol,
+            uri,
+        )
+    } else {
+        spl_stake_pool::instruction::update_token_metadata(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.manager.pubkey(),
+            &stake_pool.pool_mint,
+            name,
+            symbol,
+            uri,
+        )
+    };
+    let transaction = checked_transaction_with_signers(config, &[instruction], &signers)?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_set_validator_policy(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    max_commission: u8,
+    max_delinquent_epochs: u64,
+) -> CommandResult {
+    let mut signers = vec![config.fee_payer.as_ref(), config.manager.as_ref()];
+    unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::set_validator_policy(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.manager.pubkey(),
+            &config.fee_payer.pubkey(),
+            max_commission,
+            max_delinquent_epochs,
+        )],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_flag_validator(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    vote_account: &Pubkey,
+) -> CommandResult {
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+
+    let signers = vec![config.fee_payer.as_ref()];
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::flag_validator(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &stake_pool.validator_list,
+            vote_account,
+        )],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
 fn command_list_all_pools(config: &Config) -> CommandResult {
     let all_pools = get_stake_pools(&config.rpc_client)?;
     let cli_stake_pool_vec: Vec<CliStakePool> =
@@ -2110,6 +2669,13 @@ fn main() {
                     .takes_value(false)
                     .help("Bypass fee checks, allowing pool to be created with unsafe fees"),
             )
+            .arg(
+                Arg::with_name("token_2022")
+                    .long("token-2022")
+                    .takes_value(false)
+                    .help("Create the pool token mint with the Token-2022 program, \
+                           allowing token metadata to be stored in the mint itself"),
+            )
         )
         .subcommand(SubCommand::with_name("add-validator")
             .about("Add validator account to the stake pool. Must be signed by the pool staker.")
@@ -2194,21 +2760,145 @@ fn main() {
                     .help("Stake pool address"),
             )
             .arg(
-                Arg::with_name("vote_account")
+                Arg::with_name("vote_account")
+                    .index(2)
+                    .validator(is_pubkey)
+                    .value_name("VOTE_ACCOUNT_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Vote account for the validator to decrease stake from"),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(3)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .help("Amount in SOL to remove from the validator stake account. Must be at least the rent-exempt amount for a stake."),
+            )
+        )
+        .subcommand(SubCommand::with_name("increase-additional-validator-stake")
+            .about("Increase stake to a validator, even if its transient stake account is already activating. Must be signed by the pool staker.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address"),
+            )
+            .arg(
+                Arg::with_name("vote_account")
+                    .index(2)
+                    .validator(is_pubkey)
+                    .value_name("VOTE_ACCOUNT_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Vote account for the validator to increase stake to"),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(3)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .help("Amount in SOL to add to the validator stake account. Must be at least the rent-exempt amount for a stake plus 1 SOL for merging."),
+            )
+            .arg(
+                Arg::with_name("ephemeral_seed")
+                    .long("ephemeral-seed")
+                    .validator(is_parsable::<u64>)
+                    .value_name("SEED")
+                    .takes_value(true)
+                    .default_value("0")
+                    .help("Seed for the ephemeral stake account used during the operation"),
+            )
+        )
+        .subcommand(SubCommand::with_name("decrease-additional-validator-stake")
+            .about("Decrease stake to a validator, even if its transient stake account is already deactivating. Must be signed by the pool staker.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address"),
+            )
+            .arg(
+                Arg::with_name("vote_account")
+                    .index(2)
+                    .validator(is_pubkey)
+                    .value_name("VOTE_ACCOUNT_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Vote account for the validator to decrease stake from"),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(3)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .help("Amount in SOL to remove from the validator stake account. Must be at least the rent-exempt amount for a stake."),
+            )
+            .arg(
+                Arg::with_name("ephemeral_seed")
+                    .long("ephemeral-seed")
+                    .validator(is_parsable::<u64>)
+                    .value_name("SEED")
+                    .takes_value(true)
+                    .default_value("0")
+                    .help("Seed for the ephemeral stake account used during the operation"),
+            )
+        )
+        .subcommand(SubCommand::with_name("redelegate")
+            .about("Move stake from one validator to another without passing through the reserve. Must be signed by the pool staker.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address"),
+            )
+            .arg(
+                Arg::with_name("source_vote_account")
                     .index(2)
                     .validator(is_pubkey)
-                    .value_name("VOTE_ACCOUNT_ADDRESS")
+                    .value_name("SOURCE_VOTE_ACCOUNT_ADDRESS")
                     .takes_value(true)
                     .required(true)
-                    .help("Vote account for the validator to decrease stake from"),
+                    .help("Vote account for the validator to move stake from"),
             )
             .arg(
-                Arg::with_name("amount")
+                Arg::with_name("destination_vote_account")
                     .index(3)
+                    .validator(is_pubkey)
+                    .value_name("DESTINATION_VOTE_ACCOUNT_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Vote account for the validator to move stake to"),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(4)
                     .validator(is_amount)
                     .value_name("AMOUNT")
                     .takes_value(true)
-                    .help("Amount in SOL to remove from the validator stake account. Must be at least the rent-exempt amount for a stake."),
+                    .required(true)
+                    .help("Amount in SOL to redelegate. Must be at least twice the rent-exempt amount for a stake plus the minimum delegation."),
+            )
+            .arg(
+                Arg::with_name("ephemeral_seed")
+                    .long("ephemeral-seed")
+                    .validator(is_parsable::<u64>)
+                    .value_name("SEED")
+                    .takes_value(true)
+                    .default_value("0")
+                    .help("Seed for the ephemeral stake account used during the operation"),
             )
         )
         .subcommand(SubCommand::with_name("set-preferred-validator")
@@ -2391,6 +3081,48 @@ fn main() {
                           Defaults to the token receiver."),
             )
         )
+        .subcommand(SubCommand::with_name("deposit-rewards")
+            .about("Deposit reward SOL, such as MEV tips, into the stake pool reserve. \
+                    No pool tokens are minted, the rewards are added to the pool at the first update of the next epoch.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address"),
+            ).arg(
+                Arg::with_name("amount")
+                    .index(2)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Amount in SOL to deposit into the stake pool reserve account."),
+            )
+            .arg(
+                Arg::with_name("from")
+                    .long("from")
+                    .validator(is_valid_signer)
+                    .value_name("KEYPAIR")
+                    .takes_value(true)
+                    .help("Source account of funds. [default: cli config keypair]"),
+            )
+        )
+        .subcommand(SubCommand::with_name("realloc-stake-pool")
+            .about("Grow a stake pool account created by an earlier version of the program \
+                    to hold the current stake pool layout. The fee payer funds the extra rent.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address"),
+            )
+        )
         .subcommand(SubCommand::with_name("list")
             .about("List stake accounts managed by this pool")
             .arg(
@@ -2521,6 +3253,64 @@ fn main() {
                     .help("Pool token account to withdraw tokens from. Defaults to the token-owner's associated token account."),
             )
         )
+        .subcommand(SubCommand::with_name("create-withdrawal-ticket")
+            .about("Burn pool tokens in exchange for a withdrawal ticket, which can be claimed for SOL from the reserve starting next epoch")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(2)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Amount of pool tokens to burn for the ticket."),
+            )
+            .arg(
+                Arg::with_name("pool_account")
+                    .long("pool-account")
+                    .validator(is_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Pool token account to withdraw tokens from. Defaults to the token-owner's associated token account."),
+            )
+        )
+        .subcommand(SubCommand::with_name("claim-withdrawal-ticket")
+            .about("Claim the SOL owed by a withdrawal ticket. Must be signed by the token owner that created the ticket.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("withdrawal_ticket")
+                    .index(2)
+                    .validator(is_pubkey)
+                    .value_name("TICKET_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Withdrawal ticket to claim."),
+            )
+            .arg(
+                Arg::with_name("sol_receiver")
+                    .long("sol-receiver")
+                    .validator(is_valid_pubkey)
+                    .value_name("SYSTEM_ACCOUNT_ADDRESS_OR_KEYPAIR")
+                    .takes_value(true)
+                    .help("System account to receive SOL from the stake pool. Defaults to the payer."),
+            )
+        )
         .subcommand(SubCommand::with_name("set-manager")
             .about("Change manager or fee receiver account for the stake pool. Must be signed by the current manager.")
             .arg(
@@ -2682,6 +3472,129 @@ fn main() {
                     .help("Fee percentage, maximum 100"),
             )
         )
+        .subcommand(SubCommand::with_name("create-token-metadata")
+            .about("Create metadata for the stake pool token. Must be signed by the manager.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("name")
+                    .index(2)
+                    .value_name("TOKEN_NAME")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Name of the pool token."),
+            )
+            .arg(
+                Arg::with_name("symbol")
+                    .index(3)
+                    .value_name("TOKEN_SYMBOL")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Symbol of the pool token."),
+            )
+            .arg(
+                Arg::with_name("uri")
+                    .index(4)
+                    .value_name("TOKEN_URI")
+                    .takes_value(true)
+                    .default_value("")
+                    .help("URI of the pool token metadata."),
+            )
+        )
+        .subcommand(SubCommand::with_name("update-token-metadata")
+            .about("Update metadata for the stake pool token. Must be signed by the manager.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("name")
+                    .index(2)
+                    .value_name("TOKEN_NAME")
+                    .takes_value(true)
+                    .required(true)
+                    .help("New name of the pool token."),
+            )
+            .arg(
+                Arg::with_name("symbol")
+                    .index(3)
+                    .value_name("TOKEN_SYMBOL")
+                    .takes_value(true)
+                    .required(true)
+                    .help("New symbol of the pool token."),
+            )
+            .arg(
+                Arg::with_name("uri")
+                    .index(4)
+                    .value_name("TOKEN_URI")
+                    .takes_value(true)
+                    .default_value("")
+                    .help("New URI of the pool token metadata."),
+            )
+        )
+        .subcommand(SubCommand::with_name("set-validator-policy")
+            .about("Create or update the validator policy of the stake pool. Must be signed by the manager.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("max_commission")
+                    .long("max-commission")
+                    .validator(is_parsable::<u8>)
+                    .value_name("PERCENTAGE")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Highest vote account commission allowed, between 0 and 100."),
+            )
+            .arg(
+                Arg::with_name("max_delinquent_epochs")
+                    .long("max-delinquent-epochs")
+                    .validator(is_parsable::<u64>)
+                    .value_name("EPOCHS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Most epochs a validator may go without earning vote credits."),
+            )
+        )
+        .subcommand(SubCommand::with_name("flag-validator")
+            .about("Flag a validator violating the validator policy for deactivation. Can be run by anyone.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("vote_account")
+                    .index(2)
+                    .validator(is_pubkey)
+                    .value_name("VOTE_ACCOUNT_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Vote account of the validator violating the policy."),
+            )
+        )
         .subcommand(SubCommand::with_name("list-all")
             .about("List information about all stake pools")
         )
@@ -2793,6 +3706,7 @@ fn main() {
             let mint_keypair = keypair_of(arg_matches, "mint_keypair");
             let reserve_keypair = keypair_of(arg_matches, "reserve_keypair");
             let unsafe_fees = arg_matches.is_present("unsafe_fees");
+            let token_2022 = arg_matches.is_present("token_2022");
             command_create_pool(
                 &config,
                 deposit_authority,
@@ -2815,6 +3729,7 @@ fn main() {
                 mint_keypair,
                 reserve_keypair,
                 unsafe_fees,
+                token_2022,
             )
         }
         ("add-validator", Some(arg_matches)) => {
@@ -2839,6 +3754,48 @@ fn main() {
             let amount = value_t_or_exit!(arg_matches, "amount", f64);
             command_decrease_validator_stake(&config, &stake_pool_address, &vote_account, amount)
         }
+        ("increase-additional-validator-stake", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let vote_account = pubkey_of(arg_matches, "vote_account").unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let ephemeral_seed = value_t_or_exit!(arg_matches, "ephemeral_seed", u64);
+            command_increase_additional_validator_stake(
+                &config,
+                &stake_pool_address,
+                &vote_account,
+                amount,
+                ephemeral_seed,
+            )
+        }
+        ("decrease-additional-validator-stake", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let vote_account = pubkey_of(arg_matches, "vote_account").unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let ephemeral_seed = value_t_or_exit!(arg_matches, "ephemeral_seed", u64);
+            command_decrease_additional_validator_stake(
+                &config,
+                &stake_pool_address,
+                &vote_account,
+                amount,
+                ephemeral_seed,
+            )
+        }
+        ("redelegate", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let source_vote_account = pubkey_of(arg_matches, "source_vote_account").unwrap();
+            let destination_vote_account =
+                pubkey_of(arg_matches, "destination_vote_account").unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let ephemeral_seed = value_t_or_exit!(arg_matches, "ephemeral_seed", u64);
+            command_redelegate(
+                &config,
+                &stake_pool_address,
+                &source_vote_account,
+                &destination_vote_account,
+                amount,
+                ephemeral_seed,
+            )
+        }
         ("set-preferred-validator", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
             let preferred_type = match arg_matches.value_of("preferred_type").unwrap() {
@@ -2895,6 +3852,16 @@ fn main() {
                 amount,
             )
         }
+        ("deposit-rewards", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let from = keypair_of(arg_matches, "from");
+            let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            command_deposit_rewards(&config, &stake_pool_address, &from, amount)
+        }
+        ("realloc-stake-pool", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            command_realloc_stake_pool(&config, &stake_pool_address)
+        }
         ("list", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
             command_list(&config, &stake_pool_address)
@@ -2944,6 +3911,37 @@ fn main() {
                 pool_amount,
             )
         }
+        ("create-withdrawal-ticket", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let pool_account = pubkey_of(arg_matches, "pool_account");
+            let pool_amount = value_t_or_exit!(arg_matches, "amount", f64);
+            command_create_withdrawal_ticket(
+                &config,
+                &stake_pool_address,
+                &pool_account,
+                pool_amount,
+            )
+        }
+        ("claim-withdrawal-ticket", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let withdrawal_ticket = pubkey_of(arg_matches, "withdrawal_ticket").unwrap();
+            let sol_receiver = get_signer(
+                arg_matches,
+                "sol_receiver",
+                &cli_config.keypair_path,
+                &mut wallet_manager,
+                SignerFromPathConfig {
+                    allow_null_signer: true,
+                },
+            )
+            .pubkey();
+            command_claim_withdrawal_ticket(
+                &config,
+                &stake_pool_address,
+                &withdrawal_ticket,
+                &sol_receiver,
+            )
+        }
         ("set-manager", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
 
@@ -3033,6 +4031,36 @@ fn main() {
             };
             command_set_fee(&config, &stake_pool_address, fee_type)
         }
+        ("create-token-metadata", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let name = value_t_or_exit!(arg_matches, "name", String);
+            let symbol = value_t_or_exit!(arg_matches, "symbol", String);
+            let uri = value_t_or_exit!(arg_matches, "uri", String);
+            command_create_token_metadata(&config, &stake_pool_address, name, symbol, uri)
+        }
+        ("update-token-metadata", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let name = value_t_or_exit!(arg_matches, "name", String);
+            let symbol = value_t_or_exit!(arg_matches, "symbol", String);
+            let uri = value_t_or_exit!(arg_matches, "uri", String);
+            command_update_token_metadata(&config, &stake_pool_address, name, symbol, uri)
+        }
+        ("set-validator-policy", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let max_commission = value_t_or_exit!(arg_matches, "max_commission", u8);
+            let max_delinquent_epochs = value_t_or_exit!(arg_matches, "max_delinquent_epochs", u64);
+            command_set_validator_policy(
+                &config,
+                &stake_pool_address,
+                max_commission,
+                max_delinquent_epochs,
+            )
+        }
+        ("flag-validator", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let vote_account = pubkey_of(arg_matches, "vote_account").unwrap();
+            command_flag_validator(&config, &stake_pool_address, &vote_account)
+        }
         ("list-all", _) => command_list_all_pools(&config),
         ("deposit-all-stake", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
@@ -70,6 +70,8 @@ pub(crate) struct CliStakePool {
     pub next_sol_withdrawal_fee: Option<CliStakePoolFee>,
     pub last_epoch_pool_token_supply: u64,
     pub last_epoch_total_lamports: u64,
+    pub withdrawal_ticket_lamports: u64,
+    pub pending_reward_lamports: u64,
     pub details: Option<CliStakePoolDetails>,
 }
 
@@ -396,6 +398,9 @@ impl From<StakeStatus> for CliStakePoolValidatorStakeStatus {
                 CliStakePoolValidatorStakeStatus::DeactivatingValidator
             }
             StakeStatus::DeactivatingAll => CliStakePoolValidatorStakeStatus::DeactivatingAll,
+            StakeStatus::FlaggedForDeactivation => {
+                CliStakePoolValidatorStakeStatus::FlaggedForDeactivation
+            }
         }
     }
 }
@@ -407,6 +412,7 @@ pub(crate) enum CliStakePoolValidatorStakeStatus {
     ReadyForRemoval,
     DeactivatingValidator,
     DeactivatingAll,
+    FlaggedForDeactivation,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -497,6 +503,8 @@ impl From<(Pubkey, StakePool, ValidatorList, Pubkey)> for CliStakePool {
                 .map(CliStakePoolFee::from),
             last_epoch_pool_token_supply: stake_pool.last_epoch_pool_token_supply,
             last_epoch_total_lamports: stake_pool.last_epoch_total_lamports,
+            withdrawal_ticket_lamports: stake_pool.withdrawal_ticket_lamports,
+            pending_reward_lamports: stake_pool.pending_reward_lamports,
             details: None,
         }
     }
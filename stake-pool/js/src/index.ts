@@ -1016,6 +1016,8 @@ export async function stakePoolInfo(connection: Connection, stakePoolAddress: Pu
     nextSolWithdrawalFee: stakePool.account.data.nextSolWithdrawalFee,
     lastEpochPoolTokenSupply: stakePool.account.data.lastEpochPoolTokenSupply.toString(),
     lastEpochTotalLamports: stakePool.account.data.lastEpochTotalLamports.toString(),
+    withdrawalTicketLamports: stakePool.account.data.withdrawalTicketLamports.toString(),
+    pendingRewardLamports: stakePool.account.data.pendingRewardLamports.toString(),
     details: {
       reserveStakeLamports: reserveStake?.lamports,
       reserveAccountStakeAddress: reserveAccountStakeAddress.toBase58(),
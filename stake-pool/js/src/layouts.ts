@@ -108,6 +108,8 @@ export interface StakePool {
   nextSolWithdrawalFee?: Fee | undefined;
   lastEpochPoolTokenSupply: BN;
   lastEpochTotalLamports: BN;
+  withdrawalTicketLamports: BN;
+  pendingRewardLamports: BN;
 }
 
 export const StakePoolLayout = struct<StakePool>([
@@ -141,12 +143,17 @@ export const StakePoolLayout = struct<StakePool>([
   option(struct(feeFields), 'nextSolWithdrawalFee'),
   u64('lastEpochPoolTokenSupply'),
   u64('lastEpochTotalLamports'),
+  u64('withdrawalTicketLamports'),
+  u64('pendingRewardLamports'),
 ]);
 
 export enum ValidatorStakeInfoStatus {
   Active,
   DeactivatingTransient,
   ReadyForRemoval,
+  DeactivatingValidator,
+  DeactivatingAll,
+  FlaggedForDeactivation,
 }
 
 export interface ValidatorStakeInfo {
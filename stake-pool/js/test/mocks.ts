@@ -70,6 +70,8 @@ export const stakePoolMock = {
   },
   lastEpochPoolTokenSupply: new BN(0),
   lastEpochTotalLamports: new BN(0),
+  withdrawalTicketLamports: new BN(0),
+  pendingRewardLamports: new BN(0),
 };
 
 export const validatorListMock = {
@@ -144,6 +144,25 @@ pub enum StakePoolError {
     /// Instruction exceeds desired slippage limit
     #[error("Instruction exceeds desired slippage limit")]
     ExceededSlippage,
+
+    // 40.
+    /// Provided validator policy account does not match the one derived for the pool
+    #[error("InvalidValidatorPolicy")]
+    InvalidValidatorPolicy,
+    /// Validator meets all criteria of the validator policy
+    #[error("ValidatorPolicyNotViolated")]
+    ValidatorPolicyNotViolated,
+    /// Provided withdrawal ticket is invalid or belongs to another stake pool
+    #[error("InvalidWithdrawalTicket")]
+    InvalidWithdrawalTicket,
+    /// Withdrawal ticket cannot be claimed yet, either because its epoch has
+    /// not been reached or because the reserve does not hold enough lamports
+    #[error("WithdrawalTicketNotClaimable")]
+    WithdrawalTicketNotClaimable,
+    /// Pool mint metadata must describe the mint itself and be controlled by
+    /// the stake pool withdraw authority
+    #[error("InvalidPoolMintMetadata")]
+    InvalidPoolMintMetadata,
 }
 impl From<StakePoolError> for ProgramError {
     fn from(e: StakePoolError) -> Self {
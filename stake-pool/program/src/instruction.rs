@@ -6,7 +6,7 @@ use {
     crate::{
         find_deposit_authority_program_address, find_ephemeral_stake_program_address,
         find_stake_program_address, find_transient_stake_program_address,
-        find_withdraw_authority_program_address,
+        find_validator_policy_program_address, find_withdraw_authority_program_address,
         inline_mpl_token_metadata::{self, pda::find_metadata_account},
         state::{Fee, FeeType, StakePool, ValidatorList},
         MAX_VALIDATORS_TO_UPDATE,
@@ -369,6 +369,12 @@ pub enum StakePoolInstruction {
 
     /// Create token metadata for the stake-pool token in the
     /// metaplex-token program
+    ///
+    /// If the pool mint is a Token-2022 mint whose metadata pointer points to
+    /// itself, the metadata is instead written into the mint, by passing the
+    /// pool mint as the token metadata account and the Token-2022 program as
+    /// the metadata program.
+    ///
     /// 0. `[]` Stake pool
     /// 1. `[s]` Manager
     /// 2. `[]` Stake pool withdraw authority
@@ -388,11 +394,18 @@ pub enum StakePoolInstruction {
     /// Update token metadata for the stake-pool token in the
     /// metaplex-token program
     ///
+    /// For a Token-2022 pool mint holding its own metadata, pass the pool mint
+    /// as the token metadata account and the Token-2022 program as the
+    /// metadata program. If the mint needs more lamports to hold the new
+    /// metadata, the payer and system program must also be provided.
+    ///
     /// 0. `[]` Stake pool
     /// 1. `[s]` Manager
     /// 2. `[]` Stake pool withdraw authority
     /// 3. `[w]` Token metadata account
     /// 4. `[]` Metadata program id
+    /// 5. `[s, w]` (Optional) Payer for the additional mint rent
+    /// 6. `[]` (Optional) System program id
     UpdateTokenMetadata {
         /// Token name
         name: String,
@@ -630,6 +643,109 @@ pub enum StakePoolInstruction {
         /// Minimum amount of lamports that must be received
         minimum_lamports_out: u64,
     },
+
+    ///   (Manager only) Create or update the pool's validator policy, which
+    ///   allows anyone to flag validators that violate it for deactivation
+    ///   using `FlagValidator`.
+    ///
+    ///   0. `[]` Stake pool
+    ///   1. `[s]` Manager
+    ///   2. `[w]` Validator policy account, derived from the stake pool address
+    ///   3. `[s, w]` Payer, funds the validator policy account on creation
+    ///   4. `[]` System program
+    SetValidatorPolicy {
+        /// Maximum commission, in percent, that a validator may charge
+        max_commission: u8,
+        /// Maximum number of epochs that a validator may go without earning
+        /// vote credits
+        max_delinquent_epochs: u64,
+    },
+
+    ///   Flag a validator that violates the pool's validator policy, either
+    ///   by charging too much commission or by being delinquent. Its stake is
+    ///   deactivated during the next `UpdateValidatorListBalance`, then moved
+    ///   to the reserve, exactly as with `RemoveValidatorFromPool`.
+    ///
+    ///   A vote account without any epoch credits is never delinquent, since
+    ///   its credits history is not yet one full epoch long.
+    ///
+    ///   Anyone may call this instruction.
+    ///
+    ///   0. `[w]` Stake pool
+    ///   1. `[w]` Validator stake list storage account
+    ///   2. `[]` Validator policy account
+    ///   3. `[]` Validator vote account
+    ///   4. `[]` Sysvar clock
+    FlagValidator,
+
+    ///   Burn pool tokens in exchange for a withdrawal ticket, recording the
+    ///   lamports owed at the current exchange rate. The lamports stop
+    ///   counting towards the pool's `total_lamports` immediately, and the
+    ///   ticket can be claimed for SOL from the reserve starting next epoch.
+    ///
+    ///   The staker is expected to deactivate enough stake to cover the
+    ///   outstanding tickets, see `StakePool::withdrawal_ticket_lamports`.
+    ///
+    ///   The withdrawal ticket account must be created by the caller with
+    ///   enough space for a `WithdrawalTicket`, owned by the stake pool
+    ///   program, and rent-exempt.
+    ///
+    ///   0. `[w]` Stake pool
+    ///   1. `[s]` User transfer authority, for pool token account
+    ///   2. `[w]` User account to burn pool tokens
+    ///   3. `[w]` Uninitialized withdrawal ticket account
+    ///   4. `[]` Ticket owner, allowed to claim the ticket
+    ///   5. `[w]` Account to receive pool fee tokens
+    ///   6. `[w]` Pool token mint account
+    ///   7. `[]` Sysvar clock
+    ///   8. `[]` Token program id
+    ///   9. `[s]` (Optional) Stake pool sol withdraw authority
+    CreateWithdrawalTicket {
+        /// Pool tokens to burn
+        pool_tokens_in: u64,
+    },
+
+    ///   Claim the lamports owed by a withdrawal ticket from the reserve, and
+    ///   close the ticket. Fails if the ticket's epoch has not been reached,
+    ///   or if the reserve does not hold enough lamports yet.
+    ///
+    ///   0. `[w]` Stake pool
+    ///   1. `[]` Stake pool withdraw authority
+    ///   2. `[w]` Reserve stake account
+    ///   3. `[w]` Withdrawal ticket account
+    ///   4. `[s]` Ticket owner
+    ///   5. `[w]` Account receiving the lamports owed and the ticket's rent,
+    ///            must be a system account
+    ///   6. `[]` Sysvar clock
+    ///   7. `[]` Sysvar stake history
+    ///   8. `[]` Stake program
+    ClaimWithdrawalTicket,
+
+    ///   Deposit reward lamports, such as MEV tips or priority fees, into the
+    ///   stake pool reserve. No pool tokens are minted: the lamports are added
+    ///   to `total_lamports` by the first `UpdateStakePoolBalance` of the next
+    ///   epoch, which takes the epoch fee on them as on any other reward.
+    ///   The stake pool must be updated for the current epoch.
+    ///
+    ///   0. `[w]` Stake pool
+    ///   1. `[w]` Reserve stake account
+    ///   2. `[s, w]` Account providing the reward lamports
+    ///   3. `[]` System program account
+    ///
+    ///   userdata: amount of lamports to deposit
+    DepositRewards(u64),
+
+    ///   Grow a stake pool account created before the withdrawal ticket and
+    ///   reward fields were added to `StakePool`, so that it can hold the
+    ///   current layout. The new fields start at zero. Does nothing if the
+    ///   account is already large enough.
+    ///
+    ///   Anyone may call this instruction.
+    ///
+    ///   0. `[w]` Stake pool
+    ///   1. `[s, w]` Payer, funds the rent-exemption of the added space
+    ///   2. `[]` System program
+    ReallocStakePool,
 }
 
 /// Creates an 'initialize' instruction.
@@ -2275,3 +2391,227 @@ pub fn create_token_metadata(
             .unwrap(),
     }
 }
+
+/// Creates an instruction to write metadata into a Token-2022 pool mint with
+/// the metadata pointer extension pointing to itself
+pub fn create_token_2022_metadata(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    manager: &Pubkey,
+    pool_mint: &Pubkey,
+    payer: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Instruction {
+    let (stake_pool_withdraw_authority, _) =
+        find_withdraw_authority_program_address(program_id, stake_pool);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*stake_pool, false),
+        AccountMeta::new_readonly(*manager, true),
+        AccountMeta::new_readonly(stake_pool_withdraw_authority, false),
+        AccountMeta::new(*pool_mint, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*pool_mint, false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::CreateTokenMetadata { name, symbol, uri }
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates an instruction to update the metadata held in a Token-2022 pool mint
+pub fn update_token_2022_metadata(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    manager: &Pubkey,
+    pool_mint: &Pubkey,
+    payer: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Instruction {
+    let (stake_pool_withdraw_authority, _) =
+        find_withdraw_authority_program_address(program_id, stake_pool);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*stake_pool, false),
+        AccountMeta::new_readonly(*manager, true),
+        AccountMeta::new_readonly(stake_pool_withdraw_authority, false),
+        AccountMeta::new(*pool_mint, false),
+        AccountMeta::new_readonly(spl_token_2022::id(), false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::UpdateTokenMetadata { name, symbol, uri }
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a `SetValidatorPolicy` instruction
+pub fn set_validator_policy(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    manager: &Pubkey,
+    payer: &Pubkey,
+    max_commission: u8,
+    max_delinquent_epochs: u64,
+) -> Instruction {
+    let (validator_policy, _) = find_validator_policy_program_address(program_id, stake_pool);
+    let accounts = vec![
+        AccountMeta::new_readonly(*stake_pool, false),
+        AccountMeta::new_readonly(*manager, true),
+        AccountMeta::new(validator_policy, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::SetValidatorPolicy {
+            max_commission,
+            max_delinquent_epochs,
+        }
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a `FlagValidator` instruction
+pub fn flag_validator(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    validator_list: &Pubkey,
+    vote_account_address: &Pubkey,
+) -> Instruction {
+    let (validator_policy, _) = find_validator_policy_program_address(program_id, stake_pool);
+    let accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new(*validator_list, false),
+        AccountMeta::new_readonly(validator_policy, false),
+        AccountMeta::new_readonly(*vote_account_address, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::FlagValidator.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a `CreateWithdrawalTicket` instruction
+pub fn create_withdrawal_ticket(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    user_transfer_authority: &Pubkey,
+    pool_tokens_from: &Pubkey,
+    withdrawal_ticket: &Pubkey,
+    ticket_owner: &Pubkey,
+    manager_fee_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    sol_withdraw_authority: Option<&Pubkey>,
+    pool_tokens_in: u64,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new_readonly(*user_transfer_authority, true),
+        AccountMeta::new(*pool_tokens_from, false),
+        AccountMeta::new(*withdrawal_ticket, false),
+        AccountMeta::new_readonly(*ticket_owner, false),
+        AccountMeta::new(*manager_fee_account, false),
+        AccountMeta::new(*pool_mint, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    if let Some(sol_withdraw_authority) = sol_withdraw_authority {
+        accounts.push(AccountMeta::new_readonly(*sol_withdraw_authority, true));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::CreateWithdrawalTicket { pool_tokens_in }
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a `ClaimWithdrawalTicket` instruction
+pub fn claim_withdrawal_ticket(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    reserve_stake: &Pubkey,
+    withdrawal_ticket: &Pubkey,
+    ticket_owner: &Pubkey,
+    lamports_to: &Pubkey,
+) -> Instruction {
+    let stake_pool_withdraw_authority =
+        find_withdraw_authority_program_address(program_id, stake_pool).0;
+    let accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new_readonly(stake_pool_withdraw_authority, false),
+        AccountMeta::new(*reserve_stake, false),
+        AccountMeta::new(*withdrawal_ticket, false),
+        AccountMeta::new_readonly(*ticket_owner, true),
+        AccountMeta::new(*lamports_to, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+        AccountMeta::new_readonly(stake::program::id(), false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::ClaimWithdrawalTicket
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates instruction to deposit reward lamports into the stake pool reserve
+pub fn deposit_rewards(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    reserve_stake: &Pubkey,
+    lamports_from: &Pubkey,
+    lamports: u64,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new(*reserve_stake, false),
+        AccountMeta::new(*lamports_from, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::DepositRewards(lamports)
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates instruction to grow a stake pool account to the current layout
+pub fn realloc_stake_pool(program_id: &Pubkey, stake_pool: &Pubkey, payer: &Pubkey) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::ReallocStakePool.try_to_vec().unwrap(),
+    }
+}
@@ -32,6 +32,9 @@ const TRANSIENT_STAKE_SEED_PREFIX: &[u8] = b"transient";
 /// Seed for ephemeral stake account
 const EPHEMERAL_STAKE_SEED_PREFIX: &[u8] = b"ephemeral";
 
+/// Seed for validator policy account
+const VALIDATOR_POLICY_SEED_PREFIX: &[u8] = b"validator_policy";
+
 /// Minimum amount of staked lamports required in a validator stake account to allow
 /// for merges without a mismatch on credits observed
 pub const MINIMUM_ACTIVE_STAKE: u64 = 1_000_000;
@@ -158,6 +161,17 @@ pub fn find_ephemeral_stake_program_address(
     )
 }
 
+/// Generates the validator policy program address for the stake pool
+pub fn find_validator_policy_program_address(
+    program_id: &Pubkey,
+    stake_pool_address: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[VALIDATOR_POLICY_SEED_PREFIX, stake_pool_address.as_ref()],
+        program_id,
+    )
+}
+
 solana_program::declare_id!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
 
 #[cfg(test)]
@@ -14,17 +14,17 @@ use {
         minimum_delegation, minimum_reserve_lamports, minimum_stake_lamports,
         state::{
             is_extension_supported_for_mint, AccountType, Fee, FeeType, FutureEpoch, StakePool,
-            StakeStatus, StakeWithdrawSource, ValidatorList, ValidatorListHeader,
-            ValidatorStakeInfo,
+            StakeStatus, StakeWithdrawSource, ValidatorList, ValidatorListHeader, ValidatorPolicy,
+            ValidatorStakeInfo, WithdrawalTicket,
         },
         AUTHORITY_DEPOSIT, AUTHORITY_WITHDRAW, EPHEMERAL_STAKE_SEED_PREFIX,
-        TRANSIENT_STAKE_SEED_PREFIX,
+        TRANSIENT_STAKE_SEED_PREFIX, VALIDATOR_POLICY_SEED_PREFIX,
     },
     borsh::BorshDeserialize,
     num_traits::FromPrimitive,
     solana_program::{
         account_info::{next_account_info, AccountInfo},
-        borsh::try_from_slice_unchecked,
+        borsh::{get_packed_len, try_from_slice_unchecked},
         clock::{Clock, Epoch},
         decode_error::DecodeError,
         entrypoint::ProgramResult,
@@ -35,12 +35,16 @@ use {
         rent::Rent,
         stake, system_instruction, system_program,
         sysvar::Sysvar,
+        vote,
     },
     spl_token_2022::{
         check_spl_token_program_account,
-        extension::{BaseStateWithExtensions, StateWithExtensions},
+        extension::{
+            metadata_pointer::MetadataPointer, BaseStateWithExtensions, StateWithExtensions,
+        },
         state::Mint,
     },
+    spl_token_metadata_interface::state::{Field, TokenMetadata},
     std::num::NonZeroU32,
 };
 
@@ -56,6 +60,84 @@ fn get_stake_state(
     }
 }
 
+/// Size of a serialized `(Pubkey, Epoch, Epoch)` prior voters buffer in a
+/// vote account, including its index and emptiness flag
+const VOTE_PRIOR_VOTERS_SIZE: usize = 32 * (32 + 8 + 8) + 8 + 1;
+
+/// Read the commission and the most recent epoch in which the validator
+/// earned credits from a vote account, without deserializing the whole
+/// `VoteState`, which is too expensive on-chain
+fn get_vote_commission_and_last_credits_epoch(
+    vote_account_info: &AccountInfo,
+) -> Result<(u8, Option<Epoch>), ProgramError> {
+    check_account_owner(vote_account_info, &vote::program::id())?;
+    let data = vote_account_info.data.borrow();
+    let read_u64 = |offset: usize| -> Result<u64, ProgramError> {
+        data.get(offset..offset.saturating_add(8))
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(ProgramError::InvalidAccountData)
+    };
+    let read_len = |offset: usize, item_size: usize| -> Result<usize, ProgramError> {
+        usize::try_from(read_u64(offset)?)
+            .ok()
+            .and_then(|len| len.checked_mul(item_size))
+            .ok_or(ProgramError::InvalidAccountData)
+    };
+
+    // only the 1.14.11 and current layouts are supported, which differ in the
+    // size of each vote
+    let vote_size = match data.get(..4) {
+        Some([1, 0, 0, 0]) => 12,
+        Some([2, 0, 0, 0]) => 13,
+        _ => {
+            msg!("Unsupported vote account version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+    };
+    // version, node pubkey, authorized withdrawer
+    let commission_offset = 4 + 32 + 32;
+    let commission = *data
+        .get(commission_offset)
+        .ok_or(ProgramError::InvalidAccountData)?;
+
+    let mut offset = commission_offset + 1;
+    let votes_size = read_len(offset, vote_size)?;
+    offset = offset
+        .checked_add(8)
+        .and_then(|o| o.checked_add(votes_size))
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let root_slot_size = match data.get(offset) {
+        Some(0) => 1,
+        Some(1) => 9,
+        _ => return Err(ProgramError::InvalidAccountData),
+    };
+    offset = offset
+        .checked_add(root_slot_size)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let authorized_voters_size = read_len(offset, 8 + 32)?;
+    offset = offset
+        .checked_add(8)
+        .and_then(|o| o.checked_add(authorized_voters_size))
+        .and_then(|o| o.checked_add(VOTE_PRIOR_VOTERS_SIZE))
+        .ok_or(ProgramError::InvalidAccountData)?;
+
+    // each entry is (epoch, credits, previous credits)
+    let epoch_credits_len = read_len(offset, 1)?;
+    let last_credits_epoch = match epoch_credits_len.checked_sub(1) {
+        Some(last_index) => {
+            let last_offset = last_index
+                .checked_mul(24)
+                .and_then(|o| o.checked_add(offset))
+                .and_then(|o| o.checked_add(8))
+                .ok_or(ProgramError::InvalidAccountData)?;
+            Some(read_u64(last_offset)?)
+        }
+        None => None,
+    };
+    Ok((commission, last_credits_epoch))
+}
+
 /// Check validity of vote address for a particular stake account
 fn check_validator_stake_address(
     program_id: &Pubkey,
@@ -119,6 +201,26 @@ fn check_ephemeral_stake_address(
     }
 }
 
+/// Check address validity for the validator policy account
+fn check_validator_policy_address(
+    program_id: &Pubkey,
+    stake_pool_address: &Pubkey,
+    validator_policy_address: &Pubkey,
+) -> Result<u8, ProgramError> {
+    let (expected_address, bump_seed) =
+        crate::find_validator_policy_program_address(program_id, stake_pool_address);
+    if expected_address != *validator_policy_address {
+        msg!(
+            "Incorrect validator policy address, expected {}, received {}",
+            expected_address,
+            validator_policy_address
+        );
+        Err(StakePoolError::InvalidValidatorPolicy.into())
+    } else {
+        Ok(bump_seed)
+    }
+}
+
 /// Check mpl metadata account address for the pool mint
 fn check_mpl_metadata_account_address(
     metadata_address: &Pubkey,
@@ -174,6 +276,43 @@ fn check_mpl_metadata_program(program_id: &Pubkey) -> Result<(), ProgramError> {
     }
 }
 
+/// Check that the Token-2022 pool mint describes itself through its metadata
+/// pointer, and that nobody but the stake pool can change its metadata
+fn check_pool_mint_metadata(
+    pool_mint: &StateWithExtensions<Mint>,
+    pool_mint_address: &Pubkey,
+    withdraw_authority: &Pubkey,
+) -> Result<(), ProgramError> {
+    let metadata_pointer = match pool_mint.get_extension::<MetadataPointer>() {
+        Ok(metadata_pointer) => metadata_pointer,
+        Err(_) => return Ok(()),
+    };
+    if Option::<Pubkey>::from(metadata_pointer.metadata_address) != Some(*pool_mint_address) {
+        msg!("Pool mint metadata pointer must point to the pool mint itself");
+        return Err(StakePoolError::InvalidPoolMintMetadata.into());
+    }
+    if let Some(authority) = Option::<Pubkey>::from(metadata_pointer.authority) {
+        if authority != *withdraw_authority {
+            msg!(
+                "Pool mint metadata pointer authority must be empty or the withdraw authority {}, found {}",
+                withdraw_authority,
+                authority
+            );
+            return Err(StakePoolError::InvalidPoolMintMetadata.into());
+        }
+    }
+    if let Ok(token_metadata) = pool_mint.get_variable_len_extension::<TokenMetadata>() {
+        if Option::<Pubkey>::from(token_metadata.update_authority) != Some(*withdraw_authority) {
+            msg!(
+                "Pool mint metadata update authority must be the withdraw authority {}",
+                withdraw_authority
+            );
+            return Err(StakePoolError::InvalidPoolMintMetadata.into());
+        }
+    }
+    Ok(())
+}
+
 /// Check account owner is the given program
 fn check_account_owner(
     account_info: &AccountInfo,
@@ -847,6 +986,7 @@ impl Processor {
             {
                 return Err(StakePoolError::UnsupportedMintExtension.into());
             }
+            check_pool_mint_metadata(&pool_mint, pool_mint_info.key, &withdraw_authority_key)?;
         }
         stake_pool.check_manager_fee_info(manager_fee_info)?;
 
@@ -935,6 +1075,8 @@ impl Processor {
         stake_pool.next_sol_withdrawal_fee = FutureEpoch::None;
         stake_pool.last_epoch_pool_token_supply = 0;
         stake_pool.last_epoch_total_lamports = 0;
+        stake_pool.withdrawal_ticket_lamports = 0;
+        stake_pool.pending_reward_lamports = 0;
 
         borsh::to_writer(&mut stake_pool_info.data.borrow_mut()[..], &stake_pool)
             .map_err(|e| e.into())
@@ -1039,7 +1181,9 @@ impl Processor {
         let reserve_meta = reserve_stake
             .meta()
             .ok_or(StakePoolError::WrongStakeState)?;
-        let minimum_lamports = minimum_reserve_lamports(&reserve_meta);
+        // Lamports owed to withdrawal tickets must stay in the reserve
+        let minimum_lamports = minimum_reserve_lamports(&reserve_meta)
+            .saturating_add(stake_pool.withdrawal_ticket_lamports);
         let reserve_lamports = reserve_stake_info.lamports();
         if reserve_lamports.saturating_sub(required_lamports) < minimum_lamports {
             msg!(
@@ -1616,14 +1760,13 @@ impl Processor {
         // with `lamports + stake_rent`
         let total_lamports = lamports.saturating_add(stake_rent);
 
-        if reserve_stake_account_info
+        // Lamports owed to withdrawal tickets must stay in the reserve
+        let available_reserve_lamports = reserve_stake_account_info
             .lamports()
-            .saturating_sub(total_lamports)
-            <= stake_rent
-        {
-            let max_split_amount = reserve_stake_account_info
-                .lamports()
-                .saturating_sub(stake_rent.saturating_mul(2));
+            .saturating_sub(stake_pool.withdrawal_ticket_lamports);
+        if available_reserve_lamports.saturating_sub(total_lamports) <= stake_rent {
+            let max_split_amount =
+                available_reserve_lamports.saturating_sub(stake_rent.saturating_mul(2));
             msg!(
                 "Reserve stake does not have enough lamports for increase, must be less than {}, {} requested",
                 max_split_amount,
@@ -2243,6 +2386,11 @@ impl Processor {
                 &transient_stake_info.data.borrow(),
             )
             .ok();
+            let transient_stake_is_activating = matches!(
+                transient_stake_state,
+                Some(stake::state::StakeState::Stake(_, stake))
+                    if stake.delegation.deactivation_epoch == Epoch::MAX
+            );
 
             // Possible merge situations for transient stake
             //  * active -> merge into validator stake
@@ -2367,6 +2515,37 @@ impl Processor {
                         StakeStatus::Active => {
                             active_stake_lamports = validator_stake_info.lamports();
                         }
+                        StakeStatus::FlaggedForDeactivation => {
+                            active_stake_lamports = validator_stake_info.lamports();
+                            if no_merge
+                                || !stake_is_usable_by_pool(
+                                    &meta,
+                                    withdraw_authority_info.key,
+                                    &stake_pool.lockup,
+                                )
+                            {
+                                msg!("Validator stake on flagged validator {} can't be deactivated yet", validator_stake_record.vote_account_address);
+                            } else if transient_stake_lamports > 0 && transient_stake_is_activating
+                            {
+                                msg!("Transient stake activating on flagged validator {}, waiting to deactivate", validator_stake_record.vote_account_address);
+                            } else {
+                                // Validator violates the policy, start removing
+                                // it the same way as `RemoveValidatorFromPool`
+                                Self::stake_deactivate(
+                                    validator_stake_info.clone(),
+                                    clock_info.clone(),
+                                    withdraw_authority_info.clone(),
+                                    stake_pool_info.key,
+                                    AUTHORITY_WITHDRAW,
+                                    stake_pool.stake_withdraw_bump_seed,
+                                )?;
+                                validator_stake_record.status = if transient_stake_lamports > 0 {
+                                    StakeStatus::DeactivatingAll
+                                } else {
+                                    StakeStatus::DeactivatingValidator
+                                };
+                            }
+                        }
                         StakeStatus::DeactivatingValidator | StakeStatus::DeactivatingAll => {
                             if no_merge {
                                 active_stake_lamports = validator_stake_info.lamports();
@@ -2501,6 +2680,19 @@ impl Processor {
                 .checked_add(validator_stake_record.stake_lamports()?)
                 .ok_or(StakePoolError::CalculationFailure)?;
         }
+        // Lamports owed to withdrawal tickets no longer belong to pool token
+        // holders
+        total_lamports = total_lamports.saturating_sub(stake_pool.withdrawal_ticket_lamports);
+
+        // Rewards deposited since the last update only vest at the first
+        // update of a later epoch, so that nobody can capture them by
+        // depositing right before they are counted
+        let vested_reward_lamports = if stake_pool.last_update_epoch < clock.epoch {
+            stake_pool.pending_reward_lamports
+        } else {
+            total_lamports = total_lamports.saturating_sub(stake_pool.pending_reward_lamports);
+            0
+        };
 
         let reward_lamports = total_lamports.saturating_sub(previous_lamports);
 
@@ -2526,6 +2718,18 @@ impl Processor {
             )?;
         }
 
+        // Vested rewards are already in the reserve, and so are part of
+        // `reward_lamports`, all that's left is to clear the bucket
+        if vested_reward_lamports > 0 {
+            msg!(
+                "Rewards recognized: deposited lamports {}, total reward lamports {}, epoch fee pool tokens {}",
+                vested_reward_lamports,
+                reward_lamports,
+                fee
+            );
+            stake_pool.pending_reward_lamports = 0;
+        }
+
         if stake_pool.last_update_epoch < clock.epoch {
             if let Some(fee) = stake_pool.next_epoch_fee.get() {
                 stake_pool.epoch_fee = *fee;
@@ -3123,11 +3327,23 @@ impl Processor {
                 return Err(StakePoolError::StakeLamportsNotEqualToMinimum.into());
             }
 
-            // check that reserve has enough (should never fail, but who knows?)
-            stake_split_from
+            // check that reserve has enough, keeping the lamports owed to
+            // withdrawal tickets
+            let required_lamports = minimum_reserve_lamports(&meta)
+                .saturating_add(stake_pool.withdrawal_ticket_lamports);
+            if stake_split_from
                 .lamports()
-                .checked_sub(minimum_reserve_lamports(&meta))
-                .ok_or(StakePoolError::StakeLamportsNotEqualToMinimum)?;
+                .saturating_sub(withdraw_lamports)
+                < required_lamports
+            {
+                msg!(
+                    "Attempting to withdraw {} lamports from reserve with {} lamports, {} must remain",
+                    withdraw_lamports,
+                    stake_split_from.lamports(),
+                    required_lamports
+                );
+                return Err(StakePoolError::StakeLamportsNotEqualToMinimum.into());
+            }
             None
         } else {
             let delegation = stake_state
@@ -3400,7 +3616,9 @@ impl Processor {
             &reserve_stake_info.data.borrow(),
         )?;
         if let stake::state::StakeState::Initialized(meta) = stake_state {
-            let minimum_reserve_lamports = minimum_reserve_lamports(&meta);
+            // Lamports owed to withdrawal tickets must stay in the reserve
+            let minimum_reserve_lamports = minimum_reserve_lamports(&meta)
+                .saturating_add(stake_pool.withdrawal_ticket_lamports);
             if new_reserve_lamports < minimum_reserve_lamports {
                 msg!("Attempting to withdraw {} lamports, maximum possible SOL withdrawal is {} lamports",
                     withdraw_lamports,
@@ -3473,7 +3691,7 @@ impl Processor {
         let pool_mint_info = next_account_info(account_info_iter)?;
         let payer_info = next_account_info(account_info_iter)?;
         let metadata_info = next_account_info(account_info_iter)?;
-        let mpl_token_metadata_program_info = next_account_info(account_info_iter)?;
+        let metadata_program_info = next_account_info(account_info_iter)?;
         let system_program_info = next_account_info(account_info_iter)?;
 
         if !payer_info.is_signer {
@@ -3484,7 +3702,6 @@ impl Processor {
         check_system_program(system_program_info.key)?;
         check_account_owner(payer_info, &system_program::id())?;
         check_account_owner(stake_pool_info, program_id)?;
-        check_mpl_metadata_program(mpl_token_metadata_program_info.key)?;
 
         let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
         if !stake_pool.is_valid() {
@@ -3498,13 +3715,84 @@ impl Processor {
             stake_pool_info.key,
         )?;
         stake_pool.check_mint(pool_mint_info)?;
-        check_mpl_metadata_account_address(metadata_info.key, &stake_pool.pool_mint)?;
 
         // Token mint authority for stake-pool token is stake-pool withdraw authority
         let token_mint_authority = withdraw_authority_info;
 
+        let (_, stake_withdraw_bump_seed) =
+            crate::find_withdraw_authority_program_address(program_id, stake_pool_info.key);
+
+        let token_mint_authority_signer_seeds: &[&[_]] = &[
+            stake_pool_info.key.as_ref(),
+            AUTHORITY_WITHDRAW,
+            &[stake_withdraw_bump_seed],
+        ];
+
+        if metadata_info.key == pool_mint_info.key {
+            // Token-2022 pool mint, holding its own metadata
+            if stake_pool.token_program_id != *metadata_program_info.key {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            let token_metadata = {
+                let pool_mint_data = pool_mint_info.try_borrow_data()?;
+                let pool_mint = StateWithExtensions::<Mint>::unpack(&pool_mint_data)?;
+                if pool_mint.get_extension::<MetadataPointer>().is_err() {
+                    msg!("Pool mint does not have the metadata pointer extension");
+                    return Err(StakePoolError::InvalidPoolMintMetadata.into());
+                }
+                check_pool_mint_metadata(&pool_mint, pool_mint_info.key, token_mint_authority.key)?;
+                TokenMetadata {
+                    update_authority: Some(*token_mint_authority.key).try_into()?,
+                    mint: *pool_mint_info.key,
+                    name: name.clone(),
+                    symbol: symbol.clone(),
+                    uri: uri.clone(),
+                    ..TokenMetadata::default()
+                }
+            };
+
+            // the token program reallocates the mint, but expects it to
+            // already hold enough lamports for the new size
+            let new_len = pool_mint_info
+                .data_len()
+                .saturating_add(token_metadata.tlv_size_of()?);
+            let required_lamports = Rent::get()?
+                .minimum_balance(new_len)
+                .saturating_sub(pool_mint_info.lamports());
+            if required_lamports > 0 {
+                Self::sol_transfer(
+                    payer_info.clone(),
+                    pool_mint_info.clone(),
+                    required_lamports,
+                )?;
+            }
+
+            invoke_signed(
+                &spl_token_metadata_interface::instruction::initialize(
+                    metadata_program_info.key,
+                    pool_mint_info.key,
+                    token_mint_authority.key,
+                    pool_mint_info.key,
+                    token_mint_authority.key,
+                    name,
+                    symbol,
+                    uri,
+                ),
+                &[
+                    pool_mint_info.clone(),
+                    withdraw_authority_info.clone(),
+                    metadata_program_info.clone(),
+                ],
+                &[token_mint_authority_signer_seeds],
+            )?;
+            return Ok(());
+        }
+
+        check_mpl_metadata_program(metadata_program_info.key)?;
+        check_mpl_metadata_account_address(metadata_info.key, &stake_pool.pool_mint)?;
+
         let new_metadata_instruction = create_metadata_accounts_v3(
-            *mpl_token_metadata_program_info.key,
+            *metadata_program_info.key,
             *metadata_info.key,
             *pool_mint_info.key,
             *token_mint_authority.key,
@@ -3515,15 +3803,6 @@ impl Processor {
             uri,
         );
 
-        let (_, stake_withdraw_bump_seed) =
-            crate::find_withdraw_authority_program_address(program_id, stake_pool_info.key);
-
-        let token_mint_authority_signer_seeds: &[&[_]] = &[
-            stake_pool_info.key.as_ref(),
-            AUTHORITY_WITHDRAW,
-            &[stake_withdraw_bump_seed],
-        ];
-
         invoke_signed(
             &new_metadata_instruction,
             &[
@@ -3554,12 +3833,10 @@ impl Processor {
         let manager_info = next_account_info(account_info_iter)?;
         let withdraw_authority_info = next_account_info(account_info_iter)?;
         let metadata_info = next_account_info(account_info_iter)?;
-        let mpl_token_metadata_program_info = next_account_info(account_info_iter)?;
+        let metadata_program_info = next_account_info(account_info_iter)?;
 
         check_account_owner(stake_pool_info, program_id)?;
 
-        check_mpl_metadata_program(mpl_token_metadata_program_info.key)?;
-
         let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
         if !stake_pool.is_valid() {
             return Err(StakePoolError::InvalidState.into());
@@ -3571,13 +3848,84 @@ impl Processor {
             program_id,
             stake_pool_info.key,
         )?;
-        check_mpl_metadata_account_address(metadata_info.key, &stake_pool.pool_mint)?;
 
         // Token mint authority for stake-pool token is withdraw authority only
         let token_mint_authority = withdraw_authority_info;
 
+        let (_, stake_withdraw_bump_seed) =
+            crate::find_withdraw_authority_program_address(program_id, stake_pool_info.key);
+
+        let token_mint_authority_signer_seeds: &[&[_]] = &[
+            stake_pool_info.key.as_ref(),
+            AUTHORITY_WITHDRAW,
+            &[stake_withdraw_bump_seed],
+        ];
+
+        if *metadata_info.key == stake_pool.pool_mint {
+            // Token-2022 pool mint, holding its own metadata
+            if stake_pool.token_program_id != *metadata_program_info.key {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            let mut token_metadata = {
+                let pool_mint_data = metadata_info.try_borrow_data()?;
+                let pool_mint = StateWithExtensions::<Mint>::unpack(&pool_mint_data)?;
+                pool_mint.get_variable_len_extension::<TokenMetadata>()?
+            };
+
+            // every field update reallocates the mint, so it must hold enough
+            // lamports for the largest intermediate size
+            let fields = [
+                (Field::Name, name),
+                (Field::Symbol, symbol),
+                (Field::Uri, uri),
+            ];
+            let base_len = metadata_info
+                .data_len()
+                .saturating_sub(token_metadata.tlv_size_of()?);
+            let mut max_len = metadata_info.data_len();
+            for (field, value) in fields.iter() {
+                token_metadata.update(field.clone(), value.clone());
+                max_len = max_len.max(base_len.saturating_add(token_metadata.tlv_size_of()?));
+            }
+            let required_lamports = Rent::get()?
+                .minimum_balance(max_len)
+                .saturating_sub(metadata_info.lamports());
+            if required_lamports > 0 {
+                let payer_info = next_account_info(account_info_iter)?;
+                let system_program_info = next_account_info(account_info_iter)?;
+                if !payer_info.is_signer {
+                    msg!("Payer did not sign metadata update");
+                    return Err(StakePoolError::SignatureMissing.into());
+                }
+                check_system_program(system_program_info.key)?;
+                Self::sol_transfer(payer_info.clone(), metadata_info.clone(), required_lamports)?;
+            }
+
+            for (field, value) in fields.into_iter() {
+                invoke_signed(
+                    &spl_token_metadata_interface::instruction::update_field(
+                        metadata_program_info.key,
+                        metadata_info.key,
+                        token_mint_authority.key,
+                        field,
+                        value,
+                    ),
+                    &[
+                        metadata_info.clone(),
+                        withdraw_authority_info.clone(),
+                        metadata_program_info.clone(),
+                    ],
+                    &[token_mint_authority_signer_seeds],
+                )?;
+            }
+            return Ok(());
+        }
+
+        check_mpl_metadata_program(metadata_program_info.key)?;
+        check_mpl_metadata_account_address(metadata_info.key, &stake_pool.pool_mint)?;
+
         let update_metadata_accounts_instruction = update_metadata_accounts_v2(
-            *mpl_token_metadata_program_info.key,
+            *metadata_program_info.key,
             *metadata_info.key,
             *token_mint_authority.key,
             None,
@@ -3594,15 +3942,6 @@ impl Processor {
             Some(true),
         );
 
-        let (_, stake_withdraw_bump_seed) =
-            crate::find_withdraw_authority_program_address(program_id, stake_pool_info.key);
-
-        let token_mint_authority_signer_seeds: &[&[_]] = &[
-            stake_pool_info.key.as_ref(),
-            AUTHORITY_WITHDRAW,
-            &[stake_withdraw_bump_seed],
-        ];
-
         invoke_signed(
             &update_metadata_accounts_instruction,
             &[metadata_info.clone(), withdraw_authority_info.clone()],
@@ -3729,6 +4068,538 @@ impl Processor {
         Ok(())
     }
 
+    /// Processes [SetValidatorPolicy](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_set_validator_policy(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        max_commission: u8,
+        max_delinquent_epochs: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let manager_info = next_account_info(account_info_iter)?;
+        let validator_policy_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_manager(manager_info)?;
+        let bump_seed = check_validator_policy_address(
+            program_id,
+            stake_pool_info.key,
+            validator_policy_info.key,
+        )?;
+        check_system_program(system_program_info.key)?;
+
+        if max_commission > 100 {
+            msg!(
+                "Maximum commission must be a percentage, {} provided",
+                max_commission
+            );
+            return Err(StakePoolError::InvalidValidatorPolicy.into());
+        }
+
+        if validator_policy_info.data_is_empty() {
+            let space = get_packed_len::<ValidatorPolicy>();
+            let required_lamports = Rent::get()?
+                .minimum_balance(space)
+                .saturating_sub(validator_policy_info.lamports());
+            if required_lamports > 0 {
+                invoke(
+                    &system_instruction::transfer(
+                        payer_info.key,
+                        validator_policy_info.key,
+                        required_lamports,
+                    ),
+                    &[
+                        payer_info.clone(),
+                        validator_policy_info.clone(),
+                        system_program_info.clone(),
+                    ],
+                )?;
+            }
+            let validator_policy_signer_seeds: &[&[_]] = &[
+                VALIDATOR_POLICY_SEED_PREFIX,
+                stake_pool_info.key.as_ref(),
+                &[bump_seed],
+            ];
+            invoke_signed(
+                &system_instruction::allocate(validator_policy_info.key, space as u64),
+                &[validator_policy_info.clone(), system_program_info.clone()],
+                &[validator_policy_signer_seeds],
+            )?;
+            invoke_signed(
+                &system_instruction::assign(validator_policy_info.key, program_id),
+                &[validator_policy_info.clone(), system_program_info.clone()],
+                &[validator_policy_signer_seeds],
+            )?;
+        } else {
+            check_account_owner(validator_policy_info, program_id)?;
+            let validator_policy =
+                try_from_slice_unchecked::<ValidatorPolicy>(&validator_policy_info.data.borrow())?;
+            if !validator_policy.is_valid() {
+                return Err(StakePoolError::InvalidState.into());
+            }
+        }
+
+        let validator_policy = ValidatorPolicy {
+            account_type: AccountType::ValidatorPolicy,
+            stake_pool: *stake_pool_info.key,
+            max_commission,
+            max_delinquent_epochs,
+        };
+        borsh::to_writer(
+            &mut validator_policy_info.data.borrow_mut()[..],
+            &validator_policy,
+        )?;
+        Ok(())
+    }
+
+    /// Processes [FlagValidator](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_flag_validator(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let validator_list_info = next_account_info(account_info_iter)?;
+        let validator_policy_info = next_account_info(account_info_iter)?;
+        let vote_account_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(clock_info)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_validator_list(validator_list_info)?;
+
+        check_validator_policy_address(program_id, stake_pool_info.key, validator_policy_info.key)?;
+        check_account_owner(validator_policy_info, program_id)?;
+        let validator_policy =
+            try_from_slice_unchecked::<ValidatorPolicy>(&validator_policy_info.data.borrow())?;
+        if !validator_policy.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        check_account_owner(validator_list_info, program_id)?;
+        let mut validator_list_data = validator_list_info.data.borrow_mut();
+        let (header, mut validator_list) =
+            ValidatorListHeader::deserialize_vec(&mut validator_list_data)?;
+        if !header.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        let vote_account_address = vote_account_info.key;
+        let validator_stake_info = validator_list
+            .find_mut::<ValidatorStakeInfo, _>(|x| {
+                ValidatorStakeInfo::memcmp_pubkey(x, vote_account_address)
+            })
+            .ok_or_else(|| {
+                msg!(
+                    "Vote account {} not found in stake pool",
+                    vote_account_address
+                );
+                StakePoolError::ValidatorNotFound
+            })?;
+        if validator_stake_info.status != StakeStatus::Active {
+            msg!("Validator is already marked for removal");
+            return Err(StakePoolError::ValidatorNotFound.into());
+        }
+
+        let (commission, last_credits_epoch) =
+            get_vote_commission_and_last_credits_epoch(vote_account_info)?;
+        if !validator_policy.is_violated_by(commission, last_credits_epoch, clock.epoch) {
+            msg!(
+                "Validator {} with commission {}% last earned credits in epoch {:?}, policy is not violated",
+                vote_account_address,
+                commission,
+                last_credits_epoch
+            );
+            return Err(StakePoolError::ValidatorPolicyNotViolated.into());
+        }
+        validator_stake_info.status = StakeStatus::FlaggedForDeactivation;
+
+        if stake_pool.preferred_deposit_validator_vote_address == Some(*vote_account_address) {
+            stake_pool.preferred_deposit_validator_vote_address = None;
+        }
+        if stake_pool.preferred_withdraw_validator_vote_address == Some(*vote_account_address) {
+            stake_pool.preferred_withdraw_validator_vote_address = None;
+        }
+        borsh::to_writer(&mut stake_pool_info.data.borrow_mut()[..], &stake_pool)?;
+
+        Ok(())
+    }
+
+    /// Processes [CreateWithdrawalTicket](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_create_withdrawal_ticket(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_tokens: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let user_transfer_authority_info = next_account_info(account_info_iter)?;
+        let burn_from_pool_info = next_account_info(account_info_iter)?;
+        let withdrawal_ticket_info = next_account_info(account_info_iter)?;
+        let ticket_owner_info = next_account_info(account_info_iter)?;
+        let manager_fee_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(clock_info)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let sol_withdraw_authority_info = next_account_info(account_info_iter);
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        stake_pool.check_sol_withdraw_authority(sol_withdraw_authority_info)?;
+        let decimals = stake_pool.check_mint(pool_mint_info)?;
+
+        if stake_pool.token_program_id != *token_program_info.key {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if stake_pool.manager_fee_account != *manager_fee_info.key {
+            return Err(StakePoolError::InvalidFeeAccount.into());
+        }
+
+        // The ticket must be priced at the current exchange rate
+        if stake_pool.last_update_epoch < clock.epoch {
+            return Err(StakePoolError::StakeListAndPoolOutOfDate.into());
+        }
+
+        check_account_owner(withdrawal_ticket_info, program_id)?;
+        if withdrawal_ticket_info.data_len() != get_packed_len::<WithdrawalTicket>() {
+            msg!(
+                "Withdrawal ticket account must have {} bytes of space, {} provided",
+                get_packed_len::<WithdrawalTicket>(),
+                withdrawal_ticket_info.data_len()
+            );
+            return Err(StakePoolError::InvalidWithdrawalTicket.into());
+        }
+        let withdrawal_ticket =
+            try_from_slice_unchecked::<WithdrawalTicket>(&withdrawal_ticket_info.data.borrow())?;
+        if !withdrawal_ticket.is_uninitialized() {
+            return Err(StakePoolError::AlreadyInUse.into());
+        }
+        if !Rent::get()?.is_exempt(
+            withdrawal_ticket_info.lamports(),
+            withdrawal_ticket_info.data_len(),
+        ) {
+            msg!("Withdrawal ticket account is not rent-exempt");
+            return Err(ProgramError::AccountNotRentExempt);
+        }
+
+        // To prevent a faulty manager fee account from preventing withdrawals
+        // if the token program does not own the account, or if the account is not initialized
+        let pool_tokens_fee = if stake_pool.manager_fee_account == *burn_from_pool_info.key
+            || stake_pool.check_manager_fee_info(manager_fee_info).is_err()
+        {
+            0
+        } else {
+            stake_pool
+                .calc_pool_tokens_sol_withdrawal_fee(pool_tokens)
+                .ok_or(StakePoolError::CalculationFailure)?
+        };
+        let pool_tokens_burnt = pool_tokens
+            .checked_sub(pool_tokens_fee)
+            .ok_or(StakePoolError::CalculationFailure)?;
+
+        let ticket_lamports = stake_pool
+            .calc_lamports_withdraw_amount(pool_tokens_burnt)
+            .ok_or(StakePoolError::CalculationFailure)?;
+
+        if ticket_lamports == 0 {
+            return Err(StakePoolError::WithdrawalTooSmall.into());
+        }
+
+        Self::token_burn(
+            token_program_info.clone(),
+            burn_from_pool_info.clone(),
+            pool_mint_info.clone(),
+            user_transfer_authority_info.clone(),
+            pool_tokens_burnt,
+        )?;
+
+        if pool_tokens_fee > 0 {
+            Self::token_transfer(
+                token_program_info.clone(),
+                burn_from_pool_info.clone(),
+                pool_mint_info.clone(),
+                manager_fee_info.clone(),
+                user_transfer_authority_info.clone(),
+                pool_tokens_fee,
+                decimals,
+            )?;
+        }
+
+        let withdrawal_ticket = WithdrawalTicket {
+            account_type: AccountType::WithdrawalTicket,
+            stake_pool: *stake_pool_info.key,
+            owner: *ticket_owner_info.key,
+            lamports: ticket_lamports,
+            claimable_epoch: clock
+                .epoch
+                .checked_add(1)
+                .ok_or(StakePoolError::CalculationFailure)?,
+        };
+        borsh::to_writer(
+            &mut withdrawal_ticket_info.data.borrow_mut()[..],
+            &withdrawal_ticket,
+        )?;
+
+        stake_pool.pool_token_supply = stake_pool
+            .pool_token_supply
+            .checked_sub(pool_tokens_burnt)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        stake_pool.total_lamports = stake_pool
+            .total_lamports
+            .checked_sub(ticket_lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        stake_pool.withdrawal_ticket_lamports = stake_pool
+            .withdrawal_ticket_lamports
+            .checked_add(ticket_lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        borsh::to_writer(&mut stake_pool_info.data.borrow_mut()[..], &stake_pool)?;
+
+        Ok(())
+    }
+
+    /// Processes [ClaimWithdrawalTicket](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_claim_withdrawal_ticket(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let withdraw_authority_info = next_account_info(account_info_iter)?;
+        let reserve_stake_info = next_account_info(account_info_iter)?;
+        let withdrawal_ticket_info = next_account_info(account_info_iter)?;
+        let ticket_owner_info = next_account_info(account_info_iter)?;
+        let destination_lamports_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(clock_info)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        stake_pool.check_authority_withdraw(
+            withdraw_authority_info.key,
+            program_id,
+            stake_pool_info.key,
+        )?;
+        stake_pool.check_reserve_stake(reserve_stake_info)?;
+        check_stake_program(stake_program_info.key)?;
+
+        // Deactivated stake must be merged into the reserve before claiming
+        if stake_pool.last_update_epoch < clock.epoch {
+            return Err(StakePoolError::StakeListAndPoolOutOfDate.into());
+        }
+
+        check_account_owner(withdrawal_ticket_info, program_id)?;
+        let withdrawal_ticket =
+            try_from_slice_unchecked::<WithdrawalTicket>(&withdrawal_ticket_info.data.borrow())?;
+        if !withdrawal_ticket.is_valid() || withdrawal_ticket.stake_pool != *stake_pool_info.key {
+            return Err(StakePoolError::InvalidWithdrawalTicket.into());
+        }
+        if withdrawal_ticket.owner != *ticket_owner_info.key {
+            msg!(
+                "Withdrawal ticket owned by {}, not {}",
+                withdrawal_ticket.owner,
+                ticket_owner_info.key
+            );
+            return Err(StakePoolError::InvalidWithdrawalTicket.into());
+        }
+        if !ticket_owner_info.is_signer {
+            msg!("Withdrawal ticket owner signature missing");
+            return Err(StakePoolError::SignatureMissing.into());
+        }
+
+        if clock.epoch < withdrawal_ticket.claimable_epoch {
+            msg!(
+                "Withdrawal ticket can only be claimed starting in epoch {}",
+                withdrawal_ticket.claimable_epoch
+            );
+            return Err(StakePoolError::WithdrawalTicketNotClaimable.into());
+        }
+
+        let stake_state = try_from_slice_unchecked::<stake::state::StakeState>(
+            &reserve_stake_info.data.borrow(),
+        )?;
+        if let stake::state::StakeState::Initialized(meta) = stake_state {
+            let available_lamports = reserve_stake_info
+                .lamports()
+                .saturating_sub(minimum_reserve_lamports(&meta));
+            if available_lamports < withdrawal_ticket.lamports {
+                msg!(
+                    "Reserve only has {} lamports available, {} required to claim the ticket, the staker must deactivate more stake",
+                    available_lamports,
+                    withdrawal_ticket.lamports
+                );
+                return Err(StakePoolError::WithdrawalTicketNotClaimable.into());
+            }
+        } else {
+            msg!("Reserve stake account not in intialized state");
+            return Err(StakePoolError::WrongStakeState.into());
+        };
+
+        Self::stake_withdraw(
+            stake_pool_info.key,
+            reserve_stake_info.clone(),
+            withdraw_authority_info.clone(),
+            AUTHORITY_WITHDRAW,
+            stake_pool.stake_withdraw_bump_seed,
+            destination_lamports_info.clone(),
+            clock_info.clone(),
+            stake_history_info.clone(),
+            withdrawal_ticket.lamports,
+        )?;
+
+        stake_pool.withdrawal_ticket_lamports = stake_pool
+            .withdrawal_ticket_lamports
+            .checked_sub(withdrawal_ticket.lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        borsh::to_writer(&mut stake_pool_info.data.borrow_mut()[..], &stake_pool)?;
+
+        // Close the ticket, returning its rent
+        let ticket_rent = withdrawal_ticket_info.lamports();
+        **withdrawal_ticket_info.lamports.borrow_mut() = 0;
+        **destination_lamports_info.lamports.borrow_mut() = destination_lamports_info
+            .lamports()
+            .checked_add(ticket_rent)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        withdrawal_ticket_info.data.borrow_mut().fill(0);
+
+        Ok(())
+    }
+
+    /// Processes [DepositRewards](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_deposit_rewards(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        lamports: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let reserve_stake_info = next_account_info(account_info_iter)?;
+        let from_lamports_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_reserve_stake(reserve_stake_info)?;
+        check_system_program(system_program_info.key)?;
+
+        // Pending rewards vest at the first update of a later epoch, which
+        // must not include rewards deposited during that epoch
+        if stake_pool.last_update_epoch < Clock::get()?.epoch {
+            return Err(StakePoolError::StakeListAndPoolOutOfDate.into());
+        }
+
+        if lamports == 0 {
+            return Err(StakePoolError::DepositTooSmall.into());
+        }
+
+        Self::sol_transfer(
+            from_lamports_info.clone(),
+            reserve_stake_info.clone(),
+            lamports,
+        )?;
+
+        stake_pool.pending_reward_lamports = stake_pool
+            .pending_reward_lamports
+            .checked_add(lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        borsh::to_writer(&mut stake_pool_info.data.borrow_mut()[..], &stake_pool)?;
+
+        msg!(
+            "Rewards deposited: lamports {}, from {}, pending reward lamports {}",
+            lamports,
+            from_lamports_info.key,
+            stake_pool.pending_reward_lamports
+        );
+
+        Ok(())
+    }
+
+    /// Processes [ReallocStakePool](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_realloc_stake_pool(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        check_system_program(system_program_info.key)?;
+        if !payer_info.is_signer {
+            msg!("Payer signature missing");
+            return Err(StakePoolError::SignatureMissing.into());
+        }
+
+        let new_len = get_packed_len::<StakePool>();
+        if stake_pool_info.data_len() >= new_len {
+            msg!("Stake pool account already holds the current layout");
+            return Ok(());
+        }
+
+        if stake_pool_info.data.borrow().first() != Some(&(AccountType::StakePool as u8)) {
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        // The previous layout is a prefix of the current one, but the bytes
+        // after its serialized end may be left over from a longer encoding,
+        // e.g. an `Option` field that went from `Some` to `None`. Read the
+        // previous fields from a zero-padded copy and reset the new ones
+        // explicitly instead of trusting whatever follows them.
+        let mut previous_data = stake_pool_info.data.borrow().to_vec();
+        previous_data.resize(new_len, 0);
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&previous_data)?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.withdrawal_ticket_lamports = 0;
+        stake_pool.pending_reward_lamports = 0;
+
+        stake_pool_info.realloc(new_len, true)?;
+
+        let required_lamports = Rent::get()?
+            .minimum_balance(new_len)
+            .saturating_sub(stake_pool_info.lamports());
+        if required_lamports > 0 {
+            Self::sol_transfer(
+                payer_info.clone(),
+                stake_pool_info.clone(),
+                required_lamports,
+            )?;
+        }
+
+        let mut stake_pool_data = stake_pool_info.data.borrow_mut();
+        stake_pool_data.fill(0);
+        borsh::to_writer(&mut stake_pool_data[..], &stake_pool)?;
+
+        Ok(())
+    }
+
     /// Processes [Instruction](enum.Instruction.html).
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
         let instruction = StakePoolInstruction::try_from_slice(input)?;
@@ -3943,6 +4814,38 @@ impl Processor {
                     Some(minimum_lamports_out),
                 )
             }
+            StakePoolInstruction::SetValidatorPolicy {
+                max_commission,
+                max_delinquent_epochs,
+            } => {
+                msg!("Instruction: SetValidatorPolicy");
+                Self::process_set_validator_policy(
+                    program_id,
+                    accounts,
+                    max_commission,
+                    max_delinquent_epochs,
+                )
+            }
+            StakePoolInstruction::FlagValidator => {
+                msg!("Instruction: FlagValidator");
+                Self::process_flag_validator(program_id, accounts)
+            }
+            StakePoolInstruction::CreateWithdrawalTicket { pool_tokens_in } => {
+                msg!("Instruction: CreateWithdrawalTicket");
+                Self::process_create_withdrawal_ticket(program_id, accounts, pool_tokens_in)
+            }
+            StakePoolInstruction::ClaimWithdrawalTicket => {
+                msg!("Instruction: ClaimWithdrawalTicket");
+                Self::process_claim_withdrawal_ticket(program_id, accounts)
+            }
+            StakePoolInstruction::DepositRewards(lamports) => {
+                msg!("Instruction: DepositRewards");
+                Self::process_deposit_rewards(program_id, accounts, lamports)
+            }
+            StakePoolInstruction::ReallocStakePool => {
+                msg!("Instruction: ReallocStakePool");
+                Self::process_realloc_stake_pool(program_id, accounts)
+            }
         }
     }
 }
@@ -3995,6 +4898,11 @@ impl PrintProgramError for StakePoolError {
             StakePoolError::UnsupportedMintExtension => msg!("Error: mint has an unsupported extension"),
             StakePoolError::UnsupportedFeeAccountExtension => msg!("Error: fee account has an unsupported extension"),
             StakePoolError::ExceededSlippage => msg!("Error: instruction exceeds desired slippage limit"),
+            StakePoolError::InvalidValidatorPolicy => msg!("Error: provided validator policy account is invalid"),
+            StakePoolError::ValidatorPolicyNotViolated => msg!("Error: validator does not violate the validator policy"),
+            StakePoolError::InvalidWithdrawalTicket => msg!("Error: provided withdrawal ticket is invalid"),
+            StakePoolError::WithdrawalTicketNotClaimable => msg!("Error: withdrawal ticket cannot be claimed yet"),
+            StakePoolError::InvalidPoolMintMetadata => msg!("Error: pool mint metadata must describe the mint itself and be controlled by the stake pool"),
         }
     }
 }
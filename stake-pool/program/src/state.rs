@@ -11,6 +11,7 @@ use {
     solana_program::{
         account_info::AccountInfo,
         borsh::get_instance_packed_len,
+        clock::Epoch,
         msg,
         program_error::ProgramError,
         program_memory::sol_memcmp,
@@ -35,6 +36,10 @@ pub enum AccountType {
     StakePool,
     /// Validator stake list
     ValidatorList,
+    /// Validator policy
+    ValidatorPolicy,
+    /// Withdrawal ticket
+    WithdrawalTicket,
 }
 
 /// Initialized program details.
@@ -150,6 +155,17 @@ pub struct StakePool {
 
     /// Last epoch's total lamports, used only for APR estimation
     pub last_epoch_total_lamports: u64,
+
+    /// Lamports owed to the holders of unclaimed withdrawal tickets. These
+    /// are still held in the pool's stake accounts, but are not part of
+    /// `total_lamports`
+    pub withdrawal_ticket_lamports: u64,
+
+    /// Lamports deposited into the reserve through `DepositRewards` during
+    /// `last_update_epoch`. They are left out of `total_lamports` until the
+    /// first `UpdateStakePoolBalance` of a later epoch, which adds them and
+    /// takes the epoch fee on them
+    pub pending_reward_lamports: u64,
 }
 impl StakePool {
     /// calculate the pool tokens that should be minted for a deposit of `stake_lamports`
@@ -572,12 +588,18 @@ pub enum StakeStatus {
     /// Both the transient and validator stake account are deactivating, when
     /// a validator is removed with a transient stake active
     DeactivatingAll,
+    /// Validator violates the pool's validator policy, its stake account will
+    /// be deactivated during the next `UpdateValidatorListBalance`
+    FlaggedForDeactivation,
 }
 impl StakeStatus {
     /// Downgrade the status towards ready for removal by removing the validator stake
     pub fn remove_validator_stake(&mut self) {
         let new_self = match self {
-            Self::Active | Self::DeactivatingTransient | Self::ReadyForRemoval => *self,
+            Self::Active
+            | Self::DeactivatingTransient
+            | Self::ReadyForRemoval
+            | Self::FlaggedForDeactivation => *self,
             Self::DeactivatingAll => Self::DeactivatingTransient,
             Self::DeactivatingValidator => Self::ReadyForRemoval,
         };
@@ -586,7 +608,10 @@ impl StakeStatus {
     /// Downgrade the status towards ready for removal by removing the transient stake
     pub fn remove_transient_stake(&mut self) {
         let new_self = match self {
-            Self::Active | Self::DeactivatingValidator | Self::ReadyForRemoval => *self,
+            Self::Active
+            | Self::DeactivatingValidator
+            | Self::ReadyForRemoval
+            | Self::FlaggedForDeactivation => *self,
             Self::DeactivatingAll => Self::DeactivatingValidator,
             Self::DeactivatingTransient => Self::ReadyForRemoval,
         };
@@ -846,6 +871,89 @@ impl<T> From<FutureEpoch<T>> for Option<T> {
     }
 }
 
+/// Claim on the stake pool's lamports, created by burning pool tokens and
+/// redeemable for SOL from the reserve once `claimable_epoch` is reached
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct WithdrawalTicket {
+    /// Account type, must be WithdrawalTicket currently
+    pub account_type: AccountType,
+
+    /// Stake pool that owes the lamports
+    pub stake_pool: Pubkey,
+
+    /// Account allowed to claim the ticket
+    pub owner: Pubkey,
+
+    /// Lamports owed to the owner
+    pub lamports: u64,
+
+    /// First epoch in which the ticket can be claimed
+    pub claimable_epoch: Epoch,
+}
+impl WithdrawalTicket {
+    /// Check if the withdrawal ticket is initialized
+    pub fn is_valid(&self) -> bool {
+        self.account_type == AccountType::WithdrawalTicket
+    }
+
+    /// Check if the withdrawal ticket is uninitialized
+    pub fn is_uninitialized(&self) -> bool {
+        self.account_type == AccountType::Uninitialized
+    }
+}
+
+/// Optional criteria that validators in the pool must meet, enforced by the
+/// permissionless `FlagValidator` instruction
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct ValidatorPolicy {
+    /// Account type, must be ValidatorPolicy currently
+    pub account_type: AccountType,
+
+    /// Stake pool that the policy applies to
+    pub stake_pool: Pubkey,
+
+    /// Maximum commission, in percent, that a validator may charge
+    pub max_commission: u8,
+
+    /// Maximum number of epochs that a validator may go without earning
+    /// vote credits
+    pub max_delinquent_epochs: u64,
+}
+impl ValidatorPolicy {
+    /// Check if the validator policy is initialized
+    pub fn is_valid(&self) -> bool {
+        self.account_type == AccountType::ValidatorPolicy
+    }
+
+    /// Check if the validator policy is uninitialized
+    pub fn is_uninitialized(&self) -> bool {
+        self.account_type == AccountType::Uninitialized
+    }
+
+    /// Check if a validator with the given commission, having last earned
+    /// credits in `last_credits_epoch`, violates the policy in `current_epoch`
+    ///
+    /// A validator is only judged delinquent once its credits history spans
+    /// at least one full epoch. Without any epoch credits, e.g. for a vote
+    /// account that has just started voting, only its commission is checked.
+    pub fn is_violated_by(
+        &self,
+        commission: u8,
+        last_credits_epoch: Option<Epoch>,
+        current_epoch: Epoch,
+    ) -> bool {
+        if commission > self.max_commission {
+            return true;
+        }
+        match last_credits_epoch {
+            Some(epoch) => current_epoch.saturating_sub(epoch) > self.max_delinquent_epochs,
+            None => false,
+        }
+    }
+}
+
 /// Fee rate as a ratio, minted on `UpdateStakePoolBalance` as a proportion of
 /// the rewards
 /// If either the numerator or the denominator is 0, the fee is considered to be 0
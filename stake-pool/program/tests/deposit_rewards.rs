@@ -0,0 +1,299 @@
+#![allow(clippy::integer_arithmetic)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{instruction::InstructionError, pubkey::Pubkey},
+    solana_program_test::*,
+    solana_sdk::{
+        signature::Signer,
+        transaction::{Transaction, TransactionError},
+    },
+    spl_stake_pool::{error::StakePoolError, id, instruction, MINIMUM_RESERVE_LAMPORTS},
+};
+
+const DEPOSIT_LAMPORTS: u64 = 10_000_000_000;
+const REWARD_LAMPORTS: u64 = 1_000_000_000;
+
+async fn setup() -> (ProgramTestContext, StakePoolAccounts) {
+    let mut context = program_test().start_with_context().await;
+    let stake_pool_accounts = StakePoolAccounts::default();
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            MINIMUM_RESERVE_LAMPORTS,
+        )
+        .await
+        .unwrap();
+
+    let error = stake_pool_accounts
+        .deposit_sol(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            DEPOSIT_LAMPORTS,
+            None,
+        )
+        .await;
+    assert!(error.is_none(), "{:?}", error);
+
+    (context, stake_pool_accounts)
+}
+
+async fn deposit_rewards(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+    reserve_stake: &Pubkey,
+    lamports: u64,
+) -> Result<(), BanksClientError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::deposit_rewards(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            reserve_stake,
+            &context.payer.pubkey(),
+            lamports,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn warp_one_epoch(context: &mut ProgramTestContext) {
+    let slots_per_epoch = context.genesis_config().epoch_schedule.slots_per_epoch;
+    let slot = context.banks_client.get_root_slot().await.unwrap();
+    context.warp_to_slot(slot + slots_per_epoch).unwrap();
+    context.last_blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&context.last_blockhash)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn success() {
+    let (mut context, stake_pool_accounts) = setup().await;
+
+    let pre_stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+    let pre_reserve_lamports = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+    )
+    .await
+    .lamports;
+    let pre_fee_tokens = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await;
+
+    deposit_rewards(
+        &mut context,
+        &stake_pool_accounts,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+        REWARD_LAMPORTS,
+    )
+    .await
+    .unwrap();
+
+    // Lamports are in the reserve, but not yet part of the pool
+    let reserve_lamports = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+    )
+    .await
+    .lamports;
+    assert_eq!(reserve_lamports, pre_reserve_lamports + REWARD_LAMPORTS);
+    let stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+    assert_eq!(stake_pool.pending_reward_lamports, REWARD_LAMPORTS);
+    assert_eq!(stake_pool.total_lamports, pre_stake_pool.total_lamports);
+    assert_eq!(
+        stake_pool.pool_token_supply,
+        pre_stake_pool.pool_token_supply
+    );
+
+    // An update during the same epoch leaves the rewards pending
+    let error = stake_pool_accounts
+        .update_stake_pool_balance(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+        )
+        .await;
+    assert!(error.is_none(), "{:?}", error);
+    let stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+    assert_eq!(stake_pool.pending_reward_lamports, REWARD_LAMPORTS);
+    assert_eq!(stake_pool.total_lamports, pre_stake_pool.total_lamports);
+    assert_eq!(
+        stake_pool.pool_token_supply,
+        pre_stake_pool.pool_token_supply
+    );
+
+    warp_one_epoch(&mut context).await;
+    let error = stake_pool_accounts
+        .update_stake_pool_balance(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+        )
+        .await;
+    assert!(error.is_none(), "{:?}", error);
+
+    // Rewards are recognized, with the epoch fee taken
+    let post_stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+    assert_eq!(post_stake_pool.pending_reward_lamports, 0);
+    assert_eq!(
+        post_stake_pool.total_lamports,
+        pre_stake_pool.total_lamports + REWARD_LAMPORTS
+    );
+    let expected_fee = pre_stake_pool
+        .calc_epoch_fee_amount(REWARD_LAMPORTS)
+        .unwrap();
+    assert!(expected_fee > 0);
+    assert_eq!(
+        post_stake_pool.pool_token_supply,
+        pre_stake_pool.pool_token_supply + expected_fee
+    );
+    let fee_tokens = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await;
+    assert_eq!(fee_tokens, pre_fee_tokens + expected_fee);
+}
+
+#[tokio::test]
+async fn success_multiple_deposits_before_update() {
+    let (mut context, stake_pool_accounts) = setup().await;
+    let pre_stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+
+    deposit_rewards(
+        &mut context,
+        &stake_pool_accounts,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+        REWARD_LAMPORTS,
+    )
+    .await
+    .unwrap();
+    context.last_blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&context.last_blockhash)
+        .await
+        .unwrap();
+    deposit_rewards(
+        &mut context,
+        &stake_pool_accounts,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+        REWARD_LAMPORTS,
+    )
+    .await
+    .unwrap();
+
+    let stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+    assert_eq!(stake_pool.pending_reward_lamports, 2 * REWARD_LAMPORTS);
+
+    warp_one_epoch(&mut context).await;
+    let error = stake_pool_accounts
+        .update_stake_pool_balance(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+        )
+        .await;
+    assert!(error.is_none(), "{:?}", error);
+
+    let post_stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+    assert_eq!(post_stake_pool.pending_reward_lamports, 0);
+    assert_eq!(
+        post_stake_pool.total_lamports,
+        pre_stake_pool.total_lamports + 2 * REWARD_LAMPORTS
+    );
+}
+
+#[tokio::test]
+async fn fail_zero_lamports() {
+    let (mut context, stake_pool_accounts) = setup().await;
+
+    let error = deposit_rewards(
+        &mut context,
+        &stake_pool_accounts,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+        0,
+    )
+    .await
+    .unwrap_err()
+    .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(StakePoolError::DepositTooSmall as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn fail_wrong_reserve() {
+    let (mut context, stake_pool_accounts) = setup().await;
+
+    let error = deposit_rewards(
+        &mut context,
+        &stake_pool_accounts,
+        &Pubkey::new_unique(),
+        REWARD_LAMPORTS,
+    )
+    .await
+    .unwrap_err()
+    .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(StakePoolError::InvalidProgramAddress as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn fail_out_of_date() {
+    let (mut context, stake_pool_accounts) = setup().await;
+    warp_one_epoch(&mut context).await;
+
+    let error = deposit_rewards(
+        &mut context,
+        &stake_pool_accounts,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+        REWARD_LAMPORTS,
+    )
+    .await
+    .unwrap_err()
+    .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(StakePoolError::StakeListAndPoolOutOfDate as u32)
+        )
+    );
+}
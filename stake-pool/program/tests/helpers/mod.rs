@@ -135,6 +135,12 @@ pub async fn create_mint(
             ),
             ExtensionType::NonTransferable =>
                 spl_token_2022::instruction::initialize_non_transferable_mint(program_id, &mint_pubkey),
+            ExtensionType::MetadataPointer => spl_token_2022::extension::metadata_pointer::instruction::initialize(
+                program_id,
+                &mint_pubkey,
+                Some(*manager),
+                Some(mint_pubkey),
+            ),
             _ => unimplemented!(),
         };
         instructions.push(instruction.unwrap());
@@ -864,6 +870,7 @@ pub struct StakePoolAccounts {
     pub pool_mint: Keypair,
     pub pool_fee_account: Keypair,
     pub pool_decimals: u8,
+    pub pool_mint_extensions: Vec<ExtensionType>,
     pub manager: Keypair,
     pub staker: Keypair,
     pub withdraw_authority: Pubkey,
@@ -935,7 +942,7 @@ impl StakePoolAccounts {
             &self.pool_mint,
             &self.withdraw_authority,
             self.pool_decimals,
-            &[],
+            &self.pool_mint_extensions,
         )
         .await?;
         create_token_account(
@@ -1976,6 +1983,8 @@ impl StakePoolAccounts {
             next_sol_withdrawal_fee: FutureEpoch::None,
             last_epoch_pool_token_supply: 0,
             last_epoch_total_lamports: 0,
+            withdrawal_ticket_lamports: 0,
+            pending_reward_lamports: 0,
         };
         let mut validator_list = ValidatorList::new(self.max_validators);
         validator_list.validators = vec![];
@@ -2014,6 +2023,7 @@ impl Default for StakePoolAccounts {
             pool_mint,
             pool_fee_account,
             pool_decimals: 0,
+            pool_mint_extensions: vec![],
             manager,
             staker,
             withdraw_authority,
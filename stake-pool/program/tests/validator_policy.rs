@@ -0,0 +1,392 @@
+#![allow(clippy::integer_arithmetic)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{
+        borsh::try_from_slice_unchecked, clock::Clock, instruction::InstructionError,
+        pubkey::Pubkey,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+        transport::TransportError,
+    },
+    solana_vote_program::vote_state::{VoteState, VoteStateVersions},
+    spl_stake_pool::{
+        error::StakePoolError,
+        find_validator_policy_program_address, id, instruction,
+        state::{AccountType, StakeStatus, ValidatorPolicy},
+        MINIMUM_RESERVE_LAMPORTS,
+    },
+};
+
+async fn setup() -> (ProgramTestContext, StakePoolAccounts, ValidatorStakeAccount) {
+    let mut context = program_test().start_with_context().await;
+    let first_normal_slot = context.genesis_config().epoch_schedule.first_normal_slot;
+    context.warp_to_slot(first_normal_slot + 1).unwrap();
+
+    let stake_pool_accounts = StakePoolAccounts::default();
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            10_000_000_000 + MINIMUM_RESERVE_LAMPORTS,
+        )
+        .await
+        .unwrap();
+
+    let validator_stake = simple_add_validator_to_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &stake_pool_accounts,
+        None,
+    )
+    .await;
+
+    (context, stake_pool_accounts, validator_stake)
+}
+
+async fn set_validator_policy(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+    manager: &Keypair,
+    max_commission: u8,
+    max_delinquent_epochs: u64,
+) -> Result<(), TransportError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_validator_policy(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &manager.pubkey(),
+            &context.payer.pubkey(),
+            max_commission,
+            max_delinquent_epochs,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, manager],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.into())
+}
+
+async fn flag_validator(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+    vote_account_address: &Pubkey,
+) -> Result<(), TransportError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::flag_validator(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.validator_list.pubkey(),
+            vote_account_address,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.into())
+}
+
+/// Overwrite the commission of the vote account and give it credits in the
+/// given epoch
+async fn set_vote_state(
+    context: &mut ProgramTestContext,
+    vote_account_address: &Pubkey,
+    commission: u8,
+    credits_epoch: u64,
+) {
+    let mut vote_account = get_account(&mut context.banks_client, vote_account_address).await;
+    let mut vote_state = bincode::deserialize::<VoteStateVersions>(&vote_account.data)
+        .unwrap()
+        .convert_to_current();
+    vote_state.commission = commission;
+    vote_state.epoch_credits.push((credits_epoch, 100, 0));
+    VoteState::serialize(
+        &VoteStateVersions::new_current(vote_state),
+        &mut vote_account.data,
+    )
+    .unwrap();
+    context.set_account(vote_account_address, &vote_account.into());
+}
+
+fn check_error(error: TransportError, expected: StakePoolError) {
+    let error = error.unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(0, InstructionError::Custom(expected as u32))
+    );
+}
+
+#[tokio::test]
+async fn success_set_validator_policy() {
+    let (mut context, stake_pool_accounts, _) = setup().await;
+    let manager = stake_pool_accounts.manager.insecure_clone();
+
+    set_validator_policy(&mut context, &stake_pool_accounts, &manager, 10, 5)
+        .await
+        .unwrap();
+    let (policy_address, _) =
+        find_validator_policy_program_address(&id(), &stake_pool_accounts.stake_pool.pubkey());
+    let policy_account = get_account(&mut context.banks_client, &policy_address).await;
+    let policy =
+        try_from_slice_unchecked::<ValidatorPolicy>(policy_account.data.as_slice()).unwrap();
+    assert_eq!(
+        policy,
+        ValidatorPolicy {
+            account_type: AccountType::ValidatorPolicy,
+            stake_pool: stake_pool_accounts.stake_pool.pubkey(),
+            max_commission: 10,
+            max_delinquent_epochs: 5,
+        }
+    );
+
+    // update the existing policy
+    let last_blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&context.last_blockhash)
+        .await
+        .unwrap();
+    context.last_blockhash = last_blockhash;
+    set_validator_policy(&mut context, &stake_pool_accounts, &manager, 0, 1)
+        .await
+        .unwrap();
+    let policy_account = get_account(&mut context.banks_client, &policy_address).await;
+    let policy =
+        try_from_slice_unchecked::<ValidatorPolicy>(policy_account.data.as_slice()).unwrap();
+    assert_eq!(policy.max_commission, 0);
+    assert_eq!(policy.max_delinquent_epochs, 1);
+}
+
+#[tokio::test]
+async fn fail_set_validator_policy_wrong_manager() {
+    let (mut context, stake_pool_accounts, _) = setup().await;
+    let wrong_manager = Keypair::new();
+
+    let error = set_validator_policy(&mut context, &stake_pool_accounts, &wrong_manager, 10, 5)
+        .await
+        .unwrap_err();
+    check_error(error, StakePoolError::WrongManager);
+}
+
+#[tokio::test]
+async fn fail_set_validator_policy_invalid_commission() {
+    let (mut context, stake_pool_accounts, _) = setup().await;
+    let manager = stake_pool_accounts.manager.insecure_clone();
+
+    let error = set_validator_policy(&mut context, &stake_pool_accounts, &manager, 101, 5)
+        .await
+        .unwrap_err();
+    check_error(error, StakePoolError::InvalidValidatorPolicy);
+}
+
+#[tokio::test]
+async fn fail_flag_without_policy() {
+    let (mut context, stake_pool_accounts, validator_stake) = setup().await;
+
+    let error = flag_validator(
+        &mut context,
+        &stake_pool_accounts,
+        &validator_stake.vote.pubkey(),
+    )
+    .await
+    .unwrap_err()
+    .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(0, InstructionError::IncorrectProgramId)
+    );
+}
+
+#[tokio::test]
+async fn fail_flag_compliant_validator() {
+    let (mut context, stake_pool_accounts, validator_stake) = setup().await;
+    let manager = stake_pool_accounts.manager.insecure_clone();
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    set_vote_state(
+        &mut context,
+        &validator_stake.vote.pubkey(),
+        10,
+        clock.epoch - 2,
+    )
+    .await;
+
+    set_validator_policy(&mut context, &stake_pool_accounts, &manager, 10, 2)
+        .await
+        .unwrap();
+
+    let error = flag_validator(
+        &mut context,
+        &stake_pool_accounts,
+        &validator_stake.vote.pubkey(),
+    )
+    .await
+    .unwrap_err();
+    check_error(error, StakePoolError::ValidatorPolicyNotViolated);
+}
+
+#[tokio::test]
+async fn fail_flag_without_credits_history() {
+    let (mut context, stake_pool_accounts, validator_stake) = setup().await;
+    let manager = stake_pool_accounts.manager.insecure_clone();
+
+    // the vote account has never earned any credits
+    let vote_account = get_account(&mut context.banks_client, &validator_stake.vote.pubkey()).await;
+    let vote_state = bincode::deserialize::<VoteStateVersions>(&vote_account.data)
+        .unwrap()
+        .convert_to_current();
+    assert!(vote_state.epoch_credits.is_empty());
+
+    set_validator_policy(&mut context, &stake_pool_accounts, &manager, 100, 0)
+        .await
+        .unwrap();
+
+    let error = flag_validator(
+        &mut context,
+        &stake_pool_accounts,
+        &validator_stake.vote.pubkey(),
+    )
+    .await
+    .unwrap_err();
+    check_error(error, StakePoolError::ValidatorPolicyNotViolated);
+}
+
+#[tokio::test]
+async fn success_flag_high_commission() {
+    let (mut context, stake_pool_accounts, validator_stake) = setup().await;
+    let manager = stake_pool_accounts.manager.insecure_clone();
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    set_vote_state(
+        &mut context,
+        &validator_stake.vote.pubkey(),
+        11,
+        clock.epoch,
+    )
+    .await;
+
+    set_validator_policy(&mut context, &stake_pool_accounts, &manager, 10, 2)
+        .await
+        .unwrap();
+    flag_validator(
+        &mut context,
+        &stake_pool_accounts,
+        &validator_stake.vote.pubkey(),
+    )
+    .await
+    .unwrap();
+
+    let validator_list = stake_pool_accounts
+        .get_validator_list(&mut context.banks_client)
+        .await;
+    let validator_stake_info = validator_list.find(&validator_stake.vote.pubkey()).unwrap();
+    assert_eq!(
+        validator_stake_info.status,
+        StakeStatus::FlaggedForDeactivation
+    );
+}
+
+#[tokio::test]
+async fn success_flag_delinquent_and_remove() {
+    let (mut context, stake_pool_accounts, validator_stake) = setup().await;
+    let manager = stake_pool_accounts.manager.insecure_clone();
+
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    set_vote_state(
+        &mut context,
+        &validator_stake.vote.pubkey(),
+        0,
+        clock.epoch - 2,
+    )
+    .await;
+
+    set_validator_policy(&mut context, &stake_pool_accounts, &manager, 100, 1)
+        .await
+        .unwrap();
+    flag_validator(
+        &mut context,
+        &stake_pool_accounts,
+        &validator_stake.vote.pubkey(),
+    )
+    .await
+    .unwrap();
+
+    // flagging twice fails
+    let last_blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&context.last_blockhash)
+        .await
+        .unwrap();
+    context.last_blockhash = last_blockhash;
+    let error = flag_validator(
+        &mut context,
+        &stake_pool_accounts,
+        &validator_stake.vote.pubkey(),
+    )
+    .await
+    .unwrap_err();
+    check_error(error, StakePoolError::ValidatorNotFound);
+
+    // the update deactivates the validator stake
+    let error = stake_pool_accounts
+        .update_all(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &[validator_stake.vote.pubkey()],
+            false,
+        )
+        .await;
+    assert!(error.is_none(), "{:?}", error);
+    let validator_list = stake_pool_accounts
+        .get_validator_list(&mut context.banks_client)
+        .await;
+    let validator_stake_info = validator_list.find(&validator_stake.vote.pubkey()).unwrap();
+    assert_eq!(
+        validator_stake_info.status,
+        StakeStatus::DeactivatingValidator
+    );
+
+    // next epoch, the stake is merged into the reserve and the entry removed
+    let slots_per_epoch = context.genesis_config().epoch_schedule.slots_per_epoch;
+    let slot = context.banks_client.get_root_slot().await.unwrap();
+    context.warp_to_slot(slot + slots_per_epoch).unwrap();
+    let last_blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&context.last_blockhash)
+        .await
+        .unwrap();
+    let error = stake_pool_accounts
+        .update_all(
+            &mut context.banks_client,
+            &context.payer,
+            &last_blockhash,
+            &[validator_stake.vote.pubkey()],
+            false,
+        )
+        .await;
+    assert!(error.is_none(), "{:?}", error);
+    let validator_list = stake_pool_accounts
+        .get_validator_list(&mut context.banks_client)
+        .await;
+    assert!(validator_list.validators.is_empty());
+    let account = context
+        .banks_client
+        .get_account(validator_stake.stake_account)
+        .await
+        .unwrap();
+    assert!(account.is_none());
+}
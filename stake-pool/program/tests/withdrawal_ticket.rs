@@ -0,0 +1,700 @@
+#![allow(clippy::integer_arithmetic)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    borsh::BorshSerialize,
+    helpers::*,
+    solana_program::{
+        borsh::{get_packed_len, try_from_slice_unchecked},
+        instruction::InstructionError,
+        pubkey::Pubkey,
+        stake, system_instruction,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        account::{Account, AccountSharedData},
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+        transport::TransportError,
+    },
+    spl_stake_pool::{
+        error::StakePoolError,
+        id, instruction,
+        state::{AccountType, Fee, FutureEpoch, StakePool, WithdrawalTicket},
+        MINIMUM_RESERVE_LAMPORTS,
+    },
+};
+
+async fn setup() -> (
+    ProgramTestContext,
+    StakePoolAccounts,
+    ValidatorStakeAccount,
+    Keypair,
+    Pubkey,
+    u64,
+) {
+    let mut context = program_test().start_with_context().await;
+    let first_normal_slot = context.genesis_config().epoch_schedule.first_normal_slot;
+    context.warp_to_slot(first_normal_slot + 1).unwrap();
+
+    let stake_pool_accounts = StakePoolAccounts::default();
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            MINIMUM_RESERVE_LAMPORTS,
+        )
+        .await
+        .unwrap();
+
+    let validator_stake = simple_add_validator_to_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &stake_pool_accounts,
+        None,
+    )
+    .await;
+
+    let user = Keypair::new();
+    let pool_token_account = Keypair::new();
+    create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &stake_pool_accounts.token_program_id,
+        &pool_token_account,
+        &stake_pool_accounts.pool_mint.pubkey(),
+        &user,
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let error = stake_pool_accounts
+        .deposit_sol(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &pool_token_account.pubkey(),
+            TEST_STAKE_AMOUNT,
+            None,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let pool_tokens =
+        get_token_balance(&mut context.banks_client, &pool_token_account.pubkey()).await;
+
+    (
+        context,
+        stake_pool_accounts,
+        validator_stake,
+        user,
+        pool_token_account.pubkey(),
+        pool_tokens,
+    )
+}
+
+async fn create_withdrawal_ticket(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+    user: &Keypair,
+    pool_token_account: &Pubkey,
+    withdrawal_ticket: &Keypair,
+    pool_tokens: u64,
+) -> Result<(), TransportError> {
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let space = get_packed_len::<WithdrawalTicket>();
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::create_account(
+                &context.payer.pubkey(),
+                &withdrawal_ticket.pubkey(),
+                rent.minimum_balance(space),
+                space as u64,
+                &id(),
+            ),
+            instruction::create_withdrawal_ticket(
+                &id(),
+                &stake_pool_accounts.stake_pool.pubkey(),
+                &user.pubkey(),
+                pool_token_account,
+                &withdrawal_ticket.pubkey(),
+                &user.pubkey(),
+                &stake_pool_accounts.pool_fee_account.pubkey(),
+                &stake_pool_accounts.pool_mint.pubkey(),
+                &stake_pool_accounts.token_program_id,
+                None,
+                pool_tokens,
+            ),
+        ],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, withdrawal_ticket, user],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.into())
+}
+
+async fn claim_withdrawal_ticket(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+    owner: &Keypair,
+    withdrawal_ticket: &Pubkey,
+    lamports_to: &Pubkey,
+) -> Result<(), TransportError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::claim_withdrawal_ticket(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.reserve_stake.pubkey(),
+            withdrawal_ticket,
+            &owner.pubkey(),
+            lamports_to,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, owner],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.into())
+}
+
+async fn warp_one_epoch_and_update(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+    validator_stake: &ValidatorStakeAccount,
+) {
+    let slots_per_epoch = context.genesis_config().epoch_schedule.slots_per_epoch;
+    let slot = context.banks_client.get_root_slot().await.unwrap();
+    context.warp_to_slot(slot + slots_per_epoch).unwrap();
+    context.last_blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&context.last_blockhash)
+        .await
+        .unwrap();
+    let error = stake_pool_accounts
+        .update_all(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &[validator_stake.vote.pubkey()],
+            false,
+        )
+        .await;
+    assert!(error.is_none(), "{:?}", error);
+}
+
+fn check_error(error: TransportError, expected: StakePoolError) {
+    let error = error.unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(0, InstructionError::Custom(expected as u32))
+    );
+}
+
+#[tokio::test]
+async fn success() {
+    let (mut context, stake_pool_accounts, validator_stake, user, pool_token_account, pool_tokens) =
+        setup().await;
+    let pre_stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+    let pre_fee_tokens = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await;
+
+    let withdrawal_ticket = Keypair::new();
+    create_withdrawal_ticket(
+        &mut context,
+        &stake_pool_accounts,
+        &user,
+        &pool_token_account,
+        &withdrawal_ticket,
+        pool_tokens,
+    )
+    .await
+    .unwrap();
+
+    // pool tokens are burnt and the lamports are moved to the ticket bucket
+    let post_stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+    let ticket_account = get_account(&mut context.banks_client, &withdrawal_ticket.pubkey()).await;
+    let ticket =
+        try_from_slice_unchecked::<WithdrawalTicket>(ticket_account.data.as_slice()).unwrap();
+    assert_eq!(ticket.account_type, AccountType::WithdrawalTicket);
+    assert_eq!(ticket.stake_pool, stake_pool_accounts.stake_pool.pubkey());
+    assert_eq!(ticket.owner, user.pubkey());
+    assert_eq!(
+        ticket.lamports,
+        pre_stake_pool.total_lamports - post_stake_pool.total_lamports
+    );
+    assert_eq!(post_stake_pool.withdrawal_ticket_lamports, ticket.lamports);
+    let fee_tokens = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await
+        - pre_fee_tokens;
+    assert_eq!(
+        post_stake_pool.pool_token_supply,
+        pre_stake_pool.pool_token_supply - pool_tokens + fee_tokens
+    );
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &pool_token_account).await,
+        0
+    );
+
+    // claiming in the same epoch fails
+    let receiver = Pubkey::new_unique();
+    let error = claim_withdrawal_ticket(
+        &mut context,
+        &stake_pool_accounts,
+        &user,
+        &withdrawal_ticket.pubkey(),
+        &receiver,
+    )
+    .await
+    .unwrap_err();
+    check_error(error, StakePoolError::WithdrawalTicketNotClaimable);
+
+    // the update does not count the owed lamports in the pool value
+    warp_one_epoch_and_update(&mut context, &stake_pool_accounts, &validator_stake).await;
+    let stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+    assert_eq!(stake_pool.total_lamports, post_stake_pool.total_lamports);
+
+    claim_withdrawal_ticket(
+        &mut context,
+        &stake_pool_accounts,
+        &user,
+        &withdrawal_ticket.pubkey(),
+        &receiver,
+    )
+    .await
+    .unwrap();
+
+    let receiver_account = get_account(&mut context.banks_client, &receiver).await;
+    assert_eq!(
+        receiver_account.lamports,
+        ticket.lamports + ticket_account.lamports
+    );
+    let ticket_account = context
+        .banks_client
+        .get_account(withdrawal_ticket.pubkey())
+        .await
+        .unwrap();
+    assert!(ticket_account.is_none());
+    let stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+    assert_eq!(stake_pool.withdrawal_ticket_lamports, 0);
+    assert_eq!(stake_pool.total_lamports, post_stake_pool.total_lamports);
+}
+
+#[tokio::test]
+async fn fail_claim_wrong_owner() {
+    let (mut context, stake_pool_accounts, validator_stake, user, pool_token_account, pool_tokens) =
+        setup().await;
+
+    let withdrawal_ticket = Keypair::new();
+    create_withdrawal_ticket(
+        &mut context,
+        &stake_pool_accounts,
+        &user,
+        &pool_token_account,
+        &withdrawal_ticket,
+        pool_tokens,
+    )
+    .await
+    .unwrap();
+    warp_one_epoch_and_update(&mut context, &stake_pool_accounts, &validator_stake).await;
+
+    let wrong_owner = Keypair::new();
+    let error = claim_withdrawal_ticket(
+        &mut context,
+        &stake_pool_accounts,
+        &wrong_owner,
+        &withdrawal_ticket.pubkey(),
+        &wrong_owner.pubkey(),
+    )
+    .await
+    .unwrap_err();
+    check_error(error, StakePoolError::InvalidWithdrawalTicket);
+}
+
+#[tokio::test]
+async fn fail_reuse_ticket() {
+    let (mut context, stake_pool_accounts, _, user, pool_token_account, pool_tokens) =
+        setup().await;
+
+    let withdrawal_ticket = Keypair::new();
+    create_withdrawal_ticket(
+        &mut context,
+        &stake_pool_accounts,
+        &user,
+        &pool_token_account,
+        &withdrawal_ticket,
+        pool_tokens / 2,
+    )
+    .await
+    .unwrap();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::create_withdrawal_ticket(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &user.pubkey(),
+            &pool_token_account,
+            &withdrawal_ticket.pubkey(),
+            &user.pubkey(),
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            &stake_pool_accounts.pool_mint.pubkey(),
+            &stake_pool_accounts.token_program_id,
+            None,
+            pool_tokens / 2,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &user],
+        context.last_blockhash,
+    );
+    let error = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(StakePoolError::AlreadyInUse as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn fail_withdraw_sol_owed_to_tickets() {
+    let (mut context, stake_pool_accounts, validator_stake, user, pool_token_account, pool_tokens) =
+        setup().await;
+
+    // leave roughly a third of the deposit in the reserve
+    let current_minimum_delegation = stake_pool_get_minimum_delegation(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+    )
+    .await;
+    let error = stake_pool_accounts
+        .increase_validator_stake(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &validator_stake.transient_stake_account,
+            &validator_stake.stake_account,
+            &validator_stake.vote.pubkey(),
+            current_minimum_delegation,
+            validator_stake.transient_stake_seed,
+        )
+        .await;
+    assert!(error.is_none(), "{:?}", error);
+
+    let withdrawal_ticket = Keypair::new();
+    create_withdrawal_ticket(
+        &mut context,
+        &stake_pool_accounts,
+        &user,
+        &pool_token_account,
+        &withdrawal_ticket,
+        pool_tokens / 4,
+    )
+    .await
+    .unwrap();
+
+    // the reserve could cover this withdrawal alone, but part of it is owed to
+    // the ticket holder
+    let error = stake_pool_accounts
+        .withdraw_sol(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &user,
+            &pool_token_account,
+            pool_tokens / 4,
+            None,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(StakePoolError::SolWithdrawalTooLarge as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn success_after_reserve_refill() {
+    let (mut context, stake_pool_accounts, validator_stake, user, pool_token_account, pool_tokens) =
+        setup().await;
+
+    // move all available reserve lamports to the validator
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let stake_rent = rent.minimum_balance(std::mem::size_of::<stake::state::StakeState>());
+    let error = stake_pool_accounts
+        .increase_validator_stake(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &validator_stake.transient_stake_account,
+            &validator_stake.stake_account,
+            &validator_stake.vote.pubkey(),
+            TEST_STAKE_AMOUNT - stake_rent,
+            validator_stake.transient_stake_seed,
+        )
+        .await;
+    assert!(error.is_none(), "{:?}", error);
+
+    let withdrawal_ticket = Keypair::new();
+    create_withdrawal_ticket(
+        &mut context,
+        &stake_pool_accounts,
+        &user,
+        &pool_token_account,
+        &withdrawal_ticket,
+        pool_tokens,
+    )
+    .await
+    .unwrap();
+    warp_one_epoch_and_update(&mut context, &stake_pool_accounts, &validator_stake).await;
+
+    // the reserve cannot cover the ticket yet
+    let receiver = Pubkey::new_unique();
+    let error = claim_withdrawal_ticket(
+        &mut context,
+        &stake_pool_accounts,
+        &user,
+        &withdrawal_ticket.pubkey(),
+        &receiver,
+    )
+    .await
+    .unwrap_err();
+    check_error(error, StakePoolError::WithdrawalTicketNotClaimable);
+
+    // new deposits refill the reserve, covering the ticket
+    let ticket_account = get_account(&mut context.banks_client, &withdrawal_ticket.pubkey()).await;
+    let ticket =
+        try_from_slice_unchecked::<WithdrawalTicket>(ticket_account.data.as_slice()).unwrap();
+    let error = stake_pool_accounts
+        .deposit_sol(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &pool_token_account,
+            ticket.lamports,
+            None,
+        )
+        .await;
+    assert!(error.is_none(), "{:?}", error);
+
+    context.last_blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&context.last_blockhash)
+        .await
+        .unwrap();
+    claim_withdrawal_ticket(
+        &mut context,
+        &stake_pool_accounts,
+        &user,
+        &withdrawal_ticket.pubkey(),
+        &receiver,
+    )
+    .await
+    .unwrap();
+    let receiver_account = get_account(&mut context.banks_client, &receiver).await;
+    assert_eq!(
+        receiver_account.lamports,
+        ticket.lamports + ticket_account.lamports
+    );
+}
+
+#[tokio::test]
+async fn fail_increase_validator_stake_owed_to_tickets() {
+    let (mut context, stake_pool_accounts, validator_stake, user, pool_token_account, pool_tokens) =
+        setup().await;
+
+    let withdrawal_ticket = Keypair::new();
+    create_withdrawal_ticket(
+        &mut context,
+        &stake_pool_accounts,
+        &user,
+        &pool_token_account,
+        &withdrawal_ticket,
+        pool_tokens / 2,
+    )
+    .await
+    .unwrap();
+
+    // the reserve holds enough for this increase, but half of it is owed to
+    // the ticket holder
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let stake_rent = rent.minimum_balance(std::mem::size_of::<stake::state::StakeState>());
+    let error = stake_pool_accounts
+        .increase_validator_stake(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &validator_stake.transient_stake_account,
+            &validator_stake.stake_account,
+            &validator_stake.vote.pubkey(),
+            TEST_STAKE_AMOUNT - stake_rent,
+            validator_stake.transient_stake_seed,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(0, InstructionError::InsufficientFunds)
+    );
+}
+
+#[tokio::test]
+async fn success_realloc_stake_pool_with_previous_layout() {
+    let (mut context, stake_pool_accounts, _, _, _, _) = setup().await;
+    let pre_stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+
+    // rewrite the stake pool with the size used before the withdrawal ticket
+    // and reward fields were added
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let stake_pool_address = stake_pool_accounts.stake_pool.pubkey();
+    let stake_pool_account = get_account(&mut context.banks_client, &stake_pool_address).await;
+    let previous_len = get_packed_len::<StakePool>() - 2 * std::mem::size_of::<u64>();
+    let previous_account = Account {
+        lamports: rent.minimum_balance(previous_len),
+        data: stake_pool_account.data[..previous_len].to_vec(),
+        ..stake_pool_account
+    };
+    context.set_account(
+        &stake_pool_address,
+        &AccountSharedData::from(previous_account),
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::realloc_stake_pool(
+            &id(),
+            &stake_pool_address,
+            &context.payer.pubkey(),
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let stake_pool_account = get_account(&mut context.banks_client, &stake_pool_address).await;
+    assert_eq!(stake_pool_account.data.len(), get_packed_len::<StakePool>());
+    assert_eq!(
+        stake_pool_account.lamports,
+        rent.minimum_balance(get_packed_len::<StakePool>())
+    );
+    let stake_pool =
+        try_from_slice_unchecked::<StakePool>(stake_pool_account.data.as_slice()).unwrap();
+    assert_eq!(stake_pool, pre_stake_pool);
+    assert_eq!(stake_pool.withdrawal_ticket_lamports, 0);
+    assert_eq!(stake_pool.pending_reward_lamports, 0);
+}
+
+#[tokio::test]
+async fn success_realloc_stake_pool_with_stale_tail() {
+    let (mut context, stake_pool_accounts, _, _, _, _) = setup().await;
+    let pre_stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+
+    // write the previous layout with a next epoch fee set, then without one,
+    // like an account whose fee change took effect, leaving the tail of the
+    // longer encoding behind
+    assert_eq!(pre_stake_pool.next_epoch_fee, FutureEpoch::None);
+    let new_fields_len = 2 * std::mem::size_of::<u64>();
+    let previous_len = get_packed_len::<StakePool>() - new_fields_len;
+    let mut longer_stake_pool = pre_stake_pool.clone();
+    longer_stake_pool.next_epoch_fee = FutureEpoch::One(Fee {
+        numerator: 1,
+        denominator: 100,
+    });
+    longer_stake_pool.last_epoch_pool_token_supply = u64::MAX;
+    longer_stake_pool.last_epoch_total_lamports = u64::MAX;
+    let longer_data = longer_stake_pool.try_to_vec().unwrap();
+    let shorter_data = pre_stake_pool.try_to_vec().unwrap();
+    let mut previous_data = vec![0; previous_len];
+    previous_data[..longer_data.len() - new_fields_len]
+        .copy_from_slice(&longer_data[..longer_data.len() - new_fields_len]);
+    previous_data[..shorter_data.len() - new_fields_len]
+        .copy_from_slice(&shorter_data[..shorter_data.len() - new_fields_len]);
+
+    // read as the current layout, the stale bytes land in the new fields
+    let mut padded_data = previous_data.clone();
+    padded_data.resize(get_packed_len::<StakePool>(), 0);
+    let stale_stake_pool = try_from_slice_unchecked::<StakePool>(&padded_data).unwrap();
+    assert_eq!(stale_stake_pool.withdrawal_ticket_lamports, u64::MAX);
+    assert_eq!(stale_stake_pool.pending_reward_lamports, u64::MAX);
+
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let stake_pool_address = stake_pool_accounts.stake_pool.pubkey();
+    let stake_pool_account = get_account(&mut context.banks_client, &stake_pool_address).await;
+    let previous_account = Account {
+        lamports: rent.minimum_balance(previous_len),
+        data: previous_data,
+        ..stake_pool_account
+    };
+    context.set_account(
+        &stake_pool_address,
+        &AccountSharedData::from(previous_account),
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::realloc_stake_pool(
+            &id(),
+            &stake_pool_address,
+            &context.payer.pubkey(),
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let stake_pool = stake_pool_accounts
+        .get_stake_pool(&mut context.banks_client)
+        .await;
+    assert_eq!(stake_pool, pre_stake_pool);
+    assert_eq!(stake_pool.withdrawal_ticket_lamports, 0);
+    assert_eq!(stake_pool.pending_reward_lamports, 0);
+}
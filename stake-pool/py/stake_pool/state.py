@@ -69,6 +69,8 @@ class StakePool(NamedTuple):
     next_sol_withdrawal_fee: Optional[Fee]
     last_epoch_pool_token_supply: int
     last_epoch_total_lamports: int
+    withdrawal_ticket_lamports: int
+    pending_reward_lamports: int
 
     @classmethod
     def decode(cls, data: str, encoding: str):
@@ -104,6 +106,8 @@ class StakePool(NamedTuple):
             next_sol_withdrawal_fee=Fee.decode_optional_container(parsed['next_sol_withdrawal_fee']),
             last_epoch_pool_token_supply=parsed['last_epoch_pool_token_supply'],
             last_epoch_total_lamports=parsed['last_epoch_total_lamports'],
+            withdrawal_ticket_lamports=parsed['withdrawal_ticket_lamports'],
+            pending_reward_lamports=parsed['pending_reward_lamports'],
         )
 
 
@@ -120,6 +124,8 @@ class StakeStatus(IntEnum):
     """Validator stake account is deactivating to be merged into the reserve next epoch."""
     DEACTIVATING_ALL = 3
     """All alidator stake accounts are deactivating to be merged into the reserve next epoch."""
+    FLAGGED_FOR_DEACTIVATION = 5
+    """Validator violates the pool's validator policy and will be deactivated on the next update."""
 
 
 class ValidatorStakeInfo(NamedTuple):
@@ -229,6 +235,8 @@ STAKE_POOL_LAYOUT = Struct(
     "next_sol_withdrawal_fee" / FEE_LAYOUT,
     "last_epoch_pool_token_supply" / Int64ul,
     "last_epoch_total_lamports" / Int64ul,
+    "withdrawal_ticket_lamports" / Int64ul,
+    "pending_reward_lamports" / Int64ul,
 )
 
 DECODE_STAKE_POOL_LAYOUT = Struct(
@@ -304,6 +312,8 @@ DECODE_STAKE_POOL_LAYOUT = Struct(
         }),
     "last_epoch_pool_token_supply" / Int64ul,
     "last_epoch_total_lamports" / Int64ul,
+    "withdrawal_ticket_lamports" / Int64ul,
+    "pending_reward_lamports" / Int64ul,
 )
 
 VALIDATOR_INFO_LAYOUT = Struct(
@@ -0,0 +1,38 @@
+//! Error types
+
+use {solana_program::pubkey::Pubkey, thiserror::Error};
+
+/// Errors that may be returned while building a rebalancing plan
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum RebalanceError {
+    /// A line of a weights or scores file could not be parsed
+    #[error("Invalid line {line}: {reason}")]
+    InvalidLine {
+        /// One-based line number
+        line: usize,
+        /// Description of the problem
+        reason: String,
+    },
+    /// A vote account appears more than once in a weights or scores file
+    #[error("Duplicate entry for vote account {0}")]
+    DuplicateEntry(Pubkey),
+    /// A weights or scores file references a validator that is not in the pool
+    #[error("Vote account {0} is not an active validator in the pool")]
+    UnknownValidator(Pubkey),
+    /// Every active validator in the pool has a target weight of zero
+    #[error("No active validator has a non-zero weight")]
+    NoWeight,
+    /// The stake pool was not updated in the current epoch
+    #[error(
+        "Stake pool last updated in epoch {last_update_epoch}, current epoch is {current_epoch}"
+    )]
+    StalePool {
+        /// Epoch of the last `UpdateStakePoolBalance`
+        last_update_epoch: u64,
+        /// Current epoch of the cluster
+        current_epoch: u64,
+    },
+    /// An intermediate calculation overflowed
+    #[error("Calculation failure")]
+    CalculationFailure,
+}
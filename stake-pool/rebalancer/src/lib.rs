@@ -0,0 +1,7 @@
+#![deny(missing_docs)]
+
+//! Tooling for computing and applying rebalancing plans for stake pools
+
+pub mod error;
+pub mod plan;
+pub mod policy;
@@ -0,0 +1,362 @@
+use {
+    clap::{
+        crate_description, crate_name, crate_version, value_t, value_t_or_exit, App, Arg,
+        ArgMatches,
+    },
+    solana_clap_utils::{
+        input_parsers::pubkey_of,
+        input_validators::{is_amount, is_parsable, is_pubkey, is_url, is_valid_signer},
+        keypair::{signer_from_path_with_config, SignerFromPathConfig},
+    },
+    solana_client::rpc_client::RpcClient,
+    solana_program::{borsh::try_from_slice_unchecked, instruction::Instruction, pubkey::Pubkey},
+    solana_remote_wallet::remote_wallet::RemoteWalletManager,
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        native_token::{self, Sol},
+        signature::Signer,
+        stake,
+        transaction::Transaction,
+    },
+    spl_stake_pool::state::{StakePool, ValidatorList},
+    spl_stake_pool_rebalancer::{
+        plan::{
+            build_plan, check_pool_updated, Adjustment, BlockReason, PlanParameters, RebalancePlan,
+        },
+        policy::{parse_values, Policy},
+    },
+    std::{fs, num::NonZeroU32, process::exit, sync::Arc},
+};
+
+struct Config {
+    rpc_client: RpcClient,
+    staker: Box<dyn Signer>,
+    fee_payer: Box<dyn Signer>,
+    dry_run: bool,
+}
+
+type Error = Box<dyn std::error::Error>;
+type CommandResult = Result<(), Error>;
+
+fn get_signer(
+    matches: &ArgMatches<'_>,
+    keypair_name: &str,
+    keypair_path: &str,
+    wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
+) -> Box<dyn Signer> {
+    signer_from_path_with_config(
+        matches,
+        matches.value_of(keypair_name).unwrap_or(keypair_path),
+        keypair_name,
+        wallet_manager,
+        &SignerFromPathConfig {
+            allow_null_signer: false,
+        },
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        exit(1);
+    })
+}
+
+fn get_stake_pool(rpc_client: &RpcClient, stake_pool_address: &Pubkey) -> Result<StakePool, Error> {
+    let account_data = rpc_client.get_account_data(stake_pool_address)?;
+    let stake_pool = try_from_slice_unchecked::<StakePool>(account_data.as_slice())
+        .map_err(|err| format!("Invalid stake pool {}: {}", stake_pool_address, err))?;
+    Ok(stake_pool)
+}
+
+fn get_validator_list(
+    rpc_client: &RpcClient,
+    validator_list_address: &Pubkey,
+) -> Result<ValidatorList, Error> {
+    let account_data = rpc_client.get_account_data(validator_list_address)?;
+    let validator_list = try_from_slice_unchecked::<ValidatorList>(account_data.as_slice())
+        .map_err(|err| format!("Invalid validator list {}: {}", validator_list_address, err))?;
+    Ok(validator_list)
+}
+
+fn send_instruction(config: &Config, instruction: Instruction) -> CommandResult {
+    let mut signers = vec![config.fee_payer.as_ref(), config.staker.as_ref()];
+    signers.sort_by_key(|signer| signer.pubkey());
+    signers.dedup();
+    let recent_blockhash = config.rpc_client.get_latest_blockhash()?;
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&config.fee_payer.pubkey()),
+        &signers,
+        recent_blockhash,
+    );
+    let signature = config
+        .rpc_client
+        .send_and_confirm_transaction_with_spinner(&transaction)?;
+    println!("Signature: {}", signature);
+    Ok(())
+}
+
+fn print_plan(plan: &RebalancePlan) {
+    println!(
+        "Spendable Reserve: {}",
+        Sol(plan.spendable_reserve_lamports)
+    );
+    for validator in &plan.validators {
+        let action = match validator.adjustment {
+            Adjustment::Balanced => "balanced".to_string(),
+            Adjustment::Increase(lamports) => format!("increase by {}", Sol(lamports)),
+            Adjustment::Decrease(lamports) => format!("decrease by {}", Sol(lamports)),
+            Adjustment::Blocked(BlockReason::TransientStake) => {
+                "blocked, transient stake in progress".to_string()
+            }
+            Adjustment::Blocked(BlockReason::InsufficientReserve) => {
+                "blocked, insufficient reserve".to_string()
+            }
+        };
+        println!(
+            "Vote Account: {}\tCurrent: {}\tTarget: {}\tAction: {}",
+            validator.vote_account_address,
+            Sol(validator.current_lamports),
+            Sol(validator.target_lamports),
+            action,
+        );
+    }
+    println!(
+        "Remaining Reserve: {}",
+        Sol(plan.remaining_reserve_lamports)
+    );
+}
+
+fn command_rebalance(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    policy: &Policy,
+    reserve_buffer: u64,
+    threshold: u64,
+) -> CommandResult {
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let epoch_info = config.rpc_client.get_epoch_info()?;
+    check_pool_updated(&stake_pool, epoch_info.epoch)?;
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+
+    let params = PlanParameters {
+        stake_rent: config.rpc_client.get_minimum_balance_for_rent_exemption(
+            std::mem::size_of::<stake::state::StakeState>(),
+        )?,
+        stake_minimum_delegation: config.rpc_client.get_stake_minimum_delegation()?,
+        reserve_lamports: config.rpc_client.get_balance(&stake_pool.reserve_stake)?,
+        // outstanding withdrawal tickets are paid out of the reserve
+        reserve_buffer: reserve_buffer.saturating_add(stake_pool.withdrawal_ticket_lamports),
+        threshold,
+    };
+    let plan = build_plan(&validator_list, policy, &params)?;
+    println!("Stake Pool: {}", stake_pool_address);
+    print_plan(&plan);
+    if config.dry_run {
+        return Ok(());
+    }
+
+    let adjustments = plan
+        .decreases()
+        .map(|(vote_account, lamports)| (vote_account, lamports, false))
+        .chain(
+            plan.increases()
+                .map(|(vote_account, lamports)| (vote_account, lamports, true)),
+        );
+    for (vote_account, lamports, is_increase) in adjustments {
+        let validator_stake_info = validator_list
+            .find(vote_account)
+            .ok_or("Vote account not found in validator list")?;
+        let validator_seed = NonZeroU32::new(validator_stake_info.validator_seed_suffix);
+        let instruction = if is_increase {
+            println!("Increasing stake on {} by {}", vote_account, Sol(lamports));
+            spl_stake_pool::instruction::increase_validator_stake_with_vote(
+                &spl_stake_pool::id(),
+                &stake_pool,
+                stake_pool_address,
+                vote_account,
+                lamports,
+                validator_seed,
+                validator_stake_info.transient_seed_suffix,
+            )
+        } else {
+            println!("Decreasing stake on {} by {}", vote_account, Sol(lamports));
+            spl_stake_pool::instruction::decrease_validator_stake_with_vote(
+                &spl_stake_pool::id(),
+                &stake_pool,
+                stake_pool_address,
+                vote_account,
+                lamports,
+                validator_seed,
+                validator_stake_info.transient_seed_suffix,
+            )
+        };
+        send_instruction(config, instruction)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    solana_logger::setup_with_default("solana=info");
+
+    let matches = App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .arg({
+            let arg = Arg::with_name("config_file")
+                .short("C")
+                .long("config")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("Configuration file to use");
+            if let Some(ref config_file) = *solana_cli_config::CONFIG_FILE {
+                arg.default_value(config_file)
+            } else {
+                arg
+            }
+        })
+        .arg(
+            Arg::with_name("json_rpc_url")
+                .long("url")
+                .value_name("URL")
+                .takes_value(true)
+                .validator(is_url)
+                .help("JSON RPC URL for the cluster.  Default from the configuration file."),
+        )
+        .arg(
+            Arg::with_name("staker")
+                .long("staker")
+                .value_name("KEYPAIR")
+                .validator(is_valid_signer)
+                .takes_value(true)
+                .help("Stake pool staker. [default: cli config keypair]"),
+        )
+        .arg(
+            Arg::with_name("fee_payer")
+                .long("fee-payer")
+                .value_name("KEYPAIR")
+                .validator(is_valid_signer)
+                .takes_value(true)
+                .help("Transaction fee payer account [default: cli config keypair]"),
+        )
+        .arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .takes_value(false)
+                .help("Print the rebalancing plan without submitting any transactions"),
+        )
+        .arg(
+            Arg::with_name("pool")
+                .index(1)
+                .validator(is_pubkey)
+                .value_name("POOL_ADDRESS")
+                .takes_value(true)
+                .required(true)
+                .help("Stake pool address"),
+        )
+        .arg(
+            Arg::with_name("weights")
+                .long("weights")
+                .value_name("PATH")
+                .takes_value(true)
+                .conflicts_with("scores")
+                .help("File of `VOTE_ACCOUNT_ADDRESS WEIGHT` lines. Stake is split in proportion to the weights. [default: equal split]"),
+        )
+        .arg(
+            Arg::with_name("scores")
+                .long("scores")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("File of `VOTE_ACCOUNT_ADDRESS SCORE` lines. Stake is split in proportion to the scores."),
+        )
+        .arg(
+            Arg::with_name("min_score")
+                .long("min-score")
+                .value_name("SCORE")
+                .takes_value(true)
+                .requires("scores")
+                .validator(is_parsable::<u64>)
+                .help("Validators scoring below this value are drained down to the minimum stake"),
+        )
+        .arg(
+            Arg::with_name("reserve_buffer")
+                .long("reserve-buffer")
+                .value_name("AMOUNT")
+                .takes_value(true)
+                .validator(is_amount)
+                .default_value("0")
+                .help("Amount in SOL to keep in the reserve for withdrawals"),
+        )
+        .arg(
+            Arg::with_name("threshold")
+                .long("threshold")
+                .value_name("AMOUNT")
+                .takes_value(true)
+                .validator(is_amount)
+                .default_value("0")
+                .help("Smallest amount in SOL worth moving to or from a validator"),
+        )
+        .get_matches();
+
+    let mut wallet_manager = None;
+    let cli_config = if let Some(config_file) = matches.value_of("config_file") {
+        solana_cli_config::Config::load(config_file).unwrap_or_default()
+    } else {
+        solana_cli_config::Config::default()
+    };
+    let config = {
+        let json_rpc_url = value_t!(matches, "json_rpc_url", String)
+            .unwrap_or_else(|_| cli_config.json_rpc_url.clone());
+        let staker = get_signer(
+            &matches,
+            "staker",
+            &cli_config.keypair_path,
+            &mut wallet_manager,
+        );
+        let fee_payer = get_signer(
+            &matches,
+            "fee_payer",
+            &cli_config.keypair_path,
+            &mut wallet_manager,
+        );
+        Config {
+            rpc_client: RpcClient::new_with_commitment(json_rpc_url, CommitmentConfig::confirmed()),
+            staker,
+            fee_payer,
+            dry_run: matches.is_present("dry_run"),
+        }
+    };
+
+    let stake_pool_address = pubkey_of(&matches, "pool").unwrap();
+    let reserve_buffer =
+        native_token::sol_to_lamports(value_t_or_exit!(matches, "reserve_buffer", f64));
+    let threshold = native_token::sol_to_lamports(value_t_or_exit!(matches, "threshold", f64));
+
+    let read_values = |name: &str| {
+        let path = matches.value_of(name).unwrap();
+        fs::read_to_string(path)
+            .map_err(|err| Error::from(format!("Unable to read {}: {}", path, err)))
+            .and_then(|input| parse_values(&input).map_err(Error::from))
+    };
+    let policy = if matches.is_present("weights") {
+        read_values("weights").map(Policy::Weights)
+    } else if matches.is_present("scores") {
+        let min_score = value_t!(matches, "min_score", u64).unwrap_or(0);
+        read_values("scores").map(|scores| Policy::Scores { scores, min_score })
+    } else {
+        Ok(Policy::Equal)
+    };
+
+    let _ = policy
+        .and_then(|policy| {
+            command_rebalance(
+                &config,
+                &stake_pool_address,
+                &policy,
+                reserve_buffer,
+                threshold,
+            )
+        })
+        .map_err(|err| {
+            eprintln!("{}", err);
+            exit(1);
+        });
+}
@@ -0,0 +1,411 @@
+//! Rebalancing plan computation
+
+use {
+    crate::{error::RebalanceError, policy::Policy},
+    solana_program::pubkey::Pubkey,
+    spl_stake_pool::{
+        minimum_delegation,
+        state::{StakePool, StakeStatus, ValidatorList, ValidatorStakeInfo},
+        MINIMUM_RESERVE_LAMPORTS,
+    },
+};
+
+/// Cluster and pool parameters that a plan must respect
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlanParameters {
+    /// Rent-exempt reserve for a stake account
+    pub stake_rent: u64,
+    /// Minimum delegation required by the stake program
+    pub stake_minimum_delegation: u64,
+    /// Current lamports in the pool's reserve stake account
+    pub reserve_lamports: u64,
+    /// Lamports to keep in the reserve for withdrawals, on top of the minimum
+    /// required by the program
+    pub reserve_buffer: u64,
+    /// Smallest increase or decrease worth submitting
+    pub threshold: u64,
+}
+
+/// Reason why a validator could not be brought closer to its target
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlockReason {
+    /// The validator's transient stake account is still activating or
+    /// deactivating
+    TransientStake,
+    /// The reserve does not have enough lamports left for the increase
+    InsufficientReserve,
+}
+
+/// Change to apply to a validator
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Adjustment {
+    /// The validator is within the threshold of its target
+    Balanced,
+    /// Move the given lamports from the reserve to the validator
+    Increase(u64),
+    /// Move the given lamports from the validator back to the reserve
+    Decrease(u64),
+    /// The validator is off target, but cannot be adjusted this epoch
+    Blocked(BlockReason),
+}
+
+/// Planned state of a single validator
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ValidatorPlan {
+    /// Validator vote account address
+    pub vote_account_address: Pubkey,
+    /// Active and transient lamports currently delegated to the validator
+    pub current_lamports: u64,
+    /// Lamports the policy wants delegated to the validator
+    pub target_lamports: u64,
+    /// Change to apply this epoch
+    pub adjustment: Adjustment,
+}
+
+/// Full rebalancing plan for a stake pool
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RebalancePlan {
+    /// Plan for every active validator, in validator list order
+    pub validators: Vec<ValidatorPlan>,
+    /// Reserve lamports available for increases before applying the plan
+    pub spendable_reserve_lamports: u64,
+    /// Reserve lamports still available for increases after applying the plan
+    pub remaining_reserve_lamports: u64,
+}
+
+impl RebalancePlan {
+    /// Decreases to submit, which should go first since they do not depend on
+    /// the reserve
+    pub fn decreases(&self) -> impl Iterator<Item = (&Pubkey, u64)> {
+        self.validators
+            .iter()
+            .filter_map(|validator| match validator.adjustment {
+                Adjustment::Decrease(lamports) => Some((&validator.vote_account_address, lamports)),
+                _ => None,
+            })
+    }
+
+    /// Increases to submit
+    pub fn increases(&self) -> impl Iterator<Item = (&Pubkey, u64)> {
+        self.validators
+            .iter()
+            .filter_map(|validator| match validator.adjustment {
+                Adjustment::Increase(lamports) => Some((&validator.vote_account_address, lamports)),
+                _ => None,
+            })
+    }
+}
+
+/// Check that the pool was updated in the current epoch, otherwise the
+/// balances in the validator list cannot be trusted
+pub fn check_pool_updated(
+    stake_pool: &StakePool,
+    current_epoch: u64,
+) -> Result<(), RebalanceError> {
+    if stake_pool.last_update_epoch < current_epoch {
+        Err(RebalanceError::StalePool {
+            last_update_epoch: stake_pool.last_update_epoch,
+            current_epoch,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn current_lamports(validator: &ValidatorStakeInfo) -> Result<u64, RebalanceError> {
+    validator
+        .active_stake_lamports
+        .checked_add(validator.transient_stake_lamports)
+        .ok_or(RebalanceError::CalculationFailure)
+}
+
+/// Compute the adjustments needed to move the pool towards the allocation
+/// described by `policy`.
+///
+/// Every active validator keeps at least the minimum stake account balance,
+/// and the rest of the delegated stake, plus whatever the reserve can spare,
+/// is split according to the policy weights. Validators with a transient
+/// stake account are left alone, and increases are funded in order of the
+/// largest shortfall until the reserve runs out.
+///
+/// If the reserve holds less than its floor, for example because it must
+/// cover outstanding withdrawal tickets, the deficit is taken out of the
+/// validator targets, which produces decreases.
+pub fn build_plan(
+    validator_list: &ValidatorList,
+    policy: &Policy,
+    params: &PlanParameters,
+) -> Result<RebalancePlan, RebalanceError> {
+    let active_validators = validator_list
+        .validators
+        .iter()
+        .filter(|validator| validator.status == StakeStatus::Active)
+        .collect::<Vec<_>>();
+    policy.check_validators(
+        active_validators
+            .iter()
+            .map(|validator| &validator.vote_account_address),
+    )?;
+
+    let weights = active_validators
+        .iter()
+        .map(|validator| policy.weight(&validator.vote_account_address) as u128)
+        .collect::<Vec<_>>();
+    let total_weight = weights.iter().sum::<u128>();
+    if total_weight == 0 {
+        return Err(RebalanceError::NoWeight);
+    }
+
+    let pool_minimum_delegation = minimum_delegation(params.stake_minimum_delegation);
+    let validator_floor = params.stake_rent.saturating_add(pool_minimum_delegation);
+
+    // the program requires the reserve to keep more than `stake_rent` after
+    // splitting `lamports + stake_rent` out for an increase
+    let reserve_floor = params
+        .stake_rent
+        .saturating_add(MINIMUM_RESERVE_LAMPORTS)
+        .saturating_add(params.reserve_buffer);
+    let spendable_reserve_lamports = params.reserve_lamports.saturating_sub(reserve_floor);
+    let reserve_deficit = reserve_floor.saturating_sub(params.reserve_lamports);
+
+    let mut total_lamports = spendable_reserve_lamports as u128;
+    for validator in &active_validators {
+        total_lamports = total_lamports
+            .checked_add(current_lamports(validator)? as u128)
+            .ok_or(RebalanceError::CalculationFailure)?;
+    }
+    let total_lamports = total_lamports.saturating_sub(reserve_deficit as u128);
+    let distributable_lamports = total_lamports
+        .saturating_sub((validator_floor as u128).saturating_mul(active_validators.len() as u128));
+
+    let minimum_decrease = validator_floor.max(params.threshold);
+    let minimum_increase = pool_minimum_delegation.max(params.threshold);
+
+    let mut validators = Vec::with_capacity(active_validators.len());
+    let mut shortfalls = vec![];
+    for (validator, weight) in active_validators.iter().zip(weights) {
+        let share = distributable_lamports
+            .checked_mul(weight)
+            .ok_or(RebalanceError::CalculationFailure)?
+            / total_weight;
+        let target_lamports = u64::try_from(share)
+            .ok()
+            .and_then(|share| share.checked_add(validator_floor))
+            .ok_or(RebalanceError::CalculationFailure)?;
+        let current_lamports = current_lamports(validator)?;
+
+        let adjustment = if current_lamports > target_lamports {
+            let excess = current_lamports - target_lamports;
+            let removable = validator
+                .active_stake_lamports
+                .saturating_sub(validator_floor);
+            if excess < minimum_decrease {
+                Adjustment::Balanced
+            } else if validator.transient_stake_lamports > 0 {
+                Adjustment::Blocked(BlockReason::TransientStake)
+            } else if removable < minimum_decrease {
+                Adjustment::Balanced
+            } else {
+                Adjustment::Decrease(excess.min(removable))
+            }
+        } else {
+            let shortfall = target_lamports - current_lamports;
+            if shortfall < minimum_increase {
+                Adjustment::Balanced
+            } else if validator.transient_stake_lamports > 0 {
+                Adjustment::Blocked(BlockReason::TransientStake)
+            } else {
+                shortfalls.push((validators.len(), shortfall));
+                Adjustment::Balanced
+            }
+        };
+        validators.push(ValidatorPlan {
+            vote_account_address: validator.vote_account_address,
+            current_lamports,
+            target_lamports,
+            adjustment,
+        });
+    }
+
+    // fund the largest shortfalls first, each increase temporarily costs an
+    // extra `stake_rent` from the reserve for the transient stake account
+    shortfalls.sort_by(|(_, a), (_, b)| b.cmp(a));
+    let mut remaining_reserve_lamports = spendable_reserve_lamports;
+    for (index, shortfall) in shortfalls {
+        let affordable = remaining_reserve_lamports.saturating_sub(params.stake_rent);
+        let lamports = shortfall.min(affordable);
+        validators[index].adjustment = if lamports < minimum_increase {
+            Adjustment::Blocked(BlockReason::InsufficientReserve)
+        } else {
+            remaining_reserve_lamports = remaining_reserve_lamports
+                .saturating_sub(lamports.saturating_add(params.stake_rent));
+            Adjustment::Increase(lamports)
+        };
+    }
+
+    Ok(RebalancePlan {
+        validators,
+        spendable_reserve_lamports,
+        remaining_reserve_lamports,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::collections::HashMap};
+
+    const STAKE_RENT: u64 = 2_282_880;
+    const SOL: u64 = 1_000_000_000;
+
+    fn params(reserve_lamports: u64) -> PlanParameters {
+        PlanParameters {
+            stake_rent: STAKE_RENT,
+            stake_minimum_delegation: 1,
+            reserve_lamports,
+            reserve_buffer: 0,
+            threshold: 0,
+        }
+    }
+
+    fn validator_list(stakes: &[(u64, u64)]) -> ValidatorList {
+        ValidatorList {
+            validators: stakes
+                .iter()
+                .map(|(active, transient)| ValidatorStakeInfo {
+                    active_stake_lamports: *active,
+                    transient_stake_lamports: *transient,
+                    vote_account_address: Pubkey::new_unique(),
+                    ..ValidatorStakeInfo::default()
+                })
+                .collect(),
+            ..ValidatorList::new(0)
+        }
+    }
+
+    fn reserve_for(lamports: u64) -> u64 {
+        lamports + STAKE_RENT + MINIMUM_RESERVE_LAMPORTS
+    }
+
+    #[test]
+    fn equal_split() {
+        let list = validator_list(&[(100 * SOL, 0), (10 * SOL, 0), (10 * SOL, 0)]);
+        let plan = build_plan(&list, &Policy::Equal, &params(reserve_for(0))).unwrap();
+        assert_eq!(
+            plan.validators[0].adjustment,
+            Adjustment::Decrease(60 * SOL)
+        );
+        assert_eq!(
+            plan.validators[1].adjustment,
+            Adjustment::Blocked(BlockReason::InsufficientReserve)
+        );
+        assert_eq!(
+            plan.validators[2].adjustment,
+            Adjustment::Blocked(BlockReason::InsufficientReserve)
+        );
+        for validator in &plan.validators {
+            assert_eq!(validator.target_lamports, 40 * SOL);
+        }
+
+        // once the decrease lands in the reserve, the increases are funded
+        let list = validator_list(&[(40 * SOL, 0), (10 * SOL, 0), (10 * SOL, 0)]);
+        let plan = build_plan(&list, &Policy::Equal, &params(reserve_for(60 * SOL))).unwrap();
+        assert_eq!(plan.validators[0].adjustment, Adjustment::Balanced);
+        assert_eq!(
+            plan.validators[1].adjustment,
+            Adjustment::Increase(30 * SOL)
+        );
+        assert_eq!(
+            plan.validators[2].adjustment,
+            Adjustment::Increase(30 * SOL - 2 * STAKE_RENT)
+        );
+        assert_eq!(plan.remaining_reserve_lamports, 0);
+    }
+
+    #[test]
+    fn weighted_split() {
+        let list = validator_list(&[(10 * SOL, 0), (10 * SOL, 0)]);
+        let weights = HashMap::from([
+            (list.validators[0].vote_account_address, 3),
+            (list.validators[1].vote_account_address, 1),
+        ]);
+        let plan = build_plan(&list, &Policy::Weights(weights), &params(reserve_for(0))).unwrap();
+        let floor = STAKE_RENT + 1_000_000;
+        let share = (20 * SOL - 2 * floor) / 4;
+        assert_eq!(plan.validators[0].target_lamports, floor + 3 * share);
+        assert_eq!(plan.validators[1].target_lamports, floor + share);
+        assert_eq!(
+            plan.validators[1].adjustment,
+            Adjustment::Decrease(10 * SOL - floor - share)
+        );
+        assert_eq!(
+            plan.validators[0].adjustment,
+            Adjustment::Blocked(BlockReason::InsufficientReserve)
+        );
+    }
+
+    #[test]
+    fn zero_weight_drains_to_floor() {
+        let list = validator_list(&[(10 * SOL, 0), (10 * SOL, 0)]);
+        let weights = HashMap::from([(list.validators[0].vote_account_address, 1)]);
+        let plan = build_plan(&list, &Policy::Weights(weights), &params(reserve_for(0))).unwrap();
+        let floor = STAKE_RENT + 1_000_000;
+        assert_eq!(plan.validators[1].target_lamports, floor);
+        assert_eq!(
+            plan.validators[1].adjustment,
+            Adjustment::Decrease(10 * SOL - floor)
+        );
+
+        let weights = HashMap::from([(list.validators[0].vote_account_address, 0)]);
+        assert_eq!(
+            build_plan(&list, &Policy::Weights(weights), &params(0)),
+            Err(RebalanceError::NoWeight)
+        );
+    }
+
+    #[test]
+    fn transient_stake_blocks() {
+        let list = validator_list(&[(10 * SOL, SOL), (SOL, 0)]);
+        let plan = build_plan(&list, &Policy::Equal, &params(reserve_for(0))).unwrap();
+        assert_eq!(
+            plan.validators[0].adjustment,
+            Adjustment::Blocked(BlockReason::TransientStake)
+        );
+        assert_eq!(
+            plan.validators[1].adjustment,
+            Adjustment::Blocked(BlockReason::InsufficientReserve)
+        );
+    }
+
+    #[test]
+    fn reserve_buffer_and_threshold() {
+        let list = validator_list(&[(10 * SOL, 0), (10 * SOL, 0)]);
+        let mut params = params(reserve_for(10 * SOL));
+        params.reserve_buffer = 6 * SOL;
+        let plan = build_plan(&list, &Policy::Equal, &params).unwrap();
+        assert_eq!(plan.spendable_reserve_lamports, 4 * SOL);
+        assert_eq!(plan.validators[0].target_lamports, 12 * SOL);
+        assert_eq!(plan.validators[0].adjustment, Adjustment::Increase(2 * SOL));
+        assert_eq!(
+            plan.validators[1].adjustment,
+            Adjustment::Increase(2 * SOL - 2 * STAKE_RENT)
+        );
+
+        params.threshold = 3 * SOL;
+        let plan = build_plan(&list, &Policy::Equal, &params).unwrap();
+        assert_eq!(plan.validators[0].adjustment, Adjustment::Balanced);
+        assert_eq!(plan.validators[1].adjustment, Adjustment::Balanced);
+    }
+
+    #[test]
+    fn reserve_deficit() {
+        // e.g. 4 SOL of outstanding withdrawal tickets
+        let list = validator_list(&[(10 * SOL, 0), (10 * SOL, 0)]);
+        let mut params = params(reserve_for(0));
+        params.reserve_buffer = 4 * SOL;
+        let plan = build_plan(&list, &Policy::Equal, &params).unwrap();
+        assert_eq!(plan.spendable_reserve_lamports, 0);
+        assert_eq!(plan.validators[0].target_lamports, 8 * SOL);
+        assert_eq!(plan.validators[0].adjustment, Adjustment::Decrease(2 * SOL));
+        assert_eq!(plan.validators[1].adjustment, Adjustment::Decrease(2 * SOL));
+    }
+}
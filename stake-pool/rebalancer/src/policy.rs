@@ -0,0 +1,166 @@
+//! Target weighting policies
+
+use {
+    crate::error::RebalanceError,
+    solana_program::pubkey::Pubkey,
+    std::{
+        collections::{HashMap, HashSet},
+        str::FromStr,
+    },
+};
+
+/// Policy deciding how the pool's stake should be split between its validators
+#[derive(Clone, Debug, PartialEq)]
+pub enum Policy {
+    /// Every active validator receives the same amount of stake
+    Equal,
+    /// Each validator receives stake in proportion to a fixed weight,
+    /// validators without a weight are drained down to the minimum
+    Weights(HashMap<Pubkey, u64>),
+    /// Each validator receives stake in proportion to its score, validators
+    /// scoring under `min_score` or without a score are drained down to the
+    /// minimum
+    Scores {
+        /// Score of each validator
+        scores: HashMap<Pubkey, u64>,
+        /// Minimum score required to receive any stake above the minimum
+        min_score: u64,
+    },
+}
+
+impl Policy {
+    /// Relative weight of the given validator under this policy
+    pub fn weight(&self, vote_account_address: &Pubkey) -> u64 {
+        match self {
+            Policy::Equal => 1,
+            Policy::Weights(weights) => weights.get(vote_account_address).copied().unwrap_or(0),
+            Policy::Scores { scores, min_score } => scores
+                .get(vote_account_address)
+                .copied()
+                .filter(|score| score >= min_score)
+                .unwrap_or(0),
+        }
+    }
+
+    /// Check that every validator referenced by the policy is part of the
+    /// given set of active validators
+    pub fn check_validators<'a>(
+        &self,
+        vote_account_addresses: impl Iterator<Item = &'a Pubkey>,
+    ) -> Result<(), RebalanceError> {
+        let entries = match self {
+            Policy::Equal => return Ok(()),
+            Policy::Weights(weights) => weights,
+            Policy::Scores { scores, .. } => scores,
+        };
+        let known = vote_account_addresses.collect::<HashSet<_>>();
+        for vote_account_address in entries.keys() {
+            if !known.contains(vote_account_address) {
+                return Err(RebalanceError::UnknownValidator(*vote_account_address));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a weights or scores file.
+///
+/// Each non-empty line contains a vote account address and an integer value,
+/// separated by whitespace. Anything after a `#` is ignored.
+pub fn parse_values(input: &str) -> Result<HashMap<Pubkey, u64>, RebalanceError> {
+    let mut values = HashMap::new();
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let content = line.split('#').next().unwrap_or_default().trim();
+        if content.is_empty() {
+            continue;
+        }
+        let invalid = |reason: &str| RebalanceError::InvalidLine {
+            line: line_number,
+            reason: reason.to_string(),
+        };
+        let mut fields = content.split_whitespace();
+        let vote_account_address = fields
+            .next()
+            .and_then(|field| Pubkey::from_str(field).ok())
+            .ok_or_else(|| invalid("expected a vote account address"))?;
+        let value = fields
+            .next()
+            .and_then(|field| field.parse::<u64>().ok())
+            .ok_or_else(|| invalid("expected an integer value"))?;
+        if fields.next().is_some() {
+            return Err(invalid("unexpected trailing data"));
+        }
+        if values.insert(vote_account_address, value).is_some() {
+            return Err(RebalanceError::DuplicateEntry(vote_account_address));
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let input = format!(
+            "# vote account, weight\n{} 3\n\n  {}\t7 # trailing\n",
+            first, second
+        );
+        let values = parse_values(&input).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[&first], 3);
+        assert_eq!(values[&second], 7);
+
+        assert_eq!(
+            parse_values(&format!("{} 3\n{} -1", first, second)),
+            Err(RebalanceError::InvalidLine {
+                line: 2,
+                reason: "expected an integer value".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_values("not-a-pubkey 3"),
+            Err(RebalanceError::InvalidLine {
+                line: 1,
+                reason: "expected a vote account address".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_values(&format!("{} 3\n{} 4", first, first)),
+            Err(RebalanceError::DuplicateEntry(first))
+        );
+    }
+
+    #[test]
+    fn weights() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let missing = Pubkey::new_unique();
+        let values = HashMap::from([(first, 10), (second, 2)]);
+
+        assert_eq!(Policy::Equal.weight(&missing), 1);
+
+        let policy = Policy::Weights(values.clone());
+        assert_eq!(policy.weight(&first), 10);
+        assert_eq!(policy.weight(&second), 2);
+        assert_eq!(policy.weight(&missing), 0);
+
+        let policy = Policy::Scores {
+            scores: values,
+            min_score: 5,
+        };
+        assert_eq!(policy.weight(&first), 10);
+        assert_eq!(policy.weight(&second), 0);
+        assert_eq!(policy.weight(&missing), 0);
+
+        assert_eq!(policy.check_validators([first, second].iter()), Ok(()));
+        assert_eq!(
+            policy.check_validators([first, missing].iter()),
+            Err(RebalanceError::UnknownValidator(second))
+        );
+    }
+}
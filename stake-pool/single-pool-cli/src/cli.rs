@@ -82,6 +82,18 @@ pub enum Command {
     /// use an account created with create-stake.
     Deposit(DepositCli),
 
+    /// Delegate SOL to a pool's validator in a stake account held by the pool. This is the first
+    /// of two steps: no pool tokens are minted until the stake is active at the next epoch, and
+    /// the deposit is exchanged for them with claim-sol-deposit. A deposit that is already active
+    /// is claimed automatically before depositing again.
+    /// Provide either pool or vote account address, plus an amount of lamports to deposit.
+    DepositSol(DepositSolCli),
+
+    /// Exchange an active SOL deposit for pool tokens, returning the stake account rent. This is
+    /// the second step of deposit-sol, available from the epoch after the deposit.
+    /// Provide either pool or vote account address.
+    ClaimSolDeposit(ClaimSolDepositCli),
+
     /// Withdraw stake into a new stake account, burning tokens in exchange.
     /// Provide either pool or vote account address, plus either an amount of tokens to burn
     /// or the ALL keyword to burn all.
@@ -95,6 +107,11 @@ pub enum Command {
     /// performed by the validator vote account's withdraw authority
     UpdateTokenMetadata(UpdateMetadataCli),
 
+    /// Permissionlessly list a pool in the on-chain pool registry. Normally this is done
+    /// automatically upon initialization, so this is only needed for pools created before
+    /// the registry existed
+    Register(RegisterCli),
+
     /// Create and delegate a new stake account to a given validator, using a default address
     /// linked to the intended depository pool
     CreateDefaultStake(CreateStakeCli),
@@ -152,6 +169,57 @@ pub struct DepositCli {
     pub lamport_recipient_address: Option<Pubkey>,
 }
 
+#[derive(Clone, Debug, Args)]
+#[clap(group(pool_source_group()))]
+pub struct DepositSolCli {
+    /// Number of lamports to deposit
+    pub lamports: u64,
+
+    /// The pool to deposit into
+    #[clap(short, long = "pool", value_parser = |p: &str| parse_address(p, "pool_address"))]
+    pub pool_address: Option<Pubkey>,
+
+    /// The vote account corresponding to the pool to deposit into
+    #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
+    pub vote_account_address: Option<Pubkey>,
+
+    /// Source of the deposited lamports, who later claims the deposit. Defaults to the client keypair
+    #[clap(long = "from", id = "FROM_KEYPAIR", validator = |s| is_valid_signer(s))]
+    pub from: Option<SignerArg>,
+
+    /// The token account to mint to when claiming a previous deposit. Defaults to the client keypair's associated token account
+    #[clap(long = "token-account", value_parser = |p: &str| parse_address(p, "token_account_address"))]
+    pub token_account_address: Option<Pubkey>,
+
+    /// The wallet to refund the rent of a claimed previous deposit to. Defaults to the client keypair's pubkey
+    #[clap(long = "recipient", value_parser = |p: &str| parse_address(p, "lamport_recipient_address"))]
+    pub lamport_recipient_address: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, Args)]
+#[clap(group(pool_source_group()))]
+pub struct ClaimSolDepositCli {
+    /// The pool the SOL was deposited into
+    #[clap(short, long = "pool", value_parser = |p: &str| parse_address(p, "pool_address"))]
+    pub pool_address: Option<Pubkey>,
+
+    /// The vote account corresponding to the pool the SOL was deposited into
+    #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
+    pub vote_account_address: Option<Pubkey>,
+
+    /// The depositor. Defaults to the client keypair
+    #[clap(long = "from", id = "FROM_KEYPAIR", validator = |s| is_valid_signer(s))]
+    pub from: Option<SignerArg>,
+
+    /// The token account to mint to. Defaults to the client keypair's associated token account
+    #[clap(long = "token-account", value_parser = |p: &str| parse_address(p, "token_account_address"))]
+    pub token_account_address: Option<Pubkey>,
+
+    /// The wallet to refund stake account rent to. Defaults to the client keypair's pubkey
+    #[clap(long = "recipient", value_parser = |p: &str| parse_address(p, "lamport_recipient_address"))]
+    pub lamport_recipient_address: Option<Pubkey>,
+}
+
 #[derive(Clone, Debug, Args)]
 #[clap(group(pool_source_group()))]
 pub struct WithdrawCli {
@@ -196,6 +264,18 @@ pub struct CreateMetadataCli {
     pub vote_account_address: Option<Pubkey>,
 }
 
+#[derive(Clone, Debug, Args)]
+#[clap(group(pool_source_group()))]
+pub struct RegisterCli {
+    /// The pool to register
+    #[clap(short, long = "pool", value_parser = |p: &str| parse_address(p, "pool_address"))]
+    pub pool_address: Option<Pubkey>,
+
+    /// The vote account corresponding to the pool to register
+    #[clap(long = "vote-account", value_parser = |p: &str| parse_address(p, "vote_account_address"))]
+    pub vote_account_address: Option<Pubkey>,
+}
+
 #[derive(Clone, Debug, Args)]
 #[clap(group(pool_source_group()))]
 pub struct UpdateMetadataCli {
@@ -379,6 +459,12 @@ impl Command {
                     "stake_authority",
                 )?;
             }
+            Command::DepositSol(ref mut config) => {
+                config.from = with_signer(matches, wallet_manager, config.from.clone(), "from")?;
+            }
+            Command::ClaimSolDeposit(ref mut config) => {
+                config.from = with_signer(matches, wallet_manager, config.from.clone(), "from")?;
+            }
             Command::Withdraw(ref mut config) => {
                 config.token_authority = with_signer(
                     matches,
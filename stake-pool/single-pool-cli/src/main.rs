@@ -3,12 +3,9 @@
 use {
     borsh::BorshSerialize,
     clap::{CommandFactory, Parser},
-    solana_client::{
-        rpc_config::RpcProgramAccountsConfig,
-        rpc_filter::{Memcmp, RpcFilterType},
-    },
     solana_sdk::{
         borsh::try_from_slice_unchecked,
+        instruction::Instruction,
         pubkey::Pubkey,
         signature::Signature,
         signature::{Keypair, Signer},
@@ -17,9 +14,11 @@ use {
     },
     solana_vote_program::{self as vote_program, vote_state::VoteState},
     spl_single_validator_pool::{
-        self as single_pool, find_default_deposit_account_address, find_pool_address,
-        find_pool_mint_address, find_pool_stake_address, instruction::SinglePoolInstruction,
-        state::SinglePool,
+        self as single_pool, find_all_pool_registry_page_addresses,
+        find_default_deposit_account_address, find_pool_address, find_pool_mint_address,
+        find_pool_sol_deposit_address, find_pool_stake_address,
+        instruction::SinglePoolInstruction,
+        state::{SinglePool, SinglePoolRegistryPage},
     },
     spl_token_client::token::Token,
 };
@@ -35,6 +34,9 @@ use output::*;
 
 mod quarantine;
 
+// matches the rpc limit for getMultipleAccounts
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let cli = Cli::parse();
@@ -62,6 +64,12 @@ impl Command {
         match self {
             Command::Initialize(command_config) => command_initialize(config, command_config).await,
             Command::Deposit(command_config) => command_deposit(config, command_config).await,
+            Command::DepositSol(command_config) => {
+                command_deposit_sol(config, command_config).await
+            }
+            Command::ClaimSolDeposit(command_config) => {
+                command_claim_sol_deposit(config, command_config).await
+            }
             Command::Withdraw(command_config) => command_withdraw(config, command_config).await,
             Command::CreateTokenMetadata(command_config) => {
                 command_create_metadata(config, command_config).await
@@ -69,6 +77,7 @@ impl Command {
             Command::UpdateTokenMetadata(command_config) => {
                 command_update_metadata(config, command_config).await
             }
+            Command::Register(command_config) => command_register(config, command_config).await,
             Command::CreateDefaultStake(command_config) => {
                 command_create_stake(config, command_config).await
             }
@@ -329,6 +338,253 @@ async fn command_deposit(config: &Config, command_config: DepositCli) -> Command
     ))
 }
 
+// deposit sol
+async fn command_deposit_sol(config: &Config, command_config: DepositSolCli) -> CommandResult {
+    let payer = config.fee_payer()?;
+    let owner = config.default_signer()?;
+    let from = signer_from_arg(command_config.from, &owner)?;
+
+    let pool_address = pool_address_from_args(
+        command_config.pool_address,
+        command_config.vote_account_address,
+    );
+
+    println_display(
+        config,
+        format!(
+            "Depositing {} lamports from {} into pool {}\n",
+            command_config.lamports,
+            from.pubkey(),
+            pool_address
+        ),
+    );
+
+    let vote_account_address =
+        if let Some(pool_data) = config.program_client.get_account(pool_address).await? {
+            try_from_slice_unchecked::<SinglePool>(&pool_data.data)?.vote_account_address
+        } else {
+            return Err(format!("Pool {} has not been initialized", pool_address).into());
+        };
+
+    if command_config.lamports == 0 {
+        return Err("Cannot deposit zero lamports".into());
+    }
+
+    let current_epoch = config.rpc_client.get_epoch_info().await?.epoch;
+    let sol_deposit_address =
+        find_pool_sol_deposit_address(&single_pool::id(), &pool_address, &from.pubkey());
+
+    // a previous deposit must be claimed first, which we do here once it is active
+    let mut instructions = vec![];
+    if let Some((_, stake)) = quarantine::get_stake_info(config, &sol_deposit_address).await? {
+        if stake.delegation.activation_epoch >= current_epoch {
+            return Err(format!(
+                "SOL deposit {} is still activating and can be claimed from epoch {}",
+                sol_deposit_address,
+                stake.delegation.activation_epoch + 1
+            )
+            .into());
+        }
+
+        println_display(
+            config,
+            format!(
+                "Claiming active SOL deposit {} before depositing again\n",
+                sol_deposit_address
+            ),
+        );
+
+        let (instruction, _) = claim_sol_deposit_instruction(
+            config,
+            &pool_address,
+            &from.pubkey(),
+            command_config.token_account_address,
+            command_config.lamport_recipient_address,
+        )
+        .await?;
+        instructions.push(instruction);
+    }
+
+    instructions.push(single_pool::instruction::deposit_sol(
+        &single_pool::id(),
+        &vote_account_address,
+        &from.pubkey(),
+        command_config.lamports,
+    ));
+
+    let mut signers = vec![];
+    for signer in [payer.clone(), from] {
+        if !signers.contains(&signer) {
+            signers.push(signer);
+        }
+    }
+
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &signers,
+        config.program_client.get_latest_blockhash().await?,
+    );
+
+    let signature = process_transaction(config, transaction).await?;
+
+    println_display(
+        config,
+        format!(
+            "Pool tokens are minted once the deposit is active. From epoch {}, run claim-sol-deposit, \
+             or deposit-sol again to claim it along with the new deposit\n",
+            current_epoch + 1
+        ),
+    );
+
+    Ok(format_output(
+        config,
+        "DepositSol".to_string(),
+        CreateStakeOutput {
+            pool_address,
+            stake_account_address: sol_deposit_address,
+            signature,
+        },
+    ))
+}
+
+// claim sol deposit
+async fn command_claim_sol_deposit(
+    config: &Config,
+    command_config: ClaimSolDepositCli,
+) -> CommandResult {
+    let payer = config.fee_payer()?;
+    let owner = config.default_signer()?;
+    let from = signer_from_arg(command_config.from, &owner)?;
+
+    let pool_address = pool_address_from_args(
+        command_config.pool_address,
+        command_config.vote_account_address,
+    );
+
+    let sol_deposit_address =
+        find_pool_sol_deposit_address(&single_pool::id(), &pool_address, &from.pubkey());
+
+    println_display(
+        config,
+        format!(
+            "Claiming SOL deposit {} into pool {}\n",
+            sol_deposit_address, pool_address
+        ),
+    );
+
+    if config
+        .program_client
+        .get_account(sol_deposit_address)
+        .await?
+        .is_none()
+    {
+        return Err(format!(
+            "No SOL deposit from {} into pool {}",
+            from.pubkey(),
+            pool_address
+        )
+        .into());
+    }
+
+    let pool_mint_address = find_pool_mint_address(&single_pool::id(), &pool_address);
+    let token = Token::new(
+        config.program_client.clone(),
+        &spl_token::id(),
+        &pool_mint_address,
+        None,
+        payer.clone(),
+    );
+
+    let (instruction, token_account_address) = claim_sol_deposit_instruction(
+        config,
+        &pool_address,
+        &from.pubkey(),
+        command_config.token_account_address,
+        command_config.lamport_recipient_address,
+    )
+    .await?;
+
+    let previous_token_amount = token
+        .get_account_info(&token_account_address)
+        .await?
+        .base
+        .amount;
+
+    let mut signers = vec![];
+    for signer in [payer.clone(), from] {
+        if !signers.contains(&signer) {
+            signers.push(signer);
+        }
+    }
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &signers,
+        config.program_client.get_latest_blockhash().await?,
+    );
+
+    let signature = process_transaction(config, transaction).await?;
+    let token_amount = token
+        .get_account_info(&token_account_address)
+        .await?
+        .base
+        .amount
+        - previous_token_amount;
+
+    Ok(format_output(
+        config,
+        "ClaimSolDeposit".to_string(),
+        DepositOutput {
+            pool_address,
+            token_amount,
+            signature,
+        },
+    ))
+}
+
+// builds the claim of a sol deposit, minting to the token account provided, or to the client
+// keypair's associated token account, which is created if needed
+async fn claim_sol_deposit_instruction(
+    config: &Config,
+    pool_address: &Pubkey,
+    depositor: &Pubkey,
+    token_account_address: Option<Pubkey>,
+    lamport_recipient_address: Option<Pubkey>,
+) -> Result<(Instruction, Pubkey), Error> {
+    let payer = config.fee_payer()?;
+    let owner = config.default_signer()?;
+    let lamport_recipient = lamport_recipient_address.unwrap_or_else(|| owner.pubkey());
+
+    let token_account_address = if let Some(account) = token_account_address {
+        account
+    } else {
+        let pool_mint_address = find_pool_mint_address(&single_pool::id(), pool_address);
+        let token = Token::new(
+            config.program_client.clone(),
+            &spl_token::id(),
+            &pool_mint_address,
+            None,
+            payer,
+        );
+        token
+            .get_or_create_associated_account_info(&owner.pubkey())
+            .await?;
+        token.get_associated_token_address(&owner.pubkey())
+    };
+
+    let instruction = single_pool::instruction::claim_sol_deposit(
+        &single_pool::id(),
+        pool_address,
+        depositor,
+        &token_account_address,
+        &lamport_recipient,
+    );
+
+    Ok((instruction, token_account_address))
+}
+
 // withdraw stake
 async fn command_withdraw(config: &Config, command_config: WithdrawCli) -> CommandResult {
     let payer = config.fee_payer()?;
@@ -608,6 +864,47 @@ async fn command_update_metadata(
     ))
 }
 
+// list a pool in the registry
+async fn command_register(config: &Config, command_config: RegisterCli) -> CommandResult {
+    let payer = config.fee_payer()?;
+
+    let pool_address = pool_address_from_args(
+        command_config.pool_address,
+        command_config.vote_account_address,
+    );
+
+    println_display(config, format!("Registering pool {}\n", pool_address));
+
+    // the registry is keyed by vote account
+    let vote_account_address =
+        if let Some(pool_data) = config.program_client.get_account(pool_address).await? {
+            try_from_slice_unchecked::<SinglePool>(&pool_data.data)?.vote_account_address
+        } else {
+            return Err(format!("Pool {} has not been initialized", pool_address).into());
+        };
+
+    let instruction = single_pool::instruction::register_pool(
+        &single_pool::id(),
+        &vote_account_address,
+        &payer.pubkey(),
+    );
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &vec![payer],
+        config.program_client.get_latest_blockhash().await?,
+    );
+
+    let signature = process_transaction(config, transaction).await?;
+
+    Ok(format_output(
+        config,
+        "Register".to_string(),
+        SignatureOutput { signature },
+    ))
+}
+
 // create default stake account
 async fn command_create_stake(config: &Config, command_config: CreateStakeCli) -> CommandResult {
     let payer = config.fee_payer()?;
@@ -686,26 +983,27 @@ async fn command_create_stake(config: &Config, command_config: CreateStakeCli) -
 // display stake pool(s)
 async fn command_display(config: &Config, command_config: DisplayCli) -> CommandResult {
     if command_config.all {
-        // the filter isnt necessary now but makes the cli forward-compatible
-        let pools = config
-            .rpc_client
-            .get_program_accounts_with_config(
-                &single_pool::id(),
-                RpcProgramAccountsConfig {
-                    filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
-                        0,
-                        vec![1],
-                    ))]),
-                    ..RpcProgramAccountsConfig::default()
-                },
-            )
-            .await?;
+        // registered pools are listed in the registry pages, so no account scan is needed
+        let registry_page_addresses = find_all_pool_registry_page_addresses(&single_pool::id());
+        let mut vote_account_addresses = vec![];
+        for chunk in registry_page_addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            for registry_page in config
+                .rpc_client
+                .get_multiple_accounts(chunk)
+                .await?
+                .into_iter()
+                .flatten()
+            {
+                let (_, addresses) = SinglePoolRegistryPage::unpack(&registry_page.data)?;
+                vote_account_addresses.extend(addresses);
+            }
+        }
 
         let mut displays = vec![];
-        for pool in pools {
-            let vote_account_address =
-                try_from_slice_unchecked::<SinglePool>(&pool.1.data)?.vote_account_address;
-            displays.push(get_pool_display(config, pool.0, Some(vote_account_address)).await?);
+        for vote_account_address in vote_account_addresses {
+            let pool_address = find_pool_address(&single_pool::id(), &vote_account_address);
+            displays
+                .push(get_pool_display(config, pool_address, Some(vote_account_address)).await?);
         }
 
         Ok(format_output(
@@ -308,6 +308,55 @@ async fn deposit(use_default: bool) {
     assert!(status.success());
 }
 
+#[tokio::test]
+#[serial]
+async fn deposit_sol() {
+    let env = setup(true).await;
+
+    let deposit_sol_args = [
+        "deposit-sol",
+        "-C",
+        &env.config_file_path,
+        "--vote-account",
+        &env.vote_account.to_string(),
+        &LAMPORTS_PER_SOL.to_string(),
+    ];
+    let status = Command::new(SVSP_CLI)
+        .args(deposit_sol_args)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // an activating deposit cannot be claimed, so depositing again fails
+    let status = Command::new(SVSP_CLI)
+        .args(deposit_sol_args)
+        .status()
+        .unwrap();
+    assert!(!status.success());
+
+    // once active, depositing again claims the previous deposit
+    wait_for_next_epoch(&env.rpc_client).await;
+    let status = Command::new(SVSP_CLI)
+        .args(deposit_sol_args)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    wait_for_next_epoch(&env.rpc_client).await;
+
+    let status = Command::new(SVSP_CLI)
+        .args([
+            "claim-sol-deposit",
+            "-C",
+            &env.config_file_path,
+            "--vote-account",
+            &env.vote_account.to_string(),
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
 #[tokio::test]
 #[serial]
 async fn withdraw() {
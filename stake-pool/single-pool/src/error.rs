@@ -81,6 +81,34 @@ pub enum SinglePoolError {
     /// Attempted to initialize a pool that is already initialized.
     #[error("PoolAlreadyInitialized")]
     PoolAlreadyInitialized,
+
+    // 20
+    /// Provided pool transient stake account does not match address derived from the pool account.
+    #[error("InvalidPoolTransientStakeAccount")]
+    InvalidPoolTransientStakeAccount,
+    /// Not enough undelegated lamports in the pool to cover the provided quantity of pool tokens.
+    #[error("InsufficientUndelegatedLamports")]
+    InsufficientUndelegatedLamports,
+    /// Provided pool registry page does not match address derived from the vote account, or is otherwise invalid.
+    #[error("InvalidPoolRegistryPage")]
+    InvalidPoolRegistryPage,
+    /// Pool is already listed in its registry page.
+    #[error("PoolAlreadyRegistered")]
+    PoolAlreadyRegistered,
+    /// Provided SOL deposit stake account does not match address derived from the pool account and depositor.
+    #[error("InvalidPoolSolDepositAccount")]
+    InvalidPoolSolDepositAccount,
+
+    // 25
+    /// Provided pool SOL deposit authority does not match address derived from the pool account.
+    #[error("InvalidPoolSolDepositAuthority")]
+    InvalidPoolSolDepositAuthority,
+    /// Depositor already has a SOL deposit which has not been claimed yet.
+    #[error("SolDepositPending")]
+    SolDepositPending,
+    /// SOL deposit is still activating and cannot be claimed yet.
+    #[error("SolDepositNotActive")]
+    SolDepositNotActive,
 }
 impl From<SinglePoolError> for ProgramError {
     fn from(e: SinglePoolError) -> Self {
@@ -141,6 +169,24 @@ impl PrintProgramError for SinglePoolError {
                 msg!("Error: Attempted to deposit from or withdraw to pool stake account."),
             SinglePoolError::PoolAlreadyInitialized =>
                 msg!("Error: Attempted to initialize a pool that is already initialized."),
+            SinglePoolError::InvalidPoolTransientStakeAccount =>
+                msg!("Error: Provided pool transient stake account does not match address derived from the pool account."),
+            SinglePoolError::InsufficientUndelegatedLamports =>
+                msg!("Error: Not enough undelegated lamports in the pool to cover the provided quantity of pool tokens."),
+            SinglePoolError::InvalidPoolRegistryPage =>
+                msg!("Error: Provided pool registry page does not match address derived from the vote account, \
+                     or is otherwise invalid."),
+            SinglePoolError::PoolAlreadyRegistered =>
+                msg!("Error: Pool is already listed in its registry page."),
+            SinglePoolError::InvalidPoolSolDepositAccount =>
+                msg!("Error: Provided SOL deposit stake account does not match address derived from the pool account \
+                     and depositor."),
+            SinglePoolError::InvalidPoolSolDepositAuthority =>
+                msg!("Error: Provided pool SOL deposit authority does not match address derived from the pool account."),
+            SinglePoolError::SolDepositPending =>
+                msg!("Error: Depositor already has a SOL deposit which has not been claimed yet."),
+            SinglePoolError::SolDepositNotActive =>
+                msg!("Error: SOL deposit is still activating and cannot be claimed yet."),
         }
     }
 }
@@ -5,9 +5,12 @@
 use {
     crate::{
         find_default_deposit_account_address_and_seed, find_pool_address, find_pool_mint_address,
-        find_pool_mint_authority_address, find_pool_mpl_authority_address, find_pool_stake_address,
-        find_pool_stake_authority_address,
+        find_pool_mint_authority_address, find_pool_mpl_authority_address,
+        find_pool_registry_page_address, find_pool_sol_deposit_address,
+        find_pool_sol_deposit_authority_address, find_pool_stake_address,
+        find_pool_stake_authority_address, find_pool_transient_stake_address,
         inline_mpl_token_metadata::{self, pda::find_metadata_account},
+        pool_registry_page_index,
         state::SinglePool,
     },
     borsh::{BorshDeserialize, BorshSerialize},
@@ -27,6 +30,8 @@ pub enum SinglePoolInstruction {
     ///   Initialize the mint and stake account for a new single-validator pool.
     ///   The pool stake account must contain the rent-exempt minimum plus the minimum delegation.
     ///   No tokens will be minted: to deposit more, use `Deposit` after `InitializeStake`.
+    ///   The pool is listed in the registry page for its vote account, which the payer
+    ///   creates or extends.
     ///
     ///   0. `[]` Validator vote account
     ///   1. `[w]` Pool account
@@ -41,6 +46,8 @@ pub enum SinglePoolInstruction {
     ///  10. `[]` System program
     ///  11. `[]` Token program
     ///  12. `[]` Stake program
+    ///  13. `[w]` Pool registry page
+    ///  14. `[s, w]` Payer for pool registry page rent
     InitializePool,
 
     ///   Deposit stake into the pool.  The output is a "pool" token representing fractional
@@ -58,6 +65,7 @@ pub enum SinglePoolInstruction {
     ///   9. `[]` Stake history sysvar
     ///  10. `[]` Token program
     ///  11. `[]` Stake program
+    ///  12. `[]` Pool transient stake account
     DepositStake,
 
     ///   Redeem tokens issued by this pool for stake at the current ratio.
@@ -72,6 +80,7 @@ pub enum SinglePoolInstruction {
     ///   7. `[]` Clock sysvar
     ///   8. `[]` Token program
     ///   9. `[]` Stake program
+    ///  10. `[]` Pool transient stake account
     WithdrawStake {
         /// User authority for the new stake account
         user_stake_authority: Pubkey,
@@ -110,6 +119,105 @@ pub enum SinglePoolInstruction {
         /// URI of the uploaded metadata of the spl-token
         uri: String,
     },
+
+    ///   Deposit SOL into the pool, the first of two steps. The lamports are delegated to the pool
+    ///   validator in a stake account derived from the pool and the depositor, staked and
+    ///   withdrawn by the pool SOL deposit authority. No tokens are minted here: until the
+    ///   depositor sends `ClaimSolDeposit` once the stake is active, the deposit holds neither
+    ///   pool tokens nor a share of the pool stake, and does not share in the rewards of the
+    ///   epochs it spends activating. Clients should send the claim from the next epoch on, for
+    ///   instance ahead of the next `DepositSol`, since a depositor can only have one unclaimed
+    ///   deposit per pool. The lamports must cover the rent-exemption of the stake account plus
+    ///   the minimum delegation.
+    ///
+    ///   0. `[]` Validator vote account
+    ///   1. `[]` Pool account
+    ///   2. `[w]` Pool SOL deposit stake account for the depositor
+    ///   3. `[]` Pool SOL deposit authority
+    ///   4. `[s, w]` Depositor, the user account to take lamports from
+    ///   5. `[]` Rent sysvar
+    ///   6. `[]` Clock sysvar
+    ///   7. `[]` Stake history sysvar
+    ///   8. `[]` Stake config sysvar
+    ///   9. `[]` System program
+    ///  10. `[]` Stake program
+    DepositSol {
+        /// Amount of lamports to deposit
+        lamports: u64,
+    },
+
+    ///   Redeem tokens issued by this pool for SOL at the current ratio.
+    ///   Only lamports in the pool transient stake account which are not delegated can be withdrawn.
+    ///
+    ///   0. `[]` Pool account
+    ///   1. `[]` Pool stake account
+    ///   2. `[w]` Pool transient stake account
+    ///   3. `[w]` Pool token mint
+    ///   4. `[]` Pool stake authority
+    ///   5. `[]` Pool mint authority
+    ///   6. `[w]` User account to receive lamports
+    ///   7. `[w]` User account to take pool tokens from
+    ///   8. `[]` Clock sysvar
+    ///   9. `[]` Stake history sysvar
+    ///  10. `[]` Token program
+    ///  11. `[]` Stake program
+    WithdrawSol {
+        /// Amount of tokens to redeem for SOL
+        token_amount: u64,
+    },
+
+    ///   Permissionless crank to delegate undelegated lamports held by the pool.
+    ///   Merges the pool transient stake account into the pool stake account once it is active,
+    ///   moves any undelegated lamports from the pool stake account to the pool transient stake
+    ///   account, and delegates the pool transient stake account if it holds at least the
+    ///   minimum delegation.
+    ///
+    ///   0. `[]` Validator vote account
+    ///   1. `[]` Pool account
+    ///   2. `[w]` Pool stake account
+    ///   3. `[w]` Pool transient stake account
+    ///   4. `[]` Pool stake authority
+    ///   5. `[]` Rent sysvar
+    ///   6. `[]` Clock sysvar
+    ///   7. `[]` Stake history sysvar
+    ///   8. `[]` Stake config sysvar
+    ///   9. `[]` System program
+    ///  10. `[]` Stake program
+    ReplenishPool,
+
+    ///   Permissionlessly list an initialized pool in the registry page for its vote account,
+    ///   creating the page if it does not exist yet. The payer covers any additional rent.
+    ///   `InitializePool` registers new pools itself, so this is only needed to backfill pools
+    ///   created before the registry existed.
+    ///
+    ///   0. `[]` Validator vote account
+    ///   1. `[]` Pool account
+    ///   2. `[w]` Pool registry page
+    ///   3. `[s, w]` Payer
+    ///   4. `[]` System program
+    RegisterPool,
+
+    ///   Merge an active SOL deposit into the pool stake account and mint pool tokens for it at
+    ///   the current ratio, as `DepositStake` does, the second step of `DepositSol`. Fails with
+    ///   `SolDepositNotActive` during the epoch of the deposit. The rent-exemption of the SOL
+    ///   deposit stake account is returned to the user.
+    ///
+    ///   0. `[]` Pool account
+    ///   1. `[w]` Pool stake account
+    ///   2. `[w]` Pool token mint
+    ///   3. `[]` Pool stake authority
+    ///   4. `[]` Pool mint authority
+    ///   5. `[]` Pool SOL deposit authority
+    ///   6. `[w]` Pool SOL deposit stake account for the depositor
+    ///   7. `[s]` Depositor
+    ///   8. `[w]` User account to receive pool tokens
+    ///   9. `[w]` User account to receive lamports
+    ///  10. `[]` Clock sysvar
+    ///  11. `[]` Stake history sysvar
+    ///  12. `[]` Token program
+    ///  13. `[]` Stake program
+    ///  14. `[]` Pool transient stake account
+    ClaimSolDeposit,
 }
 
 /// Creates all necessary instructions to initialize the stake pool.
@@ -136,13 +244,17 @@ pub fn initialize(
         system_instruction::transfer(payer, &pool_address, pool_rent),
         system_instruction::transfer(payer, &stake_address, stake_rent_plus_minimum),
         system_instruction::transfer(payer, &mint_address, mint_rent),
-        initialize_pool(program_id, vote_account_address),
+        initialize_pool(program_id, vote_account_address, payer),
         create_token_metadata(program_id, &pool_address, payer),
     ]
 }
 
 /// Creates an `InitializePool` instruction.
-pub fn initialize_pool(program_id: &Pubkey, vote_account_address: &Pubkey) -> Instruction {
+pub fn initialize_pool(
+    program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
     let pool_address = find_pool_address(program_id, vote_account_address);
     let mint_address = find_pool_mint_address(program_id, &pool_address);
 
@@ -167,6 +279,14 @@ pub fn initialize_pool(program_id: &Pubkey, vote_account_address: &Pubkey) -> In
         AccountMeta::new_readonly(system_program::id(), false),
         AccountMeta::new_readonly(spl_token::id(), false),
         AccountMeta::new_readonly(stake::program::id(), false),
+        AccountMeta::new(
+            find_pool_registry_page_address(
+                program_id,
+                pool_registry_page_index(vote_account_address),
+            ),
+            false,
+        ),
+        AccountMeta::new(*payer, true),
     ];
 
     Instruction {
@@ -241,6 +361,10 @@ pub fn deposit_stake(
         AccountMeta::new_readonly(sysvar::stake_history::id(), false),
         AccountMeta::new_readonly(spl_token::id(), false),
         AccountMeta::new_readonly(stake::program::id(), false),
+        AccountMeta::new_readonly(
+            find_pool_transient_stake_address(program_id, pool_address),
+            false,
+        ),
     ];
 
     Instruction {
@@ -316,6 +440,201 @@ pub fn withdraw_stake(
         AccountMeta::new_readonly(sysvar::clock::id(), false),
         AccountMeta::new_readonly(spl_token::id(), false),
         AccountMeta::new_readonly(stake::program::id(), false),
+        AccountMeta::new_readonly(
+            find_pool_transient_stake_address(program_id, pool_address),
+            false,
+        ),
+    ];
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates a `DepositSol` instruction.
+pub fn deposit_sol(
+    program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    user_wallet: &Pubkey,
+    lamports: u64,
+) -> Instruction {
+    let pool_address = find_pool_address(program_id, vote_account_address);
+    let data = SinglePoolInstruction::DepositSol { lamports }
+        .try_to_vec()
+        .unwrap();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*vote_account_address, false),
+        AccountMeta::new_readonly(pool_address, false),
+        AccountMeta::new(
+            find_pool_sol_deposit_address(program_id, &pool_address, user_wallet),
+            false,
+        ),
+        AccountMeta::new_readonly(
+            find_pool_sol_deposit_authority_address(program_id, &pool_address),
+            false,
+        ),
+        AccountMeta::new(*user_wallet, true),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+        AccountMeta::new_readonly(stake::config::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(stake::program::id(), false),
+    ];
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates a `ClaimSolDeposit` instruction.
+pub fn claim_sol_deposit(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_wallet: &Pubkey,
+    user_token_account: &Pubkey,
+    user_lamport_account: &Pubkey,
+) -> Instruction {
+    let data = SinglePoolInstruction::ClaimSolDeposit.try_to_vec().unwrap();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*pool_address, false),
+        AccountMeta::new(find_pool_stake_address(program_id, pool_address), false),
+        AccountMeta::new(find_pool_mint_address(program_id, pool_address), false),
+        AccountMeta::new_readonly(
+            find_pool_stake_authority_address(program_id, pool_address),
+            false,
+        ),
+        AccountMeta::new_readonly(
+            find_pool_mint_authority_address(program_id, pool_address),
+            false,
+        ),
+        AccountMeta::new_readonly(
+            find_pool_sol_deposit_authority_address(program_id, pool_address),
+            false,
+        ),
+        AccountMeta::new(
+            find_pool_sol_deposit_address(program_id, pool_address, user_wallet),
+            false,
+        ),
+        AccountMeta::new_readonly(*user_wallet, true),
+        AccountMeta::new(*user_token_account, false),
+        AccountMeta::new(*user_lamport_account, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(stake::program::id(), false),
+        AccountMeta::new_readonly(
+            find_pool_transient_stake_address(program_id, pool_address),
+            false,
+        ),
+    ];
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates a `WithdrawSol` instruction.
+/// The pool mint authority must first be approved to burn `token_amount` from the user token account.
+pub fn withdraw_sol(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_lamport_account: &Pubkey,
+    user_token_account: &Pubkey,
+    token_amount: u64,
+) -> Instruction {
+    let data = SinglePoolInstruction::WithdrawSol { token_amount }
+        .try_to_vec()
+        .unwrap();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*pool_address, false),
+        AccountMeta::new_readonly(find_pool_stake_address(program_id, pool_address), false),
+        AccountMeta::new(
+            find_pool_transient_stake_address(program_id, pool_address),
+            false,
+        ),
+        AccountMeta::new(find_pool_mint_address(program_id, pool_address), false),
+        AccountMeta::new_readonly(
+            find_pool_stake_authority_address(program_id, pool_address),
+            false,
+        ),
+        AccountMeta::new_readonly(
+            find_pool_mint_authority_address(program_id, pool_address),
+            false,
+        ),
+        AccountMeta::new(*user_lamport_account, false),
+        AccountMeta::new(*user_token_account, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(stake::program::id(), false),
+    ];
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates a `ReplenishPool` instruction.
+pub fn replenish_pool(program_id: &Pubkey, vote_account_address: &Pubkey) -> Instruction {
+    let pool_address = find_pool_address(program_id, vote_account_address);
+    let data = SinglePoolInstruction::ReplenishPool.try_to_vec().unwrap();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*vote_account_address, false),
+        AccountMeta::new_readonly(pool_address, false),
+        AccountMeta::new(find_pool_stake_address(program_id, &pool_address), false),
+        AccountMeta::new(
+            find_pool_transient_stake_address(program_id, &pool_address),
+            false,
+        ),
+        AccountMeta::new_readonly(
+            find_pool_stake_authority_address(program_id, &pool_address),
+            false,
+        ),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+        AccountMeta::new_readonly(stake::config::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(stake::program::id(), false),
+    ];
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates a `RegisterPool` instruction.
+pub fn register_pool(
+    program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    let pool_address = find_pool_address(program_id, vote_account_address);
+    let registry_page_address =
+        find_pool_registry_page_address(program_id, pool_registry_page_index(vote_account_address));
+
+    let data = SinglePoolInstruction::RegisterPool.try_to_vec().unwrap();
+    let accounts = vec![
+        AccountMeta::new_readonly(*vote_account_address, false),
+        AccountMeta::new_readonly(pool_address, false),
+        AccountMeta::new(registry_page_address, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
     ];
 
     Instruction {
@@ -21,10 +21,14 @@ solana_program::declare_id!("3cqnsMsT6LE96pxv7GR4di5rLqHDZZbR3FbeSUeRLFqY");
 
 const POOL_PREFIX: &[u8] = b"pool";
 const POOL_STAKE_PREFIX: &[u8] = b"stake";
+const POOL_TRANSIENT_STAKE_PREFIX: &[u8] = b"transient";
 const POOL_MINT_PREFIX: &[u8] = b"mint";
 const POOL_MINT_AUTHORITY_PREFIX: &[u8] = b"mint_authority";
 const POOL_STAKE_AUTHORITY_PREFIX: &[u8] = b"stake_authority";
 const POOL_MPL_AUTHORITY_PREFIX: &[u8] = b"mpl_authority";
+const POOL_REGISTRY_PAGE_PREFIX: &[u8] = b"registry";
+const POOL_SOL_DEPOSIT_PREFIX: &[u8] = b"sol_deposit";
+const POOL_SOL_DEPOSIT_AUTHORITY_PREFIX: &[u8] = b"sol_deposit_authority";
 
 const MINT_DECIMALS: u8 = 9;
 
@@ -48,6 +52,13 @@ fn find_pool_stake_address_and_bump(program_id: &Pubkey, pool_address: &Pubkey)
     find_address_and_bump(program_id, pool_address, POOL_STAKE_PREFIX)
 }
 
+fn find_pool_transient_stake_address_and_bump(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+) -> (Pubkey, u8) {
+    find_address_and_bump(program_id, pool_address, POOL_TRANSIENT_STAKE_PREFIX)
+}
+
 fn find_pool_mint_address_and_bump(program_id: &Pubkey, pool_address: &Pubkey) -> (Pubkey, u8) {
     find_address_and_bump(program_id, pool_address, POOL_MINT_PREFIX)
 }
@@ -73,6 +84,32 @@ fn find_pool_mpl_authority_address_and_bump(
     find_address_and_bump(program_id, pool_address, POOL_MPL_AUTHORITY_PREFIX)
 }
 
+fn find_pool_sol_deposit_address_and_bump(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_wallet_address: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            POOL_SOL_DEPOSIT_PREFIX,
+            pool_address.as_ref(),
+            user_wallet_address.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+fn find_pool_sol_deposit_authority_address_and_bump(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+) -> (Pubkey, u8) {
+    find_address_and_bump(program_id, pool_address, POOL_SOL_DEPOSIT_AUTHORITY_PREFIX)
+}
+
+fn find_pool_registry_page_address_and_bump(program_id: &Pubkey, page_index: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[POOL_REGISTRY_PAGE_PREFIX, &[page_index]], program_id)
+}
+
 fn find_default_deposit_account_address_and_seed(
     pool_address: &Pubkey,
     user_wallet_address: &Pubkey,
@@ -95,6 +132,13 @@ pub fn find_pool_stake_address(program_id: &Pubkey, pool_address: &Pubkey) -> Pu
     find_pool_stake_address_and_bump(program_id, pool_address).0
 }
 
+/// Find the canonical transient stake account address for a given pool account.
+/// This account holds undelegated lamports moved out of the pool stake account until they are
+/// delegated and merged back into it.
+pub fn find_pool_transient_stake_address(program_id: &Pubkey, pool_address: &Pubkey) -> Pubkey {
+    find_pool_transient_stake_address_and_bump(program_id, pool_address).0
+}
+
 /// Find the canonical token mint address for a given pool account.
 pub fn find_pool_mint_address(program_id: &Pubkey, pool_address: &Pubkey) -> Pubkey {
     find_pool_mint_address_and_bump(program_id, pool_address).0
@@ -115,6 +159,41 @@ pub fn find_pool_mpl_authority_address(program_id: &Pubkey, pool_address: &Pubke
     find_pool_mpl_authority_address_and_bump(program_id, pool_address).0
 }
 
+/// Find the stake account holding a user's SOL deposit into a given pool until it is active and claimed.
+pub fn find_pool_sol_deposit_address(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_wallet_address: &Pubkey,
+) -> Pubkey {
+    find_pool_sol_deposit_address_and_bump(program_id, pool_address, user_wallet_address).0
+}
+
+/// Find the canonical SOL deposit authority address for a given pool account.
+/// This is the staker and withdrawer of SOL deposit stake accounts until they are claimed.
+pub fn find_pool_sol_deposit_authority_address(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+) -> Pubkey {
+    find_pool_sol_deposit_authority_address_and_bump(program_id, pool_address).0
+}
+
+/// Find the registry page address for a given page index.
+pub fn find_pool_registry_page_address(program_id: &Pubkey, page_index: u8) -> Pubkey {
+    find_pool_registry_page_address_and_bump(program_id, page_index).0
+}
+
+/// Find the addresses of all registry pages, which together list every pool.
+pub fn find_all_pool_registry_page_addresses(program_id: &Pubkey) -> Vec<Pubkey> {
+    (0..=u8::MAX)
+        .map(|page_index| find_pool_registry_page_address(program_id, page_index))
+        .collect()
+}
+
+/// Get the index of the registry page a pool is listed in, keyed by its vote account.
+pub fn pool_registry_page_index(vote_account_address: &Pubkey) -> u8 {
+    vote_account_address.as_ref()[0]
+}
+
 /// Find the address of the default intermediate account that holds activating user stake before deposit.
 pub fn find_default_deposit_account_address(
     pool_address: &Pubkey,
@@ -10,11 +10,13 @@ use {
             state::DataV2,
         },
         instruction::SinglePoolInstruction,
-        state::{SinglePool, SinglePoolAccountType},
+        pool_registry_page_index,
+        state::{SinglePool, SinglePoolAccountType, SinglePoolRegistryPage},
         MINT_DECIMALS, POOL_MINT_AUTHORITY_PREFIX, POOL_MINT_PREFIX, POOL_MPL_AUTHORITY_PREFIX,
-        POOL_PREFIX, POOL_STAKE_AUTHORITY_PREFIX, POOL_STAKE_PREFIX,
-        VOTE_STATE_AUTHORIZED_WITHDRAWER_END, VOTE_STATE_AUTHORIZED_WITHDRAWER_START,
-        VOTE_STATE_DISCRIMINATOR_END,
+        POOL_PREFIX, POOL_REGISTRY_PAGE_PREFIX, POOL_SOL_DEPOSIT_AUTHORITY_PREFIX,
+        POOL_SOL_DEPOSIT_PREFIX, POOL_STAKE_AUTHORITY_PREFIX, POOL_STAKE_PREFIX,
+        POOL_TRANSIENT_STAKE_PREFIX, VOTE_STATE_AUTHORIZED_WITHDRAWER_END,
+        VOTE_STATE_AUTHORIZED_WITHDRAWER_START, VOTE_STATE_DISCRIMINATOR_END,
     },
     borsh::BorshDeserialize,
     solana_program::{
@@ -23,10 +25,10 @@ use {
         entrypoint::ProgramResult,
         msg,
         native_token::LAMPORTS_PER_SOL,
-        program::invoke_signed,
+        program::{invoke, invoke_signed},
         program_error::ProgramError,
         program_pack::Pack,
-        pubkey::Pubkey,
+        pubkey::{Pubkey, PUBKEY_BYTES},
         rent::Rent,
         stake::{
             self,
@@ -88,6 +90,38 @@ fn get_stake_amount(stake_account_info: &AccountInfo) -> Result<u64, ProgramErro
     Ok(get_stake_state(stake_account_info)?.1.delegation.stake)
 }
 
+/// Deserialize the transient stake state from AccountInfo, if the account has been created
+fn get_transient_stake_state(
+    transient_stake_account_info: &AccountInfo,
+) -> Result<Option<StakeState>, ProgramError> {
+    if *transient_stake_account_info.owner == stake::program::id()
+        && transient_stake_account_info.data_len() > 0
+    {
+        Ok(Some(try_from_slice_unchecked::<StakeState>(
+            &transient_stake_account_info.data.borrow(),
+        )?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Lamports in the pool transient stake account which belong to the pool, that is, delegated
+/// stake and undelegated deposits, but not the lamports reserved for its rent-exemption
+fn get_transient_stake_value(
+    transient_stake_account_info: &AccountInfo,
+) -> Result<u64, ProgramError> {
+    let rent_exempt_reserve = match get_transient_stake_state(transient_stake_account_info)? {
+        Some(StakeState::Initialized(meta)) | Some(StakeState::Stake(meta, _)) => {
+            meta.rent_exempt_reserve
+        }
+        _ => Rent::get()?.minimum_balance(std::mem::size_of::<StakeState>()),
+    };
+
+    Ok(transient_stake_account_info
+        .lamports()
+        .saturating_sub(rent_exempt_reserve))
+}
+
 /// Determine if stake is active
 fn is_stake_active_without_history(stake: &Stake, current_epoch: Epoch) -> bool {
     stake.delegation.activation_epoch < current_epoch
@@ -126,6 +160,22 @@ fn check_pool_stake_address(
     )
 }
 
+/// Check pool transient stake account address for the pool account
+fn check_pool_transient_stake_address(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+    check_address: &Pubkey,
+) -> Result<u8, ProgramError> {
+    check_pool_pda(
+        program_id,
+        pool_address,
+        check_address,
+        &crate::find_pool_transient_stake_address_and_bump,
+        "transient stake account",
+        SinglePoolError::InvalidPoolTransientStakeAccount,
+    )
+}
+
 /// Check pool mint address for the pool account
 fn check_pool_mint_address(
     program_id: &Pubkey,
@@ -190,6 +240,70 @@ fn check_pool_mpl_authority_address(
     )
 }
 
+/// Check pool SOL deposit authority address for the pool account
+fn check_pool_sol_deposit_authority_address(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+    check_address: &Pubkey,
+) -> Result<u8, ProgramError> {
+    check_pool_pda(
+        program_id,
+        pool_address,
+        check_address,
+        &crate::find_pool_sol_deposit_authority_address_and_bump,
+        "SOL deposit authority",
+        SinglePoolError::InvalidPoolSolDepositAuthority,
+    )
+}
+
+/// Check SOL deposit stake account address for the pool account and depositor
+fn check_pool_sol_deposit_address(
+    program_id: &Pubkey,
+    pool_address: &Pubkey,
+    user_wallet_address: &Pubkey,
+    check_address: &Pubkey,
+) -> Result<u8, ProgramError> {
+    let (derived_address, bump_seed) = crate::find_pool_sol_deposit_address_and_bump(
+        program_id,
+        pool_address,
+        user_wallet_address,
+    );
+    if *check_address != derived_address {
+        msg!(
+            "Incorrect SOL deposit stake account address for pool {} and depositor {}: expected {}, received {}",
+            pool_address,
+            user_wallet_address,
+            derived_address,
+            check_address,
+        );
+        Err(SinglePoolError::InvalidPoolSolDepositAccount.into())
+    } else {
+        Ok(bump_seed)
+    }
+}
+
+/// Check registry page address for the vote account
+fn check_pool_registry_page_address(
+    program_id: &Pubkey,
+    vote_account_address: &Pubkey,
+    check_address: &Pubkey,
+) -> Result<u8, ProgramError> {
+    let page_index = pool_registry_page_index(vote_account_address);
+    let (derived_address, bump_seed) =
+        crate::find_pool_registry_page_address_and_bump(program_id, page_index);
+    if *check_address != derived_address {
+        msg!(
+            "Incorrect registry page address for vote account {}: expected {}, received {}",
+            vote_account_address,
+            derived_address,
+            check_address,
+        );
+        Err(SinglePoolError::InvalidPoolRegistryPage.into())
+    } else {
+        Ok(bump_seed)
+    }
+}
+
 fn check_pool_pda(
     program_id: &Pubkey,
     base_address: &Pubkey,
@@ -432,6 +546,47 @@ impl Processor {
         )
     }
 
+    fn sol_deposit_authorize<'a>(
+        pool_account_key: &Pubkey,
+        sol_deposit_account: AccountInfo<'a>,
+        sol_deposit_authority: AccountInfo<'a>,
+        bump_seed: u8,
+        new_stake_authority: &Pubkey,
+        clock: AccountInfo<'a>,
+    ) -> Result<(), ProgramError> {
+        let authority_seeds = &[
+            POOL_SOL_DEPOSIT_AUTHORITY_PREFIX,
+            pool_account_key.as_ref(),
+            &[bump_seed],
+        ];
+        let signers = &[&authority_seeds[..]];
+
+        for stake_authorize in [
+            stake::state::StakeAuthorize::Staker,
+            stake::state::StakeAuthorize::Withdrawer,
+        ] {
+            let authorize_instruction = stake::instruction::authorize(
+                sol_deposit_account.key,
+                sol_deposit_authority.key,
+                new_stake_authority,
+                stake_authorize,
+                None,
+            );
+
+            invoke_signed(
+                &authorize_instruction,
+                &[
+                    sol_deposit_account.clone(),
+                    clock.clone(),
+                    sol_deposit_authority.clone(),
+                ],
+                signers,
+            )?;
+        }
+
+        Ok(())
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn stake_withdraw<'a>(
         pool_account_key: &Pubkey,
@@ -471,6 +626,45 @@ impl Processor {
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
+    fn transient_stake_withdraw<'a>(
+        pool_account_key: &Pubkey,
+        transient_stake_account: AccountInfo<'a>,
+        bump_seed: u8,
+        destination_account: AccountInfo<'a>,
+        clock: AccountInfo<'a>,
+        stake_history: AccountInfo<'a>,
+        lamports: u64,
+    ) -> Result<(), ProgramError> {
+        let transient_stake_seeds = &[
+            POOL_TRANSIENT_STAKE_PREFIX,
+            pool_account_key.as_ref(),
+            &[bump_seed],
+        ];
+        let signers = &[&transient_stake_seeds[..]];
+
+        // an uninitialized stake account can only be withdrawn from with its own signature
+        let withdraw_instruction = stake::instruction::withdraw(
+            transient_stake_account.key,
+            transient_stake_account.key,
+            destination_account.key,
+            lamports,
+            None,
+        );
+
+        invoke_signed(
+            &withdraw_instruction,
+            &[
+                transient_stake_account.clone(),
+                destination_account,
+                clock,
+                stake_history,
+                transient_stake_account,
+            ],
+            signers,
+        )
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn token_mint_to<'a>(
         pool_account_key: &Pubkey,
@@ -545,6 +739,8 @@ impl Processor {
         let system_program_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
         let stake_program_info = next_account_info(account_info_iter)?;
+        let registry_page_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
 
         check_vote_account(vote_account_info)?;
         let pool_bump_seed = check_pool_address(program_id, vote_account_info.key, pool_info.key)?;
@@ -704,7 +900,14 @@ impl Processor {
             stake_authority_signers,
         )?;
 
-        Ok(())
+        // list the pool in its registry page, so clients can find it without an account scan
+        Self::append_to_registry_page(
+            program_id,
+            vote_account_info,
+            registry_page_info,
+            payer_info,
+            rent,
+        )
     }
 
     fn process_deposit_stake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
@@ -718,14 +921,19 @@ impl Processor {
         let user_token_account_info = next_account_info(account_info_iter)?;
         let user_lamport_account_info = next_account_info(account_info_iter)?;
         let clock_info = next_account_info(account_info_iter)?;
-        let clock = &Clock::from_account_info(clock_info)?;
         let stake_history_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
         let stake_program_info = next_account_info(account_info_iter)?;
+        let pool_transient_stake_info = next_account_info(account_info_iter)?;
 
         SinglePool::from_account_info(pool_info, program_id)?;
 
         check_pool_stake_address(program_id, pool_info.key, pool_stake_info.key)?;
+        check_pool_transient_stake_address(
+            program_id,
+            pool_info.key,
+            pool_transient_stake_info.key,
+        )?;
         let stake_authority_bump_seed = check_pool_stake_authority_address(
             program_id,
             pool_info.key,
@@ -744,6 +952,45 @@ impl Processor {
             return Err(SinglePoolError::InvalidPoolStakeAccountUsage.into());
         }
 
+        Self::deposit_stake_into_pool(
+            pool_info,
+            pool_stake_info,
+            pool_mint_info,
+            pool_stake_authority_info,
+            stake_authority_bump_seed,
+            pool_mint_authority_info,
+            mint_authority_bump_seed,
+            user_stake_info,
+            user_token_account_info,
+            user_lamport_account_info,
+            clock_info,
+            stake_history_info,
+            token_program_info,
+            pool_transient_stake_info,
+        )
+    }
+
+    /// Merge an active or activating stake account, authorized to the pool stake authority, into the
+    /// pool stake account and mint pool tokens for the stake it adds. Shared by `DepositStake` and
+    /// `ClaimSolDeposit`, whose callers are responsible for checking the account addresses.
+    #[allow(clippy::too_many_arguments)]
+    fn deposit_stake_into_pool<'a>(
+        pool_info: &AccountInfo<'a>,
+        pool_stake_info: &AccountInfo<'a>,
+        pool_mint_info: &AccountInfo<'a>,
+        pool_stake_authority_info: &AccountInfo<'a>,
+        stake_authority_bump_seed: u8,
+        pool_mint_authority_info: &AccountInfo<'a>,
+        mint_authority_bump_seed: u8,
+        user_stake_info: &AccountInfo<'a>,
+        user_token_account_info: &AccountInfo<'a>,
+        user_lamport_account_info: &AccountInfo<'a>,
+        clock_info: &AccountInfo<'a>,
+        stake_history_info: &AccountInfo<'a>,
+        token_program_info: &AccountInfo<'a>,
+        pool_transient_stake_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let clock = &Clock::from_account_info(clock_info)?;
         let minimum_delegation = minimum_delegation()?;
 
         let (_, pool_stake_state) = get_stake_state(pool_stake_info)?;
@@ -753,6 +1000,11 @@ impl Processor {
             .saturating_sub(minimum_delegation);
         msg!("Available stake pre merge {}", pre_pool_stake);
 
+        // undelegated and activating lamports in the transient stake account are also owned by the pool
+        let pre_pool_value = pre_pool_stake
+            .checked_add(get_transient_stake_value(pool_transient_stake_info)?)
+            .ok_or(SinglePoolError::ArithmeticOverflow)?;
+
         // user can deposit active stake into an active pool or inactive stake into an activating pool
         let (user_stake_meta, user_stake_state) = get_stake_state(user_stake_info)?;
         if user_stake_meta.authorized
@@ -811,7 +1063,7 @@ impl Processor {
         };
 
         // deposit amount is determined off stake because we return excess rent
-        let new_pool_tokens = calculate_deposit_amount(token_supply, pre_pool_stake, stake_added)
+        let new_pool_tokens = calculate_deposit_amount(token_supply, pre_pool_value, stake_added)
             .ok_or(SinglePoolError::UnexpectedMathError)?;
 
         if new_pool_tokens == 0 {
@@ -863,10 +1115,16 @@ impl Processor {
         let clock_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
         let stake_program_info = next_account_info(account_info_iter)?;
+        let pool_transient_stake_info = next_account_info(account_info_iter)?;
 
         SinglePool::from_account_info(pool_info, program_id)?;
 
         check_pool_stake_address(program_id, pool_info.key, pool_stake_info.key)?;
+        check_pool_transient_stake_address(
+            program_id,
+            pool_info.key,
+            pool_transient_stake_info.key,
+        )?;
         let stake_authority_bump_seed = check_pool_stake_authority_address(
             program_id,
             pool_info.key,
@@ -890,6 +1148,11 @@ impl Processor {
         let pre_pool_stake = get_stake_amount(pool_stake_info)?.saturating_sub(minimum_delegation);
         msg!("Available stake pre split {}", pre_pool_stake);
 
+        // undelegated and activating lamports in the transient stake account are also owned by the pool
+        let pre_pool_value = pre_pool_stake
+            .checked_add(get_transient_stake_value(pool_transient_stake_info)?)
+            .ok_or(SinglePoolError::ArithmeticOverflow)?;
+
         let token_supply = {
             let pool_mint_data = pool_mint_info.try_borrow_data()?;
             let pool_mint = Mint::unpack_from_slice(&pool_mint_data)?;
@@ -897,7 +1160,7 @@ impl Processor {
         };
 
         // withdraw amount is determined off stake just like deposit amount
-        let withdraw_stake = calculate_withdraw_amount(token_supply, pre_pool_stake, token_amount)
+        let withdraw_stake = calculate_withdraw_amount(token_supply, pre_pool_value, token_amount)
             .ok_or(SinglePoolError::UnexpectedMathError)?;
 
         if withdraw_stake == 0 {
@@ -1112,6 +1375,627 @@ impl Processor {
         Ok(())
     }
 
+    fn process_deposit_sol(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        lamports: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let vote_account_info = next_account_info(account_info_iter)?;
+        let pool_info = next_account_info(account_info_iter)?;
+        let sol_deposit_info = next_account_info(account_info_iter)?;
+        let sol_deposit_authority_info = next_account_info(account_info_iter)?;
+        let user_wallet_info = next_account_info(account_info_iter)?;
+        let rent_info = next_account_info(account_info_iter)?;
+        let rent = &Rent::from_account_info(rent_info)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let stake_config_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
+
+        check_vote_account(vote_account_info)?;
+        check_pool_address(program_id, vote_account_info.key, pool_info.key)?;
+
+        let pool = SinglePool::from_account_info(pool_info, program_id)?;
+        if pool.vote_account_address != *vote_account_info.key {
+            return Err(SinglePoolError::InvalidPoolAccount.into());
+        }
+
+        let sol_deposit_bump_seed = check_pool_sol_deposit_address(
+            program_id,
+            pool_info.key,
+            user_wallet_info.key,
+            sol_deposit_info.key,
+        )?;
+        let sol_deposit_authority_bump_seed = check_pool_sol_deposit_authority_address(
+            program_id,
+            pool_info.key,
+            sol_deposit_authority_info.key,
+        )?;
+        check_system_program(system_program_info.key)?;
+        check_stake_program(stake_program_info.key)?;
+
+        if !user_wallet_info.is_signer {
+            msg!("Depositor signature missing");
+            return Err(SinglePoolError::SignatureMissing.into());
+        }
+
+        // a delegated deposit cannot take more lamports, so the previous one must be claimed first
+        if *sol_deposit_info.owner != system_program::id() {
+            return Err(SinglePoolError::SolDepositPending.into());
+        }
+
+        let stake_space = std::mem::size_of::<stake::state::StakeState>();
+        let stake_rent_plus_minimum = rent
+            .minimum_balance(stake_space)
+            .saturating_add(minimum_delegation()?);
+        if sol_deposit_info.lamports().saturating_add(lamports) < stake_rent_plus_minimum {
+            msg!(
+                "Deposit must cover rent-exemption plus the minimum delegation, {} lamports",
+                stake_rent_plus_minimum
+            );
+            return Err(SinglePoolError::DepositTooSmall.into());
+        }
+
+        invoke(
+            &system_instruction::transfer(user_wallet_info.key, sol_deposit_info.key, lamports),
+            &[user_wallet_info.clone(), sol_deposit_info.clone()],
+        )?;
+
+        let sol_deposit_seeds = &[
+            POOL_SOL_DEPOSIT_PREFIX,
+            pool_info.key.as_ref(),
+            user_wallet_info.key.as_ref(),
+            &[sol_deposit_bump_seed],
+        ];
+        let sol_deposit_signers = &[&sol_deposit_seeds[..]];
+
+        invoke_signed(
+            &system_instruction::allocate(sol_deposit_info.key, stake_space as u64),
+            &[sol_deposit_info.clone()],
+            sol_deposit_signers,
+        )?;
+
+        invoke_signed(
+            &system_instruction::assign(sol_deposit_info.key, stake_program_info.key),
+            &[sol_deposit_info.clone()],
+            sol_deposit_signers,
+        )?;
+
+        let sol_deposit_authority_seeds = &[
+            POOL_SOL_DEPOSIT_AUTHORITY_PREFIX,
+            pool_info.key.as_ref(),
+            &[sol_deposit_authority_bump_seed],
+        ];
+        let sol_deposit_authority_signers = &[&sol_deposit_authority_seeds[..]];
+
+        // the pool holds the deposit, so the depositor cannot withdraw it and the pool stake
+        // authority cannot merge it before it is claimed
+        let authorized = stake::state::Authorized::auto(sol_deposit_authority_info.key);
+
+        invoke_signed(
+            &stake::instruction::initialize_checked(sol_deposit_info.key, &authorized),
+            &[
+                sol_deposit_info.clone(),
+                rent_info.clone(),
+                sol_deposit_authority_info.clone(),
+                sol_deposit_authority_info.clone(),
+            ],
+            sol_deposit_authority_signers,
+        )?;
+
+        invoke_signed(
+            &stake::instruction::delegate_stake(
+                sol_deposit_info.key,
+                sol_deposit_authority_info.key,
+                vote_account_info.key,
+            ),
+            &[
+                sol_deposit_info.clone(),
+                vote_account_info.clone(),
+                clock_info.clone(),
+                stake_history_info.clone(),
+                stake_config_info.clone(),
+                sol_deposit_authority_info.clone(),
+            ],
+            sol_deposit_authority_signers,
+        )?;
+
+        Ok(())
+    }
+
+    fn process_claim_sol_deposit(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let pool_stake_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let pool_stake_authority_info = next_account_info(account_info_iter)?;
+        let pool_mint_authority_info = next_account_info(account_info_iter)?;
+        let sol_deposit_authority_info = next_account_info(account_info_iter)?;
+        let sol_deposit_info = next_account_info(account_info_iter)?;
+        let user_wallet_info = next_account_info(account_info_iter)?;
+        let user_token_account_info = next_account_info(account_info_iter)?;
+        let user_lamport_account_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(clock_info)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
+        let pool_transient_stake_info = next_account_info(account_info_iter)?;
+
+        SinglePool::from_account_info(pool_info, program_id)?;
+
+        check_pool_stake_address(program_id, pool_info.key, pool_stake_info.key)?;
+        check_pool_transient_stake_address(
+            program_id,
+            pool_info.key,
+            pool_transient_stake_info.key,
+        )?;
+        let stake_authority_bump_seed = check_pool_stake_authority_address(
+            program_id,
+            pool_info.key,
+            pool_stake_authority_info.key,
+        )?;
+        let mint_authority_bump_seed = check_pool_mint_authority_address(
+            program_id,
+            pool_info.key,
+            pool_mint_authority_info.key,
+        )?;
+        let sol_deposit_authority_bump_seed = check_pool_sol_deposit_authority_address(
+            program_id,
+            pool_info.key,
+            sol_deposit_authority_info.key,
+        )?;
+        check_pool_sol_deposit_address(
+            program_id,
+            pool_info.key,
+            user_wallet_info.key,
+            sol_deposit_info.key,
+        )?;
+        check_pool_mint_address(program_id, pool_info.key, pool_mint_info.key)?;
+        check_token_program(token_program_info.key)?;
+        check_stake_program(stake_program_info.key)?;
+
+        // only the depositor chooses where the pool tokens for their deposit are minted
+        if !user_wallet_info.is_signer {
+            msg!("Depositor signature missing");
+            return Err(SinglePoolError::SignatureMissing.into());
+        }
+
+        // tokens are only minted once the deposit earns rewards like the rest of the pool stake
+        let (_, sol_deposit_stake) = get_stake_state(sol_deposit_info)?;
+        if !is_stake_active_without_history(&sol_deposit_stake, clock.epoch) {
+            return Err(SinglePoolError::SolDepositNotActive.into());
+        }
+
+        Self::sol_deposit_authorize(
+            pool_info.key,
+            sol_deposit_info.clone(),
+            sol_deposit_authority_info.clone(),
+            sol_deposit_authority_bump_seed,
+            pool_stake_authority_info.key,
+            clock_info.clone(),
+        )?;
+
+        Self::deposit_stake_into_pool(
+            pool_info,
+            pool_stake_info,
+            pool_mint_info,
+            pool_stake_authority_info,
+            stake_authority_bump_seed,
+            pool_mint_authority_info,
+            mint_authority_bump_seed,
+            sol_deposit_info,
+            user_token_account_info,
+            user_lamport_account_info,
+            clock_info,
+            stake_history_info,
+            token_program_info,
+            pool_transient_stake_info,
+        )
+    }
+
+    fn process_withdraw_sol(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        token_amount: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let pool_info = next_account_info(account_info_iter)?;
+        let pool_stake_info = next_account_info(account_info_iter)?;
+        let pool_transient_stake_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let pool_stake_authority_info = next_account_info(account_info_iter)?;
+        let pool_mint_authority_info = next_account_info(account_info_iter)?;
+        let user_lamport_account_info = next_account_info(account_info_iter)?;
+        let user_token_account_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
+
+        SinglePool::from_account_info(pool_info, program_id)?;
+
+        check_pool_stake_address(program_id, pool_info.key, pool_stake_info.key)?;
+        let transient_stake_bump_seed = check_pool_transient_stake_address(
+            program_id,
+            pool_info.key,
+            pool_transient_stake_info.key,
+        )?;
+        let stake_authority_bump_seed = check_pool_stake_authority_address(
+            program_id,
+            pool_info.key,
+            pool_stake_authority_info.key,
+        )?;
+        let mint_authority_bump_seed = check_pool_mint_authority_address(
+            program_id,
+            pool_info.key,
+            pool_mint_authority_info.key,
+        )?;
+        check_pool_mint_address(program_id, pool_info.key, pool_mint_info.key)?;
+        check_token_program(token_program_info.key)?;
+        check_stake_program(stake_program_info.key)?;
+
+        let minimum_delegation = minimum_delegation()?;
+
+        let pre_pool_stake = get_stake_amount(pool_stake_info)?.saturating_sub(minimum_delegation);
+        let pre_transient_lamports = pool_transient_stake_info.lamports();
+        let pre_pool_value = pre_pool_stake
+            .checked_add(get_transient_stake_value(pool_transient_stake_info)?)
+            .ok_or(SinglePoolError::ArithmeticOverflow)?;
+        msg!("Pool value pre withdrawal {}", pre_pool_value);
+
+        let token_supply = {
+            let pool_mint_data = pool_mint_info.try_borrow_data()?;
+            let pool_mint = Mint::unpack_from_slice(&pool_mint_data)?;
+            pool_mint.supply
+        };
+
+        let withdraw_lamports =
+            calculate_withdraw_amount(token_supply, pre_pool_value, token_amount)
+                .ok_or(SinglePoolError::UnexpectedMathError)?;
+
+        if withdraw_lamports == 0 {
+            return Err(SinglePoolError::WithdrawalTooSmall.into());
+        }
+
+        // only lamports which are neither delegated nor reserved for rent can be withdrawn
+        let transient_stake_state = get_transient_stake_state(pool_transient_stake_info)?;
+        let available_lamports = match transient_stake_state {
+            Some(StakeState::Uninitialized) => pre_transient_lamports
+                .saturating_sub(Rent::get()?.minimum_balance(pool_transient_stake_info.data_len())),
+            Some(StakeState::Stake(meta, stake)) => pre_transient_lamports
+                .saturating_sub(stake.delegation.stake)
+                .saturating_sub(meta.rent_exempt_reserve),
+            _ => 0,
+        };
+        msg!("Undelegated lamports available {}", available_lamports);
+
+        if withdraw_lamports > available_lamports {
+            return Err(SinglePoolError::InsufficientUndelegatedLamports.into());
+        }
+
+        // burn user tokens corresponding to the amount of sol they wish to withdraw
+        Self::token_burn(
+            pool_info.key,
+            token_program_info.clone(),
+            user_token_account_info.clone(),
+            pool_mint_info.clone(),
+            pool_mint_authority_info.clone(),
+            mint_authority_bump_seed,
+            token_amount,
+        )?;
+
+        if let Some(StakeState::Stake(_, _)) = transient_stake_state {
+            Self::stake_withdraw(
+                pool_info.key,
+                pool_transient_stake_info.clone(),
+                pool_stake_authority_info.clone(),
+                stake_authority_bump_seed,
+                user_lamport_account_info.clone(),
+                clock_info.clone(),
+                stake_history_info.clone(),
+                withdraw_lamports,
+            )?;
+        } else {
+            Self::transient_stake_withdraw(
+                pool_info.key,
+                pool_transient_stake_info.clone(),
+                transient_stake_bump_seed,
+                user_lamport_account_info.clone(),
+                clock_info.clone(),
+                stake_history_info.clone(),
+                withdraw_lamports,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn process_replenish_pool(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let vote_account_info = next_account_info(account_info_iter)?;
+        let pool_info = next_account_info(account_info_iter)?;
+        let pool_stake_info = next_account_info(account_info_iter)?;
+        let pool_transient_stake_info = next_account_info(account_info_iter)?;
+        let pool_stake_authority_info = next_account_info(account_info_iter)?;
+        let rent_info = next_account_info(account_info_iter)?;
+        let rent = &Rent::from_account_info(rent_info)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(clock_info)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let stake_config_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
+
+        check_vote_account(vote_account_info)?;
+        check_pool_address(program_id, vote_account_info.key, pool_info.key)?;
+
+        let pool = SinglePool::from_account_info(pool_info, program_id)?;
+        if pool.vote_account_address != *vote_account_info.key {
+            return Err(SinglePoolError::InvalidPoolAccount.into());
+        }
+
+        check_pool_stake_address(program_id, pool_info.key, pool_stake_info.key)?;
+        let transient_stake_bump_seed = check_pool_transient_stake_address(
+            program_id,
+            pool_info.key,
+            pool_transient_stake_info.key,
+        )?;
+        let stake_authority_bump_seed = check_pool_stake_authority_address(
+            program_id,
+            pool_info.key,
+            pool_stake_authority_info.key,
+        )?;
+        check_system_program(system_program_info.key)?;
+        check_stake_program(stake_program_info.key)?;
+
+        let stake_space = std::mem::size_of::<stake::state::StakeState>();
+        let stake_rent = rent.minimum_balance(stake_space);
+        let minimum_delegation = minimum_delegation()?;
+
+        // merge the transient stake account into the pool stake account once it is active
+        if let Some(StakeState::Stake(_, transient_stake_state)) =
+            get_transient_stake_state(pool_transient_stake_info)?
+        {
+            if !is_stake_active_without_history(&transient_stake_state, clock.epoch) {
+                msg!("Pool transient stake account is still activating");
+                return Ok(());
+            }
+
+            msg!("Merging pool transient stake account");
+            Self::stake_merge(
+                pool_info.key,
+                pool_transient_stake_info.clone(),
+                pool_stake_authority_info.clone(),
+                stake_authority_bump_seed,
+                pool_stake_info.clone(),
+                clock_info.clone(),
+                stake_history_info.clone(),
+            )?;
+        }
+
+        // move undelegated lamports, including rent from a merged transient, out of the pool stake account
+        let (pool_stake_meta, pool_stake_state) = get_stake_state(pool_stake_info)?;
+        let excess_lamports = pool_stake_info
+            .lamports()
+            .checked_sub(pool_stake_state.delegation.stake)
+            .and_then(|amount| amount.checked_sub(pool_stake_meta.rent_exempt_reserve))
+            .ok_or(SinglePoolError::ArithmeticOverflow)?;
+
+        let transient_exists = *pool_transient_stake_info.owner == stake::program::id();
+        let post_transient_lamports = pool_transient_stake_info
+            .lamports()
+            .saturating_add(excess_lamports);
+        if excess_lamports > 0 && (transient_exists || post_transient_lamports >= stake_rent) {
+            msg!(
+                "Moving {} undelegated lamports to the pool transient stake account",
+                excess_lamports
+            );
+            Self::stake_withdraw(
+                pool_info.key,
+                pool_stake_info.clone(),
+                pool_stake_authority_info.clone(),
+                stake_authority_bump_seed,
+                pool_transient_stake_info.clone(),
+                clock_info.clone(),
+                stake_history_info.clone(),
+                excess_lamports,
+            )?;
+        }
+
+        if pool_transient_stake_info.lamports() < stake_rent.saturating_add(minimum_delegation) {
+            msg!("Not enough lamports to delegate the pool transient stake account");
+            return Ok(());
+        }
+
+        let transient_stake_seeds = &[
+            POOL_TRANSIENT_STAKE_PREFIX,
+            pool_info.key.as_ref(),
+            &[transient_stake_bump_seed],
+        ];
+        let transient_stake_signers = &[&transient_stake_seeds[..]];
+
+        let stake_authority_seeds = &[
+            POOL_STAKE_AUTHORITY_PREFIX,
+            pool_info.key.as_ref(),
+            &[stake_authority_bump_seed],
+        ];
+        let stake_authority_signers = &[&stake_authority_seeds[..]];
+
+        // lamports may have been transferred into a transient stake account that does not exist yet
+        if *pool_transient_stake_info.owner == system_program::id() {
+            invoke_signed(
+                &system_instruction::allocate(pool_transient_stake_info.key, stake_space as u64),
+                &[pool_transient_stake_info.clone()],
+                transient_stake_signers,
+            )?;
+
+            invoke_signed(
+                &system_instruction::assign(pool_transient_stake_info.key, stake_program_info.key),
+                &[pool_transient_stake_info.clone()],
+                transient_stake_signers,
+            )?;
+        }
+
+        let authorized = stake::state::Authorized::auto(pool_stake_authority_info.key);
+
+        invoke_signed(
+            &stake::instruction::initialize_checked(pool_transient_stake_info.key, &authorized),
+            &[
+                pool_transient_stake_info.clone(),
+                rent_info.clone(),
+                pool_stake_authority_info.clone(),
+                pool_stake_authority_info.clone(),
+            ],
+            stake_authority_signers,
+        )?;
+
+        // delegate the transient stake so it can be merged once active
+        msg!("Delegating pool transient stake account");
+        invoke_signed(
+            &stake::instruction::delegate_stake(
+                pool_transient_stake_info.key,
+                pool_stake_authority_info.key,
+                vote_account_info.key,
+            ),
+            &[
+                pool_transient_stake_info.clone(),
+                vote_account_info.clone(),
+                clock_info.clone(),
+                stake_history_info.clone(),
+                stake_config_info.clone(),
+                pool_stake_authority_info.clone(),
+            ],
+            stake_authority_signers,
+        )?;
+
+        Ok(())
+    }
+
+    fn process_register_pool(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let vote_account_info = next_account_info(account_info_iter)?;
+        let pool_info = next_account_info(account_info_iter)?;
+        let registry_page_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let rent = Rent::get()?;
+
+        check_pool_address(program_id, vote_account_info.key, pool_info.key)?;
+
+        let pool = SinglePool::from_account_info(pool_info, program_id)?;
+        if pool.vote_account_address != *vote_account_info.key {
+            return Err(SinglePoolError::InvalidPoolAccount.into());
+        }
+
+        check_system_program(system_program_info.key)?;
+
+        Self::append_to_registry_page(
+            program_id,
+            vote_account_info,
+            registry_page_info,
+            payer_info,
+            &rent,
+        )
+    }
+
+    /// List a pool's vote account in its registry page, creating the page for its first pool. Shared
+    /// by `InitializePool` and by `RegisterPool`, which backfills pools created before the registry.
+    fn append_to_registry_page<'a>(
+        program_id: &Pubkey,
+        vote_account_info: &AccountInfo<'a>,
+        registry_page_info: &AccountInfo<'a>,
+        payer_info: &AccountInfo<'a>,
+        rent: &Rent,
+    ) -> ProgramResult {
+        let registry_page_bump_seed = check_pool_registry_page_address(
+            program_id,
+            vote_account_info.key,
+            registry_page_info.key,
+        )?;
+
+        if !payer_info.is_signer {
+            msg!("Payer did not sign pool registration");
+            return Err(SinglePoolError::SignatureMissing.into());
+        }
+
+        // the new address is appended at the end of the page, which is created for its first pool
+        let page_index = pool_registry_page_index(vote_account_info.key);
+        let registry_page_header_len = get_packed_len::<SinglePoolRegistryPage>();
+        let registry_page_is_new = registry_page_info.data_len() == 0;
+        let entry_offset = if registry_page_is_new {
+            registry_page_header_len
+        } else {
+            check_account_owner(registry_page_info, program_id)?;
+            let registry_page_data = registry_page_info.data.borrow();
+            SinglePoolRegistryPage::unpack_header(&registry_page_data)?;
+
+            // compare in place, so registration never deserializes the whole page
+            if registry_page_data[registry_page_header_len..]
+                .chunks_exact(PUBKEY_BYTES)
+                .any(|entry| entry == vote_account_info.key.as_ref())
+            {
+                return Err(SinglePoolError::PoolAlreadyRegistered.into());
+            }
+
+            registry_page_info.data_len()
+        };
+        let registry_page_space = entry_offset.saturating_add(PUBKEY_BYTES);
+
+        let required_lamports = rent
+            .minimum_balance(registry_page_space)
+            .saturating_sub(registry_page_info.lamports());
+        if required_lamports > 0 {
+            invoke(
+                &system_instruction::transfer(
+                    payer_info.key,
+                    registry_page_info.key,
+                    required_lamports,
+                ),
+                &[payer_info.clone(), registry_page_info.clone()],
+            )?;
+        }
+
+        if registry_page_is_new {
+            let registry_page_seeds = &[
+                POOL_REGISTRY_PAGE_PREFIX,
+                &[page_index],
+                &[registry_page_bump_seed],
+            ];
+            let registry_page_signers = &[&registry_page_seeds[..]];
+
+            invoke_signed(
+                &system_instruction::allocate(registry_page_info.key, registry_page_space as u64),
+                &[registry_page_info.clone()],
+                registry_page_signers,
+            )?;
+
+            invoke_signed(
+                &system_instruction::assign(registry_page_info.key, program_id),
+                &[registry_page_info.clone()],
+                registry_page_signers,
+            )?;
+
+            let registry_page = SinglePoolRegistryPage {
+                account_type: SinglePoolAccountType::RegistryPage,
+                page_index,
+            };
+            borsh::to_writer(
+                &mut registry_page_info.data.borrow_mut()[..registry_page_header_len],
+                &registry_page,
+            )?;
+        } else {
+            registry_page_info.realloc(registry_page_space, false)?;
+        }
+
+        registry_page_info.data.borrow_mut()[entry_offset..registry_page_space]
+            .copy_from_slice(vote_account_info.key.as_ref());
+
+        Ok(())
+    }
+
     /// Processes [Instruction](enum.Instruction.html).
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
         let instruction = SinglePoolInstruction::try_from_slice(input)?;
@@ -1144,6 +2028,26 @@ impl Processor {
                 msg!("Instruction: UpdateTokenMetadata");
                 Self::process_update_pool_token_metadata(program_id, accounts, name, symbol, uri)
             }
+            SinglePoolInstruction::DepositSol { lamports } => {
+                msg!("Instruction: DepositSol");
+                Self::process_deposit_sol(program_id, accounts, lamports)
+            }
+            SinglePoolInstruction::WithdrawSol { token_amount } => {
+                msg!("Instruction: WithdrawSol");
+                Self::process_withdraw_sol(program_id, accounts, token_amount)
+            }
+            SinglePoolInstruction::ReplenishPool => {
+                msg!("Instruction: ReplenishPool");
+                Self::process_replenish_pool(program_id, accounts)
+            }
+            SinglePoolInstruction::RegisterPool => {
+                msg!("Instruction: RegisterPool");
+                Self::process_register_pool(program_id, accounts)
+            }
+            SinglePoolInstruction::ClaimSolDeposit => {
+                msg!("Instruction: ClaimSolDeposit");
+                Self::process_claim_sol_deposit(program_id, accounts)
+            }
         }
     }
 }
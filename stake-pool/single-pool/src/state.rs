@@ -4,8 +4,10 @@ use {
     crate::{error::SinglePoolError, find_pool_address},
     borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
     solana_program::{
-        account_info::AccountInfo, borsh::try_from_slice_unchecked, program_error::ProgramError,
-        pubkey::Pubkey,
+        account_info::AccountInfo,
+        borsh::{get_packed_len, try_from_slice_unchecked},
+        program_error::ProgramError,
+        pubkey::{Pubkey, PUBKEY_BYTES},
     },
 };
 
@@ -17,6 +19,8 @@ pub enum SinglePoolAccountType {
     Uninitialized,
     /// Main pool account
     Pool,
+    /// Page of the pool registry
+    RegistryPage,
 }
 
 /// Single-Validator Stake Pool account, used to derive all PDAs
@@ -54,3 +58,43 @@ impl SinglePool {
         Ok(pool)
     }
 }
+
+/// Header of a pool registry page. Pools are assigned to pages by their vote account, and the
+/// header is followed by the vote account addresses of every pool in the page, in order of creation.
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct SinglePoolRegistryPage {
+    /// Registry page account type
+    pub account_type: SinglePoolAccountType,
+    /// Index of this page, see `pool_registry_page_index`
+    pub page_index: u8,
+}
+impl SinglePoolRegistryPage {
+    /// Unpack and validate a registry page header, without reading the addresses that follow it
+    pub fn unpack_header(data: &[u8]) -> Result<Self, ProgramError> {
+        let header_len = get_packed_len::<SinglePoolRegistryPage>();
+        if data.len() < header_len || (data.len() - header_len) % PUBKEY_BYTES != 0 {
+            return Err(SinglePoolError::InvalidPoolRegistryPage.into());
+        }
+
+        let header = try_from_slice_unchecked::<SinglePoolRegistryPage>(&data[..header_len])?;
+        if header.account_type != SinglePoolAccountType::RegistryPage {
+            return Err(SinglePoolError::InvalidPoolRegistryPage.into());
+        }
+
+        Ok(header)
+    }
+
+    /// Unpack a registry page header and the vote account addresses listed in it
+    pub fn unpack(data: &[u8]) -> Result<(Self, Vec<Pubkey>), ProgramError> {
+        let header = Self::unpack_header(data)?;
+        let header_len = get_packed_len::<SinglePoolRegistryPage>();
+
+        let vote_account_addresses = data[header_len..]
+            .chunks_exact(PUBKEY_BYTES)
+            .map(Pubkey::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| SinglePoolError::InvalidPoolRegistryPage)?;
+
+        Ok((header, vote_account_addresses))
+    }
+}
@@ -7,8 +7,8 @@ use {
     helpers::*,
     solana_program_test::*,
     solana_sdk::{
-        instruction::Instruction, program_error::ProgramError, pubkey::Pubkey, signature::Signer,
-        stake, system_program, transaction::Transaction,
+        account::AccountSharedData, instruction::Instruction, program_error::ProgramError,
+        pubkey::Pubkey, signature::Signer, stake, system_program, transaction::Transaction,
     },
     spl_single_validator_pool::{
         error::SinglePoolError,
@@ -21,11 +21,12 @@ use {
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum TestMode {
     Initialize,
+    Register,
     Deposit,
     Withdraw,
 }
 
-// build a full transaction for initialize, deposit, and withdraw
+// build a full transaction for initialize, register, deposit, and withdraw
 // this is used to test knocking out individual accounts, for the sake of confirming the pubkeys are checked
 async fn build_instructions(
     context: &mut ProgramTestContext,
@@ -71,6 +72,17 @@ async fn build_instructions(
             &rent,
             minimum_delegation,
         )
+    } else if test_mode == TestMode::Register {
+        accounts.initialize(context).await;
+
+        // drop the registry page, as for a pool created before the registry existed
+        context.set_account(&accounts.registry_page, &AccountSharedData::default());
+
+        vec![instruction::register_pool(
+            &id(),
+            &accounts.vote_account.pubkey(),
+            &accounts.alice.pubkey(),
+        )]
     } else {
         accounts
             .initialize_for_deposit(context, TEST_STAKE_AMOUNT, None)
@@ -127,6 +139,7 @@ async fn build_instructions(
 
     let (instructions, i) = match test_mode {
         TestMode::Initialize => (initialize_instructions, 3),
+        TestMode::Register => (initialize_instructions, 0),
         TestMode::Deposit => (deposit_instructions, 2),
         TestMode::Withdraw => (withdraw_instructions, 1),
     };
@@ -139,6 +152,7 @@ async fn build_instructions(
 
 // test that account addresses are checked properly
 #[test_case(TestMode::Initialize; "initialize")]
+#[test_case(TestMode::Register; "register")]
 #[test_case(TestMode::Deposit; "deposit")]
 #[test_case(TestMode::Withdraw; "withdraw")]
 #[tokio::test]
@@ -178,6 +192,8 @@ async fn fail_account_checks(test_mode: TestMode) {
             check_error(e, SinglePoolError::InvalidPoolAccount)
         } else if prev_pubkey == accounts.stake_account {
             check_error(e, SinglePoolError::InvalidPoolStakeAccount)
+        } else if prev_pubkey == accounts.transient_stake_account {
+            check_error(e, SinglePoolError::InvalidPoolTransientStakeAccount)
         } else if prev_pubkey == accounts.stake_authority {
             check_error(e, SinglePoolError::InvalidPoolStakeAuthority)
         } else if prev_pubkey == accounts.mint_authority {
@@ -186,6 +202,8 @@ async fn fail_account_checks(test_mode: TestMode) {
             check_error(e, SinglePoolError::InvalidPoolMplAuthority)
         } else if prev_pubkey == accounts.mint {
             check_error(e, SinglePoolError::InvalidPoolMint)
+        } else if prev_pubkey == accounts.registry_page {
+            check_error(e, SinglePoolError::InvalidPoolRegistryPage)
         } else if [system_program::id(), spl_token::id(), stake::program::id()]
             .contains(&prev_pubkey)
         {
@@ -202,7 +220,7 @@ fn make_basic_instruction(
 ) -> Instruction {
     match instruction_type {
         SinglePoolInstruction::InitializePool => {
-            instruction::initialize_pool(&id(), &accounts.vote_account.pubkey())
+            instruction::initialize_pool(&id(), &accounts.vote_account.pubkey(), &Pubkey::default())
         }
         SinglePoolInstruction::DepositStake => instruction::deposit_stake(
             &id(),
@@ -230,6 +248,32 @@ fn make_basic_instruction(
             "".to_string(),
             "".to_string(),
         ),
+        SinglePoolInstruction::DepositSol { .. } => instruction::deposit_sol(
+            &id(),
+            &accounts.vote_account.pubkey(),
+            &Pubkey::default(),
+            0,
+        ),
+        SinglePoolInstruction::WithdrawSol { .. } => instruction::withdraw_sol(
+            &id(),
+            &accounts.pool,
+            &Pubkey::default(),
+            &Pubkey::default(),
+            0,
+        ),
+        SinglePoolInstruction::ReplenishPool => {
+            instruction::replenish_pool(&id(), &accounts.vote_account.pubkey())
+        }
+        SinglePoolInstruction::RegisterPool => {
+            instruction::register_pool(&id(), &accounts.vote_account.pubkey(), &Pubkey::default())
+        }
+        SinglePoolInstruction::ClaimSolDeposit => instruction::claim_sol_deposit(
+            &id(),
+            &accounts.pool,
+            &Pubkey::default(),
+            &Pubkey::default(),
+            &Pubkey::default(),
+        ),
     }
 }
 
@@ -242,6 +286,7 @@ where
 }
 
 // check that major accounts always show up in the same order, to spare developer confusion
+// the transient stake account is left out, since it was appended to existing instructions
 #[test]
 fn consistent_account_order() {
     let accounts = SinglePoolAccounts::default();
@@ -275,6 +320,14 @@ fn consistent_account_order() {
                 uri: "".to_string(),
             },
         ),
+        make_basic_instruction(&accounts, SinglePoolInstruction::DepositSol { lamports: 0 }),
+        make_basic_instruction(
+            &accounts,
+            SinglePoolInstruction::WithdrawSol { token_amount: 0 },
+        ),
+        make_basic_instruction(&accounts, SinglePoolInstruction::ReplenishPool),
+        make_basic_instruction(&accounts, SinglePoolInstruction::RegisterPool),
+        make_basic_instruction(&accounts, SinglePoolInstruction::ClaimSolDeposit),
     ];
 
     for instruction in instructions {
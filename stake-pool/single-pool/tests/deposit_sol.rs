@@ -0,0 +1,341 @@
+#![allow(clippy::integer_arithmetic)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    },
+    spl_single_validator_pool::{
+        error::SinglePoolError, find_pool_sol_deposit_address, id, instruction,
+    },
+};
+
+async fn deposit_sol(
+    context: &mut ProgramTestContext,
+    accounts: &SinglePoolAccounts,
+    user: &Keypair,
+    lamports: u64,
+) -> Result<(), BanksClientError> {
+    let instruction = instruction::deposit_sol(
+        &id(),
+        &accounts.vote_account.pubkey(),
+        &user.pubkey(),
+        lamports,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, user],
+        context.last_blockhash,
+    );
+
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn claim_sol_deposit(
+    context: &mut ProgramTestContext,
+    accounts: &SinglePoolAccounts,
+    user: &Keypair,
+    user_token: &Pubkey,
+) -> Result<(), BanksClientError> {
+    let instruction = instruction::claim_sol_deposit(
+        &id(),
+        &accounts.pool,
+        &user.pubkey(),
+        user_token,
+        &user.pubkey(),
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, user],
+        context.last_blockhash,
+    );
+
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn withdraw_sol(
+    context: &mut ProgramTestContext,
+    accounts: &SinglePoolAccounts,
+    user: &Keypair,
+    user_token: &Pubkey,
+    token_amount: u64,
+) -> Result<(), BanksClientError> {
+    let instructions = vec![
+        spl_token::instruction::approve(
+            &spl_token::id(),
+            user_token,
+            &accounts.mint_authority,
+            &user.pubkey(),
+            &[],
+            token_amount,
+        )
+        .unwrap(),
+        instruction::withdraw_sol(
+            &id(),
+            &accounts.pool,
+            &user.pubkey(),
+            user_token,
+            token_amount,
+        ),
+    ];
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer, user],
+        context.last_blockhash,
+    );
+
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn replenish(context: &mut ProgramTestContext, accounts: &SinglePoolAccounts) {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::replenish_pool(
+            &id(),
+            &accounts.vote_account.pubkey(),
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn success() {
+    let mut context = program_test().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    accounts.initialize(&mut context).await;
+    advance_epoch(&mut context).await;
+
+    let stake_rent = get_stake_account_rent(&mut context.banks_client).await;
+    let sol_deposit =
+        find_pool_sol_deposit_address(&id(), &accounts.pool, &accounts.alice.pubkey());
+    let (_, pool_stake_before, _) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    let pool_stake_before = pool_stake_before.unwrap().delegation.stake;
+
+    deposit_sol(&mut context, &accounts, &accounts.alice, TEST_STAKE_AMOUNT)
+        .await
+        .unwrap();
+
+    // lamports are delegated in a stake account held by the pool, and no tokens are minted yet
+    let (meta, stake, lamports) = get_stake_account(&mut context.banks_client, &sol_deposit).await;
+    let stake = stake.unwrap();
+    assert_eq!(lamports, TEST_STAKE_AMOUNT);
+    assert_eq!(meta.authorized.staker, accounts.sol_deposit_authority);
+    assert_eq!(meta.authorized.withdrawer, accounts.sol_deposit_authority);
+    assert_eq!(
+        stake.delegation.voter_pubkey,
+        accounts.vote_account.pubkey()
+    );
+    assert_eq!(stake.delegation.stake, TEST_STAKE_AMOUNT - stake_rent);
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await,
+        0
+    );
+
+    // the deposit cannot be claimed while activating, nor topped up before it is claimed
+    let e = claim_sol_deposit(
+        &mut context,
+        &accounts,
+        &accounts.alice,
+        &accounts.alice_token,
+    )
+    .await
+    .unwrap_err();
+    check_error(e, SinglePoolError::SolDepositNotActive);
+
+    refresh_blockhash(&mut context).await;
+    let e = deposit_sol(&mut context, &accounts, &accounts.alice, TEST_STAKE_AMOUNT)
+        .await
+        .unwrap_err();
+    check_error(e, SinglePoolError::SolDepositPending);
+
+    advance_epoch(&mut context).await;
+    refresh_blockhash(&mut context).await;
+    let wallet_lamports_before = get_account(&mut context.banks_client, &accounts.alice.pubkey())
+        .await
+        .lamports;
+
+    claim_sol_deposit(
+        &mut context,
+        &accounts,
+        &accounts.alice,
+        &accounts.alice_token,
+    )
+    .await
+    .unwrap();
+
+    // first deposit is minted one to one for the stake it adds, and the rent is returned
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await,
+        TEST_STAKE_AMOUNT - stake_rent
+    );
+    let (_, pool_stake_after, _) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    assert_eq!(
+        pool_stake_after.unwrap().delegation.stake,
+        pool_stake_before + TEST_STAKE_AMOUNT - stake_rent
+    );
+    let wallet_lamports_after = get_account(&mut context.banks_client, &accounts.alice.pubkey())
+        .await
+        .lamports;
+    assert_eq!(wallet_lamports_after, wallet_lamports_before + stake_rent);
+    assert!(context
+        .banks_client
+        .get_account(sol_deposit)
+        .await
+        .unwrap()
+        .is_none());
+
+    // a new deposit can be made once the previous one is claimed
+    refresh_blockhash(&mut context).await;
+    deposit_sol(&mut context, &accounts, &accounts.alice, TEST_STAKE_AMOUNT)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn fail_claim_without_depositor_signature() {
+    let mut context = program_test().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    accounts.initialize(&mut context).await;
+    advance_epoch(&mut context).await;
+
+    deposit_sol(&mut context, &accounts, &accounts.alice, TEST_STAKE_AMOUNT)
+        .await
+        .unwrap();
+    advance_epoch(&mut context).await;
+
+    // bob cannot mint alice's deposit to his own token account
+    let mut instruction = instruction::claim_sol_deposit(
+        &id(),
+        &accounts.pool,
+        &accounts.alice.pubkey(),
+        &accounts.bob_token,
+        &accounts.bob.pubkey(),
+    );
+    instruction.accounts[7].is_signer = false;
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    let e = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err();
+    check_error(e, SinglePoolError::SignatureMissing);
+}
+
+#[tokio::test]
+async fn fail_deposit_below_minimum() {
+    let mut context = program_test().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    accounts.initialize(&mut context).await;
+
+    let stake_rent = get_stake_account_rent(&mut context.banks_client).await;
+    let e = deposit_sol(&mut context, &accounts, &accounts.alice, stake_rent)
+        .await
+        .unwrap_err();
+    check_error(e, SinglePoolError::DepositTooSmall);
+}
+
+#[tokio::test]
+async fn success_withdraw_sol() {
+    let mut context = program_test().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    let minimum_delegation = accounts
+        .initialize_for_withdraw(&mut context, TEST_STAKE_AMOUNT, None, true)
+        .await;
+    let stake_rent = get_stake_account_rent(&mut context.banks_client).await;
+
+    // lamports sent to the pool are delegated through the transient stake account
+    transfer(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &accounts.transient_stake_account,
+        TEST_STAKE_AMOUNT,
+    )
+    .await;
+    replenish(&mut context, &accounts).await;
+
+    // only lamports which arrive after delegation are undelegated
+    let undelegated_lamports = TEST_STAKE_AMOUNT / 10;
+    transfer(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &accounts.transient_stake_account,
+        undelegated_lamports,
+    )
+    .await;
+
+    let alice_tokens = get_token_balance(&mut context.banks_client, &accounts.alice_token).await;
+    let e = withdraw_sol(
+        &mut context,
+        &accounts,
+        &accounts.alice,
+        &accounts.alice_token,
+        alice_tokens,
+    )
+    .await
+    .unwrap_err();
+    check_error(e, SinglePoolError::InsufficientUndelegatedLamports);
+
+    let (_, pool_stake, _) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    let transient_lamports =
+        get_account(&mut context.banks_client, &accounts.transient_stake_account)
+            .await
+            .lamports;
+    let pool_value =
+        pool_stake.unwrap().delegation.stake - minimum_delegation + transient_lamports - stake_rent;
+    let token_amount = alice_tokens / 100;
+    let withdraw_lamports =
+        (token_amount as u128 * pool_value as u128 / alice_tokens as u128) as u64;
+    assert!(withdraw_lamports <= undelegated_lamports);
+
+    let wallet_lamports_before = get_account(&mut context.banks_client, &accounts.alice.pubkey())
+        .await
+        .lamports;
+
+    refresh_blockhash(&mut context).await;
+    withdraw_sol(
+        &mut context,
+        &accounts,
+        &accounts.alice,
+        &accounts.alice_token,
+        token_amount,
+    )
+    .await
+    .unwrap();
+
+    let wallet_lamports_after = get_account(&mut context.banks_client, &accounts.alice.pubkey())
+        .await
+        .lamports;
+    assert_eq!(
+        wallet_lamports_after,
+        wallet_lamports_before + withdraw_lamports
+    );
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &accounts.alice_token).await,
+        alice_tokens - token_amount
+    );
+}
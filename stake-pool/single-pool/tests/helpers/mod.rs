@@ -19,9 +19,10 @@ use {
     spl_associated_token_account as atoken,
     spl_single_validator_pool::{
         find_pool_address, find_pool_mint_address, find_pool_mint_authority_address,
-        find_pool_mpl_authority_address, find_pool_stake_address,
-        find_pool_stake_authority_address, id, inline_mpl_token_metadata, instruction,
-        processor::Processor,
+        find_pool_mpl_authority_address, find_pool_registry_page_address,
+        find_pool_sol_deposit_authority_address, find_pool_stake_address,
+        find_pool_stake_authority_address, find_pool_transient_stake_address, id,
+        inline_mpl_token_metadata, instruction, pool_registry_page_index, processor::Processor,
     },
 };
 
@@ -56,10 +57,13 @@ pub struct SinglePoolAccounts {
     pub vote_account: Keypair,
     pub pool: Pubkey,
     pub stake_account: Pubkey,
+    pub transient_stake_account: Pubkey,
+    pub registry_page: Pubkey,
     pub mint: Pubkey,
     pub stake_authority: Pubkey,
     pub mint_authority: Pubkey,
     pub mpl_authority: Pubkey,
+    pub sol_deposit_authority: Pubkey,
     pub alice: Keypair,
     pub bob: Keypair,
     pub alice_stake: Keypair,
@@ -307,11 +311,17 @@ impl Default for SinglePoolAccounts {
             voter: Keypair::new(),
             withdrawer: Keypair::new(),
             stake_account: find_pool_stake_address(&id(), &pool),
+            transient_stake_account: find_pool_transient_stake_address(&id(), &pool),
+            registry_page: find_pool_registry_page_address(
+                &id(),
+                pool_registry_page_index(&vote_account.pubkey()),
+            ),
             pool,
             mint,
             stake_authority: find_pool_stake_authority_address(&id(), &pool),
             mint_authority: find_pool_mint_authority_address(&id(), &pool),
             mpl_authority: find_pool_mpl_authority_address(&id(), &pool),
+            sol_deposit_authority: find_pool_sol_deposit_authority_address(&id(), &pool),
             vote_account,
             alice_stake: Keypair::new(),
             bob_stake: Keypair::new(),
@@ -6,8 +6,17 @@ mod helpers;
 use {
     helpers::*,
     solana_program_test::*,
-    solana_sdk::{program_pack::Pack, signature::Signer, stake, transaction::Transaction},
-    spl_single_validator_pool::{error::SinglePoolError, id, instruction},
+    solana_sdk::{
+        account::AccountSharedData,
+        program_pack::Pack,
+        signature::{Keypair, Signer},
+        stake,
+        transaction::Transaction,
+    },
+    spl_single_validator_pool::{
+        error::SinglePoolError, id, instruction, pool_registry_page_index,
+        state::SinglePoolRegistryPage,
+    },
     spl_token::state::Mint,
 };
 
@@ -24,6 +33,76 @@ async fn success() {
     // stake account exists
     let stake_account = get_account(&mut context.banks_client, &accounts.stake_account).await;
     assert_eq!(stake_account.owner, stake::program::id());
+
+    // pool is listed in its registry page
+    let registry_page = get_account(&mut context.banks_client, &accounts.registry_page).await;
+    assert_eq!(registry_page.owner, id());
+    let (header, vote_account_addresses) =
+        SinglePoolRegistryPage::unpack(&registry_page.data).unwrap();
+    assert_eq!(
+        header.page_index,
+        pool_registry_page_index(&accounts.vote_account.pubkey())
+    );
+    assert_eq!(vote_account_addresses, vec![accounts.vote_account.pubkey()]);
+}
+
+#[tokio::test]
+async fn success_shared_registry_page() {
+    let mut context = program_test().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    let minimum_delegation = accounts.initialize(&mut context).await;
+
+    // grind a second vote account that lands in the same registry page
+    let page_index = pool_registry_page_index(&accounts.vote_account.pubkey());
+    let vote_account = loop {
+        let keypair = Keypair::new();
+        if pool_registry_page_index(&keypair.pubkey()) == page_index {
+            break keypair;
+        }
+    };
+
+    create_vote(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &Keypair::new(),
+        &Keypair::new().pubkey(),
+        &Keypair::new().pubkey(),
+        &vote_account,
+    )
+    .await;
+
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let instructions = instruction::initialize(
+        &id(),
+        &vote_account.pubkey(),
+        &context.payer.pubkey(),
+        &rent,
+        minimum_delegation,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // both pools are listed, in order of creation, and the page stays rent-exempt
+    let registry_page = get_account(&mut context.banks_client, &accounts.registry_page).await;
+    let (_, vote_account_addresses) = SinglePoolRegistryPage::unpack(&registry_page.data).unwrap();
+    assert_eq!(
+        vote_account_addresses,
+        vec![accounts.vote_account.pubkey(), vote_account.pubkey()]
+    );
+    assert_eq!(
+        registry_page.lamports,
+        rent.minimum_balance(registry_page.data.len())
+    );
 }
 
 #[tokio::test]
@@ -56,4 +135,63 @@ async fn fail_double_init() {
     check_error(e, SinglePoolError::PoolAlreadyInitialized);
 }
 
+#[tokio::test]
+async fn success_register_existing_pool() {
+    let mut context = program_test().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    accounts.initialize(&mut context).await;
+
+    // drop the registry page, as for a pool created before the registry existed
+    context.set_account(&accounts.registry_page, &AccountSharedData::default());
+
+    let instruction = instruction::register_pool(
+        &id(),
+        &accounts.vote_account.pubkey(),
+        &context.payer.pubkey(),
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let registry_page = get_account(&mut context.banks_client, &accounts.registry_page).await;
+    assert_eq!(registry_page.owner, id());
+    let (_, vote_account_addresses) = SinglePoolRegistryPage::unpack(&registry_page.data).unwrap();
+    assert_eq!(vote_account_addresses, vec![accounts.vote_account.pubkey()]);
+}
+
+#[tokio::test]
+async fn fail_double_register() {
+    let mut context = program_test().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    accounts.initialize(&mut context).await;
+    refresh_blockhash(&mut context).await;
+
+    let instruction = instruction::register_pool(
+        &id(),
+        &accounts.vote_account.pubkey(),
+        &context.payer.pubkey(),
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+
+    let e = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err();
+    check_error(e, SinglePoolError::PoolAlreadyRegistered);
+}
+
 // TODO test that init can succeed without mpl program
@@ -0,0 +1,128 @@
+#![allow(clippy::integer_arithmetic)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use {
+    bincode::deserialize,
+    helpers::*,
+    solana_program_test::*,
+    solana_sdk::{
+        signature::Signer, stake::state::StakeState, system_program, transaction::Transaction,
+    },
+    spl_single_validator_pool::{id, instruction},
+};
+
+async fn send_lamports(
+    context: &mut ProgramTestContext,
+    accounts: &SinglePoolAccounts,
+    lamports: u64,
+) {
+    transfer(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &accounts.transient_stake_account,
+        lamports,
+    )
+    .await;
+}
+
+async fn replenish(context: &mut ProgramTestContext, accounts: &SinglePoolAccounts) {
+    let instruction = instruction::replenish_pool(&id(), &accounts.vote_account.pubkey());
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+async fn get_transient_stake_state(
+    context: &mut ProgramTestContext,
+    accounts: &SinglePoolAccounts,
+) -> (StakeState, u64) {
+    let transient = get_account(&mut context.banks_client, &accounts.transient_stake_account).await;
+    (deserialize(&transient.data).unwrap(), transient.lamports)
+}
+
+#[tokio::test]
+async fn success() {
+    let mut context = program_test().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    accounts.initialize(&mut context).await;
+    advance_epoch(&mut context).await;
+
+    let stake_rent = get_stake_account_rent(&mut context.banks_client).await;
+    let (_, pool_stake_before, _) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    let pool_stake_before = pool_stake_before.unwrap().delegation.stake;
+
+    send_lamports(&mut context, &accounts, TEST_STAKE_AMOUNT).await;
+
+    // lamports sent to the pool are delegated to the pool validator
+    replenish(&mut context, &accounts).await;
+    let (transient_state, transient_lamports) =
+        get_transient_stake_state(&mut context, &accounts).await;
+    assert_eq!(transient_lamports, TEST_STAKE_AMOUNT);
+    match transient_state {
+        StakeState::Stake(meta, stake) => {
+            assert_eq!(meta.authorized.staker, accounts.stake_authority);
+            assert_eq!(meta.authorized.withdrawer, accounts.stake_authority);
+            assert_eq!(
+                stake.delegation.voter_pubkey,
+                accounts.vote_account.pubkey()
+            );
+            assert_eq!(stake.delegation.stake, TEST_STAKE_AMOUNT - stake_rent);
+        }
+        _ => panic!("transient stake account was not delegated"),
+    }
+
+    // replenishing again before activation does nothing
+    refresh_blockhash(&mut context).await;
+    replenish(&mut context, &accounts).await;
+    let (_, pool_stake, _) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    assert_eq!(pool_stake.unwrap().delegation.stake, pool_stake_before);
+
+    // once active, the transient stake is merged and its rent is kept for the next delegation
+    advance_epoch(&mut context).await;
+    replenish(&mut context, &accounts).await;
+
+    let (pool_meta, pool_stake, pool_lamports) =
+        get_stake_account(&mut context.banks_client, &accounts.stake_account).await;
+    let pool_stake = pool_stake.unwrap().delegation.stake;
+    assert_eq!(
+        pool_stake,
+        pool_stake_before + TEST_STAKE_AMOUNT - stake_rent
+    );
+    assert_eq!(pool_lamports, pool_stake + pool_meta.rent_exempt_reserve);
+
+    let (transient_state, transient_lamports) =
+        get_transient_stake_state(&mut context, &accounts).await;
+    assert_eq!(transient_state, StakeState::Uninitialized);
+    assert_eq!(transient_lamports, stake_rent);
+}
+
+#[tokio::test]
+async fn success_below_minimum() {
+    let mut context = program_test().start_with_context().await;
+    let accounts = SinglePoolAccounts::default();
+    accounts.initialize(&mut context).await;
+    advance_epoch(&mut context).await;
+
+    let stake_rent = get_stake_account_rent(&mut context.banks_client).await;
+    send_lamports(&mut context, &accounts, stake_rent + 1).await;
+
+    // not enough to delegate, so the lamports stay where they are
+    replenish(&mut context, &accounts).await;
+    let transient = get_account(&mut context.banks_client, &accounts.transient_stake_account).await;
+    assert_eq!(transient.owner, system_program::id());
+    assert_eq!(transient.lamports, stake_rent + 1);
+}
@@ -7,11 +7,16 @@ use {
     solana_clap_utils::{
         fee_payer::fee_payer_arg,
         input_parsers::{keypair_of, pubkey_of, value_of},
-        input_validators::{is_amount, is_keypair, is_parsable, is_pubkey, is_url},
+        input_validators::{
+            is_amount, is_amount_or_all, is_keypair, is_parsable, is_pubkey, is_url,
+        },
         keypair::signer_from_path,
     },
     solana_client::rpc_client::RpcClient,
-    solana_program::{native_token::lamports_to_sol, program_pack::Pack, pubkey::Pubkey},
+    solana_program::{
+        clock::Slot, instruction::Instruction, native_token::lamports_to_sol, program_pack::Pack,
+        pubkey::Pubkey,
+    },
     solana_sdk::{
         commitment_config::CommitmentConfig,
         message::Message,
@@ -20,17 +25,28 @@ use {
         transaction::Transaction,
     },
     spl_token::{
-        instruction::{approve, revoke},
+        amount_to_ui_amount,
+        instruction::{approve, close_account, initialize_account, revoke},
         state::{Account as Token, Mint},
         ui_amount_to_amount,
     },
     spl_token_lending::{
         self,
-        instruction::{init_lending_market, init_reserve},
-        math::WAD,
-        state::{LendingMarket, Reserve, ReserveConfig, ReserveFees},
+        instruction::{
+            borrow_obligation_liquidity, deposit_obligation_collateral, deposit_reserve_liquidity,
+            init_lending_market, init_obligation, init_reserve, liquidate_obligation,
+            redeem_reserve_collateral, refresh_obligation, refresh_reserve,
+            repay_obligation_liquidity, withdraw_obligation_collateral,
+        },
+        math::{Decimal, Rate, WAD},
+        state::{LendingMarket, Obligation, Reserve, ReserveConfig, ReserveFees},
+    },
+    std::{
+        borrow::Borrow,
+        collections::{HashMap, HashSet},
+        process::exit,
+        str::FromStr,
     },
-    std::{borrow::Borrow, process::exit, str::FromStr},
     system_instruction::create_account,
 };
 
@@ -310,6 +326,324 @@ fn main() {
                         .help("Amount of fee going to host account: [0, 100]"),
                 )
         )
+        .subcommand(
+            SubCommand::with_name("deposit")
+                .about("Deposit liquidity into a reserve and use the collateral in an obligation")
+                .arg(
+                    Arg::with_name("source_liquidity_owner")
+                        .long("source-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the SPL Token account to deposit liquidity from"),
+                )
+                .arg(
+                    Arg::with_name("obligation_owner")
+                        .long("obligation-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the obligation"),
+                )
+                .arg(
+                    Arg::with_name("reserve")
+                        .long("reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address to deposit liquidity into"),
+                )
+                .arg(
+                    Arg::with_name("source_liquidity")
+                        .long("source")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("SPL Token account to deposit liquidity from"),
+                )
+                .arg(
+                    Arg::with_name("obligation")
+                        .long("obligation")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("Obligation address to deposit collateral into. \
+                               A new obligation is created if not provided."),
+                )
+                .arg(
+                    Arg::with_name("liquidity_amount")
+                        .long("amount")
+                        .validator(is_amount)
+                        .value_name("DECIMAL_AMOUNT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Amount of liquidity to deposit"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("borrow")
+                .about("Borrow liquidity from a reserve against an obligation")
+                .arg(
+                    Arg::with_name("obligation_owner")
+                        .long("obligation-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the obligation"),
+                )
+                .arg(
+                    Arg::with_name("obligation")
+                        .long("obligation")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Obligation address to borrow against"),
+                )
+                .arg(
+                    Arg::with_name("reserve")
+                        .long("reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address to borrow liquidity from"),
+                )
+                .arg(
+                    Arg::with_name("destination_liquidity")
+                        .long("destination")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("SPL Token account to receive the borrowed liquidity"),
+                )
+                .arg(
+                    Arg::with_name("host_fee_receiver")
+                        .long("host-fee-receiver")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("SPL Token account to receive the host portion of the borrow fee"),
+                )
+                .arg(
+                    Arg::with_name("liquidity_amount")
+                        .long("amount")
+                        .validator(is_amount_or_all)
+                        .value_name("DECIMAL_AMOUNT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Amount of liquidity to borrow, or ALL for 100% of borrowing power"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("repay")
+                .about("Repay borrowed liquidity to a reserve")
+                .arg(
+                    Arg::with_name("source_liquidity_owner")
+                        .long("source-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the SPL Token account to repay liquidity from"),
+                )
+                .arg(
+                    Arg::with_name("obligation")
+                        .long("obligation")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Obligation address to repay"),
+                )
+                .arg(
+                    Arg::with_name("reserve")
+                        .long("reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address the liquidity was borrowed from"),
+                )
+                .arg(
+                    Arg::with_name("source_liquidity")
+                        .long("source")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("SPL Token account to repay liquidity from"),
+                )
+                .arg(
+                    Arg::with_name("liquidity_amount")
+                        .long("amount")
+                        .validator(is_amount_or_all)
+                        .value_name("DECIMAL_AMOUNT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Amount of liquidity to repay, or ALL for 100% of the borrowed amount"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("withdraw")
+                .about("Withdraw collateral from an obligation and redeem it for liquidity")
+                .arg(
+                    Arg::with_name("obligation_owner")
+                        .long("obligation-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the obligation"),
+                )
+                .arg(
+                    Arg::with_name("obligation")
+                        .long("obligation")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Obligation address to withdraw collateral from"),
+                )
+                .arg(
+                    Arg::with_name("reserve")
+                        .long("reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address the collateral was deposited into"),
+                )
+                .arg(
+                    Arg::with_name("destination_liquidity")
+                        .long("destination")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("SPL Token account to receive the redeemed liquidity"),
+                )
+                .arg(
+                    Arg::with_name("collateral_amount")
+                        .long("amount")
+                        .validator(is_amount_or_all)
+                        .value_name("DECIMAL_AMOUNT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Amount of collateral to withdraw, or ALL for up to 100% of the deposited amount"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("liquidate")
+                .about("Repay liquidity of an unhealthy obligation to receive collateral at a discount")
+                .arg(
+                    Arg::with_name("source_liquidity_owner")
+                        .long("source-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the SPL Token account to repay liquidity from"),
+                )
+                .arg(
+                    Arg::with_name("obligation")
+                        .long("obligation")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Obligation address to liquidate"),
+                )
+                .arg(
+                    Arg::with_name("repay_reserve")
+                        .long("repay-reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address the liquidity was borrowed from"),
+                )
+                .arg(
+                    Arg::with_name("withdraw_reserve")
+                        .long("withdraw-reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address the collateral was deposited into"),
+                )
+                .arg(
+                    Arg::with_name("source_liquidity")
+                        .long("source")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("SPL Token account to repay liquidity from"),
+                )
+                .arg(
+                    Arg::with_name("destination_collateral")
+                        .long("destination")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("SPL Token account to receive the withdraw reserve collateral"),
+                )
+                .arg(
+                    Arg::with_name("liquidity_amount")
+                        .long("amount")
+                        .validator(is_amount_or_all)
+                        .value_name("DECIMAL_AMOUNT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Amount of liquidity to repay, or ALL for up to 100% of the borrowed amount"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("show-obligation")
+                .about("Refresh an obligation offchain and display its health and positions")
+                .arg(
+                    Arg::with_name("obligation")
+                        .index(1)
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Obligation address"),
+                )
+                .arg(
+                    Arg::with_name("market_price")
+                        .long("price")
+                        .validator(is_reserve_price)
+                        .value_name("RESERVE=PRICE")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Simulate a reserve liquidity market price in the quote currency, \
+                               instead of the price from the last reserve refresh. \
+                               May be specified multiple times."),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("show-reserve")
+                .about("Display a reserve's liquidity, collateral and configuration")
+                .arg(
+                    Arg::with_name("reserve")
+                        .index(1)
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address"),
+                )
+        )
         .get_matches();
 
     let mut wallet_manager = None;
@@ -410,27 +744,132 @@ fn main() {
                 pyth_price_pubkey,
             )
         }
-        _ => unreachable!(),
-    }
-    .map_err(|err| {
-        eprintln!("{}", err);
-        exit(1);
-    });
-}
+        ("deposit", Some(arg_matches)) => {
+            let source_liquidity_owner_keypair =
+                keypair_of(arg_matches, "source_liquidity_owner").unwrap();
+            let obligation_owner_keypair = keypair_of(arg_matches, "obligation_owner").unwrap();
+            let reserve_pubkey = pubkey_of(arg_matches, "reserve").unwrap();
+            let source_liquidity_pubkey = pubkey_of(arg_matches, "source_liquidity").unwrap();
+            let obligation_pubkey = pubkey_of(arg_matches, "obligation");
+            let ui_amount = value_of(arg_matches, "liquidity_amount").unwrap();
 
-// COMMANDS
+            command_deposit(
+                &config,
+                ui_amount,
+                reserve_pubkey,
+                source_liquidity_pubkey,
+                source_liquidity_owner_keypair,
+                obligation_pubkey,
+                obligation_owner_keypair,
+            )
+        }
+        ("borrow", Some(arg_matches)) => {
+            let obligation_owner_keypair = keypair_of(arg_matches, "obligation_owner").unwrap();
+            let obligation_pubkey = pubkey_of(arg_matches, "obligation").unwrap();
+            let reserve_pubkey = pubkey_of(arg_matches, "reserve").unwrap();
+            let destination_liquidity_pubkey =
+                pubkey_of(arg_matches, "destination_liquidity").unwrap();
+            let host_fee_receiver_pubkey = pubkey_of(arg_matches, "host_fee_receiver");
+            let ui_amount = amount_or_all_of(arg_matches, "liquidity_amount");
 
-fn command_create_lending_market(
-    config: &Config,
-    lending_market_owner: Pubkey,
-    quote_currency: [u8; 32],
-    oracle_program_id: Pubkey,
-) -> CommandResult {
-    let lending_market_keypair = Keypair::new();
-    println!(
-        "Creating lending market {}",
-        lending_market_keypair.pubkey()
-    );
+            command_borrow(
+                &config,
+                ui_amount,
+                obligation_pubkey,
+                obligation_owner_keypair,
+                reserve_pubkey,
+                destination_liquidity_pubkey,
+                host_fee_receiver_pubkey,
+            )
+        }
+        ("repay", Some(arg_matches)) => {
+            let source_liquidity_owner_keypair =
+                keypair_of(arg_matches, "source_liquidity_owner").unwrap();
+            let obligation_pubkey = pubkey_of(arg_matches, "obligation").unwrap();
+            let reserve_pubkey = pubkey_of(arg_matches, "reserve").unwrap();
+            let source_liquidity_pubkey = pubkey_of(arg_matches, "source_liquidity").unwrap();
+            let ui_amount = amount_or_all_of(arg_matches, "liquidity_amount");
+
+            command_repay(
+                &config,
+                ui_amount,
+                obligation_pubkey,
+                reserve_pubkey,
+                source_liquidity_pubkey,
+                source_liquidity_owner_keypair,
+            )
+        }
+        ("withdraw", Some(arg_matches)) => {
+            let obligation_owner_keypair = keypair_of(arg_matches, "obligation_owner").unwrap();
+            let obligation_pubkey = pubkey_of(arg_matches, "obligation").unwrap();
+            let reserve_pubkey = pubkey_of(arg_matches, "reserve").unwrap();
+            let destination_liquidity_pubkey =
+                pubkey_of(arg_matches, "destination_liquidity").unwrap();
+            let ui_amount = amount_or_all_of(arg_matches, "collateral_amount");
+
+            command_withdraw(
+                &config,
+                ui_amount,
+                obligation_pubkey,
+                obligation_owner_keypair,
+                reserve_pubkey,
+                destination_liquidity_pubkey,
+            )
+        }
+        ("liquidate", Some(arg_matches)) => {
+            let source_liquidity_owner_keypair =
+                keypair_of(arg_matches, "source_liquidity_owner").unwrap();
+            let obligation_pubkey = pubkey_of(arg_matches, "obligation").unwrap();
+            let repay_reserve_pubkey = pubkey_of(arg_matches, "repay_reserve").unwrap();
+            let withdraw_reserve_pubkey = pubkey_of(arg_matches, "withdraw_reserve").unwrap();
+            let source_liquidity_pubkey = pubkey_of(arg_matches, "source_liquidity").unwrap();
+            let destination_collateral_pubkey =
+                pubkey_of(arg_matches, "destination_collateral").unwrap();
+            let ui_amount = amount_or_all_of(arg_matches, "liquidity_amount");
+
+            command_liquidate(
+                &config,
+                ui_amount,
+                obligation_pubkey,
+                repay_reserve_pubkey,
+                withdraw_reserve_pubkey,
+                source_liquidity_pubkey,
+                source_liquidity_owner_keypair,
+                destination_collateral_pubkey,
+            )
+        }
+        ("show-obligation", Some(arg_matches)) => {
+            let obligation_pubkey = pubkey_of(arg_matches, "obligation").unwrap();
+            let market_prices = reserve_prices_of(arg_matches, "market_price");
+
+            command_show_obligation(&config, obligation_pubkey, market_prices)
+        }
+        ("show-reserve", Some(arg_matches)) => {
+            let reserve_pubkey = pubkey_of(arg_matches, "reserve").unwrap();
+
+            command_show_reserve(&config, reserve_pubkey)
+        }
+        _ => unreachable!(),
+    }
+    .map_err(|err| {
+        eprintln!("{}", err);
+        exit(1);
+    });
+}
+
+// COMMANDS
+
+fn command_create_lending_market(
+    config: &Config,
+    lending_market_owner: Pubkey,
+    quote_currency: [u8; 32],
+    oracle_program_id: Pubkey,
+) -> CommandResult {
+    let lending_market_keypair = Keypair::new();
+    println!(
+        "Creating lending market {}",
+        lending_market_keypair.pubkey()
+    );
 
     let lending_market_balance = config
         .rpc_client
@@ -691,8 +1130,724 @@ fn command_add_reserve(
     Ok(())
 }
 
+fn command_deposit(
+    config: &Config,
+    ui_amount: f64,
+    reserve_pubkey: Pubkey,
+    source_liquidity_pubkey: Pubkey,
+    source_liquidity_owner_keypair: Keypair,
+    obligation_pubkey: Option<Pubkey>,
+    obligation_owner_keypair: Keypair,
+) -> CommandResult {
+    let reserve = get_reserve(config, &reserve_pubkey)?;
+    let liquidity_amount = ui_amount_to_amount(ui_amount, reserve.liquidity.mint_decimals);
+
+    let obligation_keypair = Keypair::new();
+    let user_collateral_keypair = Keypair::new();
+
+    let mut instructions = vec![];
+    let mut signers: Vec<&dyn Signer> = vec![config.fee_payer.as_ref()];
+    let mut total_balance = 0;
+
+    let obligation_pubkey = if let Some(obligation_pubkey) = obligation_pubkey {
+        obligation_pubkey
+    } else {
+        println!("Creating obligation {}", obligation_keypair.pubkey());
+        let obligation_balance = config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(Obligation::LEN)?;
+        total_balance += obligation_balance;
+        instructions.push(create_account(
+            &config.fee_payer.pubkey(),
+            &obligation_keypair.pubkey(),
+            obligation_balance,
+            Obligation::LEN as u64,
+            &config.lending_program_id,
+        ));
+        instructions.push(init_obligation(
+            config.lending_program_id,
+            obligation_keypair.pubkey(),
+            reserve.lending_market,
+            obligation_owner_keypair.pubkey(),
+        ));
+        signers.push(&obligation_keypair);
+        signers.push(&obligation_owner_keypair);
+        obligation_keypair.pubkey()
+    };
+
+    if config.verbose {
+        println!(
+            "Adding user collateral {}",
+            user_collateral_keypair.pubkey()
+        );
+    }
+    let user_collateral_balance = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(Token::LEN)?;
+    total_balance += user_collateral_balance;
+    instructions.extend(create_token_account_instructions(
+        config,
+        &user_collateral_keypair.pubkey(),
+        user_collateral_balance,
+        &reserve.collateral.mint_pubkey,
+        &obligation_owner_keypair.pubkey(),
+    )?);
+    instructions.push(refresh_reserve(
+        config.lending_program_id,
+        reserve_pubkey,
+        reserve.liquidity.oracle_pubkey,
+    ));
+    instructions.push(deposit_reserve_liquidity(
+        config.lending_program_id,
+        liquidity_amount,
+        source_liquidity_pubkey,
+        user_collateral_keypair.pubkey(),
+        reserve_pubkey,
+        reserve.liquidity.supply_pubkey,
+        reserve.collateral.mint_pubkey,
+        reserve.lending_market,
+        source_liquidity_owner_keypair.pubkey(),
+    ));
+    signers.push(&user_collateral_keypair);
+    signers.push(&source_liquidity_owner_keypair);
+    send_instructions(config, &instructions, signers, total_balance)?;
+
+    if config.dry_run {
+        println!("Skipping obligation collateral deposit, which depends on the liquidity deposit");
+        return Ok(());
+    }
+
+    let collateral_amount = get_token_account(config, &user_collateral_keypair.pubkey())?.amount;
+    println!(
+        "Depositing {} collateral into obligation {}",
+        amount_to_ui_amount(collateral_amount, reserve.liquidity.mint_decimals),
+        obligation_pubkey
+    );
+    send_instructions(
+        config,
+        &[
+            refresh_reserve(
+                config.lending_program_id,
+                reserve_pubkey,
+                reserve.liquidity.oracle_pubkey,
+            ),
+            deposit_obligation_collateral(
+                config.lending_program_id,
+                collateral_amount,
+                user_collateral_keypair.pubkey(),
+                reserve.collateral.supply_pubkey,
+                reserve_pubkey,
+                obligation_pubkey,
+                reserve.lending_market,
+                obligation_owner_keypair.pubkey(),
+                obligation_owner_keypair.pubkey(),
+            ),
+            close_account(
+                &spl_token::id(),
+                &user_collateral_keypair.pubkey(),
+                &config.fee_payer.pubkey(),
+                &obligation_owner_keypair.pubkey(),
+                &[],
+            )?,
+        ],
+        vec![config.fee_payer.as_ref(), &obligation_owner_keypair],
+        0,
+    )
+}
+
+fn command_borrow(
+    config: &Config,
+    ui_amount: Option<f64>,
+    obligation_pubkey: Pubkey,
+    obligation_owner_keypair: Keypair,
+    reserve_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    host_fee_receiver_pubkey: Option<Pubkey>,
+) -> CommandResult {
+    let obligation = get_obligation(config, &obligation_pubkey)?;
+    let reserve = get_reserve(config, &reserve_pubkey)?;
+    let liquidity_amount = ui_amount.map_or(u64::MAX, |ui_amount| {
+        ui_amount_to_amount(ui_amount, reserve.liquidity.mint_decimals)
+    });
+
+    let mut instructions = refresh_obligation_instructions(
+        config,
+        &obligation_pubkey,
+        &obligation,
+        &[reserve_pubkey],
+    )?;
+    instructions.push(borrow_obligation_liquidity(
+        config.lending_program_id,
+        liquidity_amount,
+        None,
+        reserve.liquidity.supply_pubkey,
+        destination_liquidity_pubkey,
+        reserve_pubkey,
+        reserve.liquidity.fee_receiver,
+        obligation_pubkey,
+        obligation.lending_market,
+        obligation_owner_keypair.pubkey(),
+        host_fee_receiver_pubkey,
+    ));
+    send_instructions(
+        config,
+        &instructions,
+        vec![config.fee_payer.as_ref(), &obligation_owner_keypair],
+        0,
+    )
+}
+
+fn command_repay(
+    config: &Config,
+    ui_amount: Option<f64>,
+    obligation_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    source_liquidity_pubkey: Pubkey,
+    source_liquidity_owner_keypair: Keypair,
+) -> CommandResult {
+    let obligation = get_obligation(config, &obligation_pubkey)?;
+    let reserve = get_reserve(config, &reserve_pubkey)?;
+    let liquidity_amount = ui_amount.map_or(u64::MAX, |ui_amount| {
+        ui_amount_to_amount(ui_amount, reserve.liquidity.mint_decimals)
+    });
+
+    let mut instructions = refresh_obligation_instructions(
+        config,
+        &obligation_pubkey,
+        &obligation,
+        &[reserve_pubkey],
+    )?;
+    instructions.push(repay_obligation_liquidity(
+        config.lending_program_id,
+        liquidity_amount,
+        source_liquidity_pubkey,
+        reserve.liquidity.supply_pubkey,
+        reserve_pubkey,
+        obligation_pubkey,
+        obligation.lending_market,
+        source_liquidity_owner_keypair.pubkey(),
+    ));
+    send_instructions(
+        config,
+        &instructions,
+        vec![config.fee_payer.as_ref(), &source_liquidity_owner_keypair],
+        0,
+    )
+}
+
+fn command_withdraw(
+    config: &Config,
+    ui_amount: Option<f64>,
+    obligation_pubkey: Pubkey,
+    obligation_owner_keypair: Keypair,
+    reserve_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+) -> CommandResult {
+    let obligation = get_obligation(config, &obligation_pubkey)?;
+    let reserve = get_reserve(config, &reserve_pubkey)?;
+    let collateral_amount = ui_amount.map_or(u64::MAX, |ui_amount| {
+        ui_amount_to_amount(ui_amount, reserve.liquidity.mint_decimals)
+    });
+
+    let user_collateral_keypair = Keypair::new();
+    if config.verbose {
+        println!(
+            "Adding user collateral {}",
+            user_collateral_keypair.pubkey()
+        );
+    }
+    let user_collateral_balance = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(Token::LEN)?;
+
+    let mut instructions = create_token_account_instructions(
+        config,
+        &user_collateral_keypair.pubkey(),
+        user_collateral_balance,
+        &reserve.collateral.mint_pubkey,
+        &obligation_owner_keypair.pubkey(),
+    )?;
+    instructions.extend(refresh_obligation_instructions(
+        config,
+        &obligation_pubkey,
+        &obligation,
+        &[reserve_pubkey],
+    )?);
+    instructions.push(withdraw_obligation_collateral(
+        config.lending_program_id,
+        collateral_amount,
+        reserve.collateral.supply_pubkey,
+        user_collateral_keypair.pubkey(),
+        reserve_pubkey,
+        obligation_pubkey,
+        obligation.lending_market,
+        obligation_owner_keypair.pubkey(),
+    ));
+    send_instructions(
+        config,
+        &instructions,
+        vec![
+            config.fee_payer.as_ref(),
+            &user_collateral_keypair,
+            &obligation_owner_keypair,
+        ],
+        user_collateral_balance,
+    )?;
+
+    if config.dry_run {
+        println!("Skipping collateral redemption, which depends on the collateral withdrawal");
+        return Ok(());
+    }
+
+    let collateral_amount = get_token_account(config, &user_collateral_keypair.pubkey())?.amount;
+    println!(
+        "Redeeming {} collateral from reserve {}",
+        amount_to_ui_amount(collateral_amount, reserve.liquidity.mint_decimals),
+        reserve_pubkey
+    );
+    send_instructions(
+        config,
+        &[
+            refresh_reserve(
+                config.lending_program_id,
+                reserve_pubkey,
+                reserve.liquidity.oracle_pubkey,
+            ),
+            redeem_reserve_collateral(
+                config.lending_program_id,
+                collateral_amount,
+                user_collateral_keypair.pubkey(),
+                destination_liquidity_pubkey,
+                reserve_pubkey,
+                reserve.collateral.mint_pubkey,
+                reserve.liquidity.supply_pubkey,
+                reserve.lending_market,
+                obligation_owner_keypair.pubkey(),
+            ),
+            close_account(
+                &spl_token::id(),
+                &user_collateral_keypair.pubkey(),
+                &config.fee_payer.pubkey(),
+                &obligation_owner_keypair.pubkey(),
+                &[],
+            )?,
+        ],
+        vec![config.fee_payer.as_ref(), &obligation_owner_keypair],
+        0,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn command_liquidate(
+    config: &Config,
+    ui_amount: Option<f64>,
+    obligation_pubkey: Pubkey,
+    repay_reserve_pubkey: Pubkey,
+    withdraw_reserve_pubkey: Pubkey,
+    source_liquidity_pubkey: Pubkey,
+    source_liquidity_owner_keypair: Keypair,
+    destination_collateral_pubkey: Pubkey,
+) -> CommandResult {
+    let obligation = get_obligation(config, &obligation_pubkey)?;
+    let repay_reserve = get_reserve(config, &repay_reserve_pubkey)?;
+    let withdraw_reserve = get_reserve(config, &withdraw_reserve_pubkey)?;
+    let liquidity_amount = ui_amount.map_or(u64::MAX, |ui_amount| {
+        ui_amount_to_amount(ui_amount, repay_reserve.liquidity.mint_decimals)
+    });
+
+    let mut instructions = refresh_obligation_instructions(
+        config,
+        &obligation_pubkey,
+        &obligation,
+        &[repay_reserve_pubkey, withdraw_reserve_pubkey],
+    )?;
+    instructions.push(liquidate_obligation(
+        config.lending_program_id,
+        liquidity_amount,
+        source_liquidity_pubkey,
+        destination_collateral_pubkey,
+        repay_reserve_pubkey,
+        repay_reserve.liquidity.supply_pubkey,
+        withdraw_reserve_pubkey,
+        withdraw_reserve.collateral.supply_pubkey,
+        obligation_pubkey,
+        obligation.lending_market,
+        source_liquidity_owner_keypair.pubkey(),
+    ));
+    send_instructions(
+        config,
+        &instructions,
+        vec![config.fee_payer.as_ref(), &source_liquidity_owner_keypair],
+        0,
+    )
+}
+
+fn command_show_obligation(
+    config: &Config,
+    obligation_pubkey: Pubkey,
+    market_prices: HashMap<Pubkey, Decimal>,
+) -> CommandResult {
+    let mut obligation = get_obligation(config, &obligation_pubkey)?;
+    let slot = config.rpc_client.get_slot()?;
+
+    // Market prices are only updated onchain by `RefreshReserve`, so unless simulated, values are
+    // calculated with the price from the last refresh of each reserve
+    let mut reserves = HashMap::new();
+    let mut price_slots = HashMap::new();
+    for reserve_pubkey in obligation_reserve_pubkeys(&obligation) {
+        let mut reserve = get_reserve(config, &reserve_pubkey)?;
+        price_slots.insert(reserve_pubkey, reserve.last_update.slot);
+        reserve.accrue_interest(slot)?;
+        reserve.last_update.update_slot(slot);
+        reserves.insert(reserve_pubkey, reserve);
+    }
+    for (reserve_pubkey, market_price) in &market_prices {
+        let reserve = reserves.get_mut(reserve_pubkey).ok_or_else(|| {
+            format!(
+                "Reserve {} is not a deposit or borrow reserve of obligation {}",
+                reserve_pubkey, obligation_pubkey
+            )
+        })?;
+        reserve.liquidity.market_price = *market_price;
+    }
+    refresh_obligation_offchain(&mut obligation, &reserves)?;
+
+    let remaining_borrow_value = if obligation.allowed_borrow_value > obligation.borrowed_value {
+        obligation.remaining_borrow_value()?
+    } else {
+        Decimal::zero()
+    };
+    let is_healthy = obligation.borrowed_value < obligation.unhealthy_borrow_value
+        || obligation.borrows.is_empty();
+
+    println!("Obligation: {}", obligation_pubkey);
+    println!("Lending market: {}", obligation.lending_market);
+    println!("Owner: {}", obligation.owner);
+    println!("Simulated slot: {}", slot);
+    if !market_prices.is_empty() {
+        println!("Simulated market prices: {}", market_prices.len());
+    }
+    println!("Market prices are from the last reserve refresh unless simulated");
+    println!();
+    println!("Deposited value: {}", obligation.deposited_value);
+    println!("Borrowed value: {}", obligation.borrowed_value);
+    println!("Allowed borrow value: {}", obligation.allowed_borrow_value);
+    println!(
+        "Unhealthy borrow value: {}",
+        obligation.unhealthy_borrow_value
+    );
+    println!("Remaining borrow value: {}", remaining_borrow_value);
+    if obligation.deposited_value > Decimal::zero() {
+        println!("Loan to value: {}", obligation.loan_to_value()?);
+    }
+    println!(
+        "Status: {}",
+        if is_healthy {
+            "healthy"
+        } else {
+            "unhealthy, can be liquidated"
+        }
+    );
+
+    println!();
+    println!("Deposits:");
+    if obligation.deposits.is_empty() {
+        println!("  None");
+    }
+    for collateral in &obligation.deposits {
+        let reserve = &reserves[&collateral.deposit_reserve];
+        let decimals = reserve.liquidity.mint_decimals;
+        let liquidity_amount = reserve
+            .collateral_exchange_rate()?
+            .collateral_to_liquidity(collateral.deposited_amount)?;
+        let price_source = price_source(&market_prices, &price_slots, &collateral.deposit_reserve);
+        println!("  Reserve: {}", collateral.deposit_reserve);
+        println!(
+            "    Collateral amount: {}",
+            amount_to_ui_amount(collateral.deposited_amount, decimals)
+        );
+        println!(
+            "    Liquidity amount: {}",
+            amount_to_ui_amount(liquidity_amount, decimals)
+        );
+        println!(
+            "    Market price: {} ({})",
+            reserve.liquidity.market_price, price_source
+        );
+        println!("    Market value: {}", collateral.market_value);
+        println!(
+            "    Loan to value ratio: {}%",
+            reserve.config.loan_to_value_ratio
+        );
+        println!(
+            "    Liquidation threshold: {}%",
+            reserve.config.liquidation_threshold
+        );
+    }
+
+    println!();
+    println!("Borrows:");
+    if obligation.borrows.is_empty() {
+        println!("  None");
+    }
+    for liquidity in &obligation.borrows {
+        let reserve = &reserves[&liquidity.borrow_reserve];
+        let decimals = reserve.liquidity.mint_decimals;
+        let price_source = price_source(&market_prices, &price_slots, &liquidity.borrow_reserve);
+        println!("  Reserve: {}", liquidity.borrow_reserve);
+        println!(
+            "    Borrowed amount: {}",
+            amount_to_ui_amount(liquidity.borrowed_amount_wads.try_ceil_u64()?, decimals)
+        );
+        println!(
+            "    Market price: {} ({})",
+            reserve.liquidity.market_price, price_source
+        );
+        println!("    Market value: {}", liquidity.market_value);
+        println!("    Borrow rate: {}", reserve.current_borrow_rate()?);
+    }
+
+    Ok(())
+}
+
+/// Describe where the market price of a reserve shown by `show-obligation` comes from
+fn price_source(
+    market_prices: &HashMap<Pubkey, Decimal>,
+    price_slots: &HashMap<Pubkey, Slot>,
+    reserve_pubkey: &Pubkey,
+) -> String {
+    if market_prices.contains_key(reserve_pubkey) {
+        "simulated".to_string()
+    } else {
+        format!("last refreshed at slot {}", price_slots[reserve_pubkey])
+    }
+}
+
+fn command_show_reserve(config: &Config, reserve_pubkey: Pubkey) -> CommandResult {
+    let mut reserve = get_reserve(config, &reserve_pubkey)?;
+    let last_update_slot = reserve.last_update.slot;
+    let slot = config.rpc_client.get_slot()?;
+    reserve.accrue_interest(slot)?;
+    reserve.last_update.update_slot(slot);
+
+    let decimals = reserve.liquidity.mint_decimals;
+    let reserve_config = &reserve.config;
+
+    println!("Reserve: {}", reserve_pubkey);
+    println!("Lending market: {}", reserve.lending_market);
+    println!("Last update slot: {}", last_update_slot);
+    println!("Simulated slot: {}", slot);
+    println!();
+    println!("Liquidity mint: {}", reserve.liquidity.mint_pubkey);
+    println!("Liquidity supply: {}", reserve.liquidity.supply_pubkey);
+    println!("Liquidity fee receiver: {}", reserve.liquidity.fee_receiver);
+    println!("Liquidity oracle: {}", reserve.liquidity.oracle_pubkey);
+    println!(
+        "Available liquidity: {}",
+        amount_to_ui_amount(reserve.liquidity.available_amount, decimals)
+    );
+    println!(
+        "Borrowed liquidity: {}",
+        amount_to_ui_amount(
+            reserve.liquidity.borrowed_amount_wads.try_ceil_u64()?,
+            decimals
+        )
+    );
+    println!("Market price: {}", reserve.liquidity.market_price);
+    println!(
+        "Utilization rate: {}",
+        reserve.liquidity.utilization_rate()?
+    );
+    println!("Borrow rate: {}", reserve.current_borrow_rate()?);
+    println!();
+    println!("Collateral mint: {}", reserve.collateral.mint_pubkey);
+    println!("Collateral supply: {}", reserve.collateral.supply_pubkey);
+    println!(
+        "Collateral mint supply: {}",
+        amount_to_ui_amount(reserve.collateral.mint_total_supply, decimals)
+    );
+    println!(
+        "Collateral per liquidity: {}",
+        Rate::from(reserve.collateral_exchange_rate()?)
+    );
+    println!();
+    println!(
+        "Optimal utilization rate: {}%",
+        reserve_config.optimal_utilization_rate
+    );
+    println!(
+        "Loan to value ratio: {}%",
+        reserve_config.loan_to_value_ratio
+    );
+    println!("Liquidation bonus: {}%", reserve_config.liquidation_bonus);
+    println!(
+        "Liquidation threshold: {}%",
+        reserve_config.liquidation_threshold
+    );
+    println!("Min borrow rate: {}%", reserve_config.min_borrow_rate);
+    println!(
+        "Optimal borrow rate: {}%",
+        reserve_config.optimal_borrow_rate
+    );
+    println!("Max borrow rate: {}%", reserve_config.max_borrow_rate);
+    println!(
+        "Borrow fee: {}",
+        Rate::from_scaled_val(reserve_config.fees.borrow_fee_wad)
+    );
+    println!(
+        "Flash loan fee: {}",
+        Rate::from_scaled_val(reserve_config.fees.flash_loan_fee_wad)
+    );
+    println!(
+        "Host fee percentage: {}%",
+        reserve_config.fees.host_fee_percentage
+    );
+
+    Ok(())
+}
+
 // HELPERS
 
+fn get_reserve(config: &Config, reserve_pubkey: &Pubkey) -> Result<Reserve, Error> {
+    let reserve_account = config.rpc_client.get_account(reserve_pubkey)?;
+    if reserve_account.owner != config.lending_program_id {
+        return Err(format!(
+            "Reserve {} is not owned by the lending program",
+            reserve_pubkey
+        )
+        .into());
+    }
+    Ok(Reserve::unpack(reserve_account.data.borrow())?)
+}
+
+fn get_obligation(config: &Config, obligation_pubkey: &Pubkey) -> Result<Obligation, Error> {
+    let obligation_account = config.rpc_client.get_account(obligation_pubkey)?;
+    if obligation_account.owner != config.lending_program_id {
+        return Err(format!(
+            "Obligation {} is not owned by the lending program",
+            obligation_pubkey
+        )
+        .into());
+    }
+    Ok(Obligation::unpack(obligation_account.data.borrow())?)
+}
+
+fn get_token_account(config: &Config, token_account_pubkey: &Pubkey) -> Result<Token, Error> {
+    let token_account = config.rpc_client.get_account(token_account_pubkey)?;
+    Ok(Token::unpack(token_account.data.borrow())?)
+}
+
+/// Deposit reserves of an obligation in order, followed by its borrow reserves in order, as
+/// expected by `RefreshObligation`
+fn obligation_reserve_pubkeys(obligation: &Obligation) -> Vec<Pubkey> {
+    obligation
+        .deposits
+        .iter()
+        .map(|collateral| collateral.deposit_reserve)
+        .chain(
+            obligation
+                .borrows
+                .iter()
+                .map(|liquidity| liquidity.borrow_reserve),
+        )
+        .collect()
+}
+
+/// Refresh every reserve of an obligation, plus any other reserves an instruction needs
+/// refreshed, followed by the obligation itself
+fn refresh_obligation_instructions(
+    config: &Config,
+    obligation_pubkey: &Pubkey,
+    obligation: &Obligation,
+    extra_reserve_pubkeys: &[Pubkey],
+) -> Result<Vec<Instruction>, Error> {
+    let reserve_pubkeys = obligation_reserve_pubkeys(obligation);
+
+    let mut instructions = vec![];
+    let mut refreshed_reserve_pubkeys = HashSet::new();
+    for reserve_pubkey in reserve_pubkeys.iter().chain(extra_reserve_pubkeys) {
+        if refreshed_reserve_pubkeys.insert(*reserve_pubkey) {
+            let reserve = get_reserve(config, reserve_pubkey)?;
+            instructions.push(refresh_reserve(
+                config.lending_program_id,
+                *reserve_pubkey,
+                reserve.liquidity.oracle_pubkey,
+            ));
+        }
+    }
+    instructions.push(refresh_obligation(
+        config.lending_program_id,
+        *obligation_pubkey,
+        reserve_pubkeys,
+    ));
+    Ok(instructions)
+}
+
+/// Refresh an obligation offchain the same way `RefreshObligation` does onchain. Reserves must
+/// already have interest accrued up to the slot being simulated.
+fn refresh_obligation_offchain(
+    obligation: &mut Obligation,
+    reserves: &HashMap<Pubkey, Reserve>,
+) -> Result<(), Error> {
+    let deposit_reserves: Vec<Reserve> = obligation
+        .deposits
+        .iter()
+        .map(|collateral| reserves[&collateral.deposit_reserve].clone())
+        .collect();
+    let borrow_reserves: Vec<Reserve> = obligation
+        .borrows
+        .iter()
+        .map(|liquidity| reserves[&liquidity.borrow_reserve].clone())
+        .collect();
+    obligation.refresh_values(&deposit_reserves, &borrow_reserves)?;
+    Ok(())
+}
+
+fn create_token_account_instructions(
+    config: &Config,
+    token_account_pubkey: &Pubkey,
+    token_account_balance: u64,
+    mint_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+) -> Result<Vec<Instruction>, Error> {
+    Ok(vec![
+        create_account(
+            &config.fee_payer.pubkey(),
+            token_account_pubkey,
+            token_account_balance,
+            Token::LEN as u64,
+            &spl_token::id(),
+        ),
+        initialize_account(
+            &spl_token::id(),
+            token_account_pubkey,
+            mint_pubkey,
+            owner_pubkey,
+        )?,
+    ])
+}
+
+fn send_instructions(
+    config: &Config,
+    instructions: &[Instruction],
+    signers: Vec<&dyn Signer>,
+    required_balance: u64,
+) -> CommandResult {
+    let recent_blockhash = config.rpc_client.get_latest_blockhash()?;
+    let message = Message::new_with_blockhash(
+        instructions,
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+    check_fee_payer_balance(
+        config,
+        required_balance + config.rpc_client.get_fee_for_message(&message)?,
+    )?;
+    let transaction = Transaction::new(&signers, message, recent_blockhash);
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
 fn check_fee_payer_balance(config: &Config, required_balance: u64) -> Result<(), Error> {
     let balance = config.rpc_client.get_balance(&config.fee_payer.pubkey())?;
     if balance < required_balance {
@@ -739,3 +1894,63 @@ fn quote_currency_of(matches: &ArgMatches<'_>, name: &str) -> Option<[u8; 32]> {
         None
     }
 }
+
+fn amount_or_all_of(matches: &ArgMatches<'_>, name: &str) -> Option<f64> {
+    match matches.value_of(name).unwrap() {
+        "ALL" => None,
+        amount => Some(amount.parse::<f64>().unwrap()),
+    }
+}
+
+fn parse_reserve_price(value: &str) -> Result<(Pubkey, Decimal), String> {
+    let (reserve, price) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Expected RESERVE=PRICE, found {}", value))?;
+    let reserve = Pubkey::from_str(reserve).map_err(|err| format!("{}", err))?;
+    let price = price.parse::<f64>().map_err(|err| format!("{}", err))?;
+    if !price.is_finite() || price < 0.0 {
+        return Err(format!(
+            "Price must be a finite, non-negative number, found {}",
+            price
+        ));
+    }
+    Ok((
+        reserve,
+        Decimal::from_scaled_val((price * WAD as f64) as u128),
+    ))
+}
+
+fn is_reserve_price(value: String) -> Result<(), String> {
+    parse_reserve_price(&value).map(|_| ())
+}
+
+fn reserve_prices_of(matches: &ArgMatches<'_>, name: &str) -> HashMap<Pubkey, Decimal> {
+    matches
+        .values_of(name)
+        .map(|values| {
+            values
+                .map(|value| parse_reserve_price(value).unwrap())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reserve_price() {
+        let reserve = Pubkey::new_unique();
+        assert_eq!(
+            parse_reserve_price(&format!("{}=1.5", reserve)),
+            Ok((reserve, Decimal::from_scaled_val(WAD as u128 * 3 / 2)))
+        );
+        assert!(parse_reserve_price(&format!("{}=-1", reserve)).is_err());
+        assert!(parse_reserve_price(&format!("{}=nan", reserve)).is_err());
+        assert!(parse_reserve_price(&format!("{}=NaN", reserve)).is_err());
+        assert!(parse_reserve_price(&format!("{}=inf", reserve)).is_err());
+        assert!(parse_reserve_price(&format!("{}=-inf", reserve)).is_err());
+        assert!(parse_reserve_price("1.5").is_err());
+    }
+}
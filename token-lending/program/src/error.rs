@@ -163,6 +163,18 @@ pub enum LendingError {
     /// Lending instruction exceeds desired slippage limit
     #[error("Amount smaller than desired slippage limit")]
     ExceededSlippage,
+    /// Obligation account has no room for more deposits or borrows
+    #[error("Obligation account is too small")]
+    ObligationAccountTooSmall,
+    /// Flash borrow instruction is not followed by a matching flash repay
+    #[error("Flash borrow is invalid")]
+    InvalidFlashBorrow,
+    /// Flash repay instruction does not match a preceding flash borrow
+    #[error("Flash repay is invalid")]
+    InvalidFlashRepay,
+    /// Flash loans cannot be nested or repaid more than once
+    #[error("Multiple flash borrows or repays are not allowed")]
+    MultipleFlashBorrows,
 }
 
 impl From<LendingError> for ProgramError {
@@ -9,7 +9,7 @@ use solana_program::{
     msg,
     program_error::ProgramError,
     pubkey::{Pubkey, PUBKEY_BYTES},
-    sysvar,
+    system_program, sysvar,
 };
 use std::{convert::TryInto, mem::size_of};
 
@@ -322,6 +322,87 @@ pub enum LendingInstruction {
         /// Reserve configuration updated values
         new_config: ReserveConfig,
     },
+
+    // 15
+    /// Reallocate an obligation account so it has room for more collateral deposits and
+    /// liquidity borrows combined. The payer funds the additional rent.
+    ///
+    /// Obligations holding more than 13 reserves can no longer be refreshed in a legacy
+    /// transaction, which is limited to 1232 bytes. Their refresh, and any instruction that needs
+    /// the refreshed obligation, must be sent in a versioned transaction loading the reserve and
+    /// oracle addresses from an address lookup table, where the limit becomes the 64 accounts a
+    /// transaction can lock.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Obligation owner.
+    ///   3. `[writable, signer]` Payer.
+    ///   4. `[]` Rent sysvar.
+    ///   5. `[]` System program.
+    ExpandObligation {
+        /// Number of collateral and liquidity reserves combined the obligation should have room
+        /// for, up to `MAX_OBLIGATION_RESERVES`
+        max_reserves: u8,
+    },
+
+    // 16
+    /// Borrow liquidity from a reserve, to be repaid later in the same transaction by a
+    /// `FlashRepayReserveLiquidity` instruction. The lending program inspects the transaction
+    /// through the instructions sysvar to ensure that exactly one matching repay follows, so
+    /// the borrowed liquidity may be used by any instructions in between. Requires a refreshed
+    /// reserve, and marks it stale so that it must be refreshed again before other instructions
+    /// use it.
+    ///
+    /// This instruction cannot be invoked through a cross-program invocation.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source liquidity token account.
+    ///                     Minted by reserve liquidity mint.
+    ///                     Must match the reserve liquidity supply.
+    ///   1. `[writable]` Destination liquidity token account.
+    ///                     Minted by reserve liquidity mint.
+    ///   2. `[writable]` Reserve account - refreshed.
+    ///   3. `[]` Lending market account.
+    ///   4. `[]` Derived lending market authority.
+    ///   5. `[]` Clock sysvar.
+    ///   6. `[]` Instructions sysvar.
+    ///   7. `[]` Token program id.
+    FlashBorrowReserveLiquidity {
+        /// Amount of liquidity to borrow
+        liquidity_amount: u64,
+    },
+
+    // 17
+    /// Repay liquidity borrowed by a `FlashBorrowReserveLiquidity` instruction earlier in the
+    /// same transaction, along with the reserve's flash loan fee. The reserve does not need to
+    /// be refreshed after the flash borrow marked it stale.
+    ///
+    /// This instruction cannot be invoked through a cross-program invocation.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source liquidity token account.
+    ///                     Minted by reserve liquidity mint.
+    ///                     $authority can transfer $liquidity_amount plus fees.
+    ///   1. `[writable]` Destination liquidity token account.
+    ///                     Must match the reserve liquidity supply.
+    ///   2. `[writable]` Flash loan fee receiver account.
+    ///                     Must match the reserve liquidity fee receiver.
+    ///   3. `[writable]` Host fee receiver.
+    ///   4. `[writable]` Reserve account.
+    ///   5. `[]` Lending market account.
+    ///   6. `[signer]` User transfer authority ($authority).
+    ///   7. `[]` Instructions sysvar.
+    ///   8. `[]` Token program id.
+    FlashRepayReserveLiquidity {
+        /// Amount of liquidity borrowed, excluding fees. Must match the flash borrow.
+        liquidity_amount: u64,
+        /// Index of the `FlashBorrowReserveLiquidity` instruction in the transaction
+        borrow_instruction_index: u8,
+    },
 }
 
 impl LendingInstruction {
@@ -394,6 +475,22 @@ impl LendingInstruction {
                 let new_config = Self::unpack_reserve_config(rest)?;
                 Self::ModifyReserveConfig { new_config }
             }
+            15 => {
+                let (max_reserves, _rest) = Self::unpack_u8(rest)?;
+                Self::ExpandObligation { max_reserves }
+            }
+            16 => {
+                let (liquidity_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::FlashBorrowReserveLiquidity { liquidity_amount }
+            }
+            17 => {
+                let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
+                let (borrow_instruction_index, _rest) = Self::unpack_u8(rest)?;
+                Self::FlashRepayReserveLiquidity {
+                    liquidity_amount,
+                    borrow_instruction_index,
+                }
+            }
             _ => {
                 msg!("Instruction cannot be unpacked");
                 return Err(LendingError::InstructionUnpackError.into());
@@ -554,6 +651,22 @@ impl LendingInstruction {
                 buf.push(14);
                 Self::extend_buffer_from_reserve_config(&mut buf, &new_config);
             }
+            Self::ExpandObligation { max_reserves } => {
+                buf.push(15);
+                buf.extend_from_slice(&max_reserves.to_le_bytes());
+            }
+            Self::FlashBorrowReserveLiquidity { liquidity_amount } => {
+                buf.push(16);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+            }
+            Self::FlashRepayReserveLiquidity {
+                liquidity_amount,
+                borrow_instruction_index,
+            } => {
+                buf.push(17);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+                buf.extend_from_slice(&borrow_instruction_index.to_le_bytes());
+            }
         }
         buf
     }
@@ -1035,9 +1148,97 @@ pub fn modify_reserve_config(
     }
 }
 
+/// Creates an 'ExpandObligation' instruction.
+pub fn expand_obligation(
+    program_id: Pubkey,
+    max_reserves: u8,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    payer_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(obligation_owner_pubkey, true),
+            AccountMeta::new(payer_pubkey, true),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::ExpandObligation { max_reserves }.pack(),
+    }
+}
+
+/// Creates a `FlashBorrowReserveLiquidity` instruction.
+pub fn flash_borrow_reserve_liquidity(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_liquidity_pubkey, false),
+            AccountMeta::new(destination_liquidity_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::FlashBorrowReserveLiquidity { liquidity_amount }.pack(),
+    }
+}
+
+/// Creates a `FlashRepayReserveLiquidity` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn flash_repay_reserve_liquidity(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    borrow_instruction_index: u8,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    host_fee_receiver_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_liquidity_pubkey, false),
+            AccountMeta::new(destination_liquidity_pubkey, false),
+            AccountMeta::new(reserve_liquidity_fee_receiver_pubkey, false),
+            AccountMeta::new(host_fee_receiver_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::FlashRepayReserveLiquidity {
+            liquidity_amount,
+            borrow_instruction_index,
+        }
+        .pack(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::MAX_OBLIGATION_RESERVES;
 
     #[test]
     fn test_init_lending_market() {
@@ -1479,4 +1680,95 @@ mod tests {
             LendingInstruction::ModifyReserveConfig { new_config: config }.pack()
         );
     }
+
+    #[test]
+    fn test_expand_obligation() {
+        let program_id = Pubkey::new_unique();
+        let max_reserves = MAX_OBLIGATION_RESERVES as u8;
+        let obligation_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let obligation_owner_pubkey = Pubkey::new_unique();
+        let payer_pubkey = Pubkey::new_unique();
+        let instruction = expand_obligation(
+            program_id,
+            max_reserves,
+            obligation_pubkey,
+            lending_market_pubkey,
+            obligation_owner_pubkey,
+            payer_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 6);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::ExpandObligation { max_reserves }.pack()
+        );
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data).unwrap(),
+            LendingInstruction::ExpandObligation { max_reserves }
+        );
+    }
+
+    #[test]
+    fn test_flash_borrow_reserve_liquidity() {
+        let program_id = Pubkey::new_unique();
+        let liquidity_amount = 1000;
+        let source_liquidity_pubkey = Pubkey::new_unique();
+        let destination_liquidity_pubkey = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let instruction = flash_borrow_reserve_liquidity(
+            program_id,
+            liquidity_amount,
+            source_liquidity_pubkey,
+            destination_liquidity_pubkey,
+            reserve_pubkey,
+            lending_market_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 8);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::FlashBorrowReserveLiquidity { liquidity_amount }.pack()
+        );
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data).unwrap(),
+            LendingInstruction::FlashBorrowReserveLiquidity { liquidity_amount }
+        );
+    }
+
+    #[test]
+    fn test_flash_repay_reserve_liquidity() {
+        let program_id = Pubkey::new_unique();
+        let liquidity_amount = 1000;
+        let borrow_instruction_index = 2;
+        let source_liquidity_pubkey = Pubkey::new_unique();
+        let destination_liquidity_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_fee_receiver_pubkey = Pubkey::new_unique();
+        let host_fee_receiver_pubkey = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let user_transfer_authority_pubkey = Pubkey::new_unique();
+        let instruction = flash_repay_reserve_liquidity(
+            program_id,
+            liquidity_amount,
+            borrow_instruction_index,
+            source_liquidity_pubkey,
+            destination_liquidity_pubkey,
+            reserve_liquidity_fee_receiver_pubkey,
+            host_fee_receiver_pubkey,
+            reserve_pubkey,
+            lending_market_pubkey,
+            user_transfer_authority_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 9);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data).unwrap(),
+            LendingInstruction::FlashRepayReserveLiquidity {
+                liquidity_amount,
+                borrow_instruction_index,
+            }
+        );
+    }
 }
@@ -3,13 +3,13 @@
 use crate::{
     error::LendingError,
     instruction::LendingInstruction,
-    math::{Decimal, Rate, TryAdd, TryDiv, TryMul},
+    math::{Decimal, Rate, TryDiv, TryMul},
     pyth,
     state::{
         CalculateBorrowResult, CalculateLiquidationResult, CalculateRepayResult,
         InitLendingMarketParams, InitObligationParams, InitReserveParams, LendingMarket,
         NewReserveCollateralParams, NewReserveLiquidityParams, Obligation, Reserve,
-        ReserveCollateral, ReserveConfig, ReserveLiquidity,
+        ReserveCollateral, ReserveConfig, ReserveLiquidity, MAX_OBLIGATION_RESERVES,
     },
 };
 use num_traits::FromPrimitive;
@@ -23,7 +23,13 @@ use solana_program::{
     program_error::{PrintProgramError, ProgramError},
     program_pack::{IsInitialized, Pack},
     pubkey::Pubkey,
-    sysvar::{clock::Clock, rent::Rent, Sysvar},
+    system_instruction,
+    sysvar::{
+        clock::Clock,
+        instructions::{load_current_index_checked, load_instruction_at_checked},
+        rent::Rent,
+        Sysvar,
+    },
 };
 use spl_token::solana_program::instruction::AccountMeta;
 use spl_token::state::{Account, Mint};
@@ -111,6 +117,26 @@ pub fn process_instruction(
             msg!("Instruction: Modify Reserve Config");
             process_modify_reserve_config(program_id, new_config, accounts)
         }
+        LendingInstruction::ExpandObligation { max_reserves } => {
+            msg!("Instruction: Expand Obligation");
+            process_expand_obligation(program_id, max_reserves, accounts)
+        }
+        LendingInstruction::FlashBorrowReserveLiquidity { liquidity_amount } => {
+            msg!("Instruction: Flash Borrow Reserve Liquidity");
+            process_flash_borrow_reserve_liquidity(program_id, liquidity_amount, accounts)
+        }
+        LendingInstruction::FlashRepayReserveLiquidity {
+            liquidity_amount,
+            borrow_instruction_index,
+        } => {
+            msg!("Instruction: Flash Repay Reserve Liquidity");
+            process_flash_repay_reserve_liquidity(
+                program_id,
+                liquidity_amount,
+                borrow_instruction_index,
+                accounts,
+            )
+        }
     }
 }
 
@@ -664,12 +690,8 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
         return Err(LendingError::InvalidAccountOwner.into());
     }
 
-    let mut deposited_value = Decimal::zero();
-    let mut borrowed_value = Decimal::zero();
-    let mut allowed_borrow_value = Decimal::zero();
-    let mut unhealthy_borrow_value = Decimal::zero();
-
-    for (index, collateral) in obligation.deposits.iter_mut().enumerate() {
+    let mut deposit_reserves = Vec::with_capacity(obligation.deposits.len());
+    for (index, collateral) in obligation.deposits.iter().enumerate() {
         let deposit_reserve_info = next_account_info(account_info_iter)?;
         if deposit_reserve_info.owner != program_id {
             msg!(
@@ -694,31 +716,11 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
             );
             return Err(LendingError::ReserveStale.into());
         }
-
-        // @TODO: add lookup table https://git.io/JOCYq
-        let decimals = 10u64
-            .checked_pow(deposit_reserve.liquidity.mint_decimals as u32)
-            .ok_or(LendingError::MathOverflow)?;
-
-        let market_value = deposit_reserve
-            .collateral_exchange_rate()?
-            .decimal_collateral_to_liquidity(collateral.deposited_amount.into())?
-            .try_mul(deposit_reserve.liquidity.market_price)?
-            .try_div(decimals)?;
-        collateral.market_value = market_value;
-
-        let loan_to_value_rate = Rate::from_percent(deposit_reserve.config.loan_to_value_ratio);
-        let liquidation_threshold_rate =
-            Rate::from_percent(deposit_reserve.config.liquidation_threshold);
-
-        deposited_value = deposited_value.try_add(market_value)?;
-        allowed_borrow_value =
-            allowed_borrow_value.try_add(market_value.try_mul(loan_to_value_rate)?)?;
-        unhealthy_borrow_value =
-            unhealthy_borrow_value.try_add(market_value.try_mul(liquidation_threshold_rate)?)?;
+        deposit_reserves.push(deposit_reserve);
     }
 
-    for (index, liquidity) in obligation.borrows.iter_mut().enumerate() {
+    let mut borrow_reserves = Vec::with_capacity(obligation.borrows.len());
+    for (index, liquidity) in obligation.borrows.iter().enumerate() {
         let borrow_reserve_info = next_account_info(account_info_iter)?;
         if borrow_reserve_info.owner != program_id {
             msg!(
@@ -743,21 +745,7 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
             );
             return Err(LendingError::ReserveStale.into());
         }
-
-        liquidity.accrue_interest(borrow_reserve.liquidity.cumulative_borrow_rate_wads)?;
-
-        // @TODO: add lookup table https://git.io/JOCYq
-        let decimals = 10u64
-            .checked_pow(borrow_reserve.liquidity.mint_decimals as u32)
-            .ok_or(LendingError::MathOverflow)?;
-
-        let market_value = liquidity
-            .borrowed_amount_wads
-            .try_mul(borrow_reserve.liquidity.market_price)?
-            .try_div(decimals)?;
-        liquidity.market_value = market_value;
-
-        borrowed_value = borrowed_value.try_add(market_value)?;
+        borrow_reserves.push(borrow_reserve);
     }
 
     if account_info_iter.peek().is_some() {
@@ -765,10 +753,7 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
         return Err(LendingError::InvalidAccountInput.into());
     }
 
-    obligation.deposited_value = deposited_value;
-    obligation.borrowed_value = borrowed_value;
-    obligation.allowed_borrow_value = allowed_borrow_value;
-    obligation.unhealthy_borrow_value = unhealthy_borrow_value;
+    obligation.refresh_values(&deposit_reserves, &borrow_reserves)?;
 
     obligation.last_update.update_slot(clock.slot);
     Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
@@ -1727,6 +1712,352 @@ fn process_modify_reserve_config(
     Ok(())
 }
 
+fn process_expand_obligation(
+    program_id: &Pubkey,
+    max_reserves: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let max_reserves = max_reserves as usize;
+    if max_reserves > MAX_OBLIGATION_RESERVES {
+        msg!(
+            "Obligation cannot have more than {} deposits and borrows combined",
+            MAX_OBLIGATION_RESERVES
+        );
+        return Err(LendingError::ObligationReserveLimit.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let new_data_len = Obligation::space(max_reserves);
+    if new_data_len <= obligation_info.data_len() {
+        msg!(
+            "Obligation already has room for {} deposits and borrows combined",
+            max_reserves
+        );
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let required_lamports = rent
+        .minimum_balance(new_data_len)
+        .saturating_sub(obligation_info.lamports());
+    if required_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer_info.key, obligation_info.key, required_lamports),
+            &[
+                payer_info.clone(),
+                obligation_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    obligation_info.realloc(new_data_len, false)?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_flash_borrow_reserve_liquidity(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let instructions_sysvar_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey != source_liquidity_info.key {
+        msg!("Reserve liquidity supply must be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey == destination_liquidity_info.key {
+        msg!("Reserve liquidity supply cannot be used as the destination liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if reserve.last_update.is_stale(clock.slot)? {
+        msg!("Reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    let current_index = load_current_index_checked(instructions_sysvar_info)? as usize;
+    let current_instruction = load_instruction_at_checked(current_index, instructions_sysvar_info)?;
+    if &current_instruction.program_id != program_id {
+        msg!("Flash borrow cannot be invoked through a cross-program invocation");
+        return Err(LendingError::InvalidFlashBorrow.into());
+    }
+
+    // the borrow must be repaid exactly once later in this transaction, and no other flash
+    // borrow may be taken out before it is
+    let mut found_repay = false;
+    let mut index = current_index + 1;
+    while let Ok(instruction) = load_instruction_at_checked(index, instructions_sysvar_info) {
+        index += 1;
+        if &instruction.program_id != program_id {
+            continue;
+        }
+        match LendingInstruction::unpack(&instruction.data)? {
+            LendingInstruction::FlashRepayReserveLiquidity {
+                liquidity_amount: repay_liquidity_amount,
+                borrow_instruction_index,
+            } => {
+                if borrow_instruction_index as usize != current_index {
+                    continue;
+                }
+                if found_repay {
+                    msg!("Flash borrow cannot be repaid more than once");
+                    return Err(LendingError::MultipleFlashBorrows.into());
+                }
+                if instruction.accounts.get(4).map(|meta| &meta.pubkey) != Some(reserve_info.key) {
+                    msg!("Flash repay reserve does not match the flash borrow reserve");
+                    return Err(LendingError::InvalidFlashRepay.into());
+                }
+                if repay_liquidity_amount != liquidity_amount {
+                    msg!("Flash repay amount does not match the flash borrow amount");
+                    return Err(LendingError::InvalidFlashRepay.into());
+                }
+                found_repay = true;
+            }
+            LendingInstruction::FlashBorrowReserveLiquidity { .. } if !found_repay => {
+                msg!("Flash borrows cannot be nested");
+                return Err(LendingError::MultipleFlashBorrows.into());
+            }
+            _ => {}
+        }
+    }
+    if !found_repay {
+        msg!("Flash borrow must be followed by a matching flash repay");
+        return Err(LendingError::InvalidFlashBorrow.into());
+    }
+
+    reserve.liquidity.borrow(Decimal::from(liquidity_amount))?;
+    reserve.last_update.mark_stale();
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: destination_liquidity_info.clone(),
+        amount: liquidity_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_flash_repay_reserve_liquidity(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    borrow_instruction_index: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_fee_receiver_info = next_account_info(account_info_iter)?;
+    let host_fee_receiver_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let instructions_sysvar_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey != destination_liquidity_info.key {
+        msg!("Reserve liquidity supply must be used as the destination liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey == source_liquidity_info.key {
+        msg!("Reserve liquidity supply cannot be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.fee_receiver != reserve_liquidity_fee_receiver_info.key {
+        msg!("Reserve liquidity fee receiver does not match the reserve liquidity fee receiver provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let current_index = load_current_index_checked(instructions_sysvar_info)? as usize;
+    let current_instruction = load_instruction_at_checked(current_index, instructions_sysvar_info)?;
+    if &current_instruction.program_id != program_id {
+        msg!("Flash repay cannot be invoked through a cross-program invocation");
+        return Err(LendingError::InvalidFlashRepay.into());
+    }
+
+    let borrow_instruction_index = borrow_instruction_index as usize;
+    if borrow_instruction_index >= current_index {
+        msg!("Flash borrow must come before the flash repay");
+        return Err(LendingError::InvalidFlashRepay.into());
+    }
+    let borrow_instruction =
+        load_instruction_at_checked(borrow_instruction_index, instructions_sysvar_info)?;
+    if &borrow_instruction.program_id != program_id {
+        msg!("Flash borrow instruction provided is not a lending program instruction");
+        return Err(LendingError::InvalidFlashRepay.into());
+    }
+    match LendingInstruction::unpack(&borrow_instruction.data)? {
+        LendingInstruction::FlashBorrowReserveLiquidity {
+            liquidity_amount: borrow_liquidity_amount,
+        } => {
+            if borrow_instruction.accounts.get(2).map(|meta| &meta.pubkey) != Some(reserve_info.key)
+            {
+                msg!("Flash borrow reserve does not match the flash repay reserve");
+                return Err(LendingError::InvalidFlashRepay.into());
+            }
+            if borrow_liquidity_amount != liquidity_amount {
+                msg!("Flash borrow amount does not match the flash repay amount");
+                return Err(LendingError::InvalidFlashRepay.into());
+            }
+        }
+        _ => {
+            msg!("Flash borrow instruction provided is not a flash borrow");
+            return Err(LendingError::InvalidFlashRepay.into());
+        }
+    }
+
+    let (origination_fee, host_fee) = reserve
+        .config
+        .fees
+        .calculate_flash_loan_fees(Decimal::from(liquidity_amount))?;
+
+    reserve
+        .liquidity
+        .repay(liquidity_amount, Decimal::from(liquidity_amount))?;
+    reserve.last_update.mark_stale();
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: destination_liquidity_info.clone(),
+        amount: liquidity_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+
+    let mut owner_fee = origination_fee;
+    if host_fee > 0 {
+        owner_fee = owner_fee
+            .checked_sub(host_fee)
+            .ok_or(LendingError::MathOverflow)?;
+        spl_token_transfer(TokenTransferParams {
+            source: source_liquidity_info.clone(),
+            destination: host_fee_receiver_info.clone(),
+            amount: host_fee,
+            authority: user_transfer_authority_info.clone(),
+            authority_signer_seeds: &[],
+            token_program: token_program_id.clone(),
+        })?;
+    }
+
+    if owner_fee > 0 {
+        spl_token_transfer(TokenTransferParams {
+            source: source_liquidity_info.clone(),
+            destination: reserve_liquidity_fee_receiver_info.clone(),
+            amount: owner_fee,
+            authority: user_transfer_authority_info.clone(),
+            authority_signer_seeds: &[],
+            token_program: token_program_id.clone(),
+        })?;
+    }
+
+    Ok(())
+}
+
 fn assert_rent_exempt(rent: &Rent, account_info: &AccountInfo) -> ProgramResult {
     if !rent.is_exempt(account_info.lamports(), account_info.data_len()) {
         msg!(&rent.minimum_balance(account_info.data_len()).to_string());
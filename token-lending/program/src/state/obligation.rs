@@ -17,8 +17,19 @@ use std::{
     convert::{TryFrom, TryInto},
 };
 
-/// Max number of collateral and liquidity reserve accounts combined for an obligation
-pub const MAX_OBLIGATION_RESERVES: usize = 10;
+/// Max number of collateral and liquidity reserve accounts combined for an obligation. Every
+/// obligation instruction that needs a refreshed obligation has to share its transaction with a
+/// `RefreshReserve` for each reserve and a `RefreshObligation` listing all of them, so the limit
+/// is set by the 64 accounts a transaction can lock. With reserve and oracle addresses loaded
+/// from an address lookup table, 25 reserves, each with its own oracle, fit together with
+/// `LiquidateObligation` or `BorrowObligationLiquidity` and a compute budget instruction.
+/// Legacy transactions are bound by the 1232 byte packet size instead, and can refresh at most
+/// 13 reserves on their own.
+pub const MAX_OBLIGATION_RESERVES: usize = 25;
+
+/// Number of collateral and liquidity reserve accounts combined that an obligation account of
+/// `Obligation::LEN` bytes has room for, before being expanded with `ExpandObligation`
+pub const DEFAULT_OBLIGATION_RESERVES: usize = 10;
 
 /// Lending market obligation state
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -63,6 +74,70 @@ impl Obligation {
         self.borrows = params.borrows;
     }
 
+    /// Account size needed for an obligation with room for `max_reserves` collateral and liquidity
+    /// reserve accounts combined, assuming at least one of them is collateral
+    pub fn space(max_reserves: usize) -> usize {
+        OBLIGATION_HEADER_LEN
+            + OBLIGATION_COLLATERAL_LEN
+            + OBLIGATION_LIQUIDITY_LEN * max_reserves.saturating_sub(1)
+    }
+
+    /// Number of bytes needed to pack the obligation with its current deposits and borrows
+    pub fn packed_len(&self) -> usize {
+        OBLIGATION_HEADER_LEN
+            + OBLIGATION_COLLATERAL_LEN * self.deposits.len()
+            + OBLIGATION_LIQUIDITY_LEN * self.borrows.len()
+    }
+
+    /// Refresh the market value of every deposit and borrow, followed by the deposited, borrowed,
+    /// allowed and unhealthy borrow values of the obligation. Reserves must be refreshed and given
+    /// in the same order as `deposits` and `borrows`.
+    pub fn refresh_values(
+        &mut self,
+        deposit_reserves: &[Reserve],
+        borrow_reserves: &[Reserve],
+    ) -> ProgramResult {
+        if deposit_reserves.len() != self.deposits.len()
+            || borrow_reserves.len() != self.borrows.len()
+        {
+            msg!("Number of reserves does not match the obligation deposits and borrows");
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        let mut deposited_value = Decimal::zero();
+        let mut borrowed_value = Decimal::zero();
+        let mut allowed_borrow_value = Decimal::zero();
+        let mut unhealthy_borrow_value = Decimal::zero();
+
+        for (collateral, deposit_reserve) in self.deposits.iter_mut().zip(deposit_reserves) {
+            collateral.refresh_market_value(deposit_reserve)?;
+
+            let loan_to_value_rate = Rate::from_percent(deposit_reserve.config.loan_to_value_ratio);
+            let liquidation_threshold_rate =
+                Rate::from_percent(deposit_reserve.config.liquidation_threshold);
+
+            deposited_value = deposited_value.try_add(collateral.market_value)?;
+            allowed_borrow_value = allowed_borrow_value
+                .try_add(collateral.market_value.try_mul(loan_to_value_rate)?)?;
+            unhealthy_borrow_value = unhealthy_borrow_value.try_add(
+                collateral
+                    .market_value
+                    .try_mul(liquidation_threshold_rate)?,
+            )?;
+        }
+
+        for (liquidity, borrow_reserve) in self.borrows.iter_mut().zip(borrow_reserves) {
+            liquidity.refresh_market_value(borrow_reserve)?;
+            borrowed_value = borrowed_value.try_add(liquidity.market_value)?;
+        }
+
+        self.deposited_value = deposited_value;
+        self.borrowed_value = borrowed_value;
+        self.allowed_borrow_value = allowed_borrow_value;
+        self.unhealthy_borrow_value = unhealthy_borrow_value;
+        Ok(())
+    }
+
     /// Calculate the current ratio of borrowed value to deposited value
     pub fn loan_to_value(&self) -> Result<Decimal, ProgramError> {
         self.borrowed_value.try_div(self.deposited_value)
@@ -266,6 +341,15 @@ impl ObligationCollateral {
             .ok_or(LendingError::MathOverflow)?;
         Ok(())
     }
+
+    /// Update the market value of the deposited collateral from its refreshed deposit reserve
+    pub fn refresh_market_value(&mut self, deposit_reserve: &Reserve) -> ProgramResult {
+        let liquidity_amount = deposit_reserve
+            .collateral_exchange_rate()?
+            .decimal_collateral_to_liquidity(self.deposited_amount.into())?;
+        self.market_value = deposit_reserve.market_value(liquidity_amount)?;
+        Ok(())
+    }
 }
 
 /// Obligation liquidity state
@@ -326,17 +410,52 @@ impl ObligationLiquidity {
 
         Ok(())
     }
+
+    /// Accrue interest and update the market value of the borrowed liquidity from its refreshed
+    /// borrow reserve
+    pub fn refresh_market_value(&mut self, borrow_reserve: &Reserve) -> ProgramResult {
+        self.accrue_interest(borrow_reserve.liquidity.cumulative_borrow_rate_wads)?;
+        self.market_value = borrow_reserve.market_value(self.borrowed_amount_wads)?;
+        Ok(())
+    }
 }
 
+const OBLIGATION_HEADER_LEN: usize = 140; // 1 + 8 + 1 + 32 + 32 + 16 + 16 + 16 + 16 + 1 + 1
 const OBLIGATION_COLLATERAL_LEN: usize = 56; // 32 + 8 + 16
 const OBLIGATION_LIQUIDITY_LEN: usize = 80; // 32 + 16 + 16 + 16
-const OBLIGATION_LEN: usize = 916; // 1 + 8 + 1 + 32 + 32 + 16 + 16 + 16 + 16 + 1 + 1 + (56 * 1) + (80 * 9)
+const OBLIGATION_LEN: usize = 916; // 140 + (56 * 1) + (80 * 9)
                                    // @TODO: break this up by obligation / collateral / liquidity https://git.io/JOCca
+
+/// Obligations are packed as a fixed header followed by their deposits and borrows. `LEN` is the
+/// size of an obligation with room for `DEFAULT_OBLIGATION_RESERVES`, but larger accounts created
+/// or reallocated with `ExpandObligation` are accepted as well.
 impl Pack for Obligation {
     const LEN: usize = OBLIGATION_LEN;
 
+    fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::unpack_from_slice(input)
+    }
+
+    fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if src.packed_len() > dst.len() {
+            msg!(
+                "Obligation account is too small for its deposits and borrows and must be expanded"
+            );
+            return Err(LendingError::ObligationAccountTooSmall.into());
+        }
+        src.pack_into_slice(dst);
+        Ok(())
+    }
+
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let output = array_mut_ref![dst, 0, OBLIGATION_LEN];
+        let (output, data_flat) = dst.split_at_mut(OBLIGATION_HEADER_LEN);
+        let output = array_mut_ref![output, 0, OBLIGATION_HEADER_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -350,7 +469,6 @@ impl Pack for Obligation {
             unhealthy_borrow_value,
             deposits_len,
             borrows_len,
-            data_flat,
         ) = mut_array_refs![
             output,
             1,
@@ -363,8 +481,7 @@ impl Pack for Obligation {
             16,
             16,
             1,
-            1,
-            OBLIGATION_COLLATERAL_LEN + (OBLIGATION_LIQUIDITY_LEN * (MAX_OBLIGATION_RESERVES - 1))
+            1
         ];
 
         // obligation
@@ -413,7 +530,11 @@ impl Pack for Obligation {
 
     /// Unpacks a byte buffer into an [ObligationInfo](struct.ObligationInfo.html).
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let input = array_ref![src, 0, OBLIGATION_LEN];
+        if src.len() < OBLIGATION_HEADER_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let (input, data_flat) = src.split_at(OBLIGATION_HEADER_LEN);
+        let input = array_ref![input, 0, OBLIGATION_HEADER_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -427,7 +548,6 @@ impl Pack for Obligation {
             unhealthy_borrow_value,
             deposits_len,
             borrows_len,
-            data_flat,
         ) = array_refs![
             input,
             1,
@@ -440,8 +560,7 @@ impl Pack for Obligation {
             16,
             16,
             1,
-            1,
-            OBLIGATION_COLLATERAL_LEN + (OBLIGATION_LIQUIDITY_LEN * (MAX_OBLIGATION_RESERVES - 1))
+            1
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -452,6 +571,13 @@ impl Pack for Obligation {
 
         let deposits_len = u8::from_le_bytes(*deposits_len);
         let borrows_len = u8::from_le_bytes(*borrows_len);
+        if OBLIGATION_COLLATERAL_LEN * deposits_len as usize
+            + OBLIGATION_LIQUIDITY_LEN * borrows_len as usize
+            > data_flat.len()
+        {
+            msg!("Obligation deposits and borrows do not fit in the account data");
+            return Err(ProgramError::InvalidAccountData);
+        }
         let mut deposits = Vec::with_capacity(deposits_len as usize + 1);
         let mut borrows = Vec::with_capacity(borrows_len as usize + 1);
 
@@ -570,6 +696,66 @@ mod test {
         }
     }
 
+    fn obligation_with_reserves(deposits: usize, borrows: usize) -> Obligation {
+        Obligation {
+            version: PROGRAM_VERSION,
+            deposits: (0..deposits)
+                .map(|_| ObligationCollateral::new(Pubkey::new_unique()))
+                .collect(),
+            borrows: (0..borrows)
+                .map(|_| ObligationLiquidity::new(Pubkey::new_unique()))
+                .collect(),
+            ..Obligation::default()
+        }
+    }
+
+    #[test]
+    fn obligation_space() {
+        assert_eq!(
+            Obligation::space(DEFAULT_OBLIGATION_RESERVES),
+            Obligation::LEN
+        );
+        assert_eq!(
+            obligation_with_reserves(1, DEFAULT_OBLIGATION_RESERVES - 1).packed_len(),
+            Obligation::LEN
+        );
+    }
+
+    #[test]
+    fn pack_and_unpack_expanded_obligation() {
+        let obligation = obligation_with_reserves(3, MAX_OBLIGATION_RESERVES - 3);
+        let mut data = vec![0; Obligation::space(MAX_OBLIGATION_RESERVES)];
+        Obligation::pack(obligation.clone(), &mut data).unwrap();
+        assert_eq!(Obligation::unpack(&data).unwrap(), obligation);
+    }
+
+    #[test]
+    fn pack_obligation_too_small() {
+        let obligation = obligation_with_reserves(1, DEFAULT_OBLIGATION_RESERVES);
+        let mut data = vec![0; Obligation::LEN];
+        assert_eq!(
+            Obligation::pack(obligation, &mut data),
+            Err(LendingError::ObligationAccountTooSmall.into())
+        );
+
+        let mut data = vec![0; Obligation::LEN - 1];
+        assert_eq!(
+            Obligation::pack(Obligation::default(), &mut data),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn unpack_obligation_with_too_many_reserves() {
+        let obligation = obligation_with_reserves(1, DEFAULT_OBLIGATION_RESERVES);
+        let mut data = vec![0; Obligation::space(DEFAULT_OBLIGATION_RESERVES + 1)];
+        Obligation::pack(obligation, &mut data).unwrap();
+        assert_eq!(
+            Obligation::unpack(&data[..Obligation::LEN]),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
     proptest! {
         #[test]
         fn repay_partial(
@@ -124,6 +124,17 @@ impl Reserve {
         self.collateral.exchange_rate(total_liquidity)
     }
 
+    /// Calculate the market value of a liquidity amount at the market price of the last refresh
+    pub fn market_value(&self, liquidity_amount: Decimal) -> Result<Decimal, ProgramError> {
+        // @TODO: add lookup table https://git.io/JOCYq
+        let decimals = 10u64
+            .checked_pow(self.liquidity.mint_decimals as u32)
+            .ok_or(LendingError::MathOverflow)?;
+        liquidity_amount
+            .try_mul(self.liquidity.market_price)?
+            .try_div(decimals)
+    }
+
     /// Update borrow rate and accrue interest
     pub fn accrue_interest(&mut self, current_slot: Slot) -> ProgramResult {
         let slots_elapsed = self.last_update.slots_elapsed(current_slot)?;
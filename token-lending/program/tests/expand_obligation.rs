@@ -0,0 +1,426 @@
+#![allow(clippy::integer_arithmetic)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_address_lookup_table_program::state::{AddressLookupTable, LookupTableMeta};
+use solana_program::program_pack::Pack;
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    address_lookup_table_account::AddressLookupTableAccount,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{Instruction, InstructionError},
+    message::{v0, VersionedMessage},
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::{Transaction, TransactionError, VersionedTransaction},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{expand_obligation, liquidate_obligation, refresh_obligation, refresh_reserve},
+    math::TryDiv,
+    processor::process_instruction,
+    state::{Obligation, INITIAL_COLLATERAL_RATIO, MAX_OBLIGATION_RESERVES},
+};
+use std::borrow::Cow;
+
+/// Number of accounts a transaction can lock while the feature increasing the limit is inactive
+const TRANSACTION_ACCOUNT_LOCK_LIMIT: usize = 64;
+
+/// Number of reserves a legacy transaction can refresh together with their obligation
+const LEGACY_TRANSACTION_RESERVE_LIMIT: usize = 13;
+
+/// Size of a legacy transaction refreshing `num_reserves` reserves, each with its own oracle,
+/// followed by an obligation holding all of them
+fn legacy_refresh_transaction_size(num_reserves: usize) -> usize {
+    let reserve_pubkeys: Vec<Pubkey> = (0..num_reserves).map(|_| Pubkey::new_unique()).collect();
+    let mut instructions: Vec<_> = reserve_pubkeys
+        .iter()
+        .map(|reserve_pubkey| {
+            refresh_reserve(
+                spl_token_lending::id(),
+                *reserve_pubkey,
+                Pubkey::new_unique(),
+            )
+        })
+        .collect();
+    instructions.push(refresh_obligation(
+        spl_token_lending::id(),
+        Pubkey::new_unique(),
+        reserve_pubkeys,
+    ));
+    let payer = Keypair::new();
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[&payer],
+        Default::default(),
+    );
+    // One byte for the length of the signatures, which fits in a single byte of a short vec
+    1 + transaction.signatures.len() * 64 + transaction.message_data().len()
+}
+
+/// Versioned message of a transaction refreshing `num_reserves` reserves, each with its own
+/// oracle, and the obligation holding all of them, then liquidating the obligation. Every account
+/// other than the signers and the invoked programs is loaded from an address lookup table.
+fn refresh_and_liquidate_message(num_reserves: usize, liquidator: &Keypair) -> v0::Message {
+    let reserve_pubkeys: Vec<Pubkey> = (0..num_reserves).map(|_| Pubkey::new_unique()).collect();
+    let obligation_pubkey = Pubkey::new_unique();
+    let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_400_000)];
+    instructions.extend(reserve_pubkeys.iter().map(|reserve_pubkey| {
+        refresh_reserve(
+            spl_token_lending::id(),
+            *reserve_pubkey,
+            Pubkey::new_unique(),
+        )
+    }));
+    instructions.push(refresh_obligation(
+        spl_token_lending::id(),
+        obligation_pubkey,
+        reserve_pubkeys.clone(),
+    ));
+    instructions.push(liquidate_obligation(
+        spl_token_lending::id(),
+        1,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        reserve_pubkeys[0],
+        Pubkey::new_unique(),
+        reserve_pubkeys[1],
+        Pubkey::new_unique(),
+        obligation_pubkey,
+        Pubkey::new_unique(),
+        liquidator.pubkey(),
+    ));
+
+    let mut addresses: Vec<Pubkey> = instructions
+        .iter()
+        .flat_map(|instruction| instruction.accounts.iter())
+        .filter(|account_meta| !account_meta.is_signer)
+        .map(|account_meta| account_meta.pubkey)
+        .collect();
+    addresses.sort();
+    addresses.dedup();
+    let payer = Keypair::new();
+    v0::Message::try_compile(
+        &payer.pubkey(),
+        &instructions,
+        &[AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses,
+        }],
+        Hash::default(),
+    )
+    .unwrap()
+}
+
+/// Number of accounts locked by a versioned message, including those loaded from lookup tables
+fn locked_accounts(message: &v0::Message) -> usize {
+    message.account_keys.len()
+        + message
+            .address_table_lookups
+            .iter()
+            .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+            .sum::<usize>()
+}
+
+#[test]
+fn test_legacy_refresh_reserve_limit() {
+    assert!(legacy_refresh_transaction_size(LEGACY_TRANSACTION_RESERVE_LIMIT) <= PACKET_DATA_SIZE);
+    assert!(
+        legacy_refresh_transaction_size(LEGACY_TRANSACTION_RESERVE_LIMIT + 1) > PACKET_DATA_SIZE
+    );
+}
+
+#[test]
+fn test_max_reserves_refresh_and_liquidate_in_single_transaction() {
+    let liquidator = Keypair::new();
+    let message = refresh_and_liquidate_message(MAX_OBLIGATION_RESERVES, &liquidator);
+    assert!(locked_accounts(&message) <= TRANSACTION_ACCOUNT_LOCK_LIMIT);
+    let num_signatures = message.header.num_required_signatures as usize;
+    let transaction_size =
+        1 + num_signatures * 64 + VersionedMessage::V0(message).serialize().len();
+    assert!(transaction_size <= PACKET_DATA_SIZE);
+
+    let message = refresh_and_liquidate_message(MAX_OBLIGATION_RESERVES + 1, &liquidator);
+    assert!(locked_accounts(&message) > TRANSACTION_ACCOUNT_LOCK_LIMIT);
+}
+
+#[tokio::test]
+async fn test_refresh_max_reserves_with_lookup_table() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const SOL_DEPOSIT_AMOUNT: u64 = 100;
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 =
+        SOL_DEPOSIT_AMOUNT * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let oracles: Vec<TestOracle> = (0..MAX_OBLIGATION_RESERVES)
+        .map(|_| add_sol_oracle_copy(&mut test))
+        .collect();
+    let reserves: Vec<TestReserve> = oracles
+        .iter()
+        .map(|oracle| {
+            add_reserve(
+                &mut test,
+                &lending_market,
+                oracle,
+                &user_accounts_owner,
+                AddReserveArgs {
+                    collateral_amount: 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                    liquidity_mint_decimals: 9,
+                    liquidity_mint_pubkey: spl_token::native_mint::id(),
+                    config: TEST_RESERVE_CONFIG,
+                    slots_elapsed: 1, // elapsed from 1; clock.slot = 2
+                    ..AddReserveArgs::default()
+                },
+            )
+        })
+        .collect();
+    let deposits: Vec<(&TestReserve, u64)> = reserves
+        .iter()
+        .map(|reserve| (reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS))
+        .collect();
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &deposits,
+            slots_elapsed: 1, // elapsed from 1; clock.slot = 2
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let mut addresses = vec![test_obligation.pubkey, sysvar::clock::id()];
+    addresses.extend(reserves.iter().map(|reserve| reserve.pubkey));
+    addresses.extend(oracles.iter().map(|oracle| oracle.price_pubkey));
+    let lookup_table_pubkey = Pubkey::new_unique();
+    let lookup_table_data = AddressLookupTable {
+        meta: LookupTableMeta::default(),
+        addresses: Cow::Borrowed(&addresses[..]),
+    }
+    .serialize_for_tests()
+    .unwrap();
+    test.add_account(
+        lookup_table_pubkey,
+        Account {
+            lamports: u32::MAX as u64,
+            data: lookup_table_data,
+            owner: solana_address_lookup_table_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+
+    let mut test_context = test.start_with_context().await;
+    test_context.warp_to_slot(3).unwrap(); // clock.slot = 3
+
+    let ProgramTestContext {
+        mut banks_client,
+        payer,
+        last_blockhash: recent_blockhash,
+        ..
+    } = test_context;
+
+    let mut instructions: Vec<Instruction> = reserves
+        .iter()
+        .zip(oracles.iter())
+        .map(|(reserve, oracle)| {
+            refresh_reserve(spl_token_lending::id(), reserve.pubkey, oracle.price_pubkey)
+        })
+        .collect();
+    instructions.push(refresh_obligation(
+        spl_token_lending::id(),
+        test_obligation.pubkey,
+        reserves.iter().map(|reserve| reserve.pubkey).collect(),
+    ));
+    let message = v0::Message::try_compile(
+        &payer.pubkey(),
+        &instructions,
+        &[AddressLookupTableAccount {
+            key: lookup_table_pubkey,
+            addresses,
+        }],
+        recent_blockhash,
+    )
+    .unwrap();
+    let transaction =
+        VersionedTransaction::try_new(VersionedMessage::V0(message), &[&payer]).unwrap();
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert!(!obligation.last_update.stale);
+    assert_eq!(obligation.deposits.len(), MAX_OBLIGATION_RESERVES);
+    for (collateral, oracle) in obligation.deposits.iter().zip(oracles.iter()) {
+        assert_eq!(
+            collateral.market_value.try_div(SOL_DEPOSIT_AMOUNT).unwrap(),
+            oracle.price
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // limit to track compute unit increase
+    test.set_compute_max_units(10_000);
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+    let obligation = TestObligation::init(
+        &mut banks_client,
+        &lending_market,
+        &user_accounts_owner,
+        &payer,
+    )
+    .await
+    .unwrap();
+
+    let max_reserves = MAX_OBLIGATION_RESERVES as u8;
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[expand_obligation(
+            spl_token_lending::id(),
+            max_reserves,
+            obligation.pubkey,
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation_account = banks_client
+        .get_account(obligation.pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let data_len = Obligation::space(MAX_OBLIGATION_RESERVES);
+    assert!(data_len > Obligation::LEN);
+    assert_eq!(obligation_account.data.len(), data_len);
+
+    let rent = banks_client.get_rent().await.unwrap();
+    assert!(rent.is_exempt(obligation_account.lamports, data_len));
+
+    obligation.validate_state(&mut banks_client).await;
+}
+
+#[tokio::test]
+async fn test_invalid_obligation_owner() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // limit to track compute unit increase
+    test.set_compute_max_units(10_000);
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs::default(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let invalid_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[expand_obligation(
+            spl_token_lending::id(),
+            MAX_OBLIGATION_RESERVES as u8,
+            obligation.pubkey,
+            lending_market.pubkey,
+            invalid_owner.pubkey(),
+            payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &invalid_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidObligationOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_reserve_limit() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // limit to track compute unit increase
+    test.set_compute_max_units(10_000);
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs::default(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[expand_obligation(
+            spl_token_lending::id(),
+            MAX_OBLIGATION_RESERVES as u8 + 1,
+            obligation.pubkey,
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::ObligationReserveLimit as u32)
+        )
+    );
+}
@@ -0,0 +1,435 @@
+#![allow(clippy::integer_arithmetic)]
+#![cfg(feature = "test-sbf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::InstructionError,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{
+        deposit_reserve_liquidity, flash_borrow_reserve_liquidity, flash_repay_reserve_liquidity,
+    },
+    processor::process_instruction,
+};
+
+const FLASH_LOAN_AMOUNT: u64 = 1_000 * FRACTIONAL_TO_USDC;
+const FEE_AMOUNT: u64 = 3_000_000;
+const HOST_FEE_AMOUNT: u64 = 600_000;
+
+struct FlashLoanTest {
+    lending_market: TestLendingMarket,
+    usdc_test_reserve: TestReserve,
+    user_accounts_owner: Keypair,
+    user_liquidity_pubkey: Pubkey,
+}
+
+fn setup(test: &mut ProgramTest, user_liquidity_amount: u64, mark_fresh: bool) -> FlashLoanTest {
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(test);
+
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.fees.flash_loan_fee_wad = 3_000_000_000_000_000;
+
+    let usdc_mint = add_usdc_mint(test);
+    let usdc_oracle = add_usdc_oracle(test);
+    let usdc_test_reserve = add_reserve(
+        test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: FLASH_LOAN_AMOUNT,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let user_liquidity_pubkey = add_account_for_program(
+        test,
+        &user_accounts_owner.pubkey(),
+        user_liquidity_amount,
+        &usdc_mint.pubkey,
+    );
+
+    FlashLoanTest {
+        lending_market,
+        usdc_test_reserve,
+        user_accounts_owner,
+        user_liquidity_pubkey,
+    }
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // limit to track compute unit increase
+    test.set_compute_max_units(70_000);
+
+    let FlashLoanTest {
+        lending_market,
+        usdc_test_reserve,
+        user_accounts_owner,
+        user_liquidity_pubkey,
+    } = setup(&mut test, FEE_AMOUNT, true);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let initial_liquidity_supply =
+        get_token_balance(&mut banks_client, usdc_test_reserve.liquidity_supply_pubkey).await;
+    let initial_available_amount = usdc_test_reserve
+        .get_state(&mut banks_client)
+        .await
+        .liquidity
+        .available_amount;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            flash_borrow_reserve_liquidity(
+                spl_token_lending::id(),
+                FLASH_LOAN_AMOUNT,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+            ),
+            flash_repay_reserve_liquidity(
+                spl_token_lending::id(),
+                FLASH_LOAN_AMOUNT,
+                0,
+                user_liquidity_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                usdc_test_reserve.liquidity_host_pubkey,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+                user_accounts_owner.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let usdc_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(
+        usdc_reserve.liquidity.available_amount,
+        initial_available_amount
+    );
+
+    let liquidity_supply =
+        get_token_balance(&mut banks_client, usdc_test_reserve.liquidity_supply_pubkey).await;
+    assert_eq!(liquidity_supply, initial_liquidity_supply);
+
+    let user_liquidity_balance = get_token_balance(&mut banks_client, user_liquidity_pubkey).await;
+    assert_eq!(user_liquidity_balance, 0);
+
+    let fee_balance = get_token_balance(
+        &mut banks_client,
+        usdc_test_reserve.liquidity_fee_receiver_pubkey,
+    )
+    .await;
+    assert_eq!(fee_balance, FEE_AMOUNT - HOST_FEE_AMOUNT);
+
+    let host_fee_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.liquidity_host_pubkey).await;
+    assert_eq!(host_fee_balance, HOST_FEE_AMOUNT);
+}
+
+#[tokio::test]
+async fn test_missing_repay() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let FlashLoanTest {
+        lending_market,
+        usdc_test_reserve,
+        user_liquidity_pubkey,
+        ..
+    } = setup(&mut test, FEE_AMOUNT, true);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[flash_borrow_reserve_liquidity(
+            spl_token_lending::id(),
+            FLASH_LOAN_AMOUNT,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            user_liquidity_pubkey,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidFlashBorrow as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_repay_amount_mismatch() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let FlashLoanTest {
+        lending_market,
+        usdc_test_reserve,
+        user_accounts_owner,
+        user_liquidity_pubkey,
+    } = setup(&mut test, FEE_AMOUNT, true);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            flash_borrow_reserve_liquidity(
+                spl_token_lending::id(),
+                FLASH_LOAN_AMOUNT,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+            ),
+            flash_repay_reserve_liquidity(
+                spl_token_lending::id(),
+                FLASH_LOAN_AMOUNT - 1,
+                0,
+                user_liquidity_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                usdc_test_reserve.liquidity_host_pubkey,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+                user_accounts_owner.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidFlashRepay as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_insufficient_repay_balance() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let FlashLoanTest {
+        lending_market,
+        usdc_test_reserve,
+        user_accounts_owner,
+        user_liquidity_pubkey,
+    } = setup(&mut test, FEE_AMOUNT - 1, true);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            flash_borrow_reserve_liquidity(
+                spl_token_lending::id(),
+                FLASH_LOAN_AMOUNT,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+            ),
+            flash_repay_reserve_liquidity(
+                spl_token_lending::id(),
+                FLASH_LOAN_AMOUNT,
+                0,
+                user_liquidity_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                usdc_test_reserve.liquidity_host_pubkey,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+                user_accounts_owner.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::TokenTransferFailed as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_stale_reserve() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let FlashLoanTest {
+        lending_market,
+        usdc_test_reserve,
+        user_accounts_owner,
+        user_liquidity_pubkey,
+    } = setup(&mut test, FEE_AMOUNT, false);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            flash_borrow_reserve_liquidity(
+                spl_token_lending::id(),
+                FLASH_LOAN_AMOUNT,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+            ),
+            flash_repay_reserve_liquidity(
+                spl_token_lending::id(),
+                FLASH_LOAN_AMOUNT,
+                0,
+                user_liquidity_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                usdc_test_reserve.liquidity_host_pubkey,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+                user_accounts_owner.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::ReserveStale as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_reserve_stale_until_repay() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let FlashLoanTest {
+        lending_market,
+        usdc_test_reserve,
+        user_accounts_owner,
+        user_liquidity_pubkey,
+    } = setup(&mut test, FEE_AMOUNT, true);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // The borrowed liquidity cannot be deposited back without refreshing the reserve
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            flash_borrow_reserve_liquidity(
+                spl_token_lending::id(),
+                FLASH_LOAN_AMOUNT,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+            ),
+            deposit_reserve_liquidity(
+                spl_token_lending::id(),
+                FLASH_LOAN_AMOUNT,
+                user_liquidity_pubkey,
+                usdc_test_reserve.user_collateral_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.collateral_mint_pubkey,
+                lending_market.pubkey,
+                user_accounts_owner.pubkey(),
+            ),
+            flash_repay_reserve_liquidity(
+                spl_token_lending::id(),
+                FLASH_LOAN_AMOUNT,
+                0,
+                user_liquidity_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.liquidity_fee_receiver_pubkey,
+                usdc_test_reserve.liquidity_host_pubkey,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+                user_accounts_owner.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::ReserveStale as u32)
+        )
+    );
+}
@@ -194,12 +194,14 @@ pub fn add_obligation(
         obligation.last_update.update_slot(current_slot);
     }
 
-    test.add_packable_account(
-        obligation_pubkey,
+    // obligations holding more reserves than the default are added already expanded
+    let mut account = Account::new(
         u32::MAX as u64,
-        &obligation,
+        Obligation::LEN.max(obligation.packed_len()),
         &spl_token_lending::id(),
     );
+    obligation.pack_into_slice(&mut account.data);
+    test.add_account(obligation_pubkey, account);
 
     TestObligation {
         pubkey: obligation_pubkey,
@@ -1104,8 +1106,45 @@ pub fn add_oracle(
         &format!("{}.bin", product_pubkey),
     );
 
-    // Add Pyth price account after setting the price
-    let filename = &format!("{}.bin", price_pubkey);
+    add_pyth_price(test, price_pubkey, price_pubkey, price);
+
+    TestOracle {
+        product_pubkey,
+        price_pubkey,
+        price,
+    }
+}
+
+/// Adds a copy of the SOL Pyth price account at a new address, for reserves that each need their
+/// own oracle
+pub fn add_sol_oracle_copy(test: &mut ProgramTest) -> TestOracle {
+    let price_pubkey = Pubkey::new_unique();
+    let price = Decimal::from(20u64);
+    add_pyth_price(
+        test,
+        price_pubkey,
+        Pubkey::from_str(SOL_PYTH_PRICE).unwrap(),
+        price,
+    );
+
+    TestOracle {
+        product_pubkey: Pubkey::from_str(SOL_PYTH_PRODUCT).unwrap(),
+        price_pubkey,
+        price,
+    }
+}
+
+/// Adds a Pyth price account loaded from the fixture of `fixture_price_pubkey`, after setting
+/// the price
+fn add_pyth_price(
+    test: &mut ProgramTest,
+    price_pubkey: Pubkey,
+    fixture_price_pubkey: Pubkey,
+    price: Decimal,
+) {
+    let oracle_program_id = read_keypair_file("tests/fixtures/oracle_program_id.json").unwrap();
+
+    let filename = &format!("{}.bin", fixture_price_pubkey);
     let mut pyth_price_data = read_file(find_file(filename).unwrap_or_else(|| {
         panic!("Unable to locate {}", filename);
     }));
@@ -1135,12 +1174,6 @@ pub fn add_oracle(
             rent_epoch: 0,
         },
     );
-
-    TestOracle {
-        product_pubkey,
-        price_pubkey,
-        price,
-    }
 }
 
 pub async fn create_and_mint_to_token_account(
@@ -1,5 +1,5 @@
 import {Buffer} from 'buffer';
-import {struct, u8, blob} from '@solana/buffer-layout';
+import {struct, u8, u32, blob} from '@solana/buffer-layout';
 import type {
   ConfirmOptions,
   Connection,
@@ -13,7 +13,7 @@ import {
   TransactionInstruction,
   sendAndConfirmTransaction,
 } from '@solana/web3.js';
-import {u64, publicKey} from '@solana/buffer-layout-utils';
+import {i64, u64, u128, publicKey} from '@solana/buffer-layout-utils';
 import {loadAccount} from './util/account';
 
 export const TOKEN_SWAP_PROGRAM_ID: PublicKey = new PublicKey(
@@ -47,7 +47,22 @@ export interface RawTokenSwap {
   curveParameters: Uint8Array;
 }
 
-export const TokenSwapLayout = struct<RawTokenSwap>([
+export interface RawTokenSwapV2 extends RawTokenSwap {
+  lastUpdateTimestamp: bigint;
+  priceACumulativeX64: bigint;
+  priceBCumulativeX64: bigint;
+  lastPriceAX64: bigint;
+  lastPriceBX64: bigint;
+  adminAuthorityOption: number;
+  adminAuthority: PublicKey;
+  dynamicFeeMaxFeeNumerator: bigint;
+  dynamicFeeMaxVolatilityBps: bigint;
+  dynamicFeeDecayPeriod: bigint;
+  volatilityBps: bigint;
+  volatilityLastUpdateTimestamp: bigint;
+}
+
+const TOKEN_SWAP_V1_FIELDS = [
   u8('version'),
   u8('isInitialized'),
   u8('bumpSeed'),
@@ -68,6 +83,31 @@ export const TokenSwapLayout = struct<RawTokenSwap>([
   u64('hostFeeDenominator'),
   u8('curveType'),
   blob(32, 'curveParameters'),
+];
+
+/**
+ * Layout of the original version of a token swap, without a price oracle,
+ * an admin authority, or a dynamic fee
+ */
+export const TokenSwapLayoutV1 = struct<RawTokenSwap>(TOKEN_SWAP_V1_FIELDS);
+
+/**
+ * Layout of the latest version of a token swap, used for new swaps
+ */
+export const TokenSwapLayout = struct<RawTokenSwapV2>([
+  ...TOKEN_SWAP_V1_FIELDS,
+  i64('lastUpdateTimestamp'),
+  u128('priceACumulativeX64'),
+  u128('priceBCumulativeX64'),
+  u128('lastPriceAX64'),
+  u128('lastPriceBX64'),
+  u32('adminAuthorityOption'),
+  publicKey('adminAuthority'),
+  u64('dynamicFeeMaxFeeNumerator'),
+  u64('dynamicFeeMaxVolatilityBps'),
+  u64('dynamicFeeDecayPeriod'),
+  u64('volatilityBps'),
+  i64('volatilityLastUpdateTimestamp'),
 ]);
 
 export interface CreateInstruction {
@@ -120,6 +160,7 @@ export const CurveType = Object.freeze({
   ConstantProduct: 0, // Constant product curve, Uniswap-style
   ConstantPrice: 1, // Constant price curve, always X amount of A token for 1 B token, where X is defined at init
   Offset: 2, // Offset curve, like Uniswap, but with an additional offset on the token B side
+  Weighted: 3, // Weighted curve, like Balancer, with configurable weights on each token
 });
 
 /**
@@ -294,7 +335,10 @@ export class TokenSwap {
     payer: Keypair,
   ): Promise<TokenSwap> {
     const data = await loadAccount(connection, address, programId);
-    const tokenSwapData = TokenSwapLayout.decode(data);
+    const tokenSwapData: RawTokenSwap =
+      data.length === TokenSwapLayoutV1.span
+        ? TokenSwapLayoutV1.decode(data)
+        : TokenSwapLayout.decode(data);
     if (!tokenSwapData.isInitialized) {
       throw new Error(`Invalid token swap state`);
     }
@@ -551,7 +595,7 @@ export class TokenSwap {
     );
 
     const keys = [
-      {pubkey: tokenSwap, isSigner: false, isWritable: false},
+      {pubkey: tokenSwap, isSigner: false, isWritable: true},
       {pubkey: authority, isSigner: false, isWritable: false},
       {pubkey: userTransferAuthority, isSigner: true, isWritable: false},
       {pubkey: userSource, isSigner: false, isWritable: true},
@@ -668,7 +712,7 @@ export class TokenSwap {
     );
 
     const keys = [
-      {pubkey: tokenSwap, isSigner: false, isWritable: false},
+      {pubkey: tokenSwap, isSigner: false, isWritable: true},
       {pubkey: authority, isSigner: false, isWritable: false},
       {pubkey: userTransferAuthority, isSigner: true, isWritable: false},
       {pubkey: sourceA, isSigner: false, isWritable: true},
@@ -785,7 +829,7 @@ export class TokenSwap {
     );
 
     const keys = [
-      {pubkey: tokenSwap, isSigner: false, isWritable: false},
+      {pubkey: tokenSwap, isSigner: false, isWritable: true},
       {pubkey: authority, isSigner: false, isWritable: false},
       {pubkey: userTransferAuthority, isSigner: true, isWritable: false},
       {pubkey: poolMint, isSigner: false, isWritable: true},
@@ -886,7 +930,7 @@ export class TokenSwap {
     );
 
     const keys = [
-      {pubkey: tokenSwap, isSigner: false, isWritable: false},
+      {pubkey: tokenSwap, isSigner: false, isWritable: true},
       {pubkey: authority, isSigner: false, isWritable: false},
       {pubkey: userTransferAuthority, isSigner: true, isWritable: false},
       {pubkey: source, isSigner: false, isWritable: true},
@@ -986,7 +1030,7 @@ export class TokenSwap {
     );
 
     const keys = [
-      {pubkey: tokenSwap, isSigner: false, isWritable: false},
+      {pubkey: tokenSwap, isSigner: false, isWritable: true},
       {pubkey: authority, isSigner: false, isWritable: false},
       {pubkey: userTransferAuthority, isSigner: true, isWritable: false},
       {pubkey: poolMint, isSigner: false, isWritable: true},
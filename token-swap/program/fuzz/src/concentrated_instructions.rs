@@ -0,0 +1,337 @@
+#![allow(clippy::integer_arithmetic)]
+use {
+    arbitrary::Arbitrary,
+    honggfuzz::fuzz,
+    solana_program::{program_error::ProgramError, program_pack::Pack},
+    spl_token::error::TokenError,
+    spl_token_swap::{
+        curve::{calculator::TradeDirection, fees::Fees},
+        error::SwapError,
+        instruction::{DecreaseLiquidity, IncreaseLiquidity, OpenPosition, Swap},
+        state::{Position, TICK_DEPOSIT_LAMPORTS},
+    },
+    spl_token_swap_fuzz::{
+        native_account_data::NativeAccountData, native_concentrated_swap::NativeConcentratedSwap,
+        native_token::get_token_balance,
+    },
+    std::collections::HashMap,
+};
+
+#[derive(Debug, Arbitrary, Clone)]
+struct FuzzData {
+    instructions: Vec<FuzzInstruction>,
+}
+
+#[derive(Debug, Arbitrary, Clone)]
+enum FuzzInstruction {
+    OpenPosition {
+        position_id: AccountId,
+        instruction: OpenPosition,
+    },
+    IncreaseLiquidity {
+        position_id: AccountId,
+        token_a_id: AccountId,
+        token_b_id: AccountId,
+        instruction: IncreaseLiquidity,
+    },
+    DecreaseLiquidity {
+        position_id: AccountId,
+        token_a_id: AccountId,
+        token_b_id: AccountId,
+        instruction: DecreaseLiquidity,
+    },
+    Swap {
+        token_a_id: AccountId,
+        token_b_id: AccountId,
+        trade_direction: TradeDirection,
+        instruction: Swap,
+    },
+    ClosePosition {
+        position_id: AccountId,
+    },
+}
+
+/// Use u8 as an account id to simplify the address space and re-use accounts
+/// more often.
+type AccountId = u8;
+
+const TICK_SPACING: u16 = 10;
+/// Square root price of 1, at tick 0
+const INITIAL_SQRT_PRICE_X64: u128 = 1 << 64;
+/// Position boundaries are drawn from this many tick spacings on either side
+/// of the initial price, so that ranges overlap often
+const TICK_SPACINGS_PER_SIDE: i32 = 100;
+/// Upper bound on liquidity added or removed at once, to avoid spending every
+/// run on amounts that no user account can afford
+const MAX_LIQUIDITY_AMOUNT: u128 = 1 << 40;
+
+const INITIAL_USER_TOKEN_A_AMOUNT: u64 = 1_000_000_000;
+const INITIAL_USER_TOKEN_B_AMOUNT: u64 = 1_000_000_000;
+
+fn main() {
+    loop {
+        fuzz!(|fuzz_data: FuzzData| { run_fuzz(fuzz_data) });
+    }
+}
+
+fn run_fuzz(fuzz_data: FuzzData) {
+    let fees = Fees {
+        trade_fee_numerator: 25,
+        trade_fee_denominator: 10000,
+        owner_trade_fee_numerator: 0,
+        owner_trade_fee_denominator: 0,
+        owner_withdraw_fee_numerator: 0,
+        owner_withdraw_fee_denominator: 0,
+        host_fee_numerator: 0,
+        host_fee_denominator: 0,
+    };
+    let mut token_swap = NativeConcentratedSwap::new(fees, TICK_SPACING, INITIAL_SQRT_PRICE_X64);
+
+    // keep track of all accounts, including positions
+    let mut token_a_accounts: HashMap<AccountId, NativeAccountData> = HashMap::new();
+    let mut token_b_accounts: HashMap<AccountId, NativeAccountData> = HashMap::new();
+    let mut position_accounts: HashMap<AccountId, NativeAccountData> = HashMap::new();
+
+    // add all the token and position accounts that will be needed
+    for fuzz_instruction in &fuzz_data.instructions {
+        let (token_a_id, token_b_id, position_id) = match fuzz_instruction.clone() {
+            FuzzInstruction::OpenPosition { position_id, .. } => (None, None, Some(position_id)),
+            FuzzInstruction::IncreaseLiquidity {
+                position_id,
+                token_a_id,
+                token_b_id,
+                ..
+            } => (Some(token_a_id), Some(token_b_id), Some(position_id)),
+            FuzzInstruction::DecreaseLiquidity {
+                position_id,
+                token_a_id,
+                token_b_id,
+                ..
+            } => (Some(token_a_id), Some(token_b_id), Some(position_id)),
+            FuzzInstruction::Swap {
+                token_a_id,
+                token_b_id,
+                ..
+            } => (Some(token_a_id), Some(token_b_id), None),
+            FuzzInstruction::ClosePosition { position_id } => (None, None, Some(position_id)),
+        };
+        if let Some(token_a_id) = token_a_id {
+            token_a_accounts
+                .entry(token_a_id)
+                .or_insert_with(|| token_swap.create_token_a_account(INITIAL_USER_TOKEN_A_AMOUNT));
+        }
+        if let Some(token_b_id) = token_b_id {
+            token_b_accounts
+                .entry(token_b_id)
+                .or_insert_with(|| token_swap.create_token_b_account(INITIAL_USER_TOKEN_B_AMOUNT));
+        }
+        if let Some(position_id) = position_id {
+            position_accounts.entry(position_id).or_insert_with(|| {
+                let mut position_account =
+                    NativeAccountData::new(Position::LEN, spl_token_swap::id());
+                position_account.lamports = 2 * TICK_DEPOSIT_LAMPORTS;
+                position_account.is_signer = true;
+                position_account
+            });
+        }
+    }
+
+    // to ensure that we never create or remove base tokens
+    let before_total_token_a = token_a_accounts.len() as u64 * INITIAL_USER_TOKEN_A_AMOUNT;
+    let before_total_token_b = token_b_accounts.len() as u64 * INITIAL_USER_TOKEN_B_AMOUNT;
+
+    for fuzz_instruction in fuzz_data.instructions {
+        run_fuzz_instruction(
+            fuzz_instruction,
+            &mut token_swap,
+            &mut token_a_accounts,
+            &mut token_b_accounts,
+            &mut position_accounts,
+        );
+    }
+
+    // the active liquidity must always match the positions in range
+    let pool = token_swap.pool_state();
+    let positions = position_accounts
+        .values()
+        .filter_map(|account| Position::unpack(&account.data).ok())
+        .collect::<Vec<_>>();
+    let in_range_liquidity = positions
+        .iter()
+        .filter(|position| {
+            position.tick_lower <= pool.current_tick && pool.current_tick < position.tick_upper
+        })
+        .map(|position| position.liquidity)
+        .sum::<u128>();
+    assert_eq!(pool.liquidity, in_range_liquidity);
+
+    // check total token a and b amounts
+    let after_total_token_a = token_a_accounts
+        .values()
+        .map(get_token_balance)
+        .sum::<u64>()
+        + get_token_balance(&token_swap.token_a_account);
+    assert_eq!(before_total_token_a, after_total_token_a);
+    let after_total_token_b = token_b_accounts
+        .values()
+        .map(get_token_balance)
+        .sum::<u64>()
+        + get_token_balance(&token_swap.token_b_account);
+    assert_eq!(before_total_token_b, after_total_token_b);
+
+    // Final check to make sure that removing all liquidity and closing every
+    // position works
+    let mut withdrawn_token_a_account = token_swap.create_token_a_account(0);
+    let mut withdrawn_token_b_account = token_swap.create_token_b_account(0);
+    for position_account in position_accounts.values_mut() {
+        let position = match Position::unpack(&position_account.data) {
+            Ok(position) => position,
+            Err(_) => continue,
+        };
+        if position.liquidity > 0 || position.tokens_owed_a > 0 || position.tokens_owed_b > 0 {
+            token_swap
+                .decrease_liquidity(
+                    position_account,
+                    &mut withdrawn_token_a_account,
+                    &mut withdrawn_token_b_account,
+                    DecreaseLiquidity {
+                        liquidity_amount: position.liquidity,
+                        minimum_token_a_amount: 0,
+                        minimum_token_b_amount: 0,
+                    },
+                )
+                .unwrap();
+        }
+        token_swap.close_position(position_account).unwrap();
+    }
+
+    let pool = token_swap.pool_state();
+    assert_eq!(pool.liquidity, 0);
+    assert!(pool.ticks.is_empty());
+
+    let after_total_token_a = token_a_accounts
+        .values()
+        .map(get_token_balance)
+        .sum::<u64>()
+        + get_token_balance(&withdrawn_token_a_account)
+        + get_token_balance(&token_swap.token_a_account);
+    assert_eq!(before_total_token_a, after_total_token_a);
+    let after_total_token_b = token_b_accounts
+        .values()
+        .map(get_token_balance)
+        .sum::<u64>()
+        + get_token_balance(&withdrawn_token_b_account)
+        + get_token_balance(&token_swap.token_b_account);
+    assert_eq!(before_total_token_b, after_total_token_b);
+}
+
+fn run_fuzz_instruction(
+    fuzz_instruction: FuzzInstruction,
+    token_swap: &mut NativeConcentratedSwap,
+    token_a_accounts: &mut HashMap<AccountId, NativeAccountData>,
+    token_b_accounts: &mut HashMap<AccountId, NativeAccountData>,
+    position_accounts: &mut HashMap<AccountId, NativeAccountData>,
+) {
+    let result = match fuzz_instruction {
+        FuzzInstruction::OpenPosition {
+            position_id,
+            instruction,
+        } => {
+            let position_account = position_accounts.get_mut(&position_id).unwrap();
+            token_swap.open_position(
+                position_account,
+                OpenPosition {
+                    tick_lower: nearby_tick(instruction.tick_lower),
+                    tick_upper: nearby_tick(instruction.tick_upper),
+                },
+            )
+        }
+        FuzzInstruction::IncreaseLiquidity {
+            position_id,
+            token_a_id,
+            token_b_id,
+            mut instruction,
+        } => {
+            let position_account = position_accounts.get_mut(&position_id).unwrap();
+            let token_a_account = token_a_accounts.get_mut(&token_a_id).unwrap();
+            let token_b_account = token_b_accounts.get_mut(&token_b_id).unwrap();
+            instruction.liquidity_amount %= MAX_LIQUIDITY_AMOUNT;
+            token_swap.increase_liquidity(
+                position_account,
+                token_a_account,
+                token_b_account,
+                instruction,
+            )
+        }
+        FuzzInstruction::DecreaseLiquidity {
+            position_id,
+            token_a_id,
+            token_b_id,
+            mut instruction,
+        } => {
+            let position_account = position_accounts.get_mut(&position_id).unwrap();
+            let token_a_account = token_a_accounts.get_mut(&token_a_id).unwrap();
+            let token_b_account = token_b_accounts.get_mut(&token_b_id).unwrap();
+            instruction.liquidity_amount %= MAX_LIQUIDITY_AMOUNT;
+            token_swap.decrease_liquidity(
+                position_account,
+                token_a_account,
+                token_b_account,
+                instruction,
+            )
+        }
+        FuzzInstruction::Swap {
+            token_a_id,
+            token_b_id,
+            trade_direction,
+            instruction,
+        } => {
+            let token_a_account = token_a_accounts.get_mut(&token_a_id).unwrap();
+            let token_b_account = token_b_accounts.get_mut(&token_b_id).unwrap();
+            match trade_direction {
+                TradeDirection::AtoB => {
+                    token_swap.swap_a_to_b(token_a_account, token_b_account, instruction)
+                }
+                TradeDirection::BtoA => {
+                    token_swap.swap_b_to_a(token_b_account, token_a_account, instruction)
+                }
+            }
+        }
+        FuzzInstruction::ClosePosition { position_id } => {
+            let position_account = position_accounts.get_mut(&position_id).unwrap();
+            token_swap.close_position(position_account)
+        }
+    };
+    result
+        .map_err(|e| {
+            if !(e == SwapError::CalculationFailure.into()
+                || e == SwapError::ConversionFailure.into()
+                || e == SwapError::ExceededSlippage.into()
+                || e == SwapError::ZeroTradingTokens.into()
+                || e == SwapError::AlreadyInUse.into()
+                || e == SwapError::InvalidTickRange.into()
+                || e == SwapError::TickCapacityExceeded.into()
+                || e == SwapError::PositionLiquidityTooLow.into()
+                || e == SwapError::PositionNotEmpty.into()
+                || e == SwapError::InsufficientLiquidity.into()
+                || e == ProgramError::UninitializedAccount
+                || e == TokenError::InsufficientFunds.into())
+            {
+                println!("{:?}", e);
+                Err(e).unwrap()
+            }
+        })
+        .ok();
+}
+
+/// Maps an arbitrary tick onto an initializable tick near the initial price,
+/// leaving some ticks off the spacing to exercise range validation
+fn nearby_tick(tick: i32) -> i32 {
+    let spacing = i32::from(TICK_SPACING);
+    let offset = tick.rem_euclid(2 * TICK_SPACINGS_PER_SIDE * spacing + 1);
+    if offset % 7 == 0 {
+        offset - TICK_SPACINGS_PER_SIDE * spacing
+    } else {
+        (offset / spacing - TICK_SPACINGS_PER_SIDE) * spacing
+    }
+}
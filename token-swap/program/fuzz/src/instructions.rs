@@ -12,6 +12,7 @@ use {
             constant_product::ConstantProductCurve,
             fees::Fees,
             offset::OffsetCurve,
+            weighted::WeightedCurve,
         },
         error::SwapError,
         instruction::{
@@ -33,6 +34,7 @@ use {
 #[derive(Debug, Arbitrary, Clone)]
 struct FuzzData {
     curve_type: CurveType,
+    transfer_hook: bool,
     instructions: Vec<FuzzInstruction>,
 }
 
@@ -111,6 +113,7 @@ fn run_fuzz(fuzz_data: FuzzData) {
         swap_curve.clone(),
         INITIAL_SWAP_TOKEN_A_AMOUNT,
         INITIAL_SWAP_TOKEN_B_AMOUNT,
+        fuzz_data.transfer_hook,
     );
 
     // keep track of all accounts, including swap accounts
@@ -471,6 +474,10 @@ fn get_swap_curve(curve_type: CurveType) -> SwapCurve {
             CurveType::Offset => Arc::new(OffsetCurve {
                 token_b_offset: 100_000_000_000,
             }),
+            CurveType::Weighted => Arc::new(WeightedCurve {
+                token_a_weight: 80,
+                token_b_weight: 20,
+            }),
         },
     }
 }
@@ -1,5 +1,6 @@
 #![allow(clippy::integer_arithmetic)]
 pub mod native_account_data;
+pub mod native_concentrated_swap;
 pub mod native_processor;
 pub mod native_token;
 pub mod native_token_swap;
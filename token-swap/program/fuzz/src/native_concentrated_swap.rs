@@ -0,0 +1,337 @@
+//! Helpers for working with concentrated liquidity pools in a fuzzing environment
+
+use crate::native_account_data::NativeAccountData;
+use crate::native_processor::do_process_instruction;
+use crate::native_token;
+use crate::native_token_swap::create_program_account;
+
+use spl_token_swap::{
+    curve::fees::Fees,
+    instruction::{self, DecreaseLiquidity, IncreaseLiquidity, OpenPosition, Swap},
+    state::{ConcentratedSwapV1, Position, SwapVersion},
+};
+
+use solana_program::{
+    entrypoint::ProgramResult, program_pack::Pack, pubkey::Pubkey, system_program,
+};
+
+pub struct NativeConcentratedSwap {
+    pub user_account: NativeAccountData,
+    pub authority_account: NativeAccountData,
+    pub swap_account: NativeAccountData,
+    pub token_a_account: NativeAccountData,
+    pub token_a_mint_account: NativeAccountData,
+    pub token_b_account: NativeAccountData,
+    pub token_b_mint_account: NativeAccountData,
+    pub token_a_program_account: NativeAccountData,
+    pub token_b_program_account: NativeAccountData,
+}
+
+impl NativeConcentratedSwap {
+    pub fn new(fees: Fees, tick_spacing: u16, sqrt_price_x64: u128) -> Self {
+        let mut user_account = NativeAccountData::new(0, system_program::id());
+        user_account.is_signer = true;
+        let mut swap_account =
+            NativeAccountData::new(SwapVersion::CONCENTRATED_LEN, spl_token_swap::id());
+        swap_account.is_signer = true;
+        let (authority_key, _bump_seed) = Pubkey::find_program_address(
+            &[&swap_account.key.to_bytes()[..]],
+            &spl_token_swap::id(),
+        );
+        let mut authority_account = create_program_account(authority_key);
+        let mut token_a_program_account = create_program_account(spl_token::id());
+        let token_b_program_account = create_program_account(spl_token::id());
+
+        let mut token_a_mint_account = native_token::create_mint(&user_account.key);
+        let mut token_a_account = native_token::create_token_account(
+            &mut token_a_mint_account,
+            &authority_account.key,
+            0,
+        );
+        let mut token_b_mint_account = native_token::create_mint(&user_account.key);
+        let mut token_b_account = native_token::create_token_account(
+            &mut token_b_mint_account,
+            &authority_account.key,
+            0,
+        );
+
+        let init_instruction = instruction::initialize_concentrated(
+            &spl_token_swap::id(),
+            &spl_token::id(),
+            &swap_account.key,
+            &authority_account.key,
+            &token_a_account.key,
+            &token_b_account.key,
+            fees,
+            tick_spacing,
+            sqrt_price_x64,
+        )
+        .unwrap();
+
+        do_process_instruction(
+            init_instruction,
+            &[
+                swap_account.as_account_info(),
+                authority_account.as_account_info(),
+                token_a_account.as_account_info(),
+                token_b_account.as_account_info(),
+                token_a_program_account.as_account_info(),
+            ],
+        )
+        .unwrap();
+
+        Self {
+            user_account,
+            authority_account,
+            swap_account,
+            token_a_account,
+            token_a_mint_account,
+            token_b_account,
+            token_b_mint_account,
+            token_a_program_account,
+            token_b_program_account,
+        }
+    }
+
+    pub fn create_token_a_account(&mut self, amount: u64) -> NativeAccountData {
+        native_token::create_token_account(
+            &mut self.token_a_mint_account,
+            &self.user_account.key,
+            amount,
+        )
+    }
+
+    pub fn create_token_b_account(&mut self, amount: u64) -> NativeAccountData {
+        native_token::create_token_account(
+            &mut self.token_b_mint_account,
+            &self.user_account.key,
+            amount,
+        )
+    }
+
+    pub fn pool_state(&self) -> ConcentratedSwapV1 {
+        SwapVersion::unpack_concentrated(&self.swap_account.data).unwrap()
+    }
+
+    pub fn position_state(position_account: &NativeAccountData) -> Position {
+        Position::unpack(&position_account.data).unwrap()
+    }
+
+    pub fn open_position(
+        &mut self,
+        position_account: &mut NativeAccountData,
+        instruction: OpenPosition,
+    ) -> ProgramResult {
+        let open_instruction = instruction::open_position(
+            &spl_token_swap::id(),
+            &self.swap_account.key,
+            &position_account.key,
+            &self.user_account.key,
+            instruction,
+        )
+        .unwrap();
+
+        do_process_instruction(
+            open_instruction,
+            &[
+                self.swap_account.as_account_info(),
+                position_account.as_account_info(),
+                self.user_account.as_account_info(),
+            ],
+        )
+    }
+
+    pub fn increase_liquidity(
+        &mut self,
+        position_account: &mut NativeAccountData,
+        token_a_account: &mut NativeAccountData,
+        token_b_account: &mut NativeAccountData,
+        instruction: IncreaseLiquidity,
+    ) -> ProgramResult {
+        // the owner of the user token accounts also transfers them
+        let mut user_transfer_account = self.user_account.clone();
+        let increase_instruction = instruction::increase_liquidity(
+            &spl_token_swap::id(),
+            &spl_token::id(),
+            &spl_token::id(),
+            &self.swap_account.key,
+            &position_account.key,
+            &self.user_account.key,
+            &self.user_account.key,
+            &token_a_account.key,
+            &token_b_account.key,
+            &self.token_a_account.key,
+            &self.token_b_account.key,
+            &self.token_a_mint_account.key,
+            &self.token_b_mint_account.key,
+            instruction,
+        )
+        .unwrap();
+
+        do_process_instruction(
+            increase_instruction,
+            &[
+                self.swap_account.as_account_info(),
+                position_account.as_account_info(),
+                self.user_account.as_account_info(),
+                user_transfer_account.as_account_info(),
+                token_a_account.as_account_info(),
+                token_b_account.as_account_info(),
+                self.token_a_account.as_account_info(),
+                self.token_b_account.as_account_info(),
+                self.token_a_mint_account.as_account_info(),
+                self.token_b_mint_account.as_account_info(),
+                self.token_a_program_account.as_account_info(),
+                self.token_b_program_account.as_account_info(),
+            ],
+        )
+    }
+
+    pub fn decrease_liquidity(
+        &mut self,
+        position_account: &mut NativeAccountData,
+        token_a_account: &mut NativeAccountData,
+        token_b_account: &mut NativeAccountData,
+        instruction: DecreaseLiquidity,
+    ) -> ProgramResult {
+        let decrease_instruction = instruction::decrease_liquidity(
+            &spl_token_swap::id(),
+            &spl_token::id(),
+            &spl_token::id(),
+            &self.swap_account.key,
+            &self.authority_account.key,
+            &position_account.key,
+            &self.user_account.key,
+            &self.token_a_account.key,
+            &self.token_b_account.key,
+            &token_a_account.key,
+            &token_b_account.key,
+            &self.token_a_mint_account.key,
+            &self.token_b_mint_account.key,
+            instruction,
+        )
+        .unwrap();
+
+        do_process_instruction(
+            decrease_instruction,
+            &[
+                self.swap_account.as_account_info(),
+                self.authority_account.as_account_info(),
+                position_account.as_account_info(),
+                self.user_account.as_account_info(),
+                self.token_a_account.as_account_info(),
+                self.token_b_account.as_account_info(),
+                token_a_account.as_account_info(),
+                token_b_account.as_account_info(),
+                self.token_a_mint_account.as_account_info(),
+                self.token_b_mint_account.as_account_info(),
+                self.token_a_program_account.as_account_info(),
+                self.token_b_program_account.as_account_info(),
+            ],
+        )
+    }
+
+    pub fn swap_a_to_b(
+        &mut self,
+        token_a_account: &mut NativeAccountData,
+        token_b_account: &mut NativeAccountData,
+        instruction: Swap,
+    ) -> ProgramResult {
+        let swap_instruction = instruction::concentrated_swap(
+            &spl_token_swap::id(),
+            &spl_token::id(),
+            &spl_token::id(),
+            &self.swap_account.key,
+            &self.authority_account.key,
+            &self.user_account.key,
+            &token_a_account.key,
+            &self.token_a_account.key,
+            &self.token_b_account.key,
+            &token_b_account.key,
+            &self.token_a_mint_account.key,
+            &self.token_b_mint_account.key,
+            instruction,
+        )
+        .unwrap();
+
+        do_process_instruction(
+            swap_instruction,
+            &[
+                self.swap_account.as_account_info(),
+                self.authority_account.as_account_info(),
+                self.user_account.as_account_info(),
+                token_a_account.as_account_info(),
+                self.token_a_account.as_account_info(),
+                self.token_b_account.as_account_info(),
+                token_b_account.as_account_info(),
+                self.token_a_mint_account.as_account_info(),
+                self.token_b_mint_account.as_account_info(),
+                self.token_a_program_account.as_account_info(),
+                self.token_b_program_account.as_account_info(),
+            ],
+        )
+    }
+
+    pub fn swap_b_to_a(
+        &mut self,
+        token_b_account: &mut NativeAccountData,
+        token_a_account: &mut NativeAccountData,
+        instruction: Swap,
+    ) -> ProgramResult {
+        let swap_instruction = instruction::concentrated_swap(
+            &spl_token_swap::id(),
+            &spl_token::id(),
+            &spl_token::id(),
+            &self.swap_account.key,
+            &self.authority_account.key,
+            &self.user_account.key,
+            &token_b_account.key,
+            &self.token_b_account.key,
+            &self.token_a_account.key,
+            &token_a_account.key,
+            &self.token_b_mint_account.key,
+            &self.token_a_mint_account.key,
+            instruction,
+        )
+        .unwrap();
+
+        do_process_instruction(
+            swap_instruction,
+            &[
+                self.swap_account.as_account_info(),
+                self.authority_account.as_account_info(),
+                self.user_account.as_account_info(),
+                token_b_account.as_account_info(),
+                self.token_b_account.as_account_info(),
+                self.token_a_account.as_account_info(),
+                token_a_account.as_account_info(),
+                self.token_b_mint_account.as_account_info(),
+                self.token_a_mint_account.as_account_info(),
+                self.token_a_program_account.as_account_info(),
+                self.token_b_program_account.as_account_info(),
+            ],
+        )
+    }
+
+    pub fn close_position(&mut self, position_account: &mut NativeAccountData) -> ProgramResult {
+        let mut destination_account = NativeAccountData::new(0, system_program::id());
+        let close_instruction = instruction::close_position(
+            &spl_token_swap::id(),
+            &self.swap_account.key,
+            &position_account.key,
+            &self.user_account.key,
+            &destination_account.key,
+        )
+        .unwrap();
+
+        do_process_instruction(
+            close_instruction,
+            &[
+                self.swap_account.as_account_info(),
+                position_account.as_account_info(),
+                self.user_account.as_account_info(),
+                destination_account.as_account_info(),
+            ],
+        )
+    }
+}
@@ -1,10 +1,33 @@
 use crate::native_account_data::NativeAccountData;
 
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction,
-    program_error::ProgramError, program_stubs, pubkey::Pubkey,
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{ProgramResult, SUCCESS},
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_stubs,
+    pubkey::Pubkey,
+    rent::Rent,
 };
 
+/// Program id of the transfer hook program used by hooked mints
+pub const TRANSFER_HOOK_PROGRAM_ID: Pubkey = Pubkey::new_from_array([3u8; 32]);
+
+fn process_program_instruction(
+    program_id: &Pubkey,
+    account_infos: &[AccountInfo],
+    input: &[u8],
+) -> ProgramResult {
+    if *program_id == spl_token_2022::id() {
+        spl_token_2022::processor::Processor::process(program_id, account_infos, input)
+    } else if *program_id == TRANSFER_HOOK_PROGRAM_ID {
+        spl_transfer_hook_example::processor::process(program_id, account_infos, input)
+    } else {
+        spl_token::processor::Processor::process(program_id, account_infos, input)
+    }
+}
+
 struct TestSyscallStubs {}
 impl program_stubs::SyscallStubs for TestSyscallStubs {
     fn sol_invoke_signed(
@@ -16,7 +39,11 @@ impl program_stubs::SyscallStubs for TestSyscallStubs {
         let mut new_account_infos = vec![];
 
         // mimic check for token program in accounts
-        if !account_infos.iter().any(|x| *x.key == spl_token::id()) {
+        if instruction.program_id != TRANSFER_HOOK_PROGRAM_ID
+            && !account_infos
+                .iter()
+                .any(|x| *x.key == spl_token::id() || *x.key == spl_token_2022::id())
+        {
             return Err(ProgramError::InvalidAccountData);
         }
 
@@ -36,12 +63,27 @@ impl program_stubs::SyscallStubs for TestSyscallStubs {
             }
         }
 
-        spl_token::processor::Processor::process(
+        process_program_instruction(
             &instruction.program_id,
             &new_account_infos,
             &instruction.data,
         )
     }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe {
+            *(var_addr as *mut _ as *mut Clock) = Clock::default();
+        }
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        // accounts are created without lamports, so rent is free
+        unsafe {
+            *(var_addr as *mut _ as *mut Rent) = Rent::free();
+        }
+        SUCCESS
+    }
 }
 
 fn test_syscall_stubs() {
@@ -73,11 +115,7 @@ pub fn do_process_instruction(instruction: Instruction, accounts: &[AccountInfo]
             &instruction.data,
         )
     } else {
-        spl_token::processor::Processor::process(
-            &instruction.program_id,
-            &account_infos,
-            &instruction.data,
-        )
+        process_program_instruction(&instruction.program_id, &account_infos, &instruction.data)
     };
 
     if res.is_ok() {
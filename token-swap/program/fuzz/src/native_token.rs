@@ -1,6 +1,13 @@
 use crate::native_account_data::NativeAccountData;
+use crate::native_processor::do_process_instruction;
 
-use spl_token::state::{Account as TokenAccount, AccountState as TokenAccountState, Mint};
+use spl_token_2022::{
+    extension::{
+        transfer_hook, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+        StateWithExtensionsMut,
+    },
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
 
 use solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey};
 
@@ -15,11 +22,53 @@ pub fn create_mint(owner: &Pubkey) -> NativeAccountData {
     account_data
 }
 
+pub fn create_token_2022_mint(
+    owner: &Pubkey,
+    transfer_hook_program_id: Option<&Pubkey>,
+) -> NativeAccountData {
+    let extension_types = if transfer_hook_program_id.is_some() {
+        vec![ExtensionType::TransferHook]
+    } else {
+        vec![]
+    };
+    let space = ExtensionType::try_calculate_account_len::<Mint>(&extension_types).unwrap();
+    let mut account_data = NativeAccountData::new(space, spl_token_2022::id());
+    if let Some(transfer_hook_program_id) = transfer_hook_program_id {
+        do_process_instruction(
+            transfer_hook::instruction::initialize(
+                &spl_token_2022::id(),
+                &account_data.key,
+                None,
+                Some(*transfer_hook_program_id),
+            )
+            .unwrap(),
+            &[account_data.as_account_info()],
+        )
+        .unwrap();
+    }
+    do_process_instruction(
+        spl_token_2022::instruction::initialize_mint2(
+            &spl_token_2022::id(),
+            &account_data.key,
+            owner,
+            None,
+            0,
+        )
+        .unwrap(),
+        &[account_data.as_account_info()],
+    )
+    .unwrap();
+    account_data
+}
+
 pub fn create_token_account(
     mint_account: &mut NativeAccountData,
     owner: &Pubkey,
     amount: u64,
 ) -> NativeAccountData {
+    if mint_account.program_id == spl_token_2022::id() {
+        return create_token_2022_account(mint_account, owner, amount);
+    }
     let mut mint = Mint::unpack(&mint_account.data).unwrap();
     let mut account_data = NativeAccountData::new(TokenAccount::LEN, spl_token::id());
     let account = TokenAccount {
@@ -35,9 +84,45 @@ pub fn create_token_account(
     account_data
 }
 
+fn create_token_2022_account(
+    mint_account: &mut NativeAccountData,
+    owner: &Pubkey,
+    amount: u64,
+) -> NativeAccountData {
+    let extension_types = {
+        let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+        ExtensionType::get_required_init_account_extensions(&mint.get_extension_types().unwrap())
+    };
+    let space = ExtensionType::try_calculate_account_len::<TokenAccount>(&extension_types).unwrap();
+    let mut account_data = NativeAccountData::new(space, spl_token_2022::id());
+    do_process_instruction(
+        spl_token_2022::instruction::initialize_account3(
+            &spl_token_2022::id(),
+            &account_data.key,
+            &mint_account.key,
+            owner,
+        )
+        .unwrap(),
+        &[
+            account_data.as_account_info(),
+            mint_account.as_account_info(),
+        ],
+    )
+    .unwrap();
+
+    let mut mint = StateWithExtensionsMut::<Mint>::unpack(&mut mint_account.data).unwrap();
+    mint.base.supply += amount;
+    mint.pack_base();
+    let mut account =
+        StateWithExtensionsMut::<TokenAccount>::unpack(&mut account_data.data).unwrap();
+    account.base.amount = amount;
+    account.pack_base();
+    account_data
+}
+
 pub fn get_token_balance(account_data: &NativeAccountData) -> u64 {
-    let account = TokenAccount::unpack(&account_data.data).unwrap();
-    account.amount
+    let account = StateWithExtensions::<TokenAccount>::unpack(&account_data.data).unwrap();
+    account.base.amount
 }
 
 pub fn transfer(
@@ -45,11 +130,11 @@ pub fn transfer(
     to_account: &mut NativeAccountData,
     amount: u64,
 ) {
-    let mut from = TokenAccount::unpack(&from_account.data).unwrap();
-    let mut to = TokenAccount::unpack(&to_account.data).unwrap();
-    assert_eq!(from.mint, to.mint);
-    from.amount -= amount;
-    to.amount += amount;
-    TokenAccount::pack(from, &mut from_account.data[..]).unwrap();
-    TokenAccount::pack(to, &mut to_account.data[..]).unwrap();
+    let mut from = StateWithExtensionsMut::<TokenAccount>::unpack(&mut from_account.data).unwrap();
+    let mut to = StateWithExtensionsMut::<TokenAccount>::unpack(&mut to_account.data).unwrap();
+    assert_eq!(from.base.mint, to.base.mint);
+    from.base.amount -= amount;
+    to.base.amount += amount;
+    from.pack_base();
+    to.pack_base();
 }
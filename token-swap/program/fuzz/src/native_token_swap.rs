@@ -1,7 +1,7 @@
 //! Helpers for working with swaps in a fuzzing environment
 
 use crate::native_account_data::NativeAccountData;
-use crate::native_processor::do_process_instruction;
+use crate::native_processor::{do_process_instruction, TRANSFER_HOOK_PROGRAM_ID};
 use crate::native_token;
 
 use spl_token_swap::{
@@ -13,9 +13,19 @@ use spl_token_swap::{
     state::SwapVersion,
 };
 
-use spl_token::instruction::approve;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+use spl_token_2022::instruction::approve;
+use spl_transfer_hook_interface::{
+    get_extra_account_metas_address, instruction::ExecuteInstruction,
+};
 
-use solana_program::{bpf_loader, entrypoint::ProgramResult, pubkey::Pubkey, system_program};
+use solana_program::{
+    bpf_loader,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
 
 pub struct NativeTokenSwap {
     pub user_account: NativeAccountData,
@@ -34,6 +44,10 @@ pub struct NativeTokenSwap {
     pub pool_token_program_account: NativeAccountData,
     pub token_a_program_account: NativeAccountData,
     pub token_b_program_account: NativeAccountData,
+    /// Validation account, hook program and extra account needed to transfer
+    /// token A, empty if token A has no transfer hook
+    pub transfer_hook_accounts: Vec<NativeAccountData>,
+    pub transfer_hook_account_metas: Vec<AccountMeta>,
 }
 
 pub fn create_program_account(program_id: Pubkey) -> NativeAccountData {
@@ -42,12 +56,34 @@ pub fn create_program_account(program_id: Pubkey) -> NativeAccountData {
     account_data
 }
 
+fn create_transfer_hook_accounts(mint_key: &Pubkey) -> Vec<NativeAccountData> {
+    let extra_account = NativeAccountData::new(0, system_program::id());
+    let extra_account_metas =
+        [ExtraAccountMeta::new_with_pubkey(&extra_account.key, false, false).unwrap()];
+    let mut validation_account = NativeAccountData::new(
+        ExtraAccountMetaList::size_of(extra_account_metas.len()).unwrap(),
+        TRANSFER_HOOK_PROGRAM_ID,
+    );
+    validation_account.key = get_extra_account_metas_address(mint_key, &TRANSFER_HOOK_PROGRAM_ID);
+    ExtraAccountMetaList::init::<ExecuteInstruction>(
+        &mut validation_account.data,
+        &extra_account_metas,
+    )
+    .unwrap();
+    vec![
+        validation_account,
+        create_program_account(TRANSFER_HOOK_PROGRAM_ID),
+        extra_account,
+    ]
+}
+
 impl NativeTokenSwap {
     pub fn new(
         fees: Fees,
         swap_curve: SwapCurve,
         token_a_amount: u64,
         token_b_amount: u64,
+        transfer_hook: bool,
     ) -> Self {
         let mut user_account = NativeAccountData::new(0, system_program::id());
         user_account.is_signer = true;
@@ -58,31 +94,73 @@ impl NativeTokenSwap {
             &spl_token_swap::id(),
         );
         let mut authority_account = create_program_account(authority_key);
-        let mut pool_token_program_account = create_program_account(spl_token::id());
-        let token_a_program_account = create_program_account(spl_token::id());
-        let token_b_program_account = create_program_account(spl_token::id());
+        // the pool is initialized with a single token program, so a hooked
+        // token A puts every mint under Token-2022
+        let token_program_id = if transfer_hook {
+            spl_token_2022::id()
+        } else {
+            spl_token::id()
+        };
+        let create_mint = |owner: &Pubkey, transfer_hook_program_id: Option<&Pubkey>| {
+            if transfer_hook {
+                native_token::create_token_2022_mint(owner, transfer_hook_program_id)
+            } else {
+                native_token::create_mint(owner)
+            }
+        };
+        let mut pool_token_program_account = create_program_account(token_program_id);
+        let token_a_program_account = create_program_account(token_program_id);
+        let token_b_program_account = create_program_account(token_program_id);
 
-        let mut pool_mint_account = native_token::create_mint(&authority_account.key);
+        let mut pool_mint_account = create_mint(&authority_account.key, None);
         let mut pool_token_account =
             native_token::create_token_account(&mut pool_mint_account, &user_account.key, 0);
         let mut pool_fee_account =
             native_token::create_token_account(&mut pool_mint_account, &user_account.key, 0);
-        let mut token_a_mint_account = native_token::create_mint(&user_account.key);
+        let mut token_a_mint_account =
+            create_mint(&user_account.key, Some(&TRANSFER_HOOK_PROGRAM_ID));
         let mut token_a_account = native_token::create_token_account(
             &mut token_a_mint_account,
             &authority_account.key,
             token_a_amount,
         );
-        let mut token_b_mint_account = native_token::create_mint(&user_account.key);
+        let mut token_b_mint_account = create_mint(&user_account.key, None);
         let mut token_b_account = native_token::create_token_account(
             &mut token_b_mint_account,
             &authority_account.key,
             token_b_amount,
         );
 
+        let transfer_hook_accounts = if transfer_hook {
+            create_transfer_hook_accounts(&token_a_mint_account.key)
+        } else {
+            vec![]
+        };
+        let transfer_hook_account_metas = match transfer_hook_accounts.first() {
+            Some(validation_account) => {
+                // the extra account is fixed, so every token A transfer
+                // resolves to the same accounts
+                let mut hook_instruction =
+                    Instruction::new_with_bytes(spl_token_swap::id(), &[], vec![]);
+                instruction::add_transfer_hook_accounts(
+                    &mut hook_instruction,
+                    &TRANSFER_HOOK_PROGRAM_ID,
+                    &token_a_account.key,
+                    &token_a_mint_account.key,
+                    &token_a_account.key,
+                    &authority_account.key,
+                    0,
+                    &validation_account.data,
+                )
+                .unwrap();
+                hook_instruction.accounts
+            }
+            None => vec![],
+        };
+
         let init_instruction = instruction::initialize(
             &spl_token_swap::id(),
-            &spl_token::id(),
+            &token_program_id,
             &swap_account.key,
             &authority_account.key,
             &token_a_account.key,
@@ -90,6 +168,7 @@ impl NativeTokenSwap {
             &pool_mint_account.key,
             &pool_fee_account.key,
             &pool_token_account.key,
+            None,
             fees.clone(),
             swap_curve.clone(),
         )
@@ -127,6 +206,8 @@ impl NativeTokenSwap {
             pool_token_program_account,
             token_a_program_account,
             token_b_program_account,
+            transfer_hook_accounts,
+            transfer_hook_account_metas,
         }
     }
 
@@ -175,11 +256,11 @@ impl NativeTokenSwap {
             ],
         )
         .unwrap();
-        let swap_instruction = instruction::swap(
+        let mut swap_instruction = instruction::swap(
             &spl_token_swap::id(),
-            &spl_token::id(),
-            &spl_token::id(),
-            &spl_token::id(),
+            &self.token_a_program_account.key,
+            &self.token_b_program_account.key,
+            &self.pool_token_program_account.key,
             &self.swap_account.key,
             &self.authority_account.key,
             &user_transfer_account.key,
@@ -195,27 +276,33 @@ impl NativeTokenSwap {
             instruction,
         )
         .unwrap();
-
-        do_process_instruction(
-            swap_instruction,
-            &[
-                self.swap_account.as_account_info(),
-                self.authority_account.as_account_info(),
-                user_transfer_account.as_account_info(),
-                token_a_account.as_account_info(),
-                self.token_a_account.as_account_info(),
-                self.token_b_account.as_account_info(),
-                token_b_account.as_account_info(),
-                self.pool_mint_account.as_account_info(),
-                self.pool_fee_account.as_account_info(),
-                self.token_a_mint_account.as_account_info(),
-                self.token_b_mint_account.as_account_info(),
-                self.token_a_program_account.as_account_info(),
-                self.token_b_program_account.as_account_info(),
-                self.pool_token_program_account.as_account_info(),
-                self.pool_token_account.as_account_info(),
-            ],
-        )
+        swap_instruction
+            .accounts
+            .extend(self.transfer_hook_account_metas.iter().cloned());
+
+        let mut account_infos = vec![
+            self.swap_account.as_account_info(),
+            self.authority_account.as_account_info(),
+            user_transfer_account.as_account_info(),
+            token_a_account.as_account_info(),
+            self.token_a_account.as_account_info(),
+            self.token_b_account.as_account_info(),
+            token_b_account.as_account_info(),
+            self.pool_mint_account.as_account_info(),
+            self.pool_fee_account.as_account_info(),
+            self.token_a_mint_account.as_account_info(),
+            self.token_b_mint_account.as_account_info(),
+            self.token_a_program_account.as_account_info(),
+            self.token_b_program_account.as_account_info(),
+            self.pool_token_program_account.as_account_info(),
+            self.pool_token_account.as_account_info(),
+        ];
+        account_infos.extend(
+            self.transfer_hook_accounts
+                .iter_mut()
+                .map(NativeAccountData::as_account_info),
+        );
+        do_process_instruction(swap_instruction, &account_infos)
     }
 
     pub fn swap_b_to_a(
@@ -244,11 +331,11 @@ impl NativeTokenSwap {
         )
         .unwrap();
 
-        let swap_instruction = instruction::swap(
+        let mut swap_instruction = instruction::swap(
             &spl_token_swap::id(),
-            &spl_token::id(),
-            &spl_token::id(),
-            &spl_token::id(),
+            &self.token_b_program_account.key,
+            &self.token_a_program_account.key,
+            &self.pool_token_program_account.key,
             &self.swap_account.key,
             &self.authority_account.key,
             &user_transfer_account.key,
@@ -264,27 +351,33 @@ impl NativeTokenSwap {
             instruction,
         )
         .unwrap();
-
-        do_process_instruction(
-            swap_instruction,
-            &[
-                self.swap_account.as_account_info(),
-                self.authority_account.as_account_info(),
-                user_transfer_account.as_account_info(),
-                token_b_account.as_account_info(),
-                self.token_b_account.as_account_info(),
-                self.token_a_account.as_account_info(),
-                token_a_account.as_account_info(),
-                self.pool_mint_account.as_account_info(),
-                self.pool_fee_account.as_account_info(),
-                self.token_b_mint_account.as_account_info(),
-                self.token_a_mint_account.as_account_info(),
-                self.token_b_program_account.as_account_info(),
-                self.token_a_program_account.as_account_info(),
-                self.pool_token_program_account.as_account_info(),
-                self.pool_token_account.as_account_info(),
-            ],
-        )
+        swap_instruction
+            .accounts
+            .extend(self.transfer_hook_account_metas.iter().cloned());
+
+        let mut account_infos = vec![
+            self.swap_account.as_account_info(),
+            self.authority_account.as_account_info(),
+            user_transfer_account.as_account_info(),
+            token_b_account.as_account_info(),
+            self.token_b_account.as_account_info(),
+            self.token_a_account.as_account_info(),
+            token_a_account.as_account_info(),
+            self.pool_mint_account.as_account_info(),
+            self.pool_fee_account.as_account_info(),
+            self.token_b_mint_account.as_account_info(),
+            self.token_a_mint_account.as_account_info(),
+            self.token_b_program_account.as_account_info(),
+            self.token_a_program_account.as_account_info(),
+            self.pool_token_program_account.as_account_info(),
+            self.pool_token_account.as_account_info(),
+        ];
+        account_infos.extend(
+            self.transfer_hook_accounts
+                .iter_mut()
+                .map(NativeAccountData::as_account_info),
+        );
+        do_process_instruction(swap_instruction, &account_infos)
     }
 
     pub fn deposit_all_token_types(
@@ -338,11 +431,11 @@ impl NativeTokenSwap {
             instruction.pool_token_amount = 2;
         }
 
-        let deposit_instruction = instruction::deposit_all_token_types(
+        let mut deposit_instruction = instruction::deposit_all_token_types(
             &spl_token_swap::id(),
-            &spl_token::id(),
-            &spl_token::id(),
-            &spl_token::id(),
+            &self.token_a_program_account.key,
+            &self.token_b_program_account.key,
+            &self.pool_token_program_account.key,
             &self.swap_account.key,
             &self.authority_account.key,
             &user_transfer_account.key,
@@ -357,26 +450,32 @@ impl NativeTokenSwap {
             instruction,
         )
         .unwrap();
-
-        do_process_instruction(
-            deposit_instruction,
-            &[
-                self.swap_account.as_account_info(),
-                self.authority_account.as_account_info(),
-                user_transfer_account.as_account_info(),
-                token_a_account.as_account_info(),
-                token_b_account.as_account_info(),
-                self.token_a_account.as_account_info(),
-                self.token_b_account.as_account_info(),
-                self.pool_mint_account.as_account_info(),
-                pool_account.as_account_info(),
-                self.token_a_mint_account.as_account_info(),
-                self.token_b_mint_account.as_account_info(),
-                self.token_a_program_account.as_account_info(),
-                self.token_b_program_account.as_account_info(),
-                self.pool_token_program_account.as_account_info(),
-            ],
-        )
+        deposit_instruction
+            .accounts
+            .extend(self.transfer_hook_account_metas.iter().cloned());
+
+        let mut account_infos = vec![
+            self.swap_account.as_account_info(),
+            self.authority_account.as_account_info(),
+            user_transfer_account.as_account_info(),
+            token_a_account.as_account_info(),
+            token_b_account.as_account_info(),
+            self.token_a_account.as_account_info(),
+            self.token_b_account.as_account_info(),
+            self.pool_mint_account.as_account_info(),
+            pool_account.as_account_info(),
+            self.token_a_mint_account.as_account_info(),
+            self.token_b_mint_account.as_account_info(),
+            self.token_a_program_account.as_account_info(),
+            self.token_b_program_account.as_account_info(),
+            self.pool_token_program_account.as_account_info(),
+        ];
+        account_infos.extend(
+            self.transfer_hook_accounts
+                .iter_mut()
+                .map(NativeAccountData::as_account_info),
+        );
+        do_process_instruction(deposit_instruction, &account_infos)
     }
 
     pub fn withdraw_all_token_types(
@@ -412,11 +511,11 @@ impl NativeTokenSwap {
         )
         .unwrap();
 
-        let withdraw_instruction = instruction::withdraw_all_token_types(
+        let mut withdraw_instruction = instruction::withdraw_all_token_types(
             &spl_token_swap::id(),
-            &spl_token::id(),
-            &spl_token::id(),
-            &spl_token::id(),
+            &self.pool_token_program_account.key,
+            &self.token_a_program_account.key,
+            &self.token_b_program_account.key,
             &self.swap_account.key,
             &self.authority_account.key,
             &user_transfer_account.key,
@@ -432,27 +531,33 @@ impl NativeTokenSwap {
             instruction,
         )
         .unwrap();
-
-        do_process_instruction(
-            withdraw_instruction,
-            &[
-                self.swap_account.as_account_info(),
-                self.authority_account.as_account_info(),
-                user_transfer_account.as_account_info(),
-                self.pool_mint_account.as_account_info(),
-                pool_account.as_account_info(),
-                self.token_a_account.as_account_info(),
-                self.token_b_account.as_account_info(),
-                token_a_account.as_account_info(),
-                token_b_account.as_account_info(),
-                self.pool_fee_account.as_account_info(),
-                self.token_a_mint_account.as_account_info(),
-                self.token_b_mint_account.as_account_info(),
-                self.pool_token_program_account.as_account_info(),
-                self.token_a_program_account.as_account_info(),
-                self.token_b_program_account.as_account_info(),
-            ],
-        )
+        withdraw_instruction
+            .accounts
+            .extend(self.transfer_hook_account_metas.iter().cloned());
+
+        let mut account_infos = vec![
+            self.swap_account.as_account_info(),
+            self.authority_account.as_account_info(),
+            user_transfer_account.as_account_info(),
+            self.pool_mint_account.as_account_info(),
+            pool_account.as_account_info(),
+            self.token_a_account.as_account_info(),
+            self.token_b_account.as_account_info(),
+            token_a_account.as_account_info(),
+            token_b_account.as_account_info(),
+            self.pool_fee_account.as_account_info(),
+            self.token_a_mint_account.as_account_info(),
+            self.token_b_mint_account.as_account_info(),
+            self.pool_token_program_account.as_account_info(),
+            self.token_a_program_account.as_account_info(),
+            self.token_b_program_account.as_account_info(),
+        ];
+        account_infos.extend(
+            self.transfer_hook_accounts
+                .iter_mut()
+                .map(NativeAccountData::as_account_info),
+        );
+        do_process_instruction(withdraw_instruction, &account_infos)
     }
 
     pub fn deposit_single_token_type_exact_amount_in(
@@ -497,10 +602,10 @@ impl NativeTokenSwap {
             TradeDirection::BtoA => &mut self.token_b_mint_account,
         };
 
-        let deposit_instruction = instruction::deposit_single_token_type_exact_amount_in(
+        let mut deposit_instruction = instruction::deposit_single_token_type_exact_amount_in(
             &spl_token_swap::id(),
-            &spl_token::id(),
-            &spl_token::id(),
+            &source_token_program.key,
+            &self.pool_token_program_account.key,
             &self.swap_account.key,
             &self.authority_account.key,
             &user_transfer_account.key,
@@ -513,23 +618,29 @@ impl NativeTokenSwap {
             instruction,
         )
         .unwrap();
-
-        do_process_instruction(
-            deposit_instruction,
-            &[
-                self.swap_account.as_account_info(),
-                self.authority_account.as_account_info(),
-                user_transfer_account.as_account_info(),
-                source_token_account.as_account_info(),
-                self.token_a_account.as_account_info(),
-                self.token_b_account.as_account_info(),
-                self.pool_mint_account.as_account_info(),
-                pool_account.as_account_info(),
-                source_token_mint_account.as_account_info(),
-                self.token_a_program_account.as_account_info(),
-                self.pool_token_program_account.as_account_info(),
-            ],
-        )
+        deposit_instruction
+            .accounts
+            .extend(self.transfer_hook_account_metas.iter().cloned());
+
+        let mut account_infos = vec![
+            self.swap_account.as_account_info(),
+            self.authority_account.as_account_info(),
+            user_transfer_account.as_account_info(),
+            source_token_account.as_account_info(),
+            self.token_a_account.as_account_info(),
+            self.token_b_account.as_account_info(),
+            self.pool_mint_account.as_account_info(),
+            pool_account.as_account_info(),
+            source_token_mint_account.as_account_info(),
+            self.token_a_program_account.as_account_info(),
+            self.pool_token_program_account.as_account_info(),
+        ];
+        account_infos.extend(
+            self.transfer_hook_accounts
+                .iter_mut()
+                .map(NativeAccountData::as_account_info),
+        );
+        do_process_instruction(deposit_instruction, &account_infos)
     }
 
     pub fn withdraw_single_token_type_exact_amount_out(
@@ -573,10 +684,10 @@ impl NativeTokenSwap {
             TradeDirection::AtoB => &mut self.token_a_mint_account,
             TradeDirection::BtoA => &mut self.token_b_mint_account,
         };
-        let withdraw_instruction = instruction::withdraw_single_token_type_exact_amount_out(
+        let mut withdraw_instruction = instruction::withdraw_single_token_type_exact_amount_out(
             &spl_token_swap::id(),
-            &spl_token::id(),
-            &spl_token::id(),
+            &self.pool_token_program_account.key,
+            &destination_token_program.key,
             &self.swap_account.key,
             &self.authority_account.key,
             &user_transfer_account.key,
@@ -590,24 +701,30 @@ impl NativeTokenSwap {
             instruction,
         )
         .unwrap();
-
-        do_process_instruction(
-            withdraw_instruction,
-            &[
-                self.swap_account.as_account_info(),
-                self.authority_account.as_account_info(),
-                user_transfer_account.as_account_info(),
-                self.pool_mint_account.as_account_info(),
-                pool_account.as_account_info(),
-                self.token_a_account.as_account_info(),
-                self.token_b_account.as_account_info(),
-                destination_token_account.as_account_info(),
-                self.pool_fee_account.as_account_info(),
-                destination_token_mint_account.as_account_info(),
-                self.pool_token_program_account.as_account_info(),
-                destination_token_program.as_account_info(),
-            ],
-        )
+        withdraw_instruction
+            .accounts
+            .extend(self.transfer_hook_account_metas.iter().cloned());
+
+        let mut account_infos = vec![
+            self.swap_account.as_account_info(),
+            self.authority_account.as_account_info(),
+            user_transfer_account.as_account_info(),
+            self.pool_mint_account.as_account_info(),
+            pool_account.as_account_info(),
+            self.token_a_account.as_account_info(),
+            self.token_b_account.as_account_info(),
+            destination_token_account.as_account_info(),
+            self.pool_fee_account.as_account_info(),
+            destination_token_mint_account.as_account_info(),
+            self.pool_token_program_account.as_account_info(),
+            destination_token_program.as_account_info(),
+        ];
+        account_infos.extend(
+            self.transfer_hook_accounts
+                .iter_mut()
+                .map(NativeAccountData::as_account_info),
+        );
+        do_process_instruction(withdraw_instruction, &account_infos)
     }
 
     pub fn withdraw_all(
@@ -0,0 +1,458 @@
+//! Tick, price and liquidity math for concentrated liquidity pools
+//!
+//! Prices are expressed as the square root of the amount of token B per token
+//! A, stored as Q64.64 fixed point numbers. Each tick is a 0.01% (1 basis
+//! point) price increment, so the square root price at tick `i` is
+//! `sqrt(1.0001 ^ i)`.
+
+use {spl_math::uint::U256, std::convert::TryFrom};
+
+/// Minimum tick index, for a price of roughly 2^-64
+pub const MIN_TICK: i32 = -443_636;
+/// Maximum tick index, for a price of roughly 2^64
+pub const MAX_TICK: i32 = -MIN_TICK;
+
+/// Square root price at `MIN_TICK`, as a Q64.64 number
+pub const MIN_SQRT_PRICE_X64: u128 = 4_295_048_017;
+/// Square root price at `MAX_TICK`, as a Q64.64 number
+pub const MAX_SQRT_PRICE_X64: u128 = 79_226_673_515_401_279_992_447_579_062;
+
+/// Q64.64 representation of 1
+const Q64: u128 = 1 << 64;
+
+/// `2^128 / sqrt(1.0001) ^ (2 ^ i)` for every bit `i` of a tick index
+const INVERSE_SQRT_PRICE_FACTORS_X128: [u128; 19] = [
+    0xfffcb933bd6fad37aa2d162d1a594001,
+    0xfff97272373d413259a46990580e2139,
+    0xfff2e50f5f656932ef12357cf3c7fdcb,
+    0xffe5caca7e10e4e61c3624eaa0941ccf,
+    0xffcb9843d60f6159c9db58835c926643,
+    0xff973b41fa98c081472e6896dfb254bf,
+    0xff2ea16466c96a3843ec78b326b52860,
+    0xfe5dee046a99a2a811c461f1969c3052,
+    0xfcbe86c7900a88aedcffc83b479aa3a3,
+    0xf987a7253ac413176f2b074cf7815e53,
+    0xf3392b0822b70005940c7a398e4b70f2,
+    0xe7159475a2c29b7443b29c7fa6e889d8,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e4,
+    0x70d869a156d2a1b890bb3df62baf32f6,
+    0x31be135f97d08fd981231505542fcfa5,
+    0x9aa508b5b7a84e1c677de54f3e99bc8,
+    0x5d6af8dedb81196699c329225ee604,
+    0x2216e584f5fa1ea926041bedfe97,
+];
+
+/// `2^32 / log2(sqrt(1.0001))`, used to convert a Q32.32 base 2 logarithm of
+/// a price into a tick index
+const TICKS_PER_LOG2_X32: i128 = 59_543_866_431_255;
+
+/// Number of fractional bits computed for the base 2 logarithm of a price
+const LOG2_PRECISION_BITS: u32 = 24;
+
+/// Calculates the square root price at the given tick, as a Q64.64 number,
+/// rounding up
+pub fn sqrt_price_at_tick(tick: i32) -> Option<u128> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return None;
+    }
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = if abs_tick & 1 != 0 {
+        U256::from(INVERSE_SQRT_PRICE_FACTORS_X128[0])
+    } else {
+        U256::one() << 128
+    };
+    for (bit, factor) in INVERSE_SQRT_PRICE_FACTORS_X128.iter().enumerate().skip(1) {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = ratio.checked_mul(U256::from(*factor))? >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX.checked_div(ratio)?;
+    }
+    let remainder = ratio & U256::from(u64::MAX);
+    let sqrt_price = u128::try_from(ratio >> 64).ok()?;
+    if remainder.is_zero() {
+        Some(sqrt_price)
+    } else {
+        sqrt_price.checked_add(1)
+    }
+}
+
+/// Calculates the greatest tick whose square root price is less than or equal
+/// to the given Q64.64 square root price
+pub fn tick_at_sqrt_price(sqrt_price_x64: u128) -> Option<i32> {
+    if !(MIN_SQRT_PRICE_X64..=MAX_SQRT_PRICE_X64).contains(&sqrt_price_x64) {
+        return None;
+    }
+    // integer part of the logarithm is given by the most significant bit, and
+    // the fractional part is computed one bit at a time by repeatedly squaring
+    // the price normalized to [1, 2) as a Q1.63 number
+    let msb = 127 - sqrt_price_x64.leading_zeros();
+    let mut log2_x32 = (msb as i128 - 64) << 32;
+    let mut normalized = if msb >= 63 {
+        sqrt_price_x64 >> (msb - 63)
+    } else {
+        sqrt_price_x64 << (63 - msb)
+    };
+    let mut bit = 1i128 << 31;
+    for _ in 0..LOG2_PRECISION_BITS {
+        normalized = normalized.checked_mul(normalized)? >> 63;
+        let overflow = (normalized >> 64) as u32;
+        if overflow != 0 {
+            log2_x32 |= bit;
+        }
+        normalized >>= overflow;
+        bit >>= 1;
+    }
+
+    // the truncated logarithm is at most 2^-24 too small, which is under 0.001
+    // ticks, so the exact tick is one of the two candidates around the estimate
+    let tick_estimate_x64 = log2_x32.checked_mul(TICKS_PER_LOG2_X32)?;
+    let error_margin_x64 = 1i128 << 59;
+    let tick_low = i32::try_from((tick_estimate_x64 - error_margin_x64) >> 64).ok()?;
+    let tick_high = i32::try_from((tick_estimate_x64 + error_margin_x64) >> 64).ok()?;
+    if tick_low == tick_high || sqrt_price_at_tick(tick_high)? > sqrt_price_x64 {
+        Some(tick_low)
+    } else {
+        Some(tick_high)
+    }
+}
+
+fn ordered(sqrt_price_a_x64: u128, sqrt_price_b_x64: u128) -> (u128, u128) {
+    if sqrt_price_a_x64 <= sqrt_price_b_x64 {
+        (sqrt_price_a_x64, sqrt_price_b_x64)
+    } else {
+        (sqrt_price_b_x64, sqrt_price_a_x64)
+    }
+}
+
+fn div_rounding(numerator: U256, denominator: U256, round_up: bool) -> Option<U256> {
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    if round_up && !remainder.is_zero() {
+        quotient.checked_add(U256::one())
+    } else {
+        Some(quotient)
+    }
+}
+
+/// Calculates the amount of token A backing the given liquidity between two
+/// square root prices: `liquidity * (sqrt_upper - sqrt_lower) / (sqrt_upper * sqrt_lower)`
+pub fn token_a_delta(
+    sqrt_price_a_x64: u128,
+    sqrt_price_b_x64: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<u128> {
+    let (sqrt_price_lower_x64, sqrt_price_upper_x64) = ordered(sqrt_price_a_x64, sqrt_price_b_x64);
+    if sqrt_price_lower_x64 == 0 {
+        return None;
+    }
+    let numerator = (U256::from(liquidity) << 64)
+        .checked_mul(U256::from(sqrt_price_upper_x64 - sqrt_price_lower_x64))?;
+    let denominator =
+        U256::from(sqrt_price_upper_x64).checked_mul(U256::from(sqrt_price_lower_x64))?;
+    u128::try_from(div_rounding(numerator, denominator, round_up)?).ok()
+}
+
+/// Calculates the amount of token B backing the given liquidity between two
+/// square root prices: `liquidity * (sqrt_upper - sqrt_lower)`
+pub fn token_b_delta(
+    sqrt_price_a_x64: u128,
+    sqrt_price_b_x64: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<u128> {
+    let (sqrt_price_lower_x64, sqrt_price_upper_x64) = ordered(sqrt_price_a_x64, sqrt_price_b_x64);
+    let numerator = U256::from(liquidity)
+        .checked_mul(U256::from(sqrt_price_upper_x64 - sqrt_price_lower_x64))?;
+    u128::try_from(div_rounding(numerator, U256::from(Q64), round_up)?).ok()
+}
+
+/// Calculates the square root price after adding the given amount of token A
+/// to the pool, rounding up so that the price never moves too far
+fn sqrt_price_after_token_a_in(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount: u128,
+) -> Option<u128> {
+    if amount == 0 {
+        return Some(sqrt_price_x64);
+    }
+    let liquidity_x64 = U256::from(liquidity) << 64;
+    // liquidity * sqrt_price / (liquidity + amount * sqrt_price), falling back
+    // to the less precise liquidity / (liquidity / sqrt_price + amount) on overflow
+    let new_sqrt_price = match liquidity_x64.checked_mul(U256::from(sqrt_price_x64)) {
+        Some(numerator) => {
+            let denominator = liquidity_x64
+                .checked_add(U256::from(amount).checked_mul(U256::from(sqrt_price_x64))?)?;
+            div_rounding(numerator, denominator, true)?
+        }
+        None => {
+            let denominator = liquidity_x64
+                .checked_div(U256::from(sqrt_price_x64))?
+                .checked_add(U256::from(amount))?;
+            div_rounding(liquidity_x64, denominator, true)?
+        }
+    };
+    u128::try_from(new_sqrt_price).ok()
+}
+
+/// Calculates the square root price after adding the given amount of token B
+/// to the pool, rounding down so that the price never moves too far
+fn sqrt_price_after_token_b_in(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    amount: u128,
+) -> Option<u128> {
+    let delta = (U256::from(amount) << 64).checked_div(U256::from(liquidity))?;
+    sqrt_price_x64.checked_add(u128::try_from(delta).ok()?)
+}
+
+/// Result of trading within a range of prices backed by constant liquidity
+#[derive(Debug, PartialEq)]
+pub struct SwapStep {
+    /// Square root price after the trade
+    pub sqrt_price_next_x64: u128,
+    /// Amount of source token taken by the pool, excluding fees
+    pub amount_in: u128,
+    /// Amount of destination token given by the pool
+    pub amount_out: u128,
+    /// Trading fee taken by the pool, in source token
+    pub fee_amount: u128,
+}
+
+/// Trades up to `amount_remaining` source tokens, including trading fees,
+/// moving the price from `sqrt_price_current_x64` towards
+/// `sqrt_price_target_x64` but never past it. The trade is token A for token B
+/// if the target price is lower than the current price, and token B for token A
+/// otherwise.
+pub fn compute_swap_step(
+    sqrt_price_current_x64: u128,
+    sqrt_price_target_x64: u128,
+    liquidity: u128,
+    amount_remaining: u128,
+    fee_numerator: u128,
+    fee_denominator: u128,
+) -> Option<SwapStep> {
+    let a_to_b = sqrt_price_target_x64 <= sqrt_price_current_x64;
+    let fee_numerator = if fee_denominator == 0 {
+        0
+    } else {
+        fee_numerator
+    };
+    let amount_remaining_less_fee = if fee_numerator == 0 {
+        amount_remaining
+    } else {
+        amount_remaining
+            .checked_mul(fee_denominator.checked_sub(fee_numerator)?)?
+            .checked_div(fee_denominator)?
+    };
+
+    let amount_in_to_target = if a_to_b {
+        token_a_delta(
+            sqrt_price_target_x64,
+            sqrt_price_current_x64,
+            liquidity,
+            true,
+        )?
+    } else {
+        token_b_delta(
+            sqrt_price_current_x64,
+            sqrt_price_target_x64,
+            liquidity,
+            true,
+        )?
+    };
+    let sqrt_price_next_x64 = if amount_remaining_less_fee >= amount_in_to_target {
+        sqrt_price_target_x64
+    } else if a_to_b {
+        sqrt_price_after_token_a_in(sqrt_price_current_x64, liquidity, amount_remaining_less_fee)?
+    } else {
+        sqrt_price_after_token_b_in(sqrt_price_current_x64, liquidity, amount_remaining_less_fee)?
+    };
+    let reached_target = sqrt_price_next_x64 == sqrt_price_target_x64;
+
+    let (amount_in, amount_out) = if a_to_b {
+        let amount_in = if reached_target {
+            amount_in_to_target
+        } else {
+            token_a_delta(sqrt_price_next_x64, sqrt_price_current_x64, liquidity, true)?
+        };
+        let amount_out = token_b_delta(
+            sqrt_price_next_x64,
+            sqrt_price_current_x64,
+            liquidity,
+            false,
+        )?;
+        (amount_in, amount_out)
+    } else {
+        let amount_in = if reached_target {
+            amount_in_to_target
+        } else {
+            token_b_delta(sqrt_price_current_x64, sqrt_price_next_x64, liquidity, true)?
+        };
+        let amount_out = token_a_delta(
+            sqrt_price_current_x64,
+            sqrt_price_next_x64,
+            liquidity,
+            false,
+        )?;
+        (amount_in, amount_out)
+    };
+
+    // whatever was not used to move the price is kept as the fee when the
+    // trade ends within the range
+    let fee_amount = if !reached_target {
+        amount_remaining.checked_sub(amount_in)?
+    } else if fee_numerator == 0 {
+        0
+    } else {
+        let numerator = amount_in.checked_mul(fee_numerator)?;
+        let denominator = fee_denominator.checked_sub(fee_numerator)?;
+        numerator
+            .checked_add(denominator.checked_sub(1)?)?
+            .checked_div(denominator)?
+    };
+
+    Some(SwapStep {
+        sqrt_price_next_x64,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, proptest::prelude::*};
+
+    #[test]
+    fn sqrt_price_bounds() {
+        assert_eq!(sqrt_price_at_tick(0), Some(Q64));
+        assert_eq!(sqrt_price_at_tick(MIN_TICK), Some(MIN_SQRT_PRICE_X64));
+        assert_eq!(sqrt_price_at_tick(MAX_TICK), Some(MAX_SQRT_PRICE_X64));
+        assert_eq!(sqrt_price_at_tick(MIN_TICK - 1), None);
+        assert_eq!(sqrt_price_at_tick(MAX_TICK + 1), None);
+        assert_eq!(tick_at_sqrt_price(MIN_SQRT_PRICE_X64), Some(MIN_TICK));
+        assert_eq!(tick_at_sqrt_price(MAX_SQRT_PRICE_X64), Some(MAX_TICK));
+        assert_eq!(tick_at_sqrt_price(MIN_SQRT_PRICE_X64 - 1), None);
+        assert_eq!(tick_at_sqrt_price(MAX_SQRT_PRICE_X64 + 1), None);
+    }
+
+    #[test]
+    fn sqrt_price_known_values() {
+        // sqrt(1.0001) and sqrt(1.0001)^-1
+        assert_eq!(sqrt_price_at_tick(1), Some(18_447_666_387_855_959_851));
+        assert_eq!(sqrt_price_at_tick(-1), Some(18_445_821_805_675_392_312));
+        // sqrt(1.0001 ^ 23028) ~= 3.16, a price of ~10
+        let sqrt_price = sqrt_price_at_tick(23_028).unwrap();
+        assert_eq!(sqrt_price >> 64, 3);
+        assert_eq!(tick_at_sqrt_price(sqrt_price), Some(23_028));
+    }
+
+    #[test]
+    fn token_deltas() {
+        let sqrt_price_lower = sqrt_price_at_tick(-100).unwrap();
+        let sqrt_price_upper = sqrt_price_at_tick(100).unwrap();
+        let liquidity = 1_000_000_000u128;
+        let amount_a_down = token_a_delta(Q64, sqrt_price_upper, liquidity, false).unwrap();
+        let amount_a_up = token_a_delta(Q64, sqrt_price_upper, liquidity, true).unwrap();
+        assert_eq!(amount_a_up, amount_a_down + 1);
+        // ~ liquidity * (1 - 1 / sqrt(1.0001^100))
+        assert_eq!(amount_a_down, 4_987_272);
+        let amount_b_down = token_b_delta(sqrt_price_lower, Q64, liquidity, false).unwrap();
+        assert_eq!(amount_b_down, 4_987_272);
+        assert_eq!(
+            token_b_delta(Q64, sqrt_price_lower, liquidity, false),
+            Some(amount_b_down)
+        );
+    }
+
+    #[test]
+    fn swap_step_reaches_target() {
+        let sqrt_price_target = sqrt_price_at_tick(-100).unwrap();
+        let liquidity = 1_000_000_000u128;
+        let step =
+            compute_swap_step(Q64, sqrt_price_target, liquidity, 10_000_000, 3, 1000).unwrap();
+        assert_eq!(step.sqrt_price_next_x64, sqrt_price_target);
+        assert_eq!(
+            step.amount_in,
+            token_a_delta(sqrt_price_target, Q64, liquidity, true).unwrap()
+        );
+        assert_eq!(
+            step.amount_out,
+            token_b_delta(sqrt_price_target, Q64, liquidity, false).unwrap()
+        );
+        assert!(step.amount_in + step.fee_amount <= 10_000_000);
+        assert_eq!(step.fee_amount, (step.amount_in * 3 + 996) / 997);
+    }
+
+    #[test]
+    fn swap_step_within_range() {
+        let sqrt_price_target = sqrt_price_at_tick(100).unwrap();
+        let liquidity = 1_000_000_000u128;
+        let step =
+            compute_swap_step(Q64, sqrt_price_target, liquidity, 1_000_000, 3, 1000).unwrap();
+        assert!(step.sqrt_price_next_x64 > Q64);
+        assert!(step.sqrt_price_next_x64 < sqrt_price_target);
+        assert_eq!(step.amount_in + step.fee_amount, 1_000_000);
+        assert!(step.fee_amount >= 3_000);
+        assert!(step.amount_out < 1_000_000);
+    }
+
+    proptest! {
+        #[test]
+        fn tick_round_trip(tick in MIN_TICK..MAX_TICK) {
+            let sqrt_price = sqrt_price_at_tick(tick).unwrap();
+            let next_sqrt_price = sqrt_price_at_tick(tick + 1).unwrap();
+            prop_assert!(sqrt_price < next_sqrt_price);
+            prop_assert_eq!(tick_at_sqrt_price(sqrt_price), Some(tick));
+            prop_assert_eq!(tick_at_sqrt_price(next_sqrt_price - 1), Some(tick));
+        }
+
+        #[test]
+        fn swap_step_never_overpays(
+            tick_current in -10_000i32..10_000,
+            tick_target in -10_000i32..10_000,
+            liquidity in 1u128..u64::MAX as u128,
+            amount_remaining in 0u128..u64::MAX as u128,
+            fee_numerator in 0u128..100,
+        ) {
+            let sqrt_price_current = sqrt_price_at_tick(tick_current).unwrap();
+            let sqrt_price_target = sqrt_price_at_tick(tick_target).unwrap();
+            let step = compute_swap_step(
+                sqrt_price_current,
+                sqrt_price_target,
+                liquidity,
+                amount_remaining,
+                fee_numerator,
+                10_000,
+            ).unwrap();
+            prop_assert!(step.amount_in + step.fee_amount <= amount_remaining);
+            let a_to_b = sqrt_price_target <= sqrt_price_current;
+            if a_to_b {
+                prop_assert!(step.sqrt_price_next_x64 <= sqrt_price_current);
+                prop_assert!(step.sqrt_price_next_x64 >= sqrt_price_target);
+                // the pool always receives at least the value it gives out
+                let amount_in_required = token_a_delta(
+                    step.sqrt_price_next_x64,
+                    sqrt_price_current,
+                    liquidity,
+                    true,
+                ).unwrap();
+                prop_assert!(step.amount_in >= amount_in_required);
+            } else {
+                prop_assert!(step.sqrt_price_next_x64 >= sqrt_price_current);
+                prop_assert!(step.sqrt_price_next_x64 <= sqrt_price_target);
+                let amount_in_required = token_b_delta(
+                    sqrt_price_current,
+                    step.sqrt_price_next_x64,
+                    liquidity,
+                    true,
+                ).unwrap();
+                prop_assert!(step.amount_in >= amount_in_required);
+            }
+        }
+    }
+}
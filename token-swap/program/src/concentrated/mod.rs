@@ -0,0 +1,9 @@
+//! Concentrated liquidity pools
+//!
+//! Liquidity providers open positions over a range of ticks instead of
+//! receiving fungible pool tokens. The pool only trades against the liquidity
+//! of positions whose range contains the current price, and trading fees are
+//! accrued to each of those positions in proportion to their liquidity.
+
+pub mod math;
+pub mod pool;
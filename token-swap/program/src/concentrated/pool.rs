@@ -0,0 +1,542 @@
+//! Liquidity and swap accounting for concentrated liquidity pools
+
+use {
+    crate::{
+        concentrated::math::{
+            compute_swap_step, sqrt_price_at_tick, tick_at_sqrt_price, token_a_delta,
+            token_b_delta, MAX_SQRT_PRICE_X64, MAX_TICK, MIN_SQRT_PRICE_X64, MIN_TICK,
+        },
+        error::SwapError,
+        state::{
+            ConcentratedSwapV1, Position, Tick, MAX_INITIALIZED_TICKS, MIN_POSITION_LIQUIDITY,
+        },
+    },
+    spl_math::uint::U256,
+    std::convert::TryFrom,
+};
+
+/// Result of a trade against a concentrated liquidity pool
+#[derive(Debug, PartialEq)]
+pub struct ConcentratedSwapResult {
+    /// Amount of source token taken by the pool, including fees
+    pub amount_in: u128,
+    /// Amount of destination token given by the pool
+    pub amount_out: u128,
+    /// Amount of source token kept as trading fees by the pool
+    pub fee_amount: u128,
+}
+
+/// Token amounts corresponding to a change of liquidity
+#[derive(Debug, PartialEq)]
+pub struct LiquidityAmounts {
+    /// Amount of token A
+    pub token_a_amount: u128,
+    /// Amount of token B
+    pub token_b_amount: u128,
+}
+
+fn add_liquidity_delta(liquidity: u128, liquidity_delta: i128) -> Result<u128, SwapError> {
+    if liquidity_delta >= 0 {
+        liquidity.checked_add(liquidity_delta.unsigned_abs())
+    } else {
+        liquidity.checked_sub(liquidity_delta.unsigned_abs())
+    }
+    .ok_or(SwapError::CalculationFailure)
+}
+
+impl ConcentratedSwapV1 {
+    /// Checks that a position range is ordered, within bounds, and aligned to
+    /// the tick spacing of the pool
+    pub fn check_tick_range(&self, tick_lower: i32, tick_upper: i32) -> Result<(), SwapError> {
+        let tick_spacing = i32::from(self.tick_spacing);
+        if tick_lower >= tick_upper
+            || tick_lower < MIN_TICK
+            || tick_upper > MAX_TICK
+            || tick_lower % tick_spacing != 0
+            || tick_upper % tick_spacing != 0
+        {
+            return Err(SwapError::InvalidTickRange);
+        }
+        Ok(())
+    }
+
+    fn tick(&self, index: i32) -> Option<&Tick> {
+        self.ticks
+            .binary_search_by_key(&index, |tick| tick.index)
+            .ok()
+            .map(|position| &self.ticks[position])
+    }
+
+    /// Calculates the token A and token B fees earned per unit of liquidity
+    /// between two initialized ticks, as Q64.64 numbers. The values only
+    /// have meaning relative to a previous value for the same range.
+    pub fn fee_growth_inside(&self, tick_lower: i32, tick_upper: i32) -> (u128, u128) {
+        let (lower_outside_a, lower_outside_b) = self
+            .tick(tick_lower)
+            .map(|tick| (tick.fee_growth_outside_a_x64, tick.fee_growth_outside_b_x64))
+            .unwrap_or_default();
+        let (upper_outside_a, upper_outside_b) = self
+            .tick(tick_upper)
+            .map(|tick| (tick.fee_growth_outside_a_x64, tick.fee_growth_outside_b_x64))
+            .unwrap_or_default();
+        let (below_a, below_b) = if self.current_tick >= tick_lower {
+            (lower_outside_a, lower_outside_b)
+        } else {
+            (
+                self.fee_growth_global_a_x64.wrapping_sub(lower_outside_a),
+                self.fee_growth_global_b_x64.wrapping_sub(lower_outside_b),
+            )
+        };
+        let (above_a, above_b) = if self.current_tick < tick_upper {
+            (upper_outside_a, upper_outside_b)
+        } else {
+            (
+                self.fee_growth_global_a_x64.wrapping_sub(upper_outside_a),
+                self.fee_growth_global_b_x64.wrapping_sub(upper_outside_b),
+            )
+        };
+        (
+            self.fee_growth_global_a_x64
+                .wrapping_sub(below_a)
+                .wrapping_sub(above_a),
+            self.fee_growth_global_b_x64
+                .wrapping_sub(below_b)
+                .wrapping_sub(above_b),
+        )
+    }
+
+    /// Adds liquidity to a tick, initializing it if needed. Fee growth
+    /// before initialization is assumed to have happened below the tick.
+    fn update_tick(
+        &mut self,
+        index: i32,
+        liquidity_delta: i128,
+        is_upper: bool,
+    ) -> Result<(), SwapError> {
+        let position = match self.ticks.binary_search_by_key(&index, |tick| tick.index) {
+            Ok(position) => position,
+            Err(position) => {
+                if self.ticks.len() >= MAX_INITIALIZED_TICKS {
+                    return Err(SwapError::TickCapacityExceeded);
+                }
+                let tick = if index <= self.current_tick {
+                    Tick {
+                        index,
+                        fee_growth_outside_a_x64: self.fee_growth_global_a_x64,
+                        fee_growth_outside_b_x64: self.fee_growth_global_b_x64,
+                        ..Tick::default()
+                    }
+                } else {
+                    Tick {
+                        index,
+                        ..Tick::default()
+                    }
+                };
+                self.ticks.insert(position, tick);
+                position
+            }
+        };
+        let tick = &mut self.ticks[position];
+        tick.liquidity_gross = add_liquidity_delta(tick.liquidity_gross, liquidity_delta)?;
+        let liquidity_net_delta = if is_upper {
+            liquidity_delta.checked_neg()
+        } else {
+            Some(liquidity_delta)
+        };
+        tick.liquidity_net = liquidity_net_delta
+            .and_then(|delta| tick.liquidity_net.checked_add(delta))
+            .ok_or(SwapError::CalculationFailure)?;
+        Ok(())
+    }
+
+    /// Adds or removes liquidity from a position, accruing the fees earned by
+    /// the position so far. Returns the token amounts to deposit, rounded up,
+    /// when adding liquidity, or the token amounts to withdraw, rounded down,
+    /// when removing it. A position must be left with either no liquidity or
+    /// at least the minimum.
+    pub fn modify_position(
+        &mut self,
+        position: &mut Position,
+        liquidity_delta: i128,
+    ) -> Result<LiquidityAmounts, SwapError> {
+        let (tick_lower, tick_upper) = (position.tick_lower, position.tick_upper);
+        if liquidity_delta != 0 {
+            let liquidity = add_liquidity_delta(position.liquidity, liquidity_delta)?;
+            if liquidity != 0 && liquidity < MIN_POSITION_LIQUIDITY {
+                return Err(SwapError::PositionLiquidityTooLow);
+            }
+
+            self.update_tick(tick_lower, liquidity_delta, false)?;
+            self.update_tick(tick_upper, liquidity_delta, true)?;
+        } else if position.liquidity == 0 {
+            return Ok(LiquidityAmounts {
+                token_a_amount: 0,
+                token_b_amount: 0,
+            });
+        }
+
+        let (fee_growth_inside_a, fee_growth_inside_b) =
+            self.fee_growth_inside(tick_lower, tick_upper);
+        position.update(liquidity_delta, fee_growth_inside_a, fee_growth_inside_b)?;
+        if liquidity_delta < 0 {
+            self.ticks.retain(|tick| tick.liquidity_gross != 0);
+        }
+
+        let sqrt_price_lower = sqrt_price_at_tick(tick_lower).ok_or(SwapError::InvalidTickRange)?;
+        let sqrt_price_upper = sqrt_price_at_tick(tick_upper).ok_or(SwapError::InvalidTickRange)?;
+        let liquidity = liquidity_delta.unsigned_abs();
+        let round_up = liquidity_delta > 0;
+        let (token_a_amount, token_b_amount) = if self.current_tick < tick_lower {
+            (
+                token_a_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up),
+                Some(0),
+            )
+        } else if self.current_tick < tick_upper {
+            self.liquidity = add_liquidity_delta(self.liquidity, liquidity_delta)?;
+            (
+                token_a_delta(self.sqrt_price_x64, sqrt_price_upper, liquidity, round_up),
+                token_b_delta(sqrt_price_lower, self.sqrt_price_x64, liquidity, round_up),
+            )
+        } else {
+            (
+                Some(0),
+                token_b_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up),
+            )
+        };
+        Ok(LiquidityAmounts {
+            token_a_amount: token_a_amount.ok_or(SwapError::CalculationFailure)?,
+            token_b_amount: token_b_amount.ok_or(SwapError::CalculationFailure)?,
+        })
+    }
+
+    fn cross_tick(&mut self, index: i32, a_to_b: bool) -> Result<(), SwapError> {
+        let position = self
+            .ticks
+            .binary_search_by_key(&index, |tick| tick.index)
+            .map_err(|_| SwapError::CalculationFailure)?;
+        let tick = &mut self.ticks[position];
+        tick.fee_growth_outside_a_x64 = self
+            .fee_growth_global_a_x64
+            .wrapping_sub(tick.fee_growth_outside_a_x64);
+        tick.fee_growth_outside_b_x64 = self
+            .fee_growth_global_b_x64
+            .wrapping_sub(tick.fee_growth_outside_b_x64);
+        let liquidity_net = if a_to_b {
+            tick.liquidity_net
+                .checked_neg()
+                .ok_or(SwapError::CalculationFailure)?
+        } else {
+            tick.liquidity_net
+        };
+        self.liquidity = add_liquidity_delta(self.liquidity, liquidity_net)?;
+        Ok(())
+    }
+
+    /// Trades up to `amount_in` source tokens, including fees, crossing
+    /// initialized ticks as needed. The trade stops early if the price reaches
+    /// its minimum or maximum value.
+    pub fn swap(
+        &mut self,
+        amount_in: u128,
+        a_to_b: bool,
+    ) -> Result<ConcentratedSwapResult, SwapError> {
+        let fee_numerator = u128::from(self.fees.trade_fee_numerator);
+        let fee_denominator = u128::from(self.fees.trade_fee_denominator);
+        let mut amount_remaining = amount_in;
+        let mut amount_out = 0u128;
+        let mut fee_amount = 0u128;
+
+        while amount_remaining > 0 {
+            let next_tick = if a_to_b {
+                self.ticks
+                    .iter()
+                    .rev()
+                    .find(|tick| tick.index <= self.current_tick)
+            } else {
+                self.ticks
+                    .iter()
+                    .find(|tick| tick.index > self.current_tick)
+            }
+            .map(|tick| tick.index);
+            let sqrt_price_target = match next_tick {
+                Some(index) => sqrt_price_at_tick(index).ok_or(SwapError::CalculationFailure)?,
+                None if a_to_b => MIN_SQRT_PRICE_X64,
+                None => MAX_SQRT_PRICE_X64,
+            };
+
+            let step = compute_swap_step(
+                self.sqrt_price_x64,
+                sqrt_price_target,
+                self.liquidity,
+                amount_remaining,
+                fee_numerator,
+                fee_denominator,
+            )
+            .ok_or(SwapError::CalculationFailure)?;
+            amount_remaining = step
+                .amount_in
+                .checked_add(step.fee_amount)
+                .and_then(|amount| amount_remaining.checked_sub(amount))
+                .ok_or(SwapError::CalculationFailure)?;
+            amount_out = amount_out
+                .checked_add(step.amount_out)
+                .ok_or(SwapError::CalculationFailure)?;
+            fee_amount = fee_amount
+                .checked_add(step.fee_amount)
+                .ok_or(SwapError::CalculationFailure)?;
+            if self.liquidity > 0 {
+                let fee_growth = (U256::from(step.fee_amount) << 64)
+                    .checked_div(U256::from(self.liquidity))
+                    .and_then(|fee_growth| u128::try_from(fee_growth).ok())
+                    .ok_or(SwapError::CalculationFailure)?;
+                if a_to_b {
+                    self.fee_growth_global_a_x64 =
+                        self.fee_growth_global_a_x64.wrapping_add(fee_growth);
+                } else {
+                    self.fee_growth_global_b_x64 =
+                        self.fee_growth_global_b_x64.wrapping_add(fee_growth);
+                }
+            }
+
+            self.sqrt_price_x64 = step.sqrt_price_next_x64;
+            if step.sqrt_price_next_x64 == sqrt_price_target {
+                match next_tick {
+                    Some(index) => {
+                        self.cross_tick(index, a_to_b)?;
+                        self.current_tick = if a_to_b { index - 1 } else { index };
+                    }
+                    None => {
+                        self.current_tick = tick_at_sqrt_price(self.sqrt_price_x64)
+                            .ok_or(SwapError::CalculationFailure)?;
+                        break;
+                    }
+                }
+            } else {
+                self.current_tick =
+                    tick_at_sqrt_price(self.sqrt_price_x64).ok_or(SwapError::CalculationFailure)?;
+            }
+        }
+
+        Ok(ConcentratedSwapResult {
+            amount_in: amount_in
+                .checked_sub(amount_remaining)
+                .ok_or(SwapError::CalculationFailure)?,
+            amount_out,
+            fee_amount,
+        })
+    }
+}
+
+impl Position {
+    /// Accrues the fees earned by the position since its last update, then
+    /// adds the liquidity delta
+    pub fn update(
+        &mut self,
+        liquidity_delta: i128,
+        fee_growth_inside_a_x64: u128,
+        fee_growth_inside_b_x64: u128,
+    ) -> Result<(), SwapError> {
+        let owed = |fee_growth_inside: u128, fee_growth_inside_last: u128| {
+            let fee_growth = U256::from(fee_growth_inside.wrapping_sub(fee_growth_inside_last));
+            fee_growth
+                .checked_mul(U256::from(self.liquidity))
+                .and_then(|owed| u64::try_from(owed >> 64).ok())
+                .ok_or(SwapError::CalculationFailure)
+        };
+        let owed_a = owed(fee_growth_inside_a_x64, self.fee_growth_inside_a_last_x64)?;
+        let owed_b = owed(fee_growth_inside_b_x64, self.fee_growth_inside_b_last_x64)?;
+        self.tokens_owed_a = self
+            .tokens_owed_a
+            .checked_add(owed_a)
+            .ok_or(SwapError::CalculationFailure)?;
+        self.tokens_owed_b = self
+            .tokens_owed_b
+            .checked_add(owed_b)
+            .ok_or(SwapError::CalculationFailure)?;
+        self.liquidity = add_liquidity_delta(self.liquidity, liquidity_delta)?;
+        self.fee_growth_inside_a_last_x64 = fee_growth_inside_a_x64;
+        self.fee_growth_inside_b_last_x64 = fee_growth_inside_b_x64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::curve::fees::Fees};
+
+    fn new_pool(trade_fee_numerator: u64) -> ConcentratedSwapV1 {
+        ConcentratedSwapV1 {
+            is_initialized: true,
+            fees: Fees {
+                trade_fee_numerator,
+                trade_fee_denominator: 1_000,
+                ..Fees::default()
+            },
+            tick_spacing: 10,
+            sqrt_price_x64: sqrt_price_at_tick(0).unwrap(),
+            current_tick: 0,
+            ..ConcentratedSwapV1::default()
+        }
+    }
+
+    fn new_position(tick_lower: i32, tick_upper: i32) -> Position {
+        Position {
+            is_initialized: true,
+            tick_lower,
+            tick_upper,
+            ..Position::default()
+        }
+    }
+
+    #[test]
+    fn tick_range() {
+        let pool = new_pool(0);
+        assert_eq!(pool.check_tick_range(-10, 10), Ok(()));
+        assert_eq!(
+            pool.check_tick_range(10, 10),
+            Err(SwapError::InvalidTickRange)
+        );
+        assert_eq!(
+            pool.check_tick_range(-15, 10),
+            Err(SwapError::InvalidTickRange)
+        );
+        assert_eq!(
+            pool.check_tick_range(MIN_TICK - 4, 0),
+            Err(SwapError::InvalidTickRange)
+        );
+    }
+
+    #[test]
+    fn modify_position_amounts() {
+        let mut pool = new_pool(0);
+        let liquidity = 1_000_000_000;
+
+        let mut below = new_position(-200, -100);
+        let amounts = pool.modify_position(&mut below, liquidity).unwrap();
+        assert_eq!(amounts.token_a_amount, 0);
+        assert!(amounts.token_b_amount > 0);
+
+        let mut above = new_position(100, 200);
+        let amounts = pool.modify_position(&mut above, liquidity).unwrap();
+        assert!(amounts.token_a_amount > 0);
+        assert_eq!(amounts.token_b_amount, 0);
+        assert_eq!(pool.liquidity, 0);
+
+        let mut active = new_position(-100, 100);
+        let deposit = pool.modify_position(&mut active, liquidity).unwrap();
+        assert_eq!(pool.liquidity, liquidity as u128);
+        assert_eq!(pool.ticks.len(), 4);
+        assert_eq!(
+            pool.tick(-100).unwrap().liquidity_gross,
+            2 * liquidity as u128
+        );
+        assert_eq!(pool.tick(-100).unwrap().liquidity_net, 0);
+
+        let withdrawal = pool.modify_position(&mut active, -liquidity).unwrap();
+        assert_eq!(pool.liquidity, 0);
+        assert_eq!(active.liquidity, 0);
+        assert_eq!(withdrawal.token_a_amount + 1, deposit.token_a_amount);
+        assert_eq!(withdrawal.token_b_amount + 1, deposit.token_b_amount);
+        assert_eq!(pool.ticks.len(), 4);
+
+        pool.modify_position(&mut below, -liquidity).unwrap();
+        pool.modify_position(&mut above, -liquidity).unwrap();
+        assert!(pool.ticks.is_empty());
+    }
+
+    #[test]
+    fn tick_capacity() {
+        let mut pool = new_pool(0);
+        let liquidity = MIN_POSITION_LIQUIDITY as i128;
+        for i in 0..(MAX_INITIALIZED_TICKS as i32 / 2) {
+            let mut position = new_position(i * 20, i * 20 + 10);
+            pool.modify_position(&mut position, liquidity).unwrap();
+        }
+        let mut position = new_position(-20, -10);
+        assert_eq!(
+            pool.modify_position(&mut position, liquidity),
+            Err(SwapError::TickCapacityExceeded)
+        );
+    }
+
+    #[test]
+    fn minimum_position_liquidity() {
+        let mut pool = new_pool(0);
+        let liquidity = MIN_POSITION_LIQUIDITY as i128;
+        let mut position = new_position(-100, 100);
+
+        // dust positions cannot take up ticks
+        assert_eq!(
+            pool.modify_position(&mut position, liquidity - 1),
+            Err(SwapError::PositionLiquidityTooLow)
+        );
+        assert!(pool.ticks.is_empty());
+
+        // liquidity can be withdrawn down to the minimum, or entirely
+        pool.modify_position(&mut position, liquidity + 1).unwrap();
+        assert_eq!(
+            pool.modify_position(&mut position, -2),
+            Err(SwapError::PositionLiquidityTooLow)
+        );
+        pool.modify_position(&mut position, -1).unwrap();
+        pool.modify_position(&mut position, -liquidity).unwrap();
+        assert_eq!(position.liquidity, 0);
+        assert!(pool.ticks.is_empty());
+    }
+
+    #[test]
+    fn swap_crosses_ticks_and_accrues_fees() {
+        let mut pool = new_pool(3);
+        let liquidity = 1_000_000_000;
+        let mut wide = new_position(-1000, 1000);
+        let mut narrow = new_position(-100, 100);
+        pool.modify_position(&mut wide, liquidity).unwrap();
+        pool.modify_position(&mut narrow, liquidity).unwrap();
+        assert_eq!(pool.liquidity, 2 * liquidity as u128);
+
+        // large enough to leave the narrow position's range
+        let result = pool.swap(20_000_000, true).unwrap();
+        assert_eq!(result.amount_in, 20_000_000);
+        assert!(result.amount_out < 20_000_000);
+        assert!(result.fee_amount >= 60_000);
+        assert!(pool.current_tick < -100);
+        assert_eq!(pool.liquidity, liquidity as u128);
+        assert_eq!(
+            pool.current_tick,
+            tick_at_sqrt_price(pool.sqrt_price_x64).unwrap()
+        );
+
+        // both positions earned fees while the price was inside the narrow
+        // range, only the wide one after
+        pool.modify_position(&mut wide, 0).unwrap();
+        pool.modify_position(&mut narrow, 0).unwrap();
+        assert!(wide.tokens_owed_a > narrow.tokens_owed_a);
+        assert!(narrow.tokens_owed_a > 0);
+        assert_eq!(wide.tokens_owed_b, 0);
+        let total_owed = u128::from(wide.tokens_owed_a + narrow.tokens_owed_a);
+        assert!(total_owed <= result.fee_amount);
+        assert!(total_owed + 2 >= result.fee_amount);
+
+        // trading the output back returns the price into the narrow range,
+        // slightly below the starting price because of fees
+        let amount_in = result.amount_out;
+        let result = pool.swap(amount_in, false).unwrap();
+        assert_eq!(result.amount_in, amount_in);
+        assert!(pool.current_tick >= -100);
+        assert!(pool.current_tick < 0);
+        assert_eq!(pool.liquidity, 2 * liquidity as u128);
+    }
+
+    #[test]
+    fn swap_stops_at_price_bounds() {
+        let mut pool = new_pool(0);
+        let mut position = new_position(-100, 100);
+        pool.modify_position(&mut position, MIN_POSITION_LIQUIDITY as i128)
+            .unwrap();
+        let result = pool.swap(u64::MAX as u128, false).unwrap();
+        assert!(result.amount_in < u64::MAX as u128);
+        assert_eq!(pool.sqrt_price_x64, MAX_SQRT_PRICE_X64);
+        assert_eq!(pool.liquidity, 0);
+        assert_eq!(pool.current_tick, MAX_TICK);
+    }
+}
@@ -57,6 +57,19 @@ impl<'a> SwapConstraints<'a> {
             Err(SwapError::InvalidFee.into())
         }
     }
+
+    /// Checks that the trade fee is valid for the given constraints, used for
+    /// concentrated liquidity pools which have no pool token to collect owner
+    /// fees in
+    pub fn validate_trade_fee(&self, fees: &Fees) -> Result<(), ProgramError> {
+        if fees.trade_fee_numerator >= self.fees.trade_fee_numerator
+            && fees.trade_fee_denominator == self.fees.trade_fee_denominator
+        {
+            Ok(())
+        } else {
+            Err(SwapError::InvalidFee.into())
+        }
+    }
 }
 
 #[cfg(feature = "production")]
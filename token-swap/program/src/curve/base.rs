@@ -11,6 +11,7 @@ use crate::curve::{
     constant_product::ConstantProductCurve,
     fees::Fees,
     offset::OffsetCurve,
+    weighted::WeightedCurve,
 };
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use std::convert::{TryFrom, TryInto};
@@ -31,6 +32,9 @@ pub enum CurveType {
     ConstantPrice,
     /// Offset curve, like Uniswap, but the token B side has a faked offset
     Offset,
+    /// Balancer-style weighted curve, invariant =
+    /// token_a_amount ^ token_a_weight * token_b_amount ^ token_b_weight
+    Weighted,
 }
 
 /// Encodes all results of swapping from a source token to a destination token
@@ -226,6 +230,7 @@ impl Pack for SwapCurve {
                     Arc::new(ConstantPriceCurve::unpack_from_slice(calculator)?)
                 }
                 CurveType::Offset => Arc::new(OffsetCurve::unpack_from_slice(calculator)?),
+                CurveType::Weighted => Arc::new(WeightedCurve::unpack_from_slice(calculator)?),
             },
         })
     }
@@ -255,6 +260,7 @@ impl TryFrom<u8> for CurveType {
             0 => Ok(CurveType::ConstantProduct),
             1 => Ok(CurveType::ConstantPrice),
             2 => Ok(CurveType::Offset),
+            3 => Ok(CurveType::Weighted),
             _ => Err(ProgramError::InvalidAccountData),
         }
     }
@@ -1,6 +1,10 @@
 //! Swap calculations
 
-use {crate::error::SwapError, spl_math::precise_number::PreciseNumber, std::fmt::Debug};
+use {
+    crate::error::SwapError,
+    spl_math::{precise_number::PreciseNumber, uint::U256},
+    std::{convert::TryFrom, fmt::Debug},
+};
 
 #[cfg(feature = "fuzz")]
 use arbitrary::Arbitrary;
@@ -24,6 +28,13 @@ pub fn map_zero_to_none(x: u128) -> Option<u128> {
     }
 }
 
+/// Helper function for calculating `numerator / denominator` as a Q64.64
+/// fixed-point number, used for prices
+pub fn ratio_x64(numerator: u128, denominator: u128) -> Option<u128> {
+    let ratio = (U256::from(numerator) << 64).checked_div(U256::from(denominator))?;
+    u128::try_from(ratio).ok()
+}
+
 /// The direction of a trade, since curves can be specialized to treat each
 /// token differently (by adding offsets or weights)
 #[cfg_attr(feature = "fuzz", derive(Arbitrary))]
@@ -190,6 +201,23 @@ pub trait CurveCalculator: Debug + DynPack {
         swap_token_a_amount: u128,
         swap_token_b_amount: u128,
     ) -> Option<PreciseNumber>;
+
+    /// Calculates the spot prices of token A in token B and of token B in
+    /// token A, as Q64.64 fixed-point numbers, given the liquidity parameters.
+    ///
+    /// This is used to accumulate prices for the pool's price oracle.  The
+    /// default implementation gives the marginal price of the constant product
+    /// curve, which is the ratio of the token amounts.
+    fn spot_prices_x64(
+        &self,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+    ) -> Option<(u128, u128)> {
+        Some((
+            ratio_x64(swap_token_b_amount, swap_token_a_amount)?,
+            ratio_x64(swap_token_a_amount, swap_token_b_amount)?,
+        ))
+    }
 }
 
 /// Test helpers for curves
@@ -2,8 +2,8 @@
 use {
     crate::{
         curve::calculator::{
-            map_zero_to_none, CurveCalculator, DynPack, RoundDirection, SwapWithoutFeesResult,
-            TradeDirection, TradingTokenResult,
+            map_zero_to_none, ratio_x64, CurveCalculator, DynPack, RoundDirection,
+            SwapWithoutFeesResult, TradeDirection, TradingTokenResult,
         },
         error::SwapError,
     },
@@ -230,6 +230,17 @@ impl CurveCalculator for ConstantPriceCurve {
         };
         PreciseNumber::new(value)
     }
+
+    /// The spot prices of the constant price curve do not depend on the
+    /// liquidity parameters
+    fn spot_prices_x64(
+        &self,
+        _swap_token_a_amount: u128,
+        _swap_token_b_amount: u128,
+    ) -> Option<(u128, u128)> {
+        let token_b_price = self.token_b_price as u128;
+        Some((ratio_x64(1, token_b_price)?, ratio_x64(token_b_price, 1)?))
+    }
 }
 
 /// IsInitialized is required to use `Pack::pack` and `Pack::unpack`
@@ -178,6 +178,92 @@ impl Fees {
     }
 }
 
+/// Volatility-based trade fee, charged on top of the trade fee of a swap.
+/// The additional fee grows linearly with the recent price movement of the
+/// swap, up to its maximum.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DynamicFee {
+    /// Additional trade fee numerator, over the trade fee denominator, charged
+    /// once the volatility reaches its maximum.  Zero disables the fee.
+    pub max_fee_numerator: u64,
+    /// Volatility, in basis points of price movement, at which the maximum
+    /// additional fee is charged
+    pub max_volatility_bps: u64,
+    /// Number of seconds for recorded volatility to decay to zero
+    pub decay_period: u64,
+}
+
+impl DynamicFee {
+    /// Check if the dynamic fee is charged at all
+    pub fn is_enabled(&self) -> bool {
+        self.max_fee_numerator != 0
+    }
+
+    /// Calculate the additional trade fee numerator for the given volatility
+    pub fn fee_numerator(&self, volatility_bps: u64) -> Option<u64> {
+        if !self.is_enabled() {
+            return Some(0);
+        }
+        let volatility_bps = volatility_bps.min(self.max_volatility_bps);
+        let fee_numerator = u128::from(self.max_fee_numerator)
+            .checked_mul(u128::from(volatility_bps))?
+            .checked_div(u128::from(self.max_volatility_bps))?;
+        u64::try_from(fee_numerator).ok()
+    }
+
+    /// Calculate the volatility remaining after the given number of seconds,
+    /// decaying linearly over the decay period
+    pub fn decayed_volatility_bps(&self, volatility_bps: u64, elapsed: u64) -> u64 {
+        if elapsed >= self.decay_period {
+            return 0;
+        }
+        let remaining = u128::from(self.decay_period.saturating_sub(elapsed));
+        let decayed = u128::from(volatility_bps)
+            .saturating_mul(remaining)
+            .checked_div(u128::from(self.decay_period))
+            .unwrap_or(0);
+        u64::try_from(decayed).unwrap_or(u64::MAX)
+    }
+
+    /// Validate that the dynamic fee can be charged on top of the given fees
+    pub fn validate(&self, fees: &Fees) -> Result<(), SwapError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        if self.max_volatility_bps == 0 || self.decay_period == 0 {
+            return Err(SwapError::InvalidFee);
+        }
+        let max_trade_fee_numerator = fees
+            .trade_fee_numerator
+            .checked_add(self.max_fee_numerator)
+            .ok_or(SwapError::InvalidFee)?;
+        validate_fraction(max_trade_fee_numerator, fees.trade_fee_denominator)
+    }
+}
+
+impl Sealed for DynamicFee {}
+impl Pack for DynamicFee {
+    const LEN: usize = 24;
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 24];
+        let (max_fee_numerator, max_volatility_bps, decay_period) =
+            mut_array_refs![output, 8, 8, 8];
+        *max_fee_numerator = self.max_fee_numerator.to_le_bytes();
+        *max_volatility_bps = self.max_volatility_bps.to_le_bytes();
+        *decay_period = self.decay_period.to_le_bytes();
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<DynamicFee, ProgramError> {
+        let input = array_ref![input, 0, 24];
+        let (max_fee_numerator, max_volatility_bps, decay_period) = array_refs![input, 8, 8, 8];
+        Ok(Self {
+            max_fee_numerator: u64::from_le_bytes(*max_fee_numerator),
+            max_volatility_bps: u64::from_le_bytes(*max_volatility_bps),
+            decay_period: u64::from_le_bytes(*decay_period),
+        })
+    }
+}
+
 /// IsInitialized is required to use `Pack::pack` and `Pack::unpack`
 impl IsInitialized for Fees {
     fn is_initialized(&self) -> bool {
@@ -278,4 +364,49 @@ mod tests {
         let unpacked = Fees::unpack_from_slice(&packed).unwrap();
         assert_eq!(fees, unpacked);
     }
+
+    #[test]
+    fn dynamic_fee() {
+        let dynamic_fee = DynamicFee {
+            max_fee_numerator: 30,
+            max_volatility_bps: 600,
+            decay_period: 60,
+        };
+        assert_eq!(dynamic_fee.fee_numerator(0), Some(0));
+        assert_eq!(dynamic_fee.fee_numerator(200), Some(10));
+        assert_eq!(dynamic_fee.fee_numerator(600), Some(30));
+        assert_eq!(dynamic_fee.fee_numerator(u64::MAX), Some(30));
+        assert_eq!(DynamicFee::default().fee_numerator(600), Some(0));
+
+        assert_eq!(dynamic_fee.decayed_volatility_bps(600, 0), 600);
+        assert_eq!(dynamic_fee.decayed_volatility_bps(600, 15), 450);
+        assert_eq!(dynamic_fee.decayed_volatility_bps(600, 60), 0);
+        assert_eq!(dynamic_fee.decayed_volatility_bps(600, u64::MAX), 0);
+
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10000,
+            ..Fees::default()
+        };
+        assert_eq!(dynamic_fee.validate(&fees), Ok(()));
+        assert_eq!(DynamicFee::default().validate(&Fees::default()), Ok(()));
+        assert_eq!(
+            dynamic_fee.validate(&Fees::default()),
+            Err(SwapError::InvalidFee)
+        );
+        let mut invalid_fee = dynamic_fee.clone();
+        invalid_fee.max_fee_numerator = 10000 - 25;
+        assert_eq!(invalid_fee.validate(&fees), Err(SwapError::InvalidFee));
+        let mut invalid_fee = dynamic_fee.clone();
+        invalid_fee.max_volatility_bps = 0;
+        assert_eq!(invalid_fee.validate(&fees), Err(SwapError::InvalidFee));
+        let mut invalid_fee = dynamic_fee.clone();
+        invalid_fee.decay_period = 0;
+        assert_eq!(invalid_fee.validate(&fees), Err(SwapError::InvalidFee));
+
+        let mut packed = [0u8; DynamicFee::LEN];
+        Pack::pack_into_slice(&dynamic_fee, &mut packed[..]);
+        let unpacked = DynamicFee::unpack_from_slice(&packed).unwrap();
+        assert_eq!(dynamic_fee, unpacked);
+    }
 }
@@ -6,3 +6,4 @@ pub mod constant_price;
 pub mod constant_product;
 pub mod fees;
 pub mod offset;
+pub mod weighted;
@@ -4,8 +4,8 @@ use {
     crate::{
         curve::{
             calculator::{
-                CurveCalculator, DynPack, RoundDirection, SwapWithoutFeesResult, TradeDirection,
-                TradingTokenResult,
+                ratio_x64, CurveCalculator, DynPack, RoundDirection, SwapWithoutFeesResult,
+                TradeDirection, TradingTokenResult,
             },
             constant_product::{
                 deposit_single_token_type, normalized_value, pool_tokens_to_trading_tokens, swap,
@@ -154,6 +154,20 @@ impl CurveCalculator for OffsetCurve {
             swap_token_b_amount.checked_add(token_b_offset)?,
         )
     }
+
+    /// The spot prices of the offset curve also include the offset on the
+    /// token B side
+    fn spot_prices_x64(
+        &self,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+    ) -> Option<(u128, u128)> {
+        let swap_token_b_amount = swap_token_b_amount.checked_add(self.token_b_offset as u128)?;
+        Some((
+            ratio_x64(swap_token_b_amount, swap_token_a_amount)?,
+            ratio_x64(swap_token_a_amount, swap_token_b_amount)?,
+        ))
+    }
 }
 
 /// IsInitialized is required to use `Pack::pack` and `Pack::unpack`
@@ -0,0 +1,694 @@
+//! The Balancer-style weighted invariant calculator.
+
+use {
+    crate::{
+        curve::{
+            calculator::{
+                map_zero_to_none, ratio_x64, CurveCalculator, DynPack, RoundDirection,
+                SwapWithoutFeesResult, TradeDirection, TradingTokenResult,
+            },
+            constant_product::pool_tokens_to_trading_tokens,
+        },
+        error::SwapError,
+    },
+    arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs},
+    solana_program::{
+        program_error::ProgramError,
+        program_pack::{IsInitialized, Pack, Sealed},
+    },
+    spl_math::{precise_number::PreciseNumber, uint::U256},
+};
+
+/// Minimum share of the total weight held by either token, in percent.  More
+/// extreme weights make the power approximations lose too much precision.
+pub const MIN_WEIGHT_PERCENT: u64 = 1;
+
+/// Relative error, in units of 10^-12, of a square root taken by
+/// `PreciseNumber::sqrt`, which is precise to 11 digits
+const SQRT_MAX_ERROR: u128 = 10;
+
+/// Relative error of the powers approximated by `checked_pow_weight`, in units
+/// of 10^-12, per unit of the exponent they are computed with plus one.
+///
+/// `PreciseNumber::checked_pow_near_one` is within
+/// `(exponent + 1) * POW_NEAR_ONE_MAX_ERROR` of the exact power of the base it
+/// is given.  Each square root taken to bring the base near 1 is within
+/// `SQRT_MAX_ERROR`, and halves the error of the roots taken before it, so the
+/// base is within `2 * SQRT_MAX_ERROR`, which the exponent multiplies.
+const POW_ERROR_PER_EXPONENT: u128 = PreciseNumber::POW_NEAR_ONE_MAX_ERROR + 2 * SQRT_MAX_ERROR;
+
+/// Maximum number of square roots taken to bring a base close to 1, enough
+/// for any base up to u128::MAX
+const MAX_BASE_SQUARE_ROOTS: u32 = 16;
+
+/// Weighted curve, generalizing the constant product invariant to
+/// `token_a_amount ^ token_a_weight * token_b_amount ^ token_b_weight`.
+/// For example, a pool weighted 80/20 holds 80% of its value in token A.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WeightedCurve {
+    /// Weight of token A, relative to the weight of token B
+    pub token_a_weight: u64,
+    /// Weight of token B, relative to the weight of token A
+    pub token_b_weight: u64,
+}
+
+/// Raise `base`, which must be at least 1, to the power of
+/// `numerator / denominator`, returning the power and a bound on its relative
+/// error.
+///
+/// `PreciseNumber::checked_pow_near_one` only takes bases up to 3/2, so the
+/// base is first brought below 3/2 by repeatedly taking its square root and
+/// doubling the exponent.  Bases below 1 have too few significant digits for
+/// a precise result, so callers raise the reciprocal of such bases instead.
+pub fn checked_pow_weight(
+    base: &PreciseNumber,
+    numerator: u64,
+    denominator: u64,
+) -> Option<(PreciseNumber, PreciseNumber)> {
+    let one = PreciseNumber::new(1)?;
+    if base.less_than(&one) {
+        return None;
+    }
+    let upper_bound = PreciseNumber::new(3)?.checked_div(&PreciseNumber::new(2)?)?;
+    let mut base = base.clone();
+    let mut square_roots = 0;
+    while base.greater_than(&upper_bound) {
+        if square_roots == MAX_BASE_SQUARE_ROOTS {
+            return None;
+        }
+        base = base.sqrt()?;
+        square_roots += 1;
+    }
+    let numerator = u128::from(numerator).checked_shl(square_roots)?;
+    let exponent = PreciseNumber::new(numerator)?
+        .checked_div(&PreciseNumber::new(u128::from(denominator))?)?;
+    let power = base.checked_pow_near_one(&exponent)?;
+    let relative_error = exponent.checked_add(&one)?.checked_mul(&PreciseNumber {
+        value: U256::from(POW_ERROR_PER_EXPONENT),
+    })?;
+    Some((power, relative_error))
+}
+
+/// Smallest unit of a precise number, covering the rounding of the last
+/// operation on an approximated power
+fn precision_unit() -> PreciseNumber {
+    PreciseNumber {
+        value: U256::from(1),
+    }
+}
+
+/// Nudge an approximated power, or its reciprocal, which has the same relative
+/// error, above its exact value
+fn round_power_up(power: &PreciseNumber, relative_error: &PreciseNumber) -> Option<PreciseNumber> {
+    power
+        .checked_add(&power.checked_mul(relative_error)?)?
+        .checked_add(&precision_unit())
+}
+
+/// Nudge an approximated power, or its reciprocal, which has the same relative
+/// error, below its exact value, or to zero
+fn round_power_down(
+    power: &PreciseNumber,
+    relative_error: &PreciseNumber,
+) -> Option<PreciseNumber> {
+    let margin = power
+        .checked_mul(relative_error)?
+        .checked_add(&precision_unit())?;
+    Some(
+        power
+            .checked_sub(&margin)
+            .unwrap_or_else(|| PreciseNumber::new(0).unwrap()),
+    )
+}
+
+impl WeightedCurve {
+    /// Sum of both token weights
+    fn total_weight(&self) -> Option<u64> {
+        self.token_a_weight.checked_add(self.token_b_weight)
+    }
+
+    /// Weights of the source and destination tokens of a trade
+    fn weights(&self, trade_direction: TradeDirection) -> (u64, u64) {
+        match trade_direction {
+            TradeDirection::AtoB => (self.token_a_weight, self.token_b_weight),
+            TradeDirection::BtoA => (self.token_b_weight, self.token_a_weight),
+        }
+    }
+}
+
+impl CurveCalculator for WeightedCurve {
+    /// Weighted swap ensures a ^ w_a * b ^ w_b = constant, giving out:
+    ///
+    /// B_out = B * (1 - (A / (A + A_in)) ^ (w_a / w_b))
+    fn swap_without_fees(
+        &self,
+        source_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<SwapWithoutFeesResult> {
+        let (source_weight, destination_weight) = self.weights(trade_direction);
+        let new_swap_source_amount = swap_source_amount.checked_add(source_amount)?;
+        let one = PreciseNumber::new(1)?;
+        let base = PreciseNumber::new(new_swap_source_amount)?
+            .checked_div(&PreciseNumber::new(swap_source_amount)?)?;
+        // round the power up to leave more destination tokens in the pool
+        let (power, relative_error) = checked_pow_weight(&base, source_weight, destination_weight)?;
+        let power = round_power_up(&one.checked_div(&power)?, &relative_error)?;
+        let ratio_out = one.checked_sub(&power)?;
+        let destination_amount_swapped = PreciseNumber::new(swap_destination_amount)?
+            .checked_mul(&ratio_out)?
+            .floor()?
+            .to_imprecise()?;
+        Some(SwapWithoutFeesResult {
+            source_amount_swapped: source_amount,
+            destination_amount_swapped: map_zero_to_none(destination_amount_swapped)?,
+        })
+    }
+
+    /// Depositing or withdrawing both sides keeps the ratio of the pool, so
+    /// the weighted curve uses the same proportional conversion as the
+    /// constant product curve
+    fn pool_tokens_to_trading_tokens(
+        &self,
+        pool_tokens: u128,
+        pool_token_supply: u128,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+        round_direction: RoundDirection,
+    ) -> Option<TradingTokenResult> {
+        pool_tokens_to_trading_tokens(
+            pool_tokens,
+            pool_token_supply,
+            swap_token_a_amount,
+            swap_token_b_amount,
+            round_direction,
+        )
+    }
+
+    /// Get the amount of pool tokens for the deposited amount of token A or B,
+    /// using the Balancer formula:
+    ///
+    /// P_out = P * ((1 + A_in / A) ^ (w_a / (w_a + w_b)) - 1)
+    fn deposit_single_token_type(
+        &self,
+        source_amount: u128,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+        pool_supply: u128,
+        trade_direction: TradeDirection,
+    ) -> Option<u128> {
+        let swap_source_amount = match trade_direction {
+            TradeDirection::AtoB => swap_token_a_amount,
+            TradeDirection::BtoA => swap_token_b_amount,
+        };
+        let (source_weight, _) = self.weights(trade_direction);
+        let one = PreciseNumber::new(1)?;
+        let ratio = PreciseNumber::new(source_amount)?
+            .checked_div(&PreciseNumber::new(swap_source_amount)?)?;
+        let base = one.checked_add(&ratio)?;
+        // round the power down to give out fewer pool tokens
+        let (power, relative_error) =
+            checked_pow_weight(&base, source_weight, self.total_weight()?)?;
+        let growth = round_power_down(&power, &relative_error)?
+            .checked_sub(&one)
+            .unwrap_or_else(|| PreciseNumber::new(0).unwrap());
+        PreciseNumber::new(pool_supply)?
+            .checked_mul(&growth)?
+            .floor()?
+            .to_imprecise()
+    }
+
+    /// Get the amount of pool tokens for the withdrawn amount of token A or B,
+    /// using the Balancer formula:
+    ///
+    /// P_in = P * (1 - (1 - A_out / A) ^ (w_a / (w_a + w_b)))
+    fn withdraw_single_token_type_exact_out(
+        &self,
+        source_amount: u128,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+        pool_supply: u128,
+        trade_direction: TradeDirection,
+        round_direction: RoundDirection,
+    ) -> Option<u128> {
+        let swap_source_amount = match trade_direction {
+            TradeDirection::AtoB => swap_token_a_amount,
+            TradeDirection::BtoA => swap_token_b_amount,
+        };
+        let (source_weight, _) = self.weights(trade_direction);
+        let one = PreciseNumber::new(1)?;
+        let new_swap_source_amount = swap_source_amount.checked_sub(source_amount)?;
+        let base = PreciseNumber::new(swap_source_amount)?
+            .checked_div(&PreciseNumber::new(new_swap_source_amount)?)?;
+        let (power, relative_error) =
+            checked_pow_weight(&base, source_weight, self.total_weight()?)?;
+        let power = one.checked_div(&power)?;
+        let zero = PreciseNumber::new(0)?;
+        let pool_supply = PreciseNumber::new(pool_supply)?;
+        match round_direction {
+            RoundDirection::Floor => {
+                let power = round_power_up(&power, &relative_error)?;
+                let share = one.checked_sub(&power).unwrap_or(zero);
+                pool_supply.checked_mul(&share)?.floor()?.to_imprecise()
+            }
+            RoundDirection::Ceiling => {
+                let power = round_power_down(&power, &relative_error)?;
+                let share = one.checked_sub(&power)?;
+                pool_supply.checked_mul(&share)?.ceiling()?.to_imprecise()
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), SwapError> {
+        let total_weight = u128::from(self.total_weight().ok_or(SwapError::InvalidCurve)?);
+        for weight in [self.token_a_weight, self.token_b_weight] {
+            if weight == 0
+                || u128::from(weight) * 100 < total_weight * u128::from(MIN_WEIGHT_PERCENT)
+            {
+                return Err(SwapError::InvalidCurve);
+            }
+        }
+        Ok(())
+    }
+
+    /// The normalized value of the weighted curve is the weighted geometric
+    /// mean of the token amounts:
+    ///
+    /// a ^ (w_a / (w_a + w_b)) * b ^ (w_b / (w_a + w_b))
+    fn normalized_value(
+        &self,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+    ) -> Option<PreciseNumber> {
+        let total_weight = self.total_weight()?;
+        let (token_a_value, _) = checked_pow_weight(
+            &PreciseNumber::new(swap_token_a_amount)?,
+            self.token_a_weight,
+            total_weight,
+        )?;
+        let (token_b_value, _) = checked_pow_weight(
+            &PreciseNumber::new(swap_token_b_amount)?,
+            self.token_b_weight,
+            total_weight,
+        )?;
+        token_a_value.checked_mul(&token_b_value)
+    }
+
+    /// The marginal price of token A in token B on the weighted curve is
+    /// (b / w_b) / (a / w_a)
+    fn spot_prices_x64(
+        &self,
+        swap_token_a_amount: u128,
+        swap_token_b_amount: u128,
+    ) -> Option<(u128, u128)> {
+        let token_a_amount = swap_token_a_amount.checked_mul(u128::from(self.token_b_weight))?;
+        let token_b_amount = swap_token_b_amount.checked_mul(u128::from(self.token_a_weight))?;
+        Some((
+            ratio_x64(token_b_amount, token_a_amount)?,
+            ratio_x64(token_a_amount, token_b_amount)?,
+        ))
+    }
+}
+
+/// IsInitialized is required to use `Pack::pack` and `Pack::unpack`
+impl IsInitialized for WeightedCurve {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+impl Sealed for WeightedCurve {}
+impl Pack for WeightedCurve {
+    const LEN: usize = 16;
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        (self as &dyn DynPack).pack_into_slice(output);
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<WeightedCurve, ProgramError> {
+        let input = array_ref![input, 0, 16];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (token_a_weight, token_b_weight) = array_refs![input, 8, 8];
+        Ok(Self {
+            token_a_weight: u64::from_le_bytes(*token_a_weight),
+            token_b_weight: u64::from_le_bytes(*token_b_weight),
+        })
+    }
+}
+
+impl DynPack for WeightedCurve {
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 16];
+        let (token_a_weight, token_b_weight) = mut_array_refs![output, 8, 8];
+        *token_a_weight = self.token_a_weight.to_le_bytes();
+        *token_b_weight = self.token_b_weight.to_le_bytes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{
+        calculator::{
+            test::{
+                check_deposit_token_conversion, check_pool_value_from_deposit,
+                check_withdraw_token_conversion, total_and_intermediate,
+                CONVERSION_BASIS_POINTS_GUARANTEE,
+            },
+            INITIAL_SWAP_POOL_AMOUNT,
+        },
+        constant_product::swap,
+    };
+    use proptest::prelude::*;
+
+    const EIGHTY_TWENTY: WeightedCurve = WeightedCurve {
+        token_a_weight: 80,
+        token_b_weight: 20,
+    };
+
+    #[test]
+    fn pack_curve() {
+        let token_a_weight = 80;
+        let token_b_weight = u64::MAX;
+        let curve = WeightedCurve {
+            token_a_weight,
+            token_b_weight,
+        };
+
+        let mut packed = [0u8; WeightedCurve::LEN];
+        Pack::pack_into_slice(&curve, &mut packed[..]);
+        let unpacked = WeightedCurve::unpack(&packed).unwrap();
+        assert_eq!(curve, unpacked);
+
+        let mut packed = vec![];
+        packed.extend_from_slice(&token_a_weight.to_le_bytes());
+        packed.extend_from_slice(&token_b_weight.to_le_bytes());
+        let unpacked = WeightedCurve::unpack(&packed).unwrap();
+        assert_eq!(curve, unpacked);
+    }
+
+    #[test]
+    fn validate_weights() {
+        assert!(EIGHTY_TWENTY.validate().is_ok());
+        let check = |token_a_weight, token_b_weight| {
+            WeightedCurve {
+                token_a_weight,
+                token_b_weight,
+            }
+            .validate()
+        };
+        assert_eq!(check(1, 99), Ok(()));
+        assert_eq!(check(0, 1), Err(SwapError::InvalidCurve));
+        assert_eq!(check(1, 0), Err(SwapError::InvalidCurve));
+        assert_eq!(check(1, 100), Err(SwapError::InvalidCurve));
+        assert_eq!(check(u64::MAX, 1), Err(SwapError::InvalidCurve));
+    }
+
+    #[test]
+    fn pow_weight() {
+        for (base, numerator, denominator) in [
+            (1.0, 1, 2),
+            (1.000_001, 4, 1),
+            (1_000_000.0, 1, 4),
+            (1.5, 20, 100),
+            (1_000_000.0, 80, 100),
+            (18_000_000_000_000_000_000.0, 99, 100),
+        ] {
+            let expected = f64::powf(base, numerator as f64 / denominator as f64);
+            let precise_base = PreciseNumber {
+                value: U256::from((base * 1_000_000_000_000.0) as u128),
+            };
+            let (power, max_relative_error) =
+                checked_pow_weight(&precise_base, numerator, denominator).unwrap();
+            let power = power.value.as_u128() as f64 / 1_000_000_000_000.0;
+            let relative_error = (power - expected).abs() / expected;
+            assert!(relative_error <= max_relative_error.value.as_u128() as f64 / 1e12);
+            assert!(
+                relative_error < 1e-9,
+                "{}^({}/{}) gave {}, expected {}",
+                base,
+                numerator,
+                denominator,
+                power,
+                expected
+            );
+        }
+
+        // bases below 1 are not supported
+        let base = PreciseNumber::new(1)
+            .unwrap()
+            .checked_div(&PreciseNumber::new(2).unwrap())
+            .unwrap();
+        assert!(checked_pow_weight(&base, 1, 2).is_none());
+    }
+
+    #[test]
+    fn pow_weight_error_bound_with_extreme_weights() {
+        // the most extreme weights allowed give the largest exponents, and
+        // bases far from 1 need the most square roots
+        let extreme = 100 - MIN_WEIGHT_PERCENT;
+        let exponents = [
+            (extreme, MIN_WEIGHT_PERCENT),
+            (MIN_WEIGHT_PERCENT, extreme),
+            (extreme, 100),
+            (MIN_WEIGHT_PERCENT, 100),
+        ];
+        let mut bases = vec![1_000_000_000_001u128, 1_000_001_000_000, 1_499_999_999_999];
+        let mut base = 1_500_000_000_001u128;
+        while let Some(next_base) = base.checked_mul(7) {
+            bases.push(base);
+            base = next_base;
+        }
+        for (numerator, denominator) in exponents {
+            for base in bases.iter() {
+                let precise_base = PreciseNumber {
+                    value: U256::from(*base),
+                };
+                // the power can overflow for large bases and exponents
+                let Some((power, max_relative_error)) =
+                    checked_pow_weight(&precise_base, numerator, denominator)
+                else {
+                    continue;
+                };
+                let exponent = numerator as f64 / denominator as f64;
+                let expected = (*base as f64 / 1e12).powf(exponent);
+                let power = power.value.as_u128() as f64 / 1e12;
+                let max_relative_error = max_relative_error.value.as_u128() as f64 / 1e12;
+                assert!(
+                    (power - expected).abs() <= expected * max_relative_error,
+                    "{}^({}/{}) gave {}, expected {} within {}",
+                    *base as f64 / 1e12,
+                    numerator,
+                    denominator,
+                    power,
+                    expected,
+                    max_relative_error
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn swap_eighty_twenty() {
+        let swap_source_amount: u128 = 1_000_000;
+        let swap_destination_amount: u128 = 1_000_000;
+        let source_amount: u128 = 100_000;
+
+        // 1_000_000 * (1 - (1 / 1.1) ^ 4)
+        let result = EIGHTY_TWENTY
+            .swap_without_fees(
+                source_amount,
+                swap_source_amount,
+                swap_destination_amount,
+                TradeDirection::AtoB,
+            )
+            .unwrap();
+        assert_eq!(result.source_amount_swapped, source_amount);
+        assert_eq!(result.destination_amount_swapped, 316_986);
+
+        // 1_000_000 * (1 - (1 / 1.1) ^ (1 / 4))
+        let result = EIGHTY_TWENTY
+            .swap_without_fees(
+                source_amount,
+                swap_source_amount,
+                swap_destination_amount,
+                TradeDirection::BtoA,
+            )
+            .unwrap();
+        assert_eq!(result.source_amount_swapped, source_amount);
+        assert_eq!(result.destination_amount_swapped, 23_545);
+    }
+
+    #[test]
+    fn even_weights_match_constant_product() {
+        let curve = WeightedCurve {
+            token_a_weight: 1,
+            token_b_weight: 1,
+        };
+        let swap_source_amount: u128 = 1_000_000_000;
+        let swap_destination_amount: u128 = 50_000_000_000;
+        let source_amount: u128 = 123_456_789;
+        let weighted = curve
+            .swap_without_fees(
+                source_amount,
+                swap_source_amount,
+                swap_destination_amount,
+                TradeDirection::AtoB,
+            )
+            .unwrap();
+        let constant_product =
+            swap(source_amount, swap_source_amount, swap_destination_amount).unwrap();
+        assert!(weighted.destination_amount_swapped <= constant_product.destination_amount_swapped);
+        assert!(
+            constant_product.destination_amount_swapped - weighted.destination_amount_swapped
+                <= 100
+        );
+    }
+
+    #[test]
+    fn spot_prices() {
+        let one_x64 = 1u128 << 64;
+        // 80% of the value is in token A, so 1 A is worth 4 B
+        assert_eq!(
+            EIGHTY_TWENTY.spot_prices_x64(1_000, 1_000).unwrap(),
+            (4 * one_x64, one_x64 / 4)
+        );
+    }
+
+    prop_compose! {
+        pub fn weights(min_weight: u64)(token_a_weight in min_weight..=100 - min_weight)
+                        -> WeightedCurve {
+            WeightedCurve { token_a_weight, token_b_weight: 100 - token_a_weight }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn deposit_token_conversion(
+            // in the pool token conversion calcs, we simulate trading half of
+            // source_token_amount, so this needs to be at least 2
+            // keep the implicit swap small enough to not drain the other side,
+            // by depositing less than what is already in the pool
+            (swap_source_amount, source_token_amount) in total_and_intermediate(u32::MAX as u64),
+            swap_destination_amount in 1_000_000..u32::MAX as u64,
+            pool_supply in INITIAL_SWAP_POOL_AMOUNT..u64::MAX as u128,
+            curve in weights(20),
+        ) {
+            prop_assume!(source_token_amount >= 2);
+            prop_assume!(swap_source_amount >= 1_000_000);
+            for trade_direction in [TradeDirection::AtoB, TradeDirection::BtoA] {
+                check_deposit_token_conversion(
+                    &curve,
+                    source_token_amount as u128,
+                    swap_source_amount as u128,
+                    swap_destination_amount as u128,
+                    trade_direction,
+                    pool_supply,
+                    CONVERSION_BASIS_POINTS_GUARANTEE,
+                );
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn withdraw_token_conversion(
+            (pool_token_supply, pool_token_amount) in total_and_intermediate(u64::MAX),
+            swap_token_a_amount in 1_000_000..u32::MAX,
+            swap_token_b_amount in 1_000_000..u32::MAX,
+            curve in weights(20),
+        ) {
+            // keep the implicit swap small enough to not drain the other side,
+            // by withdrawing at most half of the pool
+            let pool_token_amount = (pool_token_amount / 2).max(1) as u128;
+            let pool_token_supply = pool_token_supply as u128;
+            let swap_token_a_amount = swap_token_a_amount as u128;
+            let swap_token_b_amount = swap_token_b_amount as u128;
+            prop_assume!(pool_token_amount * swap_token_a_amount / pool_token_supply >= 1);
+            prop_assume!(pool_token_amount * swap_token_b_amount / pool_token_supply >= 1);
+            for trade_direction in [TradeDirection::AtoB, TradeDirection::BtoA] {
+                check_withdraw_token_conversion(
+                    &curve,
+                    pool_token_amount,
+                    pool_token_supply,
+                    swap_token_a_amount,
+                    swap_token_b_amount,
+                    trade_direction,
+                    CONVERSION_BASIS_POINTS_GUARANTEE,
+                );
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn swap_never_exceeds_exact_curve(
+            source_token_amount in 1..u64::MAX,
+            swap_source_amount in 1..u64::MAX,
+            swap_destination_amount in 1..u64::MAX,
+            curve in weights(MIN_WEIGHT_PERCENT),
+        ) {
+            for trade_direction in [TradeDirection::AtoB, TradeDirection::BtoA] {
+                let (source_weight, destination_weight) = curve.weights(trade_direction);
+                let base = swap_source_amount as f64
+                    / (swap_source_amount as f64 + source_token_amount as f64);
+                let exponent = source_weight as f64 / destination_weight as f64;
+                let exact = swap_destination_amount as f64 * (1.0 - base.powf(exponent));
+                // tiny trades can round down to nothing, and huge ones can
+                // overflow the power
+                if let Some(result) = curve.swap_without_fees(
+                    source_token_amount as u128,
+                    swap_source_amount as u128,
+                    swap_destination_amount as u128,
+                    trade_direction,
+                ) {
+                    let destination_amount_swapped = result.destination_amount_swapped as f64;
+                    prop_assert!(destination_amount_swapped <= exact.ceil());
+                    // the pool keeps at most the error of the power, and the
+                    // margin covering it
+                    let precise_base = PreciseNumber::new(
+                        swap_source_amount as u128 + source_token_amount as u128,
+                    )
+                    .unwrap()
+                    .checked_div(&PreciseNumber::new(swap_source_amount as u128).unwrap())
+                    .unwrap();
+                    let (_, max_relative_error) =
+                        checked_pow_weight(&precise_base, source_weight, destination_weight)
+                            .unwrap();
+                    let max_relative_error = max_relative_error.value.as_u128() as f64 / 1e12;
+                    prop_assert!(
+                        exact - destination_amount_swapped
+                            <= swap_destination_amount as f64 * (2.0 * max_relative_error + 1e-9)
+                                + 1.0
+                    );
+                }
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn curve_value_does_not_decrease_from_deposit(
+            pool_token_amount in 1..u64::MAX,
+            pool_token_supply in 1..u64::MAX,
+            swap_token_a_amount in 1..u64::MAX,
+            swap_token_b_amount in 1..u64::MAX,
+            curve in weights(MIN_WEIGHT_PERCENT),
+        ) {
+            let pool_token_amount = pool_token_amount as u128;
+            let pool_token_supply = pool_token_supply as u128;
+            let swap_token_a_amount = swap_token_a_amount as u128;
+            let swap_token_b_amount = swap_token_b_amount as u128;
+            // Make sure we will get at least one trading token out for each
+            // side, otherwise the calculation fails
+            prop_assume!(pool_token_amount * swap_token_a_amount / pool_token_supply >= 1);
+            prop_assume!(pool_token_amount * swap_token_b_amount / pool_token_supply >= 1);
+            check_pool_value_from_deposit(
+                &curve,
+                pool_token_amount,
+                pool_token_supply,
+                swap_token_a_amount,
+                swap_token_b_amount,
+            );
+        }
+    }
+}
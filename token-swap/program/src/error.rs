@@ -109,6 +109,41 @@ pub enum SwapError {
     /// The pool fee account is invalid.
     #[error("The pool fee account is invalid")]
     InvalidFeeAccount,
+    /// The tick range of the position is invalid
+    #[error("The tick range of the position is invalid")]
+    InvalidTickRange,
+
+    // 30.
+    /// The pool has no room for more initialized ticks
+    #[error("The pool has no room for more initialized ticks")]
+    TickCapacityExceeded,
+    /// The position does not belong to the pool or owner
+    #[error("The position does not belong to the pool or owner")]
+    InvalidPosition,
+    /// The position still holds liquidity or uncollected fees
+    #[error("The position still holds liquidity or uncollected fees")]
+    PositionNotEmpty,
+    /// The operation is not supported by the pool type
+    #[error("The operation is not supported by the pool type")]
+    UnsupportedPoolType,
+    /// The pool does not have enough liquidity for the operation
+    #[error("The pool does not have enough liquidity for the operation")]
+    InsufficientLiquidity,
+
+    // 35.
+    /// The pool has no admin authority, or it did not sign
+    #[error("The pool has no admin authority, or it did not sign")]
+    InvalidAdminAuthority,
+    /// A pool of a routed swap did not swap all of the tokens out of the
+    /// previous pool
+    #[error("A pool of the route did not swap all of the tokens out of the previous pool")]
+    IncompleteRoutedSwap,
+    /// The position would hold less than the minimum liquidity
+    #[error("The position would hold less than the minimum liquidity")]
+    PositionLiquidityTooLow,
+    /// The position account does not hold the deposit for its boundary ticks
+    #[error("The position account does not hold the deposit for its boundary ticks")]
+    InsufficientTickDeposit,
 }
 impl From<SwapError> for ProgramError {
     fn from(e: SwapError) -> Self {
@@ -192,6 +227,36 @@ impl PrintProgramError for SwapError {
             SwapError::InvalidFeeAccount => {
                 msg!("Error: The pool fee account is invalid")
             }
+            SwapError::InvalidTickRange => {
+                msg!("Error: The tick range of the position is invalid")
+            }
+            SwapError::TickCapacityExceeded => {
+                msg!("Error: The pool has no room for more initialized ticks")
+            }
+            SwapError::InvalidPosition => {
+                msg!("Error: The position does not belong to the pool or owner")
+            }
+            SwapError::PositionNotEmpty => {
+                msg!("Error: The position still holds liquidity or uncollected fees")
+            }
+            SwapError::UnsupportedPoolType => {
+                msg!("Error: The operation is not supported by the pool type")
+            }
+            SwapError::InsufficientLiquidity => {
+                msg!("Error: The pool does not have enough liquidity for the operation")
+            }
+            SwapError::InvalidAdminAuthority => {
+                msg!("Error: The pool has no admin authority, or it did not sign")
+            }
+            SwapError::IncompleteRoutedSwap => {
+                msg!("Error: A pool of the route did not swap all of the tokens out of the previous pool")
+            }
+            SwapError::PositionLiquidityTooLow => {
+                msg!("Error: The position would hold less than the minimum liquidity")
+            }
+            SwapError::InsufficientTickDeposit => {
+                msg!("Error: The position account does not hold the deposit for its boundary ticks")
+            }
         }
     }
 }
@@ -2,7 +2,10 @@
 
 #![allow(clippy::too_many_arguments)]
 
-use crate::curve::{base::SwapCurve, fees::Fees};
+use crate::curve::{
+    base::SwapCurve,
+    fees::{DynamicFee, Fees},
+};
 use crate::error::SwapError;
 use solana_program::{
     instruction::{AccountMeta, Instruction},
@@ -10,6 +13,11 @@ use solana_program::{
     program_pack::Pack,
     pubkey::Pubkey,
 };
+use spl_tlv_account_resolution::state::ExtraAccountMetaList;
+use spl_transfer_hook_interface::{
+    get_extra_account_metas_address,
+    instruction::{execute, ExecuteInstruction},
+};
 use std::convert::TryInto;
 use std::mem::size_of;
 
@@ -27,6 +35,16 @@ pub struct Initialize {
     pub swap_curve: SwapCurve,
 }
 
+/// SetFees instruction data
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetFees {
+    /// New swap fees
+    pub fees: Fees,
+    /// New volatility-based trade fee, disabled if its maximum fee is zero
+    pub dynamic_fee: DynamicFee,
+}
+
 /// Swap instruction data
 #[cfg_attr(feature = "fuzz", derive(Arbitrary))]
 #[repr(C)]
@@ -38,6 +56,21 @@ pub struct Swap {
     pub minimum_amount_out: u64,
 }
 
+/// RoutedSwap instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoutedSwap {
+    /// SOURCE amount to transfer into the first pool
+    pub amount_in: u64,
+    /// Minimum amount of DESTINATION token out of the last pool, prevents
+    /// excessive slippage
+    pub minimum_amount_out: u64,
+    /// Number of pools in the route, each given by
+    /// `RoutedSwapPool::ACCOUNTS_LEN` accounts
+    pub pool_count: u8,
+}
+
 /// DepositAllTokenTypes instruction data
 #[cfg_attr(feature = "fuzz", derive(Arbitrary))]
 #[repr(C)]
@@ -90,6 +123,59 @@ pub struct WithdrawSingleTokenTypeExactAmountOut {
     pub maximum_pool_token_amount: u64,
 }
 
+/// InitializeConcentrated instruction data
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct InitializeConcentrated {
+    /// all swap fees, only trade fees may be non-zero
+    pub fees: Fees,
+    /// Position boundaries must be multiples of the tick spacing
+    pub tick_spacing: u16,
+    /// Initial square root price of token A in token B, as a Q64.64 number
+    pub sqrt_price_x64: u128,
+}
+
+/// OpenPosition instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpenPosition {
+    /// Lower tick of the position range, inclusive
+    pub tick_lower: i32,
+    /// Upper tick of the position range, exclusive
+    pub tick_upper: i32,
+}
+
+/// IncreaseLiquidity instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct IncreaseLiquidity {
+    /// Liquidity to add to the position. token_a and token_b amounts are set
+    /// by the current price and the range of the position
+    pub liquidity_amount: u128,
+    /// Maximum token A amount to deposit, prevents excessive slippage
+    pub maximum_token_a_amount: u64,
+    /// Maximum token B amount to deposit, prevents excessive slippage
+    pub maximum_token_b_amount: u64,
+}
+
+/// DecreaseLiquidity instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecreaseLiquidity {
+    /// Liquidity to remove from the position, may be zero to only collect
+    /// the fees earned by the position
+    pub liquidity_amount: u128,
+    /// Minimum amount of token A to receive, excluding fees, prevents
+    /// excessive slippage
+    pub minimum_token_a_amount: u64,
+    /// Minimum amount of token B to receive, excluding fees, prevents
+    /// excessive slippage
+    pub minimum_token_b_amount: u64,
+}
+
 /// Instructions supported by the token swap program.
 #[repr(C)]
 #[derive(Debug, PartialEq)]
@@ -106,11 +192,14 @@ pub enum SwapInstruction {
     ///   6. `[writable]` Pool Token Account to deposit the initial pool token
     ///   supply.  Must be empty, not owned by swap authority.
     ///   7. `[]` Pool Token program id
+    ///   8. `[optional, signer]` Admin authority, allowed to update the fees
+    ///   and the pool fee account.  Only supported by swaps of the latest
+    ///   version.
     Initialize(Initialize),
 
     ///   Swap the tokens in the pool.
     ///
-    ///   0. `[]` Token-swap
+    ///   0. `[writable]` Token-swap
     ///   1. `[]` swap authority
     ///   2. `[]` user transfer authority
     ///   3. `[writable]` token_(A|B) SOURCE Account, amount is transferable by user transfer authority,
@@ -124,14 +213,18 @@ pub enum SwapInstruction {
     ///   11. `[]` Token (A|B) SOURCE program id
     ///   12. `[]` Token (A|B) DESTINATION program id
     ///   13. `[]` Pool Token program id
-    ///   14. `[optional, writable]` Host fee account to receive additional trading fees
+    ///   14. `[optional, writable]` Host fee account to receive additional trading fees,
+    ///   or the swap program id if there is no host fee account.  Required if
+    ///   any transfer hook accounts follow.
+    ///   15. ..15+N `[]` Accounts required by the transfer hooks of the token
+    ///   mints, if any
     Swap(Swap),
 
     ///   Deposit both types of tokens into the pool.  The output is a "pool"
     ///   token representing ownership in the pool. Inputs are converted to
     ///   the current ratio.
     ///
-    ///   0. `[]` Token-swap
+    ///   0. `[writable]` Token-swap
     ///   1. `[]` swap authority
     ///   2. `[]` user transfer authority
     ///   3. `[writable]` token_a user transfer authority can transfer amount,
@@ -145,13 +238,15 @@ pub enum SwapInstruction {
     ///   11. `[]` Token A program id
     ///   12. `[]` Token B program id
     ///   13. `[]` Pool Token program id
+    ///   14. ..14+N `[]` Accounts required by the transfer hooks of the token
+    ///   mints, if any
     DepositAllTokenTypes(DepositAllTokenTypes),
 
     ///   Withdraw both types of tokens from the pool at the current ratio, given
     ///   pool tokens.  The pool tokens are burned in exchange for an equivalent
     ///   amount of token A and B.
     ///
-    ///   0. `[]` Token-swap
+    ///   0. `[writable]` Token-swap
     ///   1. `[]` swap authority
     ///   2. `[]` user transfer authority
     ///   3. `[writable]` Pool mint account, swap authority is the owner
@@ -166,13 +261,15 @@ pub enum SwapInstruction {
     ///   12. `[]` Pool Token program id
     ///   13. `[]` Token A program id
     ///   14. `[]` Token B program id
+    ///   15. ..15+N `[]` Accounts required by the transfer hooks of the token
+    ///   mints, if any
     WithdrawAllTokenTypes(WithdrawAllTokenTypes),
 
     ///   Deposit one type of tokens into the pool.  The output is a "pool" token
     ///   representing ownership into the pool. Input token is converted as if
     ///   a swap and deposit all token types were performed.
     ///
-    ///   0. `[]` Token-swap
+    ///   0. `[writable]` Token-swap
     ///   1. `[]` swap authority
     ///   2. `[]` user transfer authority
     ///   3. `[writable]` token_(A|B) SOURCE Account, amount is transferable by user transfer authority,
@@ -183,12 +280,14 @@ pub enum SwapInstruction {
     ///   8. `[]` Token (A|B) SOURCE mint
     ///   9. `[]` Token (A|B) SOURCE program id
     ///   10. `[]` Pool Token program id
+    ///   11. ..11+N `[]` Accounts required by the transfer hook of the token
+    ///   mint, if any
     DepositSingleTokenTypeExactAmountIn(DepositSingleTokenTypeExactAmountIn),
 
     ///   Withdraw one token type from the pool at the current ratio given the
     ///   exact amount out expected.
     ///
-    ///   0. `[]` Token-swap
+    ///   0. `[writable]` Token-swap
     ///   1. `[]` swap authority
     ///   2. `[]` user transfer authority
     ///   3. `[writable]` Pool mint account, swap authority is the owner
@@ -200,7 +299,138 @@ pub enum SwapInstruction {
     ///   9. `[]` Token (A|B) DESTINATION mint
     ///   10. `[]` Pool Token program id
     ///   11. `[]` Token (A|B) DESTINATION program id
+    ///   12. ..12+N `[]` Accounts required by the transfer hook of the token
+    ///   mint, if any
     WithdrawSingleTokenTypeExactAmountOut(WithdrawSingleTokenTypeExactAmountOut),
+
+    ///   Initializes a new concentrated liquidity pool, where liquidity is
+    ///   provided through positions over tick ranges instead of pool tokens.
+    ///
+    ///   0. `[writable, signer]` New Token-swap to create.
+    ///   1. `[]` swap authority derived from `create_program_address(&[Token-swap account])`
+    ///   2. `[]` token_a Account. Must be empty, owned by swap authority.
+    ///   3. `[]` token_b Account. Must be empty, owned by swap authority.
+    ///   4. `[]` Token program id
+    InitializeConcentrated(InitializeConcentrated),
+
+    ///   Opens an empty position over a tick range of a concentrated liquidity
+    ///   pool. The position account must be rent exempt and also hold
+    ///   `TICK_DEPOSIT_LAMPORTS` for each of its two boundary ticks, all of
+    ///   which is returned by `ClosePosition`.
+    ///
+    ///   0. `[]` Token-swap
+    ///   1. `[writable, signer]` New position account, owned by the program
+    ///   2. `[signer]` Position owner
+    OpenPosition(OpenPosition),
+
+    ///   Adds liquidity to a position. Inputs are set by the current price and
+    ///   the range of the position. A position holding any liquidity must
+    ///   hold at least `MIN_POSITION_LIQUIDITY`.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[writable]` Position
+    ///   2. `[signer]` Position owner
+    ///   3. `[signer]` user transfer authority
+    ///   4. `[writable]` token_a user transfer authority can transfer amount,
+    ///   5. `[writable]` token_b user transfer authority can transfer amount,
+    ///   6. `[writable]` token_a Base Account to deposit into.
+    ///   7. `[writable]` token_b Base Account to deposit into.
+    ///   8. `[]` Token A mint
+    ///   9. `[]` Token B mint
+    ///   10. `[]` Token A program id
+    ///   11. `[]` Token B program id
+    ///   12. ..12+N `[]` Accounts required by the transfer hooks of the token
+    ///   mints, if any
+    IncreaseLiquidity(IncreaseLiquidity),
+
+    ///   Removes liquidity from a position, and pays out the removed tokens
+    ///   along with all fees earned by the position. The position must be left
+    ///   with either no liquidity or at least `MIN_POSITION_LIQUIDITY`.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[]` swap authority
+    ///   2. `[writable]` Position
+    ///   3. `[signer]` Position owner
+    ///   4. `[writable]` token_a Swap Account to withdraw FROM.
+    ///   5. `[writable]` token_b Swap Account to withdraw FROM.
+    ///   6. `[writable]` token_a user Account to credit.
+    ///   7. `[writable]` token_b user Account to credit.
+    ///   8. `[]` Token A mint
+    ///   9. `[]` Token B mint
+    ///   10. `[]` Token A program id
+    ///   11. `[]` Token B program id
+    ///   12. ..12+N `[]` Accounts required by the transfer hooks of the token
+    ///   mints, if any
+    DecreaseLiquidity(DecreaseLiquidity),
+
+    ///   Swap the tokens in a concentrated liquidity pool.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[]` swap authority
+    ///   2. `[signer]` user transfer authority
+    ///   3. `[writable]` token_(A|B) SOURCE Account, amount is transferable by user transfer authority,
+    ///   4. `[writable]` token_(A|B) Base Account to swap INTO.  Must be the SOURCE token.
+    ///   5. `[writable]` token_(A|B) Base Account to swap FROM.  Must be the DESTINATION token.
+    ///   6. `[writable]` token_(A|B) DESTINATION Account assigned to USER as the owner.
+    ///   7. `[]` Token (A|B) SOURCE mint
+    ///   8. `[]` Token (A|B) DESTINATION mint
+    ///   9. `[]` Token (A|B) SOURCE program id
+    ///   10. `[]` Token (A|B) DESTINATION program id
+    ///   11. ..11+N `[]` Accounts required by the transfer hooks of the token
+    ///   mints, if any
+    ConcentratedSwap(Swap),
+
+    ///   Closes an empty position, returning its lamports.
+    ///
+    ///   0. `[]` Token-swap
+    ///   1. `[writable]` Position
+    ///   2. `[signer]` Position owner
+    ///   3. `[writable]` Account to receive the position lamports
+    ClosePosition,
+
+    ///   Swap the tokens through a sequence of pools, where the tokens out of
+    ///   each pool are swapped in the next one.  Every pool after the first
+    ///   must swap all of the tokens out of the previous one.  Slippage is
+    ///   only checked on the output of the last pool.
+    ///
+    ///   0. `[signer]` user transfer authority
+    ///   1. `[writable]` SOURCE Account, amount is transferable by user transfer authority,
+    ///   2. `[writable]` DESTINATION Account assigned to USER as the owner.
+    ///
+    ///   For each of the `pool_count` pools of the route, in order:
+    ///   3. `[writable]` Token-swap
+    ///   4. `[]` swap authority
+    ///   5. `[writable]` token_(A|B) Base Account to swap INTO.  Must be the
+    ///   token out of the previous pool, or the SOURCE token for the first pool.
+    ///   6. `[writable]` token_(A|B) Base Account to swap FROM.
+    ///   7. `[writable]` Pool token mint, to generate trading fees
+    ///   8. `[writable]` Fee account, to receive trading fees
+    ///   9. `[]` Token (A|B) mint to swap INTO
+    ///   10. `[]` Token (A|B) mint to swap FROM
+    ///   11. `[]` Token (A|B) program id to swap INTO
+    ///   12. `[]` Token (A|B) program id to swap FROM
+    ///   13. `[]` Pool Token program id
+    ///
+    ///   3+11*P. ..3+11*P+N `[]` Accounts required by the transfer hooks of
+    ///   the token mints, if any
+    RoutedSwap(RoutedSwap),
+
+    ///   Update the fees of the swap.  The fees must satisfy the program
+    ///   owner's constraints, if any.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[signer]` Admin authority
+    SetFees(SetFees),
+
+    ///   Update the pool token account receiving the trading and withdraw
+    ///   fees of the swap.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[signer]` Admin authority
+    ///   2. `[]` New pool token account to receive the fees.  Must not be
+    ///   owned by swap authority, and must be owned by the program owner if
+    ///   constrained.
+    SetPoolFeeAccount,
 }
 
 impl SwapInstruction {
@@ -262,6 +492,80 @@ impl SwapInstruction {
                     maximum_pool_token_amount,
                 })
             }
+            6 => {
+                if rest.len() >= Fees::LEN {
+                    let (fees, rest) = rest.split_at(Fees::LEN);
+                    let fees = Fees::unpack_unchecked(fees)?;
+                    let (tick_spacing, rest) = Self::unpack_u16(rest)?;
+                    let (sqrt_price_x64, _rest) = Self::unpack_u128(rest)?;
+                    Self::InitializeConcentrated(InitializeConcentrated {
+                        fees,
+                        tick_spacing,
+                        sqrt_price_x64,
+                    })
+                } else {
+                    return Err(SwapError::InvalidInstruction.into());
+                }
+            }
+            7 => {
+                let (tick_lower, rest) = Self::unpack_i32(rest)?;
+                let (tick_upper, _rest) = Self::unpack_i32(rest)?;
+                Self::OpenPosition(OpenPosition {
+                    tick_lower,
+                    tick_upper,
+                })
+            }
+            8 => {
+                let (liquidity_amount, rest) = Self::unpack_u128(rest)?;
+                let (maximum_token_a_amount, rest) = Self::unpack_u64(rest)?;
+                let (maximum_token_b_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::IncreaseLiquidity(IncreaseLiquidity {
+                    liquidity_amount,
+                    maximum_token_a_amount,
+                    maximum_token_b_amount,
+                })
+            }
+            9 => {
+                let (liquidity_amount, rest) = Self::unpack_u128(rest)?;
+                let (minimum_token_a_amount, rest) = Self::unpack_u64(rest)?;
+                let (minimum_token_b_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::DecreaseLiquidity(DecreaseLiquidity {
+                    liquidity_amount,
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
+                })
+            }
+            10 => {
+                let (amount_in, rest) = Self::unpack_u64(rest)?;
+                let (minimum_amount_out, _rest) = Self::unpack_u64(rest)?;
+                Self::ConcentratedSwap(Swap {
+                    amount_in,
+                    minimum_amount_out,
+                })
+            }
+            11 => Self::ClosePosition,
+            12 => {
+                let (amount_in, rest) = Self::unpack_u64(rest)?;
+                let (minimum_amount_out, rest) = Self::unpack_u64(rest)?;
+                let (&pool_count, _rest) =
+                    rest.split_first().ok_or(SwapError::InvalidInstruction)?;
+                Self::RoutedSwap(RoutedSwap {
+                    amount_in,
+                    minimum_amount_out,
+                    pool_count,
+                })
+            }
+            13 => {
+                if rest.len() >= Fees::LEN + DynamicFee::LEN {
+                    let (fees, rest) = rest.split_at(Fees::LEN);
+                    let fees = Fees::unpack_unchecked(fees)?;
+                    let dynamic_fee = DynamicFee::unpack_unchecked(&rest[..DynamicFee::LEN])?;
+                    Self::SetFees(SetFees { fees, dynamic_fee })
+                } else {
+                    return Err(SwapError::InvalidInstruction.into());
+                }
+            }
+            14 => Self::SetPoolFeeAccount,
             _ => return Err(SwapError::InvalidInstruction.into()),
         })
     }
@@ -280,6 +584,48 @@ impl SwapInstruction {
         }
     }
 
+    fn unpack_u128(input: &[u8]) -> Result<(u128, &[u8]), ProgramError> {
+        if input.len() >= 16 {
+            let (amount, rest) = input.split_at(16);
+            let amount = amount
+                .get(..16)
+                .and_then(|slice| slice.try_into().ok())
+                .map(u128::from_le_bytes)
+                .ok_or(SwapError::InvalidInstruction)?;
+            Ok((amount, rest))
+        } else {
+            Err(SwapError::InvalidInstruction.into())
+        }
+    }
+
+    fn unpack_i32(input: &[u8]) -> Result<(i32, &[u8]), ProgramError> {
+        if input.len() >= 4 {
+            let (value, rest) = input.split_at(4);
+            let value = value
+                .get(..4)
+                .and_then(|slice| slice.try_into().ok())
+                .map(i32::from_le_bytes)
+                .ok_or(SwapError::InvalidInstruction)?;
+            Ok((value, rest))
+        } else {
+            Err(SwapError::InvalidInstruction.into())
+        }
+    }
+
+    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        if input.len() >= 2 {
+            let (value, rest) = input.split_at(2);
+            let value = value
+                .get(..2)
+                .and_then(|slice| slice.try_into().ok())
+                .map(u16::from_le_bytes)
+                .ok_or(SwapError::InvalidInstruction)?;
+            Ok((value, rest))
+        } else {
+            Err(SwapError::InvalidInstruction.into())
+        }
+    }
+
     /// Packs a [SwapInstruction](enum.SwapInstruction.html) into a byte buffer.
     pub fn pack(&self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(size_of::<Self>());
@@ -339,6 +685,75 @@ impl SwapInstruction {
                 buf.extend_from_slice(&destination_token_amount.to_le_bytes());
                 buf.extend_from_slice(&maximum_pool_token_amount.to_le_bytes());
             }
+            Self::InitializeConcentrated(InitializeConcentrated {
+                fees,
+                tick_spacing,
+                sqrt_price_x64,
+            }) => {
+                buf.push(6);
+                let mut fees_slice = [0u8; Fees::LEN];
+                Pack::pack_into_slice(fees, &mut fees_slice[..]);
+                buf.extend_from_slice(&fees_slice);
+                buf.extend_from_slice(&tick_spacing.to_le_bytes());
+                buf.extend_from_slice(&sqrt_price_x64.to_le_bytes());
+            }
+            Self::OpenPosition(OpenPosition {
+                tick_lower,
+                tick_upper,
+            }) => {
+                buf.push(7);
+                buf.extend_from_slice(&tick_lower.to_le_bytes());
+                buf.extend_from_slice(&tick_upper.to_le_bytes());
+            }
+            Self::IncreaseLiquidity(IncreaseLiquidity {
+                liquidity_amount,
+                maximum_token_a_amount,
+                maximum_token_b_amount,
+            }) => {
+                buf.push(8);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+                buf.extend_from_slice(&maximum_token_a_amount.to_le_bytes());
+                buf.extend_from_slice(&maximum_token_b_amount.to_le_bytes());
+            }
+            Self::DecreaseLiquidity(DecreaseLiquidity {
+                liquidity_amount,
+                minimum_token_a_amount,
+                minimum_token_b_amount,
+            }) => {
+                buf.push(9);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+                buf.extend_from_slice(&minimum_token_a_amount.to_le_bytes());
+                buf.extend_from_slice(&minimum_token_b_amount.to_le_bytes());
+            }
+            Self::ConcentratedSwap(Swap {
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                buf.push(10);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+                buf.extend_from_slice(&minimum_amount_out.to_le_bytes());
+            }
+            Self::ClosePosition => buf.push(11),
+            Self::RoutedSwap(RoutedSwap {
+                amount_in,
+                minimum_amount_out,
+                pool_count,
+            }) => {
+                buf.push(12);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+                buf.extend_from_slice(&minimum_amount_out.to_le_bytes());
+                buf.push(*pool_count);
+            }
+            Self::SetFees(SetFees { fees, dynamic_fee }) => {
+                buf.push(13);
+                let mut fees_slice = [0u8; Fees::LEN];
+                Pack::pack_into_slice(fees, &mut fees_slice[..]);
+                buf.extend_from_slice(&fees_slice);
+                let mut dynamic_fee_slice = [0u8; DynamicFee::LEN];
+                Pack::pack_into_slice(dynamic_fee, &mut dynamic_fee_slice[..]);
+                buf.extend_from_slice(&dynamic_fee_slice);
+            }
+            Self::SetPoolFeeAccount => buf.push(14),
         }
         buf
     }
@@ -355,13 +770,14 @@ pub fn initialize(
     pool_pubkey: &Pubkey,
     fee_pubkey: &Pubkey,
     destination_pubkey: &Pubkey,
+    admin_authority_pubkey: Option<&Pubkey>,
     fees: Fees,
     swap_curve: SwapCurve,
 ) -> Result<Instruction, ProgramError> {
     let init_data = SwapInstruction::Initialize(Initialize { fees, swap_curve });
     let data = init_data.pack();
 
-    let accounts = vec![
+    let mut accounts = vec![
         AccountMeta::new(*swap_pubkey, true),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*token_a_pubkey, false),
@@ -371,6 +787,9 @@ pub fn initialize(
         AccountMeta::new(*destination_pubkey, false),
         AccountMeta::new_readonly(*token_program_id, false),
     ];
+    if let Some(admin_authority_pubkey) = admin_authority_pubkey {
+        accounts.push(AccountMeta::new_readonly(*admin_authority_pubkey, true));
+    }
 
     Ok(Instruction {
         program_id: *program_id,
@@ -401,7 +820,7 @@ pub fn deposit_all_token_types(
     let data = SwapInstruction::DepositAllTokenTypes(instruction).pack();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*deposit_token_a_pubkey, false),
@@ -447,7 +866,7 @@ pub fn withdraw_all_token_types(
     let data = SwapInstruction::WithdrawAllTokenTypes(instruction).pack();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*pool_mint_pubkey, false),
@@ -490,7 +909,7 @@ pub fn deposit_single_token_type_exact_amount_in(
     let data = SwapInstruction::DepositSingleTokenTypeExactAmountIn(instruction).pack();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*source_token_pubkey, false),
@@ -530,7 +949,7 @@ pub fn withdraw_single_token_type_exact_amount_out(
     let data = SwapInstruction::WithdrawSingleTokenTypeExactAmountOut(instruction).pack();
 
     let accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*pool_mint_pubkey, false),
@@ -574,7 +993,7 @@ pub fn swap(
     let data = SwapInstruction::Swap(instruction).pack();
 
     let mut accounts = vec![
-        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*swap_pubkey, false),
         AccountMeta::new_readonly(*authority_pubkey, false),
         AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
         AccountMeta::new(*source_pubkey, false),
@@ -591,6 +1010,8 @@ pub fn swap(
     ];
     if let Some(host_fee_pubkey) = host_fee_pubkey {
         accounts.push(AccountMeta::new(*host_fee_pubkey, false));
+    } else {
+        accounts.push(AccountMeta::new_readonly(*program_id, false));
     }
 
     Ok(Instruction {
@@ -600,6 +1021,382 @@ pub fn swap(
     })
 }
 
+/// Creates an 'initialize_concentrated' instruction.
+pub fn initialize_concentrated(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    token_a_pubkey: &Pubkey,
+    token_b_pubkey: &Pubkey,
+    fees: Fees,
+    tick_spacing: u16,
+    sqrt_price_x64: u128,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::InitializeConcentrated(InitializeConcentrated {
+        fees,
+        tick_spacing,
+        sqrt_price_x64,
+    })
+    .pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, true),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new_readonly(*token_a_pubkey, false),
+        AccountMeta::new_readonly(*token_b_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'open_position' instruction.
+pub fn open_position(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    position_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    instruction: OpenPosition,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::OpenPosition(instruction).pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*position_pubkey, true),
+        AccountMeta::new_readonly(*owner_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'increase_liquidity' instruction.
+pub fn increase_liquidity(
+    program_id: &Pubkey,
+    token_a_program_id: &Pubkey,
+    token_b_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    position_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    deposit_token_a_pubkey: &Pubkey,
+    deposit_token_b_pubkey: &Pubkey,
+    swap_token_a_pubkey: &Pubkey,
+    swap_token_b_pubkey: &Pubkey,
+    token_a_mint_pubkey: &Pubkey,
+    token_b_mint_pubkey: &Pubkey,
+    instruction: IncreaseLiquidity,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::IncreaseLiquidity(instruction).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new(*position_pubkey, false),
+        AccountMeta::new_readonly(*owner_pubkey, true),
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*deposit_token_a_pubkey, false),
+        AccountMeta::new(*deposit_token_b_pubkey, false),
+        AccountMeta::new(*swap_token_a_pubkey, false),
+        AccountMeta::new(*swap_token_b_pubkey, false),
+        AccountMeta::new_readonly(*token_a_mint_pubkey, false),
+        AccountMeta::new_readonly(*token_b_mint_pubkey, false),
+        AccountMeta::new_readonly(*token_a_program_id, false),
+        AccountMeta::new_readonly(*token_b_program_id, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'decrease_liquidity' instruction.
+pub fn decrease_liquidity(
+    program_id: &Pubkey,
+    token_a_program_id: &Pubkey,
+    token_b_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    position_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    swap_token_a_pubkey: &Pubkey,
+    swap_token_b_pubkey: &Pubkey,
+    destination_token_a_pubkey: &Pubkey,
+    destination_token_b_pubkey: &Pubkey,
+    token_a_mint_pubkey: &Pubkey,
+    token_b_mint_pubkey: &Pubkey,
+    instruction: DecreaseLiquidity,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::DecreaseLiquidity(instruction).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new(*position_pubkey, false),
+        AccountMeta::new_readonly(*owner_pubkey, true),
+        AccountMeta::new(*swap_token_a_pubkey, false),
+        AccountMeta::new(*swap_token_b_pubkey, false),
+        AccountMeta::new(*destination_token_a_pubkey, false),
+        AccountMeta::new(*destination_token_b_pubkey, false),
+        AccountMeta::new_readonly(*token_a_mint_pubkey, false),
+        AccountMeta::new_readonly(*token_b_mint_pubkey, false),
+        AccountMeta::new_readonly(*token_a_program_id, false),
+        AccountMeta::new_readonly(*token_b_program_id, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'concentrated_swap' instruction.
+pub fn concentrated_swap(
+    program_id: &Pubkey,
+    source_token_program_id: &Pubkey,
+    destination_token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    swap_source_pubkey: &Pubkey,
+    swap_destination_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    source_mint_pubkey: &Pubkey,
+    destination_mint_pubkey: &Pubkey,
+    instruction: Swap,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::ConcentratedSwap(instruction).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*swap_source_pubkey, false),
+        AccountMeta::new(*swap_destination_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*source_mint_pubkey, false),
+        AccountMeta::new_readonly(*destination_mint_pubkey, false),
+        AccountMeta::new_readonly(*source_token_program_id, false),
+        AccountMeta::new_readonly(*destination_token_program_id, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'close_position' instruction.
+pub fn close_position(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    position_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::ClosePosition.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new(*position_pubkey, false),
+        AccountMeta::new_readonly(*owner_pubkey, true),
+        AccountMeta::new(*destination_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Accounts of one pool in a routed swap
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoutedSwapPool {
+    /// Token-swap
+    pub swap_pubkey: Pubkey,
+    /// Swap authority
+    pub authority_pubkey: Pubkey,
+    /// Swap token account to swap into
+    pub swap_source_pubkey: Pubkey,
+    /// Swap token account to swap from
+    pub swap_destination_pubkey: Pubkey,
+    /// Pool token mint
+    pub pool_mint_pubkey: Pubkey,
+    /// Pool fee account
+    pub pool_fee_pubkey: Pubkey,
+    /// Mint of the token to swap into
+    pub source_mint_pubkey: Pubkey,
+    /// Mint of the token to swap from
+    pub destination_mint_pubkey: Pubkey,
+    /// Program id of the token to swap into
+    pub source_token_program_id: Pubkey,
+    /// Program id of the token to swap from
+    pub destination_token_program_id: Pubkey,
+    /// Program id of the pool token
+    pub pool_token_program_id: Pubkey,
+}
+
+impl RoutedSwapPool {
+    /// Number of accounts given for each pool of a routed swap
+    pub const ACCOUNTS_LEN: usize = 11;
+}
+
+/// Creates a 'routed_swap' instruction.
+pub fn routed_swap(
+    program_id: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    pools: &[RoutedSwapPool],
+    instruction: Swap,
+) -> Result<Instruction, ProgramError> {
+    let pool_count = u8::try_from(pools.len()).map_err(|_| SwapError::InvalidInput)?;
+    if pool_count == 0 {
+        return Err(SwapError::InvalidInput.into());
+    }
+    let data = SwapInstruction::RoutedSwap(RoutedSwap {
+        amount_in: instruction.amount_in,
+        minimum_amount_out: instruction.minimum_amount_out,
+        pool_count,
+    })
+    .pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+    ];
+    for pool in pools {
+        accounts.extend([
+            AccountMeta::new(pool.swap_pubkey, false),
+            AccountMeta::new_readonly(pool.authority_pubkey, false),
+            AccountMeta::new(pool.swap_source_pubkey, false),
+            AccountMeta::new(pool.swap_destination_pubkey, false),
+            AccountMeta::new(pool.pool_mint_pubkey, false),
+            AccountMeta::new(pool.pool_fee_pubkey, false),
+            AccountMeta::new_readonly(pool.source_mint_pubkey, false),
+            AccountMeta::new_readonly(pool.destination_mint_pubkey, false),
+            AccountMeta::new_readonly(pool.source_token_program_id, false),
+            AccountMeta::new_readonly(pool.destination_token_program_id, false),
+            AccountMeta::new_readonly(pool.pool_token_program_id, false),
+        ]);
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_fees' instruction.
+pub fn set_fees(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    admin_authority_pubkey: &Pubkey,
+    fees: Fees,
+    dynamic_fee: DynamicFee,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::SetFees(SetFees { fees, dynamic_fee }).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_authority_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_pool_fee_account' instruction.
+pub fn set_pool_fee_account(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    admin_authority_pubkey: &Pubkey,
+    pool_fee_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::SetPoolFeeAccount.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_authority_pubkey, true),
+        AccountMeta::new_readonly(*pool_fee_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Adds the accounts required by the transfer hook of a mint to a swap,
+/// deposit or withdraw instruction, for one transfer of the mint.
+///
+/// `validation_account_data` is the data of the hook program's extra account
+/// metas account for the mint.  Swap instructions created by `swap` already
+/// hold the host fee account, or the swap program id in its place, in front of
+/// the transfer hook accounts.
+pub fn add_transfer_hook_accounts(
+    instruction: &mut Instruction,
+    transfer_hook_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    amount: u64,
+    validation_account_data: &[u8],
+) -> Result<(), ProgramError> {
+    let validation_pubkey = get_extra_account_metas_address(mint_pubkey, transfer_hook_program_id);
+    let mut execute_instruction = execute(
+        transfer_hook_program_id,
+        source_pubkey,
+        mint_pubkey,
+        destination_pubkey,
+        authority_pubkey,
+        &validation_pubkey,
+        amount,
+    );
+    let execute_accounts_len = execute_instruction.accounts.len();
+    ExtraAccountMetaList::add_to_instruction::<ExecuteInstruction>(
+        &mut execute_instruction,
+        validation_account_data,
+    )?;
+
+    // The program looks up the hook accounts by pubkey, so the order only
+    // matters to keep them apart from the optional host fee account
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(validation_pubkey, false));
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(*transfer_hook_program_id, false));
+    instruction.accounts.extend(
+        execute_instruction
+            .accounts
+            .into_iter()
+            .skip(execute_accounts_len),
+    );
+    Ok(())
+}
+
 /// Unpacks a reference from a bytes buffer.
 /// TODO actually pack / unpack instead of relying on normal memory layout.
 pub fn unpack<T>(input: &[u8]) -> Result<&T, ProgramError> {
@@ -757,4 +1554,160 @@ mod tests {
         let unpacked = SwapInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, check);
     }
+
+    #[test]
+    fn pack_initialize_concentrated() {
+        let fees = Fees {
+            trade_fee_numerator: 3,
+            trade_fee_denominator: 1000,
+            ..Fees::default()
+        };
+        let tick_spacing: u16 = 10;
+        let sqrt_price_x64: u128 = 1 << 64;
+        let check = SwapInstruction::InitializeConcentrated(InitializeConcentrated {
+            fees: fees.clone(),
+            tick_spacing,
+            sqrt_price_x64,
+        });
+        let packed = check.pack();
+        let mut expect = vec![6u8];
+        let mut fees_slice = [0u8; Fees::LEN];
+        fees.pack_into_slice(&mut fees_slice);
+        expect.extend_from_slice(&fees_slice);
+        expect.extend_from_slice(&tick_spacing.to_le_bytes());
+        expect.extend_from_slice(&sqrt_price_x64.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_position_instructions() {
+        let tick_lower: i32 = -120;
+        let tick_upper: i32 = 60;
+        let check = SwapInstruction::OpenPosition(OpenPosition {
+            tick_lower,
+            tick_upper,
+        });
+        let packed = check.pack();
+        let mut expect = vec![7];
+        expect.extend_from_slice(&tick_lower.to_le_bytes());
+        expect.extend_from_slice(&tick_upper.to_le_bytes());
+        assert_eq!(packed, expect);
+        assert_eq!(SwapInstruction::unpack(&expect).unwrap(), check);
+
+        let liquidity_amount: u128 = 1 << 100;
+        let maximum_token_a_amount: u64 = 10;
+        let maximum_token_b_amount: u64 = 20;
+        let check = SwapInstruction::IncreaseLiquidity(IncreaseLiquidity {
+            liquidity_amount,
+            maximum_token_a_amount,
+            maximum_token_b_amount,
+        });
+        let packed = check.pack();
+        let mut expect = vec![8];
+        expect.extend_from_slice(&liquidity_amount.to_le_bytes());
+        expect.extend_from_slice(&maximum_token_a_amount.to_le_bytes());
+        expect.extend_from_slice(&maximum_token_b_amount.to_le_bytes());
+        assert_eq!(packed, expect);
+        assert_eq!(SwapInstruction::unpack(&expect).unwrap(), check);
+
+        let check = SwapInstruction::DecreaseLiquidity(DecreaseLiquidity {
+            liquidity_amount,
+            minimum_token_a_amount: maximum_token_a_amount,
+            minimum_token_b_amount: maximum_token_b_amount,
+        });
+        let packed = check.pack();
+        expect[0] = 9;
+        assert_eq!(packed, expect);
+        assert_eq!(SwapInstruction::unpack(&expect).unwrap(), check);
+
+        let check = SwapInstruction::ClosePosition;
+        assert_eq!(check.pack(), vec![11]);
+        assert_eq!(SwapInstruction::unpack(&[11]).unwrap(), check);
+    }
+
+    #[test]
+    fn pack_concentrated_swap() {
+        let amount_in: u64 = 2;
+        let minimum_amount_out: u64 = 10;
+        let check = SwapInstruction::ConcentratedSwap(Swap {
+            amount_in,
+            minimum_amount_out,
+        });
+        let packed = check.pack();
+        let mut expect = vec![10];
+        expect.extend_from_slice(&amount_in.to_le_bytes());
+        expect.extend_from_slice(&minimum_amount_out.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_routed_swap() {
+        let amount_in: u64 = 7;
+        let minimum_amount_out: u64 = 3;
+        let pool_count: u8 = 2;
+        let check = SwapInstruction::RoutedSwap(RoutedSwap {
+            amount_in,
+            minimum_amount_out,
+            pool_count,
+        });
+        let packed = check.pack();
+        let mut expect = vec![12];
+        expect.extend_from_slice(&amount_in.to_le_bytes());
+        expect.extend_from_slice(&minimum_amount_out.to_le_bytes());
+        expect.push(pool_count);
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_set_fees() {
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10000,
+            ..Fees::default()
+        };
+        let max_fee_numerator: u64 = 100;
+        let max_volatility_bps: u64 = 500;
+        let decay_period: u64 = 600;
+        let check = SwapInstruction::SetFees(SetFees {
+            fees: fees.clone(),
+            dynamic_fee: DynamicFee {
+                max_fee_numerator,
+                max_volatility_bps,
+                decay_period,
+            },
+        });
+        let packed = check.pack();
+        let mut expect = vec![13u8];
+        let mut fees_slice = [0u8; Fees::LEN];
+        fees.pack_into_slice(&mut fees_slice);
+        expect.extend_from_slice(&fees_slice);
+        expect.extend_from_slice(&max_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&max_volatility_bps.to_le_bytes());
+        expect.extend_from_slice(&decay_period.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+        assert_eq!(
+            SwapInstruction::unpack(&expect[..expect.len() - 1]).unwrap_err(),
+            SwapError::InvalidInstruction.into()
+        );
+    }
+
+    #[test]
+    fn pack_set_pool_fee_account() {
+        let check = SwapInstruction::SetPoolFeeAccount;
+        let packed = check.pack();
+        let expect = vec![14u8];
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
 }
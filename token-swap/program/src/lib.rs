@@ -3,6 +3,7 @@
 
 //! An Uniswap-like program for the Solana blockchain.
 
+pub mod concentrated;
 pub mod constraints;
 pub mod curve;
 pub mod error;
@@ -2,17 +2,23 @@
 
 use crate::constraints::{SwapConstraints, SWAP_CONSTRAINTS};
 use crate::{
+    concentrated::math::{tick_at_sqrt_price, MAX_TICK},
     curve::{
         base::SwapCurve,
         calculator::{RoundDirection, TradeDirection},
-        fees::Fees,
+        fees::{DynamicFee, Fees},
     },
     error::SwapError,
     instruction::{
-        DepositAllTokenTypes, DepositSingleTokenTypeExactAmountIn, Initialize, Swap,
-        SwapInstruction, WithdrawAllTokenTypes, WithdrawSingleTokenTypeExactAmountOut,
+        DecreaseLiquidity, DepositAllTokenTypes, DepositSingleTokenTypeExactAmountIn,
+        IncreaseLiquidity, Initialize, InitializeConcentrated, OpenPosition, RoutedSwap,
+        RoutedSwapPool, SetFees, Swap, SwapInstruction, WithdrawAllTokenTypes,
+        WithdrawSingleTokenTypeExactAmountOut,
+    },
+    state::{
+        ConcentratedSwapV1, Position, PriceOracle, PriceVolatility, SwapState, SwapV1, SwapV2,
+        SwapVersion, TICK_DEPOSIT_LAMPORTS,
     },
-    state::{SwapState, SwapV1, SwapVersion},
 };
 use num_traits::FromPrimitive;
 use solana_program::{
@@ -25,19 +31,25 @@ use solana_program::{
     program::invoke_signed,
     program_error::{PrintProgramError, ProgramError},
     program_option::COption,
+    program_pack::Pack,
     pubkey::Pubkey,
+    rent::Rent,
     sysvar::Sysvar,
 };
 use spl_token_2022::{
     check_spl_token_program_account,
     error::TokenError,
     extension::{
-        mint_close_authority::MintCloseAuthority, transfer_fee::TransferFeeConfig,
+        mint_close_authority::MintCloseAuthority, transfer_fee::TransferFeeConfig, transfer_hook,
         BaseStateWithExtensions, StateWithExtensions,
     },
     state::{Account, Mint},
 };
-use std::{convert::TryInto, error::Error};
+use spl_transfer_hook_interface::onchain::add_cpi_accounts_for_execute;
+use std::{
+    convert::{TryFrom, TryInto},
+    error::Error,
+};
 
 /// Program state handler.
 pub struct Processor {}
@@ -157,6 +169,9 @@ impl Processor {
     }
 
     /// Issue a spl_token `Transfer` instruction.
+    ///
+    /// If the mint has a transfer hook, the accounts required by the hook
+    /// program are looked up in `additional_accounts` and added to the CPI.
     #[allow(clippy::too_many_arguments)]
     pub fn token_transfer<'a>(
         swap: &Pubkey,
@@ -165,6 +180,7 @@ impl Processor {
         mint: AccountInfo<'a>,
         destination: AccountInfo<'a>,
         authority: AccountInfo<'a>,
+        additional_accounts: &[AccountInfo<'a>],
         bump_seed: u8,
         amount: u64,
         decimals: u8,
@@ -172,7 +188,7 @@ impl Processor {
         let swap_bytes = swap.to_bytes();
         let authority_signature_seeds = [&swap_bytes[..32], &[bump_seed]];
         let signers = &[&authority_signature_seeds[..]];
-        let ix = spl_token_2022::instruction::transfer_checked(
+        let mut ix = spl_token_2022::instruction::transfer_checked(
             token_program.key,
             source.key,
             mint.key,
@@ -182,11 +198,25 @@ impl Processor {
             amount,
             decimals,
         )?;
-        invoke_signed_wrapper::<TokenError>(
-            &ix,
-            &[source, mint, destination, authority, token_program],
-            signers,
-        )
+        // scope the borrow to avoid a double-borrow during the CPI
+        let transfer_hook_program_id = {
+            let mint_data = mint.try_borrow_data()?;
+            StateWithExtensions::<Mint>::unpack(&mint_data)
+                .ok()
+                .and_then(|mint| transfer_hook::get_program_id(&mint))
+        };
+        let mint_key = *mint.key;
+        let mut account_infos = vec![source, mint, destination, authority, token_program];
+        if let Some(transfer_hook_program_id) = transfer_hook_program_id {
+            add_cpi_accounts_for_execute(
+                &mut ix,
+                &mut account_infos,
+                &mint_key,
+                &transfer_hook_program_id,
+                additional_accounts,
+            )?;
+        }
+        invoke_signed_wrapper::<TokenError>(&ix, &account_infos, signers)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -241,6 +271,79 @@ impl Processor {
         Ok(())
     }
 
+    /// Accumulates the prices held by the pool since the last update into the
+    /// swap's price oracle, if its version has one, then holds the prices of
+    /// the token amounts held by the swap after the operation
+    pub fn update_price_oracle(
+        swap_info: &AccountInfo,
+        token_swap: &dyn SwapState,
+        new_swap_token_amounts: (u128, u128),
+    ) -> ProgramResult {
+        if let Some(price_oracle) = token_swap.price_oracle() {
+            let mut price_oracle = *price_oracle;
+            // An empty pool has no price, so nothing is accumulated for it
+            let (price_a_x64, price_b_x64) = token_swap
+                .swap_curve()
+                .calculator
+                .spot_prices_x64(new_swap_token_amounts.0, new_swap_token_amounts.1)
+                .unwrap_or_default();
+            price_oracle.update(Clock::get()?.unix_timestamp, price_a_x64, price_b_x64);
+            SwapVersion::pack_price_oracle(price_oracle, &mut swap_info.data.borrow_mut())?;
+        }
+        Ok(())
+    }
+
+    /// Fees charged on a trade against the swap, with its dynamic trade fee
+    /// added to the trade fee, if enabled
+    pub fn trade_fees(token_swap: &dyn SwapState) -> Result<Fees, ProgramError> {
+        let mut fees = token_swap.fees().clone();
+        if let Some((dynamic_fee, price_volatility)) = token_swap.dynamic_fee() {
+            if dynamic_fee.is_enabled() {
+                let volatility_bps = price_volatility
+                    .current_volatility_bps(dynamic_fee, Clock::get()?.unix_timestamp);
+                fees.trade_fee_numerator = dynamic_fee
+                    .fee_numerator(volatility_bps)
+                    .and_then(|fee_numerator| fees.trade_fee_numerator.checked_add(fee_numerator))
+                    .ok_or(SwapError::FeeCalculationFailure)?;
+            }
+        }
+        Ok(fees)
+    }
+
+    /// Record the price movement of a trade in the volatility of the swap, if
+    /// its dynamic fee is enabled, from the token amounts held by the swap
+    /// before and after the trade
+    pub fn update_price_volatility(
+        swap_info: &AccountInfo,
+        token_swap: &dyn SwapState,
+        swap_token_amounts: (u128, u128),
+        new_swap_token_amounts: (u128, u128),
+    ) -> ProgramResult {
+        if let Some((dynamic_fee, price_volatility)) = token_swap.dynamic_fee() {
+            if dynamic_fee.is_enabled() {
+                let calculator = &token_swap.swap_curve().calculator;
+                let (price_before_x64, _) = calculator
+                    .spot_prices_x64(swap_token_amounts.0, swap_token_amounts.1)
+                    .unwrap_or_default();
+                let (price_after_x64, _) = calculator
+                    .spot_prices_x64(new_swap_token_amounts.0, new_swap_token_amounts.1)
+                    .unwrap_or_default();
+                let mut price_volatility = *price_volatility;
+                price_volatility.update(
+                    dynamic_fee,
+                    Clock::get()?.unix_timestamp,
+                    price_before_x64,
+                    price_after_x64,
+                );
+                SwapVersion::pack_price_volatility(
+                    price_volatility,
+                    &mut swap_info.data.borrow_mut(),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
     /// Processes an [Initialize](enum.Instruction.html).
     pub fn process_initialize(
         program_id: &Pubkey,
@@ -258,7 +361,13 @@ impl Processor {
         let fee_account_info = next_account_info(account_info_iter)?;
         let destination_info = next_account_info(account_info_iter)?;
         let pool_token_program_info = next_account_info(account_info_iter)?;
+        let admin_authority_info = next_account_info(account_info_iter).ok();
 
+        if let Some(admin_authority_info) = admin_authority_info {
+            if !admin_authority_info.is_signer {
+                return Err(SwapError::InvalidAdminAuthority.into());
+            }
+        }
         let token_program_id = *pool_token_program_info.key;
         if SwapVersion::is_initialized(&swap_info.data.borrow()) {
             return Err(SwapError::AlreadyInUse.into());
@@ -342,9 +451,8 @@ impl Processor {
                 return Err(SwapError::InvalidOwner.into());
             }
             swap_constraints.validate_curve(&swap_curve)?;
-            swap_constraints.validate_fees(&fees)?;
         }
-        fees.validate()?;
+        Self::validate_fees(&fees, &DynamicFee::default(), swap_constraints)?;
         swap_curve.calculator.validate()?;
 
         let initial_amount = swap_curve.calculator.new_pool_supply();
@@ -359,7 +467,7 @@ impl Processor {
             to_u64(initial_amount)?,
         )?;
 
-        let obj = SwapVersion::SwapV1(SwapV1 {
+        let swap_v1 = SwapV1 {
             is_initialized: true,
             bump_seed,
             token_program_id,
@@ -371,7 +479,37 @@ impl Processor {
             pool_fee_account: *fee_account_info.key,
             fees,
             swap_curve,
-        });
+        };
+        // Swap accounts sized for the original version keep working, without a
+        // price oracle, an admin authority, or a dynamic fee
+        let obj = if swap_info.data_len() == SwapVersion::SWAP_V1_LEN {
+            if admin_authority_info.is_some() {
+                return Err(SwapError::UnsupportedPoolType.into());
+            }
+            SwapVersion::SwapV1(swap_v1)
+        } else {
+            let unix_timestamp = Clock::get()?.unix_timestamp;
+            let (last_price_a_x64, last_price_b_x64) = swap_v1
+                .swap_curve
+                .calculator
+                .spot_prices_x64(to_u128(token_a.amount)?, to_u128(token_b.amount)?)
+                .unwrap_or_default();
+            SwapVersion::SwapV2(SwapV2 {
+                swap: swap_v1,
+                price_oracle: PriceOracle {
+                    last_update_timestamp: unix_timestamp,
+                    last_price_a_x64,
+                    last_price_b_x64,
+                    ..PriceOracle::default()
+                },
+                admin_authority: admin_authority_info.map(|info| *info.key).into(),
+                dynamic_fee: DynamicFee::default(),
+                price_volatility: PriceVolatility {
+                    volatility_bps: 0,
+                    last_update_timestamp: unix_timestamp,
+                },
+            })
+        };
         SwapVersion::pack(obj, &mut swap_info.data.borrow_mut())?;
         Ok(())
     }
@@ -398,6 +536,12 @@ impl Processor {
         let source_token_program_info = next_account_info(account_info_iter)?;
         let destination_token_program_info = next_account_info(account_info_iter)?;
         let pool_token_program_info = next_account_info(account_info_iter)?;
+        // The host fee account keeps its position in front of the transfer hook
+        // accounts, with the swap program id standing in for it when there is none
+        let host_fee_account_info = next_account_info(account_info_iter)
+            .ok()
+            .filter(|account_info| account_info.key != program_id);
+        let transfer_hook_accounts = account_info_iter.as_slice();
 
         if swap_info.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
@@ -477,7 +621,7 @@ impl Processor {
                 to_u128(source_account.amount)?,
                 to_u128(dest_account.amount)?,
                 trade_direction,
-                token_swap.fees(),
+                &Self::trade_fees(token_swap.as_ref())?,
             )
             .ok_or(SwapError::ZeroTradingTokens)?;
 
@@ -529,6 +673,10 @@ impl Processor {
             (amount_out, destination_mint.base.decimals)
         };
 
+        let (token_a_amount, token_b_amount) = match trade_direction {
+            TradeDirection::AtoB => (source_account.amount, dest_account.amount),
+            TradeDirection::BtoA => (dest_account.amount, source_account.amount),
+        };
         let (swap_token_a_amount, swap_token_b_amount) = match trade_direction {
             TradeDirection::AtoB => (
                 result.new_swap_source_amount,
@@ -539,6 +687,17 @@ impl Processor {
                 result.new_swap_source_amount,
             ),
         };
+        Self::update_price_oracle(
+            swap_info,
+            token_swap.as_ref(),
+            (swap_token_a_amount, swap_token_b_amount),
+        )?;
+        Self::update_price_volatility(
+            swap_info,
+            token_swap.as_ref(),
+            (to_u128(token_a_amount)?, to_u128(token_b_amount)?),
+            (swap_token_a_amount, swap_token_b_amount),
+        )?;
 
         Self::token_transfer(
             swap_info.key,
@@ -547,6 +706,7 @@ impl Processor {
             source_token_mint_info.clone(),
             swap_source_info.clone(),
             user_transfer_authority_info.clone(),
+            transfer_hook_accounts,
             token_swap.bump_seed(),
             source_transfer_amount,
             source_mint_decimals,
@@ -565,8 +725,7 @@ impl Processor {
                     RoundDirection::Floor,
                 )
                 .ok_or(SwapError::FeeCalculationFailure)?;
-            // Allow error to fall through
-            if let Ok(host_fee_account_info) = next_account_info(account_info_iter) {
+            if let Some(host_fee_account_info) = host_fee_account_info {
                 let host_fee_account = Self::unpack_token_account(
                     host_fee_account_info,
                     token_swap.token_program_id(),
@@ -616,6 +775,7 @@ impl Processor {
             destination_token_mint_info.clone(),
             destination_info.clone(),
             authority_info.clone(),
+            transfer_hook_accounts,
             token_swap.bump_seed(),
             destination_transfer_amount,
             destination_mint_decimals,
@@ -624,6 +784,285 @@ impl Processor {
         Ok(())
     }
 
+    /// Processes a [RoutedSwap](enum.Instruction.html).
+    pub fn process_routed_swap(
+        program_id: &Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        pool_count: u8,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let user_transfer_authority_info = next_account_info(account_info_iter)?;
+        let source_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        if pool_count == 0 {
+            return Err(SwapError::InvalidInput.into());
+        }
+        let pool_count = usize::from(pool_count);
+        let pool_accounts_len = pool_count.saturating_mul(RoutedSwapPool::ACCOUNTS_LEN);
+        let remaining_accounts = account_info_iter.as_slice();
+        if pool_accounts_len > remaining_accounts.len() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let (pool_accounts, transfer_hook_accounts) =
+            remaining_accounts.split_at(pool_accounts_len);
+
+        // The tokens out of each pool are transferred straight into the next
+        // one, signed by the authority of the pool they come out of
+        let mut input_amount = amount_in;
+        let mut input_info = source_info;
+        let mut input_authority_info = user_transfer_authority_info;
+        let mut input_signer: Option<(&Pubkey, u8)> = None;
+
+        for (index, pool_accounts) in pool_accounts
+            .chunks_exact(RoutedSwapPool::ACCOUNTS_LEN)
+            .enumerate()
+        {
+            let pool_info_iter = &mut pool_accounts.iter();
+            let swap_info = next_account_info(pool_info_iter)?;
+            let authority_info = next_account_info(pool_info_iter)?;
+            let swap_source_info = next_account_info(pool_info_iter)?;
+            let swap_destination_info = next_account_info(pool_info_iter)?;
+            let pool_mint_info = next_account_info(pool_info_iter)?;
+            let pool_fee_account_info = next_account_info(pool_info_iter)?;
+            let source_token_mint_info = next_account_info(pool_info_iter)?;
+            let destination_token_mint_info = next_account_info(pool_info_iter)?;
+            let source_token_program_info = next_account_info(pool_info_iter)?;
+            let destination_token_program_info = next_account_info(pool_info_iter)?;
+            let pool_token_program_info = next_account_info(pool_info_iter)?;
+            let is_last_pool = index + 1 == pool_count;
+
+            if swap_info.owner != program_id {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            let token_swap = SwapVersion::unpack(&swap_info.data.borrow())?;
+            if *authority_info.key
+                != Self::authority_id(program_id, swap_info.key, token_swap.bump_seed())?
+            {
+                return Err(SwapError::InvalidProgramAddress.into());
+            }
+            if !(*swap_source_info.key == *token_swap.token_a_account()
+                || *swap_source_info.key == *token_swap.token_b_account())
+            {
+                return Err(SwapError::IncorrectSwapAccount.into());
+            }
+            if !(*swap_destination_info.key == *token_swap.token_a_account()
+                || *swap_destination_info.key == *token_swap.token_b_account())
+            {
+                return Err(SwapError::IncorrectSwapAccount.into());
+            }
+            if *swap_source_info.key == *swap_destination_info.key {
+                return Err(SwapError::InvalidInput.into());
+            }
+            if swap_source_info.key == input_info.key {
+                return Err(SwapError::InvalidInput.into());
+            }
+            if is_last_pool && swap_destination_info.key == destination_info.key {
+                return Err(SwapError::InvalidInput.into());
+            }
+            if *pool_mint_info.key != *token_swap.pool_mint() {
+                return Err(SwapError::IncorrectPoolMint.into());
+            }
+            if *pool_fee_account_info.key != *token_swap.pool_fee_account() {
+                return Err(SwapError::IncorrectFeeAccount.into());
+            }
+            if *pool_token_program_info.key != *token_swap.token_program_id() {
+                return Err(SwapError::IncorrectTokenProgramId.into());
+            }
+
+            let source_account =
+                Self::unpack_token_account(swap_source_info, token_swap.token_program_id())?;
+            let dest_account =
+                Self::unpack_token_account(swap_destination_info, token_swap.token_program_id())?;
+            let pool_mint = Self::unpack_mint(pool_mint_info, token_swap.token_program_id())?;
+
+            // Take transfer fees into account for actual amount transferred in
+            let (actual_amount_in, source_transfer_fee, source_mint_decimals) = {
+                let source_mint_data = source_token_mint_info.data.borrow();
+                let source_mint = Self::unpack_mint_with_extensions(
+                    &source_mint_data,
+                    source_token_mint_info.owner,
+                    token_swap.token_program_id(),
+                )?;
+                let transfer_fee_config = source_mint.get_extension::<TransferFeeConfig>().ok();
+                let actual_amount_in = if let Some(transfer_fee_config) = transfer_fee_config {
+                    input_amount.saturating_sub(
+                        transfer_fee_config
+                            .calculate_epoch_fee(Clock::get()?.epoch, input_amount)
+                            .ok_or(SwapError::FeeCalculationFailure)?,
+                    )
+                } else {
+                    input_amount
+                };
+                (
+                    actual_amount_in,
+                    transfer_fee_config.copied(),
+                    source_mint.base.decimals,
+                )
+            };
+
+            let trade_direction = if *swap_source_info.key == *token_swap.token_a_account() {
+                TradeDirection::AtoB
+            } else {
+                TradeDirection::BtoA
+            };
+            let result = token_swap
+                .swap_curve()
+                .swap(
+                    to_u128(actual_amount_in)?,
+                    to_u128(source_account.amount)?,
+                    to_u128(dest_account.amount)?,
+                    trade_direction,
+                    &Self::trade_fees(token_swap.as_ref())?,
+                )
+                .ok_or(SwapError::ZeroTradingTokens)?;
+
+            // The user only sends what the first pool swaps, but every other
+            // pool receives exactly the tokens out of the previous one, so it
+            // must swap all of them
+            let source_transfer_amount = if input_signer.is_none() {
+                let source_amount_swapped = to_u64(result.source_amount_swapped)?;
+                if let Some(transfer_fee_config) = source_transfer_fee {
+                    source_amount_swapped.saturating_add(
+                        transfer_fee_config
+                            .calculate_inverse_epoch_fee(Clock::get()?.epoch, source_amount_swapped)
+                            .ok_or(SwapError::FeeCalculationFailure)?,
+                    )
+                } else {
+                    source_amount_swapped
+                }
+            } else {
+                if result.source_amount_swapped < to_u128(actual_amount_in)? {
+                    return Err(SwapError::IncompleteRoutedSwap.into());
+                }
+                input_amount
+            };
+
+            let (destination_transfer_amount, destination_mint_decimals) = {
+                let destination_mint_data = destination_token_mint_info.data.borrow();
+                let destination_mint = Self::unpack_mint_with_extensions(
+                    &destination_mint_data,
+                    destination_token_mint_info.owner,
+                    token_swap.token_program_id(),
+                )?;
+                let amount_out = to_u64(result.destination_amount_swapped)?;
+                if is_last_pool {
+                    let amount_received = if let Ok(transfer_fee_config) =
+                        destination_mint.get_extension::<TransferFeeConfig>()
+                    {
+                        amount_out.saturating_sub(
+                            transfer_fee_config
+                                .calculate_epoch_fee(Clock::get()?.epoch, amount_out)
+                                .ok_or(SwapError::FeeCalculationFailure)?,
+                        )
+                    } else {
+                        amount_out
+                    };
+                    if amount_received < minimum_amount_out {
+                        return Err(SwapError::ExceededSlippage.into());
+                    }
+                }
+                (amount_out, destination_mint.base.decimals)
+            };
+
+            let (token_a_amount, token_b_amount, swap_token_a_amount, swap_token_b_amount) =
+                match trade_direction {
+                    TradeDirection::AtoB => (
+                        source_account.amount,
+                        dest_account.amount,
+                        result.new_swap_source_amount,
+                        result.new_swap_destination_amount,
+                    ),
+                    TradeDirection::BtoA => (
+                        dest_account.amount,
+                        source_account.amount,
+                        result.new_swap_destination_amount,
+                        result.new_swap_source_amount,
+                    ),
+                };
+            Self::update_price_oracle(
+                swap_info,
+                token_swap.as_ref(),
+                (swap_token_a_amount, swap_token_b_amount),
+            )?;
+            Self::update_price_volatility(
+                swap_info,
+                token_swap.as_ref(),
+                (to_u128(token_a_amount)?, to_u128(token_b_amount)?),
+                (swap_token_a_amount, swap_token_b_amount),
+            )?;
+
+            let (input_swap_key, input_bump_seed) =
+                input_signer.unwrap_or((swap_info.key, token_swap.bump_seed()));
+            Self::token_transfer(
+                input_swap_key,
+                source_token_program_info.clone(),
+                input_info.clone(),
+                source_token_mint_info.clone(),
+                swap_source_info.clone(),
+                input_authority_info.clone(),
+                transfer_hook_accounts,
+                input_bump_seed,
+                source_transfer_amount,
+                source_mint_decimals,
+            )?;
+
+            if result.owner_fee > 0 {
+                let pool_token_amount = token_swap
+                    .swap_curve()
+                    .calculator
+                    .withdraw_single_token_type_exact_out(
+                        result.owner_fee,
+                        swap_token_a_amount,
+                        swap_token_b_amount,
+                        to_u128(pool_mint.supply)?,
+                        trade_direction,
+                        RoundDirection::Floor,
+                    )
+                    .ok_or(SwapError::FeeCalculationFailure)?;
+                if token_swap
+                    .check_pool_fee_info(pool_fee_account_info)
+                    .is_ok()
+                {
+                    Self::token_mint_to(
+                        swap_info.key,
+                        pool_token_program_info.clone(),
+                        pool_mint_info.clone(),
+                        pool_fee_account_info.clone(),
+                        authority_info.clone(),
+                        token_swap.bump_seed(),
+                        to_u64(pool_token_amount)?,
+                    )?;
+                };
+            }
+
+            if is_last_pool {
+                Self::token_transfer(
+                    swap_info.key,
+                    destination_token_program_info.clone(),
+                    swap_destination_info.clone(),
+                    destination_token_mint_info.clone(),
+                    destination_info.clone(),
+                    authority_info.clone(),
+                    transfer_hook_accounts,
+                    token_swap.bump_seed(),
+                    destination_transfer_amount,
+                    destination_mint_decimals,
+                )?;
+            } else {
+                // Only the tokens swapped out of this pool move on, never the
+                // rest of its balance
+                input_amount = destination_transfer_amount;
+                input_info = swap_destination_info;
+                input_authority_info = authority_info;
+                input_signer = Some((swap_info.key, token_swap.bump_seed()));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Processes an [DepositAllTokenTypes](enum.Instruction.html).
     pub fn process_deposit_all_token_types(
         program_id: &Pubkey,
@@ -647,6 +1086,7 @@ impl Processor {
         let token_a_program_info = next_account_info(account_info_iter)?;
         let token_b_program_info = next_account_info(account_info_iter)?;
         let pool_token_program_info = next_account_info(account_info_iter)?;
+        let transfer_hook_accounts = account_info_iter.as_slice();
 
         let token_swap = SwapVersion::unpack(&swap_info.data.borrow())?;
         let calculator = &token_swap.swap_curve().calculator;
@@ -701,6 +1141,16 @@ impl Processor {
             return Err(SwapError::ZeroTradingTokens.into());
         }
 
+        let new_swap_token_amounts = (
+            to_u128(token_a.amount)?
+                .checked_add(results.token_a_amount)
+                .ok_or(SwapError::CalculationFailure)?,
+            to_u128(token_b.amount)?
+                .checked_add(results.token_b_amount)
+                .ok_or(SwapError::CalculationFailure)?,
+        );
+        Self::update_price_oracle(swap_info, token_swap.as_ref(), new_swap_token_amounts)?;
+
         let pool_token_amount = to_u64(pool_token_amount)?;
 
         Self::token_transfer(
@@ -710,6 +1160,7 @@ impl Processor {
             token_a_mint_info.clone(),
             token_a_info.clone(),
             user_transfer_authority_info.clone(),
+            transfer_hook_accounts,
             token_swap.bump_seed(),
             token_a_amount,
             Self::unpack_mint(token_a_mint_info, token_swap.token_program_id())?.decimals,
@@ -721,6 +1172,7 @@ impl Processor {
             token_b_mint_info.clone(),
             token_b_info.clone(),
             user_transfer_authority_info.clone(),
+            transfer_hook_accounts,
             token_swap.bump_seed(),
             token_b_amount,
             Self::unpack_mint(token_b_mint_info, token_swap.token_program_id())?.decimals,
@@ -762,6 +1214,7 @@ impl Processor {
         let pool_token_program_info = next_account_info(account_info_iter)?;
         let token_a_program_info = next_account_info(account_info_iter)?;
         let token_b_program_info = next_account_info(account_info_iter)?;
+        let transfer_hook_accounts = account_info_iter.as_slice();
 
         let token_swap = SwapVersion::unpack(&swap_info.data.borrow())?;
         Self::check_accounts(
@@ -828,6 +1281,16 @@ impl Processor {
             return Err(SwapError::ZeroTradingTokens.into());
         }
 
+        let new_swap_token_amounts = (
+            to_u128(token_a.amount)?
+                .checked_sub(to_u128(token_a_amount)?)
+                .ok_or(SwapError::CalculationFailure)?,
+            to_u128(token_b.amount)?
+                .checked_sub(to_u128(token_b_amount)?)
+                .ok_or(SwapError::CalculationFailure)?,
+        );
+        Self::update_price_oracle(swap_info, token_swap.as_ref(), new_swap_token_amounts)?;
+
         if withdraw_fee > 0 {
             Self::token_transfer(
                 swap_info.key,
@@ -836,6 +1299,7 @@ impl Processor {
                 pool_mint_info.clone(),
                 pool_fee_account_info.clone(),
                 user_transfer_authority_info.clone(),
+                transfer_hook_accounts,
                 token_swap.bump_seed(),
                 to_u64(withdraw_fee)?,
                 pool_mint.decimals,
@@ -859,6 +1323,7 @@ impl Processor {
                 token_a_mint_info.clone(),
                 dest_token_a_info.clone(),
                 authority_info.clone(),
+                transfer_hook_accounts,
                 token_swap.bump_seed(),
                 token_a_amount,
                 Self::unpack_mint(token_a_mint_info, token_swap.token_program_id())?.decimals,
@@ -872,6 +1337,7 @@ impl Processor {
                 token_b_mint_info.clone(),
                 dest_token_b_info.clone(),
                 authority_info.clone(),
+                transfer_hook_accounts,
                 token_swap.bump_seed(),
                 token_b_amount,
                 Self::unpack_mint(token_b_mint_info, token_swap.token_program_id())?.decimals,
@@ -899,6 +1365,7 @@ impl Processor {
         let source_token_mint_info = next_account_info(account_info_iter)?;
         let source_token_program_info = next_account_info(account_info_iter)?;
         let pool_token_program_info = next_account_info(account_info_iter)?;
+        let transfer_hook_accounts = account_info_iter.as_slice();
 
         let token_swap = SwapVersion::unpack(&swap_info.data.borrow())?;
         let calculator = &token_swap.swap_curve().calculator;
@@ -950,7 +1417,7 @@ impl Processor {
                     to_u128(swap_token_b.amount)?,
                     pool_mint_supply,
                     trade_direction,
-                    token_swap.fees(),
+                    &Self::trade_fees(token_swap.as_ref())?,
                 )
                 .ok_or(SwapError::ZeroTradingTokens)?
         } else {
@@ -965,6 +1432,30 @@ impl Processor {
             return Err(SwapError::ZeroTradingTokens.into());
         }
 
+        let (swap_token_a_amount, swap_token_b_amount) =
+            (to_u128(swap_token_a.amount)?, to_u128(swap_token_b.amount)?);
+        let new_swap_token_amounts = match trade_direction {
+            TradeDirection::AtoB => (
+                swap_token_a_amount
+                    .checked_add(to_u128(source_token_amount)?)
+                    .ok_or(SwapError::CalculationFailure)?,
+                swap_token_b_amount,
+            ),
+            TradeDirection::BtoA => (
+                swap_token_a_amount,
+                swap_token_b_amount
+                    .checked_add(to_u128(source_token_amount)?)
+                    .ok_or(SwapError::CalculationFailure)?,
+            ),
+        };
+        Self::update_price_oracle(swap_info, token_swap.as_ref(), new_swap_token_amounts)?;
+        Self::update_price_volatility(
+            swap_info,
+            token_swap.as_ref(),
+            (swap_token_a_amount, swap_token_b_amount),
+            new_swap_token_amounts,
+        )?;
+
         match trade_direction {
             TradeDirection::AtoB => {
                 Self::token_transfer(
@@ -974,6 +1465,7 @@ impl Processor {
                     source_token_mint_info.clone(),
                     swap_token_a_info.clone(),
                     user_transfer_authority_info.clone(),
+                    transfer_hook_accounts,
                     token_swap.bump_seed(),
                     source_token_amount,
                     Self::unpack_mint(source_token_mint_info, token_swap.token_program_id())?
@@ -988,6 +1480,7 @@ impl Processor {
                     source_token_mint_info.clone(),
                     swap_token_b_info.clone(),
                     user_transfer_authority_info.clone(),
+                    transfer_hook_accounts,
                     token_swap.bump_seed(),
                     source_token_amount,
                     Self::unpack_mint(source_token_mint_info, token_swap.token_program_id())?
@@ -1028,6 +1521,7 @@ impl Processor {
         let destination_token_mint_info = next_account_info(account_info_iter)?;
         let pool_token_program_info = next_account_info(account_info_iter)?;
         let destination_token_program_info = next_account_info(account_info_iter)?;
+        let transfer_hook_accounts = account_info_iter.as_slice();
 
         let token_swap = SwapVersion::unpack(&swap_info.data.borrow())?;
         let destination_account =
@@ -1076,7 +1570,7 @@ impl Processor {
                 swap_token_b_amount,
                 pool_mint_supply,
                 trade_direction,
-                token_swap.fees(),
+                &Self::trade_fees(token_swap.as_ref())?,
             )
             .ok_or(SwapError::ZeroTradingTokens)?;
 
@@ -1105,6 +1599,28 @@ impl Processor {
             return Err(SwapError::ZeroTradingTokens.into());
         }
 
+        let new_swap_token_amounts = match trade_direction {
+            TradeDirection::AtoB => (
+                swap_token_a_amount
+                    .checked_sub(to_u128(destination_token_amount)?)
+                    .ok_or(SwapError::CalculationFailure)?,
+                swap_token_b_amount,
+            ),
+            TradeDirection::BtoA => (
+                swap_token_a_amount,
+                swap_token_b_amount
+                    .checked_sub(to_u128(destination_token_amount)?)
+                    .ok_or(SwapError::CalculationFailure)?,
+            ),
+        };
+        Self::update_price_oracle(swap_info, token_swap.as_ref(), new_swap_token_amounts)?;
+        Self::update_price_volatility(
+            swap_info,
+            token_swap.as_ref(),
+            (swap_token_a_amount, swap_token_b_amount),
+            new_swap_token_amounts,
+        )?;
+
         if withdraw_fee > 0 {
             Self::token_transfer(
                 swap_info.key,
@@ -1113,6 +1629,7 @@ impl Processor {
                 pool_mint_info.clone(),
                 pool_fee_account_info.clone(),
                 user_transfer_authority_info.clone(),
+                transfer_hook_accounts,
                 token_swap.bump_seed(),
                 to_u64(withdraw_fee)?,
                 pool_mint.decimals,
@@ -1137,6 +1654,7 @@ impl Processor {
                     destination_token_mint_info.clone(),
                     destination_info.clone(),
                     authority_info.clone(),
+                    transfer_hook_accounts,
                     token_swap.bump_seed(),
                     destination_token_amount,
                     Self::unpack_mint(destination_token_mint_info, token_swap.token_program_id())?
@@ -1151,6 +1669,7 @@ impl Processor {
                     destination_token_mint_info.clone(),
                     destination_info.clone(),
                     authority_info.clone(),
+                    transfer_hook_accounts,
                     token_swap.bump_seed(),
                     destination_token_amount,
                     Self::unpack_mint(destination_token_mint_info, token_swap.token_program_id())?
@@ -1162,3710 +1681,3083 @@ impl Processor {
         Ok(())
     }
 
-    /// Processes an [Instruction](enum.Instruction.html).
-    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
-        Self::process_with_constraints(program_id, accounts, input, &SWAP_CONSTRAINTS)
+    fn transfer_fee_excluded_amount(
+        mint_info: &AccountInfo,
+        token_program_id: &Pubkey,
+        amount: u64,
+    ) -> Result<u64, ProgramError> {
+        let mint_data = mint_info.data.borrow();
+        let mint =
+            Self::unpack_mint_with_extensions(&mint_data, mint_info.owner, token_program_id)?;
+        if let Ok(transfer_fee_config) = mint.get_extension::<TransferFeeConfig>() {
+            Ok(amount.saturating_sub(
+                transfer_fee_config
+                    .calculate_epoch_fee(Clock::get()?.epoch, amount)
+                    .ok_or(SwapError::FeeCalculationFailure)?,
+            ))
+        } else {
+            Ok(amount)
+        }
     }
 
-    /// Processes an instruction given extra constraint
-    pub fn process_with_constraints(
+    fn transfer_fee_included_amount(
+        mint_info: &AccountInfo,
+        token_program_id: &Pubkey,
+        amount: u64,
+    ) -> Result<(u64, u8), ProgramError> {
+        let mint_data = mint_info.data.borrow();
+        let mint =
+            Self::unpack_mint_with_extensions(&mint_data, mint_info.owner, token_program_id)?;
+        let amount = if let Ok(transfer_fee_config) = mint.get_extension::<TransferFeeConfig>() {
+            amount.saturating_add(
+                transfer_fee_config
+                    .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+                    .ok_or(SwapError::FeeCalculationFailure)?,
+            )
+        } else {
+            amount
+        };
+        Ok((amount, mint.base.decimals))
+    }
+
+    fn unpack_position(
+        program_id: &Pubkey,
+        swap_info: &AccountInfo,
+        position_info: &AccountInfo,
+        owner_info: &AccountInfo,
+    ) -> Result<Position, ProgramError> {
+        if position_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if !owner_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let position = Position::unpack(&position_info.data.borrow())?;
+        if position.pool != *swap_info.key || position.owner != *owner_info.key {
+            return Err(SwapError::InvalidPosition.into());
+        }
+        Ok(position)
+    }
+
+    /// Processes an [InitializeConcentrated](enum.Instruction.html).
+    pub fn process_initialize_concentrated(
         program_id: &Pubkey,
+        fees: Fees,
+        tick_spacing: u16,
+        sqrt_price_x64: u128,
         accounts: &[AccountInfo],
-        input: &[u8],
         swap_constraints: &Option<SwapConstraints>,
     ) -> ProgramResult {
-        let instruction = SwapInstruction::unpack(input)?;
-        match instruction {
-            SwapInstruction::Initialize(Initialize { fees, swap_curve }) => {
-                msg!("Instruction: Init");
-                Self::process_initialize(program_id, fees, swap_curve, accounts, swap_constraints)
-            }
-            SwapInstruction::Swap(Swap {
-                amount_in,
-                minimum_amount_out,
-            }) => {
-                msg!("Instruction: Swap");
-                Self::process_swap(program_id, amount_in, minimum_amount_out, accounts)
-            }
-            SwapInstruction::DepositAllTokenTypes(DepositAllTokenTypes {
-                pool_token_amount,
-                maximum_token_a_amount,
-                maximum_token_b_amount,
-            }) => {
-                msg!("Instruction: DepositAllTokenTypes");
-                Self::process_deposit_all_token_types(
-                    program_id,
-                    pool_token_amount,
-                    maximum_token_a_amount,
-                    maximum_token_b_amount,
-                    accounts,
-                )
-            }
-            SwapInstruction::WithdrawAllTokenTypes(WithdrawAllTokenTypes {
-                pool_token_amount,
-                minimum_token_a_amount,
-                minimum_token_b_amount,
-            }) => {
-                msg!("Instruction: WithdrawAllTokenTypes");
-                Self::process_withdraw_all_token_types(
-                    program_id,
-                    pool_token_amount,
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                    accounts,
-                )
-            }
-            SwapInstruction::DepositSingleTokenTypeExactAmountIn(
-                DepositSingleTokenTypeExactAmountIn {
-                    source_token_amount,
-                    minimum_pool_token_amount,
-                },
-            ) => {
-                msg!("Instruction: DepositSingleTokenTypeExactAmountIn");
-                Self::process_deposit_single_token_type_exact_amount_in(
-                    program_id,
-                    source_token_amount,
-                    minimum_pool_token_amount,
-                    accounts,
-                )
-            }
-            SwapInstruction::WithdrawSingleTokenTypeExactAmountOut(
-                WithdrawSingleTokenTypeExactAmountOut {
-                    destination_token_amount,
-                    maximum_pool_token_amount,
-                },
-            ) => {
-                msg!("Instruction: WithdrawSingleTokenTypeExactAmountOut");
-                Self::process_withdraw_single_token_type_exact_amount_out(
-                    program_id,
-                    destination_token_amount,
-                    maximum_pool_token_amount,
-                    accounts,
-                )
-            }
-        }
-    }
-}
-
-fn to_u128(val: u64) -> Result<u128, SwapError> {
-    val.try_into().map_err(|_| SwapError::ConversionFailure)
-}
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let token_a_info = next_account_info(account_info_iter)?;
+        let token_b_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
 
-fn to_u64(val: u128) -> Result<u64, SwapError> {
-    val.try_into().map_err(|_| SwapError::ConversionFailure)
-}
+        if swap_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let token_program_id = *token_program_info.key;
+        if SwapVersion::is_initialized(&swap_info.data.borrow()) {
+            return Err(SwapError::AlreadyInUse.into());
+        }
 
-fn invoke_signed_wrapper<T>(
-    instruction: &Instruction,
-    account_infos: &[AccountInfo],
-    signers_seeds: &[&[&[u8]]],
-) -> Result<(), ProgramError>
-where
-    T: 'static + PrintProgramError + DecodeError<T> + FromPrimitive + Error,
-{
-    invoke_signed(instruction, account_infos, signers_seeds).map_err(|err| {
-        err.print::<T>();
-        err
-    })
-}
+        let (swap_authority, bump_seed) =
+            Pubkey::find_program_address(&[&swap_info.key.to_bytes()], program_id);
+        if *authority_info.key != swap_authority {
+            return Err(SwapError::InvalidProgramAddress.into());
+        }
+        let token_a = Self::unpack_token_account(token_a_info, &token_program_id)?;
+        let token_b = Self::unpack_token_account(token_b_info, &token_program_id)?;
+        if *authority_info.key != token_a.owner {
+            return Err(SwapError::InvalidOwner.into());
+        }
+        if *authority_info.key != token_b.owner {
+            return Err(SwapError::InvalidOwner.into());
+        }
+        if token_a.mint == token_b.mint {
+            return Err(SwapError::RepeatedMint.into());
+        }
+        if token_a.delegate.is_some() {
+            return Err(SwapError::InvalidDelegate.into());
+        }
+        if token_b.delegate.is_some() {
+            return Err(SwapError::InvalidDelegate.into());
+        }
+        if token_a.close_authority.is_some() {
+            return Err(SwapError::InvalidCloseAuthority.into());
+        }
+        if token_b.close_authority.is_some() {
+            return Err(SwapError::InvalidCloseAuthority.into());
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        curve::calculator::{CurveCalculator, INITIAL_SWAP_POOL_AMOUNT},
-        curve::{
-            base::CurveType, constant_price::ConstantPriceCurve,
-            constant_product::ConstantProductCurve, offset::OffsetCurve,
-        },
-        instruction::{
-            deposit_all_token_types, deposit_single_token_type_exact_amount_in, initialize, swap,
-            withdraw_all_token_types, withdraw_single_token_type_exact_amount_out,
-        },
-    };
-    use solana_program::{
-        clock::Clock, entrypoint::SUCCESS, instruction::Instruction, program_pack::Pack,
-        program_stubs, rent::Rent,
-    };
-    use solana_sdk::account::{
-        create_account_for_test, create_is_signer_account_infos, Account as SolanaAccount,
-    };
-    use spl_token_2022::{
-        error::TokenError,
-        extension::{
-            transfer_fee::{instruction::initialize_transfer_fee_config, TransferFee},
-            ExtensionType,
-        },
-        instruction::{
-            approve, close_account, freeze_account, initialize_account, initialize_immutable_owner,
-            initialize_mint, initialize_mint_close_authority, mint_to, revoke, set_authority,
-            AuthorityType,
-        },
-    };
-    use std::sync::Arc;
-    use test_case::test_case;
+        // there is no pool token to collect owner or host fees in
+        if fees.owner_trade_fee_numerator != 0
+            || fees.owner_withdraw_fee_numerator != 0
+            || fees.host_fee_numerator != 0
+        {
+            return Err(SwapError::InvalidFee.into());
+        }
+        if let Some(swap_constraints) = swap_constraints {
+            swap_constraints.validate_trade_fee(&fees)?;
+        }
+        fees.validate()?;
+        if tick_spacing == 0 || i32::from(tick_spacing) > MAX_TICK {
+            return Err(SwapError::InvalidCurve.into());
+        }
+        let current_tick = tick_at_sqrt_price(sqrt_price_x64).ok_or(SwapError::InvalidCurve)?;
 
-    // Test program id for the swap program.
-    const SWAP_PROGRAM_ID: Pubkey = Pubkey::new_from_array([2u8; 32]);
+        let obj = SwapVersion::ConcentratedSwapV1(ConcentratedSwapV1 {
+            is_initialized: true,
+            bump_seed,
+            token_program_id,
+            token_a: *token_a_info.key,
+            token_b: *token_b_info.key,
+            token_a_mint: token_a.mint,
+            token_b_mint: token_b.mint,
+            fees,
+            tick_spacing,
+            sqrt_price_x64,
+            current_tick,
+            liquidity: 0,
+            fee_growth_global_a_x64: 0,
+            fee_growth_global_b_x64: 0,
+            ticks: vec![],
+        });
+        SwapVersion::pack(obj, &mut swap_info.data.borrow_mut())?;
+        Ok(())
+    }
 
-    struct TestSyscallStubs {}
-    impl program_stubs::SyscallStubs for TestSyscallStubs {
-        fn sol_invoke_signed(
-            &self,
-            instruction: &Instruction,
-            account_infos: &[AccountInfo],
-            signers_seeds: &[&[&[u8]]],
-        ) -> ProgramResult {
-            msg!("TestSyscallStubs::sol_invoke_signed()");
+    /// Processes an [OpenPosition](enum.Instruction.html).
+    pub fn process_open_position(
+        program_id: &Pubkey,
+        tick_lower: i32,
+        tick_upper: i32,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let position_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
 
-            let mut new_account_infos = vec![];
+        if swap_info.owner != program_id || position_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if !position_info.is_signer || !owner_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let token_swap = SwapVersion::unpack_concentrated(&swap_info.data.borrow())?;
+        token_swap.check_tick_range(tick_lower, tick_upper)?;
 
-            // mimic check for token program in accounts
-            if !account_infos
-                .iter()
-                .any(|x| *x.key == spl_token::id() || *x.key == spl_token_2022::id())
-            {
-                return Err(ProgramError::InvalidAccountData);
-            }
+        let position = Position::unpack_unchecked(&position_info.data.borrow())?;
+        if position.is_initialized {
+            return Err(SwapError::AlreadyInUse.into());
+        }
+        let rent = Rent::get()?;
+        if !rent.is_exempt(position_info.lamports(), position_info.data_len()) {
+            return Err(ProgramError::AccountNotRentExempt);
+        }
+        let required_lamports = rent
+            .minimum_balance(position_info.data_len())
+            .checked_add(TICK_DEPOSIT_LAMPORTS * 2)
+            .ok_or(SwapError::CalculationFailure)?;
+        if position_info.lamports() < required_lamports {
+            return Err(SwapError::InsufficientTickDeposit.into());
+        }
+        let position = Position {
+            is_initialized: true,
+            pool: *swap_info.key,
+            owner: *owner_info.key,
+            tick_lower,
+            tick_upper,
+            ..Position::default()
+        };
+        Position::pack(position, &mut position_info.data.borrow_mut())?;
+        Ok(())
+    }
 
-            for meta in instruction.accounts.iter() {
-                for account_info in account_infos.iter() {
-                    if meta.pubkey == *account_info.key {
-                        let mut new_account_info = account_info.clone();
-                        for seeds in signers_seeds.iter() {
-                            let signer =
-                                Pubkey::create_program_address(seeds, &SWAP_PROGRAM_ID).unwrap();
-                            if *account_info.key == signer {
-                                new_account_info.is_signer = true;
-                            }
-                        }
-                        new_account_infos.push(new_account_info);
-                    }
-                }
-            }
+    /// Processes an [IncreaseLiquidity](enum.Instruction.html).
+    pub fn process_increase_liquidity(
+        program_id: &Pubkey,
+        liquidity_amount: u128,
+        maximum_token_a_amount: u64,
+        maximum_token_b_amount: u64,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let position_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+        let user_transfer_authority_info = next_account_info(account_info_iter)?;
+        let source_a_info = next_account_info(account_info_iter)?;
+        let source_b_info = next_account_info(account_info_iter)?;
+        let token_a_info = next_account_info(account_info_iter)?;
+        let token_b_info = next_account_info(account_info_iter)?;
+        let token_a_mint_info = next_account_info(account_info_iter)?;
+        let token_b_mint_info = next_account_info(account_info_iter)?;
+        let token_a_program_info = next_account_info(account_info_iter)?;
+        let token_b_program_info = next_account_info(account_info_iter)?;
+        let transfer_hook_accounts = account_info_iter.as_slice();
 
-            if instruction.program_id == spl_token::id() {
-                spl_token::processor::Processor::process(
-                    &instruction.program_id,
-                    &new_account_infos,
-                    &instruction.data,
-                )
-            } else if instruction.program_id == spl_token_2022::id() {
-                spl_token_2022::processor::Processor::process(
-                    &instruction.program_id,
-                    &new_account_infos,
-                    &instruction.data,
-                )
-            } else {
-                Err(ProgramError::IncorrectProgramId)
-            }
+        if swap_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut token_swap = SwapVersion::unpack_concentrated(&swap_info.data.borrow())?;
+        let mut position = Self::unpack_position(program_id, swap_info, position_info, owner_info)?;
+        if *token_a_info.key != token_swap.token_a || *token_b_info.key != token_swap.token_b {
+            return Err(SwapError::IncorrectSwapAccount.into());
+        }
+        if token_a_info.key == source_a_info.key || token_b_info.key == source_b_info.key {
+            return Err(SwapError::InvalidInput.into());
         }
+        if liquidity_amount == 0 {
+            return Err(SwapError::ZeroTradingTokens.into());
+        }
+        let liquidity_delta =
+            i128::try_from(liquidity_amount).map_err(|_| SwapError::CalculationFailure)?;
 
-        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
-            unsafe {
-                *(var_addr as *mut _ as *mut Clock) = Clock::default();
-            }
-            SUCCESS
+        let amounts = token_swap.modify_position(&mut position, liquidity_delta)?;
+        let (token_a_amount, token_a_decimals) = Self::transfer_fee_included_amount(
+            token_a_mint_info,
+            &token_swap.token_program_id,
+            to_u64(amounts.token_a_amount)?,
+        )?;
+        if token_a_amount > maximum_token_a_amount {
+            return Err(SwapError::ExceededSlippage.into());
+        }
+        let (token_b_amount, token_b_decimals) = Self::transfer_fee_included_amount(
+            token_b_mint_info,
+            &token_swap.token_program_id,
+            to_u64(amounts.token_b_amount)?,
+        )?;
+        if token_b_amount > maximum_token_b_amount {
+            return Err(SwapError::ExceededSlippage.into());
         }
-    }
 
-    fn test_syscall_stubs() {
-        use std::sync::Once;
-        static ONCE: Once = Once::new();
+        if token_a_amount > 0 {
+            Self::token_transfer(
+                swap_info.key,
+                token_a_program_info.clone(),
+                source_a_info.clone(),
+                token_a_mint_info.clone(),
+                token_a_info.clone(),
+                user_transfer_authority_info.clone(),
+                transfer_hook_accounts,
+                token_swap.bump_seed,
+                token_a_amount,
+                token_a_decimals,
+            )?;
+        }
+        if token_b_amount > 0 {
+            Self::token_transfer(
+                swap_info.key,
+                token_b_program_info.clone(),
+                source_b_info.clone(),
+                token_b_mint_info.clone(),
+                token_b_info.clone(),
+                user_transfer_authority_info.clone(),
+                transfer_hook_accounts,
+                token_swap.bump_seed,
+                token_b_amount,
+                token_b_decimals,
+            )?;
+        }
 
-        ONCE.call_once(|| {
-            program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs {}));
-        });
+        Position::pack(position, &mut position_info.data.borrow_mut())?;
+        SwapVersion::pack(
+            SwapVersion::ConcentratedSwapV1(token_swap),
+            &mut swap_info.data.borrow_mut(),
+        )?;
+        Ok(())
     }
 
-    #[derive(Default)]
-    struct SwapTransferFees {
-        pool_token: TransferFee,
-        token_a: TransferFee,
-        token_b: TransferFee,
-    }
+    /// Processes a [DecreaseLiquidity](enum.Instruction.html).
+    pub fn process_decrease_liquidity(
+        program_id: &Pubkey,
+        liquidity_amount: u128,
+        minimum_token_a_amount: u64,
+        minimum_token_b_amount: u64,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let position_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+        let token_a_info = next_account_info(account_info_iter)?;
+        let token_b_info = next_account_info(account_info_iter)?;
+        let dest_token_a_info = next_account_info(account_info_iter)?;
+        let dest_token_b_info = next_account_info(account_info_iter)?;
+        let token_a_mint_info = next_account_info(account_info_iter)?;
+        let token_b_mint_info = next_account_info(account_info_iter)?;
+        let token_a_program_info = next_account_info(account_info_iter)?;
+        let token_b_program_info = next_account_info(account_info_iter)?;
+        let transfer_hook_accounts = account_info_iter.as_slice();
 
-    struct SwapAccountInfo {
-        bump_seed: u8,
-        authority_key: Pubkey,
-        fees: Fees,
-        transfer_fees: SwapTransferFees,
-        swap_curve: SwapCurve,
-        swap_key: Pubkey,
-        swap_account: SolanaAccount,
-        pool_mint_key: Pubkey,
-        pool_mint_account: SolanaAccount,
-        pool_fee_key: Pubkey,
-        pool_fee_account: SolanaAccount,
-        pool_token_key: Pubkey,
-        pool_token_account: SolanaAccount,
-        token_a_key: Pubkey,
-        token_a_account: SolanaAccount,
-        token_a_mint_key: Pubkey,
-        token_a_mint_account: SolanaAccount,
-        token_b_key: Pubkey,
-        token_b_account: SolanaAccount,
-        token_b_mint_key: Pubkey,
-        token_b_mint_account: SolanaAccount,
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    }
+        if swap_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut token_swap = SwapVersion::unpack_concentrated(&swap_info.data.borrow())?;
+        if *authority_info.key
+            != Self::authority_id(program_id, swap_info.key, token_swap.bump_seed)?
+        {
+            return Err(SwapError::InvalidProgramAddress.into());
+        }
+        let mut position = Self::unpack_position(program_id, swap_info, position_info, owner_info)?;
+        if *token_a_info.key != token_swap.token_a || *token_b_info.key != token_swap.token_b {
+            return Err(SwapError::IncorrectSwapAccount.into());
+        }
+        if token_a_info.key == dest_token_a_info.key || token_b_info.key == dest_token_b_info.key {
+            return Err(SwapError::InvalidInput.into());
+        }
+        if liquidity_amount > position.liquidity {
+            return Err(SwapError::InsufficientLiquidity.into());
+        }
+        let liquidity_delta = i128::try_from(liquidity_amount)
+            .ok()
+            .and_then(|liquidity| liquidity.checked_neg())
+            .ok_or(SwapError::CalculationFailure)?;
 
-    impl SwapAccountInfo {
-        #[allow(clippy::too_many_arguments)]
-        pub fn new(
-            user_key: &Pubkey,
-            fees: Fees,
-            transfer_fees: SwapTransferFees,
-            swap_curve: SwapCurve,
-            token_a_amount: u64,
-            token_b_amount: u64,
-            pool_token_program_id: &Pubkey,
-            token_a_program_id: &Pubkey,
-            token_b_program_id: &Pubkey,
-        ) -> Self {
-            let swap_key = Pubkey::new_unique();
-            let swap_account = SolanaAccount::new(0, SwapVersion::LATEST_LEN, &SWAP_PROGRAM_ID);
-            let (authority_key, bump_seed) =
-                Pubkey::find_program_address(&[&swap_key.to_bytes()[..]], &SWAP_PROGRAM_ID);
+        let amounts = token_swap.modify_position(&mut position, liquidity_delta)?;
+        let token_a_amount = to_u64(amounts.token_a_amount)?;
+        if token_a_amount < minimum_token_a_amount {
+            return Err(SwapError::ExceededSlippage.into());
+        }
+        let token_b_amount = to_u64(amounts.token_b_amount)?;
+        if token_b_amount < minimum_token_b_amount {
+            return Err(SwapError::ExceededSlippage.into());
+        }
+        let token_a_amount = token_a_amount
+            .checked_add(position.tokens_owed_a)
+            .ok_or(SwapError::CalculationFailure)?;
+        let token_b_amount = token_b_amount
+            .checked_add(position.tokens_owed_b)
+            .ok_or(SwapError::CalculationFailure)?;
+        position.tokens_owed_a = 0;
+        position.tokens_owed_b = 0;
 
-            let (pool_mint_key, mut pool_mint_account) = create_mint(
-                pool_token_program_id,
-                &authority_key,
-                None,
-                None,
-                &transfer_fees.pool_token,
-            );
-            let (pool_token_key, pool_token_account) = mint_token(
-                pool_token_program_id,
-                &pool_mint_key,
-                &mut pool_mint_account,
-                &authority_key,
-                user_key,
-                0,
-            );
-            let (pool_fee_key, pool_fee_account) = mint_token(
-                pool_token_program_id,
-                &pool_mint_key,
-                &mut pool_mint_account,
-                &authority_key,
-                user_key,
-                0,
-            );
-            let (token_a_mint_key, mut token_a_mint_account) = create_mint(
-                token_a_program_id,
-                user_key,
-                None,
-                None,
-                &transfer_fees.token_a,
-            );
-            let (token_a_key, token_a_account) = mint_token(
-                token_a_program_id,
-                &token_a_mint_key,
-                &mut token_a_mint_account,
-                user_key,
-                &authority_key,
+        if token_a_amount > 0 {
+            let token_a_mint = Self::unpack_mint(token_a_mint_info, &token_swap.token_program_id)?;
+            Self::token_transfer(
+                swap_info.key,
+                token_a_program_info.clone(),
+                token_a_info.clone(),
+                token_a_mint_info.clone(),
+                dest_token_a_info.clone(),
+                authority_info.clone(),
+                transfer_hook_accounts,
+                token_swap.bump_seed,
                 token_a_amount,
-            );
-            let (token_b_mint_key, mut token_b_mint_account) = create_mint(
-                token_b_program_id,
-                user_key,
-                None,
-                None,
-                &transfer_fees.token_b,
-            );
-            let (token_b_key, token_b_account) = mint_token(
-                token_b_program_id,
-                &token_b_mint_key,
-                &mut token_b_mint_account,
-                user_key,
-                &authority_key,
+                token_a_mint.decimals,
+            )?;
+        }
+        if token_b_amount > 0 {
+            let token_b_mint = Self::unpack_mint(token_b_mint_info, &token_swap.token_program_id)?;
+            Self::token_transfer(
+                swap_info.key,
+                token_b_program_info.clone(),
+                token_b_info.clone(),
+                token_b_mint_info.clone(),
+                dest_token_b_info.clone(),
+                authority_info.clone(),
+                transfer_hook_accounts,
+                token_swap.bump_seed,
                 token_b_amount,
-            );
+                token_b_mint.decimals,
+            )?;
+        }
 
-            SwapAccountInfo {
-                bump_seed,
-                authority_key,
-                fees,
-                transfer_fees,
-                swap_curve,
-                swap_key,
-                swap_account,
-                pool_mint_key,
-                pool_mint_account,
-                pool_fee_key,
-                pool_fee_account,
-                pool_token_key,
-                pool_token_account,
-                token_a_key,
-                token_a_account,
-                token_a_mint_key,
-                token_a_mint_account,
-                token_b_key,
-                token_b_account,
-                token_b_mint_key,
-                token_b_mint_account,
-                pool_token_program_id: *pool_token_program_id,
-                token_a_program_id: *token_a_program_id,
-                token_b_program_id: *token_b_program_id,
-            }
+        Position::pack(position, &mut position_info.data.borrow_mut())?;
+        SwapVersion::pack(
+            SwapVersion::ConcentratedSwapV1(token_swap),
+            &mut swap_info.data.borrow_mut(),
+        )?;
+        Ok(())
+    }
+
+    /// Processes a [ConcentratedSwap](enum.Instruction.html).
+    pub fn process_concentrated_swap(
+        program_id: &Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let user_transfer_authority_info = next_account_info(account_info_iter)?;
+        let source_info = next_account_info(account_info_iter)?;
+        let swap_source_info = next_account_info(account_info_iter)?;
+        let swap_destination_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let source_token_mint_info = next_account_info(account_info_iter)?;
+        let destination_token_mint_info = next_account_info(account_info_iter)?;
+        let source_token_program_info = next_account_info(account_info_iter)?;
+        let destination_token_program_info = next_account_info(account_info_iter)?;
+        let transfer_hook_accounts = account_info_iter.as_slice();
+
+        if swap_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut token_swap = SwapVersion::unpack_concentrated(&swap_info.data.borrow())?;
+        if *authority_info.key
+            != Self::authority_id(program_id, swap_info.key, token_swap.bump_seed)?
+        {
+            return Err(SwapError::InvalidProgramAddress.into());
+        }
+        let a_to_b = if *swap_source_info.key == token_swap.token_a
+            && *swap_destination_info.key == token_swap.token_b
+        {
+            true
+        } else if *swap_source_info.key == token_swap.token_b
+            && *swap_destination_info.key == token_swap.token_a
+        {
+            false
+        } else {
+            return Err(SwapError::IncorrectSwapAccount.into());
+        };
+        if swap_source_info.key == source_info.key {
+            return Err(SwapError::InvalidInput.into());
+        }
+        if swap_destination_info.key == destination_info.key {
+            return Err(SwapError::InvalidInput.into());
         }
 
-        pub fn initialize_swap(&mut self) -> ProgramResult {
-            do_process_instruction(
-                initialize(
-                    &SWAP_PROGRAM_ID,
-                    &self.pool_token_program_id,
-                    &self.swap_key,
-                    &self.authority_key,
-                    &self.token_a_key,
-                    &self.token_b_key,
-                    &self.pool_mint_key,
-                    &self.pool_fee_key,
-                    &self.pool_token_key,
-                    self.fees.clone(),
-                    self.swap_curve.clone(),
-                )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut SolanaAccount::default(),
-                    &mut self.token_a_account,
-                    &mut self.token_b_account,
-                    &mut self.pool_mint_account,
-                    &mut self.pool_fee_account,
-                    &mut self.pool_token_account,
-                    &mut SolanaAccount::default(),
-                ],
-            )
+        // Take transfer fees into account for actual amount transferred in
+        let actual_amount_in = Self::transfer_fee_excluded_amount(
+            source_token_mint_info,
+            &token_swap.token_program_id,
+            amount_in,
+        )?;
+        let result = token_swap.swap(to_u128(actual_amount_in)?, a_to_b)?;
+        if result.amount_out == 0 {
+            return Err(SwapError::ZeroTradingTokens.into());
         }
 
-        pub fn setup_token_accounts(
-            &mut self,
-            mint_owner: &Pubkey,
-            account_owner: &Pubkey,
-            a_amount: u64,
-            b_amount: u64,
-            pool_amount: u64,
-        ) -> (
-            Pubkey,
-            SolanaAccount,
-            Pubkey,
-            SolanaAccount,
-            Pubkey,
-            SolanaAccount,
-        ) {
-            let (token_a_key, token_a_account) = mint_token(
-                &self.token_a_program_id,
-                &self.token_a_mint_key,
-                &mut self.token_a_mint_account,
-                mint_owner,
-                account_owner,
-                a_amount,
-            );
-            let (token_b_key, token_b_account) = mint_token(
-                &self.token_b_program_id,
-                &self.token_b_mint_key,
-                &mut self.token_b_mint_account,
-                mint_owner,
-                account_owner,
-                b_amount,
-            );
-            let (pool_key, pool_account) = mint_token(
-                &self.pool_token_program_id,
-                &self.pool_mint_key,
-                &mut self.pool_mint_account,
-                &self.authority_key,
-                account_owner,
-                pool_amount,
-            );
-            (
-                token_a_key,
-                token_a_account,
-                token_b_key,
-                token_b_account,
-                pool_key,
-                pool_account,
-            )
-        }
+        // Re-calculate the source amount swapped based on what the pool used
+        let (source_transfer_amount, source_mint_decimals) = Self::transfer_fee_included_amount(
+            source_token_mint_info,
+            &token_swap.token_program_id,
+            to_u64(result.amount_in)?,
+        )?;
 
-        fn get_swap_key(&self, mint_key: &Pubkey) -> &Pubkey {
-            if *mint_key == self.token_a_mint_key {
-                &self.token_a_key
-            } else if *mint_key == self.token_b_mint_key {
-                &self.token_b_key
-            } else {
-                panic!("Could not find matching swap token account");
-            }
+        let destination_transfer_amount = to_u64(result.amount_out)?;
+        let amount_received = Self::transfer_fee_excluded_amount(
+            destination_token_mint_info,
+            &token_swap.token_program_id,
+            destination_transfer_amount,
+        )?;
+        if amount_received < minimum_amount_out {
+            return Err(SwapError::ExceededSlippage.into());
         }
+        let destination_mint =
+            Self::unpack_mint(destination_token_mint_info, &token_swap.token_program_id)?;
 
-        fn get_token_program_id(&self, account_key: &Pubkey) -> &Pubkey {
-            if *account_key == self.token_a_key {
-                &self.token_a_program_id
-            } else if *account_key == self.token_b_key {
-                &self.token_b_program_id
-            } else {
-                panic!("Could not find matching swap token account");
-            }
-        }
+        Self::token_transfer(
+            swap_info.key,
+            source_token_program_info.clone(),
+            source_info.clone(),
+            source_token_mint_info.clone(),
+            swap_source_info.clone(),
+            user_transfer_authority_info.clone(),
+            transfer_hook_accounts,
+            token_swap.bump_seed,
+            source_transfer_amount,
+            source_mint_decimals,
+        )?;
+        Self::token_transfer(
+            swap_info.key,
+            destination_token_program_info.clone(),
+            swap_destination_info.clone(),
+            destination_token_mint_info.clone(),
+            destination_info.clone(),
+            authority_info.clone(),
+            transfer_hook_accounts,
+            token_swap.bump_seed,
+            destination_transfer_amount,
+            destination_mint.decimals,
+        )?;
 
-        fn get_token_mint(&self, account_key: &Pubkey) -> (Pubkey, SolanaAccount) {
-            if *account_key == self.token_a_key {
-                (self.token_a_mint_key, self.token_a_mint_account.clone())
-            } else if *account_key == self.token_b_key {
-                (self.token_b_mint_key, self.token_b_mint_account.clone())
-            } else {
-                panic!("Could not find matching swap token account");
-            }
+        SwapVersion::pack(
+            SwapVersion::ConcentratedSwapV1(token_swap),
+            &mut swap_info.data.borrow_mut(),
+        )?;
+        Ok(())
+    }
+
+    /// Processes a [ClosePosition](enum.Instruction.html).
+    pub fn process_close_position(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let position_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+
+        if swap_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let position = Self::unpack_position(program_id, swap_info, position_info, owner_info)?;
+        if position.liquidity != 0 || position.tokens_owed_a != 0 || position.tokens_owed_b != 0 {
+            return Err(SwapError::PositionNotEmpty.into());
         }
 
-        fn get_token_account(&self, account_key: &Pubkey) -> &SolanaAccount {
-            if *account_key == self.token_a_key {
-                &self.token_a_account
-            } else if *account_key == self.token_b_key {
-                &self.token_b_account
-            } else {
-                panic!("Could not find matching swap token account");
-            }
+        let destination_starting_lamports = destination_info.lamports();
+        **destination_info.lamports.borrow_mut() = destination_starting_lamports
+            .checked_add(position_info.lamports())
+            .ok_or(SwapError::CalculationFailure)?;
+        **position_info.lamports.borrow_mut() = 0;
+        position_info.data.borrow_mut().fill(0);
+        Ok(())
+    }
+
+    /// Unpacks a swap, checking that its admin authority signed
+    fn unpack_administered_swap(
+        program_id: &Pubkey,
+        swap_info: &AccountInfo,
+        admin_authority_info: &AccountInfo,
+    ) -> Result<SwapV2, ProgramError> {
+        if swap_info.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let token_swap = SwapVersion::unpack_swap_v2(&swap_info.data.borrow())?;
+        if !token_swap.swap.is_initialized {
+            return Err(ProgramError::UninitializedAccount);
         }
+        if token_swap.admin_authority != COption::Some(*admin_authority_info.key)
+            || !admin_authority_info.is_signer
+        {
+            return Err(SwapError::InvalidAdminAuthority.into());
+        }
+        Ok(token_swap)
+    }
 
-        fn set_token_account(&mut self, account_key: &Pubkey, account: SolanaAccount) {
-            if *account_key == self.token_a_key {
-                self.token_a_account = account;
-                return;
-            } else if *account_key == self.token_b_key {
-                self.token_b_account = account;
-                return;
-            }
-            panic!("Could not find matching swap token account");
+    /// Checks the fees of a swap against the program owner's constraints, if
+    /// any, including the trade fee once the dynamic fee reaches its maximum
+    fn validate_fees(
+        fees: &Fees,
+        dynamic_fee: &DynamicFee,
+        swap_constraints: &Option<SwapConstraints>,
+    ) -> ProgramResult {
+        fees.validate()?;
+        dynamic_fee.validate(fees)?;
+        if let Some(swap_constraints) = swap_constraints {
+            swap_constraints.validate_fees(fees)?;
+            let max_fees = Fees {
+                trade_fee_numerator: fees
+                    .trade_fee_numerator
+                    .checked_add(dynamic_fee.max_fee_numerator)
+                    .ok_or(SwapError::InvalidFee)?,
+                ..fees.clone()
+            };
+            swap_constraints.validate_fees(&max_fees)?;
         }
+        Ok(())
+    }
 
-        #[allow(clippy::too_many_arguments)]
-        pub fn swap(
-            &mut self,
-            user_key: &Pubkey,
-            user_source_key: &Pubkey,
-            user_source_account: &mut SolanaAccount,
-            swap_source_key: &Pubkey,
-            swap_destination_key: &Pubkey,
-            user_destination_key: &Pubkey,
-            user_destination_account: &mut SolanaAccount,
-            amount_in: u64,
-            minimum_amount_out: u64,
-        ) -> ProgramResult {
-            let user_transfer_key = Pubkey::new_unique();
-            let source_token_program_id = self.get_token_program_id(swap_source_key);
-            let destination_token_program_id = self.get_token_program_id(swap_destination_key);
-            // approve moving from user source account
-            do_process_instruction(
-                approve(
-                    source_token_program_id,
-                    user_source_key,
-                    &user_transfer_key,
-                    user_key,
-                    &[],
-                    amount_in,
-                )
-                .unwrap(),
-                vec![
-                    user_source_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-            )
-            .unwrap();
+    /// Processes a [SetFees](enum.Instruction.html).
+    pub fn process_set_fees(
+        program_id: &Pubkey,
+        fees: Fees,
+        dynamic_fee: DynamicFee,
+        accounts: &[AccountInfo],
+        swap_constraints: &Option<SwapConstraints>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let admin_authority_info = next_account_info(account_info_iter)?;
 
-            let (source_mint_key, mut source_mint_account) = self.get_token_mint(swap_source_key);
-            let (destination_mint_key, mut destination_mint_account) =
-                self.get_token_mint(swap_destination_key);
-            let mut swap_source_account = self.get_token_account(swap_source_key).clone();
-            let mut swap_destination_account = self.get_token_account(swap_destination_key).clone();
+        let mut token_swap =
+            Self::unpack_administered_swap(program_id, swap_info, admin_authority_info)?;
+        Self::validate_fees(&fees, &dynamic_fee, swap_constraints)?;
 
-            // perform the swap
-            do_process_instruction(
-                swap(
-                    &SWAP_PROGRAM_ID,
-                    source_token_program_id,
-                    destination_token_program_id,
-                    &self.pool_token_program_id,
-                    &self.swap_key,
-                    &self.authority_key,
-                    &user_transfer_key,
-                    user_source_key,
-                    swap_source_key,
-                    swap_destination_key,
-                    user_destination_key,
-                    &self.pool_mint_key,
-                    &self.pool_fee_key,
-                    &source_mint_key,
-                    &destination_mint_key,
-                    None,
-                    Swap {
-                        amount_in,
-                        minimum_amount_out,
-                    },
-                )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                    user_source_account,
-                    &mut swap_source_account,
-                    &mut swap_destination_account,
-                    user_destination_account,
-                    &mut self.pool_mint_account,
-                    &mut self.pool_fee_account,
-                    &mut source_mint_account,
-                    &mut destination_mint_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-            )?;
+        token_swap.swap.fees = fees;
+        token_swap.dynamic_fee = dynamic_fee;
+        SwapVersion::pack(
+            SwapVersion::SwapV2(token_swap),
+            &mut swap_info.data.borrow_mut(),
+        )?;
+        Ok(())
+    }
 
-            self.set_token_account(swap_source_key, swap_source_account);
-            self.set_token_account(swap_destination_key, swap_destination_account);
+    /// Processes a [SetPoolFeeAccount](enum.Instruction.html).
+    pub fn process_set_pool_fee_account(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        swap_constraints: &Option<SwapConstraints>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let swap_info = next_account_info(account_info_iter)?;
+        let admin_authority_info = next_account_info(account_info_iter)?;
+        let pool_fee_account_info = next_account_info(account_info_iter)?;
 
-            Ok(())
+        let mut token_swap =
+            Self::unpack_administered_swap(program_id, swap_info, admin_authority_info)?;
+        token_swap.check_pool_fee_info(pool_fee_account_info)?;
+        let pool_fee_account =
+            Self::unpack_token_account(pool_fee_account_info, token_swap.token_program_id())?;
+        let authority = Self::authority_id(program_id, swap_info.key, token_swap.bump_seed())?;
+        if pool_fee_account.owner == authority {
+            return Err(SwapError::InvalidOutputOwner.into());
+        }
+        if let Some(swap_constraints) = swap_constraints {
+            let owner_key = swap_constraints
+                .owner_key
+                .parse::<Pubkey>()
+                .map_err(|_| SwapError::InvalidOwner)?;
+            if pool_fee_account.owner != owner_key {
+                return Err(SwapError::InvalidOwner.into());
+            }
         }
 
-        #[allow(clippy::too_many_arguments)]
-        pub fn deposit_all_token_types(
-            &mut self,
-            depositor_key: &Pubkey,
-            depositor_token_a_key: &Pubkey,
-            depositor_token_a_account: &mut SolanaAccount,
-            depositor_token_b_key: &Pubkey,
-            depositor_token_b_account: &mut SolanaAccount,
-            depositor_pool_key: &Pubkey,
-            depositor_pool_account: &mut SolanaAccount,
-            pool_token_amount: u64,
-            maximum_token_a_amount: u64,
-            maximum_token_b_amount: u64,
-        ) -> ProgramResult {
-            let user_transfer_authority = Pubkey::new_unique();
-            let token_a_program_id = depositor_token_a_account.owner;
-            do_process_instruction(
-                approve(
-                    &token_a_program_id,
-                    depositor_token_a_key,
-                    &user_transfer_authority,
-                    depositor_key,
-                    &[],
+        token_swap.swap.pool_fee_account = *pool_fee_account_info.key;
+        SwapVersion::pack(
+            SwapVersion::SwapV2(token_swap),
+            &mut swap_info.data.borrow_mut(),
+        )?;
+        Ok(())
+    }
+
+    /// Processes an [Instruction](enum.Instruction.html).
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+        Self::process_with_constraints(program_id, accounts, input, &SWAP_CONSTRAINTS)
+    }
+
+    /// Processes an instruction given extra constraint
+    pub fn process_with_constraints(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        input: &[u8],
+        swap_constraints: &Option<SwapConstraints>,
+    ) -> ProgramResult {
+        let instruction = SwapInstruction::unpack(input)?;
+        match instruction {
+            SwapInstruction::Initialize(Initialize { fees, swap_curve }) => {
+                msg!("Instruction: Init");
+                Self::process_initialize(program_id, fees, swap_curve, accounts, swap_constraints)
+            }
+            SwapInstruction::Swap(Swap {
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                msg!("Instruction: Swap");
+                Self::process_swap(program_id, amount_in, minimum_amount_out, accounts)
+            }
+            SwapInstruction::DepositAllTokenTypes(DepositAllTokenTypes {
+                pool_token_amount,
+                maximum_token_a_amount,
+                maximum_token_b_amount,
+            }) => {
+                msg!("Instruction: DepositAllTokenTypes");
+                Self::process_deposit_all_token_types(
+                    program_id,
+                    pool_token_amount,
                     maximum_token_a_amount,
+                    maximum_token_b_amount,
+                    accounts,
                 )
-                .unwrap(),
-                vec![
-                    depositor_token_a_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-            )
-            .unwrap();
-
-            let token_b_program_id = depositor_token_b_account.owner;
-            do_process_instruction(
-                approve(
-                    &token_b_program_id,
-                    depositor_token_b_key,
-                    &user_transfer_authority,
-                    depositor_key,
-                    &[],
+            }
+            SwapInstruction::WithdrawAllTokenTypes(WithdrawAllTokenTypes {
+                pool_token_amount,
+                minimum_token_a_amount,
+                minimum_token_b_amount,
+            }) => {
+                msg!("Instruction: WithdrawAllTokenTypes");
+                Self::process_withdraw_all_token_types(
+                    program_id,
+                    pool_token_amount,
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
+                    accounts,
+                )
+            }
+            SwapInstruction::DepositSingleTokenTypeExactAmountIn(
+                DepositSingleTokenTypeExactAmountIn {
+                    source_token_amount,
+                    minimum_pool_token_amount,
+                },
+            ) => {
+                msg!("Instruction: DepositSingleTokenTypeExactAmountIn");
+                Self::process_deposit_single_token_type_exact_amount_in(
+                    program_id,
+                    source_token_amount,
+                    minimum_pool_token_amount,
+                    accounts,
+                )
+            }
+            SwapInstruction::WithdrawSingleTokenTypeExactAmountOut(
+                WithdrawSingleTokenTypeExactAmountOut {
+                    destination_token_amount,
+                    maximum_pool_token_amount,
+                },
+            ) => {
+                msg!("Instruction: WithdrawSingleTokenTypeExactAmountOut");
+                Self::process_withdraw_single_token_type_exact_amount_out(
+                    program_id,
+                    destination_token_amount,
+                    maximum_pool_token_amount,
+                    accounts,
+                )
+            }
+            SwapInstruction::InitializeConcentrated(InitializeConcentrated {
+                fees,
+                tick_spacing,
+                sqrt_price_x64,
+            }) => {
+                msg!("Instruction: InitializeConcentrated");
+                Self::process_initialize_concentrated(
+                    program_id,
+                    fees,
+                    tick_spacing,
+                    sqrt_price_x64,
+                    accounts,
+                    swap_constraints,
+                )
+            }
+            SwapInstruction::OpenPosition(OpenPosition {
+                tick_lower,
+                tick_upper,
+            }) => {
+                msg!("Instruction: OpenPosition");
+                Self::process_open_position(program_id, tick_lower, tick_upper, accounts)
+            }
+            SwapInstruction::IncreaseLiquidity(IncreaseLiquidity {
+                liquidity_amount,
+                maximum_token_a_amount,
+                maximum_token_b_amount,
+            }) => {
+                msg!("Instruction: IncreaseLiquidity");
+                Self::process_increase_liquidity(
+                    program_id,
+                    liquidity_amount,
+                    maximum_token_a_amount,
                     maximum_token_b_amount,
+                    accounts,
                 )
-                .unwrap(),
-                vec![
-                    depositor_token_b_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-            )
-            .unwrap();
-
-            let pool_token_program_id = depositor_pool_account.owner;
-            do_process_instruction(
-                deposit_all_token_types(
-                    &SWAP_PROGRAM_ID,
-                    &token_a_program_id,
-                    &token_b_program_id,
-                    &pool_token_program_id,
-                    &self.swap_key,
-                    &self.authority_key,
-                    &user_transfer_authority,
-                    depositor_token_a_key,
-                    depositor_token_b_key,
-                    &self.token_a_key,
-                    &self.token_b_key,
-                    &self.pool_mint_key,
-                    depositor_pool_key,
-                    &self.token_a_mint_key,
-                    &self.token_b_mint_key,
-                    DepositAllTokenTypes {
-                        pool_token_amount,
-                        maximum_token_a_amount,
-                        maximum_token_b_amount,
-                    },
+            }
+            SwapInstruction::DecreaseLiquidity(DecreaseLiquidity {
+                liquidity_amount,
+                minimum_token_a_amount,
+                minimum_token_b_amount,
+            }) => {
+                msg!("Instruction: DecreaseLiquidity");
+                Self::process_decrease_liquidity(
+                    program_id,
+                    liquidity_amount,
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
+                    accounts,
                 )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                    depositor_token_a_account,
-                    depositor_token_b_account,
-                    &mut self.token_a_account,
-                    &mut self.token_b_account,
-                    &mut self.pool_mint_account,
-                    depositor_pool_account,
-                    &mut self.token_a_mint_account,
-                    &mut self.token_b_mint_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-            )
+            }
+            SwapInstruction::ConcentratedSwap(Swap {
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                msg!("Instruction: ConcentratedSwap");
+                Self::process_concentrated_swap(program_id, amount_in, minimum_amount_out, accounts)
+            }
+            SwapInstruction::ClosePosition => {
+                msg!("Instruction: ClosePosition");
+                Self::process_close_position(program_id, accounts)
+            }
+            SwapInstruction::RoutedSwap(RoutedSwap {
+                amount_in,
+                minimum_amount_out,
+                pool_count,
+            }) => {
+                msg!("Instruction: RoutedSwap");
+                Self::process_routed_swap(
+                    program_id,
+                    amount_in,
+                    minimum_amount_out,
+                    pool_count,
+                    accounts,
+                )
+            }
+            SwapInstruction::SetFees(SetFees { fees, dynamic_fee }) => {
+                msg!("Instruction: SetFees");
+                Self::process_set_fees(program_id, fees, dynamic_fee, accounts, swap_constraints)
+            }
+            SwapInstruction::SetPoolFeeAccount => {
+                msg!("Instruction: SetPoolFeeAccount");
+                Self::process_set_pool_fee_account(program_id, accounts, swap_constraints)
+            }
         }
+    }
+}
 
-        #[allow(clippy::too_many_arguments)]
-        pub fn withdraw_all_token_types(
-            &mut self,
-            user_key: &Pubkey,
-            pool_key: &Pubkey,
-            pool_account: &mut SolanaAccount,
-            token_a_key: &Pubkey,
-            token_a_account: &mut SolanaAccount,
-            token_b_key: &Pubkey,
-            token_b_account: &mut SolanaAccount,
-            pool_token_amount: u64,
-            minimum_token_a_amount: u64,
-            minimum_token_b_amount: u64,
-        ) -> ProgramResult {
-            let user_transfer_authority_key = Pubkey::new_unique();
-            let pool_token_program_id = pool_account.owner;
-            // approve user transfer authority to take out pool tokens
-            do_process_instruction(
-                approve(
-                    &pool_token_program_id,
-                    pool_key,
-                    &user_transfer_authority_key,
-                    user_key,
-                    &[],
-                    pool_token_amount,
-                )
-                .unwrap(),
-                vec![
-                    pool_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-            )
-            .unwrap();
+fn to_u128(val: u64) -> Result<u128, SwapError> {
+    val.try_into().map_err(|_| SwapError::ConversionFailure)
+}
 
-            // withdraw token a and b correctly
-            let token_a_program_id = token_a_account.owner;
-            let token_b_program_id = token_b_account.owner;
-            do_process_instruction(
-                withdraw_all_token_types(
-                    &SWAP_PROGRAM_ID,
-                    &pool_token_program_id,
-                    &token_a_program_id,
-                    &token_b_program_id,
-                    &self.swap_key,
-                    &self.authority_key,
-                    &user_transfer_authority_key,
-                    &self.pool_mint_key,
-                    &self.pool_fee_key,
-                    pool_key,
-                    &self.token_a_key,
-                    &self.token_b_key,
-                    token_a_key,
-                    token_b_key,
-                    &self.token_a_mint_key,
-                    &self.token_b_mint_key,
-                    WithdrawAllTokenTypes {
-                        pool_token_amount,
-                        minimum_token_a_amount,
-                        minimum_token_b_amount,
-                    },
-                )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                    &mut self.pool_mint_account,
-                    pool_account,
-                    &mut self.token_a_account,
-                    &mut self.token_b_account,
-                    token_a_account,
-                    token_b_account,
-                    &mut self.pool_fee_account,
-                    &mut self.token_a_mint_account,
-                    &mut self.token_b_mint_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-            )
-        }
-
-        #[allow(clippy::too_many_arguments)]
-        pub fn deposit_single_token_type_exact_amount_in(
-            &mut self,
-            depositor_key: &Pubkey,
-            deposit_account_key: &Pubkey,
-            deposit_token_account: &mut SolanaAccount,
-            deposit_pool_key: &Pubkey,
-            deposit_pool_account: &mut SolanaAccount,
-            source_token_amount: u64,
-            minimum_pool_token_amount: u64,
-        ) -> ProgramResult {
-            let user_transfer_authority_key = Pubkey::new_unique();
-            let source_token_program_id = deposit_token_account.owner;
-            do_process_instruction(
-                approve(
-                    &source_token_program_id,
-                    deposit_account_key,
-                    &user_transfer_authority_key,
-                    depositor_key,
-                    &[],
-                    source_token_amount,
-                )
-                .unwrap(),
-                vec![
-                    deposit_token_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-            )
-            .unwrap();
-
-            let source_mint_key =
-                StateWithExtensions::<Account>::unpack(&deposit_token_account.data)
-                    .unwrap()
-                    .base
-                    .mint;
-            let swap_source_key = self.get_swap_key(&source_mint_key);
-            let (source_mint_key, mut source_mint_account) = self.get_token_mint(swap_source_key);
+fn to_u64(val: u128) -> Result<u64, SwapError> {
+    val.try_into().map_err(|_| SwapError::ConversionFailure)
+}
 
-            let pool_token_program_id = deposit_pool_account.owner;
-            do_process_instruction(
-                deposit_single_token_type_exact_amount_in(
-                    &SWAP_PROGRAM_ID,
-                    &source_token_program_id,
-                    &pool_token_program_id,
-                    &self.swap_key,
-                    &self.authority_key,
-                    &user_transfer_authority_key,
-                    deposit_account_key,
-                    &self.token_a_key,
-                    &self.token_b_key,
-                    &self.pool_mint_key,
-                    deposit_pool_key,
-                    &source_mint_key,
-                    DepositSingleTokenTypeExactAmountIn {
-                        source_token_amount,
-                        minimum_pool_token_amount,
-                    },
-                )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                    deposit_token_account,
-                    &mut self.token_a_account,
-                    &mut self.token_b_account,
-                    &mut self.pool_mint_account,
-                    deposit_pool_account,
-                    &mut source_mint_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-            )
-        }
+fn invoke_signed_wrapper<T>(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> Result<(), ProgramError>
+where
+    T: 'static + PrintProgramError + DecodeError<T> + FromPrimitive + Error,
+{
+    invoke_signed(instruction, account_infos, signers_seeds).map_err(|err| {
+        err.print::<T>();
+        err
+    })
+}
 
-        #[allow(clippy::too_many_arguments)]
-        pub fn withdraw_single_token_type_exact_amount_out(
-            &mut self,
-            user_key: &Pubkey,
-            pool_key: &Pubkey,
-            pool_account: &mut SolanaAccount,
-            destination_key: &Pubkey,
-            destination_account: &mut SolanaAccount,
-            destination_token_amount: u64,
-            maximum_pool_token_amount: u64,
-        ) -> ProgramResult {
-            let user_transfer_authority_key = Pubkey::new_unique();
-            let pool_token_program_id = pool_account.owner;
-            // approve user transfer authority to take out pool tokens
-            do_process_instruction(
-                approve(
-                    &pool_token_program_id,
-                    pool_key,
-                    &user_transfer_authority_key,
-                    user_key,
-                    &[],
-                    maximum_pool_token_amount,
-                )
-                .unwrap(),
-                vec![
-                    pool_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-            )
-            .unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        curve::calculator::{CurveCalculator, INITIAL_SWAP_POOL_AMOUNT},
+        curve::{
+            base::CurveType, constant_price::ConstantPriceCurve,
+            constant_product::ConstantProductCurve, offset::OffsetCurve, weighted::WeightedCurve,
+        },
+        instruction::{
+            add_transfer_hook_accounts, close_position, concentrated_swap, decrease_liquidity,
+            deposit_all_token_types, deposit_single_token_type_exact_amount_in, increase_liquidity,
+            initialize, initialize_concentrated, open_position, routed_swap, set_fees,
+            set_pool_fee_account, swap, withdraw_all_token_types,
+            withdraw_single_token_type_exact_amount_out,
+        },
+    };
+    use solana_program::{
+        clock::Clock, entrypoint::SUCCESS, instruction::Instruction, program_pack::Pack,
+        program_stubs, rent::Rent,
+    };
+    use solana_sdk::account::{
+        create_account_for_test, create_is_signer_account_infos, Account as SolanaAccount,
+    };
+    use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+    use spl_token_2022::{
+        error::TokenError,
+        extension::{
+            transfer_fee::{instruction::initialize_transfer_fee_config, TransferFee},
+            ExtensionType,
+        },
+        instruction::{
+            approve, close_account, freeze_account, initialize_account, initialize_immutable_owner,
+            initialize_mint, initialize_mint_close_authority, mint_to, revoke, set_authority,
+            AuthorityType,
+        },
+    };
+    use spl_transfer_hook_interface::{
+        error::TransferHookError, get_extra_account_metas_address, instruction::ExecuteInstruction,
+    };
+    use std::{cell::Cell, sync::Arc};
+    use test_case::test_case;
 
-            let destination_mint_key =
-                StateWithExtensions::<Account>::unpack(&destination_account.data)
-                    .unwrap()
-                    .base
-                    .mint;
-            let swap_destination_key = self.get_swap_key(&destination_mint_key);
-            let (destination_mint_key, mut destination_mint_account) =
-                self.get_token_mint(swap_destination_key);
+    // Test program id for the swap program.
+    const SWAP_PROGRAM_ID: Pubkey = Pubkey::new_from_array([2u8; 32]);
+    // Test program id for the transfer hook program.
+    const TRANSFER_HOOK_PROGRAM_ID: Pubkey = Pubkey::new_from_array([3u8; 32]);
 
-            let destination_token_program_id = destination_account.owner;
-            do_process_instruction(
-                withdraw_single_token_type_exact_amount_out(
-                    &SWAP_PROGRAM_ID,
-                    &pool_token_program_id,
-                    &destination_token_program_id,
-                    &self.swap_key,
-                    &self.authority_key,
-                    &user_transfer_authority_key,
-                    &self.pool_mint_key,
-                    &self.pool_fee_key,
-                    pool_key,
-                    &self.token_a_key,
-                    &self.token_b_key,
-                    destination_key,
-                    &destination_mint_key,
-                    WithdrawSingleTokenTypeExactAmountOut {
-                        destination_token_amount,
-                        maximum_pool_token_amount,
-                    },
-                )
-                .unwrap(),
-                vec![
-                    &mut self.swap_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                    &mut self.pool_mint_account,
-                    pool_account,
-                    &mut self.token_a_account,
-                    &mut self.token_b_account,
-                    destination_account,
-                    &mut self.pool_fee_account,
-                    &mut destination_mint_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-            )
-        }
+    thread_local! {
+        // Unix timestamp returned by the clock sysvar in the current test.
+        static CLOCK_UNIX_TIMESTAMP: Cell<i64> = const { Cell::new(0) };
     }
 
-    fn mint_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(spl_token::state::Mint::get_packed_len())
-    }
+    struct TestSyscallStubs {}
+    impl program_stubs::SyscallStubs for TestSyscallStubs {
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            account_infos: &[AccountInfo],
+            signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            msg!("TestSyscallStubs::sol_invoke_signed()");
 
-    fn account_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(spl_token::state::Account::get_packed_len())
-    }
+            let mut new_account_infos = vec![];
 
-    fn do_process_instruction_with_fee_constraints(
-        instruction: Instruction,
-        accounts: Vec<&mut SolanaAccount>,
-        swap_constraints: &Option<SwapConstraints>,
-    ) -> ProgramResult {
-        test_syscall_stubs();
+            // mimic check for token program in accounts
+            if instruction.program_id != TRANSFER_HOOK_PROGRAM_ID
+                && !account_infos
+                    .iter()
+                    .any(|x| *x.key == spl_token::id() || *x.key == spl_token_2022::id())
+            {
+                return Err(ProgramError::InvalidAccountData);
+            }
 
-        // approximate the logic in the actual runtime which runs the instruction
-        // and only updates accounts if the instruction is successful
-        let mut account_clones = accounts.iter().map(|x| (*x).clone()).collect::<Vec<_>>();
-        let mut meta = instruction
-            .accounts
-            .iter()
-            .zip(account_clones.iter_mut())
-            .map(|(account_meta, account)| (&account_meta.pubkey, account_meta.is_signer, account))
-            .collect::<Vec<_>>();
-        let mut account_infos = create_is_signer_account_infos(&mut meta);
-        let res = if instruction.program_id == SWAP_PROGRAM_ID {
-            Processor::process_with_constraints(
-                &instruction.program_id,
-                &account_infos,
-                &instruction.data,
-                swap_constraints,
-            )
-        } else if instruction.program_id == spl_token::id() {
-            spl_token::processor::Processor::process(
-                &instruction.program_id,
-                &account_infos,
-                &instruction.data,
-            )
-        } else if instruction.program_id == spl_token_2022::id() {
-            spl_token_2022::processor::Processor::process(
-                &instruction.program_id,
-                &account_infos,
-                &instruction.data,
-            )
-        } else {
-            Err(ProgramError::IncorrectProgramId)
-        };
-
-        if res.is_ok() {
-            let mut account_metas = instruction
-                .accounts
-                .iter()
-                .zip(accounts)
-                .map(|(account_meta, account)| (&account_meta.pubkey, account))
-                .collect::<Vec<_>>();
-            for account_info in account_infos.iter_mut() {
-                for account_meta in account_metas.iter_mut() {
-                    if account_info.key == account_meta.0 {
-                        let account = &mut account_meta.1;
-                        account.owner = *account_info.owner;
-                        account.lamports = **account_info.lamports.borrow();
-                        account.data = account_info.data.borrow().to_vec();
+            for meta in instruction.accounts.iter() {
+                for account_info in account_infos.iter() {
+                    if meta.pubkey == *account_info.key {
+                        let mut new_account_info = account_info.clone();
+                        for seeds in signers_seeds.iter() {
+                            let signer =
+                                Pubkey::create_program_address(seeds, &SWAP_PROGRAM_ID).unwrap();
+                            if *account_info.key == signer {
+                                new_account_info.is_signer = true;
+                            }
+                        }
+                        new_account_infos.push(new_account_info);
                     }
                 }
             }
-        }
-        res
-    }
-
-    fn do_process_instruction(
-        instruction: Instruction,
-        accounts: Vec<&mut SolanaAccount>,
-    ) -> ProgramResult {
-        do_process_instruction_with_fee_constraints(instruction, accounts, &SWAP_CONSTRAINTS)
-    }
-
-    fn mint_token(
-        program_id: &Pubkey,
-        mint_key: &Pubkey,
-        mint_account: &mut SolanaAccount,
-        mint_authority_key: &Pubkey,
-        account_owner_key: &Pubkey,
-        amount: u64,
-    ) -> (Pubkey, SolanaAccount) {
-        let account_key = Pubkey::new_unique();
-        let space = if *program_id == spl_token_2022::id() {
-            ExtensionType::try_calculate_account_len::<Account>(&[
-                ExtensionType::ImmutableOwner,
-                ExtensionType::TransferFeeAmount,
-            ])
-            .unwrap()
-        } else {
-            Account::get_packed_len()
-        };
-        let minimum_balance = Rent::default().minimum_balance(space);
-        let mut account_account = SolanaAccount::new(minimum_balance, space, program_id);
-        let mut mint_authority_account = SolanaAccount::default();
-        let mut rent_sysvar_account = create_account_for_test(&Rent::free());
-
-        // no-ops in normal token, so we're good to run it either way
-        do_process_instruction(
-            initialize_immutable_owner(program_id, &account_key).unwrap(),
-            vec![&mut account_account],
-        )
-        .unwrap();
-
-        do_process_instruction(
-            initialize_account(program_id, &account_key, mint_key, account_owner_key).unwrap(),
-            vec![
-                &mut account_account,
-                mint_account,
-                &mut mint_authority_account,
-                &mut rent_sysvar_account,
-            ],
-        )
-        .unwrap();
 
-        if amount > 0 {
-            do_process_instruction(
-                mint_to(
-                    program_id,
-                    mint_key,
-                    &account_key,
-                    mint_authority_key,
-                    &[],
-                    amount,
+            if instruction.program_id == spl_token::id() {
+                spl_token::processor::Processor::process(
+                    &instruction.program_id,
+                    &new_account_infos,
+                    &instruction.data,
+                )
+            } else if instruction.program_id == spl_token_2022::id() {
+                spl_token_2022::processor::Processor::process(
+                    &instruction.program_id,
+                    &new_account_infos,
+                    &instruction.data,
+                )
+            } else if instruction.program_id == TRANSFER_HOOK_PROGRAM_ID {
+                spl_transfer_hook_example::processor::process(
+                    &instruction.program_id,
+                    &new_account_infos,
+                    &instruction.data,
                 )
-                .unwrap(),
-                vec![
-                    mint_account,
-                    &mut account_account,
-                    &mut mint_authority_account,
-                ],
-            )
-            .unwrap();
-        }
-
-        (account_key, account_account)
-    }
-
-    fn create_mint(
-        program_id: &Pubkey,
-        authority_key: &Pubkey,
-        freeze_authority: Option<&Pubkey>,
-        close_authority: Option<&Pubkey>,
-        fees: &TransferFee,
-    ) -> (Pubkey, SolanaAccount) {
-        let mint_key = Pubkey::new_unique();
-        let space = if *program_id == spl_token_2022::id() {
-            if close_authority.is_some() {
-                ExtensionType::try_calculate_account_len::<Mint>(&[
-                    ExtensionType::MintCloseAuthority,
-                    ExtensionType::TransferFeeConfig,
-                ])
-                .unwrap()
             } else {
-                ExtensionType::try_calculate_account_len::<Mint>(&[
-                    ExtensionType::TransferFeeConfig,
-                ])
-                .unwrap()
+                Err(ProgramError::IncorrectProgramId)
             }
-        } else {
-            Mint::get_packed_len()
-        };
-        let minimum_balance = Rent::default().minimum_balance(space);
-        let mut mint_account = SolanaAccount::new(minimum_balance, space, program_id);
-        let mut rent_sysvar_account = create_account_for_test(&Rent::free());
+        }
 
-        if *program_id == spl_token_2022::id() {
-            if close_authority.is_some() {
-                do_process_instruction(
-                    initialize_mint_close_authority(program_id, &mint_key, close_authority)
-                        .unwrap(),
-                    vec![&mut mint_account],
-                )
-                .unwrap();
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe {
+                *(var_addr as *mut _ as *mut Clock) = Clock {
+                    unix_timestamp: CLOCK_UNIX_TIMESTAMP.with(Cell::get),
+                    ..Clock::default()
+                };
             }
-            do_process_instruction(
-                initialize_transfer_fee_config(
-                    program_id,
-                    &mint_key,
-                    freeze_authority,
-                    freeze_authority,
-                    fees.transfer_fee_basis_points.into(),
-                    fees.maximum_fee.into(),
-                )
-                .unwrap(),
-                vec![&mut mint_account],
-            )
-            .unwrap();
+            SUCCESS
         }
-        do_process_instruction(
-            initialize_mint(program_id, &mint_key, authority_key, freeze_authority, 2).unwrap(),
-            vec![&mut mint_account, &mut rent_sysvar_account],
-        )
-        .unwrap();
 
-        (mint_key, mint_account)
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe {
+                *(var_addr as *mut _ as *mut Rent) = Rent::default();
+            }
+            SUCCESS
+        }
     }
 
-    #[test_case(spl_token::id(); "token")]
-    #[test_case(spl_token_2022::id(); "token-2022")]
-    fn test_token_program_id_error(token_program_id: Pubkey) {
-        test_syscall_stubs();
-        let swap_key = Pubkey::new_unique();
-        let mut mint = (Pubkey::new_unique(), SolanaAccount::default());
-        let mut destination = (Pubkey::new_unique(), SolanaAccount::default());
-        let token_program = (token_program_id, SolanaAccount::default());
-        let (authority_key, bump_seed) =
-            Pubkey::find_program_address(&[&swap_key.to_bytes()[..]], &SWAP_PROGRAM_ID);
-        let mut authority = (authority_key, SolanaAccount::default());
-        let swap_bytes = swap_key.to_bytes();
-        let authority_signature_seeds = [&swap_bytes[..32], &[bump_seed]];
-        let signers = &[&authority_signature_seeds[..]];
-        let ix = mint_to(
-            &token_program.0,
-            &mint.0,
-            &destination.0,
-            &authority.0,
-            &[],
-            10,
-        )
-        .unwrap();
-        let mint = (&mut mint).into();
-        let destination = (&mut destination).into();
-        let authority = (&mut authority).into();
+    fn test_syscall_stubs() {
+        use std::sync::Once;
+        static ONCE: Once = Once::new();
 
-        let err = invoke_signed(&ix, &[mint, destination, authority], signers).unwrap_err();
-        assert_eq!(err, ProgramError::InvalidAccountData);
+        ONCE.call_once(|| {
+            program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs {}));
+        });
     }
 
-    #[test_case(spl_token::id(); "token")]
-    #[test_case(spl_token_2022::id(); "token-2022")]
-    fn test_token_error(token_program_id: Pubkey) {
-        test_syscall_stubs();
-        let swap_key = Pubkey::new_unique();
-        let mut mint = (
-            Pubkey::new_unique(),
-            SolanaAccount::new(
-                mint_minimum_balance(),
-                spl_token::state::Mint::get_packed_len(),
-                &token_program_id,
-            ),
-        );
-        let mut destination = (
-            Pubkey::new_unique(),
-            SolanaAccount::new(
-                account_minimum_balance(),
-                spl_token::state::Account::get_packed_len(),
-                &token_program_id,
-            ),
-        );
-        let mut token_program = (token_program_id, SolanaAccount::default());
-        let (authority_key, bump_seed) =
-            Pubkey::find_program_address(&[&swap_key.to_bytes()[..]], &SWAP_PROGRAM_ID);
-        let mut authority = (authority_key, SolanaAccount::default());
-        let swap_bytes = swap_key.to_bytes();
-        let authority_signature_seeds = [&swap_bytes[..32], &[bump_seed]];
-        let signers = &[&authority_signature_seeds[..]];
-        let mut rent_sysvar = (
-            Pubkey::new_unique(),
-            create_account_for_test(&Rent::default()),
-        );
-        do_process_instruction(
-            initialize_mint(
-                &token_program.0,
-                &mint.0,
-                &authority.0,
-                Some(&authority.0),
-                2,
-            )
-            .unwrap(),
-            vec![&mut mint.1, &mut rent_sysvar.1],
-        )
-        .unwrap();
-        do_process_instruction(
-            initialize_account(&token_program.0, &destination.0, &mint.0, &authority.0).unwrap(),
-            vec![
-                &mut destination.1,
-                &mut mint.1,
-                &mut authority.1,
-                &mut rent_sysvar.1,
-                &mut token_program.1,
-            ],
-        )
-        .unwrap();
-        do_process_instruction(
-            freeze_account(&token_program.0, &destination.0, &mint.0, &authority.0, &[]).unwrap(),
-            vec![
-                &mut destination.1,
-                &mut mint.1,
-                &mut authority.1,
-                &mut token_program.1,
-            ],
-        )
-        .unwrap();
-        let ix = mint_to(
-            &token_program.0,
-            &mint.0,
-            &destination.0,
-            &authority.0,
-            &[],
-            10,
-        )
-        .unwrap();
-        let mint_info = (&mut mint).into();
-        let destination_info = (&mut destination).into();
-        let authority_info = (&mut authority).into();
-        let token_program_info = (&mut token_program).into();
-
-        let err = invoke_signed_wrapper::<TokenError>(
-            &ix,
-            &[
-                mint_info,
-                destination_info,
-                authority_info,
-                token_program_info,
-            ],
-            signers,
-        )
-        .unwrap_err();
-        assert_eq!(err, ProgramError::Custom(TokenError::AccountFrozen as u32));
+    #[derive(Default)]
+    struct SwapTransferFees {
+        pool_token: TransferFee,
+        token_a: TransferFee,
+        token_b: TransferFee,
     }
 
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_initialize(
+    struct SwapAccountInfo {
+        bump_seed: u8,
+        authority_key: Pubkey,
+        fees: Fees,
+        transfer_fees: SwapTransferFees,
+        swap_curve: SwapCurve,
+        swap_key: Pubkey,
+        swap_account: SolanaAccount,
+        pool_mint_key: Pubkey,
+        pool_mint_account: SolanaAccount,
+        pool_fee_key: Pubkey,
+        pool_fee_account: SolanaAccount,
+        pool_token_key: Pubkey,
+        pool_token_account: SolanaAccount,
+        token_a_key: Pubkey,
+        token_a_account: SolanaAccount,
+        token_a_mint_key: Pubkey,
+        token_a_mint_account: SolanaAccount,
+        token_b_key: Pubkey,
+        token_b_account: SolanaAccount,
+        token_b_mint_key: Pubkey,
+        token_b_mint_account: SolanaAccount,
         pool_token_program_id: Pubkey,
         token_a_program_id: Pubkey,
         token_b_program_id: Pubkey,
-    ) {
-        let user_key = Pubkey::new_unique();
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 2;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 10;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 5;
-        let host_fee_numerator = 20;
-        let host_fee_denominator = 100;
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
-
-        let token_a_amount = 1000;
-        let token_b_amount = 2000;
-        let pool_token_amount = 10;
-        let curve_type = CurveType::ConstantProduct;
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator: Arc::new(ConstantProductCurve {}),
-        };
-
-        let mut accounts = SwapAccountInfo::new(
-            &user_key,
-            fees,
-            SwapTransferFees::default(),
-            swap_curve,
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
-        );
-
-        // uninitialized token a account
-        {
-            let old_account = accounts.token_a_account;
-            accounts.token_a_account = SolanaAccount::new(0, 0, &token_a_program_id);
-            assert_eq!(
-                Err(SwapError::ExpectedAccount.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_a_account = old_account;
-        }
+    }
 
-        // uninitialized token b account
-        {
-            let old_account = accounts.token_b_account;
-            accounts.token_b_account = SolanaAccount::new(0, 0, &token_b_program_id);
-            assert_eq!(
-                Err(SwapError::ExpectedAccount.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_b_account = old_account;
-        }
+    impl SwapAccountInfo {
+        #[allow(clippy::too_many_arguments)]
+        pub fn new(
+            user_key: &Pubkey,
+            fees: Fees,
+            transfer_fees: SwapTransferFees,
+            swap_curve: SwapCurve,
+            token_a_amount: u64,
+            token_b_amount: u64,
+            pool_token_program_id: &Pubkey,
+            token_a_program_id: &Pubkey,
+            token_b_program_id: &Pubkey,
+        ) -> Self {
+            let swap_key = Pubkey::new_unique();
+            let swap_account = SolanaAccount::new(0, SwapVersion::LATEST_LEN, &SWAP_PROGRAM_ID);
+            let (authority_key, bump_seed) =
+                Pubkey::find_program_address(&[&swap_key.to_bytes()[..]], &SWAP_PROGRAM_ID);
 
-        // uninitialized pool mint
-        {
-            let old_account = accounts.pool_mint_account;
-            accounts.pool_mint_account = SolanaAccount::new(0, 0, &pool_token_program_id);
-            assert_eq!(
-                Err(SwapError::ExpectedMint.into()),
-                accounts.initialize_swap()
+            let (pool_mint_key, mut pool_mint_account) = create_mint(
+                pool_token_program_id,
+                &authority_key,
+                None,
+                None,
+                &transfer_fees.pool_token,
             );
-            accounts.pool_mint_account = old_account;
-        }
-
-        // token A account owner is not swap authority
-        {
-            let (_token_a_key, token_a_account) = mint_token(
-                &token_a_program_id,
-                &accounts.token_a_mint_key,
-                &mut accounts.token_a_mint_account,
-                &user_key,
-                &user_key,
+            let (pool_token_key, pool_token_account) = mint_token(
+                pool_token_program_id,
+                &pool_mint_key,
+                &mut pool_mint_account,
+                &authority_key,
+                user_key,
                 0,
             );
-            let old_account = accounts.token_a_account;
-            accounts.token_a_account = token_a_account;
-            assert_eq!(
-                Err(SwapError::InvalidOwner.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_a_account = old_account;
-        }
-
-        // token B account owner is not swap authority
-        {
-            let (_token_b_key, token_b_account) = mint_token(
-                &token_b_program_id,
-                &accounts.token_b_mint_key,
-                &mut accounts.token_b_mint_account,
-                &user_key,
-                &user_key,
+            let (pool_fee_key, pool_fee_account) = mint_token(
+                pool_token_program_id,
+                &pool_mint_key,
+                &mut pool_mint_account,
+                &authority_key,
+                user_key,
                 0,
             );
-            let old_account = accounts.token_b_account;
-            accounts.token_b_account = token_b_account;
-            assert_eq!(
-                Err(SwapError::InvalidOwner.into()),
-                accounts.initialize_swap()
+            let (token_a_mint_key, mut token_a_mint_account) = create_mint(
+                token_a_program_id,
+                user_key,
+                None,
+                None,
+                &transfer_fees.token_a,
             );
-            accounts.token_b_account = old_account;
-        }
-
-        // pool token account owner is swap authority
-        {
-            let (_pool_token_key, pool_token_account) = mint_token(
-                &pool_token_program_id,
-                &accounts.pool_mint_key,
-                &mut accounts.pool_mint_account,
-                &accounts.authority_key,
-                &accounts.authority_key,
-                0,
-            );
-            let old_account = accounts.pool_token_account;
-            accounts.pool_token_account = pool_token_account;
-            assert_eq!(
-                Err(SwapError::InvalidOutputOwner.into()),
-                accounts.initialize_swap()
-            );
-            accounts.pool_token_account = old_account;
-        }
-
-        // pool fee account owner is swap authority
-        {
-            let (_pool_fee_key, pool_fee_account) = mint_token(
-                &pool_token_program_id,
-                &accounts.pool_mint_key,
-                &mut accounts.pool_mint_account,
-                &accounts.authority_key,
-                &accounts.authority_key,
-                0,
-            );
-            let old_account = accounts.pool_fee_account;
-            accounts.pool_fee_account = pool_fee_account;
-            assert_eq!(
-                Err(SwapError::InvalidOutputOwner.into()),
-                accounts.initialize_swap()
+            let (token_a_key, token_a_account) = mint_token(
+                token_a_program_id,
+                &token_a_mint_key,
+                &mut token_a_mint_account,
+                user_key,
+                &authority_key,
+                token_a_amount,
             );
-            accounts.pool_fee_account = old_account;
-        }
-
-        // pool mint authority is not swap authority
-        {
-            let (_pool_mint_key, pool_mint_account) = create_mint(
-                &pool_token_program_id,
-                &user_key,
+            let (token_b_mint_key, mut token_b_mint_account) = create_mint(
+                token_b_program_id,
+                user_key,
                 None,
                 None,
-                &TransferFee::default(),
+                &transfer_fees.token_b,
             );
-            let old_mint = accounts.pool_mint_account;
-            accounts.pool_mint_account = pool_mint_account;
-            assert_eq!(
-                Err(SwapError::InvalidOwner.into()),
-                accounts.initialize_swap()
+            let (token_b_key, token_b_account) = mint_token(
+                token_b_program_id,
+                &token_b_mint_key,
+                &mut token_b_mint_account,
+                user_key,
+                &authority_key,
+                token_b_amount,
             );
-            accounts.pool_mint_account = old_mint;
+
+            SwapAccountInfo {
+                bump_seed,
+                authority_key,
+                fees,
+                transfer_fees,
+                swap_curve,
+                swap_key,
+                swap_account,
+                pool_mint_key,
+                pool_mint_account,
+                pool_fee_key,
+                pool_fee_account,
+                pool_token_key,
+                pool_token_account,
+                token_a_key,
+                token_a_account,
+                token_a_mint_key,
+                token_a_mint_account,
+                token_b_key,
+                token_b_account,
+                token_b_mint_key,
+                token_b_mint_account,
+                pool_token_program_id: *pool_token_program_id,
+                token_a_program_id: *token_a_program_id,
+                token_b_program_id: *token_b_program_id,
+            }
         }
 
-        // pool mint token has freeze authority
-        {
-            let (_pool_mint_key, pool_mint_account) = create_mint(
-                &pool_token_program_id,
-                &accounts.authority_key,
-                Some(&user_key),
-                None,
-                &TransferFee::default(),
-            );
-            let old_mint = accounts.pool_mint_account;
-            accounts.pool_mint_account = pool_mint_account;
-            assert_eq!(
-                Err(SwapError::InvalidFreezeAuthority.into()),
-                accounts.initialize_swap()
-            );
-            accounts.pool_mint_account = old_mint;
+        pub fn initialize_swap(&mut self) -> ProgramResult {
+            self.initialize_swap_with_admin(None)
         }
 
-        // pool mint token has close authority, only available in token-2022
-        if pool_token_program_id == spl_token_2022::id() {
-            let (_pool_mint_key, pool_mint_account) = create_mint(
-                &pool_token_program_id,
-                &accounts.authority_key,
-                None,
-                Some(&user_key),
-                &TransferFee::default(),
-            );
-            let old_mint = accounts.pool_mint_account;
-            accounts.pool_mint_account = pool_mint_account;
-            assert_eq!(
-                Err(SwapError::InvalidCloseAuthority.into()),
-                accounts.initialize_swap()
-            );
-            accounts.pool_mint_account = old_mint;
+        pub fn initialize_swap_with_admin(
+            &mut self,
+            admin_authority_key: Option<&Pubkey>,
+        ) -> ProgramResult {
+            let mut authority_account = SolanaAccount::default();
+            let mut token_program_account = SolanaAccount::default();
+            let mut admin_authority_account = SolanaAccount::default();
+            let mut accounts = vec![
+                &mut self.swap_account,
+                &mut authority_account,
+                &mut self.token_a_account,
+                &mut self.token_b_account,
+                &mut self.pool_mint_account,
+                &mut self.pool_fee_account,
+                &mut self.pool_token_account,
+                &mut token_program_account,
+            ];
+            if admin_authority_key.is_some() {
+                accounts.push(&mut admin_authority_account);
+            }
+            do_process_instruction(
+                initialize(
+                    &SWAP_PROGRAM_ID,
+                    &self.pool_token_program_id,
+                    &self.swap_key,
+                    &self.authority_key,
+                    &self.token_a_key,
+                    &self.token_b_key,
+                    &self.pool_mint_key,
+                    &self.pool_fee_key,
+                    &self.pool_token_key,
+                    admin_authority_key,
+                    self.fees.clone(),
+                    self.swap_curve.clone(),
+                )
+                .unwrap(),
+                accounts,
+            )
         }
 
-        // token A account owned by wrong program
-        {
-            let (_token_a_key, mut token_a_account) = mint_token(
-                &token_a_program_id,
-                &accounts.token_a_mint_key,
-                &mut accounts.token_a_mint_account,
-                &user_key,
-                &accounts.authority_key,
-                token_a_amount,
+        pub fn setup_token_accounts(
+            &mut self,
+            mint_owner: &Pubkey,
+            account_owner: &Pubkey,
+            a_amount: u64,
+            b_amount: u64,
+            pool_amount: u64,
+        ) -> (
+            Pubkey,
+            SolanaAccount,
+            Pubkey,
+            SolanaAccount,
+            Pubkey,
+            SolanaAccount,
+        ) {
+            let (token_a_key, token_a_account) = mint_token(
+                &self.token_a_program_id,
+                &self.token_a_mint_key,
+                &mut self.token_a_mint_account,
+                mint_owner,
+                account_owner,
+                a_amount,
             );
-            token_a_account.owner = SWAP_PROGRAM_ID;
-            let old_account = accounts.token_a_account;
-            accounts.token_a_account = token_a_account;
-            assert_eq!(
-                Err(SwapError::IncorrectTokenProgramId.into()),
-                accounts.initialize_swap()
+            let (token_b_key, token_b_account) = mint_token(
+                &self.token_b_program_id,
+                &self.token_b_mint_key,
+                &mut self.token_b_mint_account,
+                mint_owner,
+                account_owner,
+                b_amount,
             );
-            accounts.token_a_account = old_account;
+            let (pool_key, pool_account) = mint_token(
+                &self.pool_token_program_id,
+                &self.pool_mint_key,
+                &mut self.pool_mint_account,
+                &self.authority_key,
+                account_owner,
+                pool_amount,
+            );
+            (
+                token_a_key,
+                token_a_account,
+                token_b_key,
+                token_b_account,
+                pool_key,
+                pool_account,
+            )
         }
 
-        // token B account owned by wrong program
-        {
-            let (_token_b_key, mut token_b_account) = mint_token(
-                &token_b_program_id,
-                &accounts.token_b_mint_key,
-                &mut accounts.token_b_mint_account,
-                &user_key,
-                &accounts.authority_key,
-                token_b_amount,
-            );
-            token_b_account.owner = SWAP_PROGRAM_ID;
-            let old_account = accounts.token_b_account;
-            accounts.token_b_account = token_b_account;
-            assert_eq!(
-                Err(SwapError::IncorrectTokenProgramId.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_b_account = old_account;
+        fn get_swap_key(&self, mint_key: &Pubkey) -> &Pubkey {
+            if *mint_key == self.token_a_mint_key {
+                &self.token_a_key
+            } else if *mint_key == self.token_b_mint_key {
+                &self.token_b_key
+            } else {
+                panic!("Could not find matching swap token account");
+            }
         }
 
-        // empty token A account
-        {
-            let (_token_a_key, token_a_account) = mint_token(
-                &token_a_program_id,
-                &accounts.token_a_mint_key,
-                &mut accounts.token_a_mint_account,
-                &user_key,
-                &accounts.authority_key,
-                0,
-            );
-            let old_account = accounts.token_a_account;
-            accounts.token_a_account = token_a_account;
-            assert_eq!(
-                Err(SwapError::EmptySupply.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_a_account = old_account;
+        fn get_token_program_id(&self, account_key: &Pubkey) -> &Pubkey {
+            if *account_key == self.token_a_key {
+                &self.token_a_program_id
+            } else if *account_key == self.token_b_key {
+                &self.token_b_program_id
+            } else {
+                panic!("Could not find matching swap token account");
+            }
         }
 
-        // empty token B account
-        {
-            let (_token_b_key, token_b_account) = mint_token(
-                &token_b_program_id,
-                &accounts.token_b_mint_key,
-                &mut accounts.token_b_mint_account,
-                &user_key,
-                &accounts.authority_key,
-                0,
-            );
-            let old_account = accounts.token_b_account;
-            accounts.token_b_account = token_b_account;
-            assert_eq!(
-                Err(SwapError::EmptySupply.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_b_account = old_account;
+        fn get_token_mint(&self, account_key: &Pubkey) -> (Pubkey, SolanaAccount) {
+            if *account_key == self.token_a_key {
+                (self.token_a_mint_key, self.token_a_mint_account.clone())
+            } else if *account_key == self.token_b_key {
+                (self.token_b_mint_key, self.token_b_mint_account.clone())
+            } else {
+                panic!("Could not find matching swap token account");
+            }
         }
 
-        // invalid pool tokens
-        {
-            let old_mint = accounts.pool_mint_account;
-            let old_pool_account = accounts.pool_token_account;
-
-            let (_pool_mint_key, pool_mint_account) = create_mint(
-                &pool_token_program_id,
-                &accounts.authority_key,
-                None,
-                None,
-                &TransferFee::default(),
-            );
-            accounts.pool_mint_account = pool_mint_account;
-
-            let (_empty_pool_token_key, empty_pool_token_account) = mint_token(
-                &pool_token_program_id,
-                &accounts.pool_mint_key,
-                &mut accounts.pool_mint_account,
-                &accounts.authority_key,
-                &user_key,
-                0,
-            );
-
-            let (_pool_token_key, pool_token_account) = mint_token(
-                &pool_token_program_id,
-                &accounts.pool_mint_key,
-                &mut accounts.pool_mint_account,
-                &accounts.authority_key,
-                &user_key,
-                pool_token_amount,
-            );
-
-            // non-empty pool token account
-            accounts.pool_token_account = pool_token_account;
-            assert_eq!(
-                Err(SwapError::InvalidSupply.into()),
-                accounts.initialize_swap()
-            );
-
-            // pool tokens already in circulation
-            accounts.pool_token_account = empty_pool_token_account;
-            assert_eq!(
-                Err(SwapError::InvalidSupply.into()),
-                accounts.initialize_swap()
-            );
-
-            accounts.pool_mint_account = old_mint;
-            accounts.pool_token_account = old_pool_account;
+        fn get_token_account(&self, account_key: &Pubkey) -> &SolanaAccount {
+            if *account_key == self.token_a_key {
+                &self.token_a_account
+            } else if *account_key == self.token_b_key {
+                &self.token_b_account
+            } else {
+                panic!("Could not find matching swap token account");
+            }
         }
 
-        // pool fee account has wrong mint
-        {
-            let (_pool_fee_key, pool_fee_account) = mint_token(
-                &token_a_program_id,
-                &accounts.token_a_mint_key,
-                &mut accounts.token_a_mint_account,
-                &user_key,
-                &user_key,
-                0,
-            );
-            let old_account = accounts.pool_fee_account;
-            accounts.pool_fee_account = pool_fee_account;
-            assert_eq!(
-                Err(SwapError::IncorrectPoolMint.into()),
-                accounts.initialize_swap()
-            );
-            accounts.pool_fee_account = old_account;
+        fn set_token_account(&mut self, account_key: &Pubkey, account: SolanaAccount) {
+            if *account_key == self.token_a_key {
+                self.token_a_account = account;
+                return;
+            } else if *account_key == self.token_b_key {
+                self.token_b_account = account;
+                return;
+            }
+            panic!("Could not find matching swap token account");
         }
 
-        // token A account is delegated
-        {
+        #[allow(clippy::too_many_arguments)]
+        pub fn swap(
+            &mut self,
+            user_key: &Pubkey,
+            user_source_key: &Pubkey,
+            user_source_account: &mut SolanaAccount,
+            swap_source_key: &Pubkey,
+            swap_destination_key: &Pubkey,
+            user_destination_key: &Pubkey,
+            user_destination_account: &mut SolanaAccount,
+            amount_in: u64,
+            minimum_amount_out: u64,
+        ) -> ProgramResult {
+            let user_transfer_key = Pubkey::new_unique();
+            let source_token_program_id = self.get_token_program_id(swap_source_key);
+            let destination_token_program_id = self.get_token_program_id(swap_destination_key);
+            // approve moving from user source account
             do_process_instruction(
                 approve(
-                    &token_a_program_id,
-                    &accounts.token_a_key,
-                    &user_key,
-                    &accounts.authority_key,
+                    source_token_program_id,
+                    user_source_key,
+                    &user_transfer_key,
+                    user_key,
                     &[],
-                    1,
+                    amount_in,
                 )
                 .unwrap(),
                 vec![
-                    &mut accounts.token_a_account,
+                    user_source_account,
                     &mut SolanaAccount::default(),
                     &mut SolanaAccount::default(),
                 ],
             )
             .unwrap();
-            assert_eq!(
-                Err(SwapError::InvalidDelegate.into()),
-                accounts.initialize_swap()
-            );
 
+            let (source_mint_key, mut source_mint_account) = self.get_token_mint(swap_source_key);
+            let (destination_mint_key, mut destination_mint_account) =
+                self.get_token_mint(swap_destination_key);
+            let mut swap_source_account = self.get_token_account(swap_source_key).clone();
+            let mut swap_destination_account = self.get_token_account(swap_destination_key).clone();
+
+            // perform the swap
             do_process_instruction(
-                revoke(
-                    &token_a_program_id,
-                    &accounts.token_a_key,
-                    &accounts.authority_key,
-                    &[],
+                swap(
+                    &SWAP_PROGRAM_ID,
+                    source_token_program_id,
+                    destination_token_program_id,
+                    &self.pool_token_program_id,
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_key,
+                    user_source_key,
+                    swap_source_key,
+                    swap_destination_key,
+                    user_destination_key,
+                    &self.pool_mint_key,
+                    &self.pool_fee_key,
+                    &source_mint_key,
+                    &destination_mint_key,
+                    None,
+                    Swap {
+                        amount_in,
+                        minimum_amount_out,
+                    },
                 )
                 .unwrap(),
-                vec![&mut accounts.token_a_account, &mut SolanaAccount::default()],
-            )
-            .unwrap();
+                vec![
+                    &mut self.swap_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    user_source_account,
+                    &mut swap_source_account,
+                    &mut swap_destination_account,
+                    user_destination_account,
+                    &mut self.pool_mint_account,
+                    &mut self.pool_fee_account,
+                    &mut source_mint_account,
+                    &mut destination_mint_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
+            )?;
+
+            self.set_token_account(swap_source_key, swap_source_account);
+            self.set_token_account(swap_destination_key, swap_destination_account);
+
+            Ok(())
         }
 
-        // token B account is delegated
-        {
+        #[allow(clippy::too_many_arguments)]
+        pub fn deposit_all_token_types(
+            &mut self,
+            depositor_key: &Pubkey,
+            depositor_token_a_key: &Pubkey,
+            depositor_token_a_account: &mut SolanaAccount,
+            depositor_token_b_key: &Pubkey,
+            depositor_token_b_account: &mut SolanaAccount,
+            depositor_pool_key: &Pubkey,
+            depositor_pool_account: &mut SolanaAccount,
+            pool_token_amount: u64,
+            maximum_token_a_amount: u64,
+            maximum_token_b_amount: u64,
+        ) -> ProgramResult {
+            let user_transfer_authority = Pubkey::new_unique();
+            let token_a_program_id = depositor_token_a_account.owner;
             do_process_instruction(
                 approve(
-                    &token_b_program_id,
-                    &accounts.token_b_key,
-                    &user_key,
-                    &accounts.authority_key,
+                    &token_a_program_id,
+                    depositor_token_a_key,
+                    &user_transfer_authority,
+                    depositor_key,
                     &[],
-                    1,
+                    maximum_token_a_amount,
                 )
                 .unwrap(),
                 vec![
-                    &mut accounts.token_b_account,
+                    depositor_token_a_account,
                     &mut SolanaAccount::default(),
                     &mut SolanaAccount::default(),
                 ],
             )
             .unwrap();
-            assert_eq!(
-                Err(SwapError::InvalidDelegate.into()),
-                accounts.initialize_swap()
-            );
 
+            let token_b_program_id = depositor_token_b_account.owner;
             do_process_instruction(
-                revoke(
+                approve(
                     &token_b_program_id,
-                    &accounts.token_b_key,
-                    &accounts.authority_key,
+                    depositor_token_b_key,
+                    &user_transfer_authority,
+                    depositor_key,
                     &[],
+                    maximum_token_b_amount,
                 )
                 .unwrap(),
-                vec![&mut accounts.token_b_account, &mut SolanaAccount::default()],
+                vec![
+                    depositor_token_b_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
             )
             .unwrap();
-        }
 
-        // token A account has close authority
-        {
+            let pool_token_program_id = depositor_pool_account.owner;
             do_process_instruction(
-                set_authority(
+                deposit_all_token_types(
+                    &SWAP_PROGRAM_ID,
                     &token_a_program_id,
-                    &accounts.token_a_key,
-                    Some(&user_key),
-                    AuthorityType::CloseAccount,
-                    &accounts.authority_key,
-                    &[],
+                    &token_b_program_id,
+                    &pool_token_program_id,
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_authority,
+                    depositor_token_a_key,
+                    depositor_token_b_key,
+                    &self.token_a_key,
+                    &self.token_b_key,
+                    &self.pool_mint_key,
+                    depositor_pool_key,
+                    &self.token_a_mint_key,
+                    &self.token_b_mint_key,
+                    DepositAllTokenTypes {
+                        pool_token_amount,
+                        maximum_token_a_amount,
+                        maximum_token_b_amount,
+                    },
                 )
                 .unwrap(),
-                vec![&mut accounts.token_a_account, &mut SolanaAccount::default()],
+                vec![
+                    &mut self.swap_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    depositor_token_a_account,
+                    depositor_token_b_account,
+                    &mut self.token_a_account,
+                    &mut self.token_b_account,
+                    &mut self.pool_mint_account,
+                    depositor_pool_account,
+                    &mut self.token_a_mint_account,
+                    &mut self.token_b_mint_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
             )
-            .unwrap();
-            assert_eq!(
-                Err(SwapError::InvalidCloseAuthority.into()),
-                accounts.initialize_swap()
-            );
+        }
 
+        #[allow(clippy::too_many_arguments)]
+        pub fn withdraw_all_token_types(
+            &mut self,
+            user_key: &Pubkey,
+            pool_key: &Pubkey,
+            pool_account: &mut SolanaAccount,
+            token_a_key: &Pubkey,
+            token_a_account: &mut SolanaAccount,
+            token_b_key: &Pubkey,
+            token_b_account: &mut SolanaAccount,
+            pool_token_amount: u64,
+            minimum_token_a_amount: u64,
+            minimum_token_b_amount: u64,
+        ) -> ProgramResult {
+            let user_transfer_authority_key = Pubkey::new_unique();
+            let pool_token_program_id = pool_account.owner;
+            // approve user transfer authority to take out pool tokens
             do_process_instruction(
-                set_authority(
-                    &token_a_program_id,
-                    &accounts.token_a_key,
-                    None,
-                    AuthorityType::CloseAccount,
-                    &user_key,
+                approve(
+                    &pool_token_program_id,
+                    pool_key,
+                    &user_transfer_authority_key,
+                    user_key,
                     &[],
+                    pool_token_amount,
                 )
                 .unwrap(),
-                vec![&mut accounts.token_a_account, &mut SolanaAccount::default()],
+                vec![
+                    pool_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
             )
             .unwrap();
-        }
 
-        // token B account has close authority
-        {
+            // withdraw token a and b correctly
+            let token_a_program_id = token_a_account.owner;
+            let token_b_program_id = token_b_account.owner;
             do_process_instruction(
-                set_authority(
+                withdraw_all_token_types(
+                    &SWAP_PROGRAM_ID,
+                    &pool_token_program_id,
+                    &token_a_program_id,
                     &token_b_program_id,
-                    &accounts.token_b_key,
-                    Some(&user_key),
-                    AuthorityType::CloseAccount,
-                    &accounts.authority_key,
-                    &[],
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_authority_key,
+                    &self.pool_mint_key,
+                    &self.pool_fee_key,
+                    pool_key,
+                    &self.token_a_key,
+                    &self.token_b_key,
+                    token_a_key,
+                    token_b_key,
+                    &self.token_a_mint_key,
+                    &self.token_b_mint_key,
+                    WithdrawAllTokenTypes {
+                        pool_token_amount,
+                        minimum_token_a_amount,
+                        minimum_token_b_amount,
+                    },
                 )
                 .unwrap(),
-                vec![&mut accounts.token_b_account, &mut SolanaAccount::default()],
+                vec![
+                    &mut self.swap_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    &mut self.pool_mint_account,
+                    pool_account,
+                    &mut self.token_a_account,
+                    &mut self.token_b_account,
+                    token_a_account,
+                    token_b_account,
+                    &mut self.pool_fee_account,
+                    &mut self.token_a_mint_account,
+                    &mut self.token_b_mint_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
             )
-            .unwrap();
-            assert_eq!(
-                Err(SwapError::InvalidCloseAuthority.into()),
-                accounts.initialize_swap()
-            );
+        }
 
+        #[allow(clippy::too_many_arguments)]
+        pub fn deposit_single_token_type_exact_amount_in(
+            &mut self,
+            depositor_key: &Pubkey,
+            deposit_account_key: &Pubkey,
+            deposit_token_account: &mut SolanaAccount,
+            deposit_pool_key: &Pubkey,
+            deposit_pool_account: &mut SolanaAccount,
+            source_token_amount: u64,
+            minimum_pool_token_amount: u64,
+        ) -> ProgramResult {
+            let user_transfer_authority_key = Pubkey::new_unique();
+            let source_token_program_id = deposit_token_account.owner;
             do_process_instruction(
-                set_authority(
-                    &token_b_program_id,
-                    &accounts.token_b_key,
-                    None,
-                    AuthorityType::CloseAccount,
-                    &user_key,
+                approve(
+                    &source_token_program_id,
+                    deposit_account_key,
+                    &user_transfer_authority_key,
+                    depositor_key,
                     &[],
+                    source_token_amount,
                 )
                 .unwrap(),
-                vec![&mut accounts.token_b_account, &mut SolanaAccount::default()],
+                vec![
+                    deposit_token_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
             )
             .unwrap();
-        }
 
-        // wrong token program id
-        {
-            let wrong_program_id = Pubkey::new_unique();
-            assert_eq!(
-                Err(ProgramError::IncorrectProgramId),
-                do_process_instruction(
-                    initialize(
-                        &SWAP_PROGRAM_ID,
-                        &wrong_program_id,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &accounts.pool_token_key,
-                        accounts.fees.clone(),
-                        accounts.swap_curve.clone(),
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut SolanaAccount::default(),
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
-                        &mut accounts.pool_token_account,
-                        &mut SolanaAccount::default(),
-                    ],
+            let source_mint_key =
+                StateWithExtensions::<Account>::unpack(&deposit_token_account.data)
+                    .unwrap()
+                    .base
+                    .mint;
+            let swap_source_key = self.get_swap_key(&source_mint_key);
+            let (source_mint_key, mut source_mint_account) = self.get_token_mint(swap_source_key);
+
+            let pool_token_program_id = deposit_pool_account.owner;
+            do_process_instruction(
+                deposit_single_token_type_exact_amount_in(
+                    &SWAP_PROGRAM_ID,
+                    &source_token_program_id,
+                    &pool_token_program_id,
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_authority_key,
+                    deposit_account_key,
+                    &self.token_a_key,
+                    &self.token_b_key,
+                    &self.pool_mint_key,
+                    deposit_pool_key,
+                    &source_mint_key,
+                    DepositSingleTokenTypeExactAmountIn {
+                        source_token_amount,
+                        minimum_pool_token_amount,
+                    },
                 )
-            );
+                .unwrap(),
+                vec![
+                    &mut self.swap_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    deposit_token_account,
+                    &mut self.token_a_account,
+                    &mut self.token_b_account,
+                    &mut self.pool_mint_account,
+                    deposit_pool_account,
+                    &mut source_mint_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
+            )
         }
 
-        // create swap with same token A and B
-        {
-            let (_token_a_repeat_key, token_a_repeat_account) = mint_token(
-                &token_a_program_id,
-                &accounts.token_a_mint_key,
-                &mut accounts.token_a_mint_account,
-                &user_key,
-                &accounts.authority_key,
-                10,
-            );
-            let old_account = accounts.token_b_account;
-            accounts.token_b_account = token_a_repeat_account;
-            assert_eq!(
-                Err(SwapError::RepeatedMint.into()),
-                accounts.initialize_swap()
-            );
-            accounts.token_b_account = old_account;
-        }
+        #[allow(clippy::too_many_arguments)]
+        pub fn withdraw_single_token_type_exact_amount_out(
+            &mut self,
+            user_key: &Pubkey,
+            pool_key: &Pubkey,
+            pool_account: &mut SolanaAccount,
+            destination_key: &Pubkey,
+            destination_account: &mut SolanaAccount,
+            destination_token_amount: u64,
+            maximum_pool_token_amount: u64,
+        ) -> ProgramResult {
+            let user_transfer_authority_key = Pubkey::new_unique();
+            let pool_token_program_id = pool_account.owner;
+            // approve user transfer authority to take out pool tokens
+            do_process_instruction(
+                approve(
+                    &pool_token_program_id,
+                    pool_key,
+                    &user_transfer_authority_key,
+                    user_key,
+                    &[],
+                    maximum_pool_token_amount,
+                )
+                .unwrap(),
+                vec![
+                    pool_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
+            )
+            .unwrap();
 
-        // create valid swap
-        accounts.initialize_swap().unwrap();
+            let destination_mint_key =
+                StateWithExtensions::<Account>::unpack(&destination_account.data)
+                    .unwrap()
+                    .base
+                    .mint;
+            let swap_destination_key = self.get_swap_key(&destination_mint_key);
+            let (destination_mint_key, mut destination_mint_account) =
+                self.get_token_mint(swap_destination_key);
 
-        // create invalid flat swap
-        {
-            let token_b_price = 0;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::ConstantPrice,
-                calculator: Arc::new(ConstantPriceCurve { token_b_price }),
-            };
-            let mut accounts = SwapAccountInfo::new(
-                &user_key,
-                fees,
-                SwapTransferFees::default(),
-                swap_curve,
-                token_a_amount,
-                token_b_amount,
-                &pool_token_program_id,
-                &token_a_program_id,
-                &token_b_program_id,
-            );
-            assert_eq!(
-                Err(SwapError::InvalidCurve.into()),
-                accounts.initialize_swap()
-            );
+            let destination_token_program_id = destination_account.owner;
+            do_process_instruction(
+                withdraw_single_token_type_exact_amount_out(
+                    &SWAP_PROGRAM_ID,
+                    &pool_token_program_id,
+                    &destination_token_program_id,
+                    &self.swap_key,
+                    &self.authority_key,
+                    &user_transfer_authority_key,
+                    &self.pool_mint_key,
+                    &self.pool_fee_key,
+                    pool_key,
+                    &self.token_a_key,
+                    &self.token_b_key,
+                    destination_key,
+                    &destination_mint_key,
+                    WithdrawSingleTokenTypeExactAmountOut {
+                        destination_token_amount,
+                        maximum_pool_token_amount,
+                    },
+                )
+                .unwrap(),
+                vec![
+                    &mut self.swap_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    &mut self.pool_mint_account,
+                    pool_account,
+                    &mut self.token_a_account,
+                    &mut self.token_b_account,
+                    destination_account,
+                    &mut self.pool_fee_account,
+                    &mut destination_mint_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
+            )
         }
+    }
 
-        // create valid flat swap
-        {
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let token_b_price = 10_000;
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::ConstantPrice,
-                calculator: Arc::new(ConstantPriceCurve { token_b_price }),
-            };
-            let mut accounts = SwapAccountInfo::new(
-                &user_key,
-                fees,
-                SwapTransferFees::default(),
-                swap_curve,
-                token_a_amount,
-                token_b_amount,
-                &pool_token_program_id,
-                &token_a_program_id,
-                &token_b_program_id,
-            );
-            accounts.initialize_swap().unwrap();
-        }
+    fn mint_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(spl_token::state::Mint::get_packed_len())
+    }
 
-        // create invalid offset swap
-        {
-            let token_b_offset = 0;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::Offset,
-                calculator: Arc::new(OffsetCurve { token_b_offset }),
-            };
-            let mut accounts = SwapAccountInfo::new(
-                &user_key,
-                fees,
-                SwapTransferFees::default(),
-                swap_curve,
-                token_a_amount,
-                token_b_amount,
-                &pool_token_program_id,
-                &token_a_program_id,
-                &token_b_program_id,
-            );
-            assert_eq!(
-                Err(SwapError::InvalidCurve.into()),
-                accounts.initialize_swap()
-            );
-        }
+    fn account_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(spl_token::state::Account::get_packed_len())
+    }
 
-        // create valid offset swap
-        {
-            let token_b_offset = 10;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::Offset,
-                calculator: Arc::new(OffsetCurve { token_b_offset }),
-            };
-            let mut accounts = SwapAccountInfo::new(
-                &user_key,
-                fees,
-                SwapTransferFees::default(),
-                swap_curve,
-                token_a_amount,
-                token_b_amount,
-                &pool_token_program_id,
-                &token_a_program_id,
-                &token_b_program_id,
-            );
-            accounts.initialize_swap().unwrap();
-        }
+    fn do_process_instruction_with_fee_constraints(
+        instruction: Instruction,
+        accounts: Vec<&mut SolanaAccount>,
+        swap_constraints: &Option<SwapConstraints>,
+    ) -> ProgramResult {
+        test_syscall_stubs();
 
-        // wrong owner key in constraint
-        {
-            let new_key = Pubkey::new_unique();
-            let trade_fee_numerator = 25;
-            let trade_fee_denominator = 10000;
-            let owner_trade_fee_numerator = 5;
-            let owner_trade_fee_denominator = 10000;
-            let host_fee_numerator = 20;
-            let host_fee_denominator = 100;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let curve = ConstantProductCurve {};
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::ConstantProduct,
-                calculator: Arc::new(curve),
-            };
-            let owner_key = &new_key.to_string();
-            let valid_curve_types = &[CurveType::ConstantProduct];
-            let constraints = Some(SwapConstraints {
-                owner_key,
-                valid_curve_types,
-                fees: &fees,
-            });
-            let mut accounts = SwapAccountInfo::new(
-                &user_key,
-                fees.clone(),
-                SwapTransferFees::default(),
-                swap_curve,
-                token_a_amount,
-                token_b_amount,
-                &pool_token_program_id,
-                &token_a_program_id,
-                &token_b_program_id,
-            );
-            assert_eq!(
-                Err(SwapError::InvalidOwner.into()),
-                do_process_instruction_with_fee_constraints(
-                    initialize(
-                        &SWAP_PROGRAM_ID,
-                        &pool_token_program_id,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &accounts.pool_token_key,
-                        accounts.fees.clone(),
-                        accounts.swap_curve.clone(),
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut SolanaAccount::default(),
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
-                        &mut accounts.pool_token_account,
-                        &mut SolanaAccount::default(),
-                    ],
-                    &constraints,
-                )
-            );
+        // approximate the logic in the actual runtime which runs the instruction
+        // and only updates accounts if the instruction is successful
+        let mut account_clones = accounts.iter().map(|x| (*x).clone()).collect::<Vec<_>>();
+        let mut meta = instruction
+            .accounts
+            .iter()
+            .zip(account_clones.iter_mut())
+            .map(|(account_meta, account)| (&account_meta.pubkey, account_meta.is_signer, account))
+            .collect::<Vec<_>>();
+        let mut account_infos = create_is_signer_account_infos(&mut meta);
+        let res = if instruction.program_id == SWAP_PROGRAM_ID {
+            Processor::process_with_constraints(
+                &instruction.program_id,
+                &account_infos,
+                &instruction.data,
+                swap_constraints,
+            )
+        } else if instruction.program_id == spl_token::id() {
+            spl_token::processor::Processor::process(
+                &instruction.program_id,
+                &account_infos,
+                &instruction.data,
+            )
+        } else if instruction.program_id == spl_token_2022::id() {
+            spl_token_2022::processor::Processor::process(
+                &instruction.program_id,
+                &account_infos,
+                &instruction.data,
+            )
+        } else {
+            Err(ProgramError::IncorrectProgramId)
+        };
+
+        if res.is_ok() {
+            let mut account_metas = instruction
+                .accounts
+                .iter()
+                .zip(accounts)
+                .map(|(account_meta, account)| (&account_meta.pubkey, account))
+                .collect::<Vec<_>>();
+            for account_info in account_infos.iter_mut() {
+                for account_meta in account_metas.iter_mut() {
+                    if account_info.key == account_meta.0 {
+                        let account = &mut account_meta.1;
+                        account.owner = *account_info.owner;
+                        account.lamports = **account_info.lamports.borrow();
+                        account.data = account_info.data.borrow().to_vec();
+                    }
+                }
+            }
         }
+        res
+    }
 
-        // wrong fee in constraint
-        {
-            let trade_fee_numerator = 25;
-            let trade_fee_denominator = 10000;
-            let owner_trade_fee_numerator = 5;
-            let owner_trade_fee_denominator = 10000;
-            let host_fee_numerator = 20;
-            let host_fee_denominator = 100;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let curve = ConstantProductCurve {};
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::ConstantProduct,
-                calculator: Arc::new(curve),
-            };
-            let owner_key = &user_key.to_string();
-            let valid_curve_types = &[CurveType::ConstantProduct];
-            let constraints = Some(SwapConstraints {
-                owner_key,
-                valid_curve_types,
-                fees: &fees,
-            });
-            let mut bad_fees = fees.clone();
-            bad_fees.trade_fee_numerator = trade_fee_numerator - 1;
-            let mut accounts = SwapAccountInfo::new(
-                &user_key,
-                bad_fees,
-                SwapTransferFees::default(),
-                swap_curve,
-                token_a_amount,
-                token_b_amount,
-                &pool_token_program_id,
-                &token_a_program_id,
-                &token_b_program_id,
+    fn do_process_instruction(
+        instruction: Instruction,
+        accounts: Vec<&mut SolanaAccount>,
+    ) -> ProgramResult {
+        do_process_instruction_with_fee_constraints(instruction, accounts, &SWAP_CONSTRAINTS)
+    }
+
+    fn mint_token(
+        program_id: &Pubkey,
+        mint_key: &Pubkey,
+        mint_account: &mut SolanaAccount,
+        mint_authority_key: &Pubkey,
+        account_owner_key: &Pubkey,
+        amount: u64,
+    ) -> (Pubkey, SolanaAccount) {
+        let account_key = Pubkey::new_unique();
+        let space = if *program_id == spl_token_2022::id() {
+            let mint = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+            let mut extension_types = ExtensionType::get_required_init_account_extensions(
+                &mint.get_extension_types().unwrap(),
             );
-            assert_eq!(
-                Err(SwapError::InvalidFee.into()),
-                do_process_instruction_with_fee_constraints(
-                    initialize(
-                        &SWAP_PROGRAM_ID,
-                        &pool_token_program_id,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &accounts.pool_token_key,
-                        accounts.fees.clone(),
-                        accounts.swap_curve.clone(),
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut SolanaAccount::default(),
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
-                        &mut accounts.pool_token_account,
-                        &mut SolanaAccount::default(),
-                    ],
-                    &constraints,
+            extension_types.push(ExtensionType::ImmutableOwner);
+            ExtensionType::try_calculate_account_len::<Account>(&extension_types).unwrap()
+        } else {
+            Account::get_packed_len()
+        };
+        let minimum_balance = Rent::default().minimum_balance(space);
+        let mut account_account = SolanaAccount::new(minimum_balance, space, program_id);
+        let mut mint_authority_account = SolanaAccount::default();
+        let mut rent_sysvar_account = create_account_for_test(&Rent::free());
+
+        // no-ops in normal token, so we're good to run it either way
+        do_process_instruction(
+            initialize_immutable_owner(program_id, &account_key).unwrap(),
+            vec![&mut account_account],
+        )
+        .unwrap();
+
+        do_process_instruction(
+            initialize_account(program_id, &account_key, mint_key, account_owner_key).unwrap(),
+            vec![
+                &mut account_account,
+                mint_account,
+                &mut mint_authority_account,
+                &mut rent_sysvar_account,
+            ],
+        )
+        .unwrap();
+
+        if amount > 0 {
+            do_process_instruction(
+                mint_to(
+                    program_id,
+                    mint_key,
+                    &account_key,
+                    mint_authority_key,
+                    &[],
+                    amount,
                 )
-            );
+                .unwrap(),
+                vec![
+                    mint_account,
+                    &mut account_account,
+                    &mut mint_authority_account,
+                ],
+            )
+            .unwrap();
         }
 
-        // create valid swap with constraints
-        {
-            let trade_fee_numerator = 25;
-            let trade_fee_denominator = 10000;
-            let owner_trade_fee_numerator = 5;
-            let owner_trade_fee_denominator = 10000;
-            let host_fee_numerator = 20;
-            let host_fee_denominator = 100;
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let curve = ConstantProductCurve {};
-            let swap_curve = SwapCurve {
-                curve_type: CurveType::ConstantProduct,
-                calculator: Arc::new(curve),
-            };
-            let owner_key = &user_key.to_string();
-            let valid_curve_types = &[CurveType::ConstantProduct];
-            let constraints = Some(SwapConstraints {
-                owner_key,
-                valid_curve_types,
-                fees: &fees,
-            });
-            let mut accounts = SwapAccountInfo::new(
-                &user_key,
-                fees.clone(),
-                SwapTransferFees::default(),
-                swap_curve,
-                token_a_amount,
-                token_b_amount,
-                &pool_token_program_id,
-                &token_a_program_id,
-                &token_b_program_id,
-            );
-            do_process_instruction_with_fee_constraints(
-                initialize(
-                    &SWAP_PROGRAM_ID,
-                    &pool_token_program_id,
-                    &accounts.swap_key,
-                    &accounts.authority_key,
-                    &accounts.token_a_key,
-                    &accounts.token_b_key,
-                    &accounts.pool_mint_key,
-                    &accounts.pool_fee_key,
-                    &accounts.pool_token_key,
-                    accounts.fees,
-                    accounts.swap_curve.clone(),
+        (account_key, account_account)
+    }
+
+    fn create_mint(
+        program_id: &Pubkey,
+        authority_key: &Pubkey,
+        freeze_authority: Option<&Pubkey>,
+        close_authority: Option<&Pubkey>,
+        fees: &TransferFee,
+    ) -> (Pubkey, SolanaAccount) {
+        let mint_key = Pubkey::new_unique();
+        let space = if *program_id == spl_token_2022::id() {
+            if close_authority.is_some() {
+                ExtensionType::try_calculate_account_len::<Mint>(&[
+                    ExtensionType::MintCloseAuthority,
+                    ExtensionType::TransferFeeConfig,
+                ])
+                .unwrap()
+            } else {
+                ExtensionType::try_calculate_account_len::<Mint>(&[
+                    ExtensionType::TransferFeeConfig,
+                ])
+                .unwrap()
+            }
+        } else {
+            Mint::get_packed_len()
+        };
+        let minimum_balance = Rent::default().minimum_balance(space);
+        let mut mint_account = SolanaAccount::new(minimum_balance, space, program_id);
+        let mut rent_sysvar_account = create_account_for_test(&Rent::free());
+
+        if *program_id == spl_token_2022::id() {
+            if close_authority.is_some() {
+                do_process_instruction(
+                    initialize_mint_close_authority(program_id, &mint_key, close_authority)
+                        .unwrap(),
+                    vec![&mut mint_account],
+                )
+                .unwrap();
+            }
+            do_process_instruction(
+                initialize_transfer_fee_config(
+                    program_id,
+                    &mint_key,
+                    freeze_authority,
+                    freeze_authority,
+                    fees.transfer_fee_basis_points.into(),
+                    fees.maximum_fee.into(),
                 )
                 .unwrap(),
-                vec![
-                    &mut accounts.swap_account,
-                    &mut SolanaAccount::default(),
-                    &mut accounts.token_a_account,
-                    &mut accounts.token_b_account,
-                    &mut accounts.pool_mint_account,
-                    &mut accounts.pool_fee_account,
-                    &mut accounts.pool_token_account,
-                    &mut SolanaAccount::default(),
-                ],
-                &constraints,
+                vec![&mut mint_account],
             )
             .unwrap();
         }
+        do_process_instruction(
+            initialize_mint(program_id, &mint_key, authority_key, freeze_authority, 2).unwrap(),
+            vec![&mut mint_account, &mut rent_sysvar_account],
+        )
+        .unwrap();
 
-        // create again
-        {
-            assert_eq!(
-                Err(SwapError::AlreadyInUse.into()),
-                accounts.initialize_swap()
-            );
-        }
-        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
-        assert!(swap_state.is_initialized());
-        assert_eq!(swap_state.bump_seed(), accounts.bump_seed);
-        assert_eq!(
-            swap_state.swap_curve().curve_type,
-            accounts.swap_curve.curve_type
-        );
-        assert_eq!(*swap_state.token_a_account(), accounts.token_a_key);
-        assert_eq!(*swap_state.token_b_account(), accounts.token_b_key);
-        assert_eq!(*swap_state.pool_mint(), accounts.pool_mint_key);
-        assert_eq!(*swap_state.token_a_mint(), accounts.token_a_mint_key);
-        assert_eq!(*swap_state.token_b_mint(), accounts.token_b_mint_key);
-        assert_eq!(*swap_state.pool_fee_account(), accounts.pool_fee_key);
-        let token_a =
-            StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
-        assert_eq!(token_a.base.amount, token_a_amount);
-        let token_b =
-            StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
-        assert_eq!(token_b.base.amount, token_b_amount);
-        let pool_account =
-            StateWithExtensions::<Account>::unpack(&accounts.pool_token_account.data).unwrap();
-        let pool_mint =
-            StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
-        assert_eq!(pool_mint.base.supply, pool_account.base.amount);
+        (mint_key, mint_account)
     }
 
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_deposit(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
-        let user_key = Pubkey::new_unique();
-        let depositor_key = Pubkey::new_unique();
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 2;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 10;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 5;
-        let host_fee_numerator = 20;
-        let host_fee_denominator = 100;
-
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
+    fn create_mint_with_transfer_hook(
+        program_id: &Pubkey,
+        authority_key: &Pubkey,
+        extra_account_key: &Pubkey,
+    ) -> (Pubkey, SolanaAccount, Pubkey, SolanaAccount) {
+        let mint_key = Pubkey::new_unique();
+        let space =
+            ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferHook])
+                .unwrap();
+        let minimum_balance = Rent::default().minimum_balance(space);
+        let mut mint_account = SolanaAccount::new(minimum_balance, space, program_id);
+        let mut rent_sysvar_account = create_account_for_test(&Rent::free());
 
-        let token_a_amount = 1000;
-        let token_b_amount = 9000;
-        let curve_type = CurveType::ConstantProduct;
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator: Arc::new(ConstantProductCurve {}),
-        };
+        do_process_instruction(
+            transfer_hook::instruction::initialize(
+                program_id,
+                &mint_key,
+                None,
+                Some(TRANSFER_HOOK_PROGRAM_ID),
+            )
+            .unwrap(),
+            vec![&mut mint_account],
+        )
+        .unwrap();
+        do_process_instruction(
+            initialize_mint(program_id, &mint_key, authority_key, None, 2).unwrap(),
+            vec![&mut mint_account, &mut rent_sysvar_account],
+        )
+        .unwrap();
 
-        let mut accounts = SwapAccountInfo::new(
-            &user_key,
-            fees,
-            SwapTransferFees::default(),
-            swap_curve,
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
+        // the hook requires one extra account on every transfer
+        let validation_key = get_extra_account_metas_address(&mint_key, &TRANSFER_HOOK_PROGRAM_ID);
+        let extra_account_metas =
+            [ExtraAccountMeta::new_with_pubkey(extra_account_key, false, false).unwrap()];
+        let mut validation_account = SolanaAccount::new(
+            0,
+            ExtraAccountMetaList::size_of(extra_account_metas.len()).unwrap(),
+            &TRANSFER_HOOK_PROGRAM_ID,
         );
+        ExtraAccountMetaList::init::<ExecuteInstruction>(
+            &mut validation_account.data,
+            &extra_account_metas,
+        )
+        .unwrap();
 
-        // depositing 10% of the current pool amount in token A and B means
-        // that our pool tokens will be worth 1 / 10 of the current pool amount
-        let pool_amount = INITIAL_SWAP_POOL_AMOUNT / 10;
-        let deposit_a = token_a_amount / 10;
-        let deposit_b = token_b_amount / 10;
+        (mint_key, mint_account, validation_key, validation_account)
+    }
 
-        // swap not initialized
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            assert_eq!(
-                Err(ProgramError::UninitializedAccount),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
-            );
-        }
+    #[test_case(spl_token::id(); "token")]
+    #[test_case(spl_token_2022::id(); "token-2022")]
+    fn test_token_program_id_error(token_program_id: Pubkey) {
+        test_syscall_stubs();
+        let swap_key = Pubkey::new_unique();
+        let mut mint = (Pubkey::new_unique(), SolanaAccount::default());
+        let mut destination = (Pubkey::new_unique(), SolanaAccount::default());
+        let token_program = (token_program_id, SolanaAccount::default());
+        let (authority_key, bump_seed) =
+            Pubkey::find_program_address(&[&swap_key.to_bytes()[..]], &SWAP_PROGRAM_ID);
+        let mut authority = (authority_key, SolanaAccount::default());
+        let swap_bytes = swap_key.to_bytes();
+        let authority_signature_seeds = [&swap_bytes[..32], &[bump_seed]];
+        let signers = &[&authority_signature_seeds[..]];
+        let ix = mint_to(
+            &token_program.0,
+            &mint.0,
+            &destination.0,
+            &authority.0,
+            &[],
+            10,
+        )
+        .unwrap();
+        let mint = (&mut mint).into();
+        let destination = (&mut destination).into();
+        let authority = (&mut authority).into();
 
-        accounts.initialize_swap().unwrap();
+        let err = invoke_signed(&ix, &[mint, destination, authority], signers).unwrap_err();
+        assert_eq!(err, ProgramError::InvalidAccountData);
+    }
 
-        // wrong owner for swap account
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let old_swap_account = accounts.swap_account;
-            let mut wrong_swap_account = old_swap_account.clone();
-            wrong_swap_account.owner = pool_token_program_id;
-            accounts.swap_account = wrong_swap_account;
+    #[test_case(spl_token::id(); "token")]
+    #[test_case(spl_token_2022::id(); "token-2022")]
+    fn test_token_error(token_program_id: Pubkey) {
+        test_syscall_stubs();
+        let swap_key = Pubkey::new_unique();
+        let mut mint = (
+            Pubkey::new_unique(),
+            SolanaAccount::new(
+                mint_minimum_balance(),
+                spl_token::state::Mint::get_packed_len(),
+                &token_program_id,
+            ),
+        );
+        let mut destination = (
+            Pubkey::new_unique(),
+            SolanaAccount::new(
+                account_minimum_balance(),
+                spl_token::state::Account::get_packed_len(),
+                &token_program_id,
+            ),
+        );
+        let mut token_program = (token_program_id, SolanaAccount::default());
+        let (authority_key, bump_seed) =
+            Pubkey::find_program_address(&[&swap_key.to_bytes()[..]], &SWAP_PROGRAM_ID);
+        let mut authority = (authority_key, SolanaAccount::default());
+        let swap_bytes = swap_key.to_bytes();
+        let authority_signature_seeds = [&swap_bytes[..32], &[bump_seed]];
+        let signers = &[&authority_signature_seeds[..]];
+        let mut rent_sysvar = (
+            Pubkey::new_unique(),
+            create_account_for_test(&Rent::default()),
+        );
+        do_process_instruction(
+            initialize_mint(
+                &token_program.0,
+                &mint.0,
+                &authority.0,
+                Some(&authority.0),
+                2,
+            )
+            .unwrap(),
+            vec![&mut mint.1, &mut rent_sysvar.1],
+        )
+        .unwrap();
+        do_process_instruction(
+            initialize_account(&token_program.0, &destination.0, &mint.0, &authority.0).unwrap(),
+            vec![
+                &mut destination.1,
+                &mut mint.1,
+                &mut authority.1,
+                &mut rent_sysvar.1,
+                &mut token_program.1,
+            ],
+        )
+        .unwrap();
+        do_process_instruction(
+            freeze_account(&token_program.0, &destination.0, &mint.0, &authority.0, &[]).unwrap(),
+            vec![
+                &mut destination.1,
+                &mut mint.1,
+                &mut authority.1,
+                &mut token_program.1,
+            ],
+        )
+        .unwrap();
+        let ix = mint_to(
+            &token_program.0,
+            &mint.0,
+            &destination.0,
+            &authority.0,
+            &[],
+            10,
+        )
+        .unwrap();
+        let mint_info = (&mut mint).into();
+        let destination_info = (&mut destination).into();
+        let authority_info = (&mut authority).into();
+        let token_program_info = (&mut token_program).into();
+
+        let err = invoke_signed_wrapper::<TokenError>(
+            &ix,
+            &[
+                mint_info,
+                destination_info,
+                authority_info,
+                token_program_info,
+            ],
+            signers,
+        )
+        .unwrap_err();
+        assert_eq!(err, ProgramError::Custom(TokenError::AccountFrozen as u32));
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_initialize(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let user_key = Pubkey::new_unique();
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 2;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 10;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 5;
+        let host_fee_numerator = 20;
+        let host_fee_denominator = 100;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_a_amount = 1000;
+        let token_b_amount = 2000;
+        let pool_token_amount = 10;
+        let curve_type = CurveType::ConstantProduct;
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator: Arc::new(ConstantProductCurve {}),
+        };
+
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees,
+            SwapTransferFees::default(),
+            swap_curve,
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+
+        // uninitialized token a account
+        {
+            let old_account = accounts.token_a_account;
+            accounts.token_a_account = SolanaAccount::new(0, 0, &token_a_program_id);
             assert_eq!(
-                Err(ProgramError::IncorrectProgramId),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::ExpectedAccount.into()),
+                accounts.initialize_swap()
             );
-            accounts.swap_account = old_swap_account;
+            accounts.token_a_account = old_account;
         }
 
-        // wrong bump seed for authority_key
+        // uninitialized token b account
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let old_authority = accounts.authority_key;
-            let (bad_authority_key, _bump_seed) = Pubkey::find_program_address(
-                &[&accounts.swap_key.to_bytes()[..]],
-                &pool_token_program_id,
-            );
-            accounts.authority_key = bad_authority_key;
+            let old_account = accounts.token_b_account;
+            accounts.token_b_account = SolanaAccount::new(0, 0, &token_b_program_id);
             assert_eq!(
-                Err(SwapError::InvalidProgramAddress.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::ExpectedAccount.into()),
+                accounts.initialize_swap()
             );
-            accounts.authority_key = old_authority;
+            accounts.token_b_account = old_account;
         }
 
-        // not enough token A
+        // uninitialized pool mint
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &depositor_key,
-                deposit_a / 2,
-                deposit_b,
-                0,
-            );
+            let old_account = accounts.pool_mint_account;
+            accounts.pool_mint_account = SolanaAccount::new(0, 0, &pool_token_program_id);
             assert_eq!(
-                Err(TokenError::InsufficientFunds.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::ExpectedMint.into()),
+                accounts.initialize_swap()
             );
+            accounts.pool_mint_account = old_account;
         }
 
-        // not enough token B
+        // token A account owner is not swap authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+            let (_token_a_key, token_a_account) = mint_token(
+                &token_a_program_id,
+                &accounts.token_a_mint_key,
+                &mut accounts.token_a_mint_account,
+                &user_key,
                 &user_key,
-                &depositor_key,
-                deposit_a,
-                deposit_b / 2,
                 0,
             );
+            let old_account = accounts.token_a_account;
+            accounts.token_a_account = token_a_account;
             assert_eq!(
-                Err(TokenError::InsufficientFunds.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::InvalidOwner.into()),
+                accounts.initialize_swap()
             );
+            accounts.token_a_account = old_account;
         }
 
-        // wrong swap token accounts
+        // token B account owner is not swap authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let expected_error: ProgramError = if token_a_account.owner == token_b_account.owner {
-                TokenError::MintMismatch.into()
-            } else {
-                ProgramError::InvalidAccountData
-            };
+            let (_token_b_key, token_b_account) = mint_token(
+                &token_b_program_id,
+                &accounts.token_b_mint_key,
+                &mut accounts.token_b_mint_account,
+                &user_key,
+                &user_key,
+                0,
+            );
+            let old_account = accounts.token_b_account;
+            accounts.token_b_account = token_b_account;
             assert_eq!(
-                Err(expected_error),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::InvalidOwner.into()),
+                accounts.initialize_swap()
             );
+            accounts.token_b_account = old_account;
         }
 
-        // wrong pool token account
+        // pool token account owner is swap authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                mut _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let (
-                wrong_token_key,
-                mut wrong_token_account,
-                _token_b_key,
-                mut _token_b_account,
-                _pool_key,
-                pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let expected_error: ProgramError = if token_a_account.owner == pool_account.owner {
-                TokenError::MintMismatch.into()
-            } else {
-                SwapError::IncorrectTokenProgramId.into()
-            };
+            let (_pool_token_key, pool_token_account) = mint_token(
+                &pool_token_program_id,
+                &accounts.pool_mint_key,
+                &mut accounts.pool_mint_account,
+                &accounts.authority_key,
+                &accounts.authority_key,
+                0,
+            );
+            let old_account = accounts.pool_token_account;
+            accounts.pool_token_account = pool_token_account;
             assert_eq!(
-                Err(expected_error),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &wrong_token_key,
-                    &mut wrong_token_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::InvalidOutputOwner.into()),
+                accounts.initialize_swap()
             );
+            accounts.pool_token_account = old_account;
         }
 
-        // no approval
+        // pool fee account owner is swap authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let user_transfer_authority_key = Pubkey::new_unique();
+            let (_pool_fee_key, pool_fee_account) = mint_token(
+                &pool_token_program_id,
+                &accounts.pool_mint_key,
+                &mut accounts.pool_mint_account,
+                &accounts.authority_key,
+                &accounts.authority_key,
+                0,
+            );
+            let old_account = accounts.pool_fee_account;
+            accounts.pool_fee_account = pool_fee_account;
             assert_eq!(
-                Err(TokenError::OwnerMismatch.into()),
-                do_process_instruction(
-                    deposit_all_token_types(
-                        &SWAP_PROGRAM_ID,
-                        &token_a_program_id,
-                        &token_b_program_id,
-                        &pool_token_program_id,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &user_transfer_authority_key,
-                        &token_a_key,
-                        &token_b_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &accounts.pool_mint_key,
-                        &pool_key,
-                        &accounts.token_a_mint_key,
-                        &accounts.token_b_mint_key,
-                        DepositAllTokenTypes {
-                            pool_token_amount: pool_amount.try_into().unwrap(),
-                            maximum_token_a_amount: deposit_a,
-                            maximum_token_b_amount: deposit_b,
-                        },
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                        &mut token_a_account,
-                        &mut token_b_account,
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut pool_account,
-                        &mut accounts.token_a_mint_account,
-                        &mut accounts.token_b_mint_account,
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                    ],
-                )
+                Err(SwapError::InvalidOutputOwner.into()),
+                accounts.initialize_swap()
             );
+            accounts.pool_fee_account = old_account;
         }
 
-        // wrong token program id
+        // pool mint authority is not swap authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let wrong_key = Pubkey::new_unique();
+            let (_pool_mint_key, pool_mint_account) = create_mint(
+                &pool_token_program_id,
+                &user_key,
+                None,
+                None,
+                &TransferFee::default(),
+            );
+            let old_mint = accounts.pool_mint_account;
+            accounts.pool_mint_account = pool_mint_account;
             assert_eq!(
-                Err(SwapError::IncorrectTokenProgramId.into()),
-                do_process_instruction(
-                    deposit_all_token_types(
-                        &SWAP_PROGRAM_ID,
-                        &wrong_key,
-                        &wrong_key,
-                        &wrong_key,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.authority_key,
-                        &token_a_key,
-                        &token_b_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &accounts.pool_mint_key,
-                        &pool_key,
-                        &accounts.token_a_mint_key,
-                        &accounts.token_b_mint_key,
-                        DepositAllTokenTypes {
-                            pool_token_amount: pool_amount.try_into().unwrap(),
-                            maximum_token_a_amount: deposit_a,
-                            maximum_token_b_amount: deposit_b,
-                        },
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                        &mut token_a_account,
-                        &mut token_b_account,
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut pool_account,
-                        &mut accounts.token_a_mint_account,
-                        &mut accounts.token_b_mint_account,
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                    ],
-                )
+                Err(SwapError::InvalidOwner.into()),
+                accounts.initialize_swap()
             );
+            accounts.pool_mint_account = old_mint;
         }
 
-        // wrong swap token accounts
+        // pool mint token has freeze authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-
-            let old_a_key = accounts.token_a_key;
-            let old_a_account = accounts.token_a_account;
-
-            accounts.token_a_key = token_a_key;
-            accounts.token_a_account = token_a_account.clone();
-
-            // wrong swap token a account
-            assert_eq!(
-                Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+            let (_pool_mint_key, pool_mint_account) = create_mint(
+                &pool_token_program_id,
+                &accounts.authority_key,
+                Some(&user_key),
+                None,
+                &TransferFee::default(),
             );
-
-            accounts.token_a_key = old_a_key;
-            accounts.token_a_account = old_a_account;
-
-            let old_b_key = accounts.token_b_key;
-            let old_b_account = accounts.token_b_account;
-
-            accounts.token_b_key = token_b_key;
-            accounts.token_b_account = token_b_account.clone();
-
-            // wrong swap token b account
+            let old_mint = accounts.pool_mint_account;
+            accounts.pool_mint_account = pool_mint_account;
             assert_eq!(
-                Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::InvalidFreezeAuthority.into()),
+                accounts.initialize_swap()
             );
-
-            accounts.token_b_key = old_b_key;
-            accounts.token_b_account = old_b_account;
+            accounts.pool_mint_account = old_mint;
         }
 
-        // wrong mint
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let (pool_mint_key, pool_mint_account) = create_mint(
+        // pool mint token has close authority, only available in token-2022
+        if pool_token_program_id == spl_token_2022::id() {
+            let (_pool_mint_key, pool_mint_account) = create_mint(
                 &pool_token_program_id,
                 &accounts.authority_key,
                 None,
-                None,
+                Some(&user_key),
                 &TransferFee::default(),
             );
-            let old_pool_key = accounts.pool_mint_key;
-            let old_pool_account = accounts.pool_mint_account;
-            accounts.pool_mint_key = pool_mint_key;
+            let old_mint = accounts.pool_mint_account;
             accounts.pool_mint_account = pool_mint_account;
-
             assert_eq!(
-                Err(SwapError::IncorrectPoolMint.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::InvalidCloseAuthority.into()),
+                accounts.initialize_swap()
             );
-
-            accounts.pool_mint_key = old_pool_key;
-            accounts.pool_mint_account = old_pool_account;
+            accounts.pool_mint_account = old_mint;
         }
 
-        // deposit 1 pool token fails beacuse it equates to 0 swap tokens
+        // token A account owned by wrong program
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let (_token_a_key, mut token_a_account) = mint_token(
+                &token_a_program_id,
+                &accounts.token_a_mint_key,
+                &mut accounts.token_a_mint_account,
+                &user_key,
+                &accounts.authority_key,
+                token_a_amount,
+            );
+            token_a_account.owner = SWAP_PROGRAM_ID;
+            let old_account = accounts.token_a_account;
+            accounts.token_a_account = token_a_account;
             assert_eq!(
-                Err(SwapError::ZeroTradingTokens.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    1,
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::IncorrectTokenProgramId.into()),
+                accounts.initialize_swap()
             );
+            accounts.token_a_account = old_account;
         }
 
-        // slippage exceeded
+        // token B account owned by wrong program
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            // maximum A amount in too low
+            let (_token_b_key, mut token_b_account) = mint_token(
+                &token_b_program_id,
+                &accounts.token_b_mint_key,
+                &mut accounts.token_b_mint_account,
+                &user_key,
+                &accounts.authority_key,
+                token_b_amount,
+            );
+            token_b_account.owner = SWAP_PROGRAM_ID;
+            let old_account = accounts.token_b_account;
+            accounts.token_b_account = token_b_account;
             assert_eq!(
-                Err(SwapError::ExceededSlippage.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a / 10,
-                    deposit_b,
-                )
+                Err(SwapError::IncorrectTokenProgramId.into()),
+                accounts.initialize_swap()
             );
-            // maximum B amount in too low
+            accounts.token_b_account = old_account;
+        }
+
+        // empty token A account
+        {
+            let (_token_a_key, token_a_account) = mint_token(
+                &token_a_program_id,
+                &accounts.token_a_mint_key,
+                &mut accounts.token_a_mint_account,
+                &user_key,
+                &accounts.authority_key,
+                0,
+            );
+            let old_account = accounts.token_a_account;
+            accounts.token_a_account = token_a_account;
             assert_eq!(
-                Err(SwapError::ExceededSlippage.into()),
-                accounts.deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b / 10,
-                )
+                Err(SwapError::EmptySupply.into()),
+                accounts.initialize_swap()
             );
+            accounts.token_a_account = old_account;
         }
 
-        // invalid input: can't use swap pool tokens as source
+        // empty token B account
         {
-            let (
-                _token_a_key,
-                _token_a_account,
-                _token_b_key,
-                _token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let swap_token_a_key = accounts.token_a_key;
-            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
-            let swap_token_b_key = accounts.token_b_key;
-            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
-            let authority_key = accounts.authority_key;
+            let (_token_b_key, token_b_account) = mint_token(
+                &token_b_program_id,
+                &accounts.token_b_mint_key,
+                &mut accounts.token_b_mint_account,
+                &user_key,
+                &accounts.authority_key,
+                0,
+            );
+            let old_account = accounts.token_b_account;
+            accounts.token_b_account = token_b_account;
             assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.deposit_all_token_types(
-                    &authority_key,
-                    &swap_token_a_key,
-                    &mut swap_token_a_account,
-                    &swap_token_b_key,
-                    &mut swap_token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
+                Err(SwapError::EmptySupply.into()),
+                accounts.initialize_swap()
             );
+            accounts.token_b_account = old_account;
         }
 
-        // correctly deposit
+        // invalid pool tokens
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            accounts
-                .deposit_all_token_types(
-                    &depositor_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount.try_into().unwrap(),
-                    deposit_a,
-                    deposit_b,
-                )
-                .unwrap();
+            let old_mint = accounts.pool_mint_account;
+            let old_pool_account = accounts.pool_token_account;
 
-            let swap_token_a =
-                StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
-            assert_eq!(swap_token_a.base.amount, deposit_a + token_a_amount);
-            let swap_token_b =
-                StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
-            assert_eq!(swap_token_b.base.amount, deposit_b + token_b_amount);
-            let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
-            assert_eq!(token_a.base.amount, 0);
-            let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
-            assert_eq!(token_b.base.amount, 0);
-            let pool_account = StateWithExtensions::<Account>::unpack(&pool_account.data).unwrap();
-            let swap_pool_account =
-                StateWithExtensions::<Account>::unpack(&accounts.pool_token_account.data).unwrap();
-            let pool_mint =
-                StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
-            assert_eq!(
-                pool_mint.base.supply,
-                pool_account.base.amount + swap_pool_account.base.amount
+            let (_pool_mint_key, pool_mint_account) = create_mint(
+                &pool_token_program_id,
+                &accounts.authority_key,
+                None,
+                None,
+                &TransferFee::default(),
             );
-        }
-    }
-
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_withdraw(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
-        let user_key = Pubkey::new_unique();
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 2;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 10;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 5;
-        let host_fee_numerator = 7;
-        let host_fee_denominator = 100;
-
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
-
-        let token_a_amount = 1000;
-        let token_b_amount = 2000;
-        let curve_type = CurveType::ConstantProduct;
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator: Arc::new(ConstantProductCurve {}),
-        };
-
-        let withdrawer_key = Pubkey::new_unique();
-        let initial_a = token_a_amount / 10;
-        let initial_b = token_b_amount / 10;
-        let initial_pool = swap_curve.calculator.new_pool_supply() / 10;
-        let withdraw_amount = initial_pool / 4;
-        let minimum_token_a_amount = initial_a / 40;
-        let minimum_token_b_amount = initial_b / 40;
-
-        let mut accounts = SwapAccountInfo::new(
-            &user_key,
-            fees,
-            SwapTransferFees::default(),
-            swap_curve,
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
-        );
+            accounts.pool_mint_account = pool_mint_account;
 
-        // swap not initialized
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
-            assert_eq!(
-                Err(ProgramError::UninitializedAccount),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+            let (_empty_pool_token_key, empty_pool_token_account) = mint_token(
+                &pool_token_program_id,
+                &accounts.pool_mint_key,
+                &mut accounts.pool_mint_account,
+                &accounts.authority_key,
+                &user_key,
+                0,
             );
-        }
 
-        accounts.initialize_swap().unwrap();
+            let (_pool_token_key, pool_token_account) = mint_token(
+                &pool_token_program_id,
+                &accounts.pool_mint_key,
+                &mut accounts.pool_mint_account,
+                &accounts.authority_key,
+                &user_key,
+                pool_token_amount,
+            );
 
-        // wrong owner for swap account
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
-            let old_swap_account = accounts.swap_account;
-            let mut wrong_swap_account = old_swap_account.clone();
-            wrong_swap_account.owner = pool_token_program_id;
-            accounts.swap_account = wrong_swap_account;
+            // non-empty pool token account
+            accounts.pool_token_account = pool_token_account;
             assert_eq!(
-                Err(ProgramError::IncorrectProgramId),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+                Err(SwapError::InvalidSupply.into()),
+                accounts.initialize_swap()
             );
-            accounts.swap_account = old_swap_account;
-        }
 
-        // wrong bump seed for authority_key
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
-            let old_authority = accounts.authority_key;
-            let (bad_authority_key, _bump_seed) = Pubkey::find_program_address(
-                &[&accounts.swap_key.to_bytes()[..]],
-                &pool_token_program_id,
-            );
-            accounts.authority_key = bad_authority_key;
+            // pool tokens already in circulation
+            accounts.pool_token_account = empty_pool_token_account;
             assert_eq!(
-                Err(SwapError::InvalidProgramAddress.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+                Err(SwapError::InvalidSupply.into()),
+                accounts.initialize_swap()
             );
-            accounts.authority_key = old_authority;
+
+            accounts.pool_mint_account = old_mint;
+            accounts.pool_token_account = old_pool_account;
         }
 
-        // not enough pool tokens
+        // pool fee account has wrong mint
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+            let (_pool_fee_key, pool_fee_account) = mint_token(
+                &token_a_program_id,
+                &accounts.token_a_mint_key,
+                &mut accounts.token_a_mint_account,
                 &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                to_u64(withdraw_amount).unwrap() / 2u64,
+                &user_key,
+                0,
             );
+            let old_account = accounts.pool_fee_account;
+            accounts.pool_fee_account = pool_fee_account;
             assert_eq!(
-                Err(TokenError::InsufficientFunds.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount / 2,
-                    minimum_token_b_amount / 2,
-                )
+                Err(SwapError::IncorrectPoolMint.into()),
+                accounts.initialize_swap()
             );
+            accounts.pool_fee_account = old_account;
         }
 
-        // wrong token a / b accounts
+        // token A account is delegated
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
-            let expected_error: ProgramError = if token_a_account.owner == token_b_account.owner {
-                TokenError::MintMismatch.into()
-            } else {
-                ProgramError::InvalidAccountData
-            };
-            assert_eq!(
-                Err(expected_error),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
+            do_process_instruction(
+                approve(
+                    &token_a_program_id,
+                    &accounts.token_a_key,
+                    &user_key,
+                    &accounts.authority_key,
+                    &[],
+                    1,
                 )
+                .unwrap(),
+                vec![
+                    &mut accounts.token_a_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
+            )
+            .unwrap();
+            assert_eq!(
+                Err(SwapError::InvalidDelegate.into()),
+                accounts.initialize_swap()
             );
+
+            do_process_instruction(
+                revoke(
+                    &token_a_program_id,
+                    &accounts.token_a_key,
+                    &accounts.authority_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut accounts.token_a_account, &mut SolanaAccount::default()],
+            )
+            .unwrap();
         }
 
-        // wrong pool token account
+        // token B account is delegated
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
-            let (
-                wrong_token_a_key,
-                mut wrong_token_a_account,
-                _token_b_key,
-                _token_b_account,
-                _pool_key,
-                pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                withdraw_amount.try_into().unwrap(),
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
-            let expected_error: ProgramError = if token_a_account.owner == pool_account.owner {
-                TokenError::MintMismatch.into()
-            } else {
-                SwapError::IncorrectTokenProgramId.into()
-            };
-            assert_eq!(
-                Err(expected_error),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &wrong_token_a_key,
-                    &mut wrong_token_a_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
+            do_process_instruction(
+                approve(
+                    &token_b_program_id,
+                    &accounts.token_b_key,
+                    &user_key,
+                    &accounts.authority_key,
+                    &[],
+                    1,
                 )
+                .unwrap(),
+                vec![
+                    &mut accounts.token_b_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
+            )
+            .unwrap();
+            assert_eq!(
+                Err(SwapError::InvalidDelegate.into()),
+                accounts.initialize_swap()
             );
+
+            do_process_instruction(
+                revoke(
+                    &token_b_program_id,
+                    &accounts.token_b_key,
+                    &accounts.authority_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut accounts.token_b_account, &mut SolanaAccount::default()],
+            )
+            .unwrap();
         }
 
-        // wrong pool fee account
+        // token A account has close authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                wrong_pool_key,
-                wrong_pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
-            let (
-                _token_a_key,
-                _token_a_account,
-                _token_b_key,
-                _token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
-            let old_pool_fee_account = accounts.pool_fee_account;
-            let old_pool_fee_key = accounts.pool_fee_key;
-            accounts.pool_fee_account = wrong_pool_account;
-            accounts.pool_fee_key = wrong_pool_key;
+            do_process_instruction(
+                set_authority(
+                    &token_a_program_id,
+                    &accounts.token_a_key,
+                    Some(&user_key),
+                    AuthorityType::CloseAccount,
+                    &accounts.authority_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut accounts.token_a_account, &mut SolanaAccount::default()],
+            )
+            .unwrap();
             assert_eq!(
-                Err(SwapError::IncorrectFeeAccount.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                ),
+                Err(SwapError::InvalidCloseAuthority.into()),
+                accounts.initialize_swap()
             );
-            accounts.pool_fee_account = old_pool_fee_account;
-            accounts.pool_fee_key = old_pool_fee_key;
+
+            do_process_instruction(
+                set_authority(
+                    &token_a_program_id,
+                    &accounts.token_a_key,
+                    None,
+                    AuthorityType::CloseAccount,
+                    &user_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut accounts.token_a_account, &mut SolanaAccount::default()],
+            )
+            .unwrap();
         }
 
-        // no approval
+        // token B account has close authority
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                0,
-                0,
-                withdraw_amount.try_into().unwrap(),
-            );
-            let user_transfer_authority_key = Pubkey::new_unique();
+            do_process_instruction(
+                set_authority(
+                    &token_b_program_id,
+                    &accounts.token_b_key,
+                    Some(&user_key),
+                    AuthorityType::CloseAccount,
+                    &accounts.authority_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut accounts.token_b_account, &mut SolanaAccount::default()],
+            )
+            .unwrap();
             assert_eq!(
-                Err(TokenError::OwnerMismatch.into()),
-                do_process_instruction(
-                    withdraw_all_token_types(
-                        &SWAP_PROGRAM_ID,
-                        &pool_token_program_id,
-                        &token_a_program_id,
-                        &token_b_program_id,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &user_transfer_authority_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &pool_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &token_a_key,
-                        &token_b_key,
-                        &accounts.token_a_mint_key,
-                        &accounts.token_b_mint_key,
-                        WithdrawAllTokenTypes {
-                            pool_token_amount: withdraw_amount.try_into().unwrap(),
-                            minimum_token_a_amount,
-                            minimum_token_b_amount,
-                        }
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                        &mut accounts.pool_mint_account,
-                        &mut pool_account,
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut token_a_account,
-                        &mut token_b_account,
-                        &mut accounts.pool_fee_account,
-                        &mut accounts.token_a_mint_account,
-                        &mut accounts.token_b_mint_account,
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                    ],
-                )
+                Err(SwapError::InvalidCloseAuthority.into()),
+                accounts.initialize_swap()
             );
+
+            do_process_instruction(
+                set_authority(
+                    &token_b_program_id,
+                    &accounts.token_b_key,
+                    None,
+                    AuthorityType::CloseAccount,
+                    &user_key,
+                    &[],
+                )
+                .unwrap(),
+                vec![&mut accounts.token_b_account, &mut SolanaAccount::default()],
+            )
+            .unwrap();
         }
 
         // wrong token program id
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                withdraw_amount.try_into().unwrap(),
-            );
-            let wrong_key = Pubkey::new_unique();
+            let wrong_program_id = Pubkey::new_unique();
             assert_eq!(
-                Err(SwapError::IncorrectTokenProgramId.into()),
+                Err(ProgramError::IncorrectProgramId),
                 do_process_instruction(
-                    withdraw_all_token_types(
+                    initialize(
                         &SWAP_PROGRAM_ID,
-                        &wrong_key,
-                        &wrong_key,
-                        &wrong_key,
+                        &wrong_program_id,
                         &accounts.swap_key,
                         &accounts.authority_key,
-                        &accounts.authority_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &pool_key,
                         &accounts.token_a_key,
                         &accounts.token_b_key,
-                        &token_a_key,
-                        &token_b_key,
-                        &accounts.token_a_mint_key,
-                        &accounts.token_b_mint_key,
-                        WithdrawAllTokenTypes {
-                            pool_token_amount: withdraw_amount.try_into().unwrap(),
-                            minimum_token_a_amount,
-                            minimum_token_b_amount,
-                        },
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &accounts.pool_token_key,
+                        None,
+                        accounts.fees.clone(),
+                        accounts.swap_curve.clone(),
                     )
                     .unwrap(),
                     vec![
                         &mut accounts.swap_account,
                         &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                        &mut accounts.pool_mint_account,
-                        &mut pool_account,
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
-                        &mut token_a_account,
-                        &mut token_b_account,
+                        &mut accounts.pool_mint_account,
                         &mut accounts.pool_fee_account,
-                        &mut accounts.token_a_mint_account,
-                        &mut accounts.token_b_mint_account,
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
+                        &mut accounts.pool_token_account,
                         &mut SolanaAccount::default(),
                     ],
                 )
             );
         }
 
-        // wrong swap token accounts
+        // create swap with same token A and B
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+            let (_token_a_repeat_key, token_a_repeat_account) = mint_token(
+                &token_a_program_id,
+                &accounts.token_a_mint_key,
+                &mut accounts.token_a_mint_account,
                 &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
-            );
-
-            let old_a_key = accounts.token_a_key;
-            let old_a_account = accounts.token_a_account;
-
-            accounts.token_a_key = token_a_key;
-            accounts.token_a_account = token_a_account.clone();
-
-            // wrong swap token a account
-            assert_eq!(
-                Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+                &accounts.authority_key,
+                10,
             );
-
-            accounts.token_a_key = old_a_key;
-            accounts.token_a_account = old_a_account;
-
-            let old_b_key = accounts.token_b_key;
-            let old_b_account = accounts.token_b_account;
-
-            accounts.token_b_key = token_b_key;
-            accounts.token_b_account = token_b_account.clone();
-
-            // wrong swap token b account
+            let old_account = accounts.token_b_account;
+            accounts.token_b_account = token_a_repeat_account;
             assert_eq!(
-                Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+                Err(SwapError::RepeatedMint.into()),
+                accounts.initialize_swap()
             );
-
-            accounts.token_b_key = old_b_key;
-            accounts.token_b_account = old_b_account;
+            accounts.token_b_account = old_account;
         }
 
-        // wrong mint
+        // create valid swap
+        accounts.initialize_swap().unwrap();
+
+        // create invalid flat swap
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+            let token_b_price = 0;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConstantPrice,
+                calculator: Arc::new(ConstantPriceCurve { token_b_price }),
+            };
+            let mut accounts = SwapAccountInfo::new(
                 &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
-            );
-            let (pool_mint_key, pool_mint_account) = create_mint(
+                fees,
+                SwapTransferFees::default(),
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
                 &pool_token_program_id,
-                &accounts.authority_key,
-                None,
-                None,
-                &TransferFee::default(),
+                &token_a_program_id,
+                &token_b_program_id,
             );
-            let old_pool_key = accounts.pool_mint_key;
-            let old_pool_account = accounts.pool_mint_account;
-            accounts.pool_mint_key = pool_mint_key;
-            accounts.pool_mint_account = pool_mint_account;
-
             assert_eq!(
-                Err(SwapError::IncorrectPoolMint.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+                Err(SwapError::InvalidCurve.into()),
+                accounts.initialize_swap()
             );
-
-            accounts.pool_mint_key = old_pool_key;
-            accounts.pool_mint_account = old_pool_account;
         }
 
-        // withdrawing 1 pool token fails because it equates to 0 output tokens
+        // create valid flat swap
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
-                &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
-            );
-            assert_eq!(
-                Err(SwapError::ZeroTradingTokens.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    1,
-                    0,
-                    0,
-                )
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let token_b_price = 10_000;
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConstantPrice,
+                calculator: Arc::new(ConstantPriceCurve { token_b_price }),
+            };
+            let mut accounts = SwapAccountInfo::new(
+                &user_key,
+                fees,
+                SwapTransferFees::default(),
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
+                &pool_token_program_id,
+                &token_a_program_id,
+                &token_b_program_id,
             );
+            accounts.initialize_swap().unwrap();
         }
 
-        // slippage exceeded
+        // create invalid offset swap
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+            let token_b_offset = 0;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::Offset,
+                calculator: Arc::new(OffsetCurve { token_b_offset }),
+            };
+            let mut accounts = SwapAccountInfo::new(
                 &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
-            );
-            // minimum A amount out too high
-            assert_eq!(
-                Err(SwapError::ExceededSlippage.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount * 10,
-                    minimum_token_b_amount,
-                )
+                fees,
+                SwapTransferFees::default(),
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
+                &pool_token_program_id,
+                &token_a_program_id,
+                &token_b_program_id,
             );
-            // minimum B amount out too high
             assert_eq!(
-                Err(SwapError::ExceededSlippage.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount * 10,
-                )
+                Err(SwapError::InvalidCurve.into()),
+                accounts.initialize_swap()
             );
         }
 
-        // invalid input: can't use swap pool tokens as destination
+        // create valid offset swap
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+            let token_b_offset = 10;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::Offset,
+                calculator: Arc::new(OffsetCurve { token_b_offset }),
+            };
+            let mut accounts = SwapAccountInfo::new(
                 &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
+                fees,
+                SwapTransferFees::default(),
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
+                &pool_token_program_id,
+                &token_a_program_id,
+                &token_b_program_id,
             );
-            let swap_token_a_key = accounts.token_a_key;
-            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
-            assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &swap_token_a_key,
-                    &mut swap_token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+            accounts.initialize_swap().unwrap();
+        }
+
+        // create invalid weighted swap
+        {
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::Weighted,
+                calculator: Arc::new(WeightedCurve {
+                    token_a_weight: 100,
+                    token_b_weight: 0,
+                }),
+            };
+            let mut accounts = SwapAccountInfo::new(
+                &user_key,
+                fees,
+                SwapTransferFees::default(),
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
+                &pool_token_program_id,
+                &token_a_program_id,
+                &token_b_program_id,
             );
-            let swap_token_b_key = accounts.token_b_key;
-            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
             assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &swap_token_b_key,
-                    &mut swap_token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
+                Err(SwapError::InvalidCurve.into()),
+                accounts.initialize_swap()
             );
         }
 
-        // correct withdrawal
+        // create valid weighted swap
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::Weighted,
+                calculator: Arc::new(WeightedCurve {
+                    token_a_weight: 80,
+                    token_b_weight: 20,
+                }),
+            };
+            let mut accounts = SwapAccountInfo::new(
                 &user_key,
-                &withdrawer_key,
-                initial_a,
-                initial_b,
-                initial_pool.try_into().unwrap(),
+                fees,
+                SwapTransferFees::default(),
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
+                &pool_token_program_id,
+                &token_a_program_id,
+                &token_b_program_id,
             );
+            accounts.initialize_swap().unwrap();
+        }
 
-            accounts
-                .withdraw_all_token_types(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    withdraw_amount.try_into().unwrap(),
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                )
-                .unwrap();
-
-            let swap_token_a =
-                StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
-            let swap_token_b =
-                StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
-            let pool_mint =
-                StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
-            let withdraw_fee = accounts.fees.owner_withdraw_fee(withdraw_amount).unwrap();
-            let results = accounts
-                .swap_curve
-                .calculator
-                .pool_tokens_to_trading_tokens(
-                    withdraw_amount - withdraw_fee,
-                    pool_mint.base.supply.try_into().unwrap(),
-                    swap_token_a.base.amount.try_into().unwrap(),
-                    swap_token_b.base.amount.try_into().unwrap(),
-                    RoundDirection::Floor,
-                )
-                .unwrap();
-            assert_eq!(
-                swap_token_a.base.amount,
-                token_a_amount - to_u64(results.token_a_amount).unwrap()
-            );
-            assert_eq!(
-                swap_token_b.base.amount,
-                token_b_amount - to_u64(results.token_b_amount).unwrap()
-            );
-            let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
-            assert_eq!(
-                token_a.base.amount,
-                initial_a + to_u64(results.token_a_amount).unwrap()
-            );
-            let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
-            assert_eq!(
-                token_b.base.amount,
-                initial_b + to_u64(results.token_b_amount).unwrap()
-            );
-            let pool_account = StateWithExtensions::<Account>::unpack(&pool_account.data).unwrap();
-            assert_eq!(
-                pool_account.base.amount,
-                to_u64(initial_pool - withdraw_amount).unwrap()
+        // wrong owner key in constraint
+        {
+            let new_key = Pubkey::new_unique();
+            let trade_fee_numerator = 25;
+            let trade_fee_denominator = 10000;
+            let owner_trade_fee_numerator = 5;
+            let owner_trade_fee_denominator = 10000;
+            let host_fee_numerator = 20;
+            let host_fee_denominator = 100;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let curve = ConstantProductCurve {};
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConstantProduct,
+                calculator: Arc::new(curve),
+            };
+            let owner_key = &new_key.to_string();
+            let valid_curve_types = &[CurveType::ConstantProduct];
+            let constraints = Some(SwapConstraints {
+                owner_key,
+                valid_curve_types,
+                fees: &fees,
+            });
+            let mut accounts = SwapAccountInfo::new(
+                &user_key,
+                fees.clone(),
+                SwapTransferFees::default(),
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
+                &pool_token_program_id,
+                &token_a_program_id,
+                &token_b_program_id,
             );
-            let fee_account =
-                StateWithExtensions::<Account>::unpack(&accounts.pool_fee_account.data).unwrap();
             assert_eq!(
-                fee_account.base.amount,
-                TryInto::<u64>::try_into(withdraw_fee).unwrap()
+                Err(SwapError::InvalidOwner.into()),
+                do_process_instruction_with_fee_constraints(
+                    initialize(
+                        &SWAP_PROGRAM_ID,
+                        &pool_token_program_id,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &accounts.pool_token_key,
+                        None,
+                        accounts.fees.clone(),
+                        accounts.swap_curve.clone(),
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut SolanaAccount::default(),
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut accounts.pool_fee_account,
+                        &mut accounts.pool_token_account,
+                        &mut SolanaAccount::default(),
+                    ],
+                    &constraints,
+                )
             );
         }
 
-        // correct withdrawal from fee account
+        // wrong fee in constraint
         {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                mut _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
-
-            let pool_fee_key = accounts.pool_fee_key;
-            let mut pool_fee_account = accounts.pool_fee_account.clone();
-            let fee_account =
-                StateWithExtensions::<Account>::unpack(&pool_fee_account.data).unwrap();
-            let pool_fee_amount = fee_account.base.amount;
-
-            accounts
-                .withdraw_all_token_types(
-                    &user_key,
-                    &pool_fee_key,
-                    &mut pool_fee_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    pool_fee_amount,
-                    0,
-                    0,
-                )
-                .unwrap();
-
-            let swap_token_a =
-                StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
-            let swap_token_b =
-                StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
-            let pool_mint =
-                StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
-            let results = accounts
-                .swap_curve
-                .calculator
-                .pool_tokens_to_trading_tokens(
-                    pool_fee_amount.try_into().unwrap(),
-                    pool_mint.base.supply.try_into().unwrap(),
-                    swap_token_a.base.amount.try_into().unwrap(),
-                    swap_token_b.base.amount.try_into().unwrap(),
-                    RoundDirection::Floor,
-                )
-                .unwrap();
-            let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
-            assert_eq!(
-                token_a.base.amount,
-                TryInto::<u64>::try_into(results.token_a_amount).unwrap()
+            let trade_fee_numerator = 25;
+            let trade_fee_denominator = 10000;
+            let owner_trade_fee_numerator = 5;
+            let owner_trade_fee_denominator = 10000;
+            let host_fee_numerator = 20;
+            let host_fee_denominator = 100;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let curve = ConstantProductCurve {};
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConstantProduct,
+                calculator: Arc::new(curve),
+            };
+            let owner_key = &user_key.to_string();
+            let valid_curve_types = &[CurveType::ConstantProduct];
+            let constraints = Some(SwapConstraints {
+                owner_key,
+                valid_curve_types,
+                fees: &fees,
+            });
+            let mut bad_fees = fees.clone();
+            bad_fees.trade_fee_numerator = trade_fee_numerator - 1;
+            let mut accounts = SwapAccountInfo::new(
+                &user_key,
+                bad_fees,
+                SwapTransferFees::default(),
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
+                &pool_token_program_id,
+                &token_a_program_id,
+                &token_b_program_id,
             );
-            let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
             assert_eq!(
-                token_b.base.amount,
-                TryInto::<u64>::try_into(results.token_b_amount).unwrap()
+                Err(SwapError::InvalidFee.into()),
+                do_process_instruction_with_fee_constraints(
+                    initialize(
+                        &SWAP_PROGRAM_ID,
+                        &pool_token_program_id,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &accounts.pool_token_key,
+                        None,
+                        accounts.fees.clone(),
+                        accounts.swap_curve.clone(),
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut SolanaAccount::default(),
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut accounts.pool_fee_account,
+                        &mut accounts.pool_token_account,
+                        &mut SolanaAccount::default(),
+                    ],
+                    &constraints,
+                )
             );
         }
-    }
 
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_deposit_one_exact_in(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
-        let user_key = Pubkey::new_unique();
-        let depositor_key = Pubkey::new_unique();
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 2;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 10;
-        let owner_withdraw_fee_numerator = 1;
+        // create valid swap with constraints
+        {
+            let trade_fee_numerator = 25;
+            let trade_fee_denominator = 10000;
+            let owner_trade_fee_numerator = 5;
+            let owner_trade_fee_denominator = 10000;
+            let host_fee_numerator = 20;
+            let host_fee_denominator = 100;
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let curve = ConstantProductCurve {};
+            let swap_curve = SwapCurve {
+                curve_type: CurveType::ConstantProduct,
+                calculator: Arc::new(curve),
+            };
+            let owner_key = &user_key.to_string();
+            let valid_curve_types = &[CurveType::ConstantProduct];
+            let constraints = Some(SwapConstraints {
+                owner_key,
+                valid_curve_types,
+                fees: &fees,
+            });
+            let mut accounts = SwapAccountInfo::new(
+                &user_key,
+                fees.clone(),
+                SwapTransferFees::default(),
+                swap_curve,
+                token_a_amount,
+                token_b_amount,
+                &pool_token_program_id,
+                &token_a_program_id,
+                &token_b_program_id,
+            );
+            do_process_instruction_with_fee_constraints(
+                initialize(
+                    &SWAP_PROGRAM_ID,
+                    &pool_token_program_id,
+                    &accounts.swap_key,
+                    &accounts.authority_key,
+                    &accounts.token_a_key,
+                    &accounts.token_b_key,
+                    &accounts.pool_mint_key,
+                    &accounts.pool_fee_key,
+                    &accounts.pool_token_key,
+                    None,
+                    accounts.fees,
+                    accounts.swap_curve.clone(),
+                )
+                .unwrap(),
+                vec![
+                    &mut accounts.swap_account,
+                    &mut SolanaAccount::default(),
+                    &mut accounts.token_a_account,
+                    &mut accounts.token_b_account,
+                    &mut accounts.pool_mint_account,
+                    &mut accounts.pool_fee_account,
+                    &mut accounts.pool_token_account,
+                    &mut SolanaAccount::default(),
+                ],
+                &constraints,
+            )
+            .unwrap();
+        }
+
+        // create again
+        {
+            assert_eq!(
+                Err(SwapError::AlreadyInUse.into()),
+                accounts.initialize_swap()
+            );
+        }
+        let swap_state = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert!(swap_state.is_initialized());
+        assert_eq!(swap_state.bump_seed(), accounts.bump_seed);
+        assert_eq!(
+            swap_state.swap_curve().curve_type,
+            accounts.swap_curve.curve_type
+        );
+        assert_eq!(*swap_state.token_a_account(), accounts.token_a_key);
+        assert_eq!(*swap_state.token_b_account(), accounts.token_b_key);
+        assert_eq!(*swap_state.pool_mint(), accounts.pool_mint_key);
+        assert_eq!(*swap_state.token_a_mint(), accounts.token_a_mint_key);
+        assert_eq!(*swap_state.token_b_mint(), accounts.token_b_mint_key);
+        assert_eq!(*swap_state.pool_fee_account(), accounts.pool_fee_key);
+        let token_a =
+            StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
+        assert_eq!(token_a.base.amount, token_a_amount);
+        let token_b =
+            StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
+        assert_eq!(token_b.base.amount, token_b_amount);
+        let pool_account =
+            StateWithExtensions::<Account>::unpack(&accounts.pool_token_account.data).unwrap();
+        let pool_mint =
+            StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
+        assert_eq!(pool_mint.base.supply, pool_account.base.amount);
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_deposit(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let user_key = Pubkey::new_unique();
+        let depositor_key = Pubkey::new_unique();
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 2;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 10;
+        let owner_withdraw_fee_numerator = 1;
         let owner_withdraw_fee_denominator = 5;
         let host_fee_numerator = 20;
         let host_fee_denominator = 100;
@@ -4901,30 +4793,35 @@ mod tests {
             &token_b_program_id,
         );
 
+        // depositing 10% of the current pool amount in token A and B means
+        // that our pool tokens will be worth 1 / 10 of the current pool amount
+        let pool_amount = INITIAL_SWAP_POOL_AMOUNT / 10;
         let deposit_a = token_a_amount / 10;
         let deposit_b = token_b_amount / 10;
-        let pool_amount = to_u64(INITIAL_SWAP_POOL_AMOUNT / 100).unwrap();
 
         // swap not initialized
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
             assert_eq!(
                 Err(ProgramError::UninitializedAccount),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
             );
         }
@@ -4936,8 +4833,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
@@ -4947,14 +4844,17 @@ mod tests {
             accounts.swap_account = wrong_swap_account;
             assert_eq!(
                 Err(ProgramError::IncorrectProgramId),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
             );
             accounts.swap_account = old_swap_account;
@@ -4965,8 +4865,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
@@ -4978,20 +4878,23 @@ mod tests {
             accounts.authority_key = bad_authority_key;
             assert_eq!(
                 Err(SwapError::InvalidProgramAddress.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
             );
             accounts.authority_key = old_authority;
         }
 
-        // not enough token A / B
+        // not enough token A
         {
             let (
                 token_a_key,
@@ -5004,31 +4907,87 @@ mod tests {
                 &user_key,
                 &depositor_key,
                 deposit_a / 2,
-                deposit_b / 2,
+                deposit_b,
                 0,
             );
             assert_eq!(
                 Err(TokenError::InsufficientFunds.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    0,
+                    deposit_b,
                 )
             );
+        }
+
+        // not enough token B
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &depositor_key,
+                deposit_a,
+                deposit_b / 2,
+                0,
+            );
             assert_eq!(
                 Err(TokenError::InsufficientFunds.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
+                    deposit_b,
+                )
+            );
+        }
+
+        // wrong swap token accounts
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let expected_error: ProgramError = if token_a_account.owner == token_b_account.owner {
+                TokenError::MintMismatch.into()
+            } else {
+                ProgramError::InvalidAccountData
+            };
+            assert_eq!(
+                Err(expected_error),
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_b_key,
                     &mut token_b_account,
+                    &token_a_key,
+                    &mut token_a_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
                     deposit_b,
-                    0,
                 )
             );
         }
@@ -5041,23 +5000,34 @@ mod tests {
                 token_b_key,
                 mut token_b_account,
                 _pool_key,
+                mut _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let (
+                wrong_token_key,
+                mut wrong_token_account,
+                _token_b_key,
+                mut _token_b_account,
+                _pool_key,
                 pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            let expected_error: ProgramError = if token_b_account.owner == pool_account.owner {
+            let expected_error: ProgramError = if token_a_account.owner == pool_account.owner {
                 TokenError::MintMismatch.into()
             } else {
                 SwapError::IncorrectTokenProgramId.into()
             };
             assert_eq!(
                 Err(expected_error),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
+                    &wrong_token_key,
+                    &mut wrong_token_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
             );
         }
@@ -5067,8 +5037,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
@@ -5076,35 +5046,42 @@ mod tests {
             assert_eq!(
                 Err(TokenError::OwnerMismatch.into()),
                 do_process_instruction(
-                    deposit_single_token_type_exact_amount_in(
+                    deposit_all_token_types(
                         &SWAP_PROGRAM_ID,
                         &token_a_program_id,
+                        &token_b_program_id,
                         &pool_token_program_id,
                         &accounts.swap_key,
                         &accounts.authority_key,
                         &user_transfer_authority_key,
                         &token_a_key,
+                        &token_b_key,
                         &accounts.token_a_key,
                         &accounts.token_b_key,
                         &accounts.pool_mint_key,
                         &pool_key,
                         &accounts.token_a_mint_key,
-                        DepositSingleTokenTypeExactAmountIn {
-                            source_token_amount: deposit_a,
-                            minimum_pool_token_amount: pool_amount,
-                        },
-                    )
+                        &accounts.token_b_mint_key,
+                        DepositAllTokenTypes {
+                            pool_token_amount: pool_amount.try_into().unwrap(),
+                            maximum_token_a_amount: deposit_a,
+                            maximum_token_b_amount: deposit_b,
+                        },
+                    )
                     .unwrap(),
                     vec![
                         &mut accounts.swap_account,
                         &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                         &mut token_a_account,
+                        &mut token_b_account,
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
                         &mut accounts.pool_mint_account,
                         &mut pool_account,
                         &mut accounts.token_a_mint_account,
+                        &mut accounts.token_b_mint_account,
+                        &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                     ],
@@ -5117,8 +5094,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
@@ -5126,22 +5103,26 @@ mod tests {
             assert_eq!(
                 Err(SwapError::IncorrectTokenProgramId.into()),
                 do_process_instruction(
-                    deposit_single_token_type_exact_amount_in(
+                    deposit_all_token_types(
                         &SWAP_PROGRAM_ID,
                         &wrong_key,
                         &wrong_key,
+                        &wrong_key,
                         &accounts.swap_key,
                         &accounts.authority_key,
                         &accounts.authority_key,
                         &token_a_key,
+                        &token_b_key,
                         &accounts.token_a_key,
                         &accounts.token_b_key,
                         &accounts.pool_mint_key,
                         &pool_key,
                         &accounts.token_a_mint_key,
-                        DepositSingleTokenTypeExactAmountIn {
-                            source_token_amount: deposit_a,
-                            minimum_pool_token_amount: pool_amount,
+                        &accounts.token_b_mint_key,
+                        DepositAllTokenTypes {
+                            pool_token_amount: pool_amount.try_into().unwrap(),
+                            maximum_token_a_amount: deposit_a,
+                            maximum_token_b_amount: deposit_b,
                         },
                     )
                     .unwrap(),
@@ -5150,11 +5131,14 @@ mod tests {
                         &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                         &mut token_a_account,
+                        &mut token_b_account,
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
                         &mut accounts.pool_mint_account,
                         &mut pool_account,
                         &mut accounts.token_a_mint_account,
+                        &mut accounts.token_b_mint_account,
+                        &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                     ],
@@ -5168,7 +5152,7 @@ mod tests {
                 token_a_key,
                 mut token_a_account,
                 token_b_key,
-                token_b_account,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
@@ -5182,14 +5166,17 @@ mod tests {
             // wrong swap token a account
             assert_eq!(
                 Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
             );
 
@@ -5200,19 +5187,22 @@ mod tests {
             let old_b_account = accounts.token_b_account;
 
             accounts.token_b_key = token_b_key;
-            accounts.token_b_account = token_b_account;
+            accounts.token_b_account = token_b_account.clone();
 
             // wrong swap token b account
             assert_eq!(
                 Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
             );
 
@@ -5225,8 +5215,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
@@ -5244,14 +5234,17 @@ mod tests {
 
             assert_eq!(
                 Err(SwapError::IncorrectPoolMint.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
             );
 
@@ -5259,6 +5252,33 @@ mod tests {
             accounts.pool_mint_account = old_pool_account;
         }
 
+        // deposit 1 pool token fails beacuse it equates to 0 swap tokens
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            assert_eq!(
+                Err(SwapError::ZeroTradingTokens.into()),
+                accounts.deposit_all_token_types(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    1,
+                    deposit_a,
+                    deposit_b,
+                )
+            );
+        }
+
         // slippage exceeded
         {
             let (
@@ -5269,30 +5289,36 @@ mod tests {
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
-            // minimum pool amount too high
+            // maximum A amount in too low
             assert_eq!(
                 Err(SwapError::ExceededSlippage.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a / 10,
-                    pool_amount,
+                    deposit_b,
                 )
             );
-            // minimum pool amount too high
+            // maximum B amount in too low
             assert_eq!(
                 Err(SwapError::ExceededSlippage.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
                     deposit_b / 10,
-                    pool_amount,
                 )
             );
         }
@@ -5314,26 +5340,17 @@ mod tests {
             let authority_key = accounts.authority_key;
             assert_eq!(
                 Err(SwapError::InvalidInput.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
+                accounts.deposit_all_token_types(
                     &authority_key,
                     &swap_token_a_key,
                     &mut swap_token_a_account,
-                    &pool_key,
-                    &mut pool_account,
-                    deposit_a,
-                    pool_amount,
-                )
-            );
-            assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.deposit_single_token_type_exact_amount_in(
-                    &authority_key,
                     &swap_token_b_key,
                     &mut swap_token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
+                    deposit_a,
                     deposit_b,
-                    pool_amount,
                 )
             );
         }
@@ -5349,42 +5366,30 @@ mod tests {
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
             accounts
-                .deposit_single_token_type_exact_amount_in(
+                .deposit_all_token_types(
                     &depositor_key,
                     &token_a_key,
                     &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
                     &pool_key,
                     &mut pool_account,
+                    pool_amount.try_into().unwrap(),
                     deposit_a,
-                    pool_amount,
+                    deposit_b,
                 )
                 .unwrap();
 
             let swap_token_a =
                 StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
             assert_eq!(swap_token_a.base.amount, deposit_a + token_a_amount);
-
-            let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
-            assert_eq!(token_a.base.amount, 0);
-
-            accounts
-                .deposit_single_token_type_exact_amount_in(
-                    &depositor_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    deposit_b,
-                    pool_amount,
-                )
-                .unwrap();
             let swap_token_b =
                 StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
             assert_eq!(swap_token_b.base.amount, deposit_b + token_b_amount);
-
+            let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
+            assert_eq!(token_a.base.amount, 0);
             let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
             assert_eq!(token_b.base.amount, 0);
-
             let pool_account = StateWithExtensions::<Account>::unpack(&pool_account.data).unwrap();
             let swap_pool_account =
                 StateWithExtensions::<Account>::unpack(&accounts.pool_token_account.data).unwrap();
@@ -5401,7 +5406,7 @@ mod tests {
     #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
     #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
     #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_withdraw_one_exact_out(
+    fn test_withdraw(
         pool_token_program_id: Pubkey,
         token_a_program_id: Pubkey,
         token_b_program_id: Pubkey,
@@ -5427,8 +5432,8 @@ mod tests {
             host_fee_denominator,
         };
 
-        let token_a_amount = 100_000;
-        let token_b_amount = 200_000;
+        let token_a_amount = 1000;
+        let token_b_amount = 2000;
         let curve_type = CurveType::ConstantProduct;
         let swap_curve = SwapCurve {
             curve_type,
@@ -5439,9 +5444,9 @@ mod tests {
         let initial_a = token_a_amount / 10;
         let initial_b = token_b_amount / 10;
         let initial_pool = swap_curve.calculator.new_pool_supply() / 10;
-        let maximum_pool_token_amount = to_u64(initial_pool / 4).unwrap();
-        let destination_a_amount = initial_a / 40;
-        let destination_b_amount = initial_b / 40;
+        let withdraw_amount = initial_pool / 4;
+        let minimum_token_a_amount = initial_a / 40;
+        let minimum_token_b_amount = initial_b / 40;
 
         let mut accounts = SwapAccountInfo::new(
             &user_key,
@@ -5460,21 +5465,24 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
             assert_eq!(
                 Err(ProgramError::UninitializedAccount),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
         }
@@ -5486,8 +5494,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
@@ -5497,14 +5505,17 @@ mod tests {
             accounts.swap_account = wrong_swap_account;
             assert_eq!(
                 Err(ProgramError::IncorrectProgramId),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
             accounts.swap_account = old_swap_account;
@@ -5513,8 +5524,8 @@ mod tests {
         // wrong bump seed for authority_key
         {
             let (
-                _token_a_key,
-                _token_a_account,
+                token_a_key,
+                mut token_a_account,
                 token_b_key,
                 mut token_b_account,
                 pool_key,
@@ -5528,14 +5539,17 @@ mod tests {
             accounts.authority_key = bad_authority_key;
             assert_eq!(
                 Err(SwapError::InvalidProgramAddress.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
             accounts.authority_key = old_authority;
@@ -5544,8 +5558,8 @@ mod tests {
         // not enough pool tokens
         {
             let (
-                _token_a_key,
-                _token_a_account,
+                token_a_key,
+                mut token_a_account,
                 token_b_key,
                 mut token_b_account,
                 pool_key,
@@ -5555,86 +5569,144 @@ mod tests {
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount / 1000,
+                to_u64(withdraw_amount).unwrap() / 2u64,
             );
             assert_eq!(
                 Err(TokenError::InsufficientFunds.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount / 2,
+                    minimum_token_b_amount / 2,
                 )
             );
         }
 
-        // wrong pool token account
+        // wrong token a / b accounts
         {
             let (
                 token_a_key,
                 mut token_a_account,
                 token_b_key,
                 mut token_b_account,
-                _pool_key,
-                pool_account,
+                pool_key,
+                mut pool_account,
             ) = accounts.setup_token_accounts(
                 &user_key,
                 &withdrawer_key,
-                maximum_pool_token_amount,
+                initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                withdraw_amount.try_into().unwrap(),
             );
-            let expected_error: ProgramError = if token_a_account.owner == pool_account.owner {
+            let expected_error: ProgramError = if token_a_account.owner == token_b_account.owner {
                 TokenError::MintMismatch.into()
             } else {
-                SwapError::IncorrectTokenProgramId.into()
+                ProgramError::InvalidAccountData
             };
             assert_eq!(
                 Err(expected_error),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
-                    &token_a_key,
-                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
                     &token_b_key,
                     &mut token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount,
+                    &token_a_key,
+                    &mut token_a_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
         }
 
-        // wrong pool fee account
+        // wrong pool token account
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
-                wrong_pool_key,
-                wrong_pool_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
             ) = accounts.setup_token_accounts(
                 &user_key,
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                withdraw_amount.try_into().unwrap(),
             );
             let (
-                _token_a_key,
-                _token_a_account,
+                wrong_token_a_key,
+                mut wrong_token_a_account,
                 _token_b_key,
                 _token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(
+                _pool_key,
+                pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                withdraw_amount.try_into().unwrap(),
+                initial_b,
+                withdraw_amount.try_into().unwrap(),
+            );
+            let expected_error: ProgramError = if token_a_account.owner == pool_account.owner {
+                TokenError::MintMismatch.into()
+            } else {
+                SwapError::IncorrectTokenProgramId.into()
+            };
+            assert_eq!(
+                Err(expected_error),
+                accounts.withdraw_all_token_types(
+                    &withdrawer_key,
+                    &wrong_token_a_key,
+                    &mut wrong_token_a_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
+                )
+            );
+        }
+
+        // wrong pool fee account
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                wrong_pool_key,
+                wrong_pool_account,
+            ) = accounts.setup_token_accounts(
                 &user_key,
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                withdraw_amount.try_into().unwrap(),
+            );
+            let (
+                _token_a_key,
+                _token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                withdraw_amount.try_into().unwrap(),
             );
             let old_pool_fee_account = accounts.pool_fee_account;
             let old_pool_fee_key = accounts.pool_fee_key;
@@ -5642,15 +5714,18 @@ mod tests {
             accounts.pool_fee_key = wrong_pool_key;
             assert_eq!(
                 Err(SwapError::IncorrectFeeAccount.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
-                )
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
+                ),
             );
             accounts.pool_fee_account = old_pool_fee_account;
             accounts.pool_fee_key = old_pool_fee_key;
@@ -5661,8 +5736,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(
@@ -5670,16 +5745,17 @@ mod tests {
                 &withdrawer_key,
                 0,
                 0,
-                maximum_pool_token_amount,
+                withdraw_amount.try_into().unwrap(),
             );
             let user_transfer_authority_key = Pubkey::new_unique();
             assert_eq!(
                 Err(TokenError::OwnerMismatch.into()),
                 do_process_instruction(
-                    withdraw_single_token_type_exact_amount_out(
+                    withdraw_all_token_types(
                         &SWAP_PROGRAM_ID,
                         &pool_token_program_id,
                         &token_a_program_id,
+                        &token_b_program_id,
                         &accounts.swap_key,
                         &accounts.authority_key,
                         &user_transfer_authority_key,
@@ -5689,10 +5765,13 @@ mod tests {
                         &accounts.token_a_key,
                         &accounts.token_b_key,
                         &token_a_key,
+                        &token_b_key,
                         &accounts.token_a_mint_key,
-                        WithdrawSingleTokenTypeExactAmountOut {
-                            destination_token_amount: destination_a_amount,
-                            maximum_pool_token_amount,
+                        &accounts.token_b_mint_key,
+                        WithdrawAllTokenTypes {
+                            pool_token_amount: withdraw_amount.try_into().unwrap(),
+                            minimum_token_a_amount,
+                            minimum_token_b_amount,
                         }
                     )
                     .unwrap(),
@@ -5705,8 +5784,11 @@ mod tests {
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
                         &mut token_a_account,
+                        &mut token_b_account,
                         &mut accounts.pool_fee_account,
                         &mut accounts.token_a_mint_account,
+                        &mut accounts.token_b_mint_account,
+                        &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                     ],
@@ -5719,8 +5801,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(
@@ -5728,16 +5810,17 @@ mod tests {
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                withdraw_amount.try_into().unwrap(),
             );
             let wrong_key = Pubkey::new_unique();
             assert_eq!(
                 Err(SwapError::IncorrectTokenProgramId.into()),
                 do_process_instruction(
-                    withdraw_single_token_type_exact_amount_out(
+                    withdraw_all_token_types(
                         &SWAP_PROGRAM_ID,
                         &wrong_key,
                         &wrong_key,
+                        &wrong_key,
                         &accounts.swap_key,
                         &accounts.authority_key,
                         &accounts.authority_key,
@@ -5747,11 +5830,14 @@ mod tests {
                         &accounts.token_a_key,
                         &accounts.token_b_key,
                         &token_a_key,
+                        &token_b_key,
                         &accounts.token_a_mint_key,
-                        WithdrawSingleTokenTypeExactAmountOut {
-                            destination_token_amount: destination_a_amount,
-                            maximum_pool_token_amount,
-                        }
+                        &accounts.token_b_mint_key,
+                        WithdrawAllTokenTypes {
+                            pool_token_amount: withdraw_amount.try_into().unwrap(),
+                            minimum_token_a_amount,
+                            minimum_token_b_amount,
+                        },
                     )
                     .unwrap(),
                     vec![
@@ -5763,8 +5849,11 @@ mod tests {
                         &mut accounts.token_a_account,
                         &mut accounts.token_b_account,
                         &mut token_a_account,
+                        &mut token_b_account,
                         &mut accounts.pool_fee_account,
                         &mut accounts.token_a_mint_account,
+                        &mut accounts.token_b_mint_account,
+                        &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                     ],
@@ -5798,14 +5887,17 @@ mod tests {
             // wrong swap token a account
             assert_eq!(
                 Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
 
@@ -5821,14 +5913,17 @@ mod tests {
             // wrong swap token b account
             assert_eq!(
                 Err(SwapError::IncorrectSwapAccount.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
 
@@ -5841,8 +5936,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(
@@ -5866,14 +5961,17 @@ mod tests {
 
             assert_eq!(
                 Err(SwapError::IncorrectPoolMint.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
 
@@ -5881,7 +5979,7 @@ mod tests {
             accounts.pool_mint_account = old_pool_account;
         }
 
-        // slippage exceeded
+        // withdrawing 1 pool token fails because it equates to 0 output tokens
         {
             let (
                 token_a_key,
@@ -5895,32 +5993,71 @@ mod tests {
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                initial_pool.try_into().unwrap(),
+            );
+            assert_eq!(
+                Err(SwapError::ZeroTradingTokens.into()),
+                accounts.withdraw_all_token_types(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    1,
+                    0,
+                    0,
+                )
             );
+        }
 
-            // maximum pool token amount too low
+        // slippage exceeded
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                initial_pool.try_into().unwrap(),
+            );
+            // minimum A amount out too high
             assert_eq!(
                 Err(SwapError::ExceededSlippage.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount / 1000,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount * 10,
+                    minimum_token_b_amount,
                 )
             );
+            // minimum B amount out too high
             assert_eq!(
                 Err(SwapError::ExceededSlippage.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
                     &token_b_key,
                     &mut token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount / 1000,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount * 10,
                 )
             );
         }
@@ -5928,10 +6065,10 @@ mod tests {
         // invalid input: can't use swap pool tokens as destination
         {
             let (
-                _token_a_key,
-                _token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(
@@ -5939,34 +6076,40 @@ mod tests {
                 &withdrawer_key,
                 initial_a,
                 initial_b,
-                maximum_pool_token_amount,
+                initial_pool.try_into().unwrap(),
             );
             let swap_token_a_key = accounts.token_a_key;
             let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
             assert_eq!(
                 Err(SwapError::InvalidInput.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
                     &swap_token_a_key,
                     &mut swap_token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
             let swap_token_b_key = accounts.token_b_key;
             let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
             assert_eq!(
                 Err(SwapError::InvalidInput.into()),
-                accounts.withdraw_single_token_type_exact_amount_out(
+                accounts.withdraw_all_token_types(
                     &withdrawer_key,
                     &pool_key,
                     &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
                     &swap_token_b_key,
                     &mut swap_token_b_account,
-                    destination_b_amount,
-                    maximum_pool_token_amount,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
                 )
             );
         }
@@ -5976,8 +6119,8 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 pool_key,
                 mut pool_account,
             ) = accounts.setup_token_accounts(
@@ -5988,56 +6131,68 @@ mod tests {
                 initial_pool.try_into().unwrap(),
             );
 
+            accounts
+                .withdraw_all_token_types(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    withdraw_amount.try_into().unwrap(),
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
+                )
+                .unwrap();
+
             let swap_token_a =
                 StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
             let swap_token_b =
                 StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
             let pool_mint =
                 StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
-
-            let pool_token_amount = accounts
+            let withdraw_fee = accounts.fees.owner_withdraw_fee(withdraw_amount).unwrap();
+            let results = accounts
                 .swap_curve
-                .withdraw_single_token_type_exact_out(
-                    destination_a_amount.try_into().unwrap(),
-                    swap_token_a.base.amount.try_into().unwrap(),
-                    swap_token_b.base.amount.try_into().unwrap(),
-                    pool_mint.base.supply.try_into().unwrap(),
-                    TradeDirection::AtoB,
-                    &accounts.fees,
-                )
-                .unwrap();
-            let withdraw_fee = accounts.fees.owner_withdraw_fee(pool_token_amount).unwrap();
-
-            accounts
-                .withdraw_single_token_type_exact_amount_out(
-                    &withdrawer_key,
-                    &pool_key,
-                    &mut pool_account,
-                    &token_a_key,
-                    &mut token_a_account,
-                    destination_a_amount,
-                    maximum_pool_token_amount,
+                .calculator
+                .pool_tokens_to_trading_tokens(
+                    withdraw_amount - withdraw_fee,
+                    pool_mint.base.supply.try_into().unwrap(),
+                    swap_token_a.base.amount.try_into().unwrap(),
+                    swap_token_b.base.amount.try_into().unwrap(),
+                    RoundDirection::Floor,
                 )
                 .unwrap();
-
-            let swap_token_a =
-                StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
-
             assert_eq!(
                 swap_token_a.base.amount,
-                token_a_amount - destination_a_amount
+                token_a_amount - to_u64(results.token_a_amount).unwrap()
+            );
+            assert_eq!(
+                swap_token_b.base.amount,
+                token_b_amount - to_u64(results.token_b_amount).unwrap()
             );
             let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
-            assert_eq!(token_a.base.amount, initial_a + destination_a_amount);
-
+            assert_eq!(
+                token_a.base.amount,
+                initial_a + to_u64(results.token_a_amount).unwrap()
+            );
+            let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
+            assert_eq!(
+                token_b.base.amount,
+                initial_b + to_u64(results.token_b_amount).unwrap()
+            );
             let pool_account = StateWithExtensions::<Account>::unpack(&pool_account.data).unwrap();
             assert_eq!(
                 pool_account.base.amount,
-                to_u64(initial_pool - pool_token_amount - withdraw_fee).unwrap()
+                to_u64(initial_pool - withdraw_amount).unwrap()
             );
             let fee_account =
                 StateWithExtensions::<Account>::unpack(&accounts.pool_fee_account.data).unwrap();
-            assert_eq!(fee_account.base.amount, to_u64(withdraw_fee).unwrap());
+            assert_eq!(
+                fee_account.base.amount,
+                TryInto::<u64>::try_into(withdraw_fee).unwrap()
+            );
         }
 
         // correct withdrawal from fee account
@@ -6045,426 +6200,633 @@ mod tests {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
+                token_b_key,
+                mut token_b_account,
                 _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
+                mut _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
 
-            let fee_a_amount = 2;
             let pool_fee_key = accounts.pool_fee_key;
             let mut pool_fee_account = accounts.pool_fee_account.clone();
             let fee_account =
                 StateWithExtensions::<Account>::unpack(&pool_fee_account.data).unwrap();
             let pool_fee_amount = fee_account.base.amount;
 
-            let swap_token_a =
-                StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
-
-            let token_a_amount = swap_token_a.base.amount;
             accounts
-                .withdraw_single_token_type_exact_amount_out(
+                .withdraw_all_token_types(
                     &user_key,
                     &pool_fee_key,
                     &mut pool_fee_account,
                     &token_a_key,
                     &mut token_a_account,
-                    fee_a_amount,
+                    &token_b_key,
+                    &mut token_b_account,
                     pool_fee_amount,
+                    0,
+                    0,
                 )
                 .unwrap();
 
             let swap_token_a =
                 StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
-
-            assert_eq!(swap_token_a.base.amount, token_a_amount - fee_a_amount);
+            let swap_token_b =
+                StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
+            let pool_mint =
+                StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
+            let results = accounts
+                .swap_curve
+                .calculator
+                .pool_tokens_to_trading_tokens(
+                    pool_fee_amount.try_into().unwrap(),
+                    pool_mint.base.supply.try_into().unwrap(),
+                    swap_token_a.base.amount.try_into().unwrap(),
+                    swap_token_b.base.amount.try_into().unwrap(),
+                    RoundDirection::Floor,
+                )
+                .unwrap();
             let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
-            assert_eq!(token_a.base.amount, initial_a + fee_a_amount);
+            assert_eq!(
+                token_a.base.amount,
+                TryInto::<u64>::try_into(results.token_a_amount).unwrap()
+            );
+            let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
+            assert_eq!(
+                token_b.base.amount,
+                TryInto::<u64>::try_into(results.token_b_amount).unwrap()
+            );
         }
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn check_valid_swap_curve(
-        fees: Fees,
-        transfer_fees: SwapTransferFees,
-        curve_type: CurveType,
-        calculator: Arc<dyn CurveCalculator + Send + Sync>,
-        token_a_amount: u64,
-        token_b_amount: u64,
-        pool_token_program_id: &Pubkey,
-        token_a_program_id: &Pubkey,
-        token_b_program_id: &Pubkey,
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_deposit_one_exact_in(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
     ) {
         let user_key = Pubkey::new_unique();
-        let swapper_key = Pubkey::new_unique();
+        let depositor_key = Pubkey::new_unique();
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 2;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 10;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 5;
+        let host_fee_numerator = 20;
+        let host_fee_denominator = 100;
+
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
 
+        let token_a_amount = 1000;
+        let token_b_amount = 9000;
+        let curve_type = CurveType::ConstantProduct;
         let swap_curve = SwapCurve {
             curve_type,
-            calculator,
+            calculator: Arc::new(ConstantProductCurve {}),
         };
 
         let mut accounts = SwapAccountInfo::new(
             &user_key,
-            fees.clone(),
-            transfer_fees,
-            swap_curve.clone(),
+            fees,
+            SwapTransferFees::default(),
+            swap_curve,
             token_a_amount,
             token_b_amount,
-            pool_token_program_id,
-            token_a_program_id,
-            token_b_program_id,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
         );
-        let initial_a = token_a_amount / 5;
-        let initial_b = token_b_amount / 5;
-        accounts.initialize_swap().unwrap();
-
-        let swap_token_a_key = accounts.token_a_key;
-        let swap_token_b_key = accounts.token_b_key;
 
-        let (
-            token_a_key,
-            mut token_a_account,
-            token_b_key,
-            mut token_b_account,
-            _pool_key,
-            _pool_account,
-        ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-        // swap one way
-        let a_to_b_amount = initial_a / 10;
-        let minimum_token_b_amount = 0;
-        let pool_mint =
-            StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
-        let initial_supply = pool_mint.base.supply;
-        accounts
-            .swap(
-                &swapper_key,
-                &token_a_key,
-                &mut token_a_account,
-                &swap_token_a_key,
-                &swap_token_b_key,
-                &token_b_key,
-                &mut token_b_account,
-                a_to_b_amount,
-                minimum_token_b_amount,
-            )
-            .unwrap();
-
-        // tweak values based on transfer fees assessed
-        let token_a_fee = accounts
-            .transfer_fees
-            .token_a
-            .calculate_fee(a_to_b_amount)
-            .unwrap();
-        let actual_a_to_b_amount = a_to_b_amount - token_a_fee;
-        let results = swap_curve
-            .swap(
-                actual_a_to_b_amount.try_into().unwrap(),
-                token_a_amount.try_into().unwrap(),
-                token_b_amount.try_into().unwrap(),
-                TradeDirection::AtoB,
-                &fees,
-            )
-            .unwrap();
+        let deposit_a = token_a_amount / 10;
+        let deposit_b = token_b_amount / 10;
+        let pool_amount = to_u64(INITIAL_SWAP_POOL_AMOUNT / 100).unwrap();
 
-        let swap_token_a =
-            StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
-        let token_a_amount = swap_token_a.base.amount;
-        assert_eq!(
-            token_a_amount,
-            TryInto::<u64>::try_into(results.new_swap_source_amount).unwrap()
-        );
-        let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
-        assert_eq!(token_a.base.amount, initial_a - a_to_b_amount);
+        // swap not initialized
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+        }
 
-        let swap_token_b =
-            StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
-        let token_b_amount = swap_token_b.base.amount;
-        assert_eq!(
-            token_b_amount,
-            TryInto::<u64>::try_into(results.new_swap_destination_amount).unwrap()
-        );
-        let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
-        assert_eq!(
-            token_b.base.amount,
-            initial_b + to_u64(results.destination_amount_swapped).unwrap()
-        );
+        accounts.initialize_swap().unwrap();
 
-        let first_fee = if results.owner_fee > 0 {
-            swap_curve
-                .calculator
-                .withdraw_single_token_type_exact_out(
-                    results.owner_fee,
-                    token_a_amount.try_into().unwrap(),
-                    token_b_amount.try_into().unwrap(),
-                    initial_supply.try_into().unwrap(),
-                    TradeDirection::AtoB,
-                    RoundDirection::Floor,
+        // wrong owner for swap account
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let old_swap_account = accounts.swap_account;
+            let mut wrong_swap_account = old_swap_account.clone();
+            wrong_swap_account.owner = pool_token_program_id;
+            accounts.swap_account = wrong_swap_account;
+            assert_eq!(
+                Err(ProgramError::IncorrectProgramId),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
                 )
-                .unwrap()
-        } else {
-            0
-        };
-        let fee_account =
-            StateWithExtensions::<Account>::unpack(&accounts.pool_fee_account.data).unwrap();
-        assert_eq!(
-            fee_account.base.amount,
-            TryInto::<u64>::try_into(first_fee).unwrap()
-        );
-
-        let first_swap_amount = results.destination_amount_swapped;
-
-        // swap the other way
-        let pool_mint =
-            StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
-        let initial_supply = pool_mint.base.supply;
-
-        let b_to_a_amount = initial_b / 10;
-        let minimum_a_amount = 0;
-        accounts
-            .swap(
-                &swapper_key,
-                &token_b_key,
-                &mut token_b_account,
-                &swap_token_b_key,
-                &swap_token_a_key,
-                &token_a_key,
-                &mut token_a_account,
-                b_to_a_amount,
-                minimum_a_amount,
-            )
-            .unwrap();
-
-        let mut results = swap_curve
-            .swap(
-                b_to_a_amount.try_into().unwrap(),
-                token_b_amount.try_into().unwrap(),
-                token_a_amount.try_into().unwrap(),
-                TradeDirection::BtoA,
-                &fees,
-            )
-            .unwrap();
-        // tweak values based on transfer fees assessed
-        let token_a_fee = accounts
-            .transfer_fees
-            .token_a
-            .calculate_fee(results.destination_amount_swapped.try_into().unwrap())
-            .unwrap();
-        results.destination_amount_swapped -= token_a_fee as u128;
-
-        let swap_token_a =
-            StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
-        let token_a_amount = swap_token_a.base.amount;
-        assert_eq!(
-            token_a_amount,
-            TryInto::<u64>::try_into(results.new_swap_destination_amount).unwrap()
-        );
-        let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
-        assert_eq!(
-            token_a.base.amount,
-            initial_a - a_to_b_amount + to_u64(results.destination_amount_swapped).unwrap()
-        );
+            );
+            accounts.swap_account = old_swap_account;
+        }
 
-        let swap_token_b =
-            StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
-        let token_b_amount = swap_token_b.base.amount;
-        assert_eq!(
-            token_b_amount,
-            TryInto::<u64>::try_into(results.new_swap_source_amount).unwrap()
-        );
-        let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
-        assert_eq!(
-            token_b.base.amount,
-            initial_b + to_u64(first_swap_amount).unwrap()
-                - to_u64(results.source_amount_swapped).unwrap()
-        );
+        // wrong bump seed for authority_key
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let old_authority = accounts.authority_key;
+            let (bad_authority_key, _bump_seed) = Pubkey::find_program_address(
+                &[&accounts.swap_key.to_bytes()[..]],
+                &pool_token_program_id,
+            );
+            accounts.authority_key = bad_authority_key;
+            assert_eq!(
+                Err(SwapError::InvalidProgramAddress.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+            accounts.authority_key = old_authority;
+        }
 
-        let second_fee = if results.owner_fee > 0 {
-            swap_curve
-                .calculator
-                .withdraw_single_token_type_exact_out(
-                    results.owner_fee,
-                    token_a_amount.try_into().unwrap(),
-                    token_b_amount.try_into().unwrap(),
-                    initial_supply.try_into().unwrap(),
-                    TradeDirection::BtoA,
-                    RoundDirection::Floor,
+        // not enough token A / B
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &depositor_key,
+                deposit_a / 2,
+                deposit_b / 2,
+                0,
+            );
+            assert_eq!(
+                Err(TokenError::InsufficientFunds.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    0,
                 )
-                .unwrap()
-        } else {
-            0
-        };
-        let fee_account =
-            StateWithExtensions::<Account>::unpack(&accounts.pool_fee_account.data).unwrap();
-        assert_eq!(
-            fee_account.base.amount,
-            to_u64(first_fee + second_fee).unwrap()
-        );
-    }
+            );
+            assert_eq!(
+                Err(TokenError::InsufficientFunds.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_b,
+                    0,
+                )
+            );
+        }
 
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_valid_swap_curve_all_fees(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
-        // All fees
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 30;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 30;
-        let host_fee_numerator = 20;
-        let host_fee_denominator = 100;
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
+        // wrong pool token account
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let expected_error: ProgramError = if token_b_account.owner == pool_account.owner {
+                TokenError::MintMismatch.into()
+            } else {
+                SwapError::IncorrectTokenProgramId.into()
+            };
+            assert_eq!(
+                Err(expected_error),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+        }
 
-        let token_a_amount = 10_000_000_000;
-        let token_b_amount = 50_000_000_000;
+        // no approval
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let user_transfer_authority_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(TokenError::OwnerMismatch.into()),
+                do_process_instruction(
+                    deposit_single_token_type_exact_amount_in(
+                        &SWAP_PROGRAM_ID,
+                        &token_a_program_id,
+                        &pool_token_program_id,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &user_transfer_authority_key,
+                        &token_a_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &accounts.pool_mint_key,
+                        &pool_key,
+                        &accounts.token_a_mint_key,
+                        DepositSingleTokenTypeExactAmountIn {
+                            source_token_amount: deposit_a,
+                            minimum_pool_token_amount: pool_amount,
+                        },
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut token_a_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut pool_account,
+                        &mut accounts.token_a_mint_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                    ],
+                )
+            );
+        }
 
-        check_valid_swap_curve(
-            fees.clone(),
-            SwapTransferFees::default(),
-            CurveType::ConstantProduct,
-            Arc::new(ConstantProductCurve {}),
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
-        );
-        let token_b_price = 1;
-        check_valid_swap_curve(
-            fees.clone(),
-            SwapTransferFees::default(),
-            CurveType::ConstantPrice,
-            Arc::new(ConstantPriceCurve { token_b_price }),
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
-        );
-        let token_b_offset = 10_000_000_000;
-        check_valid_swap_curve(
-            fees,
-            SwapTransferFees::default(),
-            CurveType::Offset,
-            Arc::new(OffsetCurve { token_b_offset }),
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
-        );
-    }
+        // wrong token program id
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let wrong_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(SwapError::IncorrectTokenProgramId.into()),
+                do_process_instruction(
+                    deposit_single_token_type_exact_amount_in(
+                        &SWAP_PROGRAM_ID,
+                        &wrong_key,
+                        &wrong_key,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &accounts.authority_key,
+                        &token_a_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &accounts.pool_mint_key,
+                        &pool_key,
+                        &accounts.token_a_mint_key,
+                        DepositSingleTokenTypeExactAmountIn {
+                            source_token_amount: deposit_a,
+                            minimum_pool_token_amount: pool_amount,
+                        },
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut token_a_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut pool_account,
+                        &mut accounts.token_a_mint_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                    ],
+                )
+            );
+        }
 
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_valid_swap_curve_trade_fee_only(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 0;
-        let owner_trade_fee_denominator = 0;
-        let owner_withdraw_fee_numerator = 0;
-        let owner_withdraw_fee_denominator = 0;
-        let host_fee_numerator = 0;
-        let host_fee_denominator = 0;
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
+        // wrong swap token accounts
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
 
-        let token_a_amount = 10_000_000_000;
-        let token_b_amount = 50_000_000_000;
+            let old_a_key = accounts.token_a_key;
+            let old_a_account = accounts.token_a_account;
 
-        check_valid_swap_curve(
-            fees.clone(),
-            SwapTransferFees::default(),
-            CurveType::ConstantProduct,
-            Arc::new(ConstantProductCurve {}),
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
-        );
-        let token_b_price = 10_000;
-        check_valid_swap_curve(
-            fees.clone(),
-            SwapTransferFees::default(),
-            CurveType::ConstantPrice,
-            Arc::new(ConstantPriceCurve { token_b_price }),
-            token_a_amount,
-            token_b_amount / token_b_price,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
-        );
-        let token_b_offset = 1;
-        check_valid_swap_curve(
-            fees,
-            SwapTransferFees::default(),
-            CurveType::Offset,
-            Arc::new(OffsetCurve { token_b_offset }),
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
-        );
-    }
+            accounts.token_a_key = token_a_key;
+            accounts.token_a_account = token_a_account.clone();
 
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_valid_swap_with_fee_constraints(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
-        let owner_key = Pubkey::new_unique();
+            // wrong swap token a account
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
 
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 30;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 30;
-        let host_fee_numerator = 10;
-        let host_fee_denominator = 100;
+            accounts.token_a_key = old_a_key;
+            accounts.token_a_account = old_a_account;
 
-        let token_a_amount = 1_000_000;
-        let token_b_amount = 5_000_000;
+            let old_b_key = accounts.token_b_key;
+            let old_b_account = accounts.token_b_account;
 
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
+            accounts.token_b_key = token_b_key;
+            accounts.token_b_account = token_b_account;
+
+            // wrong swap token b account
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+
+            accounts.token_b_key = old_b_key;
+            accounts.token_b_account = old_b_account;
+        }
+
+        // wrong mint
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let (pool_mint_key, pool_mint_account) = create_mint(
+                &pool_token_program_id,
+                &accounts.authority_key,
+                None,
+                None,
+                &TransferFee::default(),
+            );
+            let old_pool_key = accounts.pool_mint_key;
+            let old_pool_account = accounts.pool_mint_account;
+            accounts.pool_mint_key = pool_mint_key;
+            accounts.pool_mint_account = pool_mint_account;
+
+            assert_eq!(
+                Err(SwapError::IncorrectPoolMint.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+
+            accounts.pool_mint_key = old_pool_key;
+            accounts.pool_mint_account = old_pool_account;
+        }
+
+        // slippage exceeded
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            // minimum pool amount too high
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a / 10,
+                    pool_amount,
+                )
+            );
+            // minimum pool amount too high
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_b / 10,
+                    pool_amount,
+                )
+            );
+        }
+
+        // invalid input: can't use swap pool tokens as source
+        {
+            let (
+                _token_a_key,
+                _token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            let swap_token_a_key = accounts.token_a_key;
+            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
+            let swap_token_b_key = accounts.token_b_key;
+            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
+            let authority_key = accounts.authority_key;
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &authority_key,
+                    &swap_token_a_key,
+                    &mut swap_token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+            );
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.deposit_single_token_type_exact_amount_in(
+                    &authority_key,
+                    &swap_token_b_key,
+                    &mut swap_token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_b,
+                    pool_amount,
+                )
+            );
+        }
+
+        // correctly deposit
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &depositor_key, deposit_a, deposit_b, 0);
+            accounts
+                .deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_a,
+                    pool_amount,
+                )
+                .unwrap();
+
+            let swap_token_a =
+                StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
+            assert_eq!(swap_token_a.base.amount, deposit_a + token_a_amount);
+
+            let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
+            assert_eq!(token_a.base.amount, 0);
+
+            accounts
+                .deposit_single_token_type_exact_amount_in(
+                    &depositor_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    deposit_b,
+                    pool_amount,
+                )
+                .unwrap();
+            let swap_token_b =
+                StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
+            assert_eq!(swap_token_b.base.amount, deposit_b + token_b_amount);
+
+            let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
+            assert_eq!(token_b.base.amount, 0);
+
+            let pool_account = StateWithExtensions::<Account>::unpack(&pool_account.data).unwrap();
+            let swap_pool_account =
+                StateWithExtensions::<Account>::unpack(&accounts.pool_token_account.data).unwrap();
+            let pool_mint =
+                StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
+            assert_eq!(
+                pool_mint.base.supply,
+                pool_account.base.amount + swap_pool_account.base.amount
+            );
+        }
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_withdraw_one_exact_out(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let user_key = Pubkey::new_unique();
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 2;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 10;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 5;
+        let host_fee_numerator = 7;
+        let host_fee_denominator = 100;
+
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
             owner_trade_fee_denominator,
             owner_withdraw_fee_numerator,
             owner_withdraw_fee_denominator,
@@ -6472,22 +6834,25 @@ mod tests {
             host_fee_denominator,
         };
 
-        let curve = ConstantProductCurve {};
+        let token_a_amount = 100_000;
+        let token_b_amount = 200_000;
+        let curve_type = CurveType::ConstantProduct;
         let swap_curve = SwapCurve {
-            curve_type: CurveType::ConstantProduct,
-            calculator: Arc::new(curve),
+            curve_type,
+            calculator: Arc::new(ConstantProductCurve {}),
         };
 
-        let owner_key_str = &owner_key.to_string();
-        let valid_curve_types = &[CurveType::ConstantProduct];
-        let constraints = Some(SwapConstraints {
-            owner_key: owner_key_str,
-            valid_curve_types,
-            fees: &fees,
-        });
+        let withdrawer_key = Pubkey::new_unique();
+        let initial_a = token_a_amount / 10;
+        let initial_b = token_b_amount / 10;
+        let initial_pool = swap_curve.calculator.new_pool_supply() / 10;
+        let maximum_pool_token_amount = to_u64(initial_pool / 4).unwrap();
+        let destination_a_amount = initial_a / 40;
+        let destination_b_amount = initial_b / 40;
+
         let mut accounts = SwapAccountInfo::new(
-            &owner_key,
-            fees.clone(),
+            &user_key,
+            fees,
             SwapTransferFees::default(),
             swap_curve,
             token_a_amount,
@@ -6497,239 +6862,71 @@ mod tests {
             &token_b_program_id,
         );
 
-        // initialize swap
-        do_process_instruction_with_fee_constraints(
-            initialize(
-                &SWAP_PROGRAM_ID,
-                &pool_token_program_id,
-                &accounts.swap_key,
-                &accounts.authority_key,
-                &accounts.token_a_key,
-                &accounts.token_b_key,
-                &accounts.pool_mint_key,
-                &accounts.pool_fee_key,
-                &accounts.pool_token_key,
-                accounts.fees.clone(),
-                accounts.swap_curve.clone(),
-            )
-            .unwrap(),
-            vec![
-                &mut accounts.swap_account,
-                &mut SolanaAccount::default(),
-                &mut accounts.token_a_account,
-                &mut accounts.token_b_account,
-                &mut accounts.pool_mint_account,
-                &mut accounts.pool_fee_account,
-                &mut accounts.pool_token_account,
-                &mut SolanaAccount::default(),
-            ],
-            &constraints,
-        )
-        .unwrap();
-
-        let authority_key = accounts.authority_key;
-
-        let (
-            token_a_key,
-            mut token_a_account,
-            token_b_key,
-            mut token_b_account,
-            pool_key,
-            mut pool_account,
-        ) = accounts.setup_token_accounts(
-            &owner_key,
-            &authority_key,
-            token_a_amount,
-            token_b_amount,
-            0,
-        );
-
-        let amount_in = token_a_amount / 2;
-        let minimum_amount_out = 0;
-
-        // perform the swap
-        do_process_instruction_with_fee_constraints(
-            swap(
-                &SWAP_PROGRAM_ID,
-                &token_a_program_id,
-                &token_b_program_id,
-                &pool_token_program_id,
-                &accounts.swap_key,
-                &accounts.authority_key,
-                &accounts.authority_key,
-                &token_a_key,
-                &accounts.token_a_key,
-                &accounts.token_b_key,
-                &token_b_key,
-                &accounts.pool_mint_key,
-                &accounts.pool_fee_key,
-                &accounts.token_a_mint_key,
-                &accounts.token_b_mint_key,
-                Some(&pool_key),
-                Swap {
-                    amount_in,
-                    minimum_amount_out,
-                },
-            )
-            .unwrap(),
-            vec![
-                &mut accounts.swap_account,
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
-                &mut token_a_account,
-                &mut accounts.token_a_account,
-                &mut accounts.token_b_account,
-                &mut token_b_account,
-                &mut accounts.pool_mint_account,
-                &mut accounts.pool_fee_account,
-                &mut accounts.token_a_mint_account,
-                &mut accounts.token_b_mint_account,
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
-                &mut pool_account,
-            ],
-            &constraints,
-        )
-        .unwrap();
-
-        // check that fees were taken in the host fee account
-        let host_fee_account = StateWithExtensions::<Account>::unpack(&pool_account.data).unwrap();
-        let owner_fee_account =
-            StateWithExtensions::<Account>::unpack(&accounts.pool_fee_account.data).unwrap();
-        let total_fee = owner_fee_account.base.amount * host_fee_denominator
-            / (host_fee_denominator - host_fee_numerator);
-        assert_eq!(
-            total_fee,
-            host_fee_account.base.amount + owner_fee_account.base.amount
-        );
-    }
-
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_invalid_swap(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
-        let user_key = Pubkey::new_unique();
-        let swapper_key = Pubkey::new_unique();
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 4;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 10;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 5;
-        let host_fee_numerator = 9;
-        let host_fee_denominator = 100;
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
-
-        let token_a_amount = 1000;
-        let token_b_amount = 5000;
-        let curve_type = CurveType::ConstantProduct;
-        let swap_curve = SwapCurve {
-            curve_type,
-            calculator: Arc::new(ConstantProductCurve {}),
-        };
-        let mut accounts = SwapAccountInfo::new(
-            &user_key,
-            fees,
-            SwapTransferFees::default(),
-            swap_curve,
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
-        );
-
-        let initial_a = token_a_amount / 5;
-        let initial_b = token_b_amount / 5;
-        let minimum_token_b_amount = initial_b / 2;
-
-        let swap_token_a_key = accounts.token_a_key;
-        let swap_token_b_key = accounts.token_b_key;
-
         // swap not initialized
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
             assert_eq!(
                 Err(ProgramError::UninitializedAccount),
-                accounts.swap(
-                    &swapper_key,
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
                 )
             );
         }
 
         accounts.initialize_swap().unwrap();
 
-        // wrong swap account program id
+        // wrong owner for swap account
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
             let old_swap_account = accounts.swap_account;
             let mut wrong_swap_account = old_swap_account.clone();
             wrong_swap_account.owner = pool_token_program_id;
             accounts.swap_account = wrong_swap_account;
             assert_eq!(
                 Err(ProgramError::IncorrectProgramId),
-                accounts.swap(
-                    &swapper_key,
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
                 )
             );
             accounts.swap_account = old_swap_account;
         }
 
-        // wrong bump seed
+        // wrong bump seed for authority_key
         {
             let (
-                token_a_key,
-                mut token_a_account,
+                _token_a_key,
+                _token_a_account,
                 token_b_key,
                 mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
             let old_authority = accounts.authority_key;
             let (bad_authority_key, _bump_seed) = Pubkey::find_program_address(
                 &[&accounts.swap_key.to_bytes()[..]],
@@ -6738,79 +6935,50 @@ mod tests {
             accounts.authority_key = bad_authority_key;
             assert_eq!(
                 Err(SwapError::InvalidProgramAddress.into()),
-                accounts.swap(
-                    &swapper_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
                     &token_b_key,
                     &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                    destination_b_amount,
+                    maximum_pool_token_amount,
                 )
             );
             accounts.authority_key = old_authority;
         }
 
-        // wrong token program id
+        // not enough pool tokens
         {
             let (
-                token_a_key,
-                mut token_a_account,
+                _token_a_key,
+                _token_a_account,
                 token_b_key,
                 mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            let wrong_program_id = Pubkey::new_unique();
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount / 1000,
+            );
             assert_eq!(
-                Err(SwapError::IncorrectTokenProgramId.into()),
-                do_process_instruction(
-                    swap(
-                        &SWAP_PROGRAM_ID,
-                        &wrong_program_id,
-                        &wrong_program_id,
-                        &wrong_program_id,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.authority_key,
-                        &token_a_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &token_b_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &accounts.token_a_mint_key,
-                        &accounts.token_b_mint_key,
-                        None,
-                        Swap {
-                            amount_in: initial_a,
-                            minimum_amount_out: minimum_token_b_amount,
-                        },
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                        &mut token_a_account,
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
-                        &mut accounts.token_a_mint_account,
-                        &mut accounts.token_b_mint_account,
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                    ],
-                ),
+                Err(TokenError::InsufficientFunds.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    destination_b_amount,
+                    maximum_pool_token_amount,
+                )
             );
         }
 
-        // not enough token a to swap
+        // wrong pool token account
         {
             let (
                 token_a_key,
@@ -6818,143 +6986,279 @@ mod tests {
                 token_b_key,
                 mut token_b_account,
                 _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+                pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                maximum_pool_token_amount,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+            let expected_error: ProgramError = if token_a_account.owner == pool_account.owner {
+                TokenError::MintMismatch.into()
+            } else {
+                SwapError::IncorrectTokenProgramId.into()
+            };
             assert_eq!(
-                Err(TokenError::InsufficientFunds.into()),
-                accounts.swap(
-                    &swapper_key,
+                Err(expected_error),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
                     &token_a_key,
                     &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
                     &token_b_key,
                     &mut token_b_account,
-                    initial_a * 2,
-                    minimum_token_b_amount * 2,
+                    destination_b_amount,
+                    maximum_pool_token_amount,
                 )
             );
         }
 
-        // wrong swap token A / B accounts
+        // wrong pool fee account
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            let user_transfer_key = Pubkey::new_unique();
+                _token_b_key,
+                _token_b_account,
+                wrong_pool_key,
+                wrong_pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+            let (
+                _token_a_key,
+                _token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+            let old_pool_fee_account = accounts.pool_fee_account;
+            let old_pool_fee_key = accounts.pool_fee_key;
+            accounts.pool_fee_account = wrong_pool_account;
+            accounts.pool_fee_key = wrong_pool_key;
             assert_eq!(
-                Err(SwapError::IncorrectSwapAccount.into()),
+                Err(SwapError::IncorrectFeeAccount.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_a_key,
+                    &mut token_a_account,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+            accounts.pool_fee_account = old_pool_fee_account;
+            accounts.pool_fee_key = old_pool_fee_key;
+        }
+
+        // no approval
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                0,
+                0,
+                maximum_pool_token_amount,
+            );
+            let user_transfer_authority_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(TokenError::OwnerMismatch.into()),
                 do_process_instruction(
-                    swap(
+                    withdraw_single_token_type_exact_amount_out(
                         &SWAP_PROGRAM_ID,
-                        &token_a_program_id,
-                        &token_b_program_id,
                         &pool_token_program_id,
+                        &token_a_program_id,
                         &accounts.swap_key,
                         &accounts.authority_key,
-                        &user_transfer_key,
-                        &token_a_key,
-                        &token_a_key,
-                        &token_b_key,
-                        &token_b_key,
+                        &user_transfer_authority_key,
                         &accounts.pool_mint_key,
                         &accounts.pool_fee_key,
+                        &pool_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &token_a_key,
                         &accounts.token_a_mint_key,
-                        &accounts.token_b_mint_key,
-                        None,
-                        Swap {
-                            amount_in: initial_a,
-                            minimum_amount_out: minimum_token_b_amount,
-                        },
+                        WithdrawSingleTokenTypeExactAmountOut {
+                            destination_token_amount: destination_a_amount,
+                            maximum_pool_token_amount,
+                        }
                     )
                     .unwrap(),
                     vec![
                         &mut accounts.swap_account,
                         &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
-                        &mut token_a_account.clone(),
-                        &mut token_a_account,
-                        &mut token_b_account.clone(),
-                        &mut token_b_account,
                         &mut accounts.pool_mint_account,
+                        &mut pool_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut token_a_account,
                         &mut accounts.pool_fee_account,
                         &mut accounts.token_a_mint_account,
-                        &mut accounts.token_b_mint_account,
-                        &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                         &mut SolanaAccount::default(),
                     ],
-                ),
+                )
             );
         }
 
-        // wrong user token A / B accounts
+        // wrong token program id
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+            let wrong_key = Pubkey::new_unique();
             assert_eq!(
-                Err(TokenError::MintMismatch.into()),
-                accounts.swap(
-                    &swapper_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                Err(SwapError::IncorrectTokenProgramId.into()),
+                do_process_instruction(
+                    withdraw_single_token_type_exact_amount_out(
+                        &SWAP_PROGRAM_ID,
+                        &wrong_key,
+                        &wrong_key,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &accounts.authority_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &pool_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &token_a_key,
+                        &accounts.token_a_mint_key,
+                        WithdrawSingleTokenTypeExactAmountOut {
+                            destination_token_amount: destination_a_amount,
+                            maximum_pool_token_amount,
+                        }
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut accounts.pool_mint_account,
+                        &mut pool_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut token_a_account,
+                        &mut accounts.pool_fee_account,
+                        &mut accounts.token_a_mint_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                    ],
                 )
             );
         }
 
-        // swap from a to a
+        // wrong swap token accounts
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                _token_b_key,
-                _token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                initial_pool.try_into().unwrap(),
+            );
+
+            let old_a_key = accounts.token_a_key;
+            let old_a_account = accounts.token_a_account;
+
+            accounts.token_a_key = token_a_key;
+            accounts.token_a_account = token_a_account.clone();
+
+            // wrong swap token a account
             assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.swap(
-                    &swapper_key,
-                    &token_a_key,
-                    &mut token_a_account.clone(),
-                    &swap_token_a_key,
-                    &swap_token_a_key,
+                Err(SwapError::IncorrectSwapAccount.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
+                )
+            );
+
+            accounts.token_a_key = old_a_key;
+            accounts.token_a_account = old_a_account;
+
+            let old_b_key = accounts.token_b_key;
+            let old_b_account = accounts.token_b_account;
+
+            accounts.token_b_key = token_b_key;
+            accounts.token_b_account = token_b_account.clone();
+
+            // wrong swap token b account
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    destination_b_amount,
+                    maximum_pool_token_amount,
                 )
             );
+
+            accounts.token_b_key = old_b_key;
+            accounts.token_b_account = old_b_account;
         }
 
-        // incorrect mint provided
+        // wrong mint
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                initial_pool.try_into().unwrap(),
+            );
             let (pool_mint_key, pool_mint_account) = create_mint(
                 &pool_token_program_id,
                 &accounts.authority_key,
@@ -6969,16 +7273,14 @@ mod tests {
 
             assert_eq!(
                 Err(SwapError::IncorrectPoolMint.into()),
-                accounts.swap(
-                    &swapper_key,
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
                 )
             );
 
@@ -6986,319 +7288,1453 @@ mod tests {
             accounts.pool_mint_account = old_pool_account;
         }
 
-        // incorrect fee account provided
+        // slippage exceeded
         {
             let (
                 token_a_key,
                 mut token_a_account,
                 token_b_key,
                 mut token_b_account,
-                wrong_pool_key,
-                wrong_pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            let old_pool_fee_account = accounts.pool_fee_account;
-            let old_pool_fee_key = accounts.pool_fee_key;
-            accounts.pool_fee_account = wrong_pool_account;
-            accounts.pool_fee_key = wrong_pool_key;
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+
+            // maximum pool token amount too low
             assert_eq!(
-                Err(SwapError::IncorrectFeeAccount.into()),
-                accounts.swap(
-                    &swapper_key,
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
+                    destination_a_amount,
+                    maximum_pool_token_amount / 1000,
+                )
+            );
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
                     &token_b_key,
                     &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
+                    destination_b_amount,
+                    maximum_pool_token_amount / 1000,
                 )
             );
-            accounts.pool_fee_account = old_pool_fee_account;
-            accounts.pool_fee_key = old_pool_fee_key;
         }
 
-        // no approval
+        // invalid input: can't use swap pool tokens as destination
         {
             let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            let user_transfer_key = Pubkey::new_unique();
+                _token_a_key,
+                _token_a_account,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                maximum_pool_token_amount,
+            );
+            let swap_token_a_key = accounts.token_a_key;
+            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
             assert_eq!(
-                Err(TokenError::OwnerMismatch.into()),
-                do_process_instruction(
-                    swap(
-                        &SWAP_PROGRAM_ID,
-                        &token_a_program_id,
-                        &token_b_program_id,
-                        &pool_token_program_id,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &user_transfer_key,
-                        &token_a_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &token_b_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &accounts.token_a_mint_key,
-                        &accounts.token_b_mint_key,
-                        None,
-                        Swap {
-                            amount_in: initial_a,
-                            minimum_amount_out: minimum_token_b_amount,
-                        },
-                    )
-                    .unwrap(),
-                    vec![
-                        &mut accounts.swap_account,
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                        &mut token_a_account,
-                        &mut accounts.token_a_account,
-                        &mut accounts.token_b_account,
-                        &mut token_b_account,
-                        &mut accounts.pool_mint_account,
-                        &mut accounts.pool_fee_account,
-                        &mut accounts.token_a_mint_account,
-                        &mut accounts.token_b_mint_account,
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                        &mut SolanaAccount::default(),
-                    ],
-                ),
+                Err(SwapError::InvalidInput.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
+                    &swap_token_a_key,
+                    &mut swap_token_a_account,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
+                )
             );
-        }
-
-        // output token value 0
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let swap_token_b_key = accounts.token_b_key;
+            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
             assert_eq!(
-                Err(SwapError::ZeroTradingTokens.into()),
-                accounts.swap(
-                    &swapper_key,
-                    &token_b_key,
-                    &mut token_b_account,
+                Err(SwapError::InvalidInput.into()),
+                accounts.withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
                     &swap_token_b_key,
-                    &swap_token_a_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    1,
-                    1,
+                    &mut swap_token_b_account,
+                    destination_b_amount,
+                    maximum_pool_token_amount,
                 )
             );
         }
 
-        // slippage exceeded: minimum out amount too high
+        // correct withdrawal
         {
             let (
                 token_a_key,
                 mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            assert_eq!(
-                Err(SwapError::ExceededSlippage.into()),
-                accounts.swap(
-                    &swapper_key,
+                _token_b_key,
+                _token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(
+                &user_key,
+                &withdrawer_key,
+                initial_a,
+                initial_b,
+                initial_pool.try_into().unwrap(),
+            );
+
+            let swap_token_a =
+                StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
+            let swap_token_b =
+                StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
+            let pool_mint =
+                StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
+
+            let pool_token_amount = accounts
+                .swap_curve
+                .withdraw_single_token_type_exact_out(
+                    destination_a_amount.try_into().unwrap(),
+                    swap_token_a.base.amount.try_into().unwrap(),
+                    swap_token_b.base.amount.try_into().unwrap(),
+                    pool_mint.base.supply.try_into().unwrap(),
+                    TradeDirection::AtoB,
+                    &accounts.fees,
+                )
+                .unwrap();
+            let withdraw_fee = accounts.fees.owner_withdraw_fee(pool_token_amount).unwrap();
+
+            accounts
+                .withdraw_single_token_type_exact_amount_out(
+                    &withdrawer_key,
+                    &pool_key,
+                    &mut pool_account,
                     &token_a_key,
                     &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount * 2,
+                    destination_a_amount,
+                    maximum_pool_token_amount,
                 )
-            );
-        }
+                .unwrap();
+
+            let swap_token_a =
+                StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
 
-        // invalid input: can't use swap pool as user source / dest
-        {
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
-            let authority_key = accounts.authority_key;
             assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.swap(
-                    &authority_key,
-                    &swap_token_a_key,
-                    &mut swap_token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &token_b_key,
-                    &mut token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
-                )
+                swap_token_a.base.amount,
+                token_a_amount - destination_a_amount
             );
-            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
+            let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
+            assert_eq!(token_a.base.amount, initial_a + destination_a_amount);
+
+            let pool_account = StateWithExtensions::<Account>::unpack(&pool_account.data).unwrap();
             assert_eq!(
-                Err(SwapError::InvalidInput.into()),
-                accounts.swap(
-                    &swapper_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &swap_token_a_key,
-                    &swap_token_b_key,
-                    &swap_token_b_key,
-                    &mut swap_token_b_account,
-                    initial_a,
-                    minimum_token_b_amount,
-                )
+                pool_account.base.amount,
+                to_u64(initial_pool - pool_token_amount - withdraw_fee).unwrap()
             );
+            let fee_account =
+                StateWithExtensions::<Account>::unpack(&accounts.pool_fee_account.data).unwrap();
+            assert_eq!(fee_account.base.amount, to_u64(withdraw_fee).unwrap());
         }
 
-        // still correct: constraint specified, no host fee account
+        // correct withdrawal from fee account
         {
-            let authority_key = accounts.authority_key;
             let (
                 token_a_key,
                 mut token_a_account,
-                token_b_key,
-                mut token_b_account,
+                _token_b_key,
+                _token_b_account,
                 _pool_key,
                 _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
-            let owner_key = &swapper_key.to_string();
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let constraints = Some(SwapConstraints {
-                owner_key,
-                valid_curve_types: &[],
-                fees: &fees,
-            });
-            do_process_instruction_with_fee_constraints(
-                swap(
-                    &SWAP_PROGRAM_ID,
-                    &token_a_program_id,
-                    &token_b_program_id,
-                    &pool_token_program_id,
-                    &accounts.swap_key,
-                    &accounts.authority_key,
-                    &accounts.authority_key,
+            ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, initial_a, initial_b, 0);
+
+            let fee_a_amount = 2;
+            let pool_fee_key = accounts.pool_fee_key;
+            let mut pool_fee_account = accounts.pool_fee_account.clone();
+            let fee_account =
+                StateWithExtensions::<Account>::unpack(&pool_fee_account.data).unwrap();
+            let pool_fee_amount = fee_account.base.amount;
+
+            let swap_token_a =
+                StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
+
+            let token_a_amount = swap_token_a.base.amount;
+            accounts
+                .withdraw_single_token_type_exact_amount_out(
+                    &user_key,
+                    &pool_fee_key,
+                    &mut pool_fee_account,
                     &token_a_key,
-                    &accounts.token_a_key,
-                    &accounts.token_b_key,
-                    &token_b_key,
-                    &accounts.pool_mint_key,
-                    &accounts.pool_fee_key,
-                    &accounts.token_a_mint_key,
-                    &accounts.token_b_mint_key,
-                    None,
-                    Swap {
-                        amount_in: initial_a,
-                        minimum_amount_out: minimum_token_b_amount,
-                    },
-                )
-                .unwrap(),
-                vec![
-                    &mut accounts.swap_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
                     &mut token_a_account,
-                    &mut accounts.token_a_account,
-                    &mut accounts.token_b_account,
-                    &mut token_b_account,
-                    &mut accounts.pool_mint_account,
-                    &mut accounts.pool_fee_account,
-                    &mut accounts.token_a_mint_account,
-                    &mut accounts.token_b_mint_account,
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                    &mut SolanaAccount::default(),
-                ],
-                &constraints,
-            )
-            .unwrap();
+                    fee_a_amount,
+                    pool_fee_amount,
+                )
+                .unwrap();
+
+            let swap_token_a =
+                StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
+
+            assert_eq!(swap_token_a.base.amount, token_a_amount - fee_a_amount);
+            let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
+            assert_eq!(token_a.base.amount, initial_a + fee_a_amount);
         }
+    }
 
-        // invalid mint for host fee account
-        {
-            let authority_key = accounts.authority_key;
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
-            let (
-                bad_token_a_key,
-                mut bad_token_a_account,
-                _token_b_key,
-                mut _token_b_account,
-                _pool_key,
-                _pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
-            let owner_key = &swapper_key.to_string();
-            let fees = Fees {
-                trade_fee_numerator,
-                trade_fee_denominator,
-                owner_trade_fee_numerator,
-                owner_trade_fee_denominator,
-                owner_withdraw_fee_numerator,
-                owner_withdraw_fee_denominator,
-                host_fee_numerator,
-                host_fee_denominator,
-            };
-            let constraints = Some(SwapConstraints {
-                owner_key,
-                valid_curve_types: &[],
-                fees: &fees,
-            });
-            assert_eq!(
-                Err(SwapError::IncorrectPoolMint.into()),
-                do_process_instruction_with_fee_constraints(
-                    swap(
-                        &SWAP_PROGRAM_ID,
-                        &token_a_program_id,
-                        &token_b_program_id,
-                        &pool_token_program_id,
-                        &accounts.swap_key,
-                        &accounts.authority_key,
-                        &accounts.authority_key,
-                        &token_a_key,
-                        &accounts.token_a_key,
-                        &accounts.token_b_key,
-                        &token_b_key,
-                        &accounts.pool_mint_key,
-                        &accounts.pool_fee_key,
-                        &accounts.token_a_mint_key,
-                        &accounts.token_b_mint_key,
-                        Some(&bad_token_a_key),
-                        Swap {
-                            amount_in: initial_a,
+    #[allow(clippy::too_many_arguments)]
+    fn check_valid_swap_curve(
+        fees: Fees,
+        transfer_fees: SwapTransferFees,
+        curve_type: CurveType,
+        calculator: Arc<dyn CurveCalculator + Send + Sync>,
+        token_a_amount: u64,
+        token_b_amount: u64,
+        pool_token_program_id: &Pubkey,
+        token_a_program_id: &Pubkey,
+        token_b_program_id: &Pubkey,
+    ) {
+        let user_key = Pubkey::new_unique();
+        let swapper_key = Pubkey::new_unique();
+
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator,
+        };
+
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees.clone(),
+            transfer_fees,
+            swap_curve.clone(),
+            token_a_amount,
+            token_b_amount,
+            pool_token_program_id,
+            token_a_program_id,
+            token_b_program_id,
+        );
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
+        accounts.initialize_swap().unwrap();
+
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+        // swap one way
+        let a_to_b_amount = initial_a / 10;
+        let minimum_token_b_amount = 0;
+        let pool_mint =
+            StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
+        let initial_supply = pool_mint.base.supply;
+        accounts
+            .swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                a_to_b_amount,
+                minimum_token_b_amount,
+            )
+            .unwrap();
+
+        // tweak values based on transfer fees assessed
+        let token_a_fee = accounts
+            .transfer_fees
+            .token_a
+            .calculate_fee(a_to_b_amount)
+            .unwrap();
+        let actual_a_to_b_amount = a_to_b_amount - token_a_fee;
+        let results = swap_curve
+            .swap(
+                actual_a_to_b_amount.try_into().unwrap(),
+                token_a_amount.try_into().unwrap(),
+                token_b_amount.try_into().unwrap(),
+                TradeDirection::AtoB,
+                &fees,
+            )
+            .unwrap();
+
+        let swap_token_a =
+            StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
+        let token_a_amount = swap_token_a.base.amount;
+        assert_eq!(
+            token_a_amount,
+            TryInto::<u64>::try_into(results.new_swap_source_amount).unwrap()
+        );
+        let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
+        assert_eq!(token_a.base.amount, initial_a - a_to_b_amount);
+
+        let swap_token_b =
+            StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
+        let token_b_amount = swap_token_b.base.amount;
+        assert_eq!(
+            token_b_amount,
+            TryInto::<u64>::try_into(results.new_swap_destination_amount).unwrap()
+        );
+        let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
+        assert_eq!(
+            token_b.base.amount,
+            initial_b + to_u64(results.destination_amount_swapped).unwrap()
+        );
+
+        let first_fee = if results.owner_fee > 0 {
+            swap_curve
+                .calculator
+                .withdraw_single_token_type_exact_out(
+                    results.owner_fee,
+                    token_a_amount.try_into().unwrap(),
+                    token_b_amount.try_into().unwrap(),
+                    initial_supply.try_into().unwrap(),
+                    TradeDirection::AtoB,
+                    RoundDirection::Floor,
+                )
+                .unwrap()
+        } else {
+            0
+        };
+        let fee_account =
+            StateWithExtensions::<Account>::unpack(&accounts.pool_fee_account.data).unwrap();
+        assert_eq!(
+            fee_account.base.amount,
+            TryInto::<u64>::try_into(first_fee).unwrap()
+        );
+
+        let first_swap_amount = results.destination_amount_swapped;
+
+        // swap the other way
+        let pool_mint =
+            StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
+        let initial_supply = pool_mint.base.supply;
+
+        let b_to_a_amount = initial_b / 10;
+        let minimum_a_amount = 0;
+        accounts
+            .swap(
+                &swapper_key,
+                &token_b_key,
+                &mut token_b_account,
+                &swap_token_b_key,
+                &swap_token_a_key,
+                &token_a_key,
+                &mut token_a_account,
+                b_to_a_amount,
+                minimum_a_amount,
+            )
+            .unwrap();
+
+        let mut results = swap_curve
+            .swap(
+                b_to_a_amount.try_into().unwrap(),
+                token_b_amount.try_into().unwrap(),
+                token_a_amount.try_into().unwrap(),
+                TradeDirection::BtoA,
+                &fees,
+            )
+            .unwrap();
+        // tweak values based on transfer fees assessed
+        let token_a_fee = accounts
+            .transfer_fees
+            .token_a
+            .calculate_fee(results.destination_amount_swapped.try_into().unwrap())
+            .unwrap();
+        results.destination_amount_swapped -= token_a_fee as u128;
+
+        let swap_token_a =
+            StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
+        let token_a_amount = swap_token_a.base.amount;
+        assert_eq!(
+            token_a_amount,
+            TryInto::<u64>::try_into(results.new_swap_destination_amount).unwrap()
+        );
+        let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
+        assert_eq!(
+            token_a.base.amount,
+            initial_a - a_to_b_amount + to_u64(results.destination_amount_swapped).unwrap()
+        );
+
+        let swap_token_b =
+            StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
+        let token_b_amount = swap_token_b.base.amount;
+        assert_eq!(
+            token_b_amount,
+            TryInto::<u64>::try_into(results.new_swap_source_amount).unwrap()
+        );
+        let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
+        assert_eq!(
+            token_b.base.amount,
+            initial_b + to_u64(first_swap_amount).unwrap()
+                - to_u64(results.source_amount_swapped).unwrap()
+        );
+
+        let second_fee = if results.owner_fee > 0 {
+            swap_curve
+                .calculator
+                .withdraw_single_token_type_exact_out(
+                    results.owner_fee,
+                    token_a_amount.try_into().unwrap(),
+                    token_b_amount.try_into().unwrap(),
+                    initial_supply.try_into().unwrap(),
+                    TradeDirection::BtoA,
+                    RoundDirection::Floor,
+                )
+                .unwrap()
+        } else {
+            0
+        };
+        let fee_account =
+            StateWithExtensions::<Account>::unpack(&accounts.pool_fee_account.data).unwrap();
+        assert_eq!(
+            fee_account.base.amount,
+            to_u64(first_fee + second_fee).unwrap()
+        );
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_valid_swap_curve_all_fees(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        // All fees
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 20;
+        let host_fee_denominator = 100;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_a_amount = 10_000_000_000;
+        let token_b_amount = 50_000_000_000;
+
+        check_valid_swap_curve(
+            fees.clone(),
+            SwapTransferFees::default(),
+            CurveType::ConstantProduct,
+            Arc::new(ConstantProductCurve {}),
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+        let token_b_price = 1;
+        check_valid_swap_curve(
+            fees.clone(),
+            SwapTransferFees::default(),
+            CurveType::ConstantPrice,
+            Arc::new(ConstantPriceCurve { token_b_price }),
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+        let token_b_offset = 10_000_000_000;
+        check_valid_swap_curve(
+            fees.clone(),
+            SwapTransferFees::default(),
+            CurveType::Offset,
+            Arc::new(OffsetCurve { token_b_offset }),
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+        check_valid_swap_curve(
+            fees,
+            SwapTransferFees::default(),
+            CurveType::Weighted,
+            Arc::new(WeightedCurve {
+                token_a_weight: 80,
+                token_b_weight: 20,
+            }),
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_valid_swap_curve_trade_fee_only(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 0;
+        let owner_trade_fee_denominator = 0;
+        let owner_withdraw_fee_numerator = 0;
+        let owner_withdraw_fee_denominator = 0;
+        let host_fee_numerator = 0;
+        let host_fee_denominator = 0;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_a_amount = 10_000_000_000;
+        let token_b_amount = 50_000_000_000;
+
+        check_valid_swap_curve(
+            fees.clone(),
+            SwapTransferFees::default(),
+            CurveType::ConstantProduct,
+            Arc::new(ConstantProductCurve {}),
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+        let token_b_price = 10_000;
+        check_valid_swap_curve(
+            fees.clone(),
+            SwapTransferFees::default(),
+            CurveType::ConstantPrice,
+            Arc::new(ConstantPriceCurve { token_b_price }),
+            token_a_amount,
+            token_b_amount / token_b_price,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+        let token_b_offset = 1;
+        check_valid_swap_curve(
+            fees.clone(),
+            SwapTransferFees::default(),
+            CurveType::Offset,
+            Arc::new(OffsetCurve { token_b_offset }),
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+        check_valid_swap_curve(
+            fees,
+            SwapTransferFees::default(),
+            CurveType::Weighted,
+            Arc::new(WeightedCurve {
+                token_a_weight: 80,
+                token_b_weight: 20,
+            }),
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_valid_swap_with_fee_constraints(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let owner_key = Pubkey::new_unique();
+
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 10;
+        let host_fee_denominator = 100;
+
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 5_000_000;
+
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let curve = ConstantProductCurve {};
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Arc::new(curve),
+        };
+
+        let owner_key_str = &owner_key.to_string();
+        let valid_curve_types = &[CurveType::ConstantProduct];
+        let constraints = Some(SwapConstraints {
+            owner_key: owner_key_str,
+            valid_curve_types,
+            fees: &fees,
+        });
+        let mut accounts = SwapAccountInfo::new(
+            &owner_key,
+            fees.clone(),
+            SwapTransferFees::default(),
+            swap_curve,
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+
+        // initialize swap
+        do_process_instruction_with_fee_constraints(
+            initialize(
+                &SWAP_PROGRAM_ID,
+                &pool_token_program_id,
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
+                &accounts.pool_mint_key,
+                &accounts.pool_fee_key,
+                &accounts.pool_token_key,
+                None,
+                accounts.fees.clone(),
+                accounts.swap_curve.clone(),
+            )
+            .unwrap(),
+            vec![
+                &mut accounts.swap_account,
+                &mut SolanaAccount::default(),
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut accounts.pool_mint_account,
+                &mut accounts.pool_fee_account,
+                &mut accounts.pool_token_account,
+                &mut SolanaAccount::default(),
+            ],
+            &constraints,
+        )
+        .unwrap();
+
+        let authority_key = accounts.authority_key;
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(
+            &owner_key,
+            &authority_key,
+            token_a_amount,
+            token_b_amount,
+            0,
+        );
+
+        let amount_in = token_a_amount / 2;
+        let minimum_amount_out = 0;
+
+        // perform the swap
+        do_process_instruction_with_fee_constraints(
+            swap(
+                &SWAP_PROGRAM_ID,
+                &token_a_program_id,
+                &token_b_program_id,
+                &pool_token_program_id,
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &accounts.authority_key,
+                &token_a_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
+                &token_b_key,
+                &accounts.pool_mint_key,
+                &accounts.pool_fee_key,
+                &accounts.token_a_mint_key,
+                &accounts.token_b_mint_key,
+                Some(&pool_key),
+                Swap {
+                    amount_in,
+                    minimum_amount_out,
+                },
+            )
+            .unwrap(),
+            vec![
+                &mut accounts.swap_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut token_a_account,
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut token_b_account,
+                &mut accounts.pool_mint_account,
+                &mut accounts.pool_fee_account,
+                &mut accounts.token_a_mint_account,
+                &mut accounts.token_b_mint_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut pool_account,
+            ],
+            &constraints,
+        )
+        .unwrap();
+
+        // check that fees were taken in the host fee account
+        let host_fee_account = StateWithExtensions::<Account>::unpack(&pool_account.data).unwrap();
+        let owner_fee_account =
+            StateWithExtensions::<Account>::unpack(&accounts.pool_fee_account.data).unwrap();
+        let total_fee = owner_fee_account.base.amount * host_fee_denominator
+            / (host_fee_denominator - host_fee_numerator);
+        assert_eq!(
+            total_fee,
+            host_fee_account.base.amount + owner_fee_account.base.amount
+        );
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_invalid_swap(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let user_key = Pubkey::new_unique();
+        let swapper_key = Pubkey::new_unique();
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 4;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 10;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 5;
+        let host_fee_numerator = 9;
+        let host_fee_denominator = 100;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_a_amount = 1000;
+        let token_b_amount = 5000;
+        let curve_type = CurveType::ConstantProduct;
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator: Arc::new(ConstantProductCurve {}),
+        };
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees,
+            SwapTransferFees::default(),
+            swap_curve,
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
+        let minimum_token_b_amount = initial_b / 2;
+
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+
+        // swap not initialized
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(ProgramError::UninitializedAccount),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+        }
+
+        accounts.initialize_swap().unwrap();
+
+        // wrong swap account program id
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let old_swap_account = accounts.swap_account;
+            let mut wrong_swap_account = old_swap_account.clone();
+            wrong_swap_account.owner = pool_token_program_id;
+            accounts.swap_account = wrong_swap_account;
+            assert_eq!(
+                Err(ProgramError::IncorrectProgramId),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+            accounts.swap_account = old_swap_account;
+        }
+
+        // wrong bump seed
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let old_authority = accounts.authority_key;
+            let (bad_authority_key, _bump_seed) = Pubkey::find_program_address(
+                &[&accounts.swap_key.to_bytes()[..]],
+                &pool_token_program_id,
+            );
+            accounts.authority_key = bad_authority_key;
+            assert_eq!(
+                Err(SwapError::InvalidProgramAddress.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+            accounts.authority_key = old_authority;
+        }
+
+        // wrong token program id
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let wrong_program_id = Pubkey::new_unique();
+            assert_eq!(
+                Err(SwapError::IncorrectTokenProgramId.into()),
+                do_process_instruction(
+                    swap(
+                        &SWAP_PROGRAM_ID,
+                        &wrong_program_id,
+                        &wrong_program_id,
+                        &wrong_program_id,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &accounts.authority_key,
+                        &token_a_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &accounts.token_a_mint_key,
+                        &accounts.token_b_mint_key,
+                        None,
+                        Swap {
+                            amount_in: initial_a,
+                            minimum_amount_out: minimum_token_b_amount,
+                        },
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut token_a_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut accounts.pool_fee_account,
+                        &mut accounts.token_a_mint_account,
+                        &mut accounts.token_b_mint_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                    ],
+                ),
+            );
+        }
+
+        // not enough token a to swap
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(TokenError::InsufficientFunds.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a * 2,
+                    minimum_token_b_amount * 2,
+                )
+            );
+        }
+
+        // wrong swap token A / B accounts
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let user_transfer_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(SwapError::IncorrectSwapAccount.into()),
+                do_process_instruction(
+                    swap(
+                        &SWAP_PROGRAM_ID,
+                        &token_a_program_id,
+                        &token_b_program_id,
+                        &pool_token_program_id,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &user_transfer_key,
+                        &token_a_key,
+                        &token_a_key,
+                        &token_b_key,
+                        &token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &accounts.token_a_mint_key,
+                        &accounts.token_b_mint_key,
+                        None,
+                        Swap {
+                            amount_in: initial_a,
+                            minimum_amount_out: minimum_token_b_amount,
+                        },
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut token_a_account.clone(),
+                        &mut token_a_account,
+                        &mut token_b_account.clone(),
+                        &mut token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut accounts.pool_fee_account,
+                        &mut accounts.token_a_mint_account,
+                        &mut accounts.token_b_mint_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                    ],
+                ),
+            );
+        }
+
+        // wrong user token A / B accounts
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(TokenError::MintMismatch.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+        }
+
+        // swap from a to a
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                _token_b_key,
+                _token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account.clone(),
+                    &swap_token_a_key,
+                    &swap_token_a_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+        }
+
+        // incorrect mint provided
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let (pool_mint_key, pool_mint_account) = create_mint(
+                &pool_token_program_id,
+                &accounts.authority_key,
+                None,
+                None,
+                &TransferFee::default(),
+            );
+            let old_pool_key = accounts.pool_mint_key;
+            let old_pool_account = accounts.pool_mint_account;
+            accounts.pool_mint_key = pool_mint_key;
+            accounts.pool_mint_account = pool_mint_account;
+
+            assert_eq!(
+                Err(SwapError::IncorrectPoolMint.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+
+            accounts.pool_mint_key = old_pool_key;
+            accounts.pool_mint_account = old_pool_account;
+        }
+
+        // incorrect fee account provided
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                wrong_pool_key,
+                wrong_pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let old_pool_fee_account = accounts.pool_fee_account;
+            let old_pool_fee_key = accounts.pool_fee_key;
+            accounts.pool_fee_account = wrong_pool_account;
+            accounts.pool_fee_key = wrong_pool_key;
+            assert_eq!(
+                Err(SwapError::IncorrectFeeAccount.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+            accounts.pool_fee_account = old_pool_fee_account;
+            accounts.pool_fee_key = old_pool_fee_key;
+        }
+
+        // no approval
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let user_transfer_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(TokenError::OwnerMismatch.into()),
+                do_process_instruction(
+                    swap(
+                        &SWAP_PROGRAM_ID,
+                        &token_a_program_id,
+                        &token_b_program_id,
+                        &pool_token_program_id,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &user_transfer_key,
+                        &token_a_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &accounts.token_a_mint_key,
+                        &accounts.token_b_mint_key,
+                        None,
+                        Swap {
+                            amount_in: initial_a,
+                            minimum_amount_out: minimum_token_b_amount,
+                        },
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut token_a_account,
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut accounts.pool_fee_account,
+                        &mut accounts.token_a_mint_account,
+                        &mut accounts.token_b_mint_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                    ],
+                ),
+            );
+        }
+
+        // output token value 0
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(SwapError::ZeroTradingTokens.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &swap_token_b_key,
+                    &swap_token_a_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    1,
+                    1,
+                )
+            );
+        }
+
+        // slippage exceeded: minimum out amount too high
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(SwapError::ExceededSlippage.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount * 2,
+                )
+            );
+        }
+
+        // invalid input: can't use swap pool as user source / dest
+        {
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            let mut swap_token_a_account = accounts.get_token_account(&swap_token_a_key).clone();
+            let authority_key = accounts.authority_key;
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.swap(
+                    &authority_key,
+                    &swap_token_a_key,
+                    &mut swap_token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+            let mut swap_token_b_account = accounts.get_token_account(&swap_token_b_key).clone();
+            assert_eq!(
+                Err(SwapError::InvalidInput.into()),
+                accounts.swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &swap_token_b_key,
+                    &mut swap_token_b_account,
+                    initial_a,
+                    minimum_token_b_amount,
+                )
+            );
+        }
+
+        // still correct: constraint specified, no host fee account
+        {
+            let authority_key = accounts.authority_key;
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
+            let owner_key = &swapper_key.to_string();
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let constraints = Some(SwapConstraints {
+                owner_key,
+                valid_curve_types: &[],
+                fees: &fees,
+            });
+            do_process_instruction_with_fee_constraints(
+                swap(
+                    &SWAP_PROGRAM_ID,
+                    &token_a_program_id,
+                    &token_b_program_id,
+                    &pool_token_program_id,
+                    &accounts.swap_key,
+                    &accounts.authority_key,
+                    &accounts.authority_key,
+                    &token_a_key,
+                    &accounts.token_a_key,
+                    &accounts.token_b_key,
+                    &token_b_key,
+                    &accounts.pool_mint_key,
+                    &accounts.pool_fee_key,
+                    &accounts.token_a_mint_key,
+                    &accounts.token_b_mint_key,
+                    None,
+                    Swap {
+                        amount_in: initial_a,
+                        minimum_amount_out: minimum_token_b_amount,
+                    },
+                )
+                .unwrap(),
+                vec![
+                    &mut accounts.swap_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    &mut token_a_account,
+                    &mut accounts.token_a_account,
+                    &mut accounts.token_b_account,
+                    &mut token_b_account,
+                    &mut accounts.pool_mint_account,
+                    &mut accounts.pool_fee_account,
+                    &mut accounts.token_a_mint_account,
+                    &mut accounts.token_b_mint_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
+                &constraints,
+            )
+            .unwrap();
+        }
+
+        // invalid mint for host fee account
+        {
+            let authority_key = accounts.authority_key;
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
+            let (
+                bad_token_a_key,
+                mut bad_token_a_account,
+                _token_b_key,
+                mut _token_b_account,
+                _pool_key,
+                _pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &authority_key, initial_a, initial_b, 0);
+            let owner_key = &swapper_key.to_string();
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let constraints = Some(SwapConstraints {
+                owner_key,
+                valid_curve_types: &[],
+                fees: &fees,
+            });
+            assert_eq!(
+                Err(SwapError::IncorrectPoolMint.into()),
+                do_process_instruction_with_fee_constraints(
+                    swap(
+                        &SWAP_PROGRAM_ID,
+                        &token_a_program_id,
+                        &token_b_program_id,
+                        &pool_token_program_id,
+                        &accounts.swap_key,
+                        &accounts.authority_key,
+                        &accounts.authority_key,
+                        &token_a_key,
+                        &accounts.token_a_key,
+                        &accounts.token_b_key,
+                        &token_b_key,
+                        &accounts.pool_mint_key,
+                        &accounts.pool_fee_key,
+                        &accounts.token_a_mint_key,
+                        &accounts.token_b_mint_key,
+                        Some(&bad_token_a_key),
+                        Swap {
+                            amount_in: initial_a,
                             minimum_amount_out: 0,
                         },
                     )
@@ -7330,44 +8766,870 @@ mod tests {
     #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
     #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
     #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_overdraw_offset_curve(
+    fn test_overdraw_offset_curve(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 1;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 10;
+        let host_fee_denominator = 100;
+
+        let token_a_amount = 1_000_000_000;
+        let token_b_amount = 0;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_b_offset = 2_000_000;
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::Offset,
+            calculator: Arc::new(OffsetCurve { token_b_offset }),
+        };
+        let user_key = Pubkey::new_unique();
+        let swapper_key = Pubkey::new_unique();
+
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees,
+            SwapTransferFees::default(),
+            swap_curve,
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+
+        accounts.initialize_swap().unwrap();
+
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        let initial_a = 500_000;
+        let initial_b = 1_000;
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+
+        // swap a to b way, fails, there's no liquidity
+        let a_to_b_amount = initial_a;
+        let minimum_token_b_amount = 0;
+
+        assert_eq!(
+            Err(SwapError::ZeroTradingTokens.into()),
+            accounts.swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                a_to_b_amount,
+                minimum_token_b_amount,
+            )
+        );
+
+        // swap b to a, succeeds at offset price
+        let b_to_a_amount = initial_b;
+        let minimum_token_a_amount = 0;
+        accounts
+            .swap(
+                &swapper_key,
+                &token_b_key,
+                &mut token_b_account,
+                &swap_token_b_key,
+                &swap_token_a_key,
+                &token_a_key,
+                &mut token_a_account,
+                b_to_a_amount,
+                minimum_token_a_amount,
+            )
+            .unwrap();
+
+        // try a to b again, succeeds due to new liquidity
+        accounts
+            .swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                a_to_b_amount,
+                minimum_token_b_amount,
+            )
+            .unwrap();
+
+        // try a to b again, fails due to no more liquidity
+        assert_eq!(
+            Err(SwapError::ZeroTradingTokens.into()),
+            accounts.swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                a_to_b_amount,
+                minimum_token_b_amount,
+            )
+        );
+
+        // Try to deposit, fails because deposits are not allowed for offset
+        // curve swaps
+        {
+            let initial_a = 100;
+            let initial_b = 100;
+            let pool_amount = 100;
+            let (
+                token_a_key,
+                mut token_a_account,
+                token_b_key,
+                mut token_b_account,
+                pool_key,
+                mut pool_account,
+            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+            assert_eq!(
+                Err(SwapError::UnsupportedCurveOperation.into()),
+                accounts.deposit_all_token_types(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &token_b_key,
+                    &mut token_b_account,
+                    &pool_key,
+                    &mut pool_account,
+                    pool_amount,
+                    initial_a,
+                    initial_b,
+                )
+            );
+        }
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_withdraw_all_offset_curve(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 0;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 10;
+        let host_fee_denominator = 100;
+
+        let token_a_amount = 1_000_000_000;
+        let token_b_amount = 10;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_b_offset = 2_000_000;
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::Offset,
+            calculator: Arc::new(OffsetCurve { token_b_offset }),
+        };
+        let total_pool = swap_curve.calculator.new_pool_supply();
+        let user_key = Pubkey::new_unique();
+        let withdrawer_key = Pubkey::new_unique();
+
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees,
+            SwapTransferFees::default(),
+            swap_curve,
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+
+        accounts.initialize_swap().unwrap();
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
+
+        let pool_key = accounts.pool_token_key;
+        let mut pool_account = accounts.pool_token_account.clone();
+
+        // WithdrawAllTokenTypes takes all tokens for A and B.
+        // The curve's calculation for token B will say to transfer
+        // `token_b_offset + token_b_amount`, but only `token_b_amount` will be
+        // moved.
+        accounts
+            .withdraw_all_token_types(
+                &user_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                total_pool.try_into().unwrap(),
+                0,
+                0,
+            )
+            .unwrap();
+
+        let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
+        assert_eq!(token_a.base.amount, token_a_amount);
+        let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
+        assert_eq!(token_b.base.amount, token_b_amount);
+        let swap_token_a =
+            StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
+        assert_eq!(swap_token_a.base.amount, 0);
+        let swap_token_b =
+            StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
+        assert_eq!(swap_token_b.base.amount, 0);
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_withdraw_all_constant_price_curve(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 0;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 10;
+        let host_fee_denominator = 100;
+
+        // initialize "unbalanced", so that withdrawing all will have some issues
+        // A: 1_000_000_000
+        // B: 2_000_000_000 (1_000 * 2_000_000)
+        let swap_token_a_amount = 1_000_000_000;
+        let swap_token_b_amount = 1_000;
+        let token_b_price = 2_000_000;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantPrice,
+            calculator: Arc::new(ConstantPriceCurve { token_b_price }),
+        };
+        let total_pool = swap_curve.calculator.new_pool_supply();
+        let user_key = Pubkey::new_unique();
+        let withdrawer_key = Pubkey::new_unique();
+
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees,
+            SwapTransferFees::default(),
+            swap_curve,
+            swap_token_a_amount,
+            swap_token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+
+        accounts.initialize_swap().unwrap();
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
+
+        let pool_key = accounts.pool_token_key;
+        let mut pool_account = accounts.pool_token_account.clone();
+
+        // WithdrawAllTokenTypes will not take all token A and B, since their
+        // ratio is unbalanced.  It will try to take 1_500_000_000 worth of
+        // each token, which means 1_500_000_000 token A, and 750 token B.
+        // With no slippage, this will leave 250 token B in the pool.
+        assert_eq!(
+            Err(SwapError::ExceededSlippage.into()),
+            accounts.withdraw_all_token_types(
+                &user_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                total_pool.try_into().unwrap(),
+                swap_token_a_amount,
+                swap_token_b_amount,
+            )
+        );
+
+        accounts
+            .withdraw_all_token_types(
+                &user_key,
+                &pool_key,
+                &mut pool_account,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                total_pool.try_into().unwrap(),
+                0,
+                0,
+            )
+            .unwrap();
+
+        let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
+        assert_eq!(token_a.base.amount, swap_token_a_amount);
+        let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
+        assert_eq!(token_b.base.amount, 750);
+        let swap_token_a =
+            StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
+        assert_eq!(swap_token_a.base.amount, 0);
+        let swap_token_b =
+            StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
+        assert_eq!(swap_token_b.base.amount, 250);
+
+        // deposit now, not enough to cover the tokens already in there
+        let token_b_amount = 10;
+        let token_a_amount = token_b_amount * token_b_price;
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(
+            &user_key,
+            &withdrawer_key,
+            token_a_amount,
+            token_b_amount,
+            0,
+        );
+
+        assert_eq!(
+            Err(SwapError::ExceededSlippage.into()),
+            accounts.deposit_all_token_types(
+                &withdrawer_key,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                &pool_key,
+                &mut pool_account,
+                1, // doesn't matter
+                token_a_amount,
+                token_b_amount,
+            )
+        );
+
+        // deposit enough tokens, success!
+        let token_b_amount = 125;
+        let token_a_amount = token_b_amount * token_b_price;
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(
+            &user_key,
+            &withdrawer_key,
+            token_a_amount,
+            token_b_amount,
+            0,
+        );
+
+        accounts
+            .deposit_all_token_types(
+                &withdrawer_key,
+                &token_a_key,
+                &mut token_a_account,
+                &token_b_key,
+                &mut token_b_account,
+                &pool_key,
+                &mut pool_account,
+                1, // doesn't matter
+                token_a_amount,
+                token_b_amount,
+            )
+            .unwrap();
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_deposits_allowed_single_token(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let trade_fee_numerator = 1;
+        let trade_fee_denominator = 10;
+        let owner_trade_fee_numerator = 1;
+        let owner_trade_fee_denominator = 30;
+        let owner_withdraw_fee_numerator = 0;
+        let owner_withdraw_fee_denominator = 30;
+        let host_fee_numerator = 10;
+        let host_fee_denominator = 100;
+
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 0;
+        let fees = Fees {
+            trade_fee_numerator,
+            trade_fee_denominator,
+            owner_trade_fee_numerator,
+            owner_trade_fee_denominator,
+            owner_withdraw_fee_numerator,
+            owner_withdraw_fee_denominator,
+            host_fee_numerator,
+            host_fee_denominator,
+        };
+
+        let token_b_offset = 2_000_000;
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::Offset,
+            calculator: Arc::new(OffsetCurve { token_b_offset }),
+        };
+        let creator_key = Pubkey::new_unique();
+        let depositor_key = Pubkey::new_unique();
+
+        let mut accounts = SwapAccountInfo::new(
+            &creator_key,
+            fees,
+            SwapTransferFees::default(),
+            swap_curve,
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+
+        accounts.initialize_swap().unwrap();
+
+        let initial_a = 1_000_000;
+        let initial_b = 2_000_000;
+        let (
+            _depositor_token_a_key,
+            _depositor_token_a_account,
+            depositor_token_b_key,
+            mut depositor_token_b_account,
+            depositor_pool_key,
+            mut depositor_pool_account,
+        ) = accounts.setup_token_accounts(&creator_key, &depositor_key, initial_a, initial_b, 0);
+
+        assert_eq!(
+            Err(SwapError::UnsupportedCurveOperation.into()),
+            accounts.deposit_single_token_type_exact_amount_in(
+                &depositor_key,
+                &depositor_token_b_key,
+                &mut depositor_token_b_account,
+                &depositor_pool_key,
+                &mut depositor_pool_account,
+                initial_b,
+                0,
+            )
+        );
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_withdraw_with_invalid_fee_account(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let user_key = Pubkey::new_unique();
+
+        let fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 2,
+            owner_trade_fee_numerator: 1,
+            owner_trade_fee_denominator: 10,
+            owner_withdraw_fee_numerator: 1,
+            owner_withdraw_fee_denominator: 5,
+            host_fee_numerator: 7,
+            host_fee_denominator: 100,
+        };
+
+        let token_a_amount = 1000;
+        let token_b_amount = 2000;
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Arc::new(ConstantProductCurve {}),
+        };
+
+        let withdrawer_key = Pubkey::new_unique();
+        let initial_a = token_a_amount / 10;
+        let initial_b = token_b_amount / 10;
+        let initial_pool = swap_curve.calculator.new_pool_supply() / 10;
+        let withdraw_amount = initial_pool / 4;
+        let minimum_token_a_amount = initial_a / 40;
+        let minimum_token_b_amount = initial_b / 40;
+
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees,
+            SwapTransferFees::default(),
+            swap_curve,
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+
+        accounts.initialize_swap().unwrap();
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(
+            &user_key,
+            &withdrawer_key,
+            initial_a,
+            initial_b,
+            initial_pool.try_into().unwrap(),
+        );
+
+        let destination_key = Pubkey::new_unique();
+        let mut destination = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &withdrawer_key,
+        );
+
+        do_process_instruction(
+            close_account(
+                &pool_token_program_id,
+                &accounts.pool_fee_key,
+                &destination_key,
+                &user_key,
+                &[],
+            )
+            .unwrap(),
+            vec![
+                &mut accounts.pool_fee_account,
+                &mut destination,
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+
+        let user_transfer_authority_key = Pubkey::new_unique();
+        let pool_token_amount = withdraw_amount.try_into().unwrap();
+
+        do_process_instruction(
+            approve(
+                &pool_token_program_id,
+                &pool_key,
+                &user_transfer_authority_key,
+                &withdrawer_key,
+                &[],
+                pool_token_amount,
+            )
+            .unwrap(),
+            vec![
+                &mut pool_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+
+        do_process_instruction(
+            withdraw_all_token_types(
+                &SWAP_PROGRAM_ID,
+                &pool_token_program_id,
+                &token_a_program_id,
+                &token_b_program_id,
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &user_transfer_authority_key,
+                &accounts.pool_mint_key,
+                &accounts.pool_fee_key,
+                &pool_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
+                &token_a_key,
+                &token_b_key,
+                &accounts.token_a_mint_key,
+                &accounts.token_b_mint_key,
+                WithdrawAllTokenTypes {
+                    pool_token_amount,
+                    minimum_token_a_amount,
+                    minimum_token_b_amount,
+                },
+            )
+            .unwrap(),
+            vec![
+                &mut accounts.swap_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut accounts.pool_mint_account,
+                &mut pool_account,
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut token_a_account,
+                &mut token_b_account,
+                &mut accounts.pool_fee_account,
+                &mut accounts.token_a_mint_account,
+                &mut accounts.token_b_mint_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_withdraw_one_exact_out_with_invalid_fee_account(
+        pool_token_program_id: Pubkey,
+        token_a_program_id: Pubkey,
+        token_b_program_id: Pubkey,
+    ) {
+        let user_key = Pubkey::new_unique();
+
+        let fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 2,
+            owner_trade_fee_numerator: 1,
+            owner_trade_fee_denominator: 10,
+            owner_withdraw_fee_numerator: 1,
+            owner_withdraw_fee_denominator: 5,
+            host_fee_numerator: 7,
+            host_fee_denominator: 100,
+        };
+
+        let token_a_amount = 1000;
+        let token_b_amount = 2000;
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Arc::new(ConstantProductCurve {}),
+        };
+
+        let withdrawer_key = Pubkey::new_unique();
+        let initial_a = token_a_amount / 10;
+        let initial_b = token_b_amount / 10;
+        let initial_pool = swap_curve.calculator.new_pool_supply() / 10;
+        let maximum_pool_token_amount = to_u64(initial_pool / 4).unwrap();
+        let destination_a_amount = initial_a / 40;
+
+        let mut accounts = SwapAccountInfo::new(
+            &user_key,
+            fees,
+            SwapTransferFees::default(),
+            swap_curve,
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+
+        accounts.initialize_swap().unwrap();
+
+        let (
+            token_a_key,
+            mut token_a_account,
+            _token_b_key,
+            _token_b_account,
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(
+            &user_key,
+            &withdrawer_key,
+            initial_a,
+            initial_b,
+            initial_pool.try_into().unwrap(),
+        );
+
+        let destination_key = Pubkey::new_unique();
+        let mut destination = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            &withdrawer_key,
+        );
+
+        do_process_instruction(
+            close_account(
+                &pool_token_program_id,
+                &accounts.pool_fee_key,
+                &destination_key,
+                &user_key,
+                &[],
+            )
+            .unwrap(),
+            vec![
+                &mut accounts.pool_fee_account,
+                &mut destination,
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+
+        let user_transfer_authority_key = Pubkey::new_unique();
+
+        do_process_instruction(
+            approve(
+                &pool_token_program_id,
+                &pool_key,
+                &user_transfer_authority_key,
+                &withdrawer_key,
+                &[],
+                maximum_pool_token_amount,
+            )
+            .unwrap(),
+            vec![
+                &mut pool_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+
+        do_process_instruction(
+            withdraw_single_token_type_exact_amount_out(
+                &SWAP_PROGRAM_ID,
+                &pool_token_program_id,
+                &token_a_program_id,
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &user_transfer_authority_key,
+                &accounts.pool_mint_key,
+                &accounts.pool_fee_key,
+                &pool_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
+                &token_a_key,
+                &accounts.token_a_mint_key,
+                WithdrawSingleTokenTypeExactAmountOut {
+                    destination_token_amount: destination_a_amount,
+                    maximum_pool_token_amount,
+                },
+            )
+            .unwrap(),
+            vec![
+                &mut accounts.swap_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut accounts.pool_mint_account,
+                &mut pool_account,
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut token_a_account,
+                &mut accounts.pool_fee_account,
+                &mut accounts.token_a_mint_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
+    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
+    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
+    fn test_valid_swap_with_invalid_fee_account(
         pool_token_program_id: Pubkey,
         token_a_program_id: Pubkey,
         token_b_program_id: Pubkey,
     ) {
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 30;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 30;
-        let host_fee_numerator = 10;
-        let host_fee_denominator = 100;
+        let owner_key = &Pubkey::new_unique();
+
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 5_000_000;
 
-        let token_a_amount = 1_000_000_000;
-        let token_b_amount = 0;
         let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 10,
+            owner_trade_fee_numerator: 1,
+            owner_trade_fee_denominator: 30,
+            owner_withdraw_fee_numerator: 1,
+            owner_withdraw_fee_denominator: 30,
+            host_fee_numerator: 10,
+            host_fee_denominator: 100,
         };
 
-        let token_b_offset = 2_000_000;
         let swap_curve = SwapCurve {
-            curve_type: CurveType::Offset,
-            calculator: Arc::new(OffsetCurve { token_b_offset }),
+            curve_type: CurveType::ConstantProduct,
+            calculator: Arc::new(ConstantProductCurve {}),
         };
-        let user_key = Pubkey::new_unique();
-        let swapper_key = Pubkey::new_unique();
 
+        let owner_key_str = &owner_key.to_string();
+        let constraints = Some(SwapConstraints {
+            owner_key: owner_key_str,
+            valid_curve_types: &[CurveType::ConstantProduct],
+            fees: &fees,
+        });
         let mut accounts = SwapAccountInfo::new(
-            &user_key,
-            fees,
+            owner_key,
+            fees.clone(),
             SwapTransferFees::default(),
             swap_curve,
             token_a_amount,
@@ -7377,141 +9639,140 @@ mod tests {
             &token_b_program_id,
         );
 
-        accounts.initialize_swap().unwrap();
+        do_process_instruction_with_fee_constraints(
+            initialize(
+                &SWAP_PROGRAM_ID,
+                &pool_token_program_id,
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
+                &accounts.pool_mint_key,
+                &accounts.pool_fee_key,
+                &accounts.pool_token_key,
+                None,
+                accounts.fees.clone(),
+                accounts.swap_curve.clone(),
+            )
+            .unwrap(),
+            vec![
+                &mut accounts.swap_account,
+                &mut SolanaAccount::default(),
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut accounts.pool_mint_account,
+                &mut accounts.pool_fee_account,
+                &mut accounts.pool_token_account,
+                &mut SolanaAccount::default(),
+            ],
+            &constraints,
+        )
+        .unwrap();
 
-        let swap_token_a_key = accounts.token_a_key;
-        let swap_token_b_key = accounts.token_b_key;
-        let initial_a = 500_000;
-        let initial_b = 1_000;
+        let authority_key = accounts.authority_key;
 
         let (
             token_a_key,
             mut token_a_account,
             token_b_key,
             mut token_b_account,
-            _pool_key,
-            _pool_account,
-        ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-
-        // swap a to b way, fails, there's no liquidity
-        let a_to_b_amount = initial_a;
-        let minimum_token_b_amount = 0;
+            pool_key,
+            mut pool_account,
+        ) = accounts.setup_token_accounts(
+            owner_key,
+            &authority_key,
+            token_a_amount,
+            token_b_amount,
+            0,
+        );
 
-        assert_eq!(
-            Err(SwapError::ZeroTradingTokens.into()),
-            accounts.swap(
-                &swapper_key,
-                &token_a_key,
-                &mut token_a_account,
-                &swap_token_a_key,
-                &swap_token_b_key,
-                &token_b_key,
-                &mut token_b_account,
-                a_to_b_amount,
-                minimum_token_b_amount,
-            )
+        let destination_key = Pubkey::new_unique();
+        let mut destination = SolanaAccount::new(
+            account_minimum_balance(),
+            Account::get_packed_len(),
+            owner_key,
         );
 
-        // swap b to a, succeeds at offset price
-        let b_to_a_amount = initial_b;
-        let minimum_token_a_amount = 0;
-        accounts
-            .swap(
-                &swapper_key,
-                &token_b_key,
-                &mut token_b_account,
-                &swap_token_b_key,
-                &swap_token_a_key,
-                &token_a_key,
-                &mut token_a_account,
-                b_to_a_amount,
-                minimum_token_a_amount,
+        do_process_instruction(
+            close_account(
+                &pool_token_program_id,
+                &accounts.pool_fee_key,
+                &destination_key,
+                owner_key,
+                &[],
             )
-            .unwrap();
+            .unwrap(),
+            vec![
+                &mut accounts.pool_fee_account,
+                &mut destination,
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
 
-        // try a to b again, succeeds due to new liquidity
-        accounts
-            .swap(
-                &swapper_key,
+        do_process_instruction_with_fee_constraints(
+            swap(
+                &SWAP_PROGRAM_ID,
+                &token_a_program_id,
+                &token_b_program_id,
+                &pool_token_program_id,
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &accounts.authority_key,
                 &token_a_key,
-                &mut token_a_account,
-                &swap_token_a_key,
-                &swap_token_b_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
                 &token_b_key,
-                &mut token_b_account,
-                a_to_b_amount,
-                minimum_token_b_amount,
-            )
-            .unwrap();
-
-        // try a to b again, fails due to no more liquidity
-        assert_eq!(
-            Err(SwapError::ZeroTradingTokens.into()),
-            accounts.swap(
-                &swapper_key,
-                &token_a_key,
+                &accounts.pool_mint_key,
+                &accounts.pool_fee_key,
+                &accounts.token_a_mint_key,
+                &accounts.token_b_mint_key,
+                Some(&pool_key),
+                Swap {
+                    amount_in: token_a_amount / 2,
+                    minimum_amount_out: 0,
+                },
+            )
+            .unwrap(),
+            vec![
+                &mut accounts.swap_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
                 &mut token_a_account,
-                &swap_token_a_key,
-                &swap_token_b_key,
-                &token_b_key,
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
                 &mut token_b_account,
-                a_to_b_amount,
-                minimum_token_b_amount,
-            )
-        );
-
-        // Try to deposit, fails because deposits are not allowed for offset
-        // curve swaps
-        {
-            let initial_a = 100;
-            let initial_b = 100;
-            let pool_amount = 100;
-            let (
-                token_a_key,
-                mut token_a_account,
-                token_b_key,
-                mut token_b_account,
-                pool_key,
-                mut pool_account,
-            ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
-            assert_eq!(
-                Err(SwapError::UnsupportedCurveOperation.into()),
-                accounts.deposit_all_token_types(
-                    &swapper_key,
-                    &token_a_key,
-                    &mut token_a_account,
-                    &token_b_key,
-                    &mut token_b_account,
-                    &pool_key,
-                    &mut pool_account,
-                    pool_amount,
-                    initial_a,
-                    initial_b,
-                )
-            );
-        }
+                &mut accounts.pool_mint_account,
+                &mut accounts.pool_fee_account,
+                &mut accounts.token_a_mint_account,
+                &mut accounts.token_b_mint_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut pool_account,
+            ],
+            &constraints,
+        )
+        .unwrap();
     }
 
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
     #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
     #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
     #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_withdraw_all_offset_curve(
+    fn test_swap_curve_with_transfer_fees(
         pool_token_program_id: Pubkey,
         token_a_program_id: Pubkey,
         token_b_program_id: Pubkey,
     ) {
+        // All fees
         let trade_fee_numerator = 1;
         let trade_fee_denominator = 10;
         let owner_trade_fee_numerator = 1;
         let owner_trade_fee_denominator = 30;
-        let owner_withdraw_fee_numerator = 0;
+        let owner_withdraw_fee_numerator = 1;
         let owner_withdraw_fee_denominator = 30;
-        let host_fee_numerator = 10;
+        let host_fee_numerator = 20;
         let host_fee_denominator = 100;
-
-        let token_a_amount = 1_000_000_000;
-        let token_b_amount = 10;
         let fees = Fees {
             trade_fee_numerator,
             trade_fee_denominator,
@@ -7523,15 +9784,51 @@ mod tests {
             host_fee_denominator,
         };
 
-        let token_b_offset = 2_000_000;
+        let token_a_amount = 10_000_000_000;
+        let token_b_amount = 50_000_000_000;
+
+        check_valid_swap_curve(
+            fees,
+            SwapTransferFees {
+                pool_token: TransferFee::default(),
+                token_a: TransferFee {
+                    epoch: 0.into(),
+                    transfer_fee_basis_points: 100.into(),
+                    maximum_fee: 1_000_000_000.into(),
+                },
+                token_b: TransferFee::default(),
+            },
+            CurveType::ConstantProduct,
+            Arc::new(ConstantProductCurve {}),
+            token_a_amount,
+            token_b_amount,
+            &pool_token_program_id,
+            &token_a_program_id,
+            &token_b_program_id,
+        );
+    }
+
+    #[test]
+    fn test_transfer_hook() {
+        let user_key = Pubkey::new_unique();
+        let depositor_key = Pubkey::new_unique();
+        let token_program_id = spl_token_2022::id();
+        let fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 10,
+            owner_trade_fee_numerator: 1,
+            owner_trade_fee_denominator: 30,
+            owner_withdraw_fee_numerator: 1,
+            owner_withdraw_fee_denominator: 30,
+            host_fee_numerator: 20,
+            host_fee_denominator: 100,
+        };
         let swap_curve = SwapCurve {
-            curve_type: CurveType::Offset,
-            calculator: Arc::new(OffsetCurve { token_b_offset }),
+            curve_type: CurveType::ConstantProduct,
+            calculator: Arc::new(ConstantProductCurve {}),
         };
-        let total_pool = swap_curve.calculator.new_pool_supply();
-        let user_key = Pubkey::new_unique();
-        let withdrawer_key = Pubkey::new_unique();
-
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 5_000_000;
         let mut accounts = SwapAccountInfo::new(
             &user_key,
             fees,
@@ -7539,113 +9836,340 @@ mod tests {
             swap_curve,
             token_a_amount,
             token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
+            &token_program_id,
+            &token_program_id,
+            &token_program_id,
         );
 
+        // token a is hooked
+        let extra_account_key = Pubkey::new_unique();
+        let (token_a_mint_key, mut token_a_mint_account, validation_key, mut validation_account) =
+            create_mint_with_transfer_hook(&token_program_id, &user_key, &extra_account_key);
+        let (token_a_key, token_a_account) = mint_token(
+            &token_program_id,
+            &token_a_mint_key,
+            &mut token_a_mint_account,
+            &user_key,
+            &accounts.authority_key,
+            token_a_amount,
+        );
+        accounts.token_a_mint_key = token_a_mint_key;
+        accounts.token_a_mint_account = token_a_mint_account;
+        accounts.token_a_key = token_a_key;
+        accounts.token_a_account = token_a_account;
         accounts.initialize_swap().unwrap();
 
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
         let (
-            token_a_key,
-            mut token_a_account,
-            token_b_key,
-            mut token_b_account,
-            _pool_key,
-            _pool_account,
-        ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
-
-        let pool_key = accounts.pool_token_key;
-        let mut pool_account = accounts.pool_token_account.clone();
+            user_token_a_key,
+            mut user_token_a_account,
+            user_token_b_key,
+            mut user_token_b_account,
+            user_pool_key,
+            mut user_pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &depositor_key, initial_a, initial_b, 0);
+
+        // swap a to b, the user is also the transfer authority
+        let a_to_b_amount = initial_a / 10;
+        let mut swap_instruction = swap(
+            &SWAP_PROGRAM_ID,
+            &token_program_id,
+            &token_program_id,
+            &token_program_id,
+            &accounts.swap_key,
+            &accounts.authority_key,
+            &depositor_key,
+            &user_token_a_key,
+            &accounts.token_a_key,
+            &accounts.token_b_key,
+            &user_token_b_key,
+            &accounts.pool_mint_key,
+            &accounts.pool_fee_key,
+            &accounts.token_a_mint_key,
+            &accounts.token_b_mint_key,
+            None,
+            Swap {
+                amount_in: a_to_b_amount,
+                minimum_amount_out: 0,
+            },
+        )
+        .unwrap();
 
-        // WithdrawAllTokenTypes takes all tokens for A and B.
-        // The curve's calculation for token B will say to transfer
-        // `token_b_offset + token_b_amount`, but only `token_b_amount` will be
-        // moved.
-        accounts
-            .withdraw_all_token_types(
-                &user_key,
-                &pool_key,
-                &mut pool_account,
-                &token_a_key,
-                &mut token_a_account,
-                &token_b_key,
-                &mut token_b_account,
-                total_pool.try_into().unwrap(),
-                0,
-                0,
+        // missing the transfer hook accounts
+        assert_eq!(
+            Err(TransferHookError::IncorrectAccount.into()),
+            do_process_instruction(
+                swap_instruction.clone(),
+                vec![
+                    &mut accounts.swap_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    &mut user_token_a_account,
+                    &mut accounts.token_a_account,
+                    &mut accounts.token_b_account,
+                    &mut user_token_b_account,
+                    &mut accounts.pool_mint_account,
+                    &mut accounts.pool_fee_account,
+                    &mut accounts.token_a_mint_account,
+                    &mut accounts.token_b_mint_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
             )
-            .unwrap();
+        );
 
-        let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
-        assert_eq!(token_a.base.amount, token_a_amount);
-        let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
-        assert_eq!(token_b.base.amount, token_b_amount);
-        let swap_token_a =
-            StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
-        assert_eq!(swap_token_a.base.amount, 0);
-        let swap_token_b =
-            StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
-        assert_eq!(swap_token_b.base.amount, 0);
+        add_transfer_hook_accounts(
+            &mut swap_instruction,
+            &TRANSFER_HOOK_PROGRAM_ID,
+            &user_token_a_key,
+            &accounts.token_a_mint_key,
+            &accounts.token_a_key,
+            &depositor_key,
+            a_to_b_amount,
+            &validation_account.data,
+        )
+        .unwrap();
+        // the swap program id holds the place of the missing host fee account
+        assert_eq!(swap_instruction.accounts[14].pubkey, SWAP_PROGRAM_ID);
+        assert_eq!(swap_instruction.accounts[15].pubkey, validation_key);
+        do_process_instruction(
+            swap_instruction,
+            vec![
+                &mut accounts.swap_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut user_token_a_account,
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut user_token_b_account,
+                &mut accounts.pool_mint_account,
+                &mut accounts.pool_fee_account,
+                &mut accounts.token_a_mint_account,
+                &mut accounts.token_b_mint_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut validation_account.clone(),
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+        let user_token_a = StateWithExtensions::<Account>::unpack(&user_token_a_account.data)
+            .unwrap()
+            .base;
+        assert_eq!(user_token_a.amount, initial_a - a_to_b_amount);
+        let user_token_b = StateWithExtensions::<Account>::unpack(&user_token_b_account.data)
+            .unwrap()
+            .base;
+        assert!(user_token_b.amount > initial_b);
+
+        // deposit both, the hook runs on the token a leg
+        let pool_mint =
+            StateWithExtensions::<Mint>::unpack(&accounts.pool_mint_account.data).unwrap();
+        let pool_token_amount = pool_mint.base.supply / 100;
+        let mut deposit_instruction = deposit_all_token_types(
+            &SWAP_PROGRAM_ID,
+            &token_program_id,
+            &token_program_id,
+            &token_program_id,
+            &accounts.swap_key,
+            &accounts.authority_key,
+            &depositor_key,
+            &user_token_a_key,
+            &user_token_b_key,
+            &accounts.token_a_key,
+            &accounts.token_b_key,
+            &accounts.pool_mint_key,
+            &user_pool_key,
+            &accounts.token_a_mint_key,
+            &accounts.token_b_mint_key,
+            DepositAllTokenTypes {
+                pool_token_amount,
+                maximum_token_a_amount: u64::MAX,
+                maximum_token_b_amount: u64::MAX,
+            },
+        )
+        .unwrap();
+        add_transfer_hook_accounts(
+            &mut deposit_instruction,
+            &TRANSFER_HOOK_PROGRAM_ID,
+            &user_token_a_key,
+            &accounts.token_a_mint_key,
+            &accounts.token_a_key,
+            &depositor_key,
+            0,
+            &validation_account.data,
+        )
+        .unwrap();
+        do_process_instruction(
+            deposit_instruction,
+            vec![
+                &mut accounts.swap_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut user_token_a_account,
+                &mut user_token_b_account,
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut accounts.pool_mint_account,
+                &mut user_pool_account,
+                &mut accounts.token_a_mint_account,
+                &mut accounts.token_b_mint_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut validation_account.clone(),
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+        let user_pool = StateWithExtensions::<Account>::unpack(&user_pool_account.data)
+            .unwrap()
+            .base;
+        assert_eq!(user_pool.amount, pool_token_amount);
+
+        // withdraw both, the hook runs on the token a leg
+        let mut withdraw_instruction = withdraw_all_token_types(
+            &SWAP_PROGRAM_ID,
+            &token_program_id,
+            &token_program_id,
+            &token_program_id,
+            &accounts.swap_key,
+            &accounts.authority_key,
+            &depositor_key,
+            &accounts.pool_mint_key,
+            &accounts.pool_fee_key,
+            &user_pool_key,
+            &accounts.token_a_key,
+            &accounts.token_b_key,
+            &user_token_a_key,
+            &user_token_b_key,
+            &accounts.token_a_mint_key,
+            &accounts.token_b_mint_key,
+            WithdrawAllTokenTypes {
+                pool_token_amount,
+                minimum_token_a_amount: 0,
+                minimum_token_b_amount: 0,
+            },
+        )
+        .unwrap();
+        add_transfer_hook_accounts(
+            &mut withdraw_instruction,
+            &TRANSFER_HOOK_PROGRAM_ID,
+            &accounts.token_a_key,
+            &accounts.token_a_mint_key,
+            &user_token_a_key,
+            &accounts.authority_key,
+            0,
+            &validation_account.data,
+        )
+        .unwrap();
+        do_process_instruction(
+            withdraw_instruction,
+            vec![
+                &mut accounts.swap_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut accounts.pool_mint_account,
+                &mut user_pool_account,
+                &mut accounts.token_a_account,
+                &mut accounts.token_b_account,
+                &mut user_token_a_account,
+                &mut user_token_b_account,
+                &mut accounts.pool_fee_account,
+                &mut accounts.token_a_mint_account,
+                &mut accounts.token_b_mint_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut validation_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+        let user_pool = StateWithExtensions::<Account>::unpack(&user_pool_account.data)
+            .unwrap()
+            .base;
+        assert_eq!(user_pool.amount, 0);
     }
 
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_withdraw_all_constant_price_curve(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 30;
-        let owner_withdraw_fee_numerator = 0;
-        let owner_withdraw_fee_denominator = 30;
-        let host_fee_numerator = 10;
-        let host_fee_denominator = 100;
-
-        // initialize "unbalanced", so that withdrawing all will have some issues
-        // A: 1_000_000_000
-        // B: 2_000_000_000 (1_000 * 2_000_000)
-        let swap_token_a_amount = 1_000_000_000;
-        let swap_token_b_amount = 1_000;
-        let token_b_price = 2_000_000;
+    #[test]
+    fn test_price_oracle() {
+        let user_key = Pubkey::new_unique();
+        let swapper_key = Pubkey::new_unique();
+        let token_program_id = spl_token::id();
         let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 10,
+            owner_trade_fee_numerator: 1,
+            owner_trade_fee_denominator: 30,
+            owner_withdraw_fee_numerator: 1,
+            owner_withdraw_fee_denominator: 30,
+            host_fee_numerator: 20,
+            host_fee_denominator: 100,
         };
-
         let swap_curve = SwapCurve {
-            curve_type: CurveType::ConstantPrice,
-            calculator: Arc::new(ConstantPriceCurve { token_b_price }),
+            curve_type: CurveType::ConstantProduct,
+            calculator: Arc::new(ConstantProductCurve {}),
         };
-        let total_pool = swap_curve.calculator.new_pool_supply();
-        let user_key = Pubkey::new_unique();
-        let withdrawer_key = Pubkey::new_unique();
-
+        let token_a_amount = 1_000_000;
+        let token_b_amount = 5_000_000;
         let mut accounts = SwapAccountInfo::new(
             &user_key,
-            fees,
+            fees.clone(),
             SwapTransferFees::default(),
-            swap_curve,
-            swap_token_a_amount,
-            swap_token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
+            swap_curve.clone(),
+            token_a_amount,
+            token_b_amount,
+            &token_program_id,
+            &token_program_id,
+            &token_program_id,
         );
+        let price_oracle = |accounts: &SwapAccountInfo| {
+            *SwapVersion::unpack(&accounts.swap_account.data)
+                .unwrap()
+                .price_oracle()
+                .unwrap()
+        };
+        let spot_prices = |accounts: &SwapAccountInfo| {
+            let token_a = StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data)
+                .unwrap()
+                .base
+                .amount;
+            let token_b = StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data)
+                .unwrap()
+                .base
+                .amount;
+            ConstantProductCurve {}
+                .spot_prices_x64(token_a.into(), token_b.into())
+                .unwrap()
+        };
 
+        CLOCK_UNIX_TIMESTAMP.with(|timestamp| timestamp.set(100));
         accounts.initialize_swap().unwrap();
+        let (initial_price_a, initial_price_b) = spot_prices(&accounts);
+        assert_eq!(initial_price_a, 5 << 64);
+        assert_eq!(
+            price_oracle(&accounts),
+            PriceOracle {
+                last_update_timestamp: 100,
+                price_a_cumulative_x64: 0,
+                price_b_cumulative_x64: 0,
+                last_price_a_x64: initial_price_a,
+                last_price_b_x64: initial_price_b,
+            }
+        );
 
+        let initial_a = token_a_amount / 5;
+        let initial_b = token_b_amount / 5;
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
         let (
             token_a_key,
             mut token_a_account,
@@ -7653,721 +10177,1414 @@ mod tests {
             mut token_b_account,
             _pool_key,
             _pool_account,
-        ) = accounts.setup_token_accounts(&user_key, &withdrawer_key, 0, 0, 0);
-
-        let pool_key = accounts.pool_token_key;
-        let mut pool_account = accounts.pool_token_account.clone();
-
-        // WithdrawAllTokenTypes will not take all token A and B, since their
-        // ratio is unbalanced.  It will try to take 1_500_000_000 worth of
-        // each token, which means 1_500_000_000 token A, and 750 token B.
-        // With no slippage, this will leave 250 token B in the pool.
-        assert_eq!(
-            Err(SwapError::ExceededSlippage.into()),
-            accounts.withdraw_all_token_types(
-                &user_key,
-                &pool_key,
-                &mut pool_account,
-                &token_a_key,
-                &mut token_a_account,
-                &token_b_key,
-                &mut token_b_account,
-                total_pool.try_into().unwrap(),
-                swap_token_a_amount,
-                swap_token_b_amount,
-            )
-        );
+        ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
 
+        // the initial prices are accumulated until the first swap, which
+        // holds the prices after it
+        CLOCK_UNIX_TIMESTAMP.with(|timestamp| timestamp.set(110));
         accounts
-            .withdraw_all_token_types(
-                &user_key,
-                &pool_key,
-                &mut pool_account,
+            .swap(
+                &swapper_key,
                 &token_a_key,
                 &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
                 &token_b_key,
                 &mut token_b_account,
-                total_pool.try_into().unwrap(),
-                0,
+                initial_a / 10,
                 0,
             )
             .unwrap();
-
-        let token_a = StateWithExtensions::<Account>::unpack(&token_a_account.data).unwrap();
-        assert_eq!(token_a.base.amount, swap_token_a_amount);
-        let token_b = StateWithExtensions::<Account>::unpack(&token_b_account.data).unwrap();
-        assert_eq!(token_b.base.amount, 750);
-        let swap_token_a =
-            StateWithExtensions::<Account>::unpack(&accounts.token_a_account.data).unwrap();
-        assert_eq!(swap_token_a.base.amount, 0);
-        let swap_token_b =
-            StateWithExtensions::<Account>::unpack(&accounts.token_b_account.data).unwrap();
-        assert_eq!(swap_token_b.base.amount, 250);
-
-        // deposit now, not enough to cover the tokens already in there
-        let token_b_amount = 10;
-        let token_a_amount = token_b_amount * token_b_price;
-        let (
-            token_a_key,
-            mut token_a_account,
-            token_b_key,
-            mut token_b_account,
-            pool_key,
-            mut pool_account,
-        ) = accounts.setup_token_accounts(
-            &user_key,
-            &withdrawer_key,
-            token_a_amount,
-            token_b_amount,
-            0,
+        let (price_a, price_b) = spot_prices(&accounts);
+        assert!(price_a < initial_price_a);
+        let first_observation = price_oracle(&accounts);
+        assert_eq!(
+            first_observation,
+            PriceOracle {
+                last_update_timestamp: 110,
+                price_a_cumulative_x64: initial_price_a * 10,
+                price_b_cumulative_x64: initial_price_b * 10,
+                last_price_a_x64: price_a,
+                last_price_b_x64: price_b,
+            }
         );
 
-        assert_eq!(
-            Err(SwapError::ExceededSlippage.into()),
-            accounts.deposit_all_token_types(
-                &withdrawer_key,
-                &token_a_key,
-                &mut token_a_account,
-                &token_b_key,
-                &mut token_b_account,
-                &pool_key,
-                &mut pool_account,
-                1, // doesn't matter
+        // tokens donated to the pool do not move the prices it held before
+        do_process_instruction(
+            mint_to(
+                &token_program_id,
+                &accounts.token_a_mint_key,
+                &accounts.token_a_key,
+                &user_key,
+                &[],
                 token_a_amount,
-                token_b_amount,
             )
-        );
-
-        // deposit enough tokens, success!
-        let token_b_amount = 125;
-        let token_a_amount = token_b_amount * token_b_price;
-        let (
-            token_a_key,
-            mut token_a_account,
-            token_b_key,
-            mut token_b_account,
-            pool_key,
-            mut pool_account,
-        ) = accounts.setup_token_accounts(
-            &user_key,
-            &withdrawer_key,
-            token_a_amount,
-            token_b_amount,
-            0,
-        );
-
+            .unwrap(),
+            vec![
+                &mut accounts.token_a_mint_account,
+                &mut accounts.token_a_account,
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+        CLOCK_UNIX_TIMESTAMP.with(|timestamp| timestamp.set(130));
         accounts
-            .deposit_all_token_types(
-                &withdrawer_key,
-                &token_a_key,
-                &mut token_a_account,
+            .swap(
+                &swapper_key,
                 &token_b_key,
                 &mut token_b_account,
-                &pool_key,
-                &mut pool_account,
-                1, // doesn't matter
-                token_a_amount,
-                token_b_amount,
+                &swap_token_b_key,
+                &swap_token_a_key,
+                &token_a_key,
+                &mut token_a_account,
+                initial_b / 10,
+                0,
             )
             .unwrap();
-    }
-
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_deposits_allowed_single_token(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 30;
-        let owner_withdraw_fee_numerator = 0;
-        let owner_withdraw_fee_denominator = 30;
-        let host_fee_numerator = 10;
-        let host_fee_denominator = 100;
-
-        let token_a_amount = 1_000_000;
-        let token_b_amount = 0;
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
-
-        let token_b_offset = 2_000_000;
-        let swap_curve = SwapCurve {
-            curve_type: CurveType::Offset,
-            calculator: Arc::new(OffsetCurve { token_b_offset }),
-        };
-        let creator_key = Pubkey::new_unique();
-        let depositor_key = Pubkey::new_unique();
+        let (donated_price_a, donated_price_b) = spot_prices(&accounts);
+        assert!(donated_price_a < price_a);
+        let second_observation = price_oracle(&accounts);
+        assert_eq!(second_observation.last_update_timestamp, 130);
+        assert_eq!(second_observation.last_price_a_x64, donated_price_a);
+        assert_eq!(second_observation.last_price_b_x64, donated_price_b);
+        assert_eq!(
+            second_observation.average_prices_since(&first_observation),
+            Some((price_a, price_b))
+        );
+        assert_eq!(
+            second_observation.average_prices_since(&price_oracle(&accounts)),
+            None
+        );
 
+        // swaps sized for the original version still work, without an oracle
         let mut accounts = SwapAccountInfo::new(
-            &creator_key,
+            &user_key,
             fees,
             SwapTransferFees::default(),
             swap_curve,
             token_a_amount,
             token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
+            &token_program_id,
+            &token_program_id,
+            &token_program_id,
         );
-
+        accounts.swap_account = SolanaAccount::new(0, SwapVersion::SWAP_V1_LEN, &SWAP_PROGRAM_ID);
         accounts.initialize_swap().unwrap();
-
-        let initial_a = 1_000_000;
-        let initial_b = 2_000_000;
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
         let (
-            _depositor_token_a_key,
-            _depositor_token_a_account,
-            depositor_token_b_key,
-            mut depositor_token_b_account,
-            depositor_pool_key,
-            mut depositor_pool_account,
-        ) = accounts.setup_token_accounts(&creator_key, &depositor_key, initial_a, initial_b, 0);
-
-        assert_eq!(
-            Err(SwapError::UnsupportedCurveOperation.into()),
-            accounts.deposit_single_token_type_exact_amount_in(
-                &depositor_key,
-                &depositor_token_b_key,
-                &mut depositor_token_b_account,
-                &depositor_pool_key,
-                &mut depositor_pool_account,
-                initial_b,
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &swapper_key, initial_a, initial_b, 0);
+        accounts
+            .swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                initial_a / 10,
                 0,
             )
-        );
+            .unwrap();
+        assert!(SwapVersion::unpack(&accounts.swap_account.data)
+            .unwrap()
+            .price_oracle()
+            .is_none());
+        CLOCK_UNIX_TIMESTAMP.with(|timestamp| timestamp.set(0));
     }
 
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_withdraw_with_invalid_fee_account(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
+    #[test_case(SwapCurve {
+        curve_type: CurveType::ConstantProduct,
+        calculator: Arc::new(ConstantProductCurve {}),
+    }; "constant product")]
+    #[test_case(SwapCurve {
+        curve_type: CurveType::ConstantPrice,
+        calculator: Arc::new(ConstantPriceCurve { token_b_price: 1 }),
+    }; "constant price")]
+    fn test_routed_swap(swap_curve_bc: SwapCurve) {
         let user_key = Pubkey::new_unique();
-
+        let token_program_id = spl_token::id();
         let fees = Fees {
             trade_fee_numerator: 1,
-            trade_fee_denominator: 2,
+            trade_fee_denominator: 100,
             owner_trade_fee_numerator: 1,
-            owner_trade_fee_denominator: 10,
-            owner_withdraw_fee_numerator: 1,
-            owner_withdraw_fee_denominator: 5,
-            host_fee_numerator: 7,
-            host_fee_denominator: 100,
+            owner_trade_fee_denominator: 300,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 0,
+            host_fee_denominator: 0,
         };
-
-        let token_a_amount = 1000;
-        let token_b_amount = 2000;
         let swap_curve = SwapCurve {
             curve_type: CurveType::ConstantProduct,
             calculator: Arc::new(ConstantProductCurve {}),
         };
 
-        let withdrawer_key = Pubkey::new_unique();
-        let initial_a = token_a_amount / 10;
-        let initial_b = token_b_amount / 10;
-        let initial_pool = swap_curve.calculator.new_pool_supply() / 10;
-        let withdraw_amount = initial_pool / 4;
-        let minimum_token_a_amount = initial_a / 40;
-        let minimum_token_b_amount = initial_b / 40;
-
-        let mut accounts = SwapAccountInfo::new(
+        // route token A to token C through an A/B pool and a B/C pool
+        let mut pool_ab = SwapAccountInfo::new(
+            &user_key,
+            fees.clone(),
+            SwapTransferFees::default(),
+            swap_curve.clone(),
+            1_000_000,
+            5_000_000,
+            &token_program_id,
+            &token_program_id,
+            &token_program_id,
+        );
+        pool_ab.initialize_swap().unwrap();
+        let mut pool_bc = SwapAccountInfo::new(
             &user_key,
             fees,
             SwapTransferFees::default(),
-            swap_curve,
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
+            swap_curve_bc.clone(),
+            5_000_000,
+            2_000_000,
+            &token_program_id,
+            &token_program_id,
+            &token_program_id,
+        );
+        let (token_b_key, token_b_account) = mint_token(
+            &token_program_id,
+            &pool_ab.token_b_mint_key,
+            &mut pool_ab.token_b_mint_account,
+            &user_key,
+            &pool_bc.authority_key,
+            5_000_000,
+        );
+        pool_bc.token_a_mint_key = pool_ab.token_b_mint_key;
+        pool_bc.token_a_mint_account = pool_ab.token_b_mint_account.clone();
+        pool_bc.token_a_key = token_b_key;
+        pool_bc.token_a_account = token_b_account;
+        pool_bc.initialize_swap().unwrap();
+
+        let amount_in = 100_000;
+        let (source_key, mut source_account) = mint_token(
+            &token_program_id,
+            &pool_ab.token_a_mint_key,
+            &mut pool_ab.token_a_mint_account,
+            &user_key,
+            &user_key,
+            amount_in,
+        );
+        let (destination_key, mut destination_account) = mint_token(
+            &token_program_id,
+            &pool_bc.token_b_mint_key,
+            &mut pool_bc.token_b_mint_account,
+            &user_key,
+            &user_key,
+            0,
+        );
+        let user_transfer_key = Pubkey::new_unique();
+        do_process_instruction(
+            approve(
+                &token_program_id,
+                &source_key,
+                &user_transfer_key,
+                &user_key,
+                &[],
+                amount_in,
+            )
+            .unwrap(),
+            vec![
+                &mut source_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+
+        // the expected output skips any intermediate slippage check
+        let result_ab = swap_curve
+            .swap(
+                amount_in.into(),
+                1_000_000,
+                5_000_000,
+                TradeDirection::AtoB,
+                &pool_ab.fees,
+            )
+            .unwrap();
+        let result_bc = swap_curve_bc
+            .swap(
+                result_ab.destination_amount_swapped,
+                5_000_000,
+                2_000_000,
+                TradeDirection::AtoB,
+                &pool_bc.fees,
+            )
+            .unwrap();
+        let amount_out = to_u64(result_bc.destination_amount_swapped).unwrap();
+
+        let pools = [&pool_ab, &pool_bc]
+            .iter()
+            .map(|pool| RoutedSwapPool {
+                swap_pubkey: pool.swap_key,
+                authority_pubkey: pool.authority_key,
+                swap_source_pubkey: pool.token_a_key,
+                swap_destination_pubkey: pool.token_b_key,
+                pool_mint_pubkey: pool.pool_mint_key,
+                pool_fee_pubkey: pool.pool_fee_key,
+                source_mint_pubkey: pool.token_a_mint_key,
+                destination_mint_pubkey: pool.token_b_mint_key,
+                source_token_program_id: token_program_id,
+                destination_token_program_id: token_program_id,
+                pool_token_program_id: token_program_id,
+            })
+            .collect::<Vec<_>>();
+        let mut do_routed_swap =
+            |pool_ab: &mut SwapAccountInfo, pool_bc: &mut SwapAccountInfo, minimum_amount_out| {
+                do_process_instruction(
+                    routed_swap(
+                        &SWAP_PROGRAM_ID,
+                        &user_transfer_key,
+                        &source_key,
+                        &destination_key,
+                        &pools,
+                        Swap {
+                            amount_in,
+                            minimum_amount_out,
+                        },
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut SolanaAccount::default(),
+                        &mut source_account,
+                        &mut destination_account,
+                        &mut pool_ab.swap_account,
+                        &mut SolanaAccount::default(),
+                        &mut pool_ab.token_a_account,
+                        &mut pool_ab.token_b_account,
+                        &mut pool_ab.pool_mint_account,
+                        &mut pool_ab.pool_fee_account,
+                        &mut pool_ab.token_a_mint_account,
+                        &mut pool_ab.token_b_mint_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut pool_bc.swap_account,
+                        &mut SolanaAccount::default(),
+                        &mut pool_bc.token_a_account,
+                        &mut pool_bc.token_b_account,
+                        &mut pool_bc.pool_mint_account,
+                        &mut pool_bc.pool_fee_account,
+                        &mut pool_bc.token_a_mint_account,
+                        &mut pool_bc.token_b_mint_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                    ],
+                )
+            };
+
+        // the second pool must swap all of the tokens out of the first one,
+        // which the constant product curve does not do here, since it only
+        // takes what moves the pool to the next point of its invariant
+        if result_bc.source_amount_swapped < result_ab.destination_amount_swapped {
+            assert_eq!(swap_curve_bc.curve_type, CurveType::ConstantProduct);
+            assert_eq!(
+                Err(SwapError::IncompleteRoutedSwap.into()),
+                do_routed_swap(&mut pool_ab, &mut pool_bc, 0)
+            );
+            return;
+        }
+        assert_eq!(swap_curve_bc.curve_type, CurveType::ConstantPrice);
+
+        // every pool along the route must belong to the swap program
+        pool_bc.swap_account.owner = Pubkey::new_unique();
+        assert_eq!(
+            Err(ProgramError::IncorrectProgramId),
+            do_routed_swap(&mut pool_ab, &mut pool_bc, 0)
+        );
+        pool_bc.swap_account.owner = SWAP_PROGRAM_ID;
+
+        assert_eq!(
+            Err(SwapError::ExceededSlippage.into()),
+            do_routed_swap(&mut pool_ab, &mut pool_bc, amount_out + 1)
+        );
+        do_routed_swap(&mut pool_ab, &mut pool_bc, amount_out).unwrap();
+
+        let token_amount = |account: &SolanaAccount| {
+            StateWithExtensions::<Account>::unpack(&account.data)
+                .unwrap()
+                .base
+                .amount
+        };
+        assert_eq!(token_amount(&source_account), 0);
+        assert_eq!(token_amount(&destination_account), amount_out);
+        assert_eq!(
+            u128::from(token_amount(&pool_ab.token_a_account)),
+            result_ab.new_swap_source_amount
+        );
+        assert_eq!(
+            u128::from(token_amount(&pool_ab.token_b_account)),
+            result_ab.new_swap_destination_amount
+        );
+        assert_eq!(
+            u128::from(token_amount(&pool_bc.token_a_account)),
+            5_000_000 + result_ab.destination_amount_swapped
+        );
+        assert_eq!(
+            u128::from(token_amount(&pool_bc.token_b_account)),
+            result_bc.new_swap_destination_amount
+        );
+        assert!(token_amount(&pool_ab.pool_fee_account) > 0);
+        assert!(token_amount(&pool_bc.pool_fee_account) > 0);
+    }
+
+    #[test_case(spl_token::id(); "token")]
+    #[test_case(spl_token_2022::id(); "token-2022")]
+    fn test_concentrated_liquidity(token_program_id: Pubkey) {
+        let user_key = Pubkey::new_unique();
+        let swap_key = Pubkey::new_unique();
+        let (authority_key, _bump_seed) =
+            Pubkey::find_program_address(&[&swap_key.to_bytes()], &SWAP_PROGRAM_ID);
+        let fees = Fees {
+            trade_fee_numerator: 3,
+            trade_fee_denominator: 1000,
+            ..Fees::default()
+        };
+        let initial_amount = 1_000_000_000;
+
+        let (token_a_mint_key, mut token_a_mint_account) = create_mint(
+            &token_program_id,
+            &user_key,
+            None,
+            None,
+            &TransferFee::default(),
+        );
+        let (token_b_mint_key, mut token_b_mint_account) = create_mint(
+            &token_program_id,
+            &user_key,
+            None,
+            None,
+            &TransferFee::default(),
+        );
+        let (token_a_key, mut token_a_account) = mint_token(
+            &token_program_id,
+            &token_a_mint_key,
+            &mut token_a_mint_account,
+            &user_key,
+            &authority_key,
+            0,
+        );
+        let (token_b_key, mut token_b_account) = mint_token(
+            &token_program_id,
+            &token_b_mint_key,
+            &mut token_b_mint_account,
+            &user_key,
+            &authority_key,
+            0,
+        );
+        let (user_a_key, mut user_a_account) = mint_token(
+            &token_program_id,
+            &token_a_mint_key,
+            &mut token_a_mint_account,
+            &user_key,
+            &user_key,
+            initial_amount,
+        );
+        let (user_b_key, mut user_b_account) = mint_token(
+            &token_program_id,
+            &token_b_mint_key,
+            &mut token_b_mint_account,
+            &user_key,
+            &user_key,
+            initial_amount,
+        );
+        let token_amount = |account: &SolanaAccount| {
+            StateWithExtensions::<Account>::unpack(&account.data)
+                .unwrap()
+                .base
+                .amount
+        };
+
+        // the pool must be owned by the swap program
+        let initialize_instruction = initialize_concentrated(
+            &SWAP_PROGRAM_ID,
+            &token_program_id,
+            &swap_key,
+            &authority_key,
+            &token_a_key,
+            &token_b_key,
+            fees,
+            10,
+            1 << 64,
+        )
+        .unwrap();
+        let mut wrong_swap_account =
+            SolanaAccount::new(0, SwapVersion::CONCENTRATED_LEN, &Pubkey::new_unique());
+        assert_eq!(
+            Err(ProgramError::IncorrectProgramId),
+            do_process_instruction(
+                initialize_instruction.clone(),
+                vec![
+                    &mut wrong_swap_account,
+                    &mut SolanaAccount::default(),
+                    &mut token_a_account,
+                    &mut token_b_account,
+                    &mut SolanaAccount::default(),
+                ],
+            )
+        );
+
+        let mut swap_account =
+            SolanaAccount::new(0, SwapVersion::CONCENTRATED_LEN, &SWAP_PROGRAM_ID);
+        do_process_instruction(
+            initialize_instruction,
+            vec![
+                &mut swap_account,
+                &mut SolanaAccount::default(),
+                &mut token_a_account,
+                &mut token_b_account,
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+
+        // constant product instructions are not supported by the pool
+        assert_eq!(
+            Err(SwapError::UnsupportedPoolType.into()),
+            do_process_instruction(
+                swap(
+                    &SWAP_PROGRAM_ID,
+                    &token_program_id,
+                    &token_program_id,
+                    &token_program_id,
+                    &swap_key,
+                    &authority_key,
+                    &user_key,
+                    &user_a_key,
+                    &token_a_key,
+                    &token_b_key,
+                    &user_b_key,
+                    &token_a_mint_key,
+                    &token_a_key,
+                    &token_a_mint_key,
+                    &token_b_mint_key,
+                    None,
+                    Swap {
+                        amount_in: 1,
+                        minimum_amount_out: 0,
+                    },
+                )
+                .unwrap(),
+                vec![
+                    &mut swap_account,
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    &mut user_a_account.clone(),
+                    &mut token_a_account.clone(),
+                    &mut token_b_account.clone(),
+                    &mut user_b_account.clone(),
+                    &mut token_a_mint_account.clone(),
+                    &mut token_a_account.clone(),
+                    &mut token_a_mint_account.clone(),
+                    &mut token_b_mint_account.clone(),
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                    &mut SolanaAccount::default(),
+                ],
+            )
+        );
+
+        let position_key = Pubkey::new_unique();
+        let position_rent = Rent::default().minimum_balance(Position::LEN);
+
+        // the position account must be rent exempt
+        let mut position_account =
+            SolanaAccount::new(position_rent - 1, Position::LEN, &SWAP_PROGRAM_ID);
+        assert_eq!(
+            Err(ProgramError::AccountNotRentExempt),
+            do_process_instruction(
+                open_position(
+                    &SWAP_PROGRAM_ID,
+                    &swap_key,
+                    &position_key,
+                    &user_key,
+                    OpenPosition {
+                        tick_lower: -100,
+                        tick_upper: 100,
+                    },
+                )
+                .unwrap(),
+                vec![
+                    &mut swap_account,
+                    &mut position_account,
+                    &mut SolanaAccount::default(),
+                ],
+            )
         );
 
-        accounts.initialize_swap().unwrap();
+        // and hold the deposit for both of its boundary ticks
+        let position_lamports = position_rent + 2 * TICK_DEPOSIT_LAMPORTS - 1;
+        let mut position_account =
+            SolanaAccount::new(position_lamports, Position::LEN, &SWAP_PROGRAM_ID);
+        assert_eq!(
+            Err(SwapError::InsufficientTickDeposit.into()),
+            do_process_instruction(
+                open_position(
+                    &SWAP_PROGRAM_ID,
+                    &swap_key,
+                    &position_key,
+                    &user_key,
+                    OpenPosition {
+                        tick_lower: -100,
+                        tick_upper: 100,
+                    },
+                )
+                .unwrap(),
+                vec![
+                    &mut swap_account,
+                    &mut position_account,
+                    &mut SolanaAccount::default(),
+                ],
+            )
+        );
 
-        let (
-            token_a_key,
-            mut token_a_account,
-            token_b_key,
-            mut token_b_account,
-            pool_key,
-            mut pool_account,
-        ) = accounts.setup_token_accounts(
-            &user_key,
-            &withdrawer_key,
-            initial_a,
-            initial_b,
-            initial_pool.try_into().unwrap(),
+        let position_lamports = position_rent + 2 * TICK_DEPOSIT_LAMPORTS;
+        let mut position_account =
+            SolanaAccount::new(position_lamports, Position::LEN, &SWAP_PROGRAM_ID);
+        assert_eq!(
+            Err(SwapError::InvalidTickRange.into()),
+            do_process_instruction(
+                open_position(
+                    &SWAP_PROGRAM_ID,
+                    &swap_key,
+                    &position_key,
+                    &user_key,
+                    OpenPosition {
+                        tick_lower: -105,
+                        tick_upper: 100,
+                    },
+                )
+                .unwrap(),
+                vec![
+                    &mut swap_account,
+                    &mut position_account,
+                    &mut SolanaAccount::default(),
+                ],
+            )
         );
+        do_process_instruction(
+            open_position(
+                &SWAP_PROGRAM_ID,
+                &swap_key,
+                &position_key,
+                &user_key,
+                OpenPosition {
+                    tick_lower: -100,
+                    tick_upper: 100,
+                },
+            )
+            .unwrap(),
+            vec![
+                &mut swap_account,
+                &mut position_account,
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+
+        let liquidity_amount = 1_000_000_000;
+        do_process_instruction(
+            increase_liquidity(
+                &SWAP_PROGRAM_ID,
+                &token_program_id,
+                &token_program_id,
+                &swap_key,
+                &position_key,
+                &user_key,
+                &user_key,
+                &user_a_key,
+                &user_b_key,
+                &token_a_key,
+                &token_b_key,
+                &token_a_mint_key,
+                &token_b_mint_key,
+                IncreaseLiquidity {
+                    liquidity_amount,
+                    maximum_token_a_amount: initial_amount,
+                    maximum_token_b_amount: initial_amount,
+                },
+            )
+            .unwrap(),
+            vec![
+                &mut swap_account,
+                &mut position_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut user_a_account,
+                &mut user_b_account,
+                &mut token_a_account,
+                &mut token_b_account,
+                &mut token_a_mint_account,
+                &mut token_b_mint_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+        let deposited_a = token_amount(&token_a_account);
+        let deposited_b = token_amount(&token_b_account);
+        assert!(deposited_a > 0);
+        assert!(deposited_b > 0);
+        assert_eq!(token_amount(&user_a_account), initial_amount - deposited_a);
+        let pool = SwapVersion::unpack_concentrated(&swap_account.data).unwrap();
+        assert_eq!(pool.liquidity, liquidity_amount);
+        assert_eq!(pool.ticks.len(), 2);
+
+        let amount_in = 1_000_000;
+        do_process_instruction(
+            concentrated_swap(
+                &SWAP_PROGRAM_ID,
+                &token_program_id,
+                &token_program_id,
+                &swap_key,
+                &authority_key,
+                &user_key,
+                &user_a_key,
+                &token_a_key,
+                &token_b_key,
+                &user_b_key,
+                &token_a_mint_key,
+                &token_b_mint_key,
+                Swap {
+                    amount_in,
+                    minimum_amount_out: 0,
+                },
+            )
+            .unwrap(),
+            vec![
+                &mut swap_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+                &mut user_a_account,
+                &mut token_a_account,
+                &mut token_b_account,
+                &mut user_b_account,
+                &mut token_a_mint_account,
+                &mut token_b_mint_account,
+                &mut SolanaAccount::default(),
+                &mut SolanaAccount::default(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(token_amount(&token_a_account), deposited_a + amount_in);
+        let amount_out = deposited_b - token_amount(&token_b_account);
+        assert!(amount_out > 0);
+        assert!(amount_out < amount_in);
 
         let destination_key = Pubkey::new_unique();
-        let mut destination = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &withdrawer_key,
+        let mut destination_account = SolanaAccount::default();
+        assert_eq!(
+            Err(SwapError::PositionNotEmpty.into()),
+            do_process_instruction(
+                close_position(
+                    &SWAP_PROGRAM_ID,
+                    &swap_key,
+                    &position_key,
+                    &user_key,
+                    &destination_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut swap_account,
+                    &mut position_account,
+                    &mut SolanaAccount::default(),
+                    &mut destination_account,
+                ],
+            )
         );
 
         do_process_instruction(
-            close_account(
-                &pool_token_program_id,
-                &accounts.pool_fee_key,
-                &destination_key,
+            decrease_liquidity(
+                &SWAP_PROGRAM_ID,
+                &token_program_id,
+                &token_program_id,
+                &swap_key,
+                &authority_key,
+                &position_key,
                 &user_key,
-                &[],
+                &token_a_key,
+                &token_b_key,
+                &user_a_key,
+                &user_b_key,
+                &token_a_mint_key,
+                &token_b_mint_key,
+                DecreaseLiquidity {
+                    liquidity_amount,
+                    minimum_token_a_amount: 0,
+                    minimum_token_b_amount: 0,
+                },
             )
             .unwrap(),
             vec![
-                &mut accounts.pool_fee_account,
-                &mut destination,
+                &mut swap_account,
+                &mut SolanaAccount::default(),
+                &mut position_account,
+                &mut SolanaAccount::default(),
+                &mut token_a_account,
+                &mut token_b_account,
+                &mut user_a_account,
+                &mut user_b_account,
+                &mut token_a_mint_account,
+                &mut token_b_mint_account,
+                &mut SolanaAccount::default(),
                 &mut SolanaAccount::default(),
             ],
         )
         .unwrap();
+        // only rounding dust is left in the pool
+        assert!(token_amount(&token_a_account) <= 2);
+        assert!(token_amount(&token_b_account) <= 2);
+        assert_eq!(
+            token_amount(&user_a_account) + token_amount(&token_a_account),
+            initial_amount
+        );
+        assert_eq!(
+            token_amount(&user_b_account) + token_amount(&token_b_account),
+            initial_amount
+        );
+        let pool = SwapVersion::unpack_concentrated(&swap_account.data).unwrap();
+        assert_eq!(pool.liquidity, 0);
+        assert!(pool.ticks.is_empty());
 
-        let user_transfer_authority_key = Pubkey::new_unique();
-        let pool_token_amount = withdraw_amount.try_into().unwrap();
+        // a copy of the pool owned by another program is rejected
+        let mut wrong_swap_account = swap_account.clone();
+        wrong_swap_account.owner = Pubkey::new_unique();
+        assert_eq!(
+            Err(ProgramError::IncorrectProgramId),
+            do_process_instruction(
+                close_position(
+                    &SWAP_PROGRAM_ID,
+                    &swap_key,
+                    &position_key,
+                    &user_key,
+                    &destination_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut wrong_swap_account,
+                    &mut position_account,
+                    &mut SolanaAccount::default(),
+                    &mut destination_account,
+                ],
+            )
+        );
 
         do_process_instruction(
-            approve(
-                &pool_token_program_id,
-                &pool_key,
-                &user_transfer_authority_key,
-                &withdrawer_key,
-                &[],
-                pool_token_amount,
+            close_position(
+                &SWAP_PROGRAM_ID,
+                &swap_key,
+                &position_key,
+                &user_key,
+                &destination_key,
             )
             .unwrap(),
             vec![
-                &mut pool_account,
-                &mut SolanaAccount::default(),
+                &mut swap_account,
+                &mut position_account,
                 &mut SolanaAccount::default(),
+                &mut destination_account,
             ],
         )
         .unwrap();
+        assert_eq!(destination_account.lamports, position_lamports);
+        assert_eq!(position_account.lamports, 0);
+    }
+
+    fn set_swap_fees(
+        accounts: &mut SwapAccountInfo,
+        admin_authority_key: &Pubkey,
+        fees: Fees,
+        dynamic_fee: DynamicFee,
+        swap_constraints: &Option<SwapConstraints>,
+    ) -> ProgramResult {
+        do_process_instruction_with_fee_constraints(
+            set_fees(
+                &SWAP_PROGRAM_ID,
+                &accounts.swap_key,
+                admin_authority_key,
+                fees,
+                dynamic_fee,
+            )
+            .unwrap(),
+            vec![&mut accounts.swap_account, &mut SolanaAccount::default()],
+            swap_constraints,
+        )
+    }
+
+    #[test]
+    fn test_set_fees() {
+        let user_key = Pubkey::new_unique();
+        let admin_key = Pubkey::new_unique();
+        let token_program_id = spl_token::id();
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10000,
+            owner_trade_fee_numerator: 5,
+            owner_trade_fee_denominator: 10000,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 20,
+            host_fee_denominator: 100,
+        };
+        let swap_curve = SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Arc::new(ConstantProductCurve {}),
+        };
+        let new_swap_account_info = |fees: &Fees| {
+            SwapAccountInfo::new(
+                &user_key,
+                fees.clone(),
+                SwapTransferFees::default(),
+                swap_curve.clone(),
+                1_000_000,
+                5_000_000,
+                &token_program_id,
+                &token_program_id,
+                &token_program_id,
+            )
+        };
+        let new_fees = Fees {
+            trade_fee_numerator: 30,
+            ..fees.clone()
+        };
+        let dynamic_fee = DynamicFee {
+            max_fee_numerator: 100,
+            max_volatility_bps: 500,
+            decay_period: 600,
+        };
+
+        // swaps without an admin authority can never change their fees
+        {
+            let mut accounts = new_swap_account_info(&fees);
+            accounts.initialize_swap().unwrap();
+            assert_eq!(
+                Err(SwapError::InvalidAdminAuthority.into()),
+                set_swap_fees(
+                    &mut accounts,
+                    &admin_key,
+                    new_fees.clone(),
+                    DynamicFee::default(),
+                    &None,
+                )
+            );
+        }
+
+        // swaps of older versions have no admin authority
+        {
+            let mut accounts = new_swap_account_info(&fees);
+            accounts.swap_account =
+                SolanaAccount::new(0, SwapVersion::SWAP_V1_LEN, &SWAP_PROGRAM_ID);
+            assert_eq!(
+                Err(SwapError::UnsupportedPoolType.into()),
+                accounts.initialize_swap_with_admin(Some(&admin_key))
+            );
+            accounts.initialize_swap().unwrap();
+            assert_eq!(
+                Err(SwapError::UnsupportedPoolType.into()),
+                set_swap_fees(
+                    &mut accounts,
+                    &admin_key,
+                    new_fees.clone(),
+                    DynamicFee::default(),
+                    &None,
+                )
+            );
+        }
+
+        let mut accounts = new_swap_account_info(&fees);
+
+        // the admin authority must sign the initialization of the swap
+        {
+            let mut instruction = initialize(
+                &SWAP_PROGRAM_ID,
+                &accounts.pool_token_program_id,
+                &accounts.swap_key,
+                &accounts.authority_key,
+                &accounts.token_a_key,
+                &accounts.token_b_key,
+                &accounts.pool_mint_key,
+                &accounts.pool_fee_key,
+                &accounts.pool_token_key,
+                Some(&admin_key),
+                fees.clone(),
+                swap_curve.clone(),
+            )
+            .unwrap();
+            instruction.accounts[8].is_signer = false;
+            assert_eq!(
+                Err(SwapError::InvalidAdminAuthority.into()),
+                do_process_instruction(
+                    instruction,
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut SolanaAccount::default(),
+                        &mut accounts.token_a_account,
+                        &mut accounts.token_b_account,
+                        &mut accounts.pool_mint_account,
+                        &mut accounts.pool_fee_account,
+                        &mut accounts.pool_token_account,
+                        &mut SolanaAccount::default(),
+                        &mut SolanaAccount::default(),
+                    ],
+                )
+            );
+        }
+
+        accounts
+            .initialize_swap_with_admin(Some(&admin_key))
+            .unwrap();
+        let token_swap = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(token_swap.admin_authority(), Some(&admin_key));
+
+        // wrong admin authority
+        {
+            let wrong_admin_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(SwapError::InvalidAdminAuthority.into()),
+                set_swap_fees(
+                    &mut accounts,
+                    &wrong_admin_key,
+                    new_fees.clone(),
+                    DynamicFee::default(),
+                    &None,
+                )
+            );
+        }
 
-        do_process_instruction(
-            withdraw_all_token_types(
+        // admin authority did not sign
+        {
+            let mut instruction = set_fees(
                 &SWAP_PROGRAM_ID,
-                &pool_token_program_id,
-                &token_a_program_id,
-                &token_b_program_id,
                 &accounts.swap_key,
-                &accounts.authority_key,
-                &user_transfer_authority_key,
-                &accounts.pool_mint_key,
-                &accounts.pool_fee_key,
-                &pool_key,
-                &accounts.token_a_key,
-                &accounts.token_b_key,
-                &token_a_key,
-                &token_b_key,
-                &accounts.token_a_mint_key,
-                &accounts.token_b_mint_key,
-                WithdrawAllTokenTypes {
-                    pool_token_amount,
-                    minimum_token_a_amount,
-                    minimum_token_b_amount,
-                },
+                &admin_key,
+                new_fees.clone(),
+                DynamicFee::default(),
             )
-            .unwrap(),
-            vec![
-                &mut accounts.swap_account,
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
-                &mut accounts.pool_mint_account,
-                &mut pool_account,
-                &mut accounts.token_a_account,
-                &mut accounts.token_b_account,
-                &mut token_a_account,
-                &mut token_b_account,
-                &mut accounts.pool_fee_account,
-                &mut accounts.token_a_mint_account,
-                &mut accounts.token_b_mint_account,
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
-            ],
+            .unwrap();
+            instruction.accounts[1].is_signer = false;
+            assert_eq!(
+                Err(SwapError::InvalidAdminAuthority.into()),
+                do_process_instruction(
+                    instruction,
+                    vec![&mut accounts.swap_account, &mut SolanaAccount::default()],
+                )
+            );
+        }
+
+        // invalid fees
+        {
+            let invalid_fees = Fees {
+                trade_fee_numerator: 10000,
+                ..fees.clone()
+            };
+            assert_eq!(
+                Err(SwapError::InvalidFee.into()),
+                set_swap_fees(
+                    &mut accounts,
+                    &admin_key,
+                    invalid_fees,
+                    DynamicFee::default(),
+                    &None,
+                )
+            );
+        }
+
+        // dynamic fee pushing the trade fee over 100%
+        {
+            let invalid_dynamic_fee = DynamicFee {
+                max_fee_numerator: 10000 - new_fees.trade_fee_numerator,
+                ..dynamic_fee.clone()
+            };
+            assert_eq!(
+                Err(SwapError::InvalidFee.into()),
+                set_swap_fees(
+                    &mut accounts,
+                    &admin_key,
+                    new_fees.clone(),
+                    invalid_dynamic_fee,
+                    &None,
+                )
+            );
+        }
+
+        // fees below the program owner's constraints
+        let owner_key = user_key.to_string();
+        let valid_curve_types = &[CurveType::ConstantProduct];
+        let constraints = Some(SwapConstraints {
+            owner_key: &owner_key,
+            valid_curve_types,
+            fees: &fees,
+        });
+        {
+            let low_fees = Fees {
+                trade_fee_numerator: fees.trade_fee_numerator - 1,
+                ..fees.clone()
+            };
+            assert_eq!(
+                Err(SwapError::InvalidFee.into()),
+                set_swap_fees(
+                    &mut accounts,
+                    &admin_key,
+                    low_fees,
+                    DynamicFee::default(),
+                    &constraints,
+                )
+            );
+        }
+
+        // success
+        set_swap_fees(
+            &mut accounts,
+            &admin_key,
+            new_fees.clone(),
+            dynamic_fee.clone(),
+            &constraints,
         )
         .unwrap();
+        let token_swap = SwapVersion::unpack_swap_v2(&accounts.swap_account.data).unwrap();
+        assert_eq!(token_swap.swap.fees, new_fees);
+        assert_eq!(token_swap.dynamic_fee, dynamic_fee);
+        assert_eq!(token_swap.admin_authority, COption::Some(admin_key));
     }
 
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_withdraw_one_exact_out_with_invalid_fee_account(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
+    #[test]
+    fn test_set_pool_fee_account() {
         let user_key = Pubkey::new_unique();
-
+        let swapper_key = Pubkey::new_unique();
+        let admin_key = Pubkey::new_unique();
+        let token_program_id = spl_token::id();
         let fees = Fees {
             trade_fee_numerator: 1,
-            trade_fee_denominator: 2,
+            trade_fee_denominator: 10,
             owner_trade_fee_numerator: 1,
-            owner_trade_fee_denominator: 10,
-            owner_withdraw_fee_numerator: 1,
-            owner_withdraw_fee_denominator: 5,
-            host_fee_numerator: 7,
-            host_fee_denominator: 100,
+            owner_trade_fee_denominator: 30,
+            owner_withdraw_fee_numerator: 0,
+            owner_withdraw_fee_denominator: 0,
+            host_fee_numerator: 0,
+            host_fee_denominator: 0,
         };
-
-        let token_a_amount = 1000;
-        let token_b_amount = 2000;
         let swap_curve = SwapCurve {
             curve_type: CurveType::ConstantProduct,
             calculator: Arc::new(ConstantProductCurve {}),
         };
-
-        let withdrawer_key = Pubkey::new_unique();
-        let initial_a = token_a_amount / 10;
-        let initial_b = token_b_amount / 10;
-        let initial_pool = swap_curve.calculator.new_pool_supply() / 10;
-        let maximum_pool_token_amount = to_u64(initial_pool / 4).unwrap();
-        let destination_a_amount = initial_a / 40;
-
         let mut accounts = SwapAccountInfo::new(
             &user_key,
             fees,
             SwapTransferFees::default(),
             swap_curve,
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
+            1_000_000,
+            5_000_000,
+            &token_program_id,
+            &token_program_id,
+            &token_program_id,
         );
+        accounts
+            .initialize_swap_with_admin(Some(&admin_key))
+            .unwrap();
+        let do_set_pool_fee_account =
+            |accounts: &mut SwapAccountInfo,
+             admin_key: &Pubkey,
+             pool_fee_key: &Pubkey,
+             pool_fee_account: &mut SolanaAccount,
+             swap_constraints: &Option<SwapConstraints>| {
+                do_process_instruction_with_fee_constraints(
+                    set_pool_fee_account(
+                        &SWAP_PROGRAM_ID,
+                        &accounts.swap_key,
+                        admin_key,
+                        pool_fee_key,
+                    )
+                    .unwrap(),
+                    vec![
+                        &mut accounts.swap_account,
+                        &mut SolanaAccount::default(),
+                        pool_fee_account,
+                    ],
+                    swap_constraints,
+                )
+            };
 
-        accounts.initialize_swap().unwrap();
-
-        let (
-            token_a_key,
-            mut token_a_account,
-            _token_b_key,
-            _token_b_account,
-            pool_key,
-            mut pool_account,
-        ) = accounts.setup_token_accounts(
-            &user_key,
-            &withdrawer_key,
-            initial_a,
-            initial_b,
-            initial_pool.try_into().unwrap(),
+        let new_owner_key = Pubkey::new_unique();
+        let (new_pool_fee_key, mut new_pool_fee_account) = mint_token(
+            &token_program_id,
+            &accounts.pool_mint_key,
+            &mut accounts.pool_mint_account,
+            &accounts.authority_key,
+            &new_owner_key,
+            0,
         );
 
-        let destination_key = Pubkey::new_unique();
-        let mut destination = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            &withdrawer_key,
-        );
+        // wrong admin authority
+        {
+            let wrong_admin_key = Pubkey::new_unique();
+            assert_eq!(
+                Err(SwapError::InvalidAdminAuthority.into()),
+                do_set_pool_fee_account(
+                    &mut accounts,
+                    &wrong_admin_key,
+                    &new_pool_fee_key,
+                    &mut new_pool_fee_account,
+                    &None,
+                )
+            );
+        }
 
-        do_process_instruction(
-            close_account(
-                &pool_token_program_id,
-                &accounts.pool_fee_key,
-                &destination_key,
+        // fee account for the wrong mint
+        {
+            let (wrong_fee_key, mut wrong_fee_account) = mint_token(
+                &token_program_id,
+                &accounts.token_a_mint_key,
+                &mut accounts.token_a_mint_account,
                 &user_key,
-                &[],
-            )
-            .unwrap(),
-            vec![
-                &mut accounts.pool_fee_account,
-                &mut destination,
-                &mut SolanaAccount::default(),
-            ],
-        )
-        .unwrap();
-
-        let user_transfer_authority_key = Pubkey::new_unique();
-
-        do_process_instruction(
-            approve(
-                &pool_token_program_id,
-                &pool_key,
-                &user_transfer_authority_key,
-                &withdrawer_key,
-                &[],
-                maximum_pool_token_amount,
-            )
-            .unwrap(),
-            vec![
-                &mut pool_account,
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
-            ],
-        )
-        .unwrap();
+                &new_owner_key,
+                0,
+            );
+            assert_eq!(
+                Err(SwapError::InvalidFeeAccount.into()),
+                do_set_pool_fee_account(
+                    &mut accounts,
+                    &admin_key,
+                    &wrong_fee_key,
+                    &mut wrong_fee_account,
+                    &None,
+                )
+            );
+        }
 
-        do_process_instruction(
-            withdraw_single_token_type_exact_amount_out(
-                &SWAP_PROGRAM_ID,
-                &pool_token_program_id,
-                &token_a_program_id,
-                &accounts.swap_key,
-                &accounts.authority_key,
-                &user_transfer_authority_key,
+        // fee account owned by the swap authority
+        {
+            let authority_key = accounts.authority_key;
+            let (wrong_fee_key, mut wrong_fee_account) = mint_token(
+                &token_program_id,
                 &accounts.pool_mint_key,
-                &accounts.pool_fee_key,
-                &pool_key,
-                &accounts.token_a_key,
-                &accounts.token_b_key,
-                &token_a_key,
-                &accounts.token_a_mint_key,
-                WithdrawSingleTokenTypeExactAmountOut {
-                    destination_token_amount: destination_a_amount,
-                    maximum_pool_token_amount,
-                },
-            )
-            .unwrap(),
-            vec![
-                &mut accounts.swap_account,
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
                 &mut accounts.pool_mint_account,
-                &mut pool_account,
-                &mut accounts.token_a_account,
-                &mut accounts.token_b_account,
-                &mut token_a_account,
-                &mut accounts.pool_fee_account,
-                &mut accounts.token_a_mint_account,
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
-            ],
+                &authority_key,
+                &authority_key,
+                0,
+            );
+            assert_eq!(
+                Err(SwapError::InvalidOutputOwner.into()),
+                do_set_pool_fee_account(
+                    &mut accounts,
+                    &admin_key,
+                    &wrong_fee_key,
+                    &mut wrong_fee_account,
+                    &None,
+                )
+            );
+        }
+
+        // fee account not owned by the program owner
+        {
+            let owner_key = user_key.to_string();
+            let valid_curve_types = &[CurveType::ConstantProduct];
+            let constraint_fees = Fees::default();
+            let constraints = Some(SwapConstraints {
+                owner_key: &owner_key,
+                valid_curve_types,
+                fees: &constraint_fees,
+            });
+            assert_eq!(
+                Err(SwapError::InvalidOwner.into()),
+                do_set_pool_fee_account(
+                    &mut accounts,
+                    &admin_key,
+                    &new_pool_fee_key,
+                    &mut new_pool_fee_account,
+                    &constraints,
+                )
+            );
+        }
+
+        // success, with trading fees sent to the new account
+        do_set_pool_fee_account(
+            &mut accounts,
+            &admin_key,
+            &new_pool_fee_key,
+            &mut new_pool_fee_account,
+            &None,
         )
         .unwrap();
-    }
-
-    #[test_case(spl_token::id(), spl_token::id(), spl_token::id(); "all-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_valid_swap_with_invalid_fee_account(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
-        let owner_key = &Pubkey::new_unique();
+        let token_swap = SwapVersion::unpack(&accounts.swap_account.data).unwrap();
+        assert_eq!(*token_swap.pool_fee_account(), new_pool_fee_key);
 
-        let token_a_amount = 1_000_000;
-        let token_b_amount = 5_000_000;
+        let old_pool_fee_account = accounts.pool_fee_account.clone();
+        accounts.pool_fee_key = new_pool_fee_key;
+        accounts.pool_fee_account = new_pool_fee_account;
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
+        let (
+            token_a_key,
+            mut token_a_account,
+            token_b_key,
+            mut token_b_account,
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &swapper_key, 100_000, 0, 0);
+        accounts
+            .swap(
+                &swapper_key,
+                &token_a_key,
+                &mut token_a_account,
+                &swap_token_a_key,
+                &swap_token_b_key,
+                &token_b_key,
+                &mut token_b_account,
+                100_000,
+                0,
+            )
+            .unwrap();
+        let pool_fee_amount = |account: &SolanaAccount| {
+            StateWithExtensions::<Account>::unpack(&account.data)
+                .unwrap()
+                .base
+                .amount
+        };
+        assert!(pool_fee_amount(&accounts.pool_fee_account) > 0);
+        assert_eq!(pool_fee_amount(&old_pool_fee_account), 0);
+    }
 
+    #[test]
+    fn test_dynamic_fee() {
+        let user_key = Pubkey::new_unique();
+        let swapper_key = Pubkey::new_unique();
+        let admin_key = Pubkey::new_unique();
+        let token_program_id = spl_token::id();
         let fees = Fees {
-            trade_fee_numerator: 1,
-            trade_fee_denominator: 10,
-            owner_trade_fee_numerator: 1,
-            owner_trade_fee_denominator: 30,
-            owner_withdraw_fee_numerator: 1,
-            owner_withdraw_fee_denominator: 30,
-            host_fee_numerator: 10,
-            host_fee_denominator: 100,
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10000,
+            ..Fees::default()
+        };
+        let dynamic_fee = DynamicFee {
+            max_fee_numerator: 100,
+            max_volatility_bps: 1_000,
+            decay_period: 100,
         };
-
         let swap_curve = SwapCurve {
             curve_type: CurveType::ConstantProduct,
             calculator: Arc::new(ConstantProductCurve {}),
         };
-
-        let owner_key_str = &owner_key.to_string();
-        let constraints = Some(SwapConstraints {
-            owner_key: owner_key_str,
-            valid_curve_types: &[CurveType::ConstantProduct],
-            fees: &fees,
-        });
         let mut accounts = SwapAccountInfo::new(
-            owner_key,
+            &user_key,
             fees.clone(),
             SwapTransferFees::default(),
-            swap_curve,
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
+            swap_curve.clone(),
+            1_000_000,
+            1_000_000,
+            &token_program_id,
+            &token_program_id,
+            &token_program_id,
         );
-
-        do_process_instruction_with_fee_constraints(
-            initialize(
-                &SWAP_PROGRAM_ID,
-                &pool_token_program_id,
-                &accounts.swap_key,
-                &accounts.authority_key,
-                &accounts.token_a_key,
-                &accounts.token_b_key,
-                &accounts.pool_mint_key,
-                &accounts.pool_fee_key,
-                &accounts.pool_token_key,
-                accounts.fees.clone(),
-                accounts.swap_curve.clone(),
-            )
-            .unwrap(),
-            vec![
-                &mut accounts.swap_account,
-                &mut SolanaAccount::default(),
-                &mut accounts.token_a_account,
-                &mut accounts.token_b_account,
-                &mut accounts.pool_mint_account,
-                &mut accounts.pool_fee_account,
-                &mut accounts.pool_token_account,
-                &mut SolanaAccount::default(),
-            ],
-            &constraints,
+        CLOCK_UNIX_TIMESTAMP.with(|timestamp| timestamp.set(100));
+        accounts
+            .initialize_swap_with_admin(Some(&admin_key))
+            .unwrap();
+        set_swap_fees(
+            &mut accounts,
+            &admin_key,
+            fees.clone(),
+            dynamic_fee.clone(),
+            &None,
         )
         .unwrap();
 
-        let authority_key = accounts.authority_key;
-
+        let swap_token_a_key = accounts.token_a_key;
+        let swap_token_b_key = accounts.token_b_key;
         let (
             token_a_key,
             mut token_a_account,
             token_b_key,
             mut token_b_account,
-            pool_key,
-            mut pool_account,
-        ) = accounts.setup_token_accounts(
-            owner_key,
-            &authority_key,
-            token_a_amount,
-            token_b_amount,
-            0,
-        );
-
-        let destination_key = Pubkey::new_unique();
-        let mut destination = SolanaAccount::new(
-            account_minimum_balance(),
-            Account::get_packed_len(),
-            owner_key,
-        );
-
-        do_process_instruction(
-            close_account(
-                &pool_token_program_id,
-                &accounts.pool_fee_key,
-                &destination_key,
-                owner_key,
-                &[],
-            )
-            .unwrap(),
-            vec![
-                &mut accounts.pool_fee_account,
-                &mut destination,
-                &mut SolanaAccount::default(),
-            ],
-        )
-        .unwrap();
+            _pool_key,
+            _pool_account,
+        ) = accounts.setup_token_accounts(&user_key, &swapper_key, 1_000_000, 0, 0);
+        let token_amount = |account: &SolanaAccount| {
+            StateWithExtensions::<Account>::unpack(&account.data)
+                .unwrap()
+                .base
+                .amount
+        };
+        let price_volatility = |accounts: &SwapAccountInfo| {
+            SwapVersion::unpack_swap_v2(&accounts.swap_account.data)
+                .unwrap()
+                .price_volatility
+        };
+        let mut swap_and_check = |accounts: &mut SwapAccountInfo, fees: &Fees| {
+            let amount_in = 10_000;
+            let expected = swap_curve
+                .swap(
+                    amount_in.into(),
+                    token_amount(&accounts.token_a_account).into(),
+                    token_amount(&accounts.token_b_account).into(),
+                    TradeDirection::AtoB,
+                    fees,
+                )
+                .unwrap();
+            let token_b_before = token_amount(&token_b_account);
+            accounts
+                .swap(
+                    &swapper_key,
+                    &token_a_key,
+                    &mut token_a_account,
+                    &swap_token_a_key,
+                    &swap_token_b_key,
+                    &token_b_key,
+                    &mut token_b_account,
+                    amount_in,
+                    0,
+                )
+                .unwrap();
+            assert_eq!(
+                u128::from(token_amount(&token_b_account) - token_b_before),
+                expected.destination_amount_swapped
+            );
+        };
 
-        do_process_instruction_with_fee_constraints(
-            swap(
-                &SWAP_PROGRAM_ID,
-                &token_a_program_id,
-                &token_b_program_id,
-                &pool_token_program_id,
-                &accounts.swap_key,
-                &accounts.authority_key,
-                &accounts.authority_key,
-                &token_a_key,
-                &accounts.token_a_key,
-                &accounts.token_b_key,
-                &token_b_key,
-                &accounts.pool_mint_key,
-                &accounts.pool_fee_key,
-                &accounts.token_a_mint_key,
-                &accounts.token_b_mint_key,
-                Some(&pool_key),
-                Swap {
-                    amount_in: token_a_amount / 2,
-                    minimum_amount_out: 0,
-                },
-            )
-            .unwrap(),
-            vec![
-                &mut accounts.swap_account,
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
-                &mut token_a_account,
-                &mut accounts.token_a_account,
-                &mut accounts.token_b_account,
-                &mut token_b_account,
-                &mut accounts.pool_mint_account,
-                &mut accounts.pool_fee_account,
-                &mut accounts.token_a_mint_account,
-                &mut accounts.token_b_mint_account,
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
-                &mut SolanaAccount::default(),
-                &mut pool_account,
-            ],
-            &constraints,
+        // a calm pool only charges the trade fee, but the swap moves the price
+        swap_and_check(&mut accounts, &fees);
+        let volatility = price_volatility(&accounts);
+        assert!(volatility.volatility_bps > 0);
+        assert!(volatility.volatility_bps < dynamic_fee.max_volatility_bps);
+        assert_eq!(volatility.last_update_timestamp, 100);
+
+        // the next swap pays for the volatility, which decays over time
+        CLOCK_UNIX_TIMESTAMP.with(|timestamp| timestamp.set(150));
+        let volatility_bps = volatility.current_volatility_bps(&dynamic_fee, 150);
+        assert_eq!(volatility_bps, volatility.volatility_bps / 2);
+        let dynamic_fees = Fees {
+            trade_fee_numerator: fees.trade_fee_numerator
+                + dynamic_fee.fee_numerator(volatility_bps).unwrap(),
+            ..fees.clone()
+        };
+        assert!(dynamic_fees.trade_fee_numerator > fees.trade_fee_numerator);
+        swap_and_check(&mut accounts, &dynamic_fees);
+        assert!(price_volatility(&accounts).volatility_bps > volatility_bps);
+
+        // once the volatility has decayed, only the trade fee is charged
+        CLOCK_UNIX_TIMESTAMP.with(|timestamp| timestamp.set(250));
+        swap_and_check(&mut accounts, &fees);
+
+        // disabling the dynamic fee stops tracking volatility
+        set_swap_fees(
+            &mut accounts,
+            &admin_key,
+            fees.clone(),
+            DynamicFee::default(),
+            &None,
         )
         .unwrap();
-    }
-
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token_2022::id(); "all-token-2022")]
-    #[test_case(spl_token::id(), spl_token_2022::id(), spl_token_2022::id(); "mixed-pool-token")]
-    #[test_case(spl_token_2022::id(), spl_token_2022::id(), spl_token::id(); "mixed-pool-token-2022")]
-    fn test_swap_curve_with_transfer_fees(
-        pool_token_program_id: Pubkey,
-        token_a_program_id: Pubkey,
-        token_b_program_id: Pubkey,
-    ) {
-        // All fees
-        let trade_fee_numerator = 1;
-        let trade_fee_denominator = 10;
-        let owner_trade_fee_numerator = 1;
-        let owner_trade_fee_denominator = 30;
-        let owner_withdraw_fee_numerator = 1;
-        let owner_withdraw_fee_denominator = 30;
-        let host_fee_numerator = 20;
-        let host_fee_denominator = 100;
-        let fees = Fees {
-            trade_fee_numerator,
-            trade_fee_denominator,
-            owner_trade_fee_numerator,
-            owner_trade_fee_denominator,
-            owner_withdraw_fee_numerator,
-            owner_withdraw_fee_denominator,
-            host_fee_numerator,
-            host_fee_denominator,
-        };
-
-        let token_a_amount = 10_000_000_000;
-        let token_b_amount = 50_000_000_000;
-
-        check_valid_swap_curve(
-            fees,
-            SwapTransferFees {
-                pool_token: TransferFee::default(),
-                token_a: TransferFee {
-                    epoch: 0.into(),
-                    transfer_fee_basis_points: 100.into(),
-                    maximum_fee: 1_000_000_000.into(),
-                },
-                token_b: TransferFee::default(),
-            },
-            CurveType::ConstantProduct,
-            Arc::new(ConstantProductCurve {}),
-            token_a_amount,
-            token_b_amount,
-            &pool_token_program_id,
-            &token_a_program_id,
-            &token_b_program_id,
-        );
+        let volatility = price_volatility(&accounts);
+        swap_and_check(&mut accounts, &fees);
+        assert_eq!(price_volatility(&accounts), volatility);
+        CLOCK_UNIX_TIMESTAMP.with(|timestamp| timestamp.set(0));
     }
 }
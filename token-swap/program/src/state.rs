@@ -1,18 +1,24 @@
 //! State transition types
 
 use crate::{
-    curve::{base::SwapCurve, fees::Fees},
+    curve::{
+        base::SwapCurve,
+        fees::{DynamicFee, Fees},
+    },
     error::SwapError,
 };
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use enum_dispatch::enum_dispatch;
 use solana_program::{
     account_info::AccountInfo,
+    clock::UnixTimestamp,
     msg,
     program_error::ProgramError,
+    program_option::COption,
     program_pack::{IsInitialized, Pack, Sealed},
     pubkey::Pubkey,
 };
+use spl_math::uint::U256;
 use spl_token_2022::{
     extension::StateWithExtensions,
     state::{Account, AccountState},
@@ -50,13 +56,27 @@ pub trait SwapState {
     fn fees(&self) -> &Fees;
     /// Curve associated with swap
     fn swap_curve(&self) -> &SwapCurve;
+
+    /// Price accumulators of the swap, if its version has them
+    fn price_oracle(&self) -> Option<&PriceOracle>;
+
+    /// Authority allowed to update the fees and pool fee account of the swap,
+    /// if it has one
+    fn admin_authority(&self) -> Option<&Pubkey>;
+    /// Volatility-based trade fee and the recent price movement it is
+    /// computed from, if the swap version has them
+    fn dynamic_fee(&self) -> Option<(&DynamicFee, &PriceVolatility)>;
 }
 
 /// All versions of SwapState
-#[enum_dispatch(SwapState)]
 pub enum SwapVersion {
+    /// Original version, without a price oracle
+    SwapV1(SwapV1),
     /// Latest version, used for all new swaps
-    SwapV1,
+    SwapV2(SwapV2),
+    /// Concentrated liquidity pool, where liquidity is provided over tick
+    /// ranges through positions instead of pool tokens
+    ConcentratedSwapV1(ConcentratedSwapV1),
 }
 
 /// SwapVersion does not implement program_pack::Pack because there are size
@@ -64,7 +84,14 @@ pub enum SwapVersion {
 /// special implementations are provided here
 impl SwapVersion {
     /// Size of the latest version of the SwapState
-    pub const LATEST_LEN: usize = 1 + SwapV1::LEN; // add one for the version enum
+    pub const LATEST_LEN: usize = 1 + SwapV2::LEN; // add one for the version enum
+
+    /// Size of the original version of the SwapState, still accepted when
+    /// initializing a swap
+    pub const SWAP_V1_LEN: usize = 1 + SwapV1::LEN;
+
+    /// Size of a concentrated liquidity pool
+    pub const CONCENTRATED_LEN: usize = 1 + ConcentratedSwapV1::LEN;
 
     /// Pack a swap into a byte array, based on its version
     pub fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
@@ -73,6 +100,14 @@ impl SwapVersion {
                 dst[0] = 1;
                 SwapV1::pack(swap_info, &mut dst[1..])
             }
+            Self::ConcentratedSwapV1(swap_info) => {
+                dst[0] = 2;
+                ConcentratedSwapV1::pack(swap_info, &mut dst[1..])
+            }
+            Self::SwapV2(swap_info) => {
+                dst[0] = 3;
+                SwapV2::pack(swap_info, &mut dst[1..])
+            }
         }
     }
 
@@ -84,16 +119,72 @@ impl SwapVersion {
             .ok_or(ProgramError::InvalidAccountData)?;
         match version {
             1 => Ok(Arc::new(SwapV1::unpack(rest)?)),
+            2 => Err(SwapError::UnsupportedPoolType.into()),
+            3 => Ok(Arc::new(SwapV2::unpack(rest)?)),
+            _ => Err(ProgramError::UninitializedAccount),
+        }
+    }
+
+    /// Unpack a swap of the latest version, the only one that can be updated
+    /// by an admin authority
+    pub fn unpack_swap_v2(input: &[u8]) -> Result<SwapV2, ProgramError> {
+        let (&version, rest) = input
+            .split_first()
+            .ok_or(ProgramError::InvalidAccountData)?;
+        match version {
+            1 | 2 => Err(SwapError::UnsupportedPoolType.into()),
+            3 => SwapV2::unpack(rest),
+            _ => Err(ProgramError::UninitializedAccount),
+        }
+    }
+
+    /// Unpack a concentrated liquidity pool
+    pub fn unpack_concentrated(input: &[u8]) -> Result<ConcentratedSwapV1, ProgramError> {
+        let (&version, rest) = input
+            .split_first()
+            .ok_or(ProgramError::InvalidAccountData)?;
+        match version {
+            1 | 3 => Err(SwapError::UnsupportedPoolType.into()),
+            2 => ConcentratedSwapV1::unpack(rest),
             _ => Err(ProgramError::UninitializedAccount),
         }
     }
 
+    /// Pack the price accumulators into a swap account, for versions that
+    /// have them
+    pub fn pack_price_oracle(src: PriceOracle, dst: &mut [u8]) -> Result<(), ProgramError> {
+        match dst.first() {
+            Some(3) if dst.len() == Self::LATEST_LEN => {
+                src.pack_into_slice(&mut dst[Self::SWAP_V1_LEN..][..PriceOracle::LEN]);
+                Ok(())
+            }
+            _ => Err(SwapError::UnsupportedPoolType.into()),
+        }
+    }
+
+    /// Pack the recent price movement into a swap account, for versions that
+    /// have a dynamic fee
+    pub fn pack_price_volatility(src: PriceVolatility, dst: &mut [u8]) -> Result<(), ProgramError> {
+        match dst.first() {
+            Some(3) if dst.len() == Self::LATEST_LEN => {
+                src.pack_into_slice(&mut dst[Self::LATEST_LEN - PriceVolatility::LEN..]);
+                Ok(())
+            }
+            _ => Err(SwapError::UnsupportedPoolType.into()),
+        }
+    }
+
     /// Special check to be done before any instruction processing, works for
     /// all versions
     pub fn is_initialized(input: &[u8]) -> bool {
-        match Self::unpack(input) {
-            Ok(swap) => swap.is_initialized(),
-            Err(_) => false,
+        match input.first() {
+            Some(2) => Self::unpack_concentrated(input)
+                .map(|swap| swap.is_initialized)
+                .unwrap_or(false),
+            _ => match Self::unpack(input) {
+                Ok(swap) => swap.is_initialized(),
+                Err(_) => false,
+            },
         }
     }
 }
@@ -202,6 +293,18 @@ impl SwapState for SwapV1 {
     fn swap_curve(&self) -> &SwapCurve {
         &self.swap_curve
     }
+
+    fn price_oracle(&self) -> Option<&PriceOracle> {
+        None
+    }
+
+    fn admin_authority(&self) -> Option<&Pubkey> {
+        None
+    }
+
+    fn dynamic_fee(&self) -> Option<(&DynamicFee, &PriceVolatility)> {
+        None
+    }
 }
 
 impl Sealed for SwapV1 {}
@@ -279,6 +382,648 @@ impl Pack for SwapV1 {
     }
 }
 
+/// Cumulative prices of the tokens in a pool, from which a time-weighted
+/// average price can be computed between any two observations
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PriceOracle {
+    /// Unix timestamp of the last update to the accumulators
+    pub last_update_timestamp: UnixTimestamp,
+    /// Price of token A in token B, as a Q64.64 fixed-point number, summed
+    /// over every second since the swap was initialized.  Wraps on overflow.
+    pub price_a_cumulative_x64: u128,
+    /// Price of token B in token A, as a Q64.64 fixed-point number, summed
+    /// over every second since the swap was initialized.  Wraps on overflow.
+    pub price_b_cumulative_x64: u128,
+    /// Price of token A in token B, as a Q64.64 fixed-point number, observed
+    /// at the last update and held by the pool since
+    pub last_price_a_x64: u128,
+    /// Price of token B in token A, as a Q64.64 fixed-point number, observed
+    /// at the last update and held by the pool since
+    pub last_price_b_x64: u128,
+}
+
+impl PriceOracle {
+    /// Accumulate the prices observed at the last update for the time since,
+    /// then hold the new prices, which must be observed after the pool's token
+    /// balances change.
+    ///
+    /// Prices are only ever accumulated for the time they were held, so
+    /// tokens donated to the pool move the average from the next update on,
+    /// never over the time before it.
+    pub fn update(&mut self, timestamp: UnixTimestamp, price_a_x64: u128, price_b_x64: u128) {
+        if timestamp > self.last_update_timestamp {
+            let elapsed = timestamp.saturating_sub(self.last_update_timestamp) as u128;
+            self.price_a_cumulative_x64 = self
+                .price_a_cumulative_x64
+                .wrapping_add(self.last_price_a_x64.wrapping_mul(elapsed));
+            self.price_b_cumulative_x64 = self
+                .price_b_cumulative_x64
+                .wrapping_add(self.last_price_b_x64.wrapping_mul(elapsed));
+            self.last_update_timestamp = timestamp;
+        }
+        self.last_price_a_x64 = price_a_x64;
+        self.last_price_b_x64 = price_b_x64;
+    }
+
+    /// Time-weighted average prices of token A in token B and of token B in
+    /// token A, as Q64.64 fixed-point numbers, between an earlier observation
+    /// and this one.
+    ///
+    /// To observe a pool at a time after its last update, copy its oracle and
+    /// update it with the pool's current prices.
+    pub fn average_prices_since(&self, earlier: &PriceOracle) -> Option<(u128, u128)> {
+        let elapsed = self
+            .last_update_timestamp
+            .checked_sub(earlier.last_update_timestamp)?;
+        if elapsed <= 0 {
+            return None;
+        }
+        let elapsed = elapsed as u128;
+        Some((
+            self.price_a_cumulative_x64
+                .wrapping_sub(earlier.price_a_cumulative_x64)
+                / elapsed,
+            self.price_b_cumulative_x64
+                .wrapping_sub(earlier.price_b_cumulative_x64)
+                / elapsed,
+        ))
+    }
+}
+
+impl Sealed for PriceOracle {}
+impl Pack for PriceOracle {
+    const LEN: usize = 72;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 72];
+        let (
+            last_update_timestamp,
+            price_a_cumulative_x64,
+            price_b_cumulative_x64,
+            last_price_a_x64,
+            last_price_b_x64,
+        ) = mut_array_refs![output, 8, 16, 16, 16, 16];
+        *last_update_timestamp = self.last_update_timestamp.to_le_bytes();
+        *price_a_cumulative_x64 = self.price_a_cumulative_x64.to_le_bytes();
+        *price_b_cumulative_x64 = self.price_b_cumulative_x64.to_le_bytes();
+        *last_price_a_x64 = self.last_price_a_x64.to_le_bytes();
+        *last_price_b_x64 = self.last_price_b_x64.to_le_bytes();
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, 72];
+        let (
+            last_update_timestamp,
+            price_a_cumulative_x64,
+            price_b_cumulative_x64,
+            last_price_a_x64,
+            last_price_b_x64,
+        ) = array_refs![input, 8, 16, 16, 16, 16];
+        Ok(Self {
+            last_update_timestamp: i64::from_le_bytes(*last_update_timestamp),
+            price_a_cumulative_x64: u128::from_le_bytes(*price_a_cumulative_x64),
+            price_b_cumulative_x64: u128::from_le_bytes(*price_b_cumulative_x64),
+            last_price_a_x64: u128::from_le_bytes(*last_price_a_x64),
+            last_price_b_x64: u128::from_le_bytes(*last_price_b_x64),
+        })
+    }
+}
+
+/// Recent price movement of a swap, from which its dynamic trade fee is
+/// computed
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PriceVolatility {
+    /// Price movement, in basis points, accumulated over recent trades and
+    /// decaying over time
+    pub volatility_bps: u64,
+    /// Unix timestamp of the last update to the volatility
+    pub last_update_timestamp: UnixTimestamp,
+}
+
+impl PriceVolatility {
+    /// Volatility at the given time, after decaying since the last update
+    pub fn current_volatility_bps(
+        &self,
+        dynamic_fee: &DynamicFee,
+        timestamp: UnixTimestamp,
+    ) -> u64 {
+        let elapsed = timestamp.saturating_sub(self.last_update_timestamp).max(0) as u64;
+        dynamic_fee.decayed_volatility_bps(self.volatility_bps, elapsed)
+    }
+
+    /// Add the price movement of a trade, from the price of token A before it
+    /// to the price after it, to the decayed volatility, up to the maximum
+    /// volatility of the dynamic fee
+    pub fn update(
+        &mut self,
+        dynamic_fee: &DynamicFee,
+        timestamp: UnixTimestamp,
+        price_before_x64: u128,
+        price_after_x64: u128,
+    ) {
+        let price_movement_bps = if price_before_x64 == 0 {
+            0
+        } else {
+            let price_movement_bps = U256::from(price_after_x64.abs_diff(price_before_x64))
+                * U256::from(BASIS_POINTS)
+                / U256::from(price_before_x64);
+            u64::try_from(price_movement_bps).unwrap_or(u64::MAX)
+        };
+        self.volatility_bps = self
+            .current_volatility_bps(dynamic_fee, timestamp)
+            .saturating_add(price_movement_bps)
+            .min(dynamic_fee.max_volatility_bps);
+        self.last_update_timestamp = timestamp;
+    }
+}
+
+const BASIS_POINTS: u64 = 10_000;
+
+impl Sealed for PriceVolatility {}
+impl Pack for PriceVolatility {
+    const LEN: usize = 16;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 16];
+        let (volatility_bps, last_update_timestamp) = mut_array_refs![output, 8, 8];
+        *volatility_bps = self.volatility_bps.to_le_bytes();
+        *last_update_timestamp = self.last_update_timestamp.to_le_bytes();
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, 16];
+        let (volatility_bps, last_update_timestamp) = array_refs![input, 8, 8];
+        Ok(Self {
+            volatility_bps: u64::from_le_bytes(*volatility_bps),
+            last_update_timestamp: i64::from_le_bytes(*last_update_timestamp),
+        })
+    }
+}
+
+/// Program states, with price accumulators, an admin authority, and a dynamic
+/// fee appended to the version 1 layout
+#[repr(C)]
+#[derive(Debug, Default, PartialEq)]
+pub struct SwapV2 {
+    /// Swap information, laid out as in version 1
+    pub swap: SwapV1,
+    /// Cumulative prices, updated on every swap, deposit, and withdrawal
+    pub price_oracle: PriceOracle,
+    /// Authority allowed to update the fees and the pool fee account.  If
+    /// not set, they can never change.
+    pub admin_authority: COption<Pubkey>,
+    /// Volatility-based trade fee, charged on top of the trade fee
+    pub dynamic_fee: DynamicFee,
+    /// Recent price movement, updated on every trade while the dynamic fee
+    /// is enabled
+    pub price_volatility: PriceVolatility,
+}
+
+impl SwapState for SwapV2 {
+    fn is_initialized(&self) -> bool {
+        self.swap.is_initialized
+    }
+
+    fn bump_seed(&self) -> u8 {
+        self.swap.bump_seed
+    }
+
+    fn token_program_id(&self) -> &Pubkey {
+        &self.swap.token_program_id
+    }
+
+    fn token_a_account(&self) -> &Pubkey {
+        &self.swap.token_a
+    }
+
+    fn token_b_account(&self) -> &Pubkey {
+        &self.swap.token_b
+    }
+
+    fn pool_mint(&self) -> &Pubkey {
+        &self.swap.pool_mint
+    }
+
+    fn token_a_mint(&self) -> &Pubkey {
+        &self.swap.token_a_mint
+    }
+
+    fn token_b_mint(&self) -> &Pubkey {
+        &self.swap.token_b_mint
+    }
+
+    fn pool_fee_account(&self) -> &Pubkey {
+        &self.swap.pool_fee_account
+    }
+
+    fn check_pool_fee_info(&self, pool_fee_info: &AccountInfo) -> Result<(), ProgramError> {
+        self.swap.check_pool_fee_info(pool_fee_info)
+    }
+
+    fn fees(&self) -> &Fees {
+        &self.swap.fees
+    }
+
+    fn swap_curve(&self) -> &SwapCurve {
+        &self.swap.swap_curve
+    }
+
+    fn price_oracle(&self) -> Option<&PriceOracle> {
+        Some(&self.price_oracle)
+    }
+
+    fn admin_authority(&self) -> Option<&Pubkey> {
+        self.admin_authority.as_ref().into()
+    }
+
+    fn dynamic_fee(&self) -> Option<(&DynamicFee, &PriceVolatility)> {
+        Some((&self.dynamic_fee, &self.price_volatility))
+    }
+}
+
+impl Sealed for SwapV2 {}
+impl IsInitialized for SwapV2 {
+    fn is_initialized(&self) -> bool {
+        self.swap.is_initialized
+    }
+}
+
+impl Pack for SwapV2 {
+    const LEN: usize = SwapV1::LEN + PriceOracle::LEN + 36 + DynamicFee::LEN + PriceVolatility::LEN;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, SwapV2::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (swap, price_oracle, admin_authority, dynamic_fee, price_volatility) = mut_array_refs![
+            output,
+            SwapV1::LEN,
+            PriceOracle::LEN,
+            36,
+            DynamicFee::LEN,
+            PriceVolatility::LEN
+        ];
+        self.swap.pack_into_slice(&mut swap[..]);
+        self.price_oracle.pack_into_slice(&mut price_oracle[..]);
+        pack_coption_key(&self.admin_authority, admin_authority);
+        self.dynamic_fee.pack_into_slice(&mut dynamic_fee[..]);
+        self.price_volatility
+            .pack_into_slice(&mut price_volatility[..]);
+    }
+
+    /// Unpacks a byte buffer into a [SwapV2](struct.SwapV2.html).
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, SwapV2::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (swap, price_oracle, admin_authority, dynamic_fee, price_volatility) = array_refs![
+            input,
+            SwapV1::LEN,
+            PriceOracle::LEN,
+            36,
+            DynamicFee::LEN,
+            PriceVolatility::LEN
+        ];
+        Ok(Self {
+            swap: SwapV1::unpack_from_slice(swap)?,
+            price_oracle: PriceOracle::unpack_from_slice(price_oracle)?,
+            admin_authority: unpack_coption_key(admin_authority)?,
+            dynamic_fee: DynamicFee::unpack_from_slice(dynamic_fee)?,
+            price_volatility: PriceVolatility::unpack_from_slice(price_volatility)?,
+        })
+    }
+}
+
+fn pack_coption_key(src: &COption<Pubkey>, dst: &mut [u8; 36]) {
+    let (tag, body) = mut_array_refs![dst, 4, 32];
+    match src {
+        COption::Some(key) => {
+            *tag = [1, 0, 0, 0];
+            body.copy_from_slice(key.as_ref());
+        }
+        COption::None => {
+            *tag = [0; 4];
+        }
+    }
+}
+
+fn unpack_coption_key(src: &[u8; 36]) -> Result<COption<Pubkey>, ProgramError> {
+    let (tag, body) = array_refs![src, 4, 32];
+    match *tag {
+        [0, 0, 0, 0] => Ok(COption::None),
+        [1, 0, 0, 0] => Ok(COption::Some(Pubkey::new_from_array(*body))),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Maximum number of initialized ticks a concentrated liquidity pool can hold
+pub const MAX_INITIALIZED_TICKS: usize = 64;
+
+/// Minimum liquidity of a concentrated liquidity position holding any, so
+/// that the limited initialized ticks of a pool cannot be taken up by dust
+/// positions
+pub const MIN_POSITION_LIQUIDITY: u128 = 1_000_000;
+
+/// Lamports a position account must hold on top of its rent exemption for
+/// each of its two boundary ticks. A tick stays initialized only while a
+/// position uses it, so taking up all `MAX_INITIALIZED_TICKS` of a pool locks
+/// at least `MAX_INITIALIZED_TICKS * TICK_DEPOSIT_LAMPORTS`. The deposit is
+/// returned with the rest of the position lamports when it is closed.
+pub const TICK_DEPOSIT_LAMPORTS: u64 = 1_000_000_000;
+
+/// Tick at a boundary of one or more positions in a concentrated liquidity pool
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Tick {
+    /// Tick index
+    pub index: i32,
+    /// Total liquidity of all positions using this tick as a boundary
+    pub liquidity_gross: u128,
+    /// Liquidity added to the pool when the price moves up across this tick,
+    /// or removed when the price moves down across it
+    pub liquidity_net: i128,
+    /// Token A fees per unit of liquidity earned on the other side of this
+    /// tick from the current price, as a Q64.64 number
+    pub fee_growth_outside_a_x64: u128,
+    /// Token B fees per unit of liquidity earned on the other side of this
+    /// tick from the current price, as a Q64.64 number
+    pub fee_growth_outside_b_x64: u128,
+}
+
+impl Tick {
+    /// Size of a packed tick
+    pub const LEN: usize = 68;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 68];
+        let (index, liquidity_gross, liquidity_net, fee_growth_outside_a, fee_growth_outside_b) =
+            mut_array_refs![output, 4, 16, 16, 16, 16];
+        *index = self.index.to_le_bytes();
+        *liquidity_gross = self.liquidity_gross.to_le_bytes();
+        *liquidity_net = self.liquidity_net.to_le_bytes();
+        *fee_growth_outside_a = self.fee_growth_outside_a_x64.to_le_bytes();
+        *fee_growth_outside_b = self.fee_growth_outside_b_x64.to_le_bytes();
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Self {
+        let input = array_ref![input, 0, 68];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (index, liquidity_gross, liquidity_net, fee_growth_outside_a, fee_growth_outside_b) =
+            array_refs![input, 4, 16, 16, 16, 16];
+        Self {
+            index: i32::from_le_bytes(*index),
+            liquidity_gross: u128::from_le_bytes(*liquidity_gross),
+            liquidity_net: i128::from_le_bytes(*liquidity_net),
+            fee_growth_outside_a_x64: u128::from_le_bytes(*fee_growth_outside_a),
+            fee_growth_outside_b_x64: u128::from_le_bytes(*fee_growth_outside_b),
+        }
+    }
+}
+
+/// Concentrated liquidity pool state.
+#[repr(C)]
+#[derive(Debug, Default, PartialEq)]
+pub struct ConcentratedSwapV1 {
+    /// Initialized state.
+    pub is_initialized: bool,
+    /// Bump seed used in program address, giving authority over the pool's
+    /// token A and token B accounts
+    pub bump_seed: u8,
+
+    /// Program ID of the tokens being exchanged.
+    pub token_program_id: Pubkey,
+
+    /// Token A
+    pub token_a: Pubkey,
+    /// Token B
+    pub token_b: Pubkey,
+
+    /// Mint information for token A
+    pub token_a_mint: Pubkey,
+    /// Mint information for token B
+    pub token_b_mint: Pubkey,
+
+    /// All fee information, only trade fees are used since there is no pool
+    /// token to collect owner fees in
+    pub fees: Fees,
+
+    /// Position boundaries must be multiples of the tick spacing
+    pub tick_spacing: u16,
+    /// Current square root price of token A in token B, as a Q64.64 number
+    pub sqrt_price_x64: u128,
+    /// Greatest tick whose price is less than or equal to the current price
+    pub current_tick: i32,
+    /// Liquidity of all positions whose range contains the current price
+    pub liquidity: u128,
+    /// Total token A fees earned per unit of liquidity, as a Q64.64 number
+    pub fee_growth_global_a_x64: u128,
+    /// Total token B fees earned per unit of liquidity, as a Q64.64 number
+    pub fee_growth_global_b_x64: u128,
+    /// Initialized ticks, sorted by index
+    pub ticks: Vec<Tick>,
+}
+
+impl Sealed for ConcentratedSwapV1 {}
+impl IsInitialized for ConcentratedSwapV1 {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for ConcentratedSwapV1 {
+    const LEN: usize = 297 + MAX_INITIALIZED_TICKS * Tick::LEN;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let (header, ticks) = output.split_at_mut(297);
+        let header = array_mut_ref![header, 0, 297];
+        let (
+            is_initialized,
+            bump_seed,
+            token_program_id,
+            token_a,
+            token_b,
+            token_a_mint,
+            token_b_mint,
+            fees,
+            tick_spacing,
+            sqrt_price,
+            current_tick,
+            liquidity,
+            fee_growth_global_a,
+            fee_growth_global_b,
+            ticks_len,
+        ) = mut_array_refs![header, 1, 1, 32, 32, 32, 32, 32, 64, 2, 16, 4, 16, 16, 16, 1];
+        is_initialized[0] = self.is_initialized as u8;
+        bump_seed[0] = self.bump_seed;
+        token_program_id.copy_from_slice(self.token_program_id.as_ref());
+        token_a.copy_from_slice(self.token_a.as_ref());
+        token_b.copy_from_slice(self.token_b.as_ref());
+        token_a_mint.copy_from_slice(self.token_a_mint.as_ref());
+        token_b_mint.copy_from_slice(self.token_b_mint.as_ref());
+        self.fees.pack_into_slice(&mut fees[..]);
+        *tick_spacing = self.tick_spacing.to_le_bytes();
+        *sqrt_price = self.sqrt_price_x64.to_le_bytes();
+        *current_tick = self.current_tick.to_le_bytes();
+        *liquidity = self.liquidity.to_le_bytes();
+        *fee_growth_global_a = self.fee_growth_global_a_x64.to_le_bytes();
+        *fee_growth_global_b = self.fee_growth_global_b_x64.to_le_bytes();
+        ticks_len[0] = self.ticks.len() as u8;
+        for (tick, output) in self.ticks.iter().zip(ticks.chunks_exact_mut(Tick::LEN)) {
+            tick.pack_into_slice(output);
+        }
+    }
+
+    /// Unpacks a byte buffer into a [ConcentratedSwapV1](struct.ConcentratedSwapV1.html).
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let (header, ticks) = input.split_at(297);
+        let header = array_ref![header, 0, 297];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            is_initialized,
+            bump_seed,
+            token_program_id,
+            token_a,
+            token_b,
+            token_a_mint,
+            token_b_mint,
+            fees,
+            tick_spacing,
+            sqrt_price,
+            current_tick,
+            liquidity,
+            fee_growth_global_a,
+            fee_growth_global_b,
+            ticks_len,
+        ) = array_refs![header, 1, 1, 32, 32, 32, 32, 32, 64, 2, 16, 4, 16, 16, 16, 1];
+        let ticks_len = ticks_len[0] as usize;
+        if ticks_len > MAX_INITIALIZED_TICKS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Self {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            bump_seed: bump_seed[0],
+            token_program_id: Pubkey::new_from_array(*token_program_id),
+            token_a: Pubkey::new_from_array(*token_a),
+            token_b: Pubkey::new_from_array(*token_b),
+            token_a_mint: Pubkey::new_from_array(*token_a_mint),
+            token_b_mint: Pubkey::new_from_array(*token_b_mint),
+            fees: Fees::unpack_from_slice(fees)?,
+            tick_spacing: u16::from_le_bytes(*tick_spacing),
+            sqrt_price_x64: u128::from_le_bytes(*sqrt_price),
+            current_tick: i32::from_le_bytes(*current_tick),
+            liquidity: u128::from_le_bytes(*liquidity),
+            fee_growth_global_a_x64: u128::from_le_bytes(*fee_growth_global_a),
+            fee_growth_global_b_x64: u128::from_le_bytes(*fee_growth_global_b),
+            ticks: ticks
+                .chunks_exact(Tick::LEN)
+                .take(ticks_len)
+                .map(Tick::unpack_from_slice)
+                .collect(),
+        })
+    }
+}
+
+/// Liquidity provided to a concentrated liquidity pool over a range of ticks
+#[repr(C)]
+#[derive(Debug, Default, PartialEq)]
+pub struct Position {
+    /// Initialized state.
+    pub is_initialized: bool,
+    /// Pool the liquidity is provided to
+    pub pool: Pubkey,
+    /// Owner allowed to change the liquidity and collect fees
+    pub owner: Pubkey,
+    /// Lower tick of the range, inclusive
+    pub tick_lower: i32,
+    /// Upper tick of the range, exclusive
+    pub tick_upper: i32,
+    /// Liquidity provided over the range
+    pub liquidity: u128,
+    /// Token A fee growth inside the range at the last update, as a Q64.64 number
+    pub fee_growth_inside_a_last_x64: u128,
+    /// Token B fee growth inside the range at the last update, as a Q64.64 number
+    pub fee_growth_inside_b_last_x64: u128,
+    /// Token A fees earned and not yet collected
+    pub tokens_owed_a: u64,
+    /// Token B fees earned and not yet collected
+    pub tokens_owed_b: u64,
+}
+
+impl Sealed for Position {}
+impl IsInitialized for Position {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Position {
+    const LEN: usize = 137;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 137];
+        let (
+            is_initialized,
+            pool,
+            owner,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            fee_growth_inside_a_last,
+            fee_growth_inside_b_last,
+            tokens_owed_a,
+            tokens_owed_b,
+        ) = mut_array_refs![output, 1, 32, 32, 4, 4, 16, 16, 16, 8, 8];
+        is_initialized[0] = self.is_initialized as u8;
+        pool.copy_from_slice(self.pool.as_ref());
+        owner.copy_from_slice(self.owner.as_ref());
+        *tick_lower = self.tick_lower.to_le_bytes();
+        *tick_upper = self.tick_upper.to_le_bytes();
+        *liquidity = self.liquidity.to_le_bytes();
+        *fee_growth_inside_a_last = self.fee_growth_inside_a_last_x64.to_le_bytes();
+        *fee_growth_inside_b_last = self.fee_growth_inside_b_last_x64.to_le_bytes();
+        *tokens_owed_a = self.tokens_owed_a.to_le_bytes();
+        *tokens_owed_b = self.tokens_owed_b.to_le_bytes();
+    }
+
+    /// Unpacks a byte buffer into a [Position](struct.Position.html).
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, 137];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            is_initialized,
+            pool,
+            owner,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            fee_growth_inside_a_last,
+            fee_growth_inside_b_last,
+            tokens_owed_a,
+            tokens_owed_b,
+        ) = array_refs![input, 1, 32, 32, 4, 4, 16, 16, 16, 8, 8];
+        Ok(Self {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            pool: Pubkey::new_from_array(*pool),
+            owner: Pubkey::new_from_array(*owner),
+            tick_lower: i32::from_le_bytes(*tick_lower),
+            tick_upper: i32::from_le_bytes(*tick_upper),
+            liquidity: u128::from_le_bytes(*liquidity),
+            fee_growth_inside_a_last_x64: u128::from_le_bytes(*fee_growth_inside_a_last),
+            fee_growth_inside_b_last_x64: u128::from_le_bytes(*fee_growth_inside_b_last),
+            tokens_owed_a: u64::from_le_bytes(*tokens_owed_a),
+            tokens_owed_b: u64::from_le_bytes(*tokens_owed_b),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -334,7 +1079,7 @@ mod tests {
             swap_curve: swap_curve.clone(),
         });
 
-        let mut packed = [0u8; SwapVersion::LATEST_LEN];
+        let mut packed = [0u8; SwapVersion::SWAP_V1_LEN];
         SwapVersion::pack(swap_info, &mut packed).unwrap();
         let unpacked = SwapVersion::unpack(&packed).unwrap();
 
@@ -349,6 +1094,7 @@ mod tests {
         assert_eq!(*unpacked.pool_fee_account(), TEST_POOL_FEE_ACCOUNT);
         assert_eq!(*unpacked.fees(), TEST_FEES);
         assert_eq!(*unpacked.swap_curve(), swap_curve);
+        assert_eq!(unpacked.price_oracle(), None);
     }
 
     #[test]
@@ -407,4 +1153,281 @@ mod tests {
         let err = SwapV1::unpack(&packed).unwrap_err();
         assert_eq!(err, ProgramError::UninitializedAccount);
     }
+
+    #[test]
+    fn price_oracle_average_prices() {
+        let mut price_oracle = PriceOracle {
+            last_update_timestamp: 100,
+            price_a_cumulative_x64: u128::MAX - 9,
+            price_b_cumulative_x64: 0,
+            last_price_a_x64: 3,
+            last_price_b_x64: 1,
+        };
+        let start = price_oracle;
+
+        // the held price is accumulated for the time it was held, and the new
+        // one is held from then on
+        price_oracle.update(110, 7, 2);
+        assert_eq!(price_oracle.last_update_timestamp, 110);
+        assert_eq!(price_oracle.price_a_cumulative_x64, 20);
+        assert_eq!(price_oracle.price_b_cumulative_x64, 10);
+        assert_eq!(price_oracle.last_price_a_x64, 7);
+        assert_eq!(price_oracle.last_price_b_x64, 2);
+
+        // updates in the same second or in the past accumulate nothing, but
+        // still replace the held price
+        price_oracle.update(110, 100, 100);
+        price_oracle.update(105, 7, 2);
+        assert_eq!(price_oracle.last_update_timestamp, 110);
+        assert_eq!(price_oracle.price_a_cumulative_x64, 20);
+        assert_eq!(price_oracle.last_price_a_x64, 7);
+
+        price_oracle.update(140, 100, 100);
+        assert_eq!(price_oracle.average_prices_since(&start), Some((6, 1)));
+        assert_eq!(start.average_prices_since(&price_oracle), None);
+        assert_eq!(price_oracle.average_prices_since(&price_oracle), None);
+
+        let mut packed = [0u8; PriceOracle::LEN];
+        price_oracle.pack_into_slice(&mut packed);
+        assert_eq!(
+            PriceOracle::unpack_from_slice(&packed).unwrap(),
+            price_oracle
+        );
+    }
+
+    #[test]
+    fn swap_v2_version_pack() {
+        let curve_type = TEST_CURVE_TYPE.try_into().unwrap();
+        let calculator = Arc::new(TEST_CURVE);
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator,
+        };
+        let admin_authority = Pubkey::new_from_array([8u8; 32]);
+        let dynamic_fee = DynamicFee {
+            max_fee_numerator: 1,
+            max_volatility_bps: 500,
+            decay_period: 300,
+        };
+        let price_volatility = PriceVolatility {
+            volatility_bps: 200,
+            last_update_timestamp: 1_700_000_000,
+        };
+        let swap_info = SwapV2 {
+            swap: SwapV1 {
+                is_initialized: true,
+                bump_seed: TEST_BUMP_SEED,
+                token_program_id: TEST_TOKEN_PROGRAM_ID,
+                token_a: TEST_TOKEN_A,
+                token_b: TEST_TOKEN_B,
+                pool_mint: TEST_POOL_MINT,
+                token_a_mint: TEST_TOKEN_A_MINT,
+                token_b_mint: TEST_TOKEN_B_MINT,
+                pool_fee_account: TEST_POOL_FEE_ACCOUNT,
+                fees: TEST_FEES,
+                swap_curve: swap_curve.clone(),
+            },
+            price_oracle: PriceOracle {
+                last_update_timestamp: 1_700_000_000,
+                price_a_cumulative_x64: 1,
+                price_b_cumulative_x64: 2,
+                last_price_a_x64: 3,
+                last_price_b_x64: 4,
+            },
+            admin_authority: COption::Some(admin_authority),
+            dynamic_fee: dynamic_fee.clone(),
+            price_volatility,
+        };
+
+        let mut packed = [0u8; SwapVersion::LATEST_LEN];
+        SwapVersion::pack(SwapVersion::SwapV2(swap_info), &mut packed).unwrap();
+        assert!(SwapVersion::is_initialized(&packed));
+        let unpacked = SwapVersion::unpack(&packed).unwrap();
+        assert_eq!(*unpacked.pool_fee_account(), TEST_POOL_FEE_ACCOUNT);
+        assert_eq!(*unpacked.swap_curve(), swap_curve);
+        assert_eq!(unpacked.admin_authority(), Some(&admin_authority));
+        assert_eq!(
+            unpacked.dynamic_fee(),
+            Some((&dynamic_fee, &price_volatility))
+        );
+        assert_eq!(
+            SwapVersion::unpack_concentrated(&packed).unwrap_err(),
+            SwapError::UnsupportedPoolType.into()
+        );
+
+        let updated_oracle = PriceOracle {
+            last_update_timestamp: 1_700_000_010,
+            ..*unpacked.price_oracle().unwrap()
+        };
+        let updated_volatility = PriceVolatility {
+            volatility_bps: 300,
+            last_update_timestamp: 1_700_000_010,
+        };
+        SwapVersion::pack_price_oracle(updated_oracle, &mut packed).unwrap();
+        SwapVersion::pack_price_volatility(updated_volatility, &mut packed).unwrap();
+        let mut unpacked = SwapVersion::unpack_swap_v2(&packed).unwrap();
+        assert_eq!(unpacked.price_oracle, updated_oracle);
+        assert_eq!(unpacked.price_volatility, updated_volatility);
+        assert_eq!(unpacked.dynamic_fee, dynamic_fee);
+        assert_eq!(unpacked.swap.fees, TEST_FEES);
+
+        unpacked.admin_authority = COption::None;
+        SwapVersion::pack(SwapVersion::SwapV2(unpacked), &mut packed).unwrap();
+        let unpacked = SwapVersion::unpack(&packed).unwrap();
+        assert_eq!(unpacked.admin_authority(), None);
+
+        let mut packed = [0u8; SwapVersion::SWAP_V1_LEN];
+        packed[0] = 1;
+        assert_eq!(
+            SwapVersion::pack_price_oracle(updated_oracle, &mut packed).unwrap_err(),
+            SwapError::UnsupportedPoolType.into()
+        );
+        assert_eq!(
+            SwapVersion::pack_price_volatility(updated_volatility, &mut packed).unwrap_err(),
+            SwapError::UnsupportedPoolType.into()
+        );
+        assert_eq!(
+            SwapVersion::unpack_swap_v2(&packed).unwrap_err(),
+            SwapError::UnsupportedPoolType.into()
+        );
+    }
+
+    #[test]
+    fn price_volatility_update() {
+        let dynamic_fee = DynamicFee {
+            max_fee_numerator: 10,
+            max_volatility_bps: 1_000,
+            decay_period: 100,
+        };
+        let mut price_volatility = PriceVolatility {
+            volatility_bps: 0,
+            last_update_timestamp: 100,
+        };
+
+        // a 5% price increase adds 500 basis points
+        price_volatility.update(&dynamic_fee, 100, 20 << 64, 21 << 64);
+        assert_eq!(price_volatility.volatility_bps, 500);
+
+        // half of it decays over half of the decay period, and a 2% decrease
+        // adds 200 basis points
+        assert_eq!(
+            price_volatility.current_volatility_bps(&dynamic_fee, 150),
+            250
+        );
+        price_volatility.update(&dynamic_fee, 150, 50 << 64, 49 << 64);
+        assert_eq!(price_volatility.volatility_bps, 450);
+        assert_eq!(price_volatility.last_update_timestamp, 150);
+
+        // volatility is capped, and fully decays after the decay period
+        price_volatility.update(&dynamic_fee, 150, 1 << 64, 2 << 64);
+        assert_eq!(price_volatility.volatility_bps, 1_000);
+        price_volatility.update(&dynamic_fee, 150, 1, u128::MAX);
+        assert_eq!(price_volatility.volatility_bps, 1_000);
+        assert_eq!(
+            price_volatility.current_volatility_bps(&dynamic_fee, 250),
+            0
+        );
+
+        // an empty pool has no price to move from
+        price_volatility.update(&dynamic_fee, 250, 0, 1 << 64);
+        assert_eq!(price_volatility.volatility_bps, 0);
+
+        let mut packed = [0u8; PriceVolatility::LEN];
+        price_volatility.pack_into_slice(&mut packed);
+        assert_eq!(
+            PriceVolatility::unpack_from_slice(&packed).unwrap(),
+            price_volatility
+        );
+    }
+
+    #[test]
+    fn concentrated_swap_version_pack() {
+        let swap_info = ConcentratedSwapV1 {
+            is_initialized: true,
+            bump_seed: TEST_BUMP_SEED,
+            token_program_id: TEST_TOKEN_PROGRAM_ID,
+            token_a: TEST_TOKEN_A,
+            token_b: TEST_TOKEN_B,
+            token_a_mint: TEST_TOKEN_A_MINT,
+            token_b_mint: TEST_TOKEN_B_MINT,
+            fees: TEST_FEES,
+            tick_spacing: 10,
+            sqrt_price_x64: 1 << 64,
+            current_tick: 0,
+            liquidity: 1_000,
+            fee_growth_global_a_x64: 12,
+            fee_growth_global_b_x64: 34,
+            ticks: vec![
+                Tick {
+                    index: -10,
+                    liquidity_gross: 1_000,
+                    liquidity_net: 1_000,
+                    fee_growth_outside_a_x64: 5,
+                    fee_growth_outside_b_x64: 6,
+                },
+                Tick {
+                    index: 20,
+                    liquidity_gross: 1_000,
+                    liquidity_net: -1_000,
+                    fee_growth_outside_a_x64: 0,
+                    fee_growth_outside_b_x64: 0,
+                },
+            ],
+        };
+
+        let mut packed = vec![0u8; SwapVersion::CONCENTRATED_LEN];
+        SwapVersion::pack(
+            SwapVersion::ConcentratedSwapV1(ConcentratedSwapV1 {
+                ticks: swap_info.ticks.clone(),
+                ..swap_info
+            }),
+            &mut packed,
+        )
+        .unwrap();
+        assert!(SwapVersion::is_initialized(&packed));
+        let unpacked = SwapVersion::unpack_concentrated(&packed).unwrap();
+        assert_eq!(unpacked.ticks, swap_info.ticks);
+        assert_eq!(unpacked.liquidity, 1_000);
+        assert_eq!(unpacked.tick_spacing, 10);
+        assert_eq!(
+            SwapVersion::unpack(&packed).err().unwrap(),
+            SwapError::UnsupportedPoolType.into()
+        );
+
+        let mut packed = [0u8; SwapVersion::LATEST_LEN];
+        packed[0] = 1;
+        assert_eq!(
+            SwapVersion::unpack_concentrated(&packed).unwrap_err(),
+            SwapError::UnsupportedPoolType.into()
+        );
+    }
+
+    #[test]
+    fn position_pack() {
+        let position = Position {
+            is_initialized: true,
+            pool: TEST_TOKEN_A,
+            owner: TEST_TOKEN_B,
+            tick_lower: -60,
+            tick_upper: 120,
+            liquidity: u128::MAX,
+            fee_growth_inside_a_last_x64: 1,
+            fee_growth_inside_b_last_x64: 2,
+            tokens_owed_a: 3,
+            tokens_owed_b: 4,
+        };
+        let mut packed = [0u8; Position::LEN];
+        Position::pack(position, &mut packed).unwrap();
+        let unpacked = Position::unpack(&packed).unwrap();
+        assert_eq!(unpacked.tick_lower, -60);
+        assert_eq!(unpacked.tick_upper, 120);
+        assert_eq!(unpacked.liquidity, u128::MAX);
+        assert_eq!(unpacked.tokens_owed_b, 4);
+
+        let packed = [0u8; Position::LEN];
+        assert_eq!(
+            Position::unpack(&packed).unwrap_err(),
+            ProgramError::UninitializedAccount
+        );
+    }
 }